@@ -210,7 +210,7 @@ impl CheckpointManager {
                 if path.is_dir() {
                     // Skip hidden directories like .git
                     if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if name.starts_with('.') {
+                        if super::is_ignored_dir(name) {
                             continue;
                         }
                     }
@@ -467,7 +467,7 @@ impl CheckpointManager {
                 if path.is_dir() {
                     // Skip hidden directories like .git
                     if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if name.starts_with('.') {
+                        if super::is_ignored_dir(name) {
                             continue;
                         }
                     }
@@ -7,6 +7,11 @@ pub mod manager;
 pub mod state;
 pub mod storage;
 
+/// Whether a directory is left out when walking a project's files, like `.git`
+pub fn is_ignored_dir(name: &str) -> bool {
+    name.starts_with('.')
+}
+
 /// Represents a checkpoint in the session timeline
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
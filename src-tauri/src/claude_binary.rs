@@ -4,10 +4,20 @@ use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 /// Shared module for detecting Claude Code binary installations
 /// Supports NVM installations, aliased paths, and version-based selection
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
 use tauri::Manager;
 
+/// How long `claude --version` may take per installation before it's killed
+const VERSION_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stored in place of a path to select the Claude binary bundled with opcode
+pub const BUNDLED_SENTINEL: &str = "bundled";
+
+/// Name of the bundled Claude binary in `externalBin`, without its directory
+pub const BUNDLED_SIDECAR: &str = "claude";
+
 /// Type of Claude installation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum InstallationType {
@@ -15,6 +25,10 @@ pub enum InstallationType {
     System,
     /// Custom path specified by user
     Custom,
+    /// Installed inside WSL; the path is a Linux path run through `wsl.exe`
+    Wsl,
+    /// Shipped with opcode as a sidecar; the path is `BUNDLED_SENTINEL`
+    Bundled,
 }
 
 /// Represents a Claude installation with metadata
@@ -28,6 +42,51 @@ pub struct ClaudeInstallation {
     pub source: String,
     /// Type of installation
     pub installation_type: InstallationType,
+    /// Where `path` leads once symlinks are followed
+    #[serde(default)]
+    pub resolved_path: Option<String>,
+    /// Found by an earlier listing but gone from disk since
+    #[serde(default)]
+    pub missing: bool,
+    /// The Bun or Deno a shim runs under and its version, e.g. "bun 1.1.30"
+    #[serde(default)]
+    pub runtime: Option<String>,
+}
+
+/// What a binary printed for `--version`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VersionOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub success: bool,
+}
+
+impl VersionOutput {
+    /// The version in the output of a successful run. Shims for Bun and
+    /// Deno may print only to stderr, so it's checked when stdout has none.
+    pub fn version(&self) -> Option<String> {
+        if self.success {
+            extract_version_from_output(self.stdout.as_bytes())
+                .or_else(|| extract_version_from_output(self.stderr.as_bytes()))
+        } else {
+            None
+        }
+    }
+}
+
+impl ClaudeInstallation {
+    /// An installation found on disk, whose version is probed later
+    fn system(path: impl Into<String>, source: impl Into<String>) -> Self {
+        ClaudeInstallation {
+            path: path.into(),
+            version: None,
+            source: source.into(),
+            installation_type: InstallationType::System,
+            resolved_path: None,
+            missing: false,
+            runtime: None,
+        }
+    }
 }
 
 /// Main function to find the Claude binary
@@ -49,11 +108,11 @@ pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, Strin
                     info!("Found stored claude path in database: {}", stored_path);
                     
                     // Check if the path still exists
-                    let path_buf = PathBuf::from(&stored_path);
-                    if path_buf.exists() && path_buf.is_file() {
-                        return Ok(stored_path);
-                    } else {
-                        warn!("Stored claude path no longer exists: {}", stored_path);
+                    match binary_file(&stored_path) {
+                        Some(path_buf) if path_buf.is_file() => {
+                            return Ok(path_buf.to_string_lossy().into_owned());
+                        }
+                        _ => warn!("Stored claude path no longer exists: {}", stored_path),
                     }
                 }
                 
@@ -69,12 +128,24 @@ pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, Strin
         }
     }
 
-    // Discover all available system installations
-    let installations = discover_system_installations();
+    // Discover all available system installations. WSL ones can only be
+    // listed, since their path isn't one opcode can run, and the bundled
+    // binary is only used when there's nothing else.
+    let mut installations = discover_system_installations();
+    installations.retain(|i| {
+        !matches!(
+            i.installation_type,
+            InstallationType::Wsl | InstallationType::Bundled
+        )
+    });
 
     if installations.is_empty() {
+        if let Some(path) = bundled_claude_path() {
+            info!("No system Claude installation, using the bundled one");
+            return Ok(path.to_string_lossy().into_owned());
+        }
         error!("Could not find claude binary in any location");
-        return Err("Claude Code not found. Please ensure it's installed in one of these locations: PATH, /usr/local/bin, /opt/homebrew/bin, ~/.nvm/versions/node/*/bin, ~/.claude/local, ~/.local/bin".to_string());
+        return Err("Claude Code not found. Please ensure it's installed in one of these locations: PATH, /usr/local/bin, /opt/homebrew/bin, ~/.nvm/versions/node/*/bin, fnm, volta, asdf, pnpm or npm global directories, ~/.claude/local, ~/.local/bin".to_string());
     }
 
     // Log all found installations
@@ -94,14 +165,8 @@ pub fn find_claude_binary(app_handle: &tauri::AppHandle) -> Result<String, Strin
     }
 }
 
-/// Discovers all available Claude installations and returns them for selection
-/// This allows UI to show a version selector
-pub fn discover_claude_installations() -> Vec<ClaudeInstallation> {
-    info!("Discovering all Claude installations...");
-
-    let mut installations = discover_system_installations();
-
-    // Sort by version (highest first), then by source preference
+/// Sort by version (highest first), then by source preference
+pub fn sort_installations(installations: &mut [ClaudeInstallation]) {
     installations.sort_by(|a, b| {
         match (&a.version, &b.version) {
             (Some(v1), Some(v2)) => {
@@ -119,8 +184,6 @@ pub fn discover_claude_installations() -> Vec<ClaudeInstallation> {
             (None, None) => source_preference(a).cmp(&source_preference(b)),
         }
     });
-
-    installations
 }
 
 /// Returns a preference score for installation sources (lower is better)
@@ -131,20 +194,40 @@ fn source_preference(installation: &ClaudeInstallation) -> u8 {
         "system" => 3,
         "nvm-active" => 4,
         source if source.starts_with("nvm") => 5,
+        source if source.starts_with("fnm") => 5,
+        "volta" | "asdf" => 5,
         "local-bin" => 6,
         "claude-local" => 7,
-        "npm-global" => 8,
+        "npm-global" | "pnpm" => 8,
         "yarn" | "yarn-global" => 9,
-        "bun" => 10,
+        "bun" | "deno" => 10,
         "node-modules" => 11,
         "home-bin" => 12,
         "PATH" => 13,
+        "wsl" => 15,
+        "bundled" => 16,
         _ => 14,
     }
 }
 
-/// Discovers all Claude installations on the system
+/// Discovers all Claude installations on the system, probing their
+/// versions in parallel
 fn discover_system_installations() -> Vec<ClaudeInstallation> {
+    let mut installations = find_installation_candidates();
+    std::thread::scope(|scope| {
+        for installation in installations.iter_mut().filter(|i| i.version.is_none()) {
+            scope.spawn(move || {
+                installation.version = get_claude_version(&installation.path).ok().flatten();
+            });
+        }
+    });
+    installations
+}
+
+/// Claude installations on the system and the bundled one, without their
+/// versions apart from WSL's. Candidates that lead to the same binary are
+/// listed once, under the first source that found them.
+pub fn find_installation_candidates() -> Vec<ClaudeInstallation> {
     let mut installations = Vec::new();
 
     // 1. Try 'which' command first (now works in production)
@@ -152,25 +235,119 @@ fn discover_system_installations() -> Vec<ClaudeInstallation> {
         installations.push(installation);
     }
 
-    // 2. Check NVM paths (includes current active NVM)
+    // 2. Check Node version managers (includes current active NVM)
     installations.extend(find_nvm_installations());
+    installations.extend(find_version_manager_installations());
 
     // 3. Check standard paths
     installations.extend(find_standard_installations());
+    if cfg!(windows) {
+        installations.extend(find_windows_installations());
+    }
+
+    for installation in &mut installations {
+        installation.resolved_path = resolve_installation_path(installation);
+    }
+    let mut installations = dedup_installations(installations);
+    for installation in &mut installations {
+        installation.runtime = runtime_version(&installation.path);
+    }
+    if cfg!(windows) {
+        installations.extend(find_wsl_installation());
+    }
+    installations.extend(bundled_installation());
+    installations
+}
 
-    // Remove duplicates by path
-    let mut unique_paths = std::collections::HashSet::new();
-    installations.retain(|install| unique_paths.insert(install.path.clone()));
+/// The Claude binary shipped with opcode. Tauri places sidecars next to the
+/// app's executable, which is also where the shell plugin looks for them.
+pub fn bundled_claude_path() -> Option<PathBuf> {
+    let exe = tauri::utils::platform::current_exe().ok()?;
+    let mut path = exe.parent()?.join(BUNDLED_SIDECAR);
+    if cfg!(windows) {
+        path.set_extension("exe");
+    }
+    path.is_file().then_some(path)
+}
+
+/// Whether `path` is the bundled Claude binary, which is run as a sidecar
+pub fn is_bundled_binary(path: &Path) -> bool {
+    path.is_absolute()
+        && bundled_claude_path().is_some_and(|bundled| {
+            let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+            canonical(path) == canonical(&bundled)
+        })
+}
 
+/// The file a stored Claude binary setting refers to, mapping
+/// `BUNDLED_SENTINEL` to the bundled binary. None when that isn't shipped.
+pub fn binary_file(path: &str) -> Option<PathBuf> {
+    if path == BUNDLED_SENTINEL {
+        bundled_claude_path()
+    } else {
+        Some(PathBuf::from(path))
+    }
+}
+
+/// The bundled binary as an installation, selected with `BUNDLED_SENTINEL`
+fn bundled_installation() -> Option<ClaudeInstallation> {
+    let path = bundled_claude_path()?;
+    Some(ClaudeInstallation {
+        path: BUNDLED_SENTINEL.to_string(),
+        version: None,
+        source: "bundled".to_string(),
+        installation_type: InstallationType::Bundled,
+        resolved_path: Some(path.to_string_lossy().into_owned()),
+        missing: false,
+        runtime: None,
+    })
+}
+
+/// Follow symlinks to the binary an installation runs. A bare `claude` is
+/// looked up on PATH first.
+fn resolve_installation_path(installation: &ClaudeInstallation) -> Option<String> {
+    let path = if installation.path == "claude" {
+        find_in_path("claude")?
+    } else {
+        PathBuf::from(&installation.path)
+    };
+    let resolved = std::fs::canonicalize(&path).unwrap_or(path);
+    Some(resolved.to_string_lossy().into_owned())
+}
+
+/// Drop installations leading to a binary an earlier one already does
+fn dedup_installations(installations: Vec<ClaudeInstallation>) -> Vec<ClaudeInstallation> {
+    let mut seen = std::collections::HashSet::new();
     installations
+        .into_iter()
+        .filter(|installation| {
+            let key = installation
+                .resolved_path
+                .clone()
+                .unwrap_or_else(|| installation.path.clone());
+            seen.insert(key)
+        })
+        .collect()
+}
+
+/// The first `program` in a PATH directory, like `which` without running it
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = crate::commands::extra_path::extended_path();
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
 }
 
 /// Try using the 'which' command to find Claude
 fn try_which_command() -> Option<ClaudeInstallation> {
     debug!("Trying 'which claude' to find binary...");
 
-    match Command::new("which").arg("claude").output() {
-        Ok(output) if output.status.success() => {
+    let mut which = Command::new("which");
+    which
+        .arg("claude")
+        .env("PATH", crate::commands::extra_path::extended_path());
+    match output_with_timeout(which, VERSION_PROBE_TIMEOUT) {
+        Some(output) if output.status.success() => {
             let output_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
 
             if output_str.is_empty() {
@@ -195,20 +372,48 @@ fn try_which_command() -> Option<ClaudeInstallation> {
                 return None;
             }
 
-            // Get version
-            let version = get_claude_version(&path).ok().flatten();
-
-            Some(ClaudeInstallation {
-                path,
-                version,
-                source: "which".to_string(),
-                installation_type: InstallationType::System,
-            })
+            Some(ClaudeInstallation::system(path, "which"))
         }
         _ => None,
     }
 }
 
+/// `claude` in the `bin` directory of each Node version under `versions_dir`,
+/// sourced as e.g. `nvm (v20.11.0)`
+fn find_in_node_versions(
+    versions_dir: &Path,
+    bin_dir: &[&str],
+    source: &str,
+) -> Vec<ClaudeInstallation> {
+    debug!("Checking {} directory: {:?}", source, versions_dir);
+
+    let Ok(entries) = std::fs::read_dir(versions_dir) else {
+        return Vec::new();
+    };
+    let mut installations = Vec::new();
+    for entry in entries.flatten() {
+        if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            continue;
+        }
+        let claude_path = bin_dir
+            .iter()
+            .fold(entry.path(), |path, part| path.join(part))
+            .join("claude");
+        if claude_path.is_file() {
+            let node_version = entry.file_name().to_string_lossy().to_string();
+            debug!(
+                "Found Claude in {} node {}: {:?}",
+                source, node_version, claude_path
+            );
+            installations.push(ClaudeInstallation::system(
+                claude_path.to_string_lossy(),
+                format!("{} ({})", source, node_version),
+            ));
+        }
+    }
+    installations
+}
+
 /// Find Claude installations in NVM directories
 fn find_nvm_installations() -> Vec<ClaudeInstallation> {
     let mut installations = Vec::new();
@@ -218,48 +423,66 @@ fn find_nvm_installations() -> Vec<ClaudeInstallation> {
         let claude_path = PathBuf::from(&nvm_bin).join("claude");
         if claude_path.exists() && claude_path.is_file() {
             debug!("Found Claude via NVM_BIN: {:?}", claude_path);
-            let version = get_claude_version(&claude_path.to_string_lossy()).ok().flatten();
-            installations.push(ClaudeInstallation {
-                path: claude_path.to_string_lossy().to_string(),
-                version,
-                source: "nvm-active".to_string(),
-                installation_type: InstallationType::System,
-            });
+            installations.push(ClaudeInstallation::system(
+                claude_path.to_string_lossy(),
+                "nvm-active",
+            ));
         }
     }
 
     // Then check all NVM directories
-    if let Ok(home) = std::env::var("HOME") {
-        let nvm_dir = PathBuf::from(&home)
-            .join(".nvm")
-            .join("versions")
-            .join("node");
-
-        debug!("Checking NVM directory: {:?}", nvm_dir);
-
-        if let Ok(entries) = std::fs::read_dir(&nvm_dir) {
-            for entry in entries.flatten() {
-                if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
-                    let claude_path = entry.path().join("bin").join("claude");
+    let nvm_dir = std::env::var_os("NVM_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".nvm")));
+    if let Some(nvm_dir) = nvm_dir {
+        installations.extend(find_in_node_versions(
+            &nvm_dir.join("versions").join("node"),
+            &["bin"],
+            "nvm",
+        ));
+    }
 
-                    if claude_path.exists() && claude_path.is_file() {
-                        let path_str = claude_path.to_string_lossy().to_string();
-                        let node_version = entry.file_name().to_string_lossy().to_string();
+    installations
+}
 
-                        debug!("Found Claude in NVM node {}: {}", node_version, path_str);
+/// Find Claude installed through fnm, volta or asdf, which GUI apps don't
+/// get on their PATH
+fn find_version_manager_installations() -> Vec<ClaudeInstallation> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    let mut installations = Vec::new();
 
-                        // Get Claude version
-                        let version = get_claude_version(&path_str).ok().flatten();
+    let mut fnm_dirs = vec![
+        home.join(".fnm"),
+        home.join(".local").join("share").join("fnm"),
+        home.join("Library").join("Application Support").join("fnm"),
+    ];
+    if let Some(fnm_dir) = std::env::var_os("FNM_DIR") {
+        fnm_dirs.insert(0, PathBuf::from(fnm_dir));
+    }
+    for fnm_dir in fnm_dirs {
+        installations.extend(find_in_node_versions(
+            &fnm_dir.join("node-versions"),
+            &["installation", "bin"],
+            "fnm",
+        ));
+    }
 
-                        installations.push(ClaudeInstallation {
-                            path: path_str,
-                            version,
-                            source: format!("nvm ({})", node_version),
-                            installation_type: InstallationType::System,
-                        });
-                    }
-                }
-            }
+    let volta_home = std::env::var_os("VOLTA_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".volta"));
+    let asdf_dir = std::env::var_os("ASDF_DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".asdf"));
+    let shims = [
+        (volta_home.join("bin").join("claude"), "volta"),
+        (asdf_dir.join("shims").join("claude"), "asdf"),
+    ];
+    for (path, source) in shims {
+        if path.is_file() {
+            debug!("Found claude via {}: {:?}", source, path);
+            installations.push(ClaudeInstallation::system(path.to_string_lossy(), source));
         }
     }
 
@@ -270,17 +493,40 @@ fn find_nvm_installations() -> Vec<ClaudeInstallation> {
 fn find_standard_installations() -> Vec<ClaudeInstallation> {
     let mut installations = Vec::new();
 
-    // Common installation paths for claude
+    // Common installation paths for claude. On macOS /usr/local is Homebrew's
+    // prefix on Intel machines.
+    let usr_local_source = if cfg!(target_os = "macos") {
+        "homebrew"
+    } else {
+        "system"
+    };
     let mut paths_to_check: Vec<(String, String)> = vec![
-        ("/usr/local/bin/claude".to_string(), "system".to_string()),
         (
             "/opt/homebrew/bin/claude".to_string(),
             "homebrew".to_string(),
         ),
+        (
+            "/usr/local/bin/claude".to_string(),
+            usr_local_source.to_string(),
+        ),
         ("/usr/bin/claude".to_string(), "system".to_string()),
         ("/bin/claude".to_string(), "system".to_string()),
     ];
 
+    // npm, pnpm, Bun and Deno global directories moved with a custom prefix
+    for (var, suffix, source) in [
+        ("NPM_CONFIG_PREFIX", "bin/claude", "npm-global"),
+        ("npm_config_prefix", "bin/claude", "npm-global"),
+        ("PNPM_HOME", "claude", "pnpm"),
+        ("BUN_INSTALL", "bin/claude", "bun"),
+        ("DENO_INSTALL_ROOT", "bin/claude", "deno"),
+        ("DENO_INSTALL", "bin/claude", "deno"),
+    ] {
+        if let Ok(dir) = std::env::var(var) {
+            paths_to_check.push((format!("{}/{}", dir, suffix), source.to_string()));
+        }
+    }
+
     // Also check user-specific paths
     if let Ok(home) = std::env::var("HOME") {
         paths_to_check.extend(vec![
@@ -296,8 +542,18 @@ fn find_standard_installations() -> Vec<ClaudeInstallation> {
                 format!("{}/.npm-global/bin/claude", home),
                 "npm-global".to_string(),
             ),
+            (
+                format!("{}/.npm-packages/bin/claude", home),
+                "npm-global".to_string(),
+            ),
+            (
+                format!("{}/.local/share/pnpm/claude", home),
+                "pnpm".to_string(),
+            ),
+            (format!("{}/Library/pnpm/claude", home), "pnpm".to_string()),
             (format!("{}/.yarn/bin/claude", home), "yarn".to_string()),
             (format!("{}/.bun/bin/claude", home), "bun".to_string()),
+            (format!("{}/.deno/bin/claude", home), "deno".to_string()),
             (format!("{}/bin/claude", home), "home-bin".to_string()),
             // Check common node_modules locations
             (
@@ -316,49 +572,279 @@ fn find_standard_installations() -> Vec<ClaudeInstallation> {
         let path_buf = PathBuf::from(&path);
         if path_buf.exists() && path_buf.is_file() {
             debug!("Found claude at standard path: {} ({})", path, source);
+            installations.push(ClaudeInstallation::system(path, source));
+        }
+    }
 
-            // Get version
-            let version = get_claude_version(&path).ok().flatten();
+    // Also check if claude is available in PATH (without full path)
+    if find_in_path("claude").is_some() {
+        debug!("claude is available in PATH");
+        installations.push(ClaudeInstallation::system("claude", "PATH"));
+    }
 
-            installations.push(ClaudeInstallation {
-                path,
-                version,
-                source,
-                installation_type: InstallationType::System,
-            });
+    installations
+}
+
+/// Check where npm, pnpm, volta and the native installer put Claude on Windows
+fn find_windows_installations() -> Vec<ClaudeInstallation> {
+    let env_dir = |var: &str| std::env::var_os(var).map(PathBuf::from);
+    let mut paths_to_check = Vec::new();
+    if let Some(app_data) = env_dir("APPDATA") {
+        paths_to_check.push((app_data.join("npm").join("claude.cmd"), "npm-global"));
+    }
+    if let Some(local_app_data) = env_dir("LOCALAPPDATA") {
+        paths_to_check.push((local_app_data.join("pnpm").join("claude.cmd"), "pnpm"));
+        paths_to_check.push((
+            local_app_data.join("Volta").join("bin").join("claude.exe"),
+            "volta",
+        ));
+    }
+    if let Some(profile) = env_dir("USERPROFILE") {
+        paths_to_check.push((
+            profile.join(".local").join("bin").join("claude.exe"),
+            "local-bin",
+        ));
+        paths_to_check.push((
+            profile.join(".claude").join("local").join("claude.exe"),
+            "claude-local",
+        ));
+    }
+
+    paths_to_check
+        .into_iter()
+        .filter(|(path, _)| path.is_file())
+        .map(|(path, source)| ClaudeInstallation::system(path.to_string_lossy(), source))
+        .collect()
+}
+
+/// Claude installed in the default WSL distribution. Its version is read
+/// through `wsl.exe` here, since the path only exists inside WSL.
+fn find_wsl_installation() -> Option<ClaudeInstallation> {
+    // A login shell, so version managers set up in the profile are on PATH
+    let wsl_shell = |script: &str| {
+        let mut cmd = Command::new("wsl.exe");
+        cmd.args(["-e", "bash", "-lc", script]);
+        output_with_timeout(cmd, VERSION_PROBE_TIMEOUT).filter(|output| output.status.success())
+    };
+    let output = wsl_shell("command -v claude")?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if path.is_empty() {
+        return None;
+    }
+    debug!("Found claude in WSL: {}", path);
+
+    let version = wsl_shell(&format!("'{}' --version", path.replace('\'', "'\\''")))
+        .and_then(|output| extract_version_from_output(&output.stdout));
+    Some(ClaudeInstallation {
+        resolved_path: Some(path.clone()),
+        path,
+        version,
+        source: "wsl".to_string(),
+        installation_type: InstallationType::Wsl,
+        missing: false,
+        runtime: None,
+    })
+}
+
+/// Run `cmd` with its output captured, killing it once `timeout` passes
+fn output_with_timeout(mut cmd: Command, timeout: Duration) -> Option<Output> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return child.wait_with_output().ok(),
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            Ok(None) => {
+                warn!("{:?} took longer than {:?}, killing it", cmd, timeout);
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Err(e) => {
+                warn!("Failed to wait for {:?}: {}", cmd, e);
+                return None;
+            }
         }
     }
+}
 
-    // Also check if claude is available in PATH (without full path)
-    if let Ok(output) = Command::new("claude").arg("--version").output() {
-        if output.status.success() {
-            debug!("claude is available in PATH");
-            let version = extract_version_from_output(&output.stdout);
-
-            installations.push(ClaudeInstallation {
-                path: "claude".to_string(),
-                version,
-                source: "PATH".to_string(),
-                installation_type: InstallationType::System,
-            });
+/// A runtime other than Node that a Claude shim runs under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShimRuntime {
+    Bun,
+    Deno,
+}
+
+impl ShimRuntime {
+    pub fn name(self) -> &'static str {
+        match self {
+            ShimRuntime::Bun => "Bun",
+            ShimRuntime::Deno => "Deno",
         }
     }
 
-    installations
+    /// The runtime's executable name
+    pub fn program(self) -> &'static str {
+        match (self, cfg!(windows)) {
+            (ShimRuntime::Bun, false) => "bun",
+            (ShimRuntime::Bun, true) => "bun.exe",
+            (ShimRuntime::Deno, false) => "deno",
+            (ShimRuntime::Deno, true) => "deno.exe",
+        }
+    }
+
+    /// Where the runtime's installers put it
+    fn install_dirs(self) -> Vec<PathBuf> {
+        let (vars, home_dir): (&[&str], &str) = match self {
+            ShimRuntime::Bun => (&["BUN_INSTALL"], ".bun"),
+            ShimRuntime::Deno => (&["DENO_INSTALL", "DENO_INSTALL_ROOT"], ".deno"),
+        };
+        vars.iter()
+            .filter_map(std::env::var_os)
+            .map(PathBuf::from)
+            .chain(dirs::home_dir().map(|home| home.join(home_dir)))
+            .map(|dir| dir.join("bin"))
+            .collect()
+    }
+
+    /// The runtime in a directory of `path`
+    fn find_in(self, path: &std::ffi::OsStr) -> Option<PathBuf> {
+        std::env::split_paths(path)
+            .map(|dir| dir.join(self.program()))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// The runtime, on this process's PATH or where it's installed
+    fn locate(self) -> Option<PathBuf> {
+        self.find_in(&crate::commands::extra_path::extended_path())
+            .or_else(|| {
+                self.install_dirs()
+                    .into_iter()
+                    .map(|dir| dir.join(self.program()))
+                    .find(|candidate| candidate.is_file())
+            })
+    }
+}
+
+/// The runtime named by a script line: its shebang or a command it runs
+fn runtime_in_line(line: &str) -> Option<ShimRuntime> {
+    line.split(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '`'))
+        .filter_map(|word| Path::new(word).file_stem()?.to_str())
+        .find_map(|name| match name {
+            "bun" => Some(ShimRuntime::Bun),
+            "deno" => Some(ShimRuntime::Deno),
+            _ => None,
+        })
+}
+
+/// The runtime a script runs under when it's a Bun or Deno shim, judged by
+/// its shebang or, for shell wrappers, the command they exec. None for
+/// binaries and Node scripts.
+pub fn shim_runtime_of_script(script: &str) -> Option<ShimRuntime> {
+    let mut lines = script.lines();
+    let shebang = lines.next()?.strip_prefix("#!")?;
+    runtime_in_line(shebang).or_else(|| {
+        lines
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .find_map(runtime_in_line)
+    })
+}
+
+/// The runtime the Claude binary at `path` is a shim for
+pub fn shim_runtime(path: &Path) -> Option<ShimRuntime> {
+    use std::io::Read;
+
+    let mut head = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(4096)
+        .read_to_end(&mut head)
+        .ok()?;
+    shim_runtime_of_script(&String::from_utf8_lossy(&head))
+}
+
+/// The PATH `cmd` will run with
+fn command_path(cmd: &Command) -> std::ffi::OsString {
+    cmd.get_envs()
+        .find(|(key, _)| *key == "PATH")
+        .and_then(|(_, value)| value.map(|value| value.to_os_string()))
+        .or_else(|| std::env::var_os("PATH"))
+        .unwrap_or_default()
+}
+
+/// Fail when `cmd` runs a Bun or Deno shim whose runtime isn't on its PATH,
+/// naming the runtime rather than leaving a generic spawn failure
+pub fn check_shim_runtime(cmd: &Command) -> Result<(), String> {
+    let path = command_path(cmd);
+    let program = Path::new(cmd.get_program());
+    let file = if program.components().count() > 1 {
+        Some(program.to_path_buf())
+    } else {
+        std::env::split_paths(&path)
+            .map(|dir| dir.join(program))
+            .find(|candidate| candidate.is_file())
+    };
+    let Some(runtime) = file.as_deref().and_then(shim_runtime) else {
+        return Ok(());
+    };
+    if runtime.find_in(&path).is_some() {
+        return Ok(());
+    }
+    Err(format!(
+        "Claude at {} runs under {}, but `{}` wasn't found on PATH. Install {} or add \
+         the directory it's in to the extra PATH entries in settings.",
+        program.display(),
+        runtime.name(),
+        runtime.program(),
+        runtime.name()
+    ))
+}
+
+/// The runtime a Claude shim needs with its version, for debugging, e.g.
+/// "bun 1.1.30" or "deno (not found)". None when it isn't a Bun or Deno shim.
+fn runtime_version(path: &str) -> Option<String> {
+    let runtime = shim_runtime(&binary_file(path)?)?;
+    let name = runtime.program().trim_end_matches(".exe");
+    let Some(program) = runtime.locate() else {
+        return Some(format!("{} (not found)", name));
+    };
+    let mut cmd = Command::new(program);
+    cmd.arg("--version");
+    let version = output_with_timeout(cmd, VERSION_PROBE_TIMEOUT)
+        .and_then(|output| extract_version_from_output(&output.stdout));
+    Some(match version {
+        Some(version) => format!("{} {}", name, version),
+        None => name.to_string(),
+    })
+}
+
+/// Run `path --version`, giving up on it after `VERSION_PROBE_TIMEOUT`.
+/// None when it can't be run or times out.
+pub fn run_version_command(path: &str) -> Option<VersionOutput> {
+    let mut cmd = Command::new(binary_file(path)?);
+    cmd.arg("--version");
+    let output = output_with_timeout(cmd, VERSION_PROBE_TIMEOUT)?;
+    Some(VersionOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        success: output.status.success(),
+    })
 }
 
 /// Get Claude version by running --version command
 fn get_claude_version(path: &str) -> Result<Option<String>, String> {
-    match Command::new(path).arg("--version").output() {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(extract_version_from_output(&output.stdout))
-            } else {
-                Ok(None)
-            }
-        }
-        Err(e) => {
-            warn!("Failed to get version for {}: {}", path, e);
+    match run_version_command(path) {
+        Some(output) => Ok(output.version()),
+        None => {
+            warn!("Failed to get version for {}", path);
             Ok(None)
         }
     }
@@ -381,7 +867,12 @@ fn extract_version_from_output(stdout: &[u8]) -> Option<String> {
     // - Optionally followed by pre-release/build metadata
     let version_regex = regex::Regex::new(r"(\d+\.\d+\.\d+(?:-[a-zA-Z0-9.-]+)?(?:\+[a-zA-Z0-9.-]+)?)").ok()?;
     
-    if let Some(captures) = version_regex.captures(&output_str) {
+    // Shims may print download or runtime notices with versions of their
+    // own, so the line naming Claude Code wins
+    let claude_line = output_str
+        .lines()
+        .find(|line| line.contains("Claude Code") && version_regex.is_match(line));
+    if let Some(captures) = version_regex.captures(claude_line.unwrap_or(&output_str)) {
         if let Some(version_match) = captures.get(1) {
             let version = version_match.as_str().to_string();
             debug!("Extracted version: {:?}", version);
@@ -534,5 +1025,123 @@ pub fn create_command_with_env(program: &str) -> Command {
         }
     }
 
+    // Bun and Deno shims need their runtime, which GUI apps often can't see
+    if let Some(runtime) = shim_runtime(Path::new(program)) {
+        let path = command_path(&cmd);
+        if runtime.find_in(&path).is_none() {
+            if let Some(dir) = runtime
+                .install_dirs()
+                .into_iter()
+                .find(|dir| dir.join(runtime.program()).is_file())
+            {
+                debug!(
+                    "Adding {} directory to PATH: {}",
+                    runtime.name(),
+                    dir.display()
+                );
+                let path = crate::commands::extra_path::path_with_entries(&[dir], &path);
+                cmd.env("PATH", path);
+            }
+        }
+    }
+
+    // Directories the user added for tools GUI apps don't see on PATH
+    crate::commands::extra_path::apply_extra_path(&mut cmd);
+
     cmd
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_node_version_managers_are_scanned() {
+        let fnm = tempfile::tempdir().unwrap();
+        let bin = fnm.path().join("v20.11.0").join("installation").join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(bin.join("claude"), "").unwrap();
+        fs::create_dir_all(fnm.path().join("v18.0.0").join("installation")).unwrap();
+
+        let installations = find_in_node_versions(fnm.path(), &["installation", "bin"], "fnm");
+        assert_eq!(installations.len(), 1);
+        assert_eq!(installations[0].source, "fnm (v20.11.0)");
+        assert_eq!(PathBuf::from(&installations[0].path), bin.join("claude"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_to_one_binary_are_listed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("cli.js");
+        fs::write(&binary, "").unwrap();
+        let link = dir.path().join("claude");
+        std::os::unix::fs::symlink(&binary, &link).unwrap();
+
+        let mut installations = vec![
+            ClaudeInstallation::system(link.to_string_lossy(), "which"),
+            ClaudeInstallation::system(binary.to_string_lossy(), "npm-global"),
+        ];
+        for installation in &mut installations {
+            installation.resolved_path = resolve_installation_path(installation);
+        }
+        let installations = dedup_installations(installations);
+        assert_eq!(installations.len(), 1);
+        assert_eq!(installations[0].source, "which");
+        assert_eq!(
+            installations[0].resolved_path,
+            Some(
+                fs::canonicalize(&binary)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hung_probes_are_killed() {
+        let mut sleep = Command::new("sleep");
+        sleep.arg("5");
+        let started = Instant::now();
+        assert!(output_with_timeout(sleep, Duration::from_millis(100)).is_none());
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let mut echo = Command::new("echo");
+        echo.arg("1.0.41 (Claude Code)");
+        let output = output_with_timeout(echo, VERSION_PROBE_TIMEOUT).unwrap();
+        assert_eq!(
+            extract_version_from_output(&output.stdout).as_deref(),
+            Some("1.0.41")
+        );
+    }
+
+    #[test]
+    fn test_bun_and_deno_shims_are_recognized() {
+        assert_eq!(
+            shim_runtime_of_script("#!/usr/bin/env bun\nimport './cli.js'\n"),
+            Some(ShimRuntime::Bun)
+        );
+        let deno_wrapper = "#!/bin/sh\n# generated by deno install\n\
+            exec \"/home/me/.deno/bin/deno\" run --allow-all 'npm:@anthropic-ai/claude-code' \"$@\"\n";
+        assert_eq!(
+            shim_runtime_of_script(deno_wrapper),
+            Some(ShimRuntime::Deno)
+        );
+        assert_eq!(
+            shim_runtime_of_script("#!/usr/bin/env node\nrequire('bun-types')\n"),
+            None
+        );
+        assert_eq!(shim_runtime_of_script("\x7fELF binary"), None);
+
+        let output = VersionOutput {
+            stdout: String::new(),
+            stderr: "Download npm:@anthropic-ai/claude-code@2.0.1\n1.0.41 (Claude Code)\n"
+                .to_string(),
+            success: true,
+        };
+        assert_eq!(output.version().as_deref(), Some("1.0.41"));
+    }
+}
@@ -0,0 +1,293 @@
+use serde_json::{Map, Value as JsonValue};
+use sha2::{Digest, Sha256};
+
+use super::agent_icons::decode_icon_image;
+use super::agents::{AgentData, AgentExport};
+
+/// Agent export format written by `export_agent`
+pub const AGENT_EXPORT_VERSION: u64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FieldKind {
+    String,
+    OptionalString,
+    Integer,
+    Bool,
+    Object,
+}
+
+impl FieldKind {
+    fn matches(self, value: &JsonValue) -> bool {
+        match self {
+            FieldKind::String => value.is_string(),
+            FieldKind::OptionalString => value.is_string() || value.is_null(),
+            FieldKind::Integer => value.is_u64(),
+            FieldKind::Bool => value.is_boolean(),
+            FieldKind::Object => value.is_object(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            FieldKind::String => "a string",
+            FieldKind::OptionalString => "a string or null",
+            FieldKind::Integer => "a whole number",
+            FieldKind::Bool => "true or false",
+            FieldKind::Object => "an object",
+        }
+    }
+}
+
+/// (name, kind, required)
+type Schema = &'static [(&'static str, FieldKind, bool)];
+
+const EXPORT_FIELDS_V1: Schema = &[
+    ("version", FieldKind::Integer, true),
+    ("exported_at", FieldKind::String, true),
+    ("agent", FieldKind::Object, true),
+];
+
+const EXPORT_FIELDS_V2: Schema = &[
+    ("version", FieldKind::Integer, true),
+    ("exported_at", FieldKind::String, true),
+    ("checksum", FieldKind::String, true),
+    ("agent", FieldKind::Object, true),
+];
+
+const AGENT_FIELDS_V1: Schema = &[
+    ("name", FieldKind::String, true),
+    ("icon", FieldKind::String, true),
+    ("system_prompt", FieldKind::String, true),
+    ("model", FieldKind::String, true),
+    ("default_task", FieldKind::OptionalString, false),
+    ("hooks", FieldKind::OptionalString, false),
+    ("icon_image", FieldKind::Object, false),
+];
+
+const AGENT_FIELDS_V2: Schema = &[
+    ("name", FieldKind::String, true),
+    ("icon", FieldKind::String, true),
+    ("system_prompt", FieldKind::String, true),
+    ("model", FieldKind::String, true),
+    ("default_task", FieldKind::OptionalString, false),
+    ("hooks", FieldKind::OptionalString, false),
+    ("icon_image", FieldKind::Object, false),
+    ("enable_file_read", FieldKind::Bool, true),
+    ("enable_file_write", FieldKind::Bool, true),
+    ("enable_network", FieldKind::Bool, true),
+];
+
+/// Problems found checking an object against a schema
+#[derive(Default)]
+struct SchemaErrors {
+    missing: Vec<String>,
+    unknown: Vec<String>,
+    wrong_type: Vec<String>,
+}
+
+impl SchemaErrors {
+    fn check(&mut self, object: &Map<String, JsonValue>, schema: Schema, prefix: &str) {
+        for (name, kind, required) in schema {
+            match object.get(*name) {
+                None if *required => self.missing.push(format!("{}{}", prefix, name)),
+                Some(value) if !kind.matches(value) => self
+                    .wrong_type
+                    .push(format!("{}{} should be {}", prefix, name, kind.describe())),
+                _ => {}
+            }
+        }
+        let mut unknown: Vec<String> = object
+            .keys()
+            .filter(|key| !schema.iter().any(|(name, _, _)| name == key))
+            .map(|key| format!("{}{}", prefix, key))
+            .collect();
+        unknown.sort();
+        self.unknown.extend(unknown);
+    }
+
+    fn into_result(self, version: u64) -> Result<(), String> {
+        let mut parts = Vec::new();
+        if !self.missing.is_empty() {
+            parts.push(format!("missing fields: {}", self.missing.join(", ")));
+        }
+        if !self.unknown.is_empty() {
+            parts.push(format!("unknown fields: {}", self.unknown.join(", ")));
+        }
+        if !self.wrong_type.is_empty() {
+            parts.push(format!("wrong types: {}", self.wrong_type.join(", ")));
+        }
+        if parts.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Invalid agent export (version {}): {}",
+                version,
+                parts.join("; ")
+            ))
+        }
+    }
+}
+
+/// The value with object keys sorted at every level
+fn canonical_json(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(object) => {
+            let mut entries: Vec<_> = object.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            JsonValue::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonical_json(value)))
+                    .collect(),
+            )
+        }
+        JsonValue::Array(items) => JsonValue::Array(items.iter().map(canonical_json).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Checksum of an export's raw `agent` object, as written to `checksum`.
+/// Hashes the compact JSON with sorted keys, so field order and whitespace don't matter.
+pub fn agent_checksum(agent: &JsonValue) -> Result<String, String> {
+    let payload = serde_json::to_vec(&canonical_json(agent))
+        .map_err(|e| format!("Failed to serialize agent: {}", e))?;
+    Ok(format!("sha256:{:x}", Sha256::digest(&payload)))
+}
+
+/// Wrap an agent in the current export format
+pub fn build_agent_export(agent: AgentData) -> Result<AgentExport, String> {
+    let value =
+        serde_json::to_value(&agent).map_err(|e| format!("Failed to serialize agent: {}", e))?;
+    Ok(AgentExport {
+        version: AGENT_EXPORT_VERSION as u32,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        checksum: Some(agent_checksum(&value)?),
+        agent,
+    })
+}
+
+/// Parse and validate an agent export of any supported version. Version 1
+/// files, which have no checksum or permission flags, get the permissions
+/// imports always gave them: file read and write on, network off.
+pub fn validate_agent_export(json_data: &str) -> Result<AgentExport, String> {
+    let value: JsonValue = serde_json::from_str(json_data).map_err(|e| {
+        if e.is_eof() {
+            format!("Incomplete JSON, the file may be truncated: {}", e)
+        } else {
+            format!("Invalid JSON format: {}", e)
+        }
+    })?;
+    let mut export = match value {
+        JsonValue::Object(export) => export,
+        _ => return Err("Not an agent export: expected a JSON object".to_string()),
+    };
+
+    let version = match export.get("version") {
+        None => return Err("Not an agent export: missing 'version'".to_string()),
+        Some(version) => version
+            .as_u64()
+            .filter(|v| *v > 0)
+            .ok_or("Invalid agent export: 'version' should be a positive whole number")?,
+    };
+    if version > AGENT_EXPORT_VERSION {
+        return Err(format!(
+            "Export version {} is newer than this app supports (version {}). Update the app to import it.",
+            version, AGENT_EXPORT_VERSION
+        ));
+    }
+    let (export_fields, agent_fields) = match version {
+        1 => (EXPORT_FIELDS_V1, AGENT_FIELDS_V1),
+        _ => (EXPORT_FIELDS_V2, AGENT_FIELDS_V2),
+    };
+
+    let mut errors = SchemaErrors::default();
+    errors.check(&export, export_fields, "");
+    if let Some(JsonValue::Object(agent)) = export.get("agent") {
+        errors.check(agent, agent_fields, "agent.");
+    }
+    errors.into_result(version)?;
+    // Hash the agent as written, before any defaults are filled in
+    let actual_checksum = export.get("agent").map(agent_checksum).transpose()?;
+
+    if version == 1 {
+        if let Some(JsonValue::Object(agent)) = export.get_mut("agent") {
+            agent.insert("enable_file_read".to_string(), JsonValue::Bool(true));
+            agent.insert("enable_file_write".to_string(), JsonValue::Bool(true));
+            agent.insert("enable_network".to_string(), JsonValue::Bool(false));
+        }
+    }
+
+    let export: AgentExport = serde_json::from_value(JsonValue::Object(export))
+        .map_err(|e| format!("Invalid agent export: {}", e))?;
+    if export.agent.name.trim().is_empty() {
+        return Err("Invalid agent export: agent name is empty".to_string());
+    }
+    if let Some(icon_image) = &export.agent.icon_image {
+        decode_icon_image(icon_image).map_err(|e| format!("Invalid agent export: {}", e))?;
+    }
+    if let Some(checksum) = &export.checksum {
+        if Some(checksum) != actual_checksum.as_ref() {
+            return Err(
+                "Checksum mismatch: the agent export was changed or corrupted after it was exported"
+                    .to_string(),
+            );
+        }
+    }
+
+    Ok(export)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1_FIXTURE: &str = include_str!("../../tests/fixtures/agent_export_v1.opcode.json");
+    const V2_FIXTURE: &str = include_str!("../../tests/fixtures/agent_export_v2.opcode.json");
+
+    #[test]
+    fn test_v1_exports_import_with_default_permissions() {
+        let export = validate_agent_export(V1_FIXTURE).unwrap();
+        assert_eq!((export.version, export.checksum.as_deref()), (1, None));
+        assert_eq!(export.agent.name, "Changelog Writer");
+        assert!(export.agent.enable_file_read && export.agent.enable_file_write);
+        assert!(!export.agent.enable_network);
+    }
+
+    #[test]
+    fn test_v2_exports_verify_their_checksum() {
+        let export = validate_agent_export(V2_FIXTURE).unwrap();
+        assert_eq!(export.version, 2);
+        assert!(export.agent.enable_network && !export.agent.enable_file_write);
+
+        let tampered = V2_FIXTURE.replace("Review the diff", "Delete the repo");
+        assert!(validate_agent_export(&tampered).unwrap_err().contains("Checksum mismatch"));
+
+        let reordered = V2_FIXTURE
+            .replace("    \"name\": \"PR Reviewer\",\n", "")
+            .replace("\"enable_network\": true", "\"enable_network\": true, \"name\": \"PR Reviewer\"");
+        assert_ne!(reordered, V2_FIXTURE);
+        assert_eq!(validate_agent_export(&reordered).unwrap().agent, export.agent);
+
+        let rebuilt = build_agent_export(export.agent).unwrap();
+        let json = serde_json::to_string_pretty(&rebuilt).unwrap();
+        assert_eq!(validate_agent_export(&json).unwrap().agent, rebuilt.agent);
+    }
+
+    #[test]
+    fn test_schema_errors_list_every_problem() {
+        let err = validate_agent_export(
+            r#"{"version": 2, "exported_at": "now", "agent": {"name": "x", "icon": 3, "color": "red", "model": "sonnet", "enable_file_read": true, "enable_file_write": true, "enable_network": false}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "Invalid agent export (version 2): missing fields: checksum, agent.system_prompt; unknown fields: agent.color; wrong types: agent.icon should be a string"
+        );
+
+        let err = validate_agent_export(r#"{"version": 1, "exported_at": "now", "agent": {"name": "x", "icon": "bot", "system_prompt": "p", "model": "m", "enable_network": true}}"#).unwrap_err();
+        assert!(err.ends_with("unknown fields: agent.enable_network"));
+
+        assert!(validate_agent_export(r#"{"version": 3}"#).unwrap_err().contains("newer"));
+        assert!(validate_agent_export(r#"{"agent": {}}"#).unwrap_err().contains("missing 'version'"));
+    }
+}
@@ -0,0 +1,149 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Largest icon image accepted, before base64 encoding
+const MAX_ICON_IMAGE_BYTES: usize = 256 * 1024;
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// A custom agent icon, shown instead of the agent's named icon
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AgentIconImage {
+    pub mime: String, // 'image/png' or 'image/svg+xml'
+    pub data: String, // Base64 encoded image
+}
+
+/// Check that `bytes` is a PNG or SVG image of the given type and within the size limit
+fn validate_icon_image(bytes: &[u8], mime: &str) -> Result<(), String> {
+    if bytes.len() > MAX_ICON_IMAGE_BYTES {
+        return Err(format!(
+            "Icon image is {} KB, the limit is {} KB",
+            bytes.len().div_ceil(1024),
+            MAX_ICON_IMAGE_BYTES / 1024
+        ));
+    }
+
+    let matches_type = match mime {
+        "image/png" => bytes.starts_with(PNG_MAGIC),
+        "image/svg+xml" => std::str::from_utf8(bytes).is_ok_and(|text| {
+            let text = text.trim_start_matches('\u{feff}').trim_start();
+            (text.starts_with("<svg") || text.starts_with("<?xml")) && text.contains("<svg")
+        }),
+        _ => {
+            return Err(format!(
+                "Unsupported icon type '{}', use image/png or image/svg+xml",
+                mime
+            ))
+        }
+    };
+    if !matches_type {
+        return Err(format!("Icon image content is not a valid {}", mime));
+    }
+    Ok(())
+}
+
+/// Decode and validate an icon image, returning its bytes
+pub fn decode_icon_image(icon: &AgentIconImage) -> Result<Vec<u8>, String> {
+    let bytes = STANDARD
+        .decode(icon.data.trim())
+        .map_err(|e| format!("Icon image is not valid base64: {}", e))?;
+    validate_icon_image(&bytes, &icon.mime)?;
+    Ok(bytes)
+}
+
+/// Store an agent's icon image, replacing any previous one
+pub fn save_icon_image(conn: &Connection, agent_id: i64, icon: &AgentIconImage) -> Result<(), String> {
+    let bytes = decode_icon_image(icon)?;
+    conn.execute(
+        "INSERT INTO agent_icons (agent_id, mime, data) VALUES (?1, ?2, ?3)
+         ON CONFLICT(agent_id) DO UPDATE SET mime = ?2, data = ?3, updated_at = CURRENT_TIMESTAMP",
+        params![agent_id, icon.mime, bytes],
+    )
+    .map_err(|e| format!("Failed to save icon image: {}", e))?;
+    Ok(())
+}
+
+pub fn load_icon_image(conn: &Connection, agent_id: i64) -> Result<Option<AgentIconImage>, String> {
+    conn.query_row(
+        "SELECT mime, data FROM agent_icons WHERE agent_id = ?1",
+        params![agent_id],
+        |row| {
+            Ok(AgentIconImage {
+                mime: row.get(0)?,
+                data: STANDARD.encode(row.get::<_, Vec<u8>>(1)?),
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+pub fn delete_icon_image(conn: &Connection, agent_id: i64) -> Result<(), String> {
+    conn.execute("DELETE FROM agent_icons WHERE agent_id = ?1", params![agent_id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Set a custom PNG or SVG icon for an agent
+#[tauri::command]
+pub async fn set_agent_icon_image(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    bytes_base64: String,
+    mime: String,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM agents WHERE id = ?1)",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+
+    save_icon_image(
+        &conn,
+        agent_id,
+        &AgentIconImage {
+            mime: mime.trim().to_lowercase(),
+            data: bytes_base64,
+        },
+    )
+}
+
+/// Get an agent's custom icon image, or None when it uses a named icon
+#[tauri::command]
+pub async fn get_agent_icon_image(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+) -> Result<Option<AgentIconImage>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_icon_image(&conn, agent_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_images_are_checked_against_their_type() {
+        let png = [PNG_MAGIC, b"rest"].concat();
+        assert!(validate_icon_image(&png, "image/png").is_ok());
+        assert!(validate_icon_image(b"GIF89a", "image/png").is_err());
+        assert!(validate_icon_image(&png, "image/gif").is_err());
+
+        let svg = br#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg"/>"#;
+        assert!(validate_icon_image(svg, "image/svg+xml").is_ok());
+        assert!(validate_icon_image(b"<html></html>", "image/svg+xml").is_err());
+
+        let too_big = [PNG_MAGIC, &vec![0; MAX_ICON_IMAGE_BYTES]].concat();
+        assert!(validate_icon_image(&too_big, "image/png").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Format of `agent_runs.created_at`, which SQLite fills with CURRENT_TIMESTAMP
+const DB_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Aggregated run statistics for one agent, or for all agents when `agent_id` is None
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AgentMetricsRow {
+    pub agent_id: Option<i64>,
+    pub agent_name: Option<String>,
+    pub run_count: i64,
+    pub completed: i64,
+    pub failed: i64,    // 'failed', 'timed_out', 'budget_exceeded' and 'interrupted'
+    pub cancelled: i64, // 'cancelled' and 'killed'
+    pub running: i64,   // Still pending, queued or running
+    pub success_rate: Option<f64>, // Completed share of finished runs
+    pub avg_duration_ms: Option<f64>, // Finished runs only
+    pub total_tokens: i64,
+    pub total_cost_usd: f64,
+}
+
+/// Per-agent statistics plus the overall total
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentMetricsReport {
+    pub agents: Vec<AgentMetricsRow>,
+    pub total: AgentMetricsRow,
+}
+
+/// Turn a `since` or `until` bound into the database timestamp format, for
+/// comparing with `created_at`. Both bounds are inclusive: dates cover the
+/// whole day, so an `until` date includes runs created on that day.
+pub fn window_bound(value: &str, is_until: bool) -> Result<String, String> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp
+            .with_timezone(&Utc)
+            .format(DB_TIMESTAMP_FORMAT)
+            .to_string());
+    }
+    if let Ok(timestamp) = NaiveDateTime::parse_from_str(value, DB_TIMESTAMP_FORMAT) {
+        return Ok(timestamp.format(DB_TIMESTAMP_FORMAT).to_string());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = if is_until { "23:59:59" } else { "00:00:00" };
+        return Ok(format!("{} {}", date.format("%Y-%m-%d"), time));
+    }
+    Err(format!(
+        "Invalid date '{}': expected YYYY-MM-DD or an RFC 3339 timestamp",
+        value
+    ))
+}
+
+/// Aggregate `agent_runs` per agent (or overall) within an optional window
+fn aggregate_runs(
+    conn: &Connection,
+    agent_id: Option<i64>,
+    since: Option<&str>,
+    until: Option<&str>,
+    per_agent: bool,
+) -> Result<Vec<AgentMetricsRow>, String> {
+    // Conditions are only added when set so the indexes on agent_id and created_at apply
+    let mut conditions = Vec::new();
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(agent_id) = agent_id {
+        conditions.push(format!("agent_id = ?{}", values.len() + 1));
+        values.push(agent_id.into());
+    }
+    if let Some(since) = since {
+        conditions.push(format!("created_at >= ?{}", values.len() + 1));
+        values.push(since.to_string().into());
+    }
+    if let Some(until) = until {
+        conditions.push(format!("created_at <= ?{}", values.len() + 1));
+        values.push(until.to_string().into());
+    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    let (group_columns, group_clause) = if per_agent {
+        ("agent_id, MAX(agent_name)", "GROUP BY agent_id ORDER BY COUNT(*) DESC, agent_id")
+    } else {
+        ("NULL, NULL", "")
+    };
+
+    let sql = format!(
+        "SELECT {group_columns},
+            COUNT(*),
+            COALESCE(SUM(status = 'completed'), 0),
+            COALESCE(SUM(status IN ('failed', 'timed_out', 'budget_exceeded', 'interrupted')), 0),
+            COALESCE(SUM(status IN ('cancelled', 'killed')), 0),
+            COALESCE(SUM(status IN ('pending', 'queued', 'running')), 0),
+            AVG(CASE WHEN status NOT IN ('pending', 'queued', 'running') AND completed_at IS NOT NULL
+                THEN (julianday(completed_at) - julianday(COALESCE(process_started_at, created_at))) * 86400000.0
+            END),
+            COALESCE(SUM(COALESCE(input_tokens, 0) + COALESCE(output_tokens, 0)), 0),
+            COALESCE(SUM(cost_usd), 0.0)
+         FROM agent_runs {where_clause} {group_clause}"
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params_from_iter(values), |row| {
+            let run_count: i64 = row.get(2)?;
+            let completed: i64 = row.get(3)?;
+            let running: i64 = row.get(6)?;
+            let finished = run_count - running;
+            Ok(AgentMetricsRow {
+                agent_id: row.get(0)?,
+                agent_name: row.get(1)?,
+                run_count,
+                completed,
+                failed: row.get(4)?,
+                cancelled: row.get(5)?,
+                running,
+                success_rate: (finished > 0).then(|| completed as f64 / finished as f64),
+                avg_duration_ms: row.get(7)?,
+                total_tokens: row.get(8)?,
+                total_cost_usd: row.get(9)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+/// Run counts, success rate, average duration, tokens and cost per agent.
+/// `since` and `until` accept YYYY-MM-DD dates or RFC 3339 timestamps.
+#[tauri::command]
+pub async fn get_agent_metrics(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+    since: Option<String>,
+    until: Option<String>,
+) -> Result<AgentMetricsReport, String> {
+    let since = since.as_deref().map(|s| window_bound(s, false)).transpose()?;
+    let until = until.as_deref().map(|u| window_bound(u, true)).transpose()?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let agents = aggregate_runs(&conn, agent_id, since.as_deref(), until.as_deref(), true)?;
+    let total = aggregate_runs(&conn, agent_id, since.as_deref(), until.as_deref(), false)?
+        .into_iter()
+        .next()
+        .ok_or("Failed to aggregate agent runs")?;
+
+    Ok(AgentMetricsReport { agents, total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_bounds_normalize_to_db_format() {
+        assert_eq!(window_bound("2025-03-01", false).unwrap(), "2025-03-01 00:00:00");
+        assert_eq!(window_bound("2025-03-01", true).unwrap(), "2025-03-01 23:59:59");
+        assert_eq!(
+            window_bound("2025-03-01T10:00:00+02:00", false).unwrap(),
+            "2025-03-01 08:00:00"
+        );
+        assert!(window_bound("March 1st", false).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_excludes_running_runs_from_durations() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE agent_runs (agent_id INTEGER, agent_name TEXT, status TEXT,
+                created_at TEXT, process_started_at TEXT, completed_at TEXT,
+                input_tokens INTEGER, output_tokens INTEGER, cost_usd REAL);
+             INSERT INTO agent_runs VALUES (1, 'a', 'completed', '2025-03-01 10:00:00', NULL, '2025-03-01 10:00:10', 10, 5, 0.5);
+             INSERT INTO agent_runs VALUES (1, 'a', 'failed', '2025-03-01 11:00:00', NULL, '2025-03-01 11:00:30', NULL, NULL, NULL);
+             INSERT INTO agent_runs VALUES (1, 'a', 'running', '2025-03-01 12:00:00', NULL, NULL, NULL, NULL, NULL);
+             INSERT INTO agent_runs VALUES (2, 'b', 'completed', '2025-04-01 10:00:00', NULL, '2025-04-01 10:00:01', 1, 1, 0.1);",
+        )
+        .unwrap();
+
+        let rows = aggregate_runs(&conn, None, None, Some("2025-03-31 23:59:59"), true).unwrap();
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!((row.run_count, row.completed, row.failed, row.running), (3, 1, 1, 1));
+        assert_eq!(row.success_rate, Some(0.5));
+        assert!((row.avg_duration_ms.unwrap() - 20_000.0).abs() < 1.0);
+        assert_eq!(row.total_tokens, 15);
+
+        let total = aggregate_runs(&conn, None, None, None, false).unwrap();
+        assert_eq!(total[0].run_count, 4);
+        assert!((total[0].total_cost_usd - 0.6).abs() < 1e-9);
+    }
+}
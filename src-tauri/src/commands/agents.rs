@@ -6,7 +6,11 @@ use reqwest;
 use rusqlite::{params, Connection, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
 use std::io::{BufRead, BufReader};
+use std::pin::Pin;
 use std::process::Stdio;
 use std::sync::Mutex;
 use tauri::{AppHandle, Emitter, Manager, State};
@@ -14,11 +18,19 @@ use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::io::{AsyncBufReadExt, BufReader as TokioBufReader};
 use tokio::process::Command;
 
-/// Finds the full path to the claude binary
-/// This is necessary because macOS apps have a limited PATH environment
-fn find_claude_binary(app_handle: &AppHandle) -> Result<String, String> {
-    crate::claude_binary::find_claude_binary(app_handle)
-}
+use super::agent_export::{build_agent_export, validate_agent_export};
+use super::agent_icons::{delete_icon_image, load_icon_image, save_icon_image, AgentIconImage};
+use super::agent_metrics::window_bound;
+use super::claude_version::installed_claude_version;
+use super::github_cache::{
+    get_cache_ttl_setting, github_cache_key, load_cache_entry, store_cache_entry,
+    touch_cache_entry, GitHubCacheEntry,
+};
+use super::pricing::UsageTally;
+use super::project_settings::resolve_claude_binary;
+use crate::process::{
+    OutputChunk, TerminationOutcome, WithResources, LIVE_OUTPUT_TAIL_BYTES, MAX_OUTPUT_CHUNK_BYTES,
+};
 
 /// Represents a CC Agent stored in the database
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -33,6 +45,15 @@ pub struct Agent {
     pub enable_file_write: bool,
     pub enable_network: bool,
     pub hooks: Option<String>, // JSON string of hooks configuration
+    pub environment_variables: Option<String>, // JSON object of env vars for the Claude process
+    pub timeout_minutes: Option<i64>, // Hard timeout per run, None for no limit
+    pub max_cost_usd: Option<f64>,    // Cost budget per run, None for no limit
+    pub max_tokens: Option<i64>,      // Output token budget per run, None for no limit
+    pub favorite: bool,
+    pub sort_order: Option<i64>, // Position in the manual ordering of the agents list
+    pub source_url: Option<String>, // URL the agent was imported from, if any
+    pub allowed_tools: Option<String>, // JSON array of tools the agent may use, None for all
+    pub disallowed_tools: Option<String>, // JSON array of tools the agent may never use
     pub created_at: String,
     pub updated_at: String,
 }
@@ -45,14 +66,47 @@ pub struct AgentRun {
     pub agent_name: String,
     pub agent_icon: String,
     pub task: String,
-    pub model: String,
+    pub model: String, // Model the run actually used, including any per-run override
     pub project_path: String,
     pub session_id: String, // UUID session ID from Claude Code
-    pub status: String,     // 'pending', 'running', 'completed', 'failed', 'cancelled'
+    pub status: String,     // 'pending', 'queued', 'running', 'completed', 'failed', 'cancelled', 'killed', 'timed_out', 'budget_exceeded', 'interrupted'
     pub pid: Option<u32>,
     pub process_started_at: Option<String>,
     pub created_at: String,
     pub completed_at: Option<String>,
+    pub triggered_by: String, // 'manual', 'schedule', 'retry', 'continuation', 'batch'
+    pub retried_from_run_id: Option<i64>,
+    pub metadata: Option<String>, // JSON object with extra details about the run
+    pub timeout_minutes: Option<i64>,
+    pub max_cost_usd: Option<f64>,
+    pub max_tokens: Option<i64>,
+    pub input_tokens: Option<i64>,  // Final tallies, recorded when the run completes
+    pub output_tokens: Option<i64>,
+    pub cost_usd: Option<f64>,
+    pub pipeline_run_id: Option<i64>, // Set when the run is a step of a pipeline
+    pub cost_estimated: bool,         // Cost used default rates for an unknown model
+    pub notes: Option<String>,        // Reviewer's notes on the run
+    pub starred: bool,
+    pub parent_run_id: Option<i64>, // Run whose Claude session this run continues
+    pub allowed_tools: Option<String>, // Tool lists the run was launched with, as JSON arrays
+    pub disallowed_tools: Option<String>,
+    pub changes_count: Option<i64>, // Project files the run changed, None if not captured
+    pub resolved_system_prompt: Option<String>, // Prompt after template substitution, secrets masked
+    pub batch_id: Option<i64>, // Set when the run is part of a batch across projects
+    pub claude_binary: Option<String>, // Binary the run was spawned with, and the version it reported
+    pub claude_version: Option<String>,
+}
+
+/// A previous state of an agent's prompt, default task and model
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentVersion {
+    pub id: Option<i64>,
+    pub agent_id: i64,
+    pub system_prompt: String,
+    pub default_task: Option<String>,
+    pub model: String,
+    pub reason: String, // 'update', 'restore', 'import' - what replaced this state
+    pub created_at: String,
 }
 
 /// Represents runtime metrics calculated from JSONL
@@ -61,6 +115,8 @@ pub struct AgentRunMetrics {
     pub duration_ms: Option<i64>,
     pub total_tokens: Option<i64>,
     pub cost_usd: Option<f64>,
+    #[serde(default)]
+    pub cost_estimated: bool,
     pub message_count: Option<i64>,
 }
 
@@ -71,18 +127,22 @@ pub struct AgentRunWithMetrics {
     pub run: AgentRun,
     pub metrics: Option<AgentRunMetrics>,
     pub output: Option<String>, // Real-time JSONL content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throughput: Option<crate::process::ThroughputMetrics>, // Only while the run is in progress
 }
 
-/// Agent export format
+/// Agent export format, validated by `agent_export::validate_agent_export`
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AgentExport {
     pub version: u32,
     pub exported_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>, // SHA-256 of the agent payload, from version 2
     pub agent: AgentData,
 }
 
 /// Agent data within export
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct AgentData {
     pub name: String,
     pub icon: String,
@@ -90,17 +150,393 @@ pub struct AgentData {
     pub default_task: Option<String>,
     pub model: String,
     pub hooks: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_image: Option<AgentIconImage>,
+    pub enable_file_read: bool,
+    pub enable_file_write: bool,
+    pub enable_network: bool,
 }
 
 /// Database connection state
 pub struct AgentDb(pub Mutex<Connection>);
 
+/// Column list matching the field order expected by `agent_from_row`
+pub const AGENT_COLUMNS: &str = "id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at, environment_variables, timeout_minutes, max_cost_usd, max_tokens, favorite, sort_order, source_url, allowed_tools, disallowed_tools";
+
+/// Map a row selected with `AGENT_COLUMNS` to an `Agent`
+pub fn agent_from_row(row: &rusqlite::Row) -> rusqlite::Result<Agent> {
+    Ok(Agent {
+        id: Some(row.get(0)?),
+        name: row.get(1)?,
+        icon: row.get(2)?,
+        system_prompt: row.get(3)?,
+        default_task: row.get(4)?,
+        model: row
+            .get::<_, String>(5)
+            .unwrap_or_else(|_| "sonnet".to_string()),
+        enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
+        enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
+        enable_network: row.get::<_, bool>(8).unwrap_or(false),
+        hooks: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        environment_variables: row.get(12)?,
+        timeout_minutes: row.get(13)?,
+        max_cost_usd: row.get(14)?,
+        max_tokens: row.get(15)?,
+        favorite: row.get::<_, Option<bool>>(16)?.unwrap_or(false),
+        sort_order: row.get(17)?,
+        source_url: row.get(18)?,
+        allowed_tools: row.get(19)?,
+        disallowed_tools: row.get(20)?,
+    })
+}
+
+/// Column list matching the field order expected by `agent_run_from_row`
+pub const AGENT_RUN_COLUMNS: &str = "id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at, triggered_by, retried_from_run_id, metadata, timeout_minutes, max_cost_usd, max_tokens, input_tokens, output_tokens, cost_usd, pipeline_run_id, cost_estimated, notes, starred, parent_run_id, allowed_tools, disallowed_tools, changes_count, resolved_system_prompt, batch_id, claude_binary, claude_version";
+
+/// Map a row selected with `AGENT_RUN_COLUMNS` to an `AgentRun`
+pub fn agent_run_from_row(row: &rusqlite::Row) -> rusqlite::Result<AgentRun> {
+    Ok(AgentRun {
+        id: Some(row.get(0)?),
+        agent_id: row.get(1)?,
+        agent_name: row.get(2)?,
+        agent_icon: row.get(3)?,
+        task: row.get(4)?,
+        model: row.get(5)?,
+        project_path: row.get(6)?,
+        session_id: row.get(7)?,
+        status: row
+            .get::<_, String>(8)
+            .unwrap_or_else(|_| "pending".to_string()),
+        pid: row
+            .get::<_, Option<i64>>(9)
+            .ok()
+            .flatten()
+            .map(|p| p as u32),
+        process_started_at: row.get(10)?,
+        created_at: row.get(11)?,
+        completed_at: row.get(12)?,
+        triggered_by: row
+            .get::<_, Option<String>>(13)?
+            .unwrap_or_else(|| "manual".to_string()),
+        retried_from_run_id: row.get(14)?,
+        metadata: row.get(15)?,
+        timeout_minutes: row.get(16)?,
+        max_cost_usd: row.get(17)?,
+        max_tokens: row.get(18)?,
+        input_tokens: row.get(19)?,
+        output_tokens: row.get(20)?,
+        cost_usd: row.get(21)?,
+        pipeline_run_id: row.get(22)?,
+        cost_estimated: row.get::<_, Option<bool>>(23)?.unwrap_or(false),
+        notes: row.get(24)?,
+        starred: row.get::<_, Option<bool>>(25)?.unwrap_or(false),
+        parent_run_id: row.get(26)?,
+        allowed_tools: row.get(27)?,
+        disallowed_tools: row.get(28)?,
+        changes_count: row.get(29)?,
+        resolved_system_prompt: row.get(30)?,
+        batch_id: row.get(31)?,
+        claude_binary: row.get(32)?,
+        claude_version: row.get(33)?,
+    })
+}
+
+/// Options describing how an agent run was requested
+#[derive(Debug, Clone, Default)]
+pub struct AgentRunOptions {
+    /// What started the run ('manual' when not set)
+    pub triggered_by: Option<String>,
+    /// The run this one retries, if any
+    pub retried_from_run_id: Option<i64>,
+    /// Extra entries merged into the run's metadata
+    pub metadata: serde_json::Map<String, JsonValue>,
+    /// Overrides the agent's model for this run
+    pub model: Option<String>,
+    /// Overrides the agent's `timeout_minutes` for this run
+    pub timeout_minutes: Option<i64>,
+    /// Overrides the agent's `max_cost_usd` for this run
+    pub max_cost_usd: Option<f64>,
+    /// Overrides the agent's `max_tokens` for this run
+    pub max_tokens: Option<i64>,
+    /// The pipeline run this run is a step of, if any
+    pub pipeline_run_id: Option<i64>,
+    /// The run whose Claude session this run resumes, if any
+    pub parent_run_id: Option<i64>,
+    /// Values for the system prompt's template variables
+    pub variables: HashMap<String, String>,
+    /// Leave unresolved template variables as written instead of failing
+    pub allow_missing_variables: bool,
+    /// Start even while another run is live in the same project
+    pub allow_busy_project: bool,
+    /// Queue the run until the project is free instead of failing
+    pub queue_if_busy: bool,
+    /// The batch this run belongs to, if any
+    pub batch_id: Option<i64>,
+}
+
+/// Share of a run budget at which `agent-run-budget-warning` is emitted
+const BUDGET_WARNING_FRACTION: f64 = 0.8;
+
+/// Token and cost limits for a single agent run
+#[derive(Debug, Clone, Copy)]
+struct RunBudget {
+    max_cost_usd: Option<f64>,
+    max_tokens: Option<i64>,
+}
+
+impl RunBudget {
+    /// Budget for a run, or None when it has no limits
+    fn for_run(run: &AgentRun) -> Option<Self> {
+        let budget = Self {
+            max_cost_usd: run.max_cost_usd.filter(|c| *c > 0.0),
+            max_tokens: run.max_tokens.filter(|t| *t > 0),
+        };
+        if budget.max_cost_usd.is_none() && budget.max_tokens.is_none() {
+            None
+        } else {
+            Some(budget)
+        }
+    }
+}
+
+/// Running token and cost totals for a budgeted agent run
+struct BudgetTracker {
+    budget: RunBudget,
+    default_model: String,
+    tally: UsageTally,
+    warned: bool,
+}
+
+impl BudgetTracker {
+    fn new(budget: RunBudget, default_model: String) -> Self {
+        Self {
+            budget,
+            default_model,
+            tally: UsageTally::new(),
+            warned: false,
+        }
+    }
+
+    /// Record the usage carried by one line of stream-json output
+    fn record(&mut self, json: &JsonValue) {
+        self.tally.record(json, &self.default_model);
+    }
+
+    /// Largest share of any configured limit used so far
+    fn fraction_used(&self) -> f64 {
+        let cost = self
+            .budget
+            .max_cost_usd
+            .map_or(0.0, |max| self.tally.cost_usd() / max);
+        let tokens = self
+            .budget
+            .max_tokens
+            .map_or(0.0, |max| self.tally.output_tokens() as f64 / max as f64);
+        cost.max(tokens)
+    }
+
+    fn to_json(&self, run_id: i64) -> JsonValue {
+        serde_json::json!({
+            "run_id": run_id,
+            "input_tokens": self.tally.input_tokens(),
+            "output_tokens": self.tally.output_tokens(),
+            "cost_usd": self.tally.cost_usd(),
+            "cost_estimated": self.tally.cost_estimated(),
+            "max_cost_usd": self.budget.max_cost_usd,
+            "max_tokens": self.budget.max_tokens,
+            "fraction_used": self.fraction_used(),
+        })
+    }
+
+    /// Store the current totals on the run row
+    fn save(&self, db_path: &std::path::Path, run_id: i64) {
+        let result =
+            Connection::open(db_path).and_then(|conn| save_run_usage(&conn, run_id, &self.tally));
+        if let Err(e) = result {
+            error!("Failed to save usage totals for run {}: {}", run_id, e);
+        }
+    }
+}
+
+/// Store token and cost totals on a run row
+fn save_run_usage(conn: &Connection, run_id: i64, tally: &UsageTally) -> SqliteResult<usize> {
+    conn.execute(
+        "UPDATE agent_runs SET input_tokens = ?1, output_tokens = ?2, cost_usd = ?3, cost_estimated = ?4 WHERE id = ?5",
+        params![
+            tally.input_tokens(),
+            tally.output_tokens(),
+            tally.cost_usd(),
+            tally.cost_estimated(),
+            run_id
+        ],
+    )
+}
+
+/// Parse an agent's `environment_variables` JSON object into name/value pairs
+pub fn parse_environment_variables(env_json: &str) -> Result<Vec<(String, String)>, String> {
+    if env_json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let value: JsonValue = serde_json::from_str(env_json)
+        .map_err(|e| format!("Invalid environment variables JSON: {}", e))?;
+    let object = value
+        .as_object()
+        .ok_or("Environment variables must be a JSON object")?;
+
+    let mut vars = Vec::new();
+    for (key, value) in object {
+        if key.is_empty() || key.contains('=') || key.contains('\0') {
+            return Err(format!("Invalid environment variable name: '{}'", key));
+        }
+        let value = match value {
+            JsonValue::String(s) => s.clone(),
+            JsonValue::Number(n) => n.to_string(),
+            JsonValue::Bool(b) => b.to_string(),
+            _ => {
+                return Err(format!(
+                    "Environment variable '{}' must be a string, number or boolean",
+                    key
+                ))
+            }
+        };
+        vars.push((key.clone(), value));
+    }
+
+    Ok(vars)
+}
+
+/// Validate an agent's `environment_variables` for storage, None when there
+/// are none so an empty object clears them
+fn normalize_environment_variables(env_json: &str) -> Result<Option<String>, String> {
+    if parse_environment_variables(env_json)?.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(env_json.to_string()))
+}
+
+/// Built-in Claude Code tools accepted in `allowed_tools` and `disallowed_tools`
+pub const KNOWN_TOOLS: &[&str] = &[
+    "Bash",
+    "BashOutput",
+    "Edit",
+    "ExitPlanMode",
+    "Glob",
+    "Grep",
+    "KillShell",
+    "LS",
+    "MultiEdit",
+    "NotebookEdit",
+    "NotebookRead",
+    "Read",
+    "SlashCommand",
+    "Task",
+    "TodoRead",
+    "TodoWrite",
+    "WebFetch",
+    "WebSearch",
+    "Write",
+];
+
+/// Split a tool list on commas and whitespace outside of rule parentheses, so
+/// `Bash(git log:*)` stays one entry
+fn split_tool_list(tools: &str) -> Vec<String> {
+    let mut entries = vec![String::new()];
+    let mut depth = 0usize;
+    for c in tools.chars() {
+        if depth == 0 && (c == ',' || c.is_whitespace()) {
+            entries.push(String::new());
+            continue;
+        }
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if let Some(entry) = entries.last_mut() {
+            entry.push(c);
+        }
+    }
+    entries
+}
+
+/// Parse a JSON array of tool names, or names separated by commas or
+/// whitespace, accepting known tools (optionally with a rule such as
+/// `Bash(git log:*)`) and any `mcp__*` tool. Empty entries are skipped.
+pub fn parse_tool_list(tools_json: &str) -> Result<Vec<String>, String> {
+    let tools_json = tools_json.trim();
+    let tools: Vec<String> = if tools_json.starts_with('[') {
+        serde_json::from_str(tools_json)
+            .map_err(|e| format!("Tool list must be a JSON array of tool names: {}", e))?
+    } else {
+        split_tool_list(tools_json)
+    };
+    let mut parsed = Vec::new();
+    for tool in tools {
+        let tool = tool.trim().to_string();
+        if tool.is_empty() {
+            continue;
+        }
+        let name = tool.split('(').next().unwrap_or_default();
+        let has_valid_rule =
+            !tool.contains('(') || (tool.ends_with(')') && tool.len() > name.len() + 2);
+        let known =
+            KNOWN_TOOLS.contains(&name) || (name.starts_with("mcp__") && name.len() > 5);
+        if !known || !has_valid_rule {
+            return Err(format!(
+                "Unknown tool '{}'. Expected one of {} or an mcp__ tool",
+                tool,
+                KNOWN_TOOLS.join(", ")
+            ));
+        }
+        if !parsed.contains(&tool) {
+            parsed.push(tool);
+        }
+    }
+    Ok(parsed)
+}
+
+/// Normalize a tool list for storage, None when it is empty
+fn normalize_tool_list(tools_json: Option<&str>) -> Result<Option<String>, String> {
+    let tools = match tools_json {
+        Some(json) => parse_tool_list(json)?,
+        None => return Ok(None),
+    };
+    if tools.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(&tools)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Hash an agent system prompt so later runs can tell whether it changed
+fn system_prompt_hash(system_prompt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(system_prompt.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
 /// Real-time JSONL reading and processing functions
 impl AgentRunMetrics {
-    /// Calculate metrics from JSONL content
-    pub fn from_jsonl(jsonl_content: &str) -> Self {
+    /// Calculate metrics for a run's output, preferring the cost stored when it finished
+    pub fn for_run(run: &AgentRun, jsonl_content: &str) -> Self {
+        let mut metrics = Self::from_jsonl(jsonl_content, &run.model);
+        if run.status != "running" {
+            if let Some(cost_usd) = run.cost_usd {
+                metrics.cost_usd = Some(cost_usd);
+                metrics.cost_estimated = run.cost_estimated;
+            }
+        }
+        metrics
+    }
+
+    /// Calculate metrics from JSONL content, pricing usage without a model as `default_model`
+    pub fn from_jsonl(jsonl_content: &str, default_model: &str) -> Self {
         let mut total_tokens = 0i64;
-        let mut cost_usd = 0.0f64;
+        let mut tally = UsageTally::new();
         let mut message_count = 0i64;
         let mut start_time: Option<chrono::DateTime<chrono::Utc>> = None;
         let mut end_time: Option<chrono::DateTime<chrono::Utc>> = None;
@@ -137,10 +573,7 @@ impl AgentRunMetrics {
                     }
                 }
 
-                // Extract cost information
-                if let Some(cost) = json.get("cost").and_then(|c| c.as_f64()) {
-                    cost_usd += cost;
-                }
+                tally.record(&json, default_model);
             }
         }
 
@@ -156,7 +589,12 @@ impl AgentRunMetrics {
             } else {
                 None
             },
-            cost_usd: if cost_usd > 0.0 { Some(cost_usd) } else { None },
+            cost_usd: if tally.has_usage() {
+                Some(tally.cost_usd())
+            } else {
+                None
+            },
+            cost_estimated: tally.cost_estimated(),
             message_count: if message_count > 0 {
                 Some(message_count)
             } else {
@@ -192,11 +630,12 @@ pub async fn read_session_jsonl(session_id: &str, project_path: &str) -> Result<
 pub async fn get_agent_run_with_metrics(run: AgentRun) -> AgentRunWithMetrics {
     match read_session_jsonl(&run.session_id, &run.project_path).await {
         Ok(jsonl_content) => {
-            let metrics = AgentRunMetrics::from_jsonl(&jsonl_content);
+            let metrics = AgentRunMetrics::for_run(&run, &jsonl_content);
             AgentRunWithMetrics {
                 run,
                 metrics: Some(metrics),
                 output: Some(jsonl_content),
+                throughput: None,
             }
         }
         Err(e) => {
@@ -205,140 +644,33 @@ pub async fn get_agent_run_with_metrics(run: AgentRun) -> AgentRunWithMetrics {
                 run,
                 metrics: None,
                 output: None,
+                throughput: None,
             }
         }
     }
 }
 
-/// Initialize the agents database
-pub fn init_database(app: &AppHandle) -> SqliteResult<Connection> {
+/// Initialize the agents database, applying any pending schema migrations
+pub fn init_database(app: &AppHandle) -> Result<Connection, String> {
     let app_dir = app
         .path()
         .app_data_dir()
-        .expect("Failed to get app data dir");
-    std::fs::create_dir_all(&app_dir).expect("Failed to create app data dir");
+        .map_err(|e| format!("Failed to get app data dir: {}", e))?;
+    std::fs::create_dir_all(&app_dir)
+        .map_err(|e| format!("Failed to create app data dir: {}", e))?;
 
     let db_path = app_dir.join("agents.db");
-    let conn = Connection::open(db_path)?;
-
-    // Create agents table
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS agents (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL,
-            icon TEXT NOT NULL,
-            system_prompt TEXT NOT NULL,
-            default_task TEXT,
-            model TEXT NOT NULL DEFAULT 'sonnet',
-            enable_file_read BOOLEAN NOT NULL DEFAULT 1,
-            enable_file_write BOOLEAN NOT NULL DEFAULT 1,
-            enable_network BOOLEAN NOT NULL DEFAULT 0,
-            hooks TEXT,
-            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
-        )",
-        [],
-    )?;
-
-    // Add columns to existing table if they don't exist
-    let _ = conn.execute("ALTER TABLE agents ADD COLUMN default_task TEXT", []);
-    let _ = conn.execute(
-        "ALTER TABLE agents ADD COLUMN model TEXT DEFAULT 'sonnet'",
-        [],
-    );
-    let _ = conn.execute("ALTER TABLE agents ADD COLUMN hooks TEXT", []);
-    let _ = conn.execute(
-        "ALTER TABLE agents ADD COLUMN enable_file_read BOOLEAN DEFAULT 1",
-        [],
-    );
-    let _ = conn.execute(
-        "ALTER TABLE agents ADD COLUMN enable_file_write BOOLEAN DEFAULT 1",
-        [],
-    );
-    let _ = conn.execute(
-        "ALTER TABLE agents ADD COLUMN enable_network BOOLEAN DEFAULT 0",
-        [],
-    );
-
-    // Create agent_runs table
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS agent_runs (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            agent_id INTEGER NOT NULL,
-            agent_name TEXT NOT NULL,
-            agent_icon TEXT NOT NULL,
-            task TEXT NOT NULL,
-            model TEXT NOT NULL,
-            project_path TEXT NOT NULL,
-            session_id TEXT NOT NULL,
-            status TEXT NOT NULL DEFAULT 'pending',
-            pid INTEGER,
-            process_started_at TEXT,
-            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            completed_at TEXT,
-            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
-        )",
-        [],
-    )?;
-
-    // Migrate existing agent_runs table if needed
-    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN session_id TEXT", []);
-    let _ = conn.execute(
-        "ALTER TABLE agent_runs ADD COLUMN status TEXT DEFAULT 'pending'",
-        [],
-    );
-    let _ = conn.execute("ALTER TABLE agent_runs ADD COLUMN pid INTEGER", []);
-    let _ = conn.execute(
-        "ALTER TABLE agent_runs ADD COLUMN process_started_at TEXT",
-        [],
-    );
-
-    // Drop old columns that are no longer needed (data is now read from JSONL files)
-    // Note: SQLite doesn't support DROP COLUMN, so we'll ignore errors for existing columns
-    let _ = conn.execute(
-        "UPDATE agent_runs SET session_id = '' WHERE session_id IS NULL",
-        [],
-    );
-    let _ = conn.execute("UPDATE agent_runs SET status = 'completed' WHERE status IS NULL AND completed_at IS NOT NULL", []);
-    let _ = conn.execute("UPDATE agent_runs SET status = 'failed' WHERE status IS NULL AND completed_at IS NOT NULL AND session_id = ''", []);
-    let _ = conn.execute(
-        "UPDATE agent_runs SET status = 'pending' WHERE status IS NULL",
-        [],
-    );
-
-    // Create trigger to update the updated_at timestamp
-    conn.execute(
-        "CREATE TRIGGER IF NOT EXISTS update_agent_timestamp 
-         AFTER UPDATE ON agents 
-         FOR EACH ROW
-         BEGIN
-             UPDATE agents SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
-         END",
-        [],
-    )?;
-
-
-    // Create settings table for app-wide settings
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS app_settings (
-            key TEXT PRIMARY KEY,
-            value TEXT NOT NULL,
-            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
-            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
-        )",
-        [],
-    )?;
-
-    // Create trigger to update the updated_at timestamp
-    conn.execute(
-        "CREATE TRIGGER IF NOT EXISTS update_app_settings_timestamp 
-         AFTER UPDATE ON app_settings 
-         FOR EACH ROW
-         BEGIN
-             UPDATE app_settings SET updated_at = CURRENT_TIMESTAMP WHERE key = NEW.key;
-         END",
-        [],
-    )?;
+    let mut conn =
+        Connection::open(db_path).map_err(|e| format!("Failed to open agents database: {}", e))?;
+    crate::db::migrations::run_migrations(&mut conn)?;
+
+    // Create the full-text indexes over run outputs and sessions, when FTS5 is available
+    super::run_search::init_search_index(&conn);
+    super::session_search::init_session_search_index(&conn);
+    super::pricing::load_pricing(&conn);
+    super::extra_path::load_extra_path(&conn);
+    super::binary_pin::load_binary_pin(&conn);
+    super::mcp_monitor::load_mcp_statuses(&conn);
 
     Ok(conn)
 }
@@ -349,28 +681,14 @@ pub async fn list_agents(db: State<'_, AgentDb>) -> Result<Vec<Agent>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = conn
-        .prepare("SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents ORDER BY created_at DESC")
+        .prepare(&format!(
+            "SELECT {} FROM agents ORDER BY favorite DESC, sort_order ASC, name COLLATE NOCASE ASC",
+            AGENT_COLUMNS
+        ))
         .map_err(|e| e.to_string())?;
 
     let agents = stmt
-        .query_map([], |row| {
-            Ok(Agent {
-                id: Some(row.get(0)?),
-                name: row.get(1)?,
-                icon: row.get(2)?,
-                system_prompt: row.get(3)?,
-                default_task: row.get(4)?,
-                model: row
-                    .get::<_, String>(5)
-                    .unwrap_or_else(|_| "sonnet".to_string()),
-                enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
-                enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
-                enable_network: row.get::<_, bool>(8).unwrap_or(false),
-                hooks: row.get(9)?,
-                created_at: row.get(10)?,
-                updated_at: row.get(11)?,
-            })
-        })
+        .query_map([], agent_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
@@ -378,6 +696,101 @@ pub async fn list_agents(db: State<'_, AgentDb>) -> Result<Vec<Agent>, String> {
     Ok(agents)
 }
 
+/// Mark or unmark an agent as a favorite, which lists it first
+#[tauri::command]
+pub async fn set_agent_favorite(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    favorite: bool,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE agents SET favorite = ?1 WHERE id = ?2",
+            params![favorite, agent_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Agent {} not found", agent_id));
+    }
+    Ok(())
+}
+
+/// Result of reordering the agents list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReorderAgentsResult {
+    pub ignored_ids: Vec<i64>,  // Given IDs that match no agent
+    pub unlisted_ids: Vec<i64>, // Agents missing from the list, kept after the listed ones
+    pub warnings: Vec<String>,
+}
+
+/// Set the manual ordering of the agents list
+#[tauri::command]
+pub async fn reorder_agents(
+    db: State<'_, AgentDb>,
+    ordered_ids: Vec<i64>,
+) -> Result<ReorderAgentsResult, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let existing_ids: Vec<i64> = {
+        let mut stmt = conn
+            .prepare("SELECT id FROM agents ORDER BY sort_order ASC, id ASC")
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        ids
+    };
+
+    let mut ordering = Vec::new();
+    let mut ignored_ids = Vec::new();
+    for id in ordered_ids {
+        if !existing_ids.contains(&id) {
+            ignored_ids.push(id);
+        } else if !ordering.contains(&id) {
+            ordering.push(id);
+        }
+    }
+    let unlisted_ids: Vec<i64> = existing_ids
+        .iter()
+        .filter(|id| !ordering.contains(id))
+        .copied()
+        .collect();
+    ordering.extend(&unlisted_ids);
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    for (position, id) in ordering.iter().enumerate() {
+        tx.execute(
+            "UPDATE agents SET sort_order = ?1 WHERE id = ?2",
+            params![position as i64 + 1, id],
+        )
+        .map_err(|e| format!("Failed to reorder agents: {}", e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    let mut warnings = Vec::new();
+    if !ignored_ids.is_empty() {
+        warnings.push(format!("Ignored unknown agent IDs: {:?}", ignored_ids));
+    }
+    if !unlisted_ids.is_empty() {
+        warnings.push(format!(
+            "Agents missing from the ordering were placed last: {:?}",
+            unlisted_ids
+        ));
+    }
+    for warning in &warnings {
+        warn!("{}", warning);
+    }
+
+    Ok(ReorderAgentsResult {
+        ignored_ids,
+        unlisted_ids,
+        warnings,
+    })
+}
+
 /// Create a new agent
 #[tauri::command]
 pub async fn create_agent(
@@ -391,7 +804,23 @@ pub async fn create_agent(
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
     hooks: Option<String>,
+    environment_variables: Option<String>,
+    timeout_minutes: Option<i64>,
+    max_cost_usd: Option<f64>,
+    max_tokens: Option<i64>,
+    allowed_tools: Option<String>,
+    disallowed_tools: Option<String>,
 ) -> Result<Agent, String> {
+    let environment_variables = environment_variables
+        .map(|env_json| normalize_environment_variables(&env_json))
+        .transpose()?
+        .flatten();
+    let allowed_tools = normalize_tool_list(allowed_tools.as_deref())?;
+    let disallowed_tools = normalize_tool_list(disallowed_tools.as_deref())?;
+    let timeout_minutes = timeout_minutes.filter(|m| *m > 0);
+    let max_cost_usd = max_cost_usd.filter(|c| *c > 0.0);
+    let max_tokens = max_tokens.filter(|t| *t > 0);
+
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     let model = model.unwrap_or_else(|| "sonnet".to_string());
     let enable_file_read = enable_file_read.unwrap_or(true);
@@ -399,8 +828,8 @@ pub async fn create_agent(
     let enable_network = enable_network.unwrap_or(false);
 
     conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks],
+        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, environment_variables, timeout_minutes, max_cost_usd, max_tokens, allowed_tools, disallowed_tools) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        params![name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, environment_variables, timeout_minutes, max_cost_usd, max_tokens, allowed_tools, disallowed_tools],
     )
     .map_err(|e| e.to_string())?;
 
@@ -409,24 +838,9 @@ pub async fn create_agent(
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -447,10 +861,54 @@ pub async fn update_agent(
     enable_file_write: Option<bool>,
     enable_network: Option<bool>,
     hooks: Option<String>,
+    environment_variables: Option<String>,
+    timeout_minutes: Option<i64>,
+    max_cost_usd: Option<f64>,
+    max_tokens: Option<i64>,
+    allowed_tools: Option<String>,
+    disallowed_tools: Option<String>,
 ) -> Result<Agent, String> {
+    // An empty object clears the variables, None leaves them unchanged
+    let environment_variables = environment_variables
+        .map(|env_json| normalize_environment_variables(&env_json))
+        .transpose()?;
+    // An empty list clears the restriction, None leaves it unchanged
+    let allowed_tools = allowed_tools
+        .map(|tools| normalize_tool_list(Some(&tools)))
+        .transpose()?;
+    let disallowed_tools = disallowed_tools
+        .map(|tools| normalize_tool_list(Some(&tools)))
+        .transpose()?;
+
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     let model = model.unwrap_or_else(|| "sonnet".to_string());
 
+    // Keep the previous prompt, task and model when any of them change
+    let versioned_fields_changed = conn
+        .query_row(
+            "SELECT system_prompt, default_task, model FROM agents WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(row.get::<_, String>(0)? != system_prompt
+                    || row.get::<_, Option<String>>(1)? != default_task
+                    || row.get::<_, String>(2)? != model)
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    if versioned_fields_changed {
+        record_agent_version(&conn, id, "update")?;
+    }
+
+    // Picking another named icon replaces a custom icon image
+    let icon_changed = conn
+        .query_row("SELECT icon FROM agents WHERE id = ?1", params![id], |row| {
+            Ok(row.get::<_, String>(0)? != icon)
+        })
+        .map_err(|e| e.to_string())?;
+    if icon_changed {
+        delete_icon_image(&conn, id)?;
+    }
+
     // Build dynamic query based on provided parameters
     let mut query =
         "UPDATE agents SET name = ?1, icon = ?2, system_prompt = ?3, default_task = ?4, model = ?5, hooks = ?6"
@@ -480,6 +938,38 @@ pub async fn update_agent(
         query.push_str(&format!(", enable_network = ?{}", param_count));
         params_vec.push(Box::new(en));
     }
+    if let Some(env) = environment_variables {
+        param_count += 1;
+        query.push_str(&format!(", environment_variables = ?{}", param_count));
+        params_vec.push(Box::new(env));
+    }
+    if let Some(timeout) = timeout_minutes {
+        // Zero or a negative value removes the timeout
+        param_count += 1;
+        query.push_str(&format!(", timeout_minutes = ?{}", param_count));
+        params_vec.push(Box::new(Some(timeout).filter(|m| *m > 0)));
+    }
+    if let Some(max_cost) = max_cost_usd {
+        // Zero or a negative value removes the budget
+        param_count += 1;
+        query.push_str(&format!(", max_cost_usd = ?{}", param_count));
+        params_vec.push(Box::new(Some(max_cost).filter(|c| *c > 0.0)));
+    }
+    if let Some(max_tokens) = max_tokens {
+        param_count += 1;
+        query.push_str(&format!(", max_tokens = ?{}", param_count));
+        params_vec.push(Box::new(Some(max_tokens).filter(|t| *t > 0)));
+    }
+    if let Some(allowed) = allowed_tools {
+        param_count += 1;
+        query.push_str(&format!(", allowed_tools = ?{}", param_count));
+        params_vec.push(Box::new(allowed));
+    }
+    if let Some(disallowed) = disallowed_tools {
+        param_count += 1;
+        query.push_str(&format!(", disallowed_tools = ?{}", param_count));
+        params_vec.push(Box::new(disallowed));
+    }
 
     param_count += 1;
     query.push_str(&format!(" WHERE id = ?{}", param_count));
@@ -494,24 +984,9 @@ pub async fn update_agent(
     // Fetch the updated agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| e.to_string())?;
 
@@ -523,95 +998,444 @@ pub async fn update_agent(
 pub async fn delete_agent(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
+    conn.execute("DELETE FROM agent_versions WHERE agent_id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    delete_icon_image(&conn, id)?;
     conn.execute("DELETE FROM agents WHERE id = ?1", params![id])
         .map_err(|e| e.to_string())?;
 
     Ok(())
 }
 
-/// Get a single agent by ID
-#[tauri::command]
-pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+/// Number of versions kept per agent when no limit is configured
+const DEFAULT_AGENT_VERSION_LIMIT: usize = 50;
 
-    let agent = conn
-        .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get::<_, String>(5).unwrap_or_else(|_| "sonnet".to_string()),
-                    enable_file_read: row.get::<_, bool>(6).unwrap_or(true),
-                    enable_file_write: row.get::<_, bool>(7).unwrap_or(true),
-                    enable_network: row.get::<_, bool>(8).unwrap_or(false),
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
-        )
-        .map_err(|e| e.to_string())?;
+/// Read the `agent_version_limit` setting
+fn agent_version_limit(conn: &Connection) -> Result<usize, String> {
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = 'agent_version_limit'",
+        [],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(value) => Ok(value
+            .parse::<usize>()
+            .ok()
+            .filter(|limit| *limit > 0)
+            .unwrap_or(DEFAULT_AGENT_VERSION_LIMIT)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(DEFAULT_AGENT_VERSION_LIMIT),
+        Err(e) => Err(e.to_string()),
+    }
+}
 
-    Ok(agent)
+/// Delete an agent's oldest versions beyond the limit
+fn prune_agent_versions(conn: &Connection, agent_id: i64, limit: usize) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM agent_versions WHERE agent_id = ?1 AND id NOT IN (
+            SELECT id FROM agent_versions WHERE agent_id = ?1 ORDER BY id DESC LIMIT ?2
+        )",
+        params![agent_id, limit as i64],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Save an agent's current prompt, task and model as a version before they are replaced
+fn record_agent_version(conn: &Connection, agent_id: i64, reason: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO agent_versions (agent_id, system_prompt, default_task, model, reason)
+         SELECT id, system_prompt, default_task, model, ?2 FROM agents WHERE id = ?1",
+        params![agent_id, reason],
+    )
+    .map_err(|e| format!("Failed to save agent version: {}", e))?;
+
+    prune_agent_versions(conn, agent_id, agent_version_limit(conn)?)
 }
 
-/// List agent runs (optionally filtered by agent_id)
+/// List an agent's saved versions, newest first
 #[tauri::command]
-pub async fn list_agent_runs(
+pub async fn list_agent_versions(
     db: State<'_, AgentDb>,
-    agent_id: Option<i64>,
-) -> Result<Vec<AgentRun>, String> {
+    agent_id: i64,
+) -> Result<Vec<AgentVersion>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
-    let query = if agent_id.is_some() {
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-         FROM agent_runs WHERE agent_id = ?1 ORDER BY created_at DESC"
-    } else {
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-         FROM agent_runs ORDER BY created_at DESC"
-    };
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, agent_id, system_prompt, default_task, model, reason, created_at
+             FROM agent_versions WHERE agent_id = ?1 ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
 
-    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
-
-    let run_mapper = |row: &rusqlite::Row| -> rusqlite::Result<AgentRun> {
-        Ok(AgentRun {
-            id: Some(row.get(0)?),
-            agent_id: row.get(1)?,
-            agent_name: row.get(2)?,
-            agent_icon: row.get(3)?,
-            task: row.get(4)?,
-            model: row.get(5)?,
-            project_path: row.get(6)?,
-            session_id: row.get(7)?,
-            status: row
-                .get::<_, String>(8)
-                .unwrap_or_else(|_| "pending".to_string()),
-            pid: row
-                .get::<_, Option<i64>>(9)
-                .ok()
-                .flatten()
-                .map(|p| p as u32),
-            process_started_at: row.get(10)?,
-            created_at: row.get(11)?,
-            completed_at: row.get(12)?,
+    let versions = stmt
+        .query_map(params![agent_id], |row| {
+            Ok(AgentVersion {
+                id: Some(row.get(0)?),
+                agent_id: row.get(1)?,
+                system_prompt: row.get(2)?,
+                default_task: row.get(3)?,
+                model: row.get(4)?,
+                reason: row.get(5)?,
+                created_at: row.get(6)?,
+            })
         })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(versions)
+}
+
+/// Restore an agent's prompt, task and model from a saved version.
+/// The state being replaced is saved as a new version so the restore can be undone.
+#[tauri::command]
+pub async fn restore_agent_version(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    version_id: i64,
+) -> Result<Agent, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let (system_prompt, default_task, model) = conn
+        .query_row(
+            "SELECT system_prompt, default_task, model FROM agent_versions WHERE id = ?1 AND agent_id = ?2",
+            params![version_id, agent_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            },
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => {
+                format!("Version {} not found for agent {}", version_id, agent_id)
+            }
+            e => e.to_string(),
+        })?;
+
+    record_agent_version(&conn, agent_id, "restore")?;
+    conn.execute(
+        "UPDATE agents SET system_prompt = ?1, default_task = ?2, model = ?3, updated_at = CURRENT_TIMESTAMP WHERE id = ?4",
+        params![system_prompt, default_task, model, agent_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    conn.query_row(
+        &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
+        params![agent_id],
+        agent_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Get how many versions are kept per agent
+#[tauri::command]
+pub async fn get_agent_version_limit(db: State<'_, AgentDb>) -> Result<usize, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    agent_version_limit(&conn)
+}
+
+/// Set how many versions are kept per agent, pruning older versions right away
+#[tauri::command]
+pub async fn set_agent_version_limit(db: State<'_, AgentDb>, limit: usize) -> Result<(), String> {
+    if limit == 0 {
+        return Err("The version limit must be at least 1".to_string());
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES ('agent_version_limit', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1",
+        params![limit.to_string()],
+    )
+    .map_err(|e| format!("Failed to save agent_version_limit: {}", e))?;
+
+    let agent_ids = {
+        let mut stmt = conn
+            .prepare("SELECT DISTINCT agent_id FROM agent_versions")
+            .map_err(|e| e.to_string())?;
+        let agent_ids = stmt
+            .query_map([], |row| row.get::<_, i64>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        agent_ids
     };
+    for agent_id in agent_ids {
+        prune_agent_versions(&conn, agent_id, limit)?;
+    }
+
+    Ok(())
+}
+
+/// Get a single agent by ID
+#[tauri::command]
+pub async fn get_agent(db: State<'_, AgentDb>, id: i64) -> Result<Agent, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let agent = conn
+        .query_row(
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
+            params![id],
+            agent_from_row,
+        )
+        .map_err(|e| e.to_string())?;
+
+    Ok(agent)
+}
+
+/// Columns that identify an agent row rather than describe it, and so are never copied
+const AGENT_IDENTITY_COLUMNS: [&str; 6] = [
+    "id",
+    "name",
+    "favorite",
+    "sort_order",
+    "created_at",
+    "updated_at",
+];
+
+/// Duplicate an agent into a new row with a unique name
+#[tauri::command]
+pub async fn duplicate_agent(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    new_name: Option<String>,
+) -> Result<Agent, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    copy_agent(&conn, agent_id, new_name)
+}
+
+/// Copy an agent's row and custom icon under a unique name
+fn copy_agent(conn: &Connection, agent_id: i64, new_name: Option<String>) -> Result<Agent, String> {
+    let original_name: String = conn
+        .query_row(
+            "SELECT name FROM agents WHERE id = ?1",
+            params![agent_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Agent {} not found", agent_id),
+            e => e.to_string(),
+        })?;
+
+    let base_name = new_name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("{} (copy)", original_name));
+
+    // Deduplicate against existing names: "Name", "Name (2)", "Name (3)", ...
+    let mut final_name = base_name.clone();
+    let mut suffix = 2;
+    loop {
+        let exists: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM agents WHERE name = ?1",
+                params![final_name],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if exists == 0 {
+            break;
+        }
+        final_name = format!("{} ({})", base_name, suffix);
+        suffix += 1;
+    }
+
+    // Copy every descriptive column so fields added later are duplicated too
+    let mut stmt = conn
+        .prepare("SELECT name FROM pragma_table_info('agents')")
+        .map_err(|e| e.to_string())?;
+    let copy_columns: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|c| !AGENT_IDENTITY_COLUMNS.contains(&c.as_str()))
+        .collect();
+    drop(stmt);
 
-    let runs = if let Some(aid) = agent_id {
-        stmt.query_map(params![aid], run_mapper)
+    let column_list = copy_columns.join(", ");
+    conn.execute(
+        &format!(
+            "INSERT INTO agents (name, {cols}) SELECT ?1, {cols} FROM agents WHERE id = ?2",
+            cols = column_list
+        ),
+        params![final_name, agent_id],
+    )
+    .map_err(|e| format!("Failed to duplicate agent: {}", e))?;
+
+    let id = conn.last_insert_rowid();
+    // Custom icons live in their own table
+    if let Some(icon) = load_icon_image(conn, agent_id)? {
+        save_icon_image(conn, id, &icon)?;
+    }
+    info!("Duplicated agent {} as {} ('{}')", agent_id, id, final_name);
+
+    // Fetch the created agent
+    let agent = conn
+        .query_row(
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
+            params![id],
+            agent_from_row,
+        )
+        .map_err(|e| e.to_string())?;
+
+    Ok(agent)
+}
+
+/// Default and largest number of runs returned per page
+const DEFAULT_RUN_PAGE_SIZE: i64 = 100;
+const MAX_RUN_PAGE_SIZE: i64 = 1000;
+
+/// Filters and pagination for listing agent runs. Pages are newest first; pass
+/// a page's `next_before_id` as `before_id` to fetch the one after it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AgentRunQuery {
+    pub agent_id: Option<i64>,
+    pub starred_only: Option<bool>,
+    pub status: Option<String>,
+    pub since: Option<String>, // RFC 3339 or YYYY-MM-DD, inclusive
+    pub until: Option<String>, // Inclusive, a bare date including that whole day
+    pub before_id: Option<i64>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// One page of agent runs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentRunPage<T> {
+    pub runs: Vec<T>,
+    pub total_count: i64,            // Runs matching the filters across all pages
+    pub next_before_id: Option<i64>, // None on the last page
+}
+
+/// List agent runs newest first, filtered and paginated by `query`
+#[tauri::command]
+pub async fn list_agent_runs(
+    db: State<'_, AgentDb>,
+    query: Option<AgentRunQuery>,
+) -> Result<AgentRunPage<AgentRun>, String> {
+    let query = query.unwrap_or_default();
+    let since = query
+        .since
+        .as_deref()
+        .map(|since| window_bound(since, false))
+        .transpose()?;
+    let until = query
+        .until
+        .as_deref()
+        .map(|until| window_bound(until, true))
+        .transpose()?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_RUN_PAGE_SIZE)
+        .clamp(1, MAX_RUN_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let filter = "(?1 IS NULL OR agent_id = ?1) AND (?2 = 0 OR starred = 1) AND (?3 IS NULL OR status = ?3)
+         AND (?4 IS NULL OR created_at >= ?4) AND (?5 IS NULL OR created_at <= ?5)";
+    let filter_params = params![
+        query.agent_id,
+        query.starred_only.unwrap_or(false),
+        query.status,
+        since,
+        until
+    ];
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let total_count: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM agent_runs WHERE {}", filter),
+            filter_params,
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    // Keyset on id keeps pages stable while new runs are inserted
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_runs WHERE {} AND (?6 IS NULL OR id < ?6) ORDER BY id DESC LIMIT ?7 OFFSET ?8",
+            AGENT_RUN_COLUMNS, filter
+        ))
+        .map_err(|e| e.to_string())?;
+    let page_params: Vec<&dyn rusqlite::ToSql> = filter_params
+        .iter()
+        .copied()
+        .chain([&query.before_id as &dyn rusqlite::ToSql, &limit, &offset])
+        .collect();
+
+    let runs = stmt
+        .query_map(page_params.as_slice(), agent_run_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let next_before_id = if runs.len() as i64 == limit {
+        runs.last().and_then(|run| run.id)
     } else {
-        stmt.query_map(params![], run_mapper)
+        None
+    };
+
+    Ok(AgentRunPage {
+        runs,
+        total_count,
+        next_before_id,
+    })
+}
+
+/// Longest note that can be attached to an agent run
+const MAX_RUN_NOTE_CHARS: usize = 10_000;
+
+/// Set or clear (with an empty note) the note on an agent run
+#[tauri::command]
+pub async fn set_agent_run_note(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+    note: String,
+) -> Result<(), String> {
+    let note_chars = note.chars().count();
+    if note_chars > MAX_RUN_NOTE_CHARS {
+        return Err(format!(
+            "Note is {} characters, the limit is {}",
+            note_chars, MAX_RUN_NOTE_CHARS
+        ));
     }
-    .map_err(|e| e.to_string())?
-    .collect::<Result<Vec<_>, _>>()
-    .map_err(|e| e.to_string())?;
+    let note = Some(note).filter(|n| !n.trim().is_empty());
 
-    Ok(runs)
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE agent_runs SET notes = ?1 WHERE id = ?2",
+            params![note, run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Agent run {} not found", run_id));
+    }
+    Ok(())
+}
+
+/// Star or unstar an agent run
+#[tauri::command]
+pub async fn set_agent_run_starred(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+    starred: bool,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE agent_runs SET starred = ?1 WHERE id = ?2",
+            params![starred, run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Agent run {} not found", run_id));
+    }
+    Ok(())
 }
 
 /// Get a single agent run by ID
@@ -621,60 +1445,92 @@ pub async fn get_agent_run(db: State<'_, AgentDb>, id: i64) -> Result<AgentRun,
 
     let run = conn
         .query_row(
-            "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-             FROM agent_runs WHERE id = ?1",
+            &format!("SELECT {} FROM agent_runs WHERE id = ?1", AGENT_RUN_COLUMNS),
             params![id],
-            |row| {
-                Ok(AgentRun {
-                    id: Some(row.get(0)?),
-                    agent_id: row.get(1)?,
-                    agent_name: row.get(2)?,
-                    agent_icon: row.get(3)?,
-                    task: row.get(4)?,
-                    model: row.get(5)?,
-                    project_path: row.get(6)?,
-                    session_id: row.get(7)?,
-                    status: row.get::<_, String>(8).unwrap_or_else(|_| "pending".to_string()),
-                    pid: row.get::<_, Option<i64>>(9).ok().flatten().map(|p| p as u32),
-                    process_started_at: row.get(10)?,
-                    created_at: row.get(11)?,
-                    completed_at: row.get(12)?,
-                })
-            },
+            agent_run_from_row,
         )
         .map_err(|e| e.to_string())?;
 
     Ok(run)
 }
 
-/// Get agent run with real-time metrics from JSONL
+/// Get agent run with real-time metrics from JSONL, and output rates while it runs
 #[tauri::command]
 pub async fn get_agent_run_with_real_time_metrics(
     db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
     id: i64,
 ) -> Result<AgentRunWithMetrics, String> {
+    let stall_after = std::time::Duration::from_secs(get_stall_seconds_setting(&db)?);
     let run = get_agent_run(db, id).await?;
-    Ok(get_agent_run_with_metrics(run).await)
+    let mut run_with_metrics = get_agent_run_with_metrics(run).await;
+    run_with_metrics.throughput = registry.0.get_throughput(id, stall_after)?;
+    Ok(run_with_metrics)
 }
 
 /// List agent runs with real-time metrics from JSONL
 #[tauri::command]
 pub async fn list_agent_runs_with_metrics(
     db: State<'_, AgentDb>,
-    agent_id: Option<i64>,
-) -> Result<Vec<AgentRunWithMetrics>, String> {
-    let runs = list_agent_runs(db, agent_id).await?;
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    query: Option<AgentRunQuery>,
+) -> Result<AgentRunPage<AgentRunWithMetrics>, String> {
+    let stall_after = std::time::Duration::from_secs(get_stall_seconds_setting(&db)?);
+    let page = list_agent_runs(db, query).await?;
     let mut runs_with_metrics = Vec::new();
 
-    for run in runs {
-        let run_with_metrics = get_agent_run_with_metrics(run).await;
+    // Metrics are only read for the runs on this page
+    for run in page.runs {
+        let run_id = run.id;
+        let mut run_with_metrics = get_agent_run_with_metrics(run).await;
+        if let Some(run_id) = run_id {
+            run_with_metrics.throughput = registry.0.get_throughput(run_id, stall_after)?;
+        }
         runs_with_metrics.push(run_with_metrics);
     }
 
-    Ok(runs_with_metrics)
+    Ok(AgentRunPage {
+        runs: runs_with_metrics,
+        total_count: page.total_count,
+        next_before_id: page.next_before_id,
+    })
 }
 
-/// Execute a CC agent with streaming output
+/// Model aliases and IDs accepted as run overrides without `allow_unknown_model`
+const KNOWN_MODELS: [&str; 11] = [
+    "sonnet",
+    "opus",
+    "haiku",
+    "opusplan",
+    "sonnet[1m]",
+    "claude-opus-4-1-20250805",
+    "claude-opus-4-20250514",
+    "claude-sonnet-4-20250514",
+    "claude-3-7-sonnet-20250219",
+    "claude-3-5-sonnet-20241022",
+    "claude-3-5-haiku-20241022",
+];
+
+/// Check a per-run model override, so a typo doesn't silently fall back to another model
+pub fn validate_model_override(model: &str, allow_unknown_model: bool) -> Result<String, String> {
+    let model = model.trim();
+    if model.is_empty() {
+        return Err("Model cannot be empty".to_string());
+    }
+    if !allow_unknown_model && !KNOWN_MODELS.iter().any(|m| m.eq_ignore_ascii_case(model)) {
+        return Err(format!(
+            "Unknown model '{}'. Known models: {}. Set allow_unknown_model to use it anyway",
+            model,
+            KNOWN_MODELS.join(", ")
+        ));
+    }
+    Ok(model.to_string())
+}
+
+/// Execute a CC agent with streaming output. `model` overrides the agent's model for this run only.
+/// `variables` fill `{{NAME}}` placeholders in the system prompt alongside the built-in ones.
+/// While another run is live in the same project this fails, unless `override_busy`
+/// starts it anyway or `queue_if_busy` queues it until the project is free.
 #[tauri::command]
 pub async fn execute_agent(
     app: AppHandle,
@@ -682,6 +1538,58 @@ pub async fn execute_agent(
     project_path: String,
     task: String,
     model: Option<String>,
+    allow_unknown_model: Option<bool>,
+    timeout_minutes: Option<i64>,
+    max_cost_usd: Option<f64>,
+    max_tokens: Option<i64>,
+    variables: Option<HashMap<String, String>>,
+    allow_missing: Option<bool>,
+    override_busy: Option<bool>,
+    queue_if_busy: Option<bool>,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<i64, String> {
+    let model = model
+        .map(|m| validate_model_override(&m, allow_unknown_model.unwrap_or(false)))
+        .transpose()?;
+    let options = AgentRunOptions {
+        model,
+        timeout_minutes,
+        max_cost_usd,
+        max_tokens,
+        variables: variables.unwrap_or_default(),
+        allow_missing_variables: allow_missing.unwrap_or(false),
+        allow_busy_project: override_busy.unwrap_or(false),
+        queue_if_busy: queue_if_busy.unwrap_or(false),
+        ..Default::default()
+    };
+    let run_id = execute_agent_with_options(
+        app,
+        agent_id,
+        project_path.clone(),
+        task.clone(),
+        options,
+        db.clone(),
+        registry,
+    )
+    .await?;
+    super::prompt_history::record_prompt_or_warn(&db, &task, &project_path, None, true);
+
+    // Offer the path again next time, without failing the started run
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    if let Err(e) = super::project_paths::record_project_path(&conn, agent_id, &project_path) {
+        warn!("{}", e);
+    }
+    Ok(run_id)
+}
+
+/// Execute a CC agent, recording the given run options on the new run
+pub async fn execute_agent_with_options(
+    app: AppHandle,
+    agent_id: i64,
+    project_path: String,
+    task: String,
+    options: AgentRunOptions,
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
 ) -> Result<i64, String> {
@@ -689,7 +1597,15 @@ pub async fn execute_agent(
 
     // Get the agent from database
     let agent = get_agent(db.clone(), agent_id).await?;
-    let execution_model = model.unwrap_or(agent.model.clone());
+    let execution_model = options.model.clone().unwrap_or(agent.model.clone());
+    super::project_paths::ensure_usable_project_path(&project_path)?;
+
+    let resolved_prompt = super::prompt_templates::resolve_system_prompt(
+        &agent.system_prompt,
+        &super::prompt_templates::builtin_variables(&project_path, &agent.name, &execution_model),
+        &options.variables,
+        options.allow_missing_variables,
+    )?;
     
     // Create .claude/settings.json with agent hooks if it doesn't exist
     if let Some(hooks_json) = &agent.hooks {
@@ -727,61 +1643,780 @@ pub async fn execute_agent(
         }
     }
 
+    // Record the prompt hash alongside any caller-provided metadata
+    let mut metadata = options.metadata.clone();
+    metadata.insert(
+        "system_prompt_sha256".to_string(),
+        JsonValue::String(system_prompt_hash(&agent.system_prompt)),
+    );
+    if resolved_prompt.has_masked_values() {
+        metadata.insert("system_prompt_masked".to_string(), JsonValue::Bool(true));
+    }
+    let metadata_json = JsonValue::Object(metadata).to_string();
+    let timeout_minutes = options
+        .timeout_minutes
+        .or(agent.timeout_minutes)
+        .filter(|m| *m > 0);
+    let max_cost_usd = options
+        .max_cost_usd
+        .or(agent.max_cost_usd)
+        .filter(|c| *c > 0.0);
+    let max_tokens = options.max_tokens.or(agent.max_tokens).filter(|t| *t > 0);
+
+    // Hold the launch lock so concurrent executions can't both take the last
+    // slot or both start in a free project
+    let _launch_guard = registry.0.lock_launch().await;
+
+    // Runs sharing a working tree would overwrite each other's changes
+    let busy_run = if options.allow_busy_project {
+        None
+    } else {
+        registry.0.find_live_agent_run_on_path(&project_path)?
+    };
+    if let Some(busy) = busy_run.as_ref().filter(|_| !options.queue_if_busy) {
+        return Err(project_busy_error(busy));
+    }
+
     // Create a new run record
     let run_id = {
         let conn = db.0.lock().map_err(|e| e.to_string())?;
         conn.execute(
-            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![agent_id, agent.name, agent.icon, task, execution_model, project_path, ""],
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, triggered_by, retried_from_run_id, metadata, timeout_minutes, max_cost_usd, max_tokens, pipeline_run_id, parent_run_id, allowed_tools, disallowed_tools, resolved_system_prompt, allow_busy_project, batch_id, template_variables) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+            params![
+                agent_id,
+                agent.name,
+                agent.icon,
+                task,
+                execution_model,
+                project_path,
+                "",
+                options.triggered_by.as_deref().unwrap_or("manual"),
+                options.retried_from_run_id,
+                metadata_json,
+                timeout_minutes,
+                max_cost_usd,
+                max_tokens,
+                options.pipeline_run_id,
+                options.parent_run_id,
+                agent.allowed_tools,
+                agent.disallowed_tools,
+                resolved_prompt.masked,
+                options.allow_busy_project,
+                options.batch_id,
+                super::prompt_templates::variables_to_json(
+                    &super::prompt_templates::recordable_variables(&options.variables)
+                )
+            ],
         )
         .map_err(|e| e.to_string())?;
         conn.last_insert_rowid()
     };
+    super::prompt_templates::remember_resolved_prompt(run_id, resolved_prompt.prompt);
+    // A run that won't launch must not leave its prompt behind
+    let forget_prompt = |e: String| {
+        super::prompt_templates::forget_resolved_prompt(run_id);
+        e
+    };
+
+    if let Some(busy) = busy_run {
+        queue_run(&app, &db, run_id).map_err(forget_prompt)?;
+        info!(
+            "Queued agent run {} until run {} in {} finishes",
+            run_id, busy.run_id, project_path
+        );
+        return Ok(run_id);
+    }
+
+    let max_concurrent_runs = get_max_concurrent_runs_setting(&db).map_err(forget_prompt)?;
+    if let Some(limit) = max_concurrent_runs {
+        let live = registry.0.live_agent_process_count().map_err(forget_prompt)?;
+        if live >= limit {
+            queue_run(&app, &db, run_id).map_err(forget_prompt)?;
+            info!(
+                "Queued agent run {} ({} of {} concurrent runs in use)",
+                run_id, live, limit
+            );
+            return Ok(run_id);
+        }
+    }
+
+    launch_agent_run(app, run_id, db, registry.clone()).await
+}
+
+/// Error for a run refused because another run is live in its project
+fn project_busy_error(busy: &crate::process::ProcessInfo) -> String {
+    let agent_name = match &busy.process_type {
+        crate::process::ProcessType::AgentRun { agent_name, .. } => agent_name.as_str(),
+        _ => "unknown",
+    };
+    format!(
+        "Project busy with run #{}, agent {} ({}). Pass override_busy to run in parallel or queue_if_busy to wait for it",
+        busy.run_id, agent_name, busy.project_path
+    )
+}
+
+/// Mark a recorded run as waiting in the run queue
+fn queue_run(app: &AppHandle, db: &AgentDb, run_id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE agent_runs SET status = 'queued' WHERE id = ?1",
+        params![run_id],
+    )
+    .map_err(|e| e.to_string())?;
+    let _ = app.emit("agent-run-queued", run_id);
+    Ok(())
+}
 
-    // Find Claude binary
+/// Launch a previously recorded agent run, building the invocation from its run row
+async fn launch_agent_run(
+    app: AppHandle,
+    run_id: i64,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<i64, String> {
+    let launched = start_recorded_run(app, run_id, db, registry).await;
+    if launched.is_err() {
+        // The prompt may not have been taken yet, and may hold secrets
+        super::prompt_templates::forget_resolved_prompt(run_id);
+    }
+    launched
+}
+
+async fn start_recorded_run(
+    app: AppHandle,
+    run_id: i64,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<i64, String> {
+    let run = get_agent_run(db.clone(), run_id).await?;
+    let agent = get_agent(db.clone(), run.agent_id).await?;
+
+    // Find Claude binary, the project's own if it has one
     info!("Running agent '{}'", agent.name);
-    let claude_path = match find_claude_binary(&app) {
+    let claude_path = match resolve_claude_binary(&app, &db, &run.project_path) {
         Ok(path) => path,
         Err(e) => {
             error!("Failed to find claude binary: {}", e);
             return Err(e);
         }
     };
+    let claude_version = installed_claude_version(&claude_path)
+        .await
+        .map(|version| version.raw);
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE agent_runs SET claude_binary = ?1, claude_version = ?2 WHERE id = ?3",
+            params![claude_path, claude_version, run_id],
+        )
+        .map_err(|e| format!("Failed to record the run's Claude binary: {}", e))?;
+    }
 
-    // Build arguments
-    let args = vec![
-        "-p".to_string(),
-        task.clone(),
-        "--system-prompt".to_string(),
-        agent.system_prompt.clone(),
-        "--model".to_string(),
-        execution_model.clone(),
-        "--output-format".to_string(),
-        "stream-json".to_string(),
-        "--verbose".to_string(),
-        "--dangerously-skip-permissions".to_string(),
-    ];
+    // The recorded prompt can only be reused when nothing in it was masked
+    let prompt_masked = run
+        .metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<JsonValue>(m).ok())
+        .and_then(|m| m.get("system_prompt_masked").and_then(|v| v.as_bool()))
+        .unwrap_or(false);
+    let system_prompt = match super::prompt_templates::take_resolved_prompt(run_id) {
+        Some(prompt) => prompt,
+        None if prompt_masked => {
+            return Err(format!(
+                "Agent run {} used secret template variables that are no longer available, start it again",
+                run_id
+            ))
+        }
+        None => run
+            .resolved_system_prompt
+            .clone()
+            .unwrap_or_else(|| agent.system_prompt.clone()),
+    };
 
-    // Always use system binary execution (sidecar removed)
-    spawn_agent_system(
+    // Continuations resume the parent run's Claude session
+    let resume_session_id = match run.parent_run_id {
+        Some(parent_run_id) => Some(get_agent_run(db.clone(), parent_run_id).await?.session_id),
+        None => None,
+    };
+
+    // Tool lists come from the run row so queued runs keep what was recorded
+    let AgentInvocation { args, environment } = AgentInvocationSpec {
+        task: &run.task,
+        system_prompt: &system_prompt,
+        model: &run.model,
+        allowed_tools: run.allowed_tools.as_deref(),
+        disallowed_tools: run.disallowed_tools.as_deref(),
+        resume_session_id: resume_session_id.as_deref(),
+        environment_variables: agent.environment_variables.as_deref(),
+        project_path: &run.project_path,
+    }
+    .build()?;
+
+    let environment = with_claude_env(&db, environment);
+    let budget = RunBudget::for_run(&run);
+
+    // Snapshot the project so the files the run changes can be listed afterwards
+    super::run_artifacts::begin_capture(run_id, &run.project_path);
+
+    // The bundled binary is spawned as a sidecar, anything else directly
+    let spawned = spawn_agent_system(
         app,
         run_id,
-        agent_id,
+        run.agent_id,
         agent.name.clone(),
         claude_path,
         args,
-        project_path,
-        task,
-        execution_model,
+        environment,
+        budget,
+        run.project_path,
+        run.task,
+        run.model,
+        db,
+        registry.clone(),
+    ).await;
+    if let Err(e) = spawned {
+        super::run_artifacts::discard_capture(run_id);
+        return Err(e);
+    }
+
+    if let Some(timeout_minutes) = run.timeout_minutes {
+        registry.0.set_process_timeout(run_id, timeout_minutes)?;
+        info!("Agent run {} will time out after {} minutes", run_id, timeout_minutes);
+    }
+
+    Ok(run_id)
+}
+
+/// What the Claude CLI invocation of an agent run is built from
+struct AgentInvocationSpec<'a> {
+    task: &'a str,
+    system_prompt: &'a str,
+    model: &'a str,
+    allowed_tools: Option<&'a str>,
+    disallowed_tools: Option<&'a str>,
+    resume_session_id: Option<&'a str>,
+    environment_variables: Option<&'a str>, // The agent's JSON object of variables
+    project_path: &'a str,
+}
+
+/// Claude CLI arguments and agent environment variables for a run
+struct AgentInvocation {
+    args: Vec<String>,
+    environment: Vec<(String, String)>,
+}
+
+impl AgentInvocationSpec<'_> {
+    /// Build the invocation. Shared by `launch_agent_run` and
+    /// `preview_agent_execution` so previews match what actually runs.
+    fn build(&self) -> Result<AgentInvocation, String> {
+        let mut args = vec![
+            "-p".to_string(),
+            self.task.to_string(),
+            "--system-prompt".to_string(),
+            self.system_prompt.to_string(),
+            "--model".to_string(),
+            self.model.to_string(),
+            "--output-format".to_string(),
+            "stream-json".to_string(),
+            "--verbose".to_string(),
+        ];
+
+        // With an allow list, skipping permission checks would permit every other
+        // tool too, so unlisted tools are denied in the non-interactive session.
+        let allowed_tools = parse_tool_list(self.allowed_tools.unwrap_or_default())?;
+        let disallowed_tools = parse_tool_list(self.disallowed_tools.unwrap_or_default())?;
+        if allowed_tools.is_empty() {
+            args.push("--dangerously-skip-permissions".to_string());
+        } else {
+            args.push("--allowedTools".to_string());
+            args.push(allowed_tools.join(","));
+        }
+        if !disallowed_tools.is_empty() {
+            args.push("--disallowedTools".to_string());
+            args.push(disallowed_tools.join(","));
+        }
+
+        if let Some(session_id) = self.resume_session_id {
+            args.push("--resume".to_string());
+            args.push(session_id.to_string());
+        }
+
+        // Expand per-agent environment variables for this project
+        let environment = match self.environment_variables {
+            Some(env_json) => parse_environment_variables(env_json)?
+                .into_iter()
+                .map(|(key, value)| (key, value.replace("{{PROJECT_PATH}}", self.project_path)))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(AgentInvocation { args, environment })
+    }
+}
+
+/// The saved Claude environment followed by the agent's own variables, which
+/// are set later and so win where both set one
+fn with_claude_env(db: &AgentDb, environment: Vec<(String, String)>) -> Vec<(String, String)> {
+    super::claude_env::claude_env_for(db, None)
+        .into_iter()
+        .chain(environment)
+        .collect()
+}
+
+/// The Claude CLI invocation `execute_agent` would start, without starting it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentExecutionPreview {
+    pub program: String,
+    pub args: Vec<String>,
+    pub command_line: String, // Shell-quoted program and arguments
+    pub working_directory: String,
+    pub environment: std::collections::BTreeMap<String, String>, // Secret-looking values masked
+    pub unresolved_variables: bool, // The system prompt has placeholders execution would reject
+    pub claude_version: Option<String>, // What the binary reports, when it runs
+}
+
+/// Quote an argument for a POSIX shell when it needs it
+pub(crate) fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Show the command line, working directory and environment an agent would be
+/// executed with, built by the same code as `execute_agent` but not spawned
+#[tauri::command]
+pub async fn preview_agent_execution(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    project_path: String,
+    task: String,
+    model: Option<String>,
+    variables: Option<HashMap<String, String>>,
+) -> Result<AgentExecutionPreview, String> {
+    let agent = get_agent(db.clone(), agent_id).await?;
+    let model = model.unwrap_or_else(|| agent.model.clone());
+    let claude_path = resolve_claude_binary(&app, &db, &project_path)?;
+    let claude_version = installed_claude_version(&claude_path)
+        .await
+        .map(|version| version.raw);
+
+    let variables = variables.unwrap_or_default();
+    let builtins = super::prompt_templates::builtin_variables(&project_path, &agent.name, &model);
+    let unresolved_variables =
+        super::prompt_templates::resolve_system_prompt(&agent.system_prompt, &builtins, &variables, false)
+            .is_err();
+    // Secret variable values are shown masked, as on the recorded run
+    let resolved = super::prompt_templates::resolve_system_prompt(
+        &agent.system_prompt,
+        &builtins,
+        &variables,
+        true,
+    )?;
+
+    let AgentInvocation { args, environment } = AgentInvocationSpec {
+        task: &task,
+        system_prompt: &resolved.masked,
+        model: &model,
+        allowed_tools: agent.allowed_tools.as_deref(),
+        disallowed_tools: agent.disallowed_tools.as_deref(),
+        resume_session_id: None,
+        environment_variables: agent.environment_variables.as_deref(),
+        project_path: &project_path,
+    }
+    .build()?;
+    let environment = with_claude_env(&db, environment);
+    let cmd = create_agent_system_command(&claude_path, args, &environment, &project_path);
+    let cmd = cmd.as_std();
+
+    // The process inherits this one's environment with the command's changes on top
+    let environment = super::claude_env::effective_env(cmd);
+
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let args: Vec<String> = cmd
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let command_line = std::iter::once(program.as_str())
+        .chain(args.iter().map(String::as_str))
+        .map(shell_quote)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(AgentExecutionPreview {
+        program,
+        args,
+        command_line,
+        working_directory: cmd
+            .get_current_dir()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or(project_path),
+        environment,
+        unresolved_variables,
+        claude_version,
+    })
+}
+
+/// Start queued runs while there are free slots under `max_concurrent_runs`
+///
+/// Boxed because it is reached from the monitor task spawned by `spawn_agent_system`,
+/// which would otherwise make the future type recursive.
+pub fn promote_queued_runs(app: AppHandle) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+    Box::pin(async move {
+        let db = app.state::<AgentDb>();
+        let registry = app.state::<crate::process::ProcessRegistryState>();
+
+        // Runs are promoted whenever one ends, which may have finished its batch
+        super::batches::complete_finished_batches(&app, &db);
+
+        loop {
+            let _launch_guard = registry.0.lock_launch().await;
+
+            let limit = match get_max_concurrent_runs_setting(&db) {
+                Ok(limit) => limit,
+                Err(e) => {
+                    error!("Failed to read max_concurrent_runs: {}", e);
+                    return;
+                }
+            };
+            if let Some(limit) = limit {
+                match registry.0.live_agent_process_count() {
+                    Ok(live) if live < limit => {}
+                    Ok(_) => return,
+                    Err(e) => {
+                        error!("Failed to count live agent processes: {}", e);
+                        return;
+                    }
+                }
+            }
+
+            // Claim the oldest queued run whose project isn't busy with another run
+            let next_run_id = match db.0.lock() {
+                Ok(conn) => {
+                    let next = queued_runs(&conn)
+                        .into_iter()
+                        .find(|(_, project_path, allow_busy_project)| {
+                            *allow_busy_project
+                                || matches!(
+                                    registry.0.find_live_agent_run_on_path(project_path),
+                                    Ok(None)
+                                )
+                        })
+                        .map(|(id, _, _)| id);
+                    if let Some(id) = next {
+                        let _ = conn.execute(
+                            "UPDATE agent_runs SET status = 'pending' WHERE id = ?1",
+                            params![id],
+                        );
+                    }
+                    next
+                }
+                Err(e) => {
+                    error!("Failed to lock database to dequeue runs: {}", e);
+                    return;
+                }
+            };
+
+            let Some(run_id) = next_run_id else {
+                return;
+            };
+
+            info!("Dequeued agent run {}", run_id);
+            match launch_agent_run(app.clone(), run_id, db.clone(), registry.clone()).await {
+                Ok(_) => {
+                    let _ = app.emit("agent-run-dequeued", run_id);
+                }
+                Err(e) => {
+                    error!("Failed to start queued agent run {}: {}", run_id, e);
+                    if let Ok(conn) = db.0.lock() {
+                        let _ = conn.execute(
+                            "UPDATE agent_runs SET status = 'failed', completed_at = CURRENT_TIMESTAMP WHERE id = ?1",
+                            params![run_id],
+                        );
+                    }
+                    let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+                }
+            }
+        }
+    })
+}
+
+/// Queued runs oldest first, with their project path and whether they may share it
+fn queued_runs(conn: &Connection) -> Vec<(i64, String, bool)> {
+    let queued = conn
+        .prepare(
+            "SELECT id, project_path, allow_busy_project FROM agent_runs WHERE status = 'queued' ORDER BY id ASC",
+        )
+        .and_then(|mut stmt| {
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                .collect::<Result<Vec<_>, _>>();
+            rows
+        });
+    match queued {
+        Ok(queued) => queued,
+        Err(e) => {
+            error!("Failed to list queued runs: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Read the `max_concurrent_runs` setting (None means unlimited)
+fn get_max_concurrent_runs_setting(db: &AgentDb) -> Result<Option<usize>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = 'max_concurrent_runs'",
+        [],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(value) => Ok(value.parse::<usize>().ok().filter(|limit| *limit > 0)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Get the maximum number of concurrently running agents (None means unlimited)
+#[tauri::command]
+pub async fn get_max_concurrent_runs(db: State<'_, AgentDb>) -> Result<Option<usize>, String> {
+    get_max_concurrent_runs_setting(&db)
+}
+
+/// Set the maximum number of concurrently running agents (None or 0 removes the limit)
+#[tauri::command]
+pub async fn set_max_concurrent_runs(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    limit: Option<usize>,
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES ('max_concurrent_runs', ?1)
+             ON CONFLICT(key) DO UPDATE SET value = ?1",
+            params![limit.unwrap_or(0).to_string()],
+        )
+        .map_err(|e| format!("Failed to save max_concurrent_runs: {}", e))?;
+    }
+
+    // A raised limit may free slots for queued runs
+    promote_queued_runs(app).await;
+    Ok(())
+}
+
+/// List agent runs waiting for a free execution slot, oldest first
+#[tauri::command]
+pub async fn list_queued_runs(db: State<'_, AgentDb>) -> Result<Vec<AgentRun>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM agent_runs WHERE status = 'queued' ORDER BY id ASC",
+            AGENT_RUN_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let runs = stmt
+        .query_map([], agent_run_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(runs)
+}
+
+/// Mark a queued run cancelled and drop its resolved prompt
+fn cancel_queued(conn: &Connection, run_id: i64) -> Result<(), String> {
+    let updated = conn
+        .execute(
+            "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'queued'",
+            params![run_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err(format!("Agent run {} is not queued", run_id));
+    }
+    super::prompt_templates::forget_resolved_prompt(run_id);
+    Ok(())
+}
+
+/// Cancel a queued agent run before it starts
+#[tauri::command]
+pub async fn cancel_queued_run(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        cancel_queued(&conn, run_id)?;
+    }
+
+    let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+    super::batches::complete_finished_batches(&app, &db);
+    Ok(())
+}
+
+/// The template variables a run recorded for its retries
+fn recorded_run_variables(conn: &Connection, run_id: i64) -> Result<HashMap<String, String>, String> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT template_variables FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(super::prompt_templates::variables_from_json(json.as_deref()))
+}
+
+/// Retry an agent run with the same agent, project path, task and model.
+/// The original run's template variables are reused; secret-looking ones
+/// aren't recorded, so they have to be supplied again in `variables`.
+#[tauri::command]
+pub async fn retry_agent_run(
+    app: AppHandle,
+    run_id: i64,
+    variables: Option<HashMap<String, String>>,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<i64, String> {
+    info!("Retrying agent run {}", run_id);
+
+    let original = get_agent_run(db.clone(), run_id).await?;
+
+    let agent = match get_agent(db.clone(), original.agent_id).await {
+        Ok(agent) => agent,
+        Err(_) => {
+            return Err(format!(
+                "Cannot retry run {}: agent '{}' (id {}) no longer exists",
+                run_id, original.agent_name, original.agent_id
+            ))
+        }
+    };
+
+    // Flag retries that will run with a different prompt than the original run
+    let original_prompt_hash = original
+        .metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<JsonValue>(m).ok())
+        .and_then(|m| {
+            m.get("system_prompt_sha256")
+                .and_then(|h| h.as_str())
+                .map(|h| h.to_string())
+        });
+    let mut metadata = serde_json::Map::new();
+    if let Some(original_hash) = original_prompt_hash {
+        let changed = original_hash != system_prompt_hash(&agent.system_prompt);
+        if changed {
+            warn!(
+                "Agent {} prompt changed since run {}, retrying with the current prompt",
+                agent.name, run_id
+            );
+        }
+        metadata.insert("prompt_changed".to_string(), JsonValue::Bool(changed));
+    }
+
+    let mut run_variables = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        recorded_run_variables(&conn, run_id)?
+    };
+    run_variables.extend(variables.unwrap_or_default());
+
+    let options = AgentRunOptions {
+        triggered_by: Some("retry".to_string()),
+        retried_from_run_id: Some(run_id),
+        metadata,
+        model: Some(original.model),
+        timeout_minutes: original.timeout_minutes,
+        max_cost_usd: original.max_cost_usd,
+        max_tokens: original.max_tokens,
+        variables: run_variables,
+        ..Default::default()
+    };
+
+    execute_agent_with_options(
+        app,
+        original.agent_id,
+        original.project_path,
+        original.task,
+        options,
         db,
         registry,
-    ).await
+    )
+    .await
+}
+
+/// Follow up on a finished agent run in the same Claude session, as a new linked run
+#[tauri::command]
+pub async fn continue_agent_run(
+    app: AppHandle,
+    run_id: i64,
+    prompt: String,
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+) -> Result<i64, String> {
+    if prompt.trim().is_empty() {
+        return Err("Prompt cannot be empty".to_string());
+    }
+
+    let original = get_agent_run(db.clone(), run_id).await?;
+    if matches!(original.status.as_str(), "pending" | "queued" | "running") {
+        return Err(format!("Agent run {} has not finished yet", run_id));
+    }
+    if original.session_id.is_empty() {
+        return Err(format!("Agent run {} has no Claude session to continue", run_id));
+    }
+    if find_session_file(&original.session_id)?.is_none() {
+        let expected = dirs::home_dir()
+            .ok_or("Failed to get home directory")?
+            .join(".claude")
+            .join("projects")
+            .join(original.project_path.replace('/', "-"))
+            .join(format!("{}.jsonl", original.session_id));
+        return Err(format!(
+            "Session file for agent run {} not found: {}",
+            run_id,
+            expected.display()
+        ));
+    }
+
+    info!("Continuing agent run {} in session {}", run_id, original.session_id);
+
+    let options = AgentRunOptions {
+        triggered_by: Some("continuation".to_string()),
+        model: Some(original.model),
+        timeout_minutes: original.timeout_minutes,
+        max_cost_usd: original.max_cost_usd,
+        max_tokens: original.max_tokens,
+        parent_run_id: Some(run_id),
+        ..Default::default()
+    };
+
+    execute_agent_with_options(
+        app,
+        original.agent_id,
+        original.project_path,
+        prompt,
+        options,
+        db,
+        registry,
+    )
+    .await
 }
 
 /// Creates a system binary command for agent execution
 fn create_agent_system_command(
     claude_path: &str,
     args: Vec<String>,
+    environment: &[(String, String)],
     project_path: &str,
 ) -> Command {
     let mut cmd = create_command_with_env(claude_path);
@@ -790,11 +2425,33 @@ fn create_agent_system_command(
     for arg in args {
         cmd.arg(arg);
     }
+
+    // Layer agent environment variables over the inherited ones
+    for (key, value) in environment {
+        if key == "PATH" {
+            // Merge rather than replace so Node/Claude stay discoverable
+            let inherited = cmd
+                .as_std()
+                .get_envs()
+                .find(|(k, _)| *k == "PATH")
+                .and_then(|(_, v)| v.map(|v| v.to_string_lossy().to_string()))
+                .unwrap_or_else(|| std::env::var("PATH").unwrap_or_default());
+            let separator = if cfg!(windows) { ";" } else { ":" };
+            cmd.env("PATH", format!("{}{}{}", value, separator, inherited));
+        } else {
+            cmd.env(key, value);
+        }
+    }
+    super::extra_path::apply_extra_path(cmd.as_std_mut());
     
     cmd.current_dir(project_path)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+
+    // Lead a new process group so stopping the run also stops its MCP servers
+    #[cfg(unix)]
+    cmd.process_group(0);
     
     cmd
 }
@@ -807,6 +2464,8 @@ async fn spawn_agent_system(
     agent_name: String,
     claude_path: String,
     args: Vec<String>,
+    environment: Vec<(String, String)>,
+    budget: Option<RunBudget>,
     project_path: String,
     task: String,
     execution_model: String,
@@ -814,14 +2473,38 @@ async fn spawn_agent_system(
     registry: State<'_, crate::process::ProcessRegistryState>,
 ) -> Result<i64, String> {
     // Build the command
-    let mut cmd = create_agent_system_command(&claude_path, args, &project_path);
+    let cmd = create_agent_system_command(&claude_path, args, &environment, &project_path);
+    if !environment.is_empty() {
+        // Only names are logged - values may hold secrets
+        let names: Vec<&str> = environment.iter().map(|(k, _)| k.as_str()).collect();
+        info!("Setting agent environment variables: {}", names.join(", "));
+    }
+
+    // Refuse a binary that no longer matches a pinned checksum, and say which
+    // runtime is missing for Bun and Deno shims
+    super::binary_pin::check_binary_pin(cmd.as_std().get_program().to_os_string()).await?;
+    crate::claude_binary::check_shim_runtime(cmd.as_std())?;
 
     // Spawn the process
     info!("🚀 Spawning Claude system process...");
-    let mut child = cmd.spawn().map_err(|e| {
-        error!("❌ Failed to spawn Claude process: {}", e);
-        format!("Failed to spawn Claude: {}", e)
-    })?;
+    let launch = match super::binary_fallback::spawn_claude_with_fallback(&app, cmd).await {
+        Ok(launch) => launch,
+        Err(e) => {
+            error!("❌ Failed to spawn Claude process: {}", e);
+            return Err(e);
+        }
+    };
+    if let Some(fallback) = &launch.fallback {
+        // Record the binary that actually runs in place of the configured one
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE agent_runs SET claude_binary = ?1, claude_version = ?2 WHERE id = ?3",
+            params![fallback.used_path, fallback.version, run_id],
+        )
+        .map_err(|e| format!("Failed to record the run's Claude binary: {}", e))?;
+    }
+    let spawned = launch.spawned;
+    let child = spawned.child;
 
     info!("🔌 Using Stdio::null() for stdin - no input expected");
 
@@ -840,14 +2523,10 @@ async fn spawn_agent_system(
         info!("📝 Updated database with running status and PID");
     }
 
-    // Get stdout and stderr
-    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
-    let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
-    info!("📡 Set up stdout/stderr readers");
-
     // Create readers
-    let stdout_reader = TokioBufReader::new(stdout);
-    let stderr_reader = TokioBufReader::new(stderr);
+    let stdout_reader = TokioBufReader::new(spawned.stdout);
+    let stderr_reader = TokioBufReader::new(spawned.stderr);
+    info!("📡 Set up stdout/stderr readers");
 
     // Create variables we need for the spawned tasks
     let app_dir = app
@@ -856,19 +2535,18 @@ async fn spawn_agent_system(
         .expect("Failed to get app data dir");
     let db_path = app_dir.join("agents.db");
 
-    // Shared state for collecting session ID and live output
+    // Shared state for collecting session ID
     let session_id = std::sync::Arc::new(Mutex::new(String::new()));
-    let live_output = std::sync::Arc::new(Mutex::new(String::new()));
     let start_time = std::time::Instant::now();
 
     // Spawn tasks to read stdout and stderr
     let app_handle = app.clone();
     let session_id_clone = session_id.clone();
-    let live_output_clone = live_output.clone();
     let registry_clone = registry.0.clone();
     let first_output = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
     let first_output_clone = first_output.clone();
     let db_path_for_stdout = db_path.clone(); // Clone the db_path for the stdout task
+    let mut budget_tracker = budget.map(|b| BudgetTracker::new(b, execution_model.clone()));
 
     let stdout_task = tokio::spawn(async move {
         info!("📖 Starting to read Claude stdout...");
@@ -877,6 +2555,7 @@ async fn spawn_agent_system(
 
         while let Ok(Some(line)) = lines.next_line().await {
             line_count += 1;
+            let mut budget_exceeded = false;
 
             // Log first output
             if !first_output_clone.load(std::sync::atomic::Ordering::Relaxed) {
@@ -893,17 +2572,13 @@ async fn spawn_agent_system(
                 debug!("stdout[{}]: {}", line_count, line);
             }
 
-            // Store live output in both local buffer and registry
-            if let Ok(mut output) = live_output_clone.lock() {
-                output.push_str(&line);
-                output.push('\n');
-            }
-
-            // Also store in process registry for cross-session access
+            // Store live output in the process registry, which spills it to disk
             let _ = registry_clone.append_live_output(run_id, &line);
 
             // Extract session ID from JSONL output
             if let Ok(json) = serde_json::from_str::<JsonValue>(&line) {
+                let _ = registry_clone.record_throughput(run_id, &json);
+
                 // Claude Code uses "session_id" (underscore), not "sessionId"
                 if json.get("type").and_then(|t| t.as_str()) == Some("system") &&
                    json.get("subtype").and_then(|s| s.as_str()) == Some("init") {
@@ -933,12 +2608,52 @@ async fn spawn_agent_system(
                         }
                     }
                 }
+
+                if let Some(tracker) = budget_tracker.as_mut() {
+                    tracker.record(&json);
+                    let used = tracker.fraction_used();
+                    if used >= 1.0 {
+                        budget_exceeded = true;
+                    } else if used >= BUDGET_WARNING_FRACTION && !tracker.warned {
+                        tracker.warned = true;
+                        warn!("Agent run {} has used {:.0}% of its budget", run_id, used * 100.0);
+                        let _ = app_handle.emit("agent-run-budget-warning", tracker.to_json(run_id));
+                    }
+                }
             }
 
             // Emit the line to the frontend with run_id for isolation
             let _ = app_handle.emit(&format!("agent-output:{}", run_id), &line);
             // Also emit to the generic event for backward compatibility
             let _ = app_handle.emit("agent-output", &line);
+
+            if budget_exceeded {
+                if let Some(tracker) = budget_tracker.as_ref() {
+                    warn!("Agent run {} exceeded its budget, killing it", run_id);
+                    tracker.save(&db_path_for_stdout, run_id);
+
+                    // Leave a marker in the output so the transcript shows why it ended
+                    let mut marker = tracker.to_json(run_id);
+                    marker["type"] = JsonValue::from("system");
+                    marker["subtype"] = JsonValue::from("budget_exceeded");
+                    marker["timestamp"] = JsonValue::from(chrono::Utc::now().to_rfc3339());
+                    let marker = marker.to_string();
+                    let _ = registry_clone.append_live_output(run_id, &marker);
+                    let _ = app_handle.emit(&format!("agent-output:{}", run_id), &marker);
+
+                    let db = app_handle.state::<AgentDb>();
+                    if let Err(e) =
+                        stop_agent_run(&db, &registry_clone, run_id, "budget_exceeded", false).await
+                    {
+                        error!("Failed to stop agent run {} over budget: {}", run_id, e);
+                    }
+                }
+                break;
+            }
+        }
+
+        if let Some(tracker) = budget_tracker.as_ref() {
+            tracker.save(&db_path_for_stdout, run_id);
         }
 
         info!(
@@ -999,6 +2714,9 @@ async fn spawn_agent_system(
     info!("📋 Registered process in registry");
 
     let db_path_for_monitor = db_path.clone(); // Clone for the monitor task
+    let project_path_for_monitor = project_path.clone();
+    let registry_for_monitor = registry.0.clone();
+    let model_for_monitor = execution_model.clone();
 
     // Monitor process status and wait for completion
     tokio::spawn(async move {
@@ -1059,6 +2777,12 @@ async fn spawn_agent_system(
 
                 let _ = app.emit("agent-complete", false);
                 let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+
+                // Free the slot for queued runs
+                let _ = registry_for_monitor
+                    .wait_for_exit(run_id, std::time::Duration::from_secs(5))
+                    .await;
+                promote_queued_runs(app).await;
                 return;
             }
 
@@ -1070,9 +2794,26 @@ async fn spawn_agent_system(
         let _ = stdout_task.await;
         let _ = stderr_task.await;
 
+        // Reap the process so it no longer counts against the concurrency limit
+        if let Ok(false) = registry_for_monitor
+            .wait_for_exit(run_id, std::time::Duration::from_secs(5))
+            .await
+        {
+            warn!("Process for run {} still alive after its output closed", run_id);
+        }
+
         let duration_ms = start_time.elapsed().as_millis() as i64;
         info!("⏱️ Process execution took {} ms", duration_ms);
 
+        // A crash or non-zero exit fails the run; an unknown exit counts as completed
+        let exit = registry_for_monitor.exit_status(run_id);
+        let final_status = if exit.is_some_and(|exit| !exit.success()) {
+            warn!("Process for run {} exited unsuccessfully ({})", run_id, exit.unwrap());
+            "failed"
+        } else {
+            "completed"
+        };
+
         // Get the session ID that was extracted
         let extracted_session_id = if let Ok(sid) = session_id.lock() {
             sid.clone()
@@ -1086,9 +2827,13 @@ async fn spawn_agent_system(
         // Update the run record with session ID and mark as completed - open a new connection
         if let Ok(conn) = Connection::open(&db_path_for_monitor) {
             info!("🔄 Updating database with extracted session ID: {}", extracted_session_id);
+            // Keep a final status (cancelled, timed out, ...) set while the process was stopping
             match conn.execute(
-                "UPDATE agent_runs SET session_id = ?1, status = 'completed', completed_at = CURRENT_TIMESTAMP WHERE id = ?2",
-                params![extracted_session_id, run_id],
+                "UPDATE agent_runs SET session_id = ?1,
+                    status = CASE WHEN status = 'running' THEN ?3 ELSE status END,
+                    completed_at = COALESCE(completed_at, CURRENT_TIMESTAMP)
+                 WHERE id = ?2",
+                params![extracted_session_id, run_id, final_status],
             ) {
                 Ok(rows_affected) => {
                     if rows_affected > 0 {
@@ -1105,53 +2850,79 @@ async fn spawn_agent_system(
             error!("❌ Failed to open database to update session ID for run {}", run_id);
         }
 
+        // Index the output so later searches don't need to re-read it
+        let jsonl = Some(&extracted_session_id)
+            .filter(|sid| !sid.is_empty())
+            .and_then(|sid| find_session_file(sid).ok().flatten())
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .or_else(|| {
+                registry_for_monitor
+                    .read_run_output(run_id)
+                    .ok()
+                    .filter(|output| !output.is_empty())
+            });
+        if let (Some(jsonl), Ok(conn)) = (jsonl, Connection::open(&db_path_for_monitor)) {
+            if let Err(e) = super::run_search::index_run_output(&conn, run_id, &jsonl) {
+                warn!("Failed to index output of run {}: {}", run_id, e);
+            }
+
+            // Store the final cost so run lists don't need to recompute it
+            let mut tally = UsageTally::new();
+            tally.record_jsonl(&jsonl, &model_for_monitor);
+            if tally.has_usage() {
+                if let Err(e) = save_run_usage(&conn, run_id, &tally) {
+                    warn!("Failed to save usage totals for run {}: {}", run_id, e);
+                }
+            }
+        }
+
+        // Record changed files without holding up completion on large projects
+        tokio::spawn(super::run_artifacts::finish_capture(
+            db_path_for_monitor.clone(),
+            run_id,
+            project_path_for_monitor,
+        ));
+
         // Cleanup will be handled by the cleanup_finished_processes function
 
-        let _ = app.emit("agent-complete", true);
-        let _ = app.emit(&format!("agent-complete:{}", run_id), true);
+        let success = Connection::open(&db_path_for_monitor)
+            .and_then(|conn| {
+                conn.query_row(
+                    "SELECT status FROM agent_runs WHERE id = ?1",
+                    params![run_id],
+                    |row| row.get::<_, String>(0),
+                )
+            })
+            .map(|status| status == "completed")
+            .unwrap_or(true);
+
+        let _ = app.emit("agent-complete", success);
+        let _ = app.emit(&format!("agent-complete:{}", run_id), success);
+
+        // Start the next queued run, if any
+        promote_queued_runs(app).await;
     });
 
     Ok(run_id)
 }
 
-/// List all currently running agent sessions
+/// List all currently running agent sessions with the CPU and memory each
+/// is using
 #[tauri::command]
 pub async fn list_running_sessions(
     db: State<'_, AgentDb>,
     registry: State<'_, crate::process::ProcessRegistryState>,
-) -> Result<Vec<AgentRun>, String> {
+) -> Result<Vec<WithResources<AgentRun>>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     // First get all running sessions from the database
-    let mut stmt = conn.prepare(
-        "SELECT id, agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at, created_at, completed_at 
-         FROM agent_runs WHERE status = 'running' ORDER BY process_started_at DESC"
-    ).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM agent_runs WHERE status = 'running' ORDER BY process_started_at DESC",
+        AGENT_RUN_COLUMNS
+    )).map_err(|e| e.to_string())?;
 
     let mut runs = stmt
-        .query_map([], |row| {
-            Ok(AgentRun {
-                id: Some(row.get(0)?),
-                agent_id: row.get(1)?,
-                agent_name: row.get(2)?,
-                agent_icon: row.get(3)?,
-                task: row.get(4)?,
-                model: row.get(5)?,
-                project_path: row.get(6)?,
-                session_id: row.get(7)?,
-                status: row
-                    .get::<_, String>(8)
-                    .unwrap_or_else(|_| "pending".to_string()),
-                pid: row
-                    .get::<_, Option<i64>>(9)
-                    .ok()
-                    .flatten()
-                    .map(|p| p as u32),
-                process_started_at: row.get(10)?,
-                created_at: row.get(11)?,
-                completed_at: row.get(12)?,
-            })
-        })
+        .query_map([], agent_run_from_row)
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
@@ -1162,22 +2933,32 @@ pub async fn list_running_sessions(
     // Cross-check with the process registry to ensure accuracy
     // Get actually running processes from the registry
     let registry_processes = registry.0.get_running_agent_processes()?;
-    let registry_run_ids: std::collections::HashSet<i64> = registry_processes
-        .iter()
-        .map(|p| p.run_id)
+    let mut processes_by_run: HashMap<i64, crate::process::ProcessInfo> = registry_processes
+        .into_iter()
+        .map(|p| (p.run_id, p))
         .collect();
 
     // Filter out any database entries that aren't actually running in the registry
     // This handles cases where processes crashed without updating the database
     runs.retain(|run| {
         if let Some(run_id) = run.id {
-            registry_run_ids.contains(&run_id)
+            processes_by_run.contains_key(&run_id)
         } else {
             false
         }
     });
 
-    Ok(runs)
+    // Processes that exited since are flagged stale and unregistered
+    let processes: Vec<crate::process::ProcessInfo> = runs
+        .iter()
+        .filter_map(|run| run.id.and_then(|id| processes_by_run.remove(&id)))
+        .collect();
+    let resources = registry.0.sample_resources(&processes)?;
+    Ok(runs
+        .into_iter()
+        .zip(resources)
+        .map(|(item, resources)| WithResources { item, resources })
+        .collect())
 }
 
 /// Kill a running agent session
@@ -1190,52 +2971,228 @@ pub async fn kill_agent_session(
 ) -> Result<bool, String> {
     info!("Attempting to kill agent session {}", run_id);
 
-    // First try to kill using the process registry
-    let killed_via_registry = match registry.0.kill_process(run_id).await {
-        Ok(success) => {
-            if success {
-                info!("Successfully killed process {} via registry", run_id);
-                true
-            } else {
-                warn!("Process {} not found in registry", run_id);
-                false
-            }
-        }
-        Err(e) => {
-            warn!("Failed to kill process {} via registry: {}", run_id, e);
-            false
-        }
-    };
+    let (updated, outcome) = stop_agent_run(&db, &registry.0, run_id, "cancelled", true).await?;
 
-    // If registry kill didn't work, try fallback with PID from database
-    if !killed_via_registry {
-        let pid_result = {
-            let conn = db.0.lock().map_err(|e| e.to_string())?;
-            conn.query_row(
-                "SELECT pid FROM agent_runs WHERE id = ?1 AND status = 'running'",
-                params![run_id],
-                |row| row.get::<_, Option<i64>>(0),
-            )
-            .map_err(|e| e.to_string())?
+    // Record runs that ignored the graceful stop
+    if outcome == TerminationOutcome::ForceKilled {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE agent_runs SET status = 'killed' WHERE id = ?1 AND status = 'cancelled'",
+            params![run_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    // Emit cancellation event with run_id for proper isolation
+    let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+
+    // The killed run's slot is free now
+    promote_queued_runs(app).await;
+
+    Ok(updated || outcome != TerminationOutcome::NotFound)
+}
+
+/// Give a running agent run its final status and stop its process tree,
+/// force-killing it if it outlives the kill grace period.
+///
+/// The status is written before the kill so the monitor task does not mark the
+/// run completed once its output closes. Unless `force` is set, the process is
+/// left alone when the run was no longer running. Returns (status updated, how the process ended).
+pub(crate) async fn stop_agent_run(
+    db: &AgentDb,
+    registry: &crate::process::ProcessRegistry,
+    run_id: i64,
+    final_status: &str,
+    force: bool,
+) -> Result<(bool, TerminationOutcome), String> {
+    let (pid, updated) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let pid = match conn.query_row(
+            "SELECT pid FROM agent_runs WHERE id = ?1 AND status = 'running'",
+            params![run_id],
+            |row| row.get::<_, Option<i64>>(0),
+        ) {
+            Ok(pid) => pid,
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(e.to_string()),
         };
+        let updated = conn
+            .execute(
+                "UPDATE agent_runs SET status = ?1, completed_at = CURRENT_TIMESTAMP WHERE id = ?2 AND status = 'running'",
+                params![final_status, run_id],
+            )
+            .map_err(|e| e.to_string())?;
+        (pid, updated > 0)
+    };
+
+    if !updated && !force {
+        return Ok((false, TerminationOutcome::NotFound));
+    }
+
+    let grace = std::time::Duration::from_secs(get_kill_grace_seconds_setting(db)?);
+    let outcome = registry.terminate_process(run_id, grace).await?;
 
-        if let Some(pid) = pid_result {
-            info!("Attempting fallback kill for PID {} from database", pid);
-            let _ = registry.0.kill_process_by_pid(run_id, pid as u32)?;
+    // Fall back to the PID from the database when the registry lost track of the process
+    if outcome == TerminationOutcome::NotFound {
+        if let Some(pid) = pid {
+            info!("Process {} not in registry, stopping PID {} from database", run_id, pid);
+            return Ok((updated, registry.terminate_pid(pid as u32, grace).await));
         }
     }
 
-    // Update the database to mark as cancelled
+    Ok((updated, outcome))
+}
+
+/// Seconds a stopped agent gets to exit before its process tree is force-killed
+const DEFAULT_KILL_GRACE_SECONDS: u64 = 5;
+
+fn get_kill_grace_seconds_setting(db: &AgentDb) -> Result<u64, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let updated = conn.execute(
-        "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE id = ?1 AND status = 'running'",
-        params![run_id],
-    ).map_err(|e| e.to_string())?;
 
-    // Emit cancellation event with run_id for proper isolation
-    let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = 'agent_kill_grace_seconds'",
+        [],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(value) => Ok(value.parse::<u64>().unwrap_or(DEFAULT_KILL_GRACE_SECONDS)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(DEFAULT_KILL_GRACE_SECONDS),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Get how long a stopped agent may take to exit before it is force-killed
+#[tauri::command]
+pub async fn get_agent_kill_grace_seconds(db: State<'_, AgentDb>) -> Result<u64, String> {
+    get_kill_grace_seconds_setting(&db)
+}
+
+/// Set how long a stopped agent may take to exit before it is force-killed
+#[tauri::command]
+pub async fn set_agent_kill_grace_seconds(
+    db: State<'_, AgentDb>,
+    seconds: u64,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES ('agent_kill_grace_seconds', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1",
+        params![seconds.to_string()],
+    )
+    .map_err(|e| format!("Failed to save agent_kill_grace_seconds: {}", e))?;
+    Ok(())
+}
+
+/// Seconds without output after which a running agent counts as stalled
+const DEFAULT_STALL_SECONDS: u64 = 300;
+
+fn get_stall_seconds_setting(db: &AgentDb) -> Result<u64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = 'agent_stall_seconds'",
+        [],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(value) => Ok(value.parse::<u64>().unwrap_or(DEFAULT_STALL_SECONDS)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(DEFAULT_STALL_SECONDS),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Get how long a running agent may go without output before it counts as stalled
+#[tauri::command]
+pub async fn get_agent_stall_seconds(db: State<'_, AgentDb>) -> Result<u64, String> {
+    get_stall_seconds_setting(&db)
+}
+
+/// Set how long a running agent may go without output before it counts as stalled
+#[tauri::command]
+pub async fn set_agent_stall_seconds(db: State<'_, AgentDb>, seconds: u64) -> Result<(), String> {
+    if seconds == 0 {
+        return Err("Stall threshold must be at least 1 second".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES ('agent_stall_seconds', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1",
+        params![seconds.to_string()],
+    )
+    .map_err(|e| format!("Failed to save agent_stall_seconds: {}", e))?;
+    Ok(())
+}
+
+/// Start the background task that kills agent runs past their hard timeout
+/// and reports runs that have stalled
+pub fn start_timeout_reaper(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            if let Err(e) = reap_timed_out_runs(&app).await {
+                error!("Agent timeout check failed: {}", e);
+            }
+            if let Err(e) = report_stalled_runs(&app) {
+                error!("Agent stall check failed: {}", e);
+            }
+        }
+    });
+}
+
+/// Emit `agent-run-stalled` once for each run that has gone quiet
+fn report_stalled_runs(app: &AppHandle) -> Result<(), String> {
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    let db = app.state::<AgentDb>();
+    let stall_after = std::time::Duration::from_secs(get_stall_seconds_setting(&db)?);
+
+    for (run_id, metrics) in registry.0.take_newly_stalled_agent_runs(stall_after)? {
+        warn!(
+            "Agent run {} has produced no output for {} seconds",
+            run_id, metrics.seconds_since_last_output
+        );
+        let _ = app.emit(&format!("agent-run-stalled:{}", run_id), &metrics);
+        let _ = app.emit(
+            "agent-run-stalled",
+            serde_json::json!({ "run_id": run_id, "metrics": metrics }),
+        );
+    }
+    Ok(())
+}
+
+/// Kill every agent run whose deadline has passed and mark it `timed_out`
+async fn reap_timed_out_runs(app: &AppHandle) -> Result<(), String> {
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    let db = app.state::<AgentDb>();
 
-    Ok(updated > 0 || killed_via_registry)
+    for info in registry.0.get_timed_out_agent_processes()? {
+        let run_id = info.run_id;
+        if !registry.0.is_process_running(run_id).await? {
+            continue;
+        }
+
+        let elapsed_minutes = (chrono::Utc::now() - info.started_at).num_minutes();
+        warn!(
+            "Agent run {} exceeded its timeout after {} minutes, killing it",
+            run_id, elapsed_minutes
+        );
+
+        let (updated, _) = stop_agent_run(&db, &registry.0, run_id, "timed_out", false).await?;
+        // A run that finished on its own in the meantime keeps its transcript as is
+        if updated {
+            // Leave a marker in the output so the transcript shows why it ended
+            let marker = serde_json::json!({
+                "type": "system",
+                "subtype": "timeout",
+                "message": format!("Run killed after exceeding its timeout ({} minutes elapsed)", elapsed_minutes),
+                "timestamp": chrono::Utc::now().to_rfc3339(),
+            })
+            .to_string();
+            let _ = registry.0.append_run_output(run_id, &marker);
+            let _ = app.emit(&format!("agent-output:{}", run_id), &marker);
+            let _ = app.emit(&format!("agent-timeout:{}", run_id), elapsed_minutes);
+            promote_queued_runs(app.clone()).await;
+        }
+    }
+
+    Ok(())
 }
 
 /// Get the status of a specific agent session
@@ -1323,13 +3280,22 @@ pub async fn cleanup_finished_processes(db: State<'_, AgentDb>) -> Result<Vec<i6
     Ok(cleaned_up)
 }
 
-/// Get live output from a running process
+/// Get live output from a running process. Without an offset this is the
+/// tail of the output; with one it is the complete lines from that byte
+/// offset of the run's output file. Pass `next_offset` back to keep reading.
+/// A chunk starting before the offset passed means the output file was
+/// replaced and is shorter than the offset.
 #[tauri::command]
 pub async fn get_live_session_output(
     registry: State<'_, crate::process::ProcessRegistryState>,
     run_id: i64,
-) -> Result<String, String> {
-    registry.0.get_live_output(run_id)
+    offset: Option<u64>,
+) -> Result<OutputChunk, String> {
+    let max_bytes = match offset {
+        Some(_) => MAX_OUTPUT_CHUNK_BYTES,
+        None => LIVE_OUTPUT_TAIL_BYTES,
+    };
+    registry.0.read_output_from(run_id, offset, max_bytes)
 }
 
 /// Get real-time output for a running session by reading its JSONL file with live output fallback
@@ -1415,13 +3381,122 @@ pub async fn get_session_output(
     }
 }
 
-/// Stream real-time session output by watching the JSONL file
+/// Whether a run is still pending, queued or running, assuming it is when
+/// its status can't be read
+fn run_in_progress(app: &AppHandle, run_id: i64) -> bool {
+    let status = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|dir| Connection::open(dir.join("agents.db")).map_err(|e| e.to_string()))
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(|e| e.to_string())
+        });
+    match status {
+        Ok(status) => matches!(status.as_str(), "pending" | "queued" | "running"),
+        Err(e) => {
+            debug!("Could not query session status for {}, continuing stream: {}", run_id, e);
+            true
+        }
+    }
+}
+
+/// Emit a run's output file from `offset` as `session-output-chunk:{run_id}`
+/// events until the run finishes
+/// The live output stream of each run, bumped when a client reconnects so the
+/// previous stream stops instead of emitting alongside the new one
+fn output_streams() -> &'static Mutex<HashMap<i64, u64>> {
+    static STREAMS: std::sync::OnceLock<Mutex<HashMap<i64, u64>>> = std::sync::OnceLock::new();
+    STREAMS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn claim_output_stream(run_id: i64) -> u64 {
+    static NEXT_STREAM: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+    let stream = NEXT_STREAM.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    if let Ok(mut streams) = output_streams().lock() {
+        streams.insert(run_id, stream);
+    }
+    stream
+}
+
+fn owns_output_stream(run_id: i64, stream: u64) -> bool {
+    output_streams()
+        .lock()
+        .map(|streams| streams.get(&run_id) == Some(&stream))
+        .unwrap_or(false)
+}
+
+async fn stream_run_output(
+    app: AppHandle,
+    registry: std::sync::Arc<crate::process::ProcessRegistry>,
+    run_id: i64,
+    mut offset: u64,
+) {
+    let stream = claim_output_stream(run_id);
+    let event = format!("session-output-chunk:{}", run_id);
+    loop {
+        // Check before reading so output written just before the run ends is still sent
+        let in_progress = run_in_progress(&app, run_id);
+        let drained = crate::process::drain_output(
+            |offset| registry.read_live_output_range(run_id, offset),
+            offset,
+            |chunk| {
+                if !owns_output_stream(run_id, stream) {
+                    return false;
+                }
+                let _ = app.emit(&event, chunk);
+                true
+            },
+        );
+        match drained {
+            Ok(next_offset) => offset = next_offset,
+            Err(e) => {
+                warn!("Stopped streaming output of run {}: {}", run_id, e);
+                break;
+            }
+        }
+        if !in_progress || !owns_output_stream(run_id, stream) {
+            break;
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+    }
+
+    if let Ok(mut streams) = output_streams().lock() {
+        if streams.get(&run_id) == Some(&stream) {
+            streams.remove(&run_id);
+        }
+    }
+    debug!("Stopped streaming output of run {}", run_id);
+}
+
+/// Stream real-time session output. Without an offset this watches the
+/// session JSONL file and emits its tail on `session-output-update`.
+///
+/// With `from_offset` the run's output file is replayed from that byte offset
+/// and then tailed, as `OutputChunk`s on `session-output-chunk:{run_id}`. Each
+/// chunk's `offset` follows the previous chunk's `next_offset`, so a client
+/// that reconnects passes the last `next_offset` it saw and drops any chunk
+/// whose `offset` isn't the one it expects. Only the latest stream of a run
+/// emits; starting one stops the previous one.
 #[tauri::command]
 pub async fn stream_session_output(
     app: AppHandle,
     db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
     run_id: i64,
+    from_offset: Option<u64>,
 ) -> Result<(), String> {
+    if let Some(offset) = from_offset {
+        let registry = registry.0.clone();
+        tokio::spawn(stream_run_output(app, registry, run_id, offset));
+        return Ok(());
+    }
+
     // Get the session information
     let run = get_agent_run(db, run_id).await?;
 
@@ -1453,8 +3528,11 @@ pub async fn stream_session_output(
                     let current_size = metadata.len();
 
                     if current_size > last_size {
-                        // File has grown, read new content
-                        if let Ok(content) = tokio::fs::read_to_string(&session_file).await {
+                        // File has grown, send its tail rather than the whole file
+                        if let Ok(content) = crate::process::read_output_tail(
+                            &session_file,
+                            LIVE_OUTPUT_TAIL_BYTES,
+                        ) {
                             let _ = app
                                 .emit("session-output-update", &format!("{}:{}", run_id, content));
                         }
@@ -1467,30 +3545,10 @@ pub async fn stream_session_output(
                 continue;
             }
 
-            // Check if the session is still running by querying the database
             // If the session is no longer running, stop streaming
-            if let Ok(conn) = rusqlite::Connection::open(
-                app.path()
-                    .app_data_dir()
-                    .expect("Failed to get app data dir")
-                    .join("agents.db"),
-            ) {
-                if let Ok(status) = conn.query_row(
-                    "SELECT status FROM agent_runs WHERE id = ?1",
-                    rusqlite::params![run_id],
-                    |row| row.get::<_, String>(0),
-                ) {
-                    if status != "running" {
-                        debug!("Session {} is no longer running, stopping stream", run_id);
-                        break;
-                    }
-                } else {
-                    // If we can't query the status, assume it's still running
-                    debug!(
-                        "Could not query session status for {}, continuing stream",
-                        run_id
-                    );
-                }
+            if !run_in_progress(&app, run_id) {
+                debug!("Session {} is no longer running, stopping stream", run_id);
+                break;
             }
 
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
@@ -1508,29 +3566,30 @@ pub async fn export_agent(db: State<'_, AgentDb>, id: i64) -> Result<String, Str
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     // Fetch the agent
-    let agent = conn
+    let mut agent = conn
         .query_row(
-            "SELECT name, icon, system_prompt, default_task, model, hooks FROM agents WHERE id = ?1",
+            "SELECT name, icon, system_prompt, default_task, model, hooks, enable_file_read, enable_file_write, enable_network FROM agents WHERE id = ?1",
             params![id],
             |row| {
-                Ok(serde_json::json!({
-                    "name": row.get::<_, String>(0)?,
-                    "icon": row.get::<_, String>(1)?,
-                    "system_prompt": row.get::<_, String>(2)?,
-                    "default_task": row.get::<_, Option<String>>(3)?,
-                    "model": row.get::<_, String>(4)?,
-                    "hooks": row.get::<_, Option<String>>(5)?
-                }))
+                Ok(AgentData {
+                    name: row.get(0)?,
+                    icon: row.get(1)?,
+                    system_prompt: row.get(2)?,
+                    default_task: row.get(3)?,
+                    model: row.get(4)?,
+                    hooks: row.get(5)?,
+                    icon_image: None,
+                    enable_file_read: row.get(6)?,
+                    enable_file_write: row.get(7)?,
+                    enable_network: row.get(8)?,
+                })
             },
         )
         .map_err(|e| format!("Failed to fetch agent: {}", e))?;
+    agent.icon_image = load_icon_image(&conn, id)?;
 
     // Create the export wrapper
-    let export_data = serde_json::json!({
-        "version": 1,
-        "exported_at": chrono::Utc::now().to_rfc3339(),
-        "agent": agent
-    });
+    let export_data = build_agent_export(agent)?;
 
     // Convert to pretty JSON string
     serde_json::to_string_pretty(&export_data)
@@ -1553,6 +3612,175 @@ pub async fn export_agent_to_file(
     Ok(())
 }
 
+/// File name suffix used for exported agents
+const AGENT_EXPORT_SUFFIX: &str = ".opcode.json";
+
+/// File written next to bulk exported agents describing the export
+const EXPORT_MANIFEST_FILE: &str = "opcode-export-manifest.json";
+
+/// An agent left out of a bulk export
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SkippedAgentExport {
+    pub agent_id: i64,
+    pub name: String,
+    pub reason: String,
+}
+
+/// Result of exporting every agent to a directory
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BulkExportResult {
+    pub files: Vec<String>,
+    pub skipped: Vec<SkippedAgentExport>,
+    pub manifest_path: String,
+}
+
+/// Turn an agent name into a safe file name stem
+fn sanitize_file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let stem = stem
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+
+    if stem.is_empty() {
+        "agent".to_string()
+    } else {
+        stem
+    }
+}
+
+/// Write data to a path, refusing to replace an existing file unless `overwrite` is set
+fn write_export_file(path: &std::path::Path, data: &str, overwrite: bool) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut file = if overwrite {
+        std::fs::File::create(path)?
+    } else {
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?
+    };
+    file.write_all(data.as_bytes())
+}
+
+/// Export all agents to a directory, one `.opcode.json` file per agent plus a manifest
+#[tauri::command]
+pub async fn export_all_agents(
+    db: State<'_, AgentDb>,
+    output_dir: String,
+    overwrite: Option<bool>,
+) -> Result<BulkExportResult, String> {
+    let overwrite = overwrite.unwrap_or(false);
+    let dir = std::path::PathBuf::from(&output_dir);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create directory {}: {}", output_dir, e))?;
+
+    let manifest_path = dir.join(EXPORT_MANIFEST_FILE);
+    if manifest_path.exists() && !overwrite {
+        return Err(format!(
+            "{} already exists, pass overwrite to replace a previous export",
+            manifest_path.display()
+        ));
+    }
+
+    let agents: Vec<(i64, String)> = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT id, name FROM agents ORDER BY name ASC, id ASC")
+            .map_err(|e| e.to_string())?;
+        let agents = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        agents
+    };
+
+    let mut used_stems = std::collections::HashSet::new();
+    let mut files = Vec::new();
+    let mut manifest_entries = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (agent_id, name) in agents {
+        // Agents with the same sanitized name get -2, -3, ... suffixes
+        let base = sanitize_file_stem(&name);
+        let mut stem = base.clone();
+        let mut counter = 2;
+        while !used_stems.insert(stem.to_lowercase()) {
+            stem = format!("{}-{}", base, counter);
+            counter += 1;
+        }
+        let path = dir.join(format!("{}{}", stem, AGENT_EXPORT_SUFFIX));
+
+        let json_data = match export_agent(db.clone(), agent_id).await {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Skipping agent {} in bulk export: {}", agent_id, e);
+                skipped.push(SkippedAgentExport { agent_id, name, reason: e });
+                continue;
+            }
+        };
+
+        match write_export_file(&path, &json_data, overwrite) {
+            Ok(()) => {
+                let file = path.to_string_lossy().to_string();
+                manifest_entries.push(serde_json::json!({
+                    "agent_id": agent_id,
+                    "name": name,
+                    "file": path.file_name().map(|f| f.to_string_lossy().to_string()),
+                }));
+                files.push(file);
+            }
+            Err(e) => {
+                let reason = if e.kind() == std::io::ErrorKind::AlreadyExists {
+                    format!("{} already exists", path.display())
+                } else {
+                    format!("Failed to write {}: {}", path.display(), e)
+                };
+                warn!("Skipping agent {} in bulk export: {}", agent_id, reason);
+                skipped.push(SkippedAgentExport { agent_id, name, reason });
+            }
+        }
+    }
+
+    let manifest = serde_json::json!({
+        "version": 1,
+        "exported_at": chrono::Utc::now().to_rfc3339(),
+        "count": files.len(),
+        "agents": manifest_entries,
+        "skipped": skipped,
+    });
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    write_export_file(&manifest_path, &manifest_json, overwrite)
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    info!(
+        "Exported {} agents to {} ({} skipped)",
+        files.len(),
+        output_dir,
+        skipped.len()
+    );
+
+    Ok(BulkExportResult {
+        files,
+        skipped,
+        manifest_path: manifest_path.to_string_lossy().to_string(),
+    })
+}
+
 /// Get the stored Claude binary path from settings
 #[tauri::command]
 pub async fn get_claude_binary_path(db: State<'_, AgentDb>) -> Result<Option<String>, String> {
@@ -1569,13 +3797,15 @@ pub async fn get_claude_binary_path(db: State<'_, AgentDb>) -> Result<Option<Str
     }
 }
 
-/// Set the Claude binary path in settings
+/// Set the Claude binary path in settings. `"bundled"` selects the binary
+/// shipped with opcode.
 #[tauri::command]
 pub async fn set_claude_binary_path(db: State<'_, AgentDb>, path: String) -> Result<(), String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     // Validate that the path exists and is executable
-    let path_buf = std::path::PathBuf::from(&path);
+    let path_buf = crate::claude_binary::binary_file(&path)
+        .ok_or("This build of opcode doesn't include a bundled Claude binary")?;
     if !path_buf.exists() {
         return Err(format!("File does not exist: {}", path));
     }
@@ -1603,12 +3833,17 @@ pub async fn set_claude_binary_path(db: State<'_, AgentDb>, path: String) -> Res
     Ok(())
 }
 
-/// List all available Claude installations on the system
+/// List all available Claude installations on the system. Versions come
+/// from the cache, with stale ones refreshed in the background unless
+/// `force_refresh` is set.
 #[tauri::command]
 pub async fn list_claude_installations(
-    _app: AppHandle,
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    force_refresh: Option<bool>,
 ) -> Result<Vec<crate::claude_binary::ClaudeInstallation>, String> {
-    let installations = crate::claude_binary::discover_claude_installations();
+    let installations =
+        super::binary_versions::list_installations(&app, &db, force_refresh.unwrap_or(false))?;
 
     if installations.is_empty() {
         return Err("No Claude Code installations found on the system".to_string());
@@ -1675,22 +3910,15 @@ fn create_command_with_env(program: &str) -> Command {
     tokio_cmd
 }
 
-/// Import an agent from JSON data
-#[tauri::command]
-pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<Agent, String> {
-    // Parse the JSON data
-    let export_data: AgentExport =
-        serde_json::from_str(&json_data).map_err(|e| format!("Invalid JSON format: {}", e))?;
-
-    // Validate version
-    if export_data.version != 1 {
-        return Err(format!(
-            "Unsupported export version: {}. This version of the app only supports version 1.",
-            export_data.version
-        ));
-    }
+/// Import an agent from JSON data
+#[tauri::command]
+pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<Agent, String> {
+    let agent_data = validate_agent_export(&json_data)?.agent;
+    import_agent_data(&db, &agent_data)
+}
 
-    let agent_data = export_data.agent;
+/// Create an agent from validated export data, renaming it if the name is taken
+fn import_agent_data(db: &AgentDb, agent_data: &AgentData) -> Result<Agent, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
 
     // Check if an agent with the same name already exists
@@ -1706,46 +3934,19 @@ pub async fn import_agent(db: State<'_, AgentDb>, json_data: String) -> Result<A
     let final_name = if existing_count > 0 {
         format!("{} (Imported)", agent_data.name)
     } else {
-        agent_data.name
+        agent_data.name.clone()
     };
 
     // Create the agent
-    conn.execute(
-        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, 1, 1, 0, ?6)",
-        params![
-            final_name,
-            agent_data.icon,
-            agent_data.system_prompt,
-            agent_data.default_task,
-            agent_data.model,
-            agent_data.hooks
-        ],
-    )
-    .map_err(|e| format!("Failed to create agent: {}", e))?;
-
-    let id = conn.last_insert_rowid();
+    let id = insert_imported_agent(&conn, agent_data, &final_name)
+        .map_err(|e| format!("Failed to create agent: {}", e))?;
 
     // Fetch the created agent
     let agent = conn
         .query_row(
-            "SELECT id, name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks, created_at, updated_at FROM agents WHERE id = ?1",
+            &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
             params![id],
-            |row| {
-                Ok(Agent {
-                    id: Some(row.get(0)?),
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    system_prompt: row.get(3)?,
-                    default_task: row.get(4)?,
-                    model: row.get(5)?,
-                    enable_file_read: row.get(6)?,
-                    enable_file_write: row.get(7)?,
-                    enable_network: row.get(8)?,
-                    hooks: row.get(9)?,
-                    created_at: row.get(10)?,
-                    updated_at: row.get(11)?,
-                })
-            },
+            agent_from_row,
         )
         .map_err(|e| format!("Failed to fetch created agent: {}", e))?;
 
@@ -1758,19 +3959,246 @@ pub async fn import_agent_from_file(
     db: State<'_, AgentDb>,
     file_path: String,
 ) -> Result<Agent, String> {
-    // Read the file
-    let mut json_data =
-        std::fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let json_data = read_agent_export_file(std::path::Path::new(&file_path))?;
+
+    // Import the agent
+    import_agent(db, json_data).await
+}
+
+/// Read an export file, dropping any BOM and surrounding whitespace
+fn read_agent_export_file(path: &std::path::Path) -> Result<String, String> {
+    let json_data =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
 
     // Normalize potential BOM and whitespace issues
-    if json_data.starts_with('\u{feff}') {
-        json_data = json_data.trim_start_matches('\u{feff}').to_string();
+    Ok(json_data.trim_start_matches('\u{feff}').trim().to_string())
+}
+
+/// Insert an agent from export data under the given name, returning its ID
+fn insert_imported_agent(
+    conn: &Connection,
+    agent_data: &AgentData,
+    name: &str,
+) -> Result<i64, String> {
+    conn.execute(
+        "INSERT INTO agents (name, icon, system_prompt, default_task, model, enable_file_read, enable_file_write, enable_network, hooks) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            name,
+            agent_data.icon,
+            agent_data.system_prompt,
+            agent_data.default_task,
+            agent_data.model,
+            agent_data.enable_file_read,
+            agent_data.enable_file_write,
+            agent_data.enable_network,
+            agent_data.hooks
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+
+    if let Some(icon_image) = &agent_data.icon_image {
+        save_icon_image(conn, id, icon_image)?;
     }
-    // Also trim leading/trailing whitespace to avoid parse surprises
-    json_data = json_data.trim().to_string();
+    Ok(id)
+}
 
-    // Import the agent
-    import_agent(db, json_data).await
+/// What a bulk import does when an agent with the same name already exists
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImportConflictPolicy {
+    Skip,
+    Rename,
+    Overwrite,
+}
+
+impl ImportConflictPolicy {
+    fn parse(policy: &str) -> Result<Self, String> {
+        match policy {
+            "skip" => Ok(Self::Skip),
+            "rename" => Ok(Self::Rename),
+            "overwrite" => Ok(Self::Overwrite),
+            other => Err(format!(
+                "Unknown conflict policy '{}', expected skip, rename or overwrite",
+                other
+            )),
+        }
+    }
+}
+
+/// Outcome of importing a single file in a bulk import
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentImportReport {
+    pub file: String,
+    pub status: String, // 'imported', 'skipped', 'failed'
+    pub agent_id: Option<i64>,
+    pub name: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Import every `.opcode.json` file in a directory, reporting the outcome per file
+#[tauri::command]
+pub async fn import_agents_from_directory(
+    db: State<'_, AgentDb>,
+    dir: String,
+    conflict_policy: String,
+) -> Result<Vec<AgentImportReport>, String> {
+    let policy = ImportConflictPolicy::parse(&conflict_policy)?;
+
+    let mut paths: Vec<std::path::PathBuf> = std::fs::read_dir(&dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", dir, e))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().ends_with(AGENT_EXPORT_SUFFIX))
+        })
+        .collect();
+    paths.sort();
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut reports = Vec::new();
+
+    for path in paths {
+        let file = path.to_string_lossy().to_string();
+        let report = match read_agent_export_file(&path)
+            .and_then(|json_data| validate_agent_export(&json_data))
+            .map(|export| export.agent)
+        {
+            Ok(agent_data) => import_agent_with_policy(&conn, &file, &agent_data, policy)
+                .unwrap_or_else(|e| AgentImportReport {
+                    file: file.clone(),
+                    status: "failed".to_string(),
+                    agent_id: None,
+                    name: Some(agent_data.name.clone()),
+                    message: Some(e),
+                }),
+            Err(e) => AgentImportReport {
+                file: file.clone(),
+                status: "failed".to_string(),
+                agent_id: None,
+                name: None,
+                message: Some(e),
+            },
+        };
+
+        if report.status == "failed" {
+            warn!("Failed to import {}: {:?}", file, report.message);
+        }
+        reports.push(report);
+    }
+
+    info!(
+        "Imported {} of {} agent files from {}",
+        reports.iter().filter(|r| r.status == "imported").count(),
+        reports.len(),
+        dir
+    );
+
+    Ok(reports)
+}
+
+/// Import one agent, applying the conflict policy when its name is taken
+fn import_agent_with_policy(
+    conn: &Connection,
+    file: &str,
+    agent_data: &AgentData,
+    policy: ImportConflictPolicy,
+) -> Result<AgentImportReport, String> {
+    let report = |status: &str, agent_id: i64, name: String, message: Option<String>| {
+        AgentImportReport {
+            file: file.to_string(),
+            status: status.to_string(),
+            agent_id: Some(agent_id),
+            name: Some(name),
+            message,
+        }
+    };
+
+    let existing_id: Option<i64> = match conn.query_row(
+        "SELECT id FROM agents WHERE name = ?1 ORDER BY id ASC LIMIT 1",
+        params![agent_data.name],
+        |row| row.get(0),
+    ) {
+        Ok(id) => Some(id),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let existing_id = match existing_id {
+        None => {
+            let id = insert_imported_agent(conn, agent_data, &agent_data.name)
+                .map_err(|e| format!("Failed to create agent: {}", e))?;
+            return Ok(report("imported", id, agent_data.name.clone(), None));
+        }
+        Some(id) => id,
+    };
+
+    match policy {
+        ImportConflictPolicy::Skip => Ok(report(
+            "skipped",
+            existing_id,
+            agent_data.name.clone(),
+            Some(format!("An agent named '{}' already exists", agent_data.name)),
+        )),
+        ImportConflictPolicy::Rename => {
+            let base = format!("{} (imported)", agent_data.name);
+            let mut name = base.clone();
+            let mut counter = 2;
+            loop {
+                let taken: i64 = conn
+                    .query_row(
+                        "SELECT COUNT(*) FROM agents WHERE name = ?1",
+                        params![name],
+                        |row| row.get(0),
+                    )
+                    .map_err(|e| e.to_string())?;
+                if taken == 0 {
+                    break;
+                }
+                name = format!("{} (imported {})", agent_data.name, counter);
+                counter += 1;
+            }
+
+            let id = insert_imported_agent(conn, agent_data, &name)
+                .map_err(|e| format!("Failed to create agent: {}", e))?;
+            Ok(report(
+                "imported",
+                id,
+                name,
+                Some(format!("Renamed from '{}'", agent_data.name)),
+            ))
+        }
+        ImportConflictPolicy::Overwrite => {
+            record_agent_version(conn, existing_id, "import")?;
+            conn.execute(
+                "UPDATE agents SET icon = ?1, system_prompt = ?2, default_task = ?3, model = ?4, hooks = ?5, enable_file_read = ?6, enable_file_write = ?7, enable_network = ?8, updated_at = CURRENT_TIMESTAMP WHERE id = ?9",
+                params![
+                    agent_data.icon,
+                    agent_data.system_prompt,
+                    agent_data.default_task,
+                    agent_data.model,
+                    agent_data.hooks,
+                    agent_data.enable_file_read,
+                    agent_data.enable_file_write,
+                    agent_data.enable_network,
+                    existing_id
+                ],
+            )
+            .map_err(|e| format!("Failed to update agent: {}", e))?;
+            match &agent_data.icon_image {
+                Some(icon_image) => save_icon_image(conn, existing_id, icon_image)?,
+                None => delete_icon_image(conn, existing_id)?,
+            }
+            Ok(report(
+                "imported",
+                existing_id,
+                agent_data.name.clone(),
+                Some(format!("Overwrote existing agent {}", existing_id)),
+            ))
+        }
+    }
 }
 
 // GitHub Agent Import functionality
@@ -1797,32 +4225,280 @@ struct GitHubApiResponse {
     file_type: String,
 }
 
-/// Fetch list of agents from GitHub repository
-#[tauri::command]
-pub async fn fetch_github_agents() -> Result<Vec<GitHubAgentFile>, String> {
-    info!("Fetching agents from GitHub repository...");
+/// Repository `fetch_github_agents` reads when no other is given
+const DEFAULT_GITHUB_AGENT_REPO: &str = "getAsterisk/opcode";
+
+/// Keychain service and account holding the GitHub personal access token
+const GITHUB_TOKEN_SERVICE: &str = "opcode";
+const GITHUB_TOKEN_ACCOUNT: &str = "github_token";
+
+fn github_token_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(GITHUB_TOKEN_SERVICE, GITHUB_TOKEN_ACCOUNT)
+        .map_err(|e| format!("Failed to open the system keychain: {}", e))
+}
+
+/// The saved GitHub token, if any
+fn load_github_token() -> Option<String> {
+    let entry = match github_token_entry() {
+        Ok(entry) => entry,
+        Err(e) => {
+            warn!("{}", e);
+            return None;
+        }
+    };
+    match entry.get_password() {
+        Ok(token) => Some(token),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            warn!("Failed to read the GitHub token: {}", e);
+            None
+        }
+    }
+}
+
+/// Check an `owner/name` repository reference
+fn validate_github_repo(repo: &str) -> Result<(), String> {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part != "."
+            && part != ".."
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    match repo.split_once('/') {
+        Some((owner, name)) if valid_part(owner) && valid_part(name) => Ok(()),
+        _ => Err(format!(
+            "Invalid GitHub repository '{}': expected owner/name",
+            repo
+        )),
+    }
+}
 
-    let client = reqwest::Client::new();
-    let url = "https://api.github.com/repos/getAsterisk/opcode/contents/cc_agents";
+/// Contents API URL for a path in a repository
+fn github_contents_url(repo: &str, path: &str) -> Result<reqwest::Url, String> {
+    validate_github_repo(repo)?;
+    let mut url = reqwest::Url::parse("https://api.github.com/repos")
+        .map_err(|e| e.to_string())?;
+    {
+        let mut segments = url
+            .path_segments_mut()
+            .map_err(|_| "Invalid GitHub API URL".to_string())?;
+        segments.extend(repo.split('/'));
+        segments.push("contents");
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            if segment == "." || segment == ".." {
+                return Err(format!("Invalid agent path '{}'", path));
+            }
+            segments.push(segment);
+        }
+    }
+    Ok(url)
+}
 
-    let response = client
+/// GET a GitHub API URL at an optional ref, authenticated when a token is saved
+async fn github_api_get(
+    url: reqwest::Url,
+    git_ref: Option<&str>,
+    accept: &str,
+    cached: Option<&GitHubCacheEntry>,
+) -> Result<reqwest::Response, String> {
+    let mut request = reqwest::Client::new()
         .get(url)
-        .header("Accept", "application/vnd.github+json")
-        .header("User-Agent", "opcode-App")
+        .header("Accept", accept)
+        .header("User-Agent", "opcode-App");
+    if let Some(git_ref) = git_ref.map(str::trim).filter(|r| !r.is_empty()) {
+        request = request.query(&[("ref", git_ref)]);
+    }
+    // Conditional requests answered with 304 don't count against the rate limit
+    if let Some(etag) = cached.and_then(|entry| entry.etag.as_deref()) {
+        request = request.header("If-None-Match", etag);
+    } else if let Some(last_modified) = cached.and_then(|entry| entry.last_modified.as_deref()) {
+        request = request.header("If-Modified-Since", last_modified);
+    }
+    let has_token = match load_github_token() {
+        Some(token) => {
+            request = request.bearer_auth(token);
+            true
+        }
+        None => false,
+    };
+
+    let response = request
         .send()
         .await
         .map_err(|e| format!("Failed to fetch from GitHub: {}", e))?;
+    if response.status().is_success() || response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(response);
+    }
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("GitHub API error ({}): {}", status, error_text));
+    let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let rate_limited = matches!(status.as_u16(), 403 | 429)
+        && header("x-ratelimit-remaining").as_deref() == Some("0");
+    if rate_limited {
+        let reset = header("x-ratelimit-reset")
+            .and_then(|r| r.parse::<i64>().ok())
+            .and_then(|r| chrono::DateTime::from_timestamp(r, 0))
+            .map(|r| {
+                r.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S %:z")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "an unknown time".to_string());
+        return Err(if has_token {
+            format!("GitHub API rate limit exceeded; it resets at {}", reset)
+        } else {
+            format!(
+                "GitHub API rate limit exceeded; it resets at {}. Set a GitHub token to raise the limit",
+                reset
+            )
+        });
     }
 
-    let api_files: Vec<GitHubApiResponse> = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse GitHub response: {}", e))?;
+    let error_text = response.text().await.unwrap_or_default();
+    Err(format!("GitHub API error ({}): {}", status, error_text))
+}
+
+/// A GitHub response body, from GitHub or the cache
+struct GitHubBody {
+    body: String,
+    stale: bool, // Served from the cache because GitHub couldn't be reached
+    fetched_at: String,
+}
+
+/// GET a GitHub API URL through the response cache. Fresh entries are served
+/// as they are, older ones are revalidated, and a cached copy is served with
+/// `stale` set when GitHub fails. `force_refresh` skips the cache unless GitHub
+/// fails. Bodies are only cached once `check` accepts them.
+async fn github_get_cached(
+    db: &AgentDb,
+    key: &str,
+    url: reqwest::Url,
+    git_ref: Option<&str>,
+    accept: &str,
+    force_refresh: bool,
+    check: impl Fn(&str) -> Result<(), String>,
+) -> Result<GitHubBody, String> {
+    let (cached, ttl) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (load_cache_entry(&conn, key)?, get_cache_ttl_setting(&conn)?)
+    };
+    let now = chrono::Utc::now().timestamp();
+    let from_cache = |entry: &GitHubCacheEntry, stale: bool| GitHubBody {
+        body: entry.body.clone(),
+        stale,
+        fetched_at: entry.fetched_at_rfc3339(),
+    };
+
+    if let Some(entry) = cached.as_ref().filter(|e| !force_refresh && e.is_fresh(now, ttl)) {
+        debug!("Serving {} from the GitHub cache", key);
+        return Ok(from_cache(entry, false));
+    }
+
+    let conditional = cached.as_ref().filter(|_| !force_refresh);
+    let fetched = match github_api_get(url, git_ref, accept, conditional).await {
+        Ok(response) if response.status() == reqwest::StatusCode::NOT_MODIFIED => Ok(None),
+        Ok(response) => {
+            let header = |name: &str| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.to_string())
+            };
+            let etag = header("etag");
+            let last_modified = header("last-modified");
+            response
+                .text()
+                .await
+                .map(|body| {
+                    Some(GitHubCacheEntry {
+                        body,
+                        etag,
+                        last_modified,
+                        fetched_at: now,
+                    })
+                })
+                .map_err(|e| format!("Failed to read response: {}", e))
+        }
+        Err(e) => Err(e),
+    };
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    match (fetched, cached) {
+        (Ok(Some(entry)), _) => {
+            check(&entry.body)?;
+            store_cache_entry(&conn, key, &entry)?;
+            Ok(from_cache(&entry, false))
+        }
+        (Ok(None), Some(entry)) => {
+            touch_cache_entry(&conn, key, now)?;
+            Ok(from_cache(&GitHubCacheEntry { fetched_at: now, ..entry }, false))
+        }
+        (Ok(None), None) => Err("GitHub answered Not Modified for an uncached request".to_string()),
+        (Err(e), Some(entry)) => {
+            warn!("Serving cached {} because GitHub failed: {}", key, e);
+            Ok(from_cache(&entry, true))
+        }
+        (Err(e), None) => Err(e),
+    }
+}
+
+/// Agent files listed in a GitHub repository
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GitHubAgentListing {
+    pub agents: Vec<GitHubAgentFile>,
+    pub stale: bool, // Cached copy served because GitHub couldn't be reached
+    pub fetched_at: String,
+}
+
+/// An agent file fetched from GitHub
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitHubAgentContent {
+    #[serde(flatten)]
+    pub export: AgentExport,
+    pub stale: bool, // Cached copy served because GitHub couldn't be reached
+    pub fetched_at: String,
+}
+
+/// Fetch list of agents from GitHub repository at an optional branch, tag or commit
+#[tauri::command]
+pub async fn fetch_github_agents(
+    db: State<'_, AgentDb>,
+    git_ref: Option<String>,
+    repo: Option<String>,
+    force_refresh: Option<bool>,
+) -> Result<GitHubAgentListing, String> {
+    let repo = repo.as_deref().unwrap_or(DEFAULT_GITHUB_AGENT_REPO);
+    info!(
+        "Fetching agents from GitHub repository {} at {}...",
+        repo,
+        git_ref.as_deref().unwrap_or("the default branch")
+    );
+
+    let url = github_contents_url(repo, "cc_agents")?;
+    let parse = |body: &str| {
+        serde_json::from_str::<Vec<GitHubApiResponse>>(body)
+            .map_err(|e| format!("Failed to parse GitHub response: {}", e))
+    };
+    let response = github_get_cached(
+        &db,
+        &github_cache_key(repo, git_ref.as_deref(), "cc_agents"),
+        url,
+        git_ref.as_deref(),
+        "application/vnd.github+json",
+        force_refresh.unwrap_or(false),
+        |body| parse(body).map(|_| ()),
+    )
+    .await?;
+    let api_files = parse(&response.body)?;
 
     // Filter only .opcode.json agent files
     let agent_files: Vec<GitHubAgentFile> = api_files
@@ -1840,77 +4516,201 @@ pub async fn fetch_github_agents() -> Result<Vec<GitHubAgentFile>, String> {
         .collect();
 
     info!("Found {} agents on GitHub", agent_files.len());
-    Ok(agent_files)
+    Ok(GitHubAgentListing {
+        agents: agent_files,
+        stale: response.stale,
+        fetched_at: response.fetched_at,
+    })
+}
+
+/// Fetch and preview a specific agent from GitHub by its path in the repository
+#[tauri::command]
+pub async fn fetch_github_agent_content(
+    db: State<'_, AgentDb>,
+    path: String,
+    git_ref: Option<String>,
+    repo: Option<String>,
+    force_refresh: Option<bool>,
+) -> Result<GitHubAgentContent, String> {
+    let repo = repo.as_deref().unwrap_or(DEFAULT_GITHUB_AGENT_REPO);
+    info!("Fetching agent content from {}: {}", repo, path);
+
+    let url = github_contents_url(repo, &path)?;
+    let validate = |body: &str| {
+        validate_agent_export(body).map_err(|e| format!("Invalid agent file {}: {}", path, e))
+    };
+    let response = github_get_cached(
+        &db,
+        &github_cache_key(repo, git_ref.as_deref(), &path),
+        url,
+        git_ref.as_deref(),
+        "application/vnd.github.raw+json",
+        force_refresh.unwrap_or(false),
+        |body| validate(body).map(|_| ()),
+    )
+    .await
+    .map_err(|e| format!("Failed to download agent: {}", e))?;
+
+    // Parse and validate the agent data
+    Ok(GitHubAgentContent {
+        export: validate(&response.body)?,
+        stale: response.stale,
+        fetched_at: response.fetched_at,
+    })
+}
+
+/// Import an agent directly from GitHub
+#[tauri::command]
+pub async fn import_agent_from_github(
+    db: State<'_, AgentDb>,
+    path: String,
+    git_ref: Option<String>,
+    repo: Option<String>,
+) -> Result<Agent, String> {
+    info!("Importing agent from GitHub: {}", path);
+
+    // First, fetch and validate the agent content
+    let content = fetch_github_agent_content(db.clone(), path, git_ref, repo, None).await?;
+
+    import_agent_data(&db, &content.export.agent)
+}
+
+/// Save the GitHub token in the system keychain; an empty token removes it
+#[tauri::command]
+pub async fn set_github_token(token: String) -> Result<(), String> {
+    let entry = github_token_entry()?;
+    let token = token.trim();
+    if token.is_empty() {
+        return match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Failed to remove the GitHub token: {}", e)),
+        };
+    }
+    entry
+        .set_password(token)
+        .map_err(|e| format!("Failed to save the GitHub token: {}", e))
 }
 
-/// Fetch and preview a specific agent from GitHub
+/// Whether a GitHub token is saved
 #[tauri::command]
-pub async fn fetch_github_agent_content(download_url: String) -> Result<AgentExport, String> {
-    info!("Fetching agent content from: {}", download_url);
+pub async fn has_github_token() -> Result<bool, String> {
+    Ok(load_github_token().is_some())
+}
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&download_url)
+/// Largest agent document `import_agent_from_url` will download
+const MAX_AGENT_URL_BYTES: usize = 1024 * 1024;
+
+/// Download an agent export over HTTPS, refusing oversized or non-JSON bodies
+async fn fetch_agent_export_from_url(url: &str) -> Result<String, String> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| format!("Invalid URL: {}", e))?;
+    if parsed.scheme() != "https" {
+        return Err(format!("Only https:// URLs are supported, got {}://", parsed.scheme()));
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.url().scheme() != "https" {
+                attempt.error("redirected to a non-HTTPS URL")
+            } else if attempt.previous().len() >= 10 {
+                attempt.error("too many redirects")
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    let mut response = client
+        .get(parsed.clone())
         .header("Accept", "application/json")
         .header("User-Agent", "opcode-App")
         .send()
         .await
-        .map_err(|e| format!("Failed to download agent: {}", e))?;
+        .map_err(|e| format!("Failed to download agent from {}: {}", parsed, e))?;
 
     if !response.status().is_success() {
         return Err(format!(
-            "Failed to download agent: HTTP {}",
+            "Failed to download agent from {}: HTTP {}",
+            parsed,
             response.status()
         ));
     }
 
-    let json_text = response
-        .text()
+    let too_large = || {
+        format!(
+            "Agent document at {} is larger than the {} KB limit",
+            parsed,
+            MAX_AGENT_URL_BYTES / 1024
+        )
+    };
+    if response
+        .content_length()
+        .is_some_and(|length| length as usize > MAX_AGENT_URL_BYTES)
+    {
+        return Err(too_large());
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("unknown")
+        .to_string();
+
+    // The declared length may be missing or wrong, so enforce the limit while reading
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
         .await
-        .map_err(|e| format!("Failed to read response: {}", e))?;
-
-    // Parse and validate the agent data
-    let export_data: AgentExport = serde_json::from_str(&json_text)
-        .map_err(|e| format!("Invalid agent JSON format: {}", e))?;
+        .map_err(|e| format!("Failed to read response from {}: {}", parsed, e))?
+    {
+        if body.len() + chunk.len() > MAX_AGENT_URL_BYTES {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
 
-    // Validate version
-    if export_data.version != 1 {
+    let text = String::from_utf8(body)
+        .map_err(|_| format!("Response from {} is not text (content type: {})", parsed, content_type))?;
+    let text = text.trim_start_matches('\u{feff}').trim().to_string();
+    if serde_json::from_str::<JsonValue>(&text).is_err() {
         return Err(format!(
-            "Unsupported agent version: {}",
-            export_data.version
+            "Response from {} is not JSON (content type: {})",
+            parsed, content_type
         ));
     }
-
-    Ok(export_data)
+    Ok(text)
 }
 
-/// Import an agent directly from GitHub
+/// Import an agent from a URL serving an `.opcode.json` export, remembering the URL
 #[tauri::command]
-pub async fn import_agent_from_github(
-    db: State<'_, AgentDb>,
-    download_url: String,
-) -> Result<Agent, String> {
-    info!("Importing agent from GitHub: {}", download_url);
+pub async fn import_agent_from_url(db: State<'_, AgentDb>, url: String) -> Result<Agent, String> {
+    info!("Importing agent from URL: {}", url);
 
-    // First, fetch the agent content
-    let export_data = fetch_github_agent_content(download_url).await?;
+    let json_data = fetch_agent_export_from_url(&url).await?;
+    let export_data =
+        validate_agent_export(&json_data).map_err(|e| format!("Not a valid agent export: {}", e))?;
 
-    // Convert to JSON string and use existing import logic
-    let json_data = serde_json::to_string(&export_data)
-        .map_err(|e| format!("Failed to serialize agent data: {}", e))?;
+    let agent = import_agent_data(&db, &export_data.agent)?;
+    let id = agent.id.ok_or("Imported agent has no ID")?;
 
-    // Import using existing function
-    import_agent(db, json_data).await
-}
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE agents SET source_url = ?1 WHERE id = ?2",
+        params![url.trim(), id],
+    )
+    .map_err(|e| format!("Failed to record source URL: {}", e))?;
 
-/// Load agent session history from JSONL file
-/// Similar to Claude Code's load_session_history, but searches across all project directories
-#[tauri::command]
-pub async fn load_agent_session_history(
-    session_id: String,
-) -> Result<Vec<serde_json::Value>, String> {
-    log::info!("Loading agent session history for session: {}", session_id);
+    conn.query_row(
+        &format!("SELECT {} FROM agents WHERE id = ?1", AGENT_COLUMNS),
+        params![id],
+        agent_from_row,
+    )
+    .map_err(|e| format!("Failed to fetch imported agent: {}", e))
+}
 
+/// Find a session's JSONL file by searching every Claude project directory
+pub fn find_session_file(session_id: &str) -> Result<Option<std::path::PathBuf>, String> {
     let claude_dir = dirs::home_dir()
         .ok_or("Failed to get home directory")?
         .join(".claude");
@@ -1947,6 +4747,19 @@ pub async fn load_agent_session_history(
         log::error!("Failed to read projects directory");
     }
 
+    Ok(session_file_path)
+}
+
+/// Load agent session history from JSONL file
+/// Similar to Claude Code's load_session_history, but searches across all project directories
+#[tauri::command]
+pub async fn load_agent_session_history(
+    session_id: String,
+) -> Result<Vec<serde_json::Value>, String> {
+    log::info!("Loading agent session history for session: {}", session_id);
+
+    let session_file_path = find_session_file(&session_id)?;
+
     if let Some(session_path) = session_file_path {
         let file = std::fs::File::open(&session_path)
             .map_err(|e| format!("Failed to open session file: {}", e))?;
@@ -1967,3 +4780,100 @@ pub async fn load_agent_session_history(
         Err(format!("Session file not found: {}", session_id))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_environment_variables_clear_them() {
+        assert_eq!(normalize_environment_variables("{}").unwrap(), None);
+        assert_eq!(normalize_environment_variables("  ").unwrap(), None);
+        assert_eq!(
+            normalize_environment_variables(r#"{"AWS_PROFILE": "dev"}"#).unwrap(),
+            Some(r#"{"AWS_PROFILE": "dev"}"#.to_string())
+        );
+        assert!(normalize_environment_variables(r#"["AWS_PROFILE"]"#).is_err());
+    }
+
+    #[test]
+    fn test_duplicated_agents_keep_their_icon_image() {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        let conn = crate::db::test_connection();
+        let agent_id = crate::db::seed_agent(&conn, "Reviewer");
+        let icon = AgentIconImage {
+            mime: "image/svg+xml".to_string(),
+            data: STANDARD.encode(r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#),
+        };
+        save_icon_image(&conn, agent_id, &icon).unwrap();
+
+        let copy = copy_agent(&conn, agent_id, None).unwrap();
+        assert_eq!(copy.name, "Reviewer (copy)");
+        assert_eq!(load_icon_image(&conn, copy.id.unwrap()).unwrap(), Some(icon));
+    }
+
+    #[test]
+    fn test_retries_reuse_the_recorded_variables() {
+        let conn = crate::db::test_connection();
+        let agent_id = crate::db::seed_agent(&conn, "a");
+        let variables = HashMap::from([("VERSION".to_string(), "2.1".to_string())]);
+        conn.execute(
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, template_variables) VALUES (?1, 'a', 'bot', 't', 'sonnet', '/p', '', ?2)",
+            params![agent_id, super::super::prompt_templates::variables_to_json(&variables)],
+        )
+        .unwrap();
+
+        assert_eq!(recorded_run_variables(&conn, conn.last_insert_rowid()).unwrap(), variables);
+        assert!(recorded_run_variables(&conn, 999).is_err());
+    }
+
+    #[test]
+    fn test_cancelling_a_queued_run_drops_its_prompt() {
+        let conn = crate::db::test_connection();
+        let agent_id = crate::db::seed_agent(&conn, "a");
+        conn.execute(
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status) VALUES (?1, 'a', 'bot', 't', 'sonnet', '/p', '', 'queued')",
+            params![agent_id],
+        )
+        .unwrap();
+        let run_id = conn.last_insert_rowid();
+        super::super::prompt_templates::remember_resolved_prompt(run_id, "token=s3cret".into());
+
+        cancel_queued(&conn, run_id).unwrap();
+        assert_eq!(super::super::prompt_templates::take_resolved_prompt(run_id), None);
+        assert!(cancel_queued(&conn, run_id).is_err());
+    }
+
+    #[test]
+    fn test_tool_lists_split_on_commas_and_whitespace() {
+        assert_eq!(
+            parse_tool_list("Read, Grep\tGlob\nmcp__github__create_issue").unwrap(),
+            ["Read", "Grep", "Glob", "mcp__github__create_issue"]
+        );
+        assert_eq!(parse_tool_list("Read,, ,Read,").unwrap(), ["Read"]);
+        assert_eq!(
+            parse_tool_list(r#"[" Read ", "", "Grep"]"#).unwrap(),
+            ["Read", "Grep"]
+        );
+        assert!(parse_tool_list("  ").unwrap().is_empty());
+        assert!(parse_tool_list("[]").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tool_rules_are_kept_whole() {
+        assert_eq!(
+            parse_tool_list("Bash(git:*), Bash(git log:*) Edit").unwrap(),
+            ["Bash(git:*)", "Bash(git log:*)", "Edit"]
+        );
+        assert_eq!(
+            parse_tool_list(r#"["Bash(npm run test:*)"]"#).unwrap(),
+            ["Bash(npm run test:*)"]
+        );
+        assert!(parse_tool_list("Bash()").is_err());
+        assert!(parse_tool_list("Bash(git:*").is_err());
+        assert!(parse_tool_list("Teleport").is_err());
+        assert!(parse_tool_list("mcp__").is_err());
+        assert!(parse_tool_list("[\"Read\"").is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Largest image Claude accepts
+const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Largest text file inlined into a prompt
+const MAX_TEXT_BYTES: u64 = 256 * 1024;
+
+/// Where images from outside the project are copied so Claude may read them
+const ATTACHMENTS_DIR: &str = ".claude/attachments";
+
+const IMAGE_TYPES: [(&str, &str); 5] = [
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+];
+
+/// Binary types that are recognized only to be refused by name
+const UNSUPPORTED_TYPES: [(&str, &str); 6] = [
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("svg", "image/svg+xml"),
+    ("bmp", "image/bmp"),
+    ("tiff", "image/tiff"),
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentMethod {
+    /// An image inside the project, referenced by its path
+    Referenced,
+    /// An image from outside the project, copied into it and referenced
+    Copied,
+    /// A text file whose content was added to the prompt
+    Inlined,
+}
+
+/// How an attachment was included in the prompt
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IncludedAttachment {
+    pub path: String,
+    pub mime_type: String,
+    pub method: AttachmentMethod,
+    /// The path Claude was pointed at, for images
+    pub referenced_path: Option<String>,
+}
+
+/// Attachments ready to be added to a prompt
+#[derive(Debug, Default)]
+pub struct PreparedAttachments {
+    blocks: Vec<String>,
+    pub included: Vec<IncludedAttachment>,
+}
+
+impl PreparedAttachments {
+    /// The prompt followed by the attachments
+    pub fn apply_to(&self, prompt: &str) -> String {
+        if self.blocks.is_empty() {
+            return prompt.to_string();
+        }
+        format!("{}\n\n{}", prompt, self.blocks.join("\n\n"))
+    }
+}
+
+fn extension_of(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+fn lookup(types: &[(&str, &'static str)], extension: &str) -> Option<&'static str> {
+    types
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| *mime)
+}
+
+/// A code fence longer than any run of backticks in `content`
+fn fence_for(content: &str) -> String {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest_run.max(2) + 1)
+}
+
+fn check_size(path: &str, size: u64, max: u64, kind: &str) -> Result<(), String> {
+    if size > max {
+        return Err(format!(
+            "Attachment {} is {} bytes; {} attachments are limited to {} bytes",
+            path, size, kind, max
+        ));
+    }
+    Ok(())
+}
+
+fn include_image(
+    project_root: &Path,
+    path: &str,
+    file: &Path,
+    mime_type: &str,
+) -> Result<(IncludedAttachment, String), String> {
+    let (method, referenced) = if file.starts_with(project_root) {
+        (AttachmentMethod::Referenced, file.to_path_buf())
+    } else {
+        let dir = project_root.join(ATTACHMENTS_DIR);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create attachments directory: {}", e))?;
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let copy = dir.join(format!("{}-{}", uuid::Uuid::new_v4(), name));
+        fs::copy(file, &copy).map_err(|e| format!("Failed to copy attachment {}: {}", path, e))?;
+        (AttachmentMethod::Copied, copy)
+    };
+    let referenced = referenced.to_string_lossy().into_owned();
+    let block = format!("Attached image (read it from this path): {}", referenced);
+    Ok((
+        IncludedAttachment {
+            path: path.to_string(),
+            mime_type: mime_type.to_string(),
+            method,
+            referenced_path: Some(referenced),
+        },
+        block,
+    ))
+}
+
+fn include_text(path: &str, file: &Path) -> Result<(IncludedAttachment, String), String> {
+    let mut bytes = Vec::new();
+    fs::File::open(file)
+        .and_then(|mut f| f.read_to_end(&mut bytes))
+        .map_err(|e| format!("Failed to read attachment {}: {}", path, e))?;
+    let content = match String::from_utf8(bytes) {
+        Ok(content) if !content.contains('\0') => content,
+        _ => {
+            return Err(format!(
+                "Attachment {} has unsupported type application/octet-stream; attach images or text files",
+                path
+            ))
+        }
+    };
+
+    let fence = fence_for(&content);
+    let block = format!(
+        "Attached file {}:\n{}{}\n{}\n{}",
+        path,
+        fence,
+        extension_of(file),
+        content.trim_end_matches('\n'),
+        fence
+    );
+    Ok((
+        IncludedAttachment {
+            path: path.to_string(),
+            mime_type: "text/plain".to_string(),
+            method: AttachmentMethod::Inlined,
+            referenced_path: None,
+        },
+        block,
+    ))
+}
+
+/// Validate attachments and prepare them for a prompt to Claude in
+/// `project_path`. Images are pointed to by path, copied into the project
+/// first when they live outside it; text files are inlined in fenced blocks.
+/// Anything else is refused.
+pub fn prepare_attachments(
+    project_path: &str,
+    paths: &[String],
+) -> Result<PreparedAttachments, String> {
+    let mut prepared = PreparedAttachments::default();
+    if paths.is_empty() {
+        return Ok(prepared);
+    }
+    let project_root = fs::canonicalize(project_path)
+        .map_err(|e| format!("Failed to resolve project path: {}", e))?;
+
+    for path in paths {
+        let file: PathBuf = fs::canonicalize(path)
+            .map_err(|e| format!("Attachment not found: {} ({})", path, e))?;
+        let metadata = fs::metadata(&file)
+            .map_err(|e| format!("Failed to read attachment {}: {}", path, e))?;
+        if !metadata.is_file() {
+            return Err(format!("Attachment is not a file: {}", path));
+        }
+
+        let extension = extension_of(&file);
+        let (included, block) = if let Some(mime_type) = lookup(&IMAGE_TYPES, &extension) {
+            check_size(path, metadata.len(), MAX_IMAGE_BYTES, "image")?;
+            include_image(&project_root, path, &file, mime_type)?
+        } else if let Some(mime_type) = lookup(&UNSUPPORTED_TYPES, &extension) {
+            return Err(format!(
+                "Attachment {} has unsupported type {}; attach images or text files",
+                path, mime_type
+            ));
+        } else {
+            check_size(path, metadata.len(), MAX_TEXT_BYTES, "text")?;
+            include_text(path, &file)?
+        };
+        prepared.included.push(included);
+        prepared.blocks.push(block);
+    }
+    Ok(prepared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_string(path: &Path) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_images_are_referenced_and_text_is_inlined() {
+        let project = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let inside_image = project.path().join("shot.png");
+        fs::write(&inside_image, [0x89, b'P', b'N', b'G']).unwrap();
+        let outside_image = outside.path().join("photo.JPG");
+        fs::write(&outside_image, [0xff, 0xd8]).unwrap();
+        let notes = outside.path().join("notes.md");
+        fs::write(&notes, "Use ```rust``` fences\n").unwrap();
+
+        let prepared = prepare_attachments(
+            &path_string(project.path()),
+            &[
+                path_string(&inside_image),
+                path_string(&outside_image),
+                path_string(&notes),
+            ],
+        )
+        .unwrap();
+
+        let methods: Vec<_> = prepared.included.iter().map(|a| a.method).collect();
+        assert_eq!(
+            methods,
+            [
+                AttachmentMethod::Referenced,
+                AttachmentMethod::Copied,
+                AttachmentMethod::Inlined
+            ]
+        );
+        assert_eq!(prepared.included[1].mime_type, "image/jpeg");
+        let copy = PathBuf::from(prepared.included[1].referenced_path.clone().unwrap());
+        assert!(copy.starts_with(fs::canonicalize(project.path()).unwrap()));
+        assert!(copy.exists());
+
+        let prompt = prepared.apply_to("Look at these");
+        assert!(prompt.starts_with("Look at these\n\nAttached image"));
+        assert!(prompt.ends_with("````md\nUse ```rust``` fences\n````"));
+    }
+
+    #[test]
+    fn test_invalid_attachments_are_refused() {
+        let project = tempfile::tempdir().unwrap();
+        let project_path = path_string(project.path());
+        let refused = |name: &str, content: &[u8]| {
+            let path = project.path().join(name);
+            fs::write(&path, content).unwrap();
+            prepare_attachments(&project_path, &[path_string(&path)]).unwrap_err()
+        };
+
+        assert!(refused("doc.pdf", b"%PDF").contains("application/pdf"));
+        assert!(refused("blob.bin", &[0, 159, 146, 150]).contains("application/octet-stream"));
+        assert!(refused("huge.txt", &vec![b'a'; MAX_TEXT_BYTES as usize + 1]).contains("limited"));
+        assert!(prepare_attachments(&project_path, &["/no/such/file.txt".to_string()]).is_err());
+        assert!(prepare_attachments(&project_path, std::slice::from_ref(&project_path)).is_err());
+    }
+}
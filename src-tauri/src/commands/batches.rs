@@ -0,0 +1,361 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::agents::{
+    execute_agent_with_options, promote_queued_runs, stop_agent_run, AgentDb, AgentRunOptions,
+};
+use crate::process::ProcessRegistryState;
+
+/// Run statuses that haven't reached a final state yet
+const ACTIVE_RUN_FILTER: &str = "status IN ('pending', 'queued', 'running')";
+
+/// A project in a batch whose run could not be created
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BatchStartError {
+    pub project_path: String,
+    pub error: String,
+}
+
+/// One run of a batch
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BatchRun {
+    pub run_id: i64,
+    pub project_path: String,
+    pub status: String,
+    pub completed_at: Option<String>,
+}
+
+/// Progress of a batch and every run in it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BatchStatus {
+    pub batch_id: i64,
+    pub agent_id: i64,
+    pub task: String,
+    pub status: String, // 'starting', 'running', 'cancelling', 'completed' or 'cancelled'
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    pub runs: Vec<BatchRun>,
+    pub start_errors: Vec<BatchStartError>,
+    pub status_counts: BTreeMap<String, usize>, // Runs per run status
+    pub active_count: usize,                    // Runs not finished yet
+}
+
+fn load_batch_status(conn: &Connection, batch_id: i64) -> Result<BatchStatus, String> {
+    let (agent_id, task, status, created_at, completed_at, start_errors) = conn
+        .query_row(
+            "SELECT agent_id, task, status, created_at, completed_at, start_errors FROM agent_run_batches WHERE id = ?1",
+            params![batch_id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                ))
+            },
+        )
+        .map_err(|e| format!("Batch {} not found: {}", batch_id, e))?;
+    let start_errors: Vec<BatchStartError> = start_errors
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, project_path, status, completed_at, {} FROM agent_runs WHERE batch_id = ?1 ORDER BY id ASC",
+            ACTIVE_RUN_FILTER
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![batch_id], |row| {
+            Ok((
+                BatchRun {
+                    run_id: row.get(0)?,
+                    project_path: row.get(1)?,
+                    status: row.get(2)?,
+                    completed_at: row.get(3)?,
+                },
+                row.get::<_, bool>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut status_counts = BTreeMap::new();
+    let mut active_count = 0;
+    let mut runs = Vec::with_capacity(rows.len());
+    for (run, active) in rows {
+        *status_counts.entry(run.status.clone()).or_insert(0) += 1;
+        if active {
+            active_count += 1;
+        }
+        runs.push(run);
+    }
+
+    Ok(BatchStatus {
+        batch_id,
+        agent_id,
+        task,
+        status,
+        created_at,
+        completed_at,
+        runs,
+        start_errors,
+        status_counts,
+        active_count,
+    })
+}
+
+/// Close batches whose runs have all finished, emitting `agent-batch-complete`
+pub fn complete_finished_batches(app: &AppHandle, db: &AgentDb) {
+    let finished = match db.0.lock() {
+        Ok(conn) => finish_batches(&conn),
+        Err(e) => Err(e.to_string()),
+    };
+    match finished {
+        Ok(finished) => {
+            for status in finished {
+                info!("Agent batch {} finished ({})", status.batch_id, status.status);
+                let _ = app.emit(&format!("agent-batch-complete:{}", status.batch_id), &status);
+                let _ = app.emit("agent-batch-complete", &status);
+            }
+        }
+        Err(e) => warn!("Failed to check agent batches: {}", e),
+    }
+}
+
+/// Mark open batches without active runs as finished, returning their final status
+fn finish_batches(conn: &Connection) -> Result<Vec<BatchStatus>, String> {
+    let batch_ids: Vec<i64> = {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id FROM agent_run_batches b WHERE status IN ('running', 'cancelling')
+                 AND NOT EXISTS (SELECT 1 FROM agent_runs WHERE batch_id = b.id AND {})",
+                ACTIVE_RUN_FILTER
+            ))
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        ids
+    };
+
+    let mut finished = Vec::new();
+    for batch_id in batch_ids {
+        conn.execute(
+            "UPDATE agent_run_batches
+             SET status = CASE status WHEN 'cancelling' THEN 'cancelled' ELSE 'completed' END,
+                 completed_at = CURRENT_TIMESTAMP
+             WHERE id = ?1",
+            params![batch_id],
+        )
+        .map_err(|e| e.to_string())?;
+        finished.push(load_batch_status(conn, batch_id)?);
+    }
+    Ok(finished)
+}
+
+fn batch_is_cancelling(db: &AgentDb, batch_id: i64) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.query_row(
+        "SELECT status = 'cancelling' FROM agent_run_batches WHERE id = ?1",
+        params![batch_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Execute one agent in several projects as a batch. Runs go through the run
+/// queue, so `max_concurrent_runs` applies; a project that fails to start is
+/// recorded in `start_errors` without stopping the others.
+#[tauri::command]
+pub async fn execute_agent_batch(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    agent_id: i64,
+    project_paths: Vec<String>,
+    task: String,
+    model: Option<String>,
+) -> Result<BatchStatus, String> {
+    let mut paths: Vec<String> = Vec::new();
+    for path in project_paths {
+        let path = path.trim().to_string();
+        if !path.is_empty() && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    if paths.is_empty() {
+        return Err("A batch needs at least one project path".to_string());
+    }
+    let model = model
+        .map(|m| super::agents::validate_model_override(&m, false))
+        .transpose()?;
+
+    let batch_id = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO agent_run_batches (agent_id, task, status) VALUES (?1, ?2, 'starting')",
+            params![agent_id, task],
+        )
+        .map_err(|e| format!("Failed to create batch: {}", e))?;
+        conn.last_insert_rowid()
+    };
+    info!("Starting agent batch {} across {} projects", batch_id, paths.len());
+
+    let mut start_errors = Vec::new();
+    for path in paths {
+        let result = if batch_is_cancelling(&db, batch_id)? {
+            Err("The batch was cancelled before this run started".to_string())
+        } else {
+            let options = AgentRunOptions {
+                triggered_by: Some("batch".to_string()),
+                batch_id: Some(batch_id),
+                model: model.clone(),
+                queue_if_busy: true,
+                ..Default::default()
+            };
+            execute_agent_with_options(
+                app.clone(),
+                agent_id,
+                path.clone(),
+                task.clone(),
+                options,
+                db.clone(),
+                registry.clone(),
+            )
+            .await
+        };
+        if let Err(error) = result {
+            warn!("Agent batch {} could not start in {}: {}", batch_id, path, error);
+            start_errors.push(BatchStartError {
+                project_path: path,
+                error,
+            });
+        }
+    }
+
+    // Runs that finished while others were starting couldn't close the batch early
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let json = serde_json::to_string(&start_errors).map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE agent_run_batches
+             SET start_errors = ?1, status = CASE status WHEN 'starting' THEN 'running' ELSE status END
+             WHERE id = ?2",
+            params![json, batch_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    // Every run may already have failed to start or finished
+    complete_finished_batches(&app, &db);
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_batch_status(&conn, batch_id)
+}
+
+/// Status of a batch with per-run statuses and counts
+#[tauri::command]
+pub async fn get_batch_status(db: State<'_, AgentDb>, batch_id: i64) -> Result<BatchStatus, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_batch_status(&conn, batch_id)
+}
+
+/// Cancel a batch: queued runs are cancelled and running ones are killed
+#[tauri::command]
+pub async fn cancel_agent_batch(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    batch_id: i64,
+) -> Result<BatchStatus, String> {
+    let (queued, running) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let updated = conn
+            .execute(
+                "UPDATE agent_run_batches SET status = 'cancelling' WHERE id = ?1 AND status IN ('starting', 'running')",
+                params![batch_id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err(format!("Batch {} is not running", batch_id));
+        }
+
+        let run_ids = |status: &str| -> Result<Vec<i64>, String> {
+            let mut stmt = conn
+                .prepare("SELECT id FROM agent_runs WHERE batch_id = ?1 AND status = ?2")
+                .map_err(|e| e.to_string())?;
+            let ids = stmt
+                .query_map(params![batch_id, status], |row| row.get(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            Ok(ids)
+        };
+        let queued = run_ids("queued")?;
+        let running = run_ids("running")?;
+
+        conn.execute(
+            "UPDATE agent_runs SET status = 'cancelled', completed_at = CURRENT_TIMESTAMP WHERE batch_id = ?1 AND status = 'queued'",
+            params![batch_id],
+        )
+        .map_err(|e| e.to_string())?;
+        (queued, running)
+    };
+
+    for run_id in queued {
+        super::prompt_templates::forget_resolved_prompt(run_id);
+        let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+    }
+    for run_id in running {
+        if let Err(e) = stop_agent_run(&db, &registry.0, run_id, "cancelled", true).await {
+            warn!("Failed to stop run {} of batch {}: {}", run_id, batch_id, e);
+        }
+        let _ = app.emit(&format!("agent-cancelled:{}", run_id), true);
+    }
+    info!("Cancelled agent batch {}", batch_id);
+
+    // Frees slots for other queued runs and closes the batch once it's idle
+    promote_queued_runs(app).await;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_batch_status(&conn, batch_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_finish_once_every_run_is_final() {
+        let conn = crate::db::test_connection();
+        crate::db::seed_agent(&conn, "a");
+        conn.execute_batch(
+            "INSERT INTO agent_run_batches (agent_id, task) VALUES (1, 'upgrade');
+             INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, batch_id) VALUES
+                 (1, 'a', 'bot', 'upgrade', 'sonnet', '/one', '', 'failed', 1),
+                 (1, 'a', 'bot', 'upgrade', 'sonnet', '/two', '', 'running', 1);",
+        )
+        .unwrap();
+
+        assert!(finish_batches(&conn).unwrap().is_empty());
+        let status = load_batch_status(&conn, 1).unwrap();
+        assert_eq!((status.active_count, status.status_counts["failed"]), (1, 1));
+
+        conn.execute("UPDATE agent_runs SET status = 'completed' WHERE id = 2", [])
+            .unwrap();
+        let finished = finish_batches(&conn).unwrap();
+        assert_eq!(finished.len(), 1);
+        assert_eq!((finished[0].status.as_str(), finished[0].active_count), ("completed", 0));
+        // A finished batch isn't reported again
+        assert!(finish_batches(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,304 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::process::Command;
+
+use super::agents::AgentDb;
+use super::binary_health::{check_binary_health, diagnose_spawn_failure, BinaryHealth};
+use crate::claude_binary::{binary_file, InstallationType};
+use crate::process::{spawn_claude, SpawnedClaude};
+
+/// Emitted with a `BinaryFallback` when another binary was run in place of
+/// the configured one
+pub const BINARY_FALLBACK_EVENT: &str = "claude-binary-fallback";
+
+/// `app_settings` key for whether a fallback binary becomes the configured one
+const PERSIST_FALLBACK_SETTING_KEY: &str = "claude_binary_fallback_persist";
+
+/// What happened when the configured binary failed to spawn
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BinaryFallback {
+    pub failed_path: String,
+    /// Why the configured binary couldn't be spawned
+    pub error: String,
+    /// The installation that was run instead
+    pub used_path: String,
+    pub version: String,
+    /// Whether `used_path` was saved as the configured binary
+    pub persisted: bool,
+}
+
+/// A spawned Claude process along with the binary that actually runs
+pub struct ClaudeLaunch {
+    pub spawned: SpawnedClaude,
+    pub binary: String,
+    /// Set when the configured binary failed and another one was run
+    pub fallback: Option<BinaryFallback>,
+}
+
+/// The arguments, directory and environment of a command, to run them with
+/// another binary
+struct CommandTemplate {
+    args: Vec<OsString>,
+    envs: Vec<(OsString, Option<OsString>)>,
+    dir: Option<PathBuf>,
+}
+
+impl CommandTemplate {
+    fn of(cmd: &std::process::Command) -> Self {
+        CommandTemplate {
+            args: cmd.get_args().map(OsStr::to_os_string).collect(),
+            envs: cmd
+                .get_envs()
+                .map(|(key, value)| (key.to_os_string(), value.map(OsStr::to_os_string)))
+                .collect(),
+            dir: cmd.get_current_dir().map(Path::to_path_buf),
+        }
+    }
+
+    /// The command run with `program`, whose directory is put in front of
+    /// PATH so a Node next to it is found
+    fn command(&self, program: &Path) -> Command {
+        let mut cmd = std::process::Command::new(program);
+        cmd.args(&self.args);
+        let mut path = std::env::var_os("PATH").unwrap_or_default();
+        for (key, value) in &self.envs {
+            match value {
+                Some(value) if key == "PATH" => path = value.clone(),
+                Some(value) => {
+                    cmd.env(key, value);
+                }
+                None => {
+                    cmd.env_remove(key);
+                }
+            }
+        }
+        let program_dir: Vec<PathBuf> = program
+            .parent()
+            .filter(|dir| dir.is_absolute())
+            .map(Path::to_path_buf)
+            .into_iter()
+            .collect();
+        cmd.env(
+            "PATH",
+            super::extra_path::path_with_entries(&program_dir, &path),
+        );
+        if let Some(dir) = &self.dir {
+            cmd.current_dir(dir);
+        }
+        cmd.stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        // Lead a new process group so cancelling can stop everything Claude started
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+        Command::from(cmd)
+    }
+}
+
+/// The error listing every binary tried and why each one failed
+fn consolidated_error(failures: &[(String, String)]) -> String {
+    let mut message = String::from("Claude failed to start with every installation tried:");
+    for (path, reason) in failures {
+        message.push_str(&format!("\n- {}: {}", path, reason));
+    }
+    if failures.len() < 2 {
+        message.push_str("\nNo other Claude installations were found.");
+    }
+    message
+}
+
+fn read_persist_setting(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![PERSIST_FALLBACK_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .map(|value| value == "true")
+    .unwrap_or(false)
+}
+
+/// Installations to try in place of `failed`, best first, found by running
+/// discovery again
+async fn fallback_candidates(app: &AppHandle, failed: &OsStr) -> Vec<String> {
+    let handle = app.clone();
+    let installations = tauri::async_runtime::spawn_blocking(move || {
+        super::binary_versions::list_installations(&handle, &handle.state::<AgentDb>(), false)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|installations| installations);
+    let installations = match installations {
+        Ok(installations) => installations,
+        Err(e) => {
+            warn!("Failed to look for other Claude installations: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let canonical =
+        |path: &Path| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let failed = canonical(Path::new(failed));
+    installations
+        .into_iter()
+        .filter(|i| !i.missing && i.installation_type != InstallationType::Wsl)
+        .filter(|i| binary_file(&i.path).is_some_and(|file| canonical(&file) != failed))
+        .map(|i| i.path)
+        .collect()
+}
+
+/// Spawn `cmd`, built for the configured Claude binary. When that fails to
+/// spawn, discovery is run again and the best healthy installation is
+/// spawned instead, emitting `BINARY_FALLBACK_EVENT`. With no working
+/// alternative, the error lists each binary tried and why it failed.
+pub async fn spawn_claude_with_fallback(
+    app: &AppHandle,
+    cmd: Command,
+) -> Result<ClaudeLaunch, String> {
+    let program = cmd.as_std().get_program().to_os_string();
+    let configured = PathBuf::from(&program).to_string_lossy().into_owned();
+    let template = CommandTemplate::of(cmd.as_std());
+    let error = match spawn_claude(app, cmd) {
+        Ok(spawned) => {
+            return Ok(ClaudeLaunch {
+                spawned,
+                binary: configured,
+                fallback: None,
+            })
+        }
+        Err(e) => diagnose_spawn_failure(&program, &e).await,
+    };
+    warn!("{}; looking for another Claude installation", error);
+
+    let mut failures = vec![(configured.clone(), error.clone())];
+    for path in fallback_candidates(app, &program).await {
+        let version = match check_binary_health(&path, &BTreeMap::new(), true).await {
+            BinaryHealth::Ok { version } => version,
+            health => {
+                failures.push((path, health.describe()));
+                continue;
+            }
+        };
+        let Some(file) = binary_file(&path) else {
+            continue;
+        };
+        let cmd = template.command(&file);
+        let checked = super::binary_pin::check_binary_pin(file.clone().into_os_string())
+            .await
+            .and_then(|_| crate::claude_binary::check_shim_runtime(cmd.as_std()));
+        if let Err(e) = checked {
+            failures.push((path, e));
+            continue;
+        }
+        let spawned = match spawn_claude(app, cmd) {
+            Ok(spawned) => spawned,
+            Err(e) => {
+                failures.push((path, format!("Failed to spawn Claude: {}", e)));
+                continue;
+            }
+        };
+
+        let persist = app
+            .state::<AgentDb>()
+            .0
+            .lock()
+            .map(|conn| read_persist_setting(&conn))
+            .unwrap_or(false);
+        let persisted = persist
+            && match super::agents::set_claude_binary_path(app.state::<AgentDb>(), path.clone())
+                .await
+            {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Failed to save fallback Claude binary: {}", e);
+                    false
+                }
+            };
+        info!("Fell back from {} to Claude at {}", configured, path);
+        let fallback = BinaryFallback {
+            failed_path: configured,
+            error,
+            used_path: path.clone(),
+            version,
+            persisted,
+        };
+        let _ = app.emit(BINARY_FALLBACK_EVENT, &fallback);
+        return Ok(ClaudeLaunch {
+            spawned,
+            binary: path,
+            fallback: Some(fallback),
+        });
+    }
+    Err(consolidated_error(&failures))
+}
+
+/// Get whether a binary run after the configured one failed is saved as the
+/// configured binary
+#[tauri::command]
+pub async fn get_persist_binary_fallback(db: State<'_, AgentDb>) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(read_persist_setting(&conn))
+}
+
+/// Set whether a binary run after the configured one failed is saved as the
+/// configured binary
+#[tauri::command]
+pub async fn set_persist_binary_fallback(
+    db: State<'_, AgentDb>,
+    enabled: bool,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![PERSIST_FALLBACK_SETTING_KEY, enabled.to_string()],
+    )
+    .map_err(|e| format!("Failed to save fallback setting: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands_are_retargeted_and_failures_listed() {
+        let mut original = std::process::Command::new("/old/node/bin/claude");
+        original
+            .args(["-p", "hello"])
+            .env("PATH", "/old/node/bin:/usr/bin")
+            .env("CLAUDE_CODE_ENTRYPOINT", "opcode")
+            .current_dir("/tmp");
+        let cmd = CommandTemplate::of(&original).command(Path::new("/new/node/bin/claude"));
+        let cmd = cmd.as_std();
+        assert_eq!(cmd.get_program(), "/new/node/bin/claude");
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["-p", "hello"]);
+        assert_eq!(cmd.get_current_dir(), Some(Path::new("/tmp")));
+        let envs: BTreeMap<_, _> = cmd.get_envs().collect();
+        assert_eq!(
+            envs[OsStr::new("CLAUDE_CODE_ENTRYPOINT")],
+            Some(OsStr::new("opcode"))
+        );
+        assert_eq!(
+            std::env::split_paths(envs[OsStr::new("PATH")].unwrap()).collect::<Vec<_>>(),
+            ["/new/node/bin", "/old/node/bin", "/usr/bin"].map(PathBuf::from)
+        );
+
+        let error = consolidated_error(&[
+            ("/old/claude".to_string(), "No such file".to_string()),
+            ("/usr/bin/claude".to_string(), "Not executable".to_string()),
+        ]);
+        assert!(error.contains("\n- /old/claude: No such file"));
+        assert!(error.contains("\n- /usr/bin/claude: Not executable"));
+        assert!(!error.contains("No other"));
+    }
+}
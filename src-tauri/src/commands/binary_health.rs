@@ -0,0 +1,390 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Output, Stdio};
+use std::time::Duration;
+use tauri::State;
+use tokio::process::Command;
+
+use super::agents::AgentDb;
+use super::claude_env::{apply_claude_env, claude_env_for};
+use super::claude_version::parse_version;
+use crate::claude_binary;
+
+/// How long `--version` may take
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the prompt probe may take, which waits on the API
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What Claude prints when it has no credentials, lowercased
+const AUTH_MARKERS: &[&str] = &[
+    "invalid api key",
+    "please run /login",
+    "not logged in",
+    "login required",
+    "oauth token has expired",
+    "authentication_error",
+];
+
+/// Whether a Claude binary can run, and what's wrong with it if not
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BinaryHealth {
+    Ok {
+        version: String,
+    },
+    Missing {
+        path: String,
+    },
+    NotExecutable {
+        path: String,
+    },
+    /// The program its `#!` line runs is gone, e.g. a removed Node version
+    InterpreterMissing {
+        interpreter: String,
+    },
+    VersionUnparseable {
+        output: String,
+    },
+    AuthRequired {
+        message: String,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl BinaryHealth {
+    /// A sentence explaining the result to the user
+    pub fn describe(&self) -> String {
+        match self {
+            BinaryHealth::Ok { version } => format!("Claude {} works", version),
+            BinaryHealth::Missing { path } => format!("The Claude binary doesn't exist: {}", path),
+            BinaryHealth::NotExecutable { path } => {
+                format!("The Claude binary isn't executable: {}", path)
+            }
+            BinaryHealth::InterpreterMissing { interpreter } => format!(
+                "The Claude binary runs with {}, which is missing, e.g. because its Node version was removed. Reinstall Claude or select another installation.",
+                interpreter
+            ),
+            BinaryHealth::VersionUnparseable { output } => {
+                format!("claude --version printed no version: {}", output)
+            }
+            BinaryHealth::AuthRequired { .. } => {
+                "Claude isn't logged in. Run `claude` in a terminal and log in.".to_string()
+            }
+            BinaryHealth::Failed { message } => format!("The Claude binary failed: {}", message),
+        }
+    }
+}
+
+/// The program a script's `#!` line runs, looking through `env`
+pub fn shebang_interpreter(head: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(head);
+    let line = text.strip_prefix("#!")?.lines().next()?.trim();
+    let mut parts = line.split_whitespace();
+    let program = parts.next()?;
+    if Path::new(program).file_name() == Some(OsStr::new("env")) {
+        parts
+            .find(|part| !part.starts_with('-') && !part.contains('='))
+            .map(str::to_string)
+    } else {
+        Some(program.to_string())
+    }
+}
+
+/// Whether `interpreter` can be found the way the binary will be run: on
+/// PATH, or next to the binary, whose directory is put on PATH for Node
+/// version managers
+fn interpreter_exists(interpreter: &str, binary: &Path) -> bool {
+    if interpreter.contains('/') {
+        return Path::new(interpreter).is_file();
+    }
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    binary
+        .parent()
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(std::env::split_paths(&path))
+        .any(|dir| dir.join(interpreter).is_file())
+}
+
+/// The failure a Claude run's output points to: an interpreter `env`
+/// couldn't find, or missing credentials
+pub fn classify_output(output: &str) -> Option<BinaryHealth> {
+    for line in output.lines() {
+        if let Some((_, rest)) = line.split_once("env:") {
+            if rest.contains("No such file or directory") {
+                let interpreter = rest
+                    .split(':')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .trim_matches(|c| matches!(c, '\'' | '"' | '‘' | '’'));
+                return Some(BinaryHealth::InterpreterMissing {
+                    interpreter: interpreter.to_string(),
+                });
+            }
+        }
+    }
+    let lower = output.to_lowercase();
+    AUTH_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+        .then(|| BinaryHealth::AuthRequired {
+            message: output.trim().to_string(),
+        })
+}
+
+/// Check the file itself: that it exists, is executable, and that a
+/// script's interpreter is there
+fn check_file(path: &Path) -> Option<BinaryHealth> {
+    let display = path.to_string_lossy().into_owned();
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Some(BinaryHealth::Missing { path: display }),
+    };
+    if !metadata.is_file() {
+        return Some(BinaryHealth::NotExecutable { path: display });
+    }
+
+    #[cfg(unix)]
+    {
+        use std::io::Read;
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Some(BinaryHealth::NotExecutable { path: display });
+        }
+        let mut head = [0u8; 256];
+        let read = std::fs::File::open(path)
+            .and_then(|mut file| file.read(&mut head))
+            .unwrap_or(0);
+        if let Some(interpreter) = shebang_interpreter(&head[..read]) {
+            if !interpreter_exists(&interpreter, path) {
+                return Some(BinaryHealth::InterpreterMissing { interpreter });
+            }
+        }
+    }
+    None
+}
+
+/// Run the binary with `args`, killing it after `timeout`
+async fn run_probe(
+    path: &Path,
+    args: &[&str],
+    env: &BTreeMap<String, String>,
+    timeout: Duration,
+) -> Result<Output, BinaryHealth> {
+    let mut cmd = Command::from(claude_binary::create_command_with_env(
+        &path.to_string_lossy(),
+    ));
+    apply_claude_env(cmd.as_std_mut(), env);
+    cmd.args(args).stdin(Stdio::null()).kill_on_drop(true);
+    match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(Ok(output)) => Ok(output),
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            Err(BinaryHealth::NotExecutable {
+                path: path.to_string_lossy().into_owned(),
+            })
+        }
+        Ok(Err(e)) => Err(BinaryHealth::Failed {
+            message: e.to_string(),
+        }),
+        Err(_) => Err(BinaryHealth::Failed {
+            message: format!(
+                "claude {} didn't finish within {} seconds",
+                args.join(" "),
+                timeout.as_secs()
+            ),
+        }),
+    }
+}
+
+/// Diagnose the Claude binary at `path` (or `"bundled"`): the file, then
+/// `--version`, then unless `skip_prompt` a one-turn prompt, which is what
+/// reveals missing credentials
+pub async fn check_binary_health(
+    path: &str,
+    env: &BTreeMap<String, String>,
+    skip_prompt: bool,
+) -> BinaryHealth {
+    let Some(file) = claude_binary::binary_file(path) else {
+        return BinaryHealth::Missing {
+            path: path.to_string(),
+        };
+    };
+    if let Some(problem) = check_file(&file) {
+        return problem;
+    }
+
+    let output = match run_probe(&file, &["--version"], env, VERSION_TIMEOUT).await {
+        Ok(output) => output,
+        Err(problem) => return problem,
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    if !output.status.success() {
+        return classify_output(&stderr).unwrap_or_else(|| BinaryHealth::Failed {
+            message: format!("--version exited with {}: {}", output.status, stderr.trim()),
+        });
+    }
+    let Some(version) = parse_version(&stdout) else {
+        return BinaryHealth::VersionUnparseable {
+            output: format!("{}{}", stdout, stderr).trim().to_string(),
+        };
+    };
+    if skip_prompt {
+        return BinaryHealth::Ok {
+            version: version.raw,
+        };
+    }
+
+    let output = match run_probe(
+        &file,
+        &["-p", "ping", "--max-turns", "1"],
+        env,
+        PROMPT_TIMEOUT,
+    )
+    .await
+    {
+        Ok(output) => output,
+        Err(problem) => return problem,
+    };
+    let combined = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    if let Some(problem) = classify_output(&combined) {
+        return problem;
+    }
+    if !output.status.success() {
+        return BinaryHealth::Failed {
+            message: format!(
+                "A test prompt exited with {}: {}",
+                output.status,
+                combined.trim()
+            ),
+        };
+    }
+    BinaryHealth::Ok {
+        version: version.raw,
+    }
+}
+
+/// The error for a Claude binary that couldn't be spawned, with a diagnosis
+/// of the binary when it's at fault
+pub async fn diagnose_spawn_failure(path: impl AsRef<OsStr>, error: &std::io::Error) -> String {
+    let message = format!("Failed to spawn Claude: {}", error);
+    let path = PathBuf::from(path.as_ref()).to_string_lossy().into_owned();
+    match check_binary_health(&path, &BTreeMap::new(), true).await {
+        BinaryHealth::Ok { .. } => message,
+        health => {
+            log::warn!("Claude binary {} is unhealthy: {:?}", path, health);
+            format!("{}. {}", message, health.describe())
+        }
+    }
+}
+
+/// Check whether the Claude binary at `path` can run, with the saved Claude
+/// environment. `skip_prompt` leaves out the test prompt, which uses the API.
+#[tauri::command]
+pub async fn check_claude_binary_health(
+    db: State<'_, AgentDb>,
+    path: String,
+    skip_prompt: Option<bool>,
+) -> Result<BinaryHealth, String> {
+    let env = claude_env_for(&db, None);
+    Ok(check_binary_health(&path, &env, skip_prompt.unwrap_or(false)).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shebang_interpreter_looks_through_env() {
+        assert_eq!(
+            shebang_interpreter(b"#!/usr/bin/env node\nconsole.log(1)").as_deref(),
+            Some("node")
+        );
+        assert_eq!(
+            shebang_interpreter(b"#!/usr/bin/env -S NODE_OPTIONS=x node --no-warnings\n")
+                .as_deref(),
+            Some("node")
+        );
+        assert_eq!(
+            shebang_interpreter(b"#!/home/me/.nvm/versions/node/v18.0.0/bin/node\n").as_deref(),
+            Some("/home/me/.nvm/versions/node/v18.0.0/bin/node")
+        );
+        assert_eq!(shebang_interpreter(b"\x7fELF\x02\x01"), None);
+    }
+
+    #[test]
+    fn test_output_is_classified() {
+        assert_eq!(
+            classify_output("/usr/bin/env: ‘node’: No such file or directory"),
+            Some(BinaryHealth::InterpreterMissing {
+                interpreter: "node".to_string()
+            })
+        );
+        assert_eq!(
+            classify_output("env: node: No such file or directory\n"),
+            Some(BinaryHealth::InterpreterMissing {
+                interpreter: "node".to_string()
+            })
+        );
+        assert!(matches!(
+            classify_output("Invalid API key · Please run /login"),
+            Some(BinaryHealth::AuthRequired { .. })
+        ));
+        assert_eq!(classify_output("pong"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_file_problems_are_found_before_running() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = BTreeMap::new();
+        let missing = dir.path().join("claude");
+        assert_eq!(
+            check_binary_health(&missing.to_string_lossy(), &env, true).await,
+            BinaryHealth::Missing {
+                path: missing.to_string_lossy().into_owned()
+            }
+        );
+
+        let script = dir.path().join("claude");
+        std::fs::write(&script, "#!/nonexistent/node\n").unwrap();
+        assert!(matches!(
+            check_binary_health(&script.to_string_lossy(), &env, true).await,
+            BinaryHealth::NotExecutable { .. }
+        ));
+
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            check_binary_health(&script.to_string_lossy(), &env, true).await,
+            BinaryHealth::InterpreterMissing {
+                interpreter: "/nonexistent/node".to_string()
+            }
+        );
+
+        std::fs::write(&script, "#!/bin/sh\necho 'Claude Code'\n").unwrap();
+        assert!(matches!(
+            check_binary_health(&script.to_string_lossy(), &env, true).await,
+            BinaryHealth::VersionUnparseable { .. }
+        ));
+
+        std::fs::write(&script, "#!/bin/sh\necho '2.0.1 (Claude Code)'\n").unwrap();
+        assert_eq!(
+            check_binary_health(&script.to_string_lossy(), &env, true).await,
+            BinaryHealth::Ok {
+                version: "2.0.1".to_string()
+            }
+        );
+    }
+}
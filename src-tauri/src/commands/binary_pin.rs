@@ -0,0 +1,224 @@
+use log::warn;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::SystemTime;
+use tauri::State;
+
+use super::agents::AgentDb;
+use crate::claude_binary::binary_file;
+
+/// `app_settings` key holding the pinned checksum as JSON
+const BINARY_PIN_SETTING_KEY: &str = "claude_binary_pin";
+
+/// Prefix of the error returned when a pinned binary has changed
+pub const CHECKSUM_MISMATCH_ERROR: &str = "binary_checksum_mismatch";
+
+/// The SHA-256 a Claude binary must have to be launched
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BinaryPin {
+    /// The binary as it was selected when pinned
+    pub path: String,
+    pub sha256: String,
+    pub pinned_at: String,
+}
+
+/// A file's hash along with the modification time and size it was taken at
+struct HashedFile {
+    modified: Option<SystemTime>,
+    len: u64,
+    sha256: String,
+}
+
+fn pin_lock() -> &'static RwLock<Option<BinaryPin>> {
+    static PIN: OnceLock<RwLock<Option<BinaryPin>>> = OnceLock::new();
+    PIN.get_or_init(|| RwLock::new(None))
+}
+
+fn hash_cache() -> &'static Mutex<HashMap<PathBuf, HashedFile>> {
+    static HASHES: OnceLock<Mutex<HashMap<PathBuf, HashedFile>>> = OnceLock::new();
+    HASHES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The SHA-256 of `file`, only read again when its modification time or
+/// size changed since it was last hashed
+pub fn file_sha256(file: &Path) -> Result<String, String> {
+    let file = std::fs::canonicalize(file)
+        .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let metadata = std::fs::metadata(&file)
+        .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let modified = metadata.modified().ok();
+    if let Ok(cache) = hash_cache().lock() {
+        if let Some(hashed) = cache.get(&file) {
+            if hashed.modified.is_some()
+                && hashed.modified == modified
+                && hashed.len == metadata.len()
+            {
+                return Ok(hashed.sha256.clone());
+            }
+        }
+    }
+
+    let mut reader = std::fs::File::open(&file)
+        .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut reader, &mut hasher)
+        .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let sha256 = format!("{:x}", hasher.finalize());
+    if let Ok(mut cache) = hash_cache().lock() {
+        cache.insert(
+            file,
+            HashedFile {
+                modified,
+                len: metadata.len(),
+                sha256: sha256.clone(),
+            },
+        );
+    }
+    Ok(sha256)
+}
+
+/// The file `program` runs, searching PATH for a bare name
+fn program_file(program: &OsStr) -> PathBuf {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.to_path_buf();
+    }
+    std::env::split_paths(&super::extra_path::extended_path())
+        .map(|dir| dir.join(path))
+        .find(|candidate| candidate.is_file())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Ok when `file` still has the pinned hash
+fn verify_file(pin: &BinaryPin, file: &Path) -> Result<(), String> {
+    let sha256 = file_sha256(file)?;
+    if sha256 == pin.sha256 {
+        return Ok(());
+    }
+    Err(format!(
+        "{}: {} doesn't match the checksum pinned on {} (expected sha256 {}, found {}). \
+         Re-pin the binary or clear the pin to launch it.",
+        CHECKSUM_MISMATCH_ERROR,
+        file.display(),
+        pin.pinned_at,
+        pin.sha256,
+        sha256
+    ))
+}
+
+/// Refuse to launch `program` when a checksum is pinned and the file differs.
+/// Does nothing unless a pin was set.
+pub async fn check_binary_pin(program: OsString) -> Result<(), String> {
+    let pin = match pin_lock().read().ok().and_then(|pin| pin.clone()) {
+        Some(pin) => pin,
+        None => return Ok(()),
+    };
+    tauri::async_runtime::spawn_blocking(move || verify_file(&pin, &program_file(&program)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn read_pin(conn: &Connection) -> Option<BinaryPin> {
+    let json = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![BINARY_PIN_SETTING_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()?;
+    match serde_json::from_str(&json) {
+        Ok(pin) => Some(pin),
+        Err(e) => {
+            warn!("Ignoring invalid Claude binary pin: {}", e);
+            None
+        }
+    }
+}
+
+/// Load the pinned checksum
+pub fn load_binary_pin(conn: &Connection) {
+    let pin = read_pin(conn);
+    if let Ok(mut current) = pin_lock().write() {
+        *current = pin;
+    }
+}
+
+/// Get the pinned checksum, if any
+#[tauri::command]
+pub async fn get_claude_binary_pin(db: State<'_, AgentDb>) -> Result<Option<BinaryPin>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(read_pin(&conn))
+}
+
+/// Pin the SHA-256 of the binary at `path`; launches are refused from then on
+/// if the binary run doesn't match it
+#[tauri::command]
+pub async fn pin_claude_binary_checksum(
+    db: State<'_, AgentDb>,
+    path: String,
+) -> Result<BinaryPin, String> {
+    let file = binary_file(&path).ok_or_else(|| "No bundled Claude binary found".to_string())?;
+    let file = program_file(file.as_os_str());
+    let sha256 = tauri::async_runtime::spawn_blocking(move || file_sha256(&file))
+        .await
+        .map_err(|e| e.to_string())??;
+    let pin = BinaryPin {
+        path,
+        sha256,
+        pinned_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let json = serde_json::to_string(&pin).map_err(|e| e.to_string())?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![BINARY_PIN_SETTING_KEY, json],
+    )
+    .map_err(|e| format!("Failed to save Claude binary pin: {}", e))?;
+    load_binary_pin(&conn);
+    Ok(pin)
+}
+
+/// Remove the pinned checksum so any binary can be launched again
+#[tauri::command]
+pub async fn clear_claude_binary_pin(db: State<'_, AgentDb>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM app_settings WHERE key = ?1",
+        params![BINARY_PIN_SETTING_KEY],
+    )
+    .map_err(|e| format!("Failed to clear Claude binary pin: {}", e))?;
+    load_binary_pin(&conn);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_binary_fails_the_pin() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("claude");
+        std::fs::write(&binary, "#!/bin/sh\necho 1.0.0\n").unwrap();
+        let pin = BinaryPin {
+            path: binary.display().to_string(),
+            sha256: file_sha256(&binary).unwrap(),
+            pinned_at: "2025-01-01T00:00:00Z".to_string(),
+        };
+        assert!(verify_file(&pin, &binary).is_ok());
+
+        // A different size changes the hash even if the mtime looks the same
+        std::fs::write(&binary, "#!/bin/sh\necho swapped\n").unwrap();
+        let error = verify_file(&pin, &binary).unwrap_err();
+        assert!(error.starts_with(CHECKSUM_MISMATCH_ERROR));
+
+        assert!(verify_file(&pin, &dir.path().join("missing")).is_err());
+    }
+}
@@ -0,0 +1,413 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::claude_binary::{self, ClaudeInstallation, InstallationType, VersionOutput};
+use crate::commands::agents::AgentDb;
+
+/// Emitted with the installation list once stale versions were probed again
+pub const INSTALLATIONS_UPDATED_EVENT: &str = "claude-installations-updated";
+
+/// How long a version is trusted while its binary is unchanged
+const VERSION_CACHE_TTL_SECONDS: i64 = 24 * 60 * 60;
+
+/// Modification time and size of a binary, which change when it's replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryFingerprint {
+    pub mtime: i64,
+    pub size: i64,
+}
+
+impl BinaryFingerprint {
+    /// The fingerprint of the file `path` leads to
+    pub fn of(path: &str) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let mtime = metadata
+            .modified()
+            .ok()?
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_secs() as i64;
+        Some(BinaryFingerprint {
+            mtime,
+            size: metadata.len() as i64,
+        })
+    }
+}
+
+/// A cached `--version` result. It's stale once the binary changed or the
+/// result is older than a day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedVersion {
+    pub output: VersionOutput,
+    pub fresh: bool,
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+pub fn lookup_version(
+    conn: &Connection,
+    path: &str,
+    fingerprint: BinaryFingerprint,
+    now: i64,
+) -> Result<Option<CachedVersion>, String> {
+    let row = conn
+        .query_row(
+            "SELECT mtime, size, stdout, stderr, success, checked_at
+             FROM claude_version_cache WHERE path = ?1",
+            params![path],
+            |row| {
+                Ok((
+                    BinaryFingerprint {
+                        mtime: row.get(0)?,
+                        size: row.get(1)?,
+                    },
+                    VersionOutput {
+                        stdout: row.get(2)?,
+                        stderr: row.get(3)?,
+                        success: row.get(4)?,
+                    },
+                    row.get::<_, i64>(5)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(row.map(|(cached, output, checked_at)| CachedVersion {
+        output,
+        fresh: cached == fingerprint && now - checked_at <= VERSION_CACHE_TTL_SECONDS,
+    }))
+}
+
+pub fn store_version(
+    conn: &Connection,
+    path: &str,
+    fingerprint: BinaryFingerprint,
+    output: &VersionOutput,
+    now: i64,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO claude_version_cache (path, mtime, size, stdout, stderr, success, checked_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(path) DO UPDATE SET mtime = ?2, size = ?3, stdout = ?4, stderr = ?5,
+             success = ?6, checked_at = ?7",
+        params![
+            path,
+            fingerprint.mtime,
+            fingerprint.size,
+            output.stdout,
+            output.stderr,
+            output.success,
+            now
+        ],
+    )
+    .map_err(|e| format!("Failed to cache Claude version: {}", e))?;
+    Ok(())
+}
+
+/// Remove cached binaries that are gone from disk, returning them with the
+/// version last seen so the next listing can flag them
+pub fn evict_missing(conn: &Connection) -> Result<Vec<ClaudeInstallation>, String> {
+    let mut stmt = conn
+        .prepare("SELECT path, stdout, success FROM claude_version_cache ORDER BY path")
+        .map_err(|e| e.to_string())?;
+    let cached = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                VersionOutput {
+                    stdout: row.get(1)?,
+                    success: row.get(2)?,
+                    ..Default::default()
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut missing = Vec::new();
+    for (path, output) in cached {
+        if Path::new(&path).is_file() {
+            continue;
+        }
+        conn.execute(
+            "DELETE FROM claude_version_cache WHERE path = ?1",
+            params![path],
+        )
+        .map_err(|e| e.to_string())?;
+        missing.push(ClaudeInstallation {
+            resolved_path: Some(path.clone()),
+            path,
+            version: output.version(),
+            source: "cache".to_string(),
+            installation_type: InstallationType::System,
+            missing: true,
+            runtime: None,
+        });
+    }
+    Ok(missing)
+}
+
+/// The path an installation's version is cached under: the binary its path
+/// leads to. WSL installations aren't cached.
+fn cache_key(installation: &ClaudeInstallation) -> Option<&str> {
+    if installation.installation_type == InstallationType::Wsl {
+        return None;
+    }
+    installation
+        .resolved_path
+        .as_deref()
+        .or(Some(installation.path.as_str()))
+}
+
+/// Fill in cached versions, returning the indexes of installations whose
+/// version is stale or unknown
+fn apply_cached_versions(
+    conn: &Connection,
+    installations: &mut [ClaudeInstallation],
+    now: i64,
+) -> Result<Vec<usize>, String> {
+    let mut stale = Vec::new();
+    for (index, installation) in installations.iter_mut().enumerate() {
+        let Some(key) = cache_key(installation) else {
+            continue;
+        };
+        let Some(fingerprint) = BinaryFingerprint::of(key) else {
+            continue;
+        };
+        match lookup_version(conn, key, fingerprint, now)? {
+            Some(cached) => {
+                installation.version = cached.output.version();
+                if !cached.fresh {
+                    stale.push(index);
+                }
+            }
+            None => stale.push(index),
+        }
+    }
+    Ok(stale)
+}
+
+/// Run `--version` for the installations at `indexes` in parallel and cache
+/// the results
+fn probe_and_store(db: &AgentDb, installations: &mut [ClaudeInstallation], indexes: &[usize]) {
+    let probed: Vec<(usize, Option<VersionOutput>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = indexes
+            .iter()
+            .map(|&index| {
+                let path = installations[index].path.clone();
+                (
+                    index,
+                    scope.spawn(move || claude_binary::run_version_command(&path)),
+                )
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|(index, handle)| (index, handle.join().ok().flatten()))
+            .collect()
+    });
+
+    let now = now();
+    let conn = match db.0.lock() {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::warn!("Not caching Claude versions: {}", e);
+            return;
+        }
+    };
+    for (index, output) in probed {
+        let installation = &mut installations[index];
+        installation.version = output.as_ref().and_then(VersionOutput::version);
+        let (Some(output), Some(key)) = (output, cache_key(installation)) else {
+            continue;
+        };
+        if let Some(fingerprint) = BinaryFingerprint::of(key) {
+            if let Err(e) = store_version(&conn, key, fingerprint, &output, now) {
+                log::warn!("{}", e);
+            }
+        }
+    }
+}
+
+/// Claude installations with cached versions. Stale versions are probed
+/// again in the background, after which `INSTALLATIONS_UPDATED_EVENT` is
+/// emitted with the updated list; with `force_refresh` every version is
+/// probed before returning. Cached binaries gone from disk are listed once
+/// more, flagged as missing.
+pub fn list_installations(
+    app: &AppHandle,
+    db: &AgentDb,
+    force_refresh: bool,
+) -> Result<Vec<ClaudeInstallation>, String> {
+    let mut installations = claude_binary::find_installation_candidates();
+    let (stale, missing) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let missing = evict_missing(&conn)?;
+        let stale = if force_refresh {
+            (0..installations.len())
+                .filter(|&index| cache_key(&installations[index]).is_some())
+                .collect()
+        } else {
+            apply_cached_versions(&conn, &mut installations, now())?
+        };
+        (stale, missing)
+    };
+
+    if force_refresh {
+        probe_and_store(db, &mut installations, &stale);
+    } else if !stale.is_empty() {
+        let app = app.clone();
+        let mut refreshed = installations.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            probe_and_store(&app.state::<AgentDb>(), &mut refreshed, &stale);
+            claude_binary::sort_installations(&mut refreshed);
+            let _ = app.emit(INSTALLATIONS_UPDATED_EVENT, &refreshed);
+        });
+    }
+
+    installations.extend(missing);
+    claude_binary::sort_installations(&mut installations);
+    Ok(installations)
+}
+
+/// `--version` output of the binary at `path`, cached like installation
+/// versions. A stale result is returned while it's refreshed in the
+/// background; None when the binary can't be run.
+pub fn version_output(
+    app: &AppHandle,
+    db: &AgentDb,
+    path: &str,
+    force_refresh: bool,
+) -> Option<VersionOutput> {
+    let Some(fingerprint) = BinaryFingerprint::of(path) else {
+        // A bare name found on PATH can't be fingerprinted
+        return claude_binary::run_version_command(path);
+    };
+    let cached = if force_refresh {
+        None
+    } else {
+        db.0.lock()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| lookup_version(&conn, path, fingerprint, now()))
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to read cached Claude version: {}", e);
+                None
+            })
+    };
+
+    let probe = move |db: &AgentDb, path: &str| {
+        let output = claude_binary::run_version_command(path)?;
+        let stored =
+            db.0.lock()
+                .map_err(|e| e.to_string())
+                .and_then(|conn| store_version(&conn, path, fingerprint, &output, now()));
+        if let Err(e) = stored {
+            log::warn!("{}", e);
+        }
+        Some(output)
+    };
+    match cached {
+        Some(cached) if cached.fresh => Some(cached.output),
+        Some(cached) => {
+            let app = app.clone();
+            let path = path.to_string();
+            tauri::async_runtime::spawn_blocking(move || {
+                probe(&app.state::<AgentDb>(), &path);
+            });
+            Some(cached.output)
+        }
+        None => probe(db, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(stdout: &str) -> VersionOutput {
+        VersionOutput {
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            success: true,
+        }
+    }
+
+    #[test]
+    fn test_cached_versions_go_stale_when_the_binary_changes_or_ages() {
+        let conn = crate::db::test_connection();
+        let binary = BinaryFingerprint {
+            mtime: 1_700_000_000,
+            size: 1024,
+        };
+        let now = 1_700_000_100;
+        assert_eq!(
+            lookup_version(&conn, "/bin/claude", binary, now).unwrap(),
+            None
+        );
+
+        store_version(
+            &conn,
+            "/bin/claude",
+            binary,
+            &output("1.0.41 (Claude Code)"),
+            now,
+        )
+        .unwrap();
+        let cached = lookup_version(&conn, "/bin/claude", binary, now + 60)
+            .unwrap()
+            .unwrap();
+        assert!(cached.fresh);
+        assert_eq!(cached.output.version().as_deref(), Some("1.0.41"));
+
+        let upgraded = BinaryFingerprint {
+            mtime: binary.mtime + 5,
+            ..binary
+        };
+        assert!(
+            !lookup_version(&conn, "/bin/claude", upgraded, now)
+                .unwrap()
+                .unwrap()
+                .fresh
+        );
+        let tomorrow = now + VERSION_CACHE_TTL_SECONDS + 1;
+        assert!(
+            !lookup_version(&conn, "/bin/claude", binary, tomorrow)
+                .unwrap()
+                .unwrap()
+                .fresh
+        );
+    }
+
+    #[test]
+    fn test_binaries_gone_from_disk_are_evicted_and_flagged() {
+        let conn = crate::db::test_connection();
+        let dir = tempfile::tempdir().unwrap();
+        let present = dir.path().join("claude");
+        fs::write(&present, "").unwrap();
+        let present = present.to_string_lossy().into_owned();
+        let gone = dir.path().join("old-claude").to_string_lossy().into_owned();
+        let fingerprint = BinaryFingerprint { mtime: 1, size: 1 };
+        for path in [&present, &gone] {
+            store_version(&conn, path, fingerprint, &output("1.0.3 (Claude Code)"), 0).unwrap();
+        }
+
+        let missing = evict_missing(&conn).unwrap();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].path, gone);
+        assert!(missing[0].missing);
+        assert_eq!(missing[0].version.as_deref(), Some("1.0.3"));
+        assert!(evict_missing(&conn).unwrap().is_empty());
+        assert!(lookup_version(&conn, &present, fingerprint, 0)
+            .unwrap()
+            .is_some());
+    }
+}
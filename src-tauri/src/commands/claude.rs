@@ -1,25 +1,60 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
+use rusqlite::OptionalExtension;
 use tauri::{AppHandle, Emitter, Manager};
-use tokio::process::{Child, Command};
+use tokio::process::Command;
 use tokio::sync::Mutex;
 
-
-/// Global state to track current Claude process
+use super::attachments::{prepare_attachments, IncludedAttachment};
+use super::binary_fallback::spawn_claude_with_fallback;
+use super::binary_pin::check_binary_pin;
+use super::claude_env::{apply_claude_env, claude_env_for, effective_env};
+use super::claude_version::{
+    assess_version, check_output_format_supported, installed_claude_version,
+    latest_published_version, parse_version, ClaudeVersion, COMPACT_MINIMUM_VERSION,
+    MINIMUM_CLAUDE_VERSION,
+};
+use super::extra_path::apply_extra_path;
+use super::claude_settings::{
+    read_settings, restore_backup, save_settings, write_settings, ClaudeSettings,
+    SettingsSaveResult,
+};
+use super::directory_listing::{self, DirectoryListing, DirectoryListingOptions};
+use super::file_search::{self, FileSearchOptions, FileSearchResult};
+use super::memory_files::{read_memory_file, write_memory_file, MemoryFileContent, MemoryFileSave};
+use super::live_usage::{LiveSessionUsage, LiveUsageState, SESSION_USAGE_EVENT};
+use super::claude_sessions::{
+    get_max_claude_sessions_setting, ClaudeSessionProcess, ClaudeSessions, QueuedPrompt,
+};
+use super::project_prompts::project_system_prompt_for;
+use super::project_settings::{resolve_claude_binary, resolve_model};
+use super::prompt_history::record_prompt_or_warn;
+use super::session_autotitle::AutoTitleWatch;
+use super::session_options::{ClaudeSessionOptions, OutputFormat};
+use super::settings_history::record_snapshot_or_warn;
+use crate::process::{OutputChunk, WithResources, MAX_OUTPUT_CHUNK_BYTES};
+
+
+/// How long a cancelled Claude session may take to exit before its process
+/// group is force-killed
+const CANCEL_GRACE: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Global state to track the running interactive Claude processes
 pub struct ClaudeProcessState {
-    pub current_process: Arc<Mutex<Option<Child>>>,
+    pub sessions: Arc<Mutex<ClaudeSessions>>,
 }
 
 impl Default for ClaudeProcessState {
     fn default() -> Self {
         Self {
-            current_process: Arc::new(Mutex::new(None)),
+            sessions: Arc::new(Mutex::new(ClaudeSessions::default())),
         }
     }
 }
@@ -35,8 +70,16 @@ pub struct Project {
     pub sessions: Vec<String>,
     /// Unix timestamp when the project directory was created
     pub created_at: u64,
-    /// Unix timestamp of the most recent session (if any)
-    pub most_recent_session: Option<u64>,
+    /// Number of sessions (JSONL files)
+    pub session_count: usize,
+    /// Unix timestamp of the most recently modified session (if any)
+    pub last_session_at: Option<u64>,
+    /// Combined size of the session files
+    pub total_size_bytes: u64,
+    /// Whether the project is archived in the app (hidden unless asked for)
+    pub archived: bool,
+    /// Whether the project's directory no longer exists on disk
+    pub missing: bool,
 }
 
 /// Represents a session with its metadata
@@ -56,6 +99,29 @@ pub struct Session {
     pub first_message: Option<String>,
     /// Timestamp of the first user message (if available)
     pub message_timestamp: Option<String>,
+    /// Title given to the session in the app, if any
+    pub custom_title: Option<String>,
+    /// Whether the session is pinned; pinned sessions are listed first
+    pub pinned: bool,
+}
+
+/// A page of a project's sessions, newest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPage {
+    pub sessions: Vec<Session>,
+    /// Number of sessions in the project, across all pages
+    pub total_count: usize,
+}
+
+/// What `delete_session` removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedSession {
+    /// Files and directories removed, as they were named before removal
+    pub removed_paths: Vec<String>,
+    /// Where they were moved, when moved to the trash rather than deleted
+    pub trash_path: Option<String>,
+    /// Whether checkpoints of the session exist; they are left in place
+    pub has_checkpoints: bool,
 }
 
 /// Represents a message entry in the JSONL file
@@ -75,23 +141,8 @@ struct MessageContent {
     content: Option<String>,
 }
 
-/// Represents the settings from ~/.claude/settings.json
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ClaudeSettings {
-    #[serde(flatten)]
-    pub data: serde_json::Value,
-}
-
-impl Default for ClaudeSettings {
-    fn default() -> Self {
-        Self {
-            data: serde_json::json!({}),
-        }
-    }
-}
-
 /// Represents the Claude Code version status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ClaudeVersionStatus {
     /// Whether Claude Code is installed and working
     pub is_installed: bool,
@@ -99,9 +150,20 @@ pub struct ClaudeVersionStatus {
     pub version: Option<String>,
     /// The full output from the command
     pub output: String,
+    /// The version, parsed
+    pub parsed: Option<ClaudeVersion>,
+    /// The output was read but held no recognizable version
+    pub parse_failed: bool,
+    /// Oldest version supporting the flags opcode uses
+    pub minimum_version: String,
+    /// The installed version is older than `minimum_version`
+    pub below_minimum: bool,
+    /// Latest version on npm, when checked
+    pub latest_version: Option<String>,
+    pub update_available: bool,
 }
 
-/// Represents a CLAUDE.md file found in the project
+/// Represents a memory file (CLAUDE.md or CLAUDE.local.md) Claude loads for the project
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClaudeMdFile {
     /// Relative path from the project root
@@ -112,6 +174,8 @@ pub struct ClaudeMdFile {
     pub size: u64,
     /// Last modified timestamp
     pub modified: u64,
+    /// Whether the file is the user's, the project's, a local one or a parent directory's
+    pub scope: super::memory_files::MemoryScope,
 }
 
 /// Represents a file or directory entry
@@ -136,7 +200,7 @@ fn find_claude_binary(app_handle: &AppHandle) -> Result<String, String> {
 }
 
 /// Gets the path to the ~/.claude directory
-fn get_claude_dir() -> Result<PathBuf> {
+pub(crate) fn get_claude_dir() -> Result<PathBuf> {
     dirs::home_dir()
         .context("Could not find home directory")?
         .join(".claude")
@@ -144,38 +208,9 @@ fn get_claude_dir() -> Result<PathBuf> {
         .context("Could not find ~/.claude directory")
 }
 
-/// Gets the actual project path by reading the cwd from the first JSONL entry
-fn get_project_path_from_sessions(project_dir: &PathBuf) -> Result<String, String> {
-    // Try to read any JSONL file in the directory
-    let entries = fs::read_dir(project_dir)
-        .map_err(|e| format!("Failed to read project directory: {}", e))?;
-
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-                // Read the first line of the JSONL file
-                if let Ok(file) = fs::File::open(&path) {
-                    let reader = BufReader::new(file);
-                    if let Some(Ok(first_line)) = reader.lines().next() {
-                        // Parse the JSON and extract cwd
-                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&first_line) {
-                            if let Some(cwd) = json.get("cwd").and_then(|v| v.as_str()) {
-                                return Ok(cwd.to_string());
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Err("Could not determine project path from session files".to_string())
-}
-
 /// Decodes a project directory name back to its original path
 /// The directory names in ~/.claude/projects are encoded paths
-/// DEPRECATED: Use get_project_path_from_sessions instead when possible
+/// DEPRECATED: Use project_dirs::resolve_project_path instead when possible
 fn decode_project_path(encoded: &str) -> String {
     // This is a fallback - the encoding isn't reversible when paths contain hyphens
     // For example: -Users-mufeedvh-dev-jsonl-viewer could be /Users/mufeedvh/dev/jsonl-viewer
@@ -183,15 +218,8 @@ fn decode_project_path(encoded: &str) -> String {
     encoded.replace('-', "/")
 }
 
-/// Extracts the first valid user message from a JSONL file
-fn extract_first_user_message(jsonl_path: &PathBuf) -> (Option<String>, Option<String>) {
-    let file = match fs::File::open(jsonl_path) {
-        Ok(file) => file,
-        Err(_) => return (None, None),
-    };
-
-    let reader = BufReader::new(file);
-
+/// Extracts the first valid user message and its timestamp from JSONL lines
+pub(crate) fn first_user_message<R: BufRead>(reader: R) -> (Option<String>, Option<String>) {
     for line in reader.lines() {
         if let Ok(line) = line {
             if let Ok(entry) = serde_json::from_str::<JsonlEntry>(&line) {
@@ -279,13 +307,17 @@ fn create_command_with_env(program: &str) -> Command {
     tokio_cmd
 }
 
-/// Creates a system binary command with the given arguments
-fn create_system_command(
+/// Creates a system binary command with the given arguments and `env`, the
+/// variables from the Claude environment settings
+pub(crate) fn create_system_command(
     claude_path: &str,
     args: Vec<String>,
     project_path: &str,
+    env: &BTreeMap<String, String>,
 ) -> Command {
     let mut cmd = create_command_with_env(claude_path);
+    apply_claude_env(cmd.as_std_mut(), env);
+    apply_extra_path(cmd.as_std_mut());
     
     // Add all arguments
     for arg in args {
@@ -295,6 +327,10 @@ fn create_system_command(
     cmd.current_dir(project_path)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
+
+    // Lead a new process group so cancelling can stop everything Claude started
+    #[cfg(unix)]
+    cmd.process_group(0);
     
     cmd
 }
@@ -308,10 +344,36 @@ pub async fn get_home_directory() -> Result<String, String> {
 }
 
 
-/// Lists all projects in the ~/.claude/projects directory
+/// Lists all projects in the ~/.claude/projects directory. Archived projects
+/// are left out unless `include_archived` is set.
+///
+/// Only file metadata is read, apart from the start of a session used to
+/// find the project path once per change to the project directory. Projects are sorted by `sort_by` (`recent`, the
+/// default, `name` or `sessions`) before `offset` and `limit` are applied.
 #[tauri::command]
-pub async fn list_projects() -> Result<Vec<Project>, String> {
+pub async fn list_projects(
+    db: tauri::State<'_, super::agents::AgentDb>,
+    include_archived: Option<bool>,
+    sort_by: Option<String>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<Vec<Project>, String> {
     log::info!("Listing projects from ~/.claude/projects");
+    let sort_by = sort_by.unwrap_or_else(|| "recent".to_string());
+    if !matches!(sort_by.as_str(), "recent" | "name" | "sessions") {
+        return Err(format!(
+            "Unsupported sort '{}', expected recent, name or sessions",
+            sort_by
+        ));
+    }
+    let include_archived = include_archived.unwrap_or(false);
+    let (archived_projects, project_dirs) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (
+            super::project_archive::load_archived_projects(&conn)?,
+            super::project_dirs::load_project_dirs(&conn)?,
+        )
+    };
 
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
     let projects_dir = claude_dir.join("projects");
@@ -337,6 +399,11 @@ pub async fn list_projects() -> Result<Vec<Project>, String> {
                 .and_then(|n| n.to_str())
                 .ok_or_else(|| "Invalid directory name".to_string())?;
 
+            let archived = archived_projects.contains(dir_name);
+            if archived && !include_archived {
+                continue;
+            }
+
             // Get directory creation time
             let metadata = fs::metadata(&path)
                 .map_err(|e| format!("Failed to read directory metadata: {}", e))?;
@@ -349,44 +416,44 @@ pub async fn list_projects() -> Result<Vec<Project>, String> {
                 .unwrap_or_default()
                 .as_secs();
 
-            // Get the actual project path from JSONL files
-            let project_path = match get_project_path_from_sessions(&path) {
+            // Get the actual project path from JSONL files, or from when opcode
+            // created the directory
+            let project_path = match super::project_dirs::resolve_project_path(&path) {
                 Ok(path) => path,
+                Err(_) if project_dirs.contains_key(dir_name) => project_dirs[dir_name].clone(),
                 Err(e) => {
                     log::warn!("Failed to get project path from sessions for {}: {}, falling back to decode", dir_name, e);
                     decode_project_path(dir_name)
                 }
             };
+            let missing = !std::path::Path::new(&project_path).exists();
 
-            // List all JSONL files (sessions) in this project directory
+            // Stat the JSONL files (sessions) in this project directory
             let mut sessions = Vec::new();
-            let mut most_recent_session: Option<u64> = None;
-            
+            let mut last_session_at: Option<u64> = None;
+            let mut total_size_bytes = 0;
+
             if let Ok(session_entries) = fs::read_dir(&path) {
                 for session_entry in session_entries.flatten() {
                     let session_path = session_entry.path();
-                    if session_path.is_file()
-                        && session_path.extension().and_then(|s| s.to_str()) == Some("jsonl")
-                    {
-                        if let Some(session_id) = session_path.file_stem().and_then(|s| s.to_str())
-                        {
-                            sessions.push(session_id.to_string());
-                            
-                            // Track the most recent session timestamp
-                            if let Ok(metadata) = fs::metadata(&session_path) {
-                                let modified = metadata
-                                    .modified()
-                                    .unwrap_or(SystemTime::UNIX_EPOCH)
-                                    .duration_since(UNIX_EPOCH)
-                                    .unwrap_or_default()
-                                    .as_secs();
-                                
-                                most_recent_session = Some(match most_recent_session {
-                                    Some(current) => current.max(modified),
-                                    None => modified,
-                                });
-                            }
-                        }
+                    if session_path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+                        continue;
+                    }
+                    let metadata = match session_entry.metadata() {
+                        Ok(metadata) if metadata.is_file() => metadata,
+                        _ => continue,
+                    };
+                    if let Some(session_id) = session_path.file_stem().and_then(|s| s.to_str()) {
+                        sessions.push(session_id.to_string());
+                        total_size_bytes += metadata.len();
+
+                        let modified = metadata
+                            .modified()
+                            .unwrap_or(SystemTime::UNIX_EPOCH)
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs();
+                        last_session_at = Some(last_session_at.map_or(modified, |t| t.max(modified)));
                     }
                 }
             }
@@ -394,53 +461,64 @@ pub async fn list_projects() -> Result<Vec<Project>, String> {
             projects.push(Project {
                 id: dir_name.to_string(),
                 path: project_path,
+                session_count: sessions.len(),
                 sessions,
                 created_at,
-                most_recent_session,
+                last_session_at,
+                total_size_bytes,
+                archived,
+                missing,
             });
         }
     }
 
-    // Sort projects by most recent session activity, then by creation time
-    projects.sort_by(|a, b| {
-        // First compare by most recent session
-        match (a.most_recent_session, b.most_recent_session) {
-            (Some(a_time), Some(b_time)) => b_time.cmp(&a_time),
-            (Some(_), None) => std::cmp::Ordering::Less,
-            (None, Some(_)) => std::cmp::Ordering::Greater,
-            (None, None) => b.created_at.cmp(&a.created_at),
-        }
-    });
+    // Most recent session activity first, then by creation time
+    let by_recent = |a: &Project, b: &Project| match (a.last_session_at, b.last_session_at) {
+        (Some(a_time), Some(b_time)) => b_time.cmp(&a_time),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => b.created_at.cmp(&a.created_at),
+    };
+    match sort_by.as_str() {
+        "name" => projects.sort_by(|a, b| {
+            a.path
+                .to_lowercase()
+                .cmp(&b.path.to_lowercase())
+                .then_with(|| a.id.cmp(&b.id))
+        }),
+        "sessions" => projects.sort_by(|a, b| {
+            b.session_count
+                .cmp(&a.session_count)
+                .then_with(|| by_recent(a, b))
+        }),
+        _ => projects.sort_by(by_recent),
+    }
 
-    log::info!("Found {} projects", projects.len());
+    let total = projects.len();
+    let projects: Vec<Project> = projects
+        .into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+
+    log::info!("Found {} projects, returning {}", total, projects.len());
     Ok(projects)
 }
 
 /// Creates a new project for the given directory path
 #[tauri::command]
-pub async fn create_project(path: String) -> Result<Project, String> {
+pub async fn create_project(
+    db: tauri::State<'_, super::agents::AgentDb>,
+    path: String,
+) -> Result<Project, String> {
     log::info!("Creating project for path: {}", path);
-    
-    // Encode the path to create a project ID
-    let project_id = path.replace('/', "-");
-    
-    // Get claude directory
-    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
-    let projects_dir = claude_dir.join("projects");
-    
-    // Create projects directory if it doesn't exist
-    if !projects_dir.exists() {
-        fs::create_dir_all(&projects_dir)
-            .map_err(|e| format!("Failed to create projects directory: {}", e))?;
-    }
-    
-    // Create project directory if it doesn't exist
-    let project_dir = projects_dir.join(&project_id);
-    if !project_dir.exists() {
-        fs::create_dir_all(&project_dir)
-            .map_err(|e| format!("Failed to create project directory: {}", e))?;
-    }
-    
+
+    let project_id = super::project_dirs::ensure_project(&db, &path)?;
+    let project_dir = get_claude_dir()
+        .map_err(|e| e.to_string())?
+        .join("projects")
+        .join(&project_id);
+
     // Get creation time
     let metadata = fs::metadata(&project_dir)
         .map_err(|e| format!("Failed to read directory metadata: {}", e))?;
@@ -453,19 +531,35 @@ pub async fn create_project(path: String) -> Result<Project, String> {
         .unwrap_or_default()
         .as_secs();
     
+    let archived = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        super::project_archive::load_archived_projects(&conn)?.contains(&project_id)
+    };
+
     // Return the created project
     Ok(Project {
         id: project_id,
         path,
         sessions: Vec::new(),
+        session_count: 0,
         created_at,
-        most_recent_session: None,
+        last_session_at: None,
+        total_size_bytes: 0,
+        archived,
+        missing: false,
     })
 }
 
-/// Gets sessions for a specific project
+/// Gets a page of a project's sessions: pinned ones first, then newest first
+/// by modification time.
+/// Previews are read from the start of each file and cached by path and mtime.
 #[tauri::command]
-pub async fn get_project_sessions(project_id: String) -> Result<Vec<Session>, String> {
+pub async fn get_project_sessions(
+    db: tauri::State<'_, super::agents::AgentDb>,
+    project_id: String,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<SessionPage, String> {
     log::info!("Getting sessions for project: {}", project_id);
 
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
@@ -477,7 +571,7 @@ pub async fn get_project_sessions(project_id: String) -> Result<Vec<Session>, St
     }
 
     // Get the actual project path from JSONL files
-    let project_path = match get_project_path_from_sessions(&project_dir) {
+    let project_path = match super::project_dirs::resolve_project_path(&project_dir) {
         Ok(path) => path,
         Err(e) => {
             log::warn!(
@@ -489,94 +583,170 @@ pub async fn get_project_sessions(project_id: String) -> Result<Vec<Session>, St
         }
     };
 
-    let mut sessions = Vec::new();
+    let pinned = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        super::session_pins::load_pinned_sessions(&conn, &project_id)?
+    };
 
-    // Read all JSONL files in the project directory
+    // List the JSONL files, pinned first, then newest first by modification time
     let entries = fs::read_dir(&project_dir)
         .map_err(|e| format!("Failed to read project directory: {}", e))?;
 
+    let mut files = Vec::new();
     for entry in entries {
         let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
         let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) != Some("jsonl") {
+            continue;
+        }
+        let metadata = entry
+            .metadata()
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata
+            .modified()
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        let created_at = metadata
+            .created()
+            .or_else(|_| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        files.push((path, modified.as_millis() as i64, created_at));
+    }
+    let is_pinned = |path: &PathBuf| {
+        path.file_stem()
+            .and_then(|s| s.to_str())
+            .is_some_and(|session_id| pinned.contains(session_id))
+    };
+    files.sort_by(|a, b| is_pinned(&b.0).cmp(&is_pinned(&a.0)).then(b.1.cmp(&a.1)));
 
-        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("jsonl") {
-            if let Some(session_id) = path.file_stem().and_then(|s| s.to_str()) {
-                // Get file creation time
-                let metadata = fs::metadata(&path)
-                    .map_err(|e| format!("Failed to read file metadata: {}", e))?;
-
-                let created_at = metadata
-                    .created()
-                    .or_else(|_| metadata.modified())
-                    .unwrap_or(SystemTime::UNIX_EPOCH)
-                    .duration_since(SystemTime::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-
-                // Extract first user message and timestamp
-                let (first_message, message_timestamp) = extract_first_user_message(&path);
-
-                // Try to load associated todo data
-                let todo_path = todos_dir.join(format!("{}.json", session_id));
-                let todo_data = if todo_path.exists() {
-                    fs::read_to_string(&todo_path)
-                        .ok()
-                        .and_then(|content| serde_json::from_str(&content).ok())
-                } else {
-                    None
-                };
-
-                sessions.push(Session {
-                    id: session_id.to_string(),
-                    project_id: project_id.clone(),
-                    project_path: project_path.clone(),
-                    todo_data,
-                    created_at,
-                    first_message,
-                    message_timestamp,
-                });
-            }
+    let total_count = files.len();
+    let files: Vec<_> = files
+        .into_iter()
+        .skip(offset.unwrap_or(0))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+
+    // Previews come from the cache unless the file changed since it was read
+    let keys: Vec<(String, i64)> = files
+        .iter()
+        .map(|(path, mtime, _)| (path.to_string_lossy().to_string(), *mtime))
+        .collect();
+    let (mut titles, mut previews) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        (
+            super::session_titles::load_session_titles(&conn, &project_id)?,
+            super::session_previews::load_cached_previews(&conn, &keys)?,
+        )
+    };
+    let mut fresh_previews = Vec::new();
+    for ((path, _, _), (key, mtime)) in files.iter().zip(&keys) {
+        if !previews.contains_key(key) {
+            let preview = super::session_previews::read_session_preview(path);
+            fresh_previews.push((key.clone(), *mtime, preview.clone()));
+            previews.insert(key.clone(), preview);
+        }
+    }
+    if !fresh_previews.is_empty() {
+        let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+        if let Err(e) = super::session_previews::save_previews(&mut conn, &fresh_previews) {
+            log::warn!("Failed to cache session previews: {}", e);
         }
     }
 
-    // Sort sessions by creation time (newest first)
-    sessions.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    let mut sessions = Vec::new();
+    for ((path, _, created_at), (key, _)) in files.iter().zip(&keys) {
+        let session_id = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(session_id) => session_id,
+            None => continue,
+        };
+        let preview = previews.remove(key).unwrap_or_default();
+
+        // Try to load associated todo data
+        let todo_path = todos_dir.join(format!("{}.json", session_id));
+        let todo_data = if todo_path.exists() {
+            fs::read_to_string(&todo_path)
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok())
+        } else {
+            None
+        };
+
+        sessions.push(Session {
+            id: session_id.to_string(),
+            project_id: project_id.clone(),
+            project_path: project_path.clone(),
+            todo_data,
+            created_at: *created_at,
+            first_message: preview.first_message,
+            message_timestamp: preview.message_timestamp,
+            custom_title: titles.remove(session_id),
+            pinned: pinned.contains(session_id),
+        });
+    }
 
     log::info!(
-        "Found {} sessions for project {}",
+        "Returning {} of {} sessions for project {}",
         sessions.len(),
+        total_count,
         project_id
     );
-    Ok(sessions)
+    Ok(SessionPage {
+        sessions,
+        total_count,
+    })
 }
 
-/// Reads the Claude settings file
+/// Reads the Claude settings file. A file that isn't valid JSON is reported
+/// in `parse_errors` so the backup can be offered instead.
 #[tauri::command]
 pub async fn get_claude_settings() -> Result<ClaudeSettings, String> {
     log::info!("Reading Claude settings");
 
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
-    let settings_path = claude_dir.join("settings.json");
-
-    if !settings_path.exists() {
-        log::warn!("Settings file not found, returning empty settings");
-        return Ok(ClaudeSettings {
-            data: serde_json::json!({}),
-        });
+    let settings = read_settings(&claude_dir.join("settings.json"))?;
+    if !settings.parse_errors.is_empty() {
+        log::warn!("Settings file is broken: {:?}", settings.parse_errors);
     }
+    Ok(settings)
+}
 
-    let content = fs::read_to_string(&settings_path)
-        .map_err(|e| format!("Failed to read settings file: {}", e))?;
+/// Restores the settings file from its backup, returning the restored settings
+#[tauri::command]
+pub async fn restore_claude_settings_backup() -> Result<ClaudeSettings, String> {
+    log::info!("Restoring Claude settings from backup");
 
-    let data: serde_json::Value = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse settings JSON: {}", e))?;
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let settings_path = claude_dir.join("settings.json");
+    restore_backup(&settings_path)?;
+    read_settings(&settings_path)
+}
 
-    Ok(ClaudeSettings { data })
+/// A Claude Code session started outside opcode by `open_new_session`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenedSession {
+    pub message: String,
+    /// The project the session belongs to, listed even before Claude writes to it
+    pub project_id: Option<String>,
 }
 
-/// Opens a new Claude Code session by executing the claude command
+/// Opens a new Claude Code session by executing the claude command. The
+/// project directory Claude will use is created first, so it can be shown
+/// right away. Models are chosen as for `execute_claude_code`.
 #[tauri::command]
-pub async fn open_new_session(app: AppHandle, path: Option<String>) -> Result<String, String> {
+pub async fn open_new_session(
+    app: AppHandle,
+    db: tauri::State<'_, super::agents::AgentDb>,
+    path: Option<String>,
+    model: Option<String>,
+    remember_model: Option<bool>,
+) -> Result<OpenedSession, String> {
     log::info!("Opening new Claude Code session at path: {:?}", path);
 
     #[cfg(not(debug_assertions))]
@@ -589,6 +759,7 @@ pub async fn open_new_session(app: AppHandle, path: Option<String>) -> Result<St
     // The user should launch Claude Code through other means or use the execute_claude_code command
     #[cfg(not(debug_assertions))]
     {
+        let _ = (&db, &path, &model, remember_model);
         log::error!("Cannot spawn processes directly in production builds");
         return Err("Direct process spawning is not available in production builds. Please use Claude Code directly or use the integrated execution commands.".to_string());
     }
@@ -598,15 +769,30 @@ pub async fn open_new_session(app: AppHandle, path: Option<String>) -> Result<St
         let mut cmd = std::process::Command::new(claude_path);
 
         // If a path is provided, use it; otherwise use current directory
-        if let Some(project_path) = path {
-            cmd.current_dir(&project_path);
-        }
+        let project_id = match &path {
+            Some(project_path) => {
+                cmd.current_dir(project_path);
+                let model =
+                    resolve_model(&db, project_path, model, remember_model.unwrap_or(false));
+                cmd.arg("--model").arg(model);
+                Some(super::project_dirs::ensure_project(&db, project_path)?)
+            }
+            None => {
+                if let Some(model) = model {
+                    cmd.arg("--model").arg(model);
+                }
+                None
+            }
+        };
 
         // Execute the command
         match cmd.spawn() {
             Ok(_) => {
                 log::info!("Successfully launched Claude Code");
-                Ok("Claude Code session started".to_string())
+                Ok(OpenedSession {
+                    message: "Claude Code session started".to_string(),
+                    project_id,
+                })
             }
             Err(e) => {
                 log::error!("Failed to launch Claude Code: {}", e);
@@ -616,9 +802,10 @@ pub async fn open_new_session(app: AppHandle, path: Option<String>) -> Result<St
     }
 }
 
-/// Reads the CLAUDE.md system prompt file
+/// Reads the CLAUDE.md system prompt file, with the version to pass back
+/// when saving it
 #[tauri::command]
-pub async fn get_system_prompt() -> Result<String, String> {
+pub async fn get_system_prompt() -> Result<MemoryFileContent, String> {
     log::info!("Reading CLAUDE.md system prompt");
 
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
@@ -626,15 +813,20 @@ pub async fn get_system_prompt() -> Result<String, String> {
 
     if !claude_md_path.exists() {
         log::warn!("CLAUDE.md not found");
-        return Ok(String::new());
     }
 
-    fs::read_to_string(&claude_md_path).map_err(|e| format!("Failed to read CLAUDE.md: {}", e))
+    read_memory_file(&claude_md_path).map_err(|e| format!("Failed to read CLAUDE.md: {}", e))
 }
 
-/// Checks if Claude Code is installed and gets its version
+/// Checks if Claude Code is installed and gets its version. The version is
+/// cached per binary unless `force_refresh` is set.
 #[tauri::command]
-pub async fn check_claude_version(app: AppHandle) -> Result<ClaudeVersionStatus, String> {
+pub async fn check_claude_version(
+    app: AppHandle,
+    db: tauri::State<'_, super::agents::AgentDb>,
+    check_latest: Option<bool>,
+    force_refresh: Option<bool>,
+) -> Result<ClaudeVersionStatus, String> {
     log::info!("Checking Claude Code version");
 
     let claude_path = match find_claude_binary(&app) {
@@ -642,8 +834,9 @@ pub async fn check_claude_version(app: AppHandle) -> Result<ClaudeVersionStatus,
         Err(e) => {
             return Ok(ClaudeVersionStatus {
                 is_installed: false,
-                version: None,
                 output: e,
+                minimum_version: MINIMUM_CLAUDE_VERSION.to_string(),
+                ..Default::default()
             });
         }
     };
@@ -653,45 +846,40 @@ pub async fn check_claude_version(app: AppHandle) -> Result<ClaudeVersionStatus,
     // In production builds, we can't check the version directly
     #[cfg(not(debug_assertions))]
     {
+        let _ = (&db, check_latest, force_refresh);
         log::warn!("Cannot check claude version in production build");
         // If we found a path (either stored or in common locations), assume it's installed
         if claude_path != "claude" && PathBuf::from(&claude_path).exists() {
             return Ok(ClaudeVersionStatus {
                 is_installed: true,
-                version: None,
                 output: "Claude binary found at: ".to_string() + &claude_path,
+                minimum_version: MINIMUM_CLAUDE_VERSION.to_string(),
+                ..Default::default()
             });
         } else {
             return Ok(ClaudeVersionStatus {
                 is_installed: false,
-                version: None,
                 output: "Cannot verify Claude installation in production build. Please ensure Claude Code is installed.".to_string(),
+                minimum_version: MINIMUM_CLAUDE_VERSION.to_string(),
+                ..Default::default()
             });
         }
     }
 
     #[cfg(debug_assertions)]
     {
-        let output = std::process::Command::new(claude_path)
-            .arg("--version")
-            .output();
+        let output = super::binary_versions::version_output(
+            &app,
+            &db,
+            &claude_path,
+            force_refresh.unwrap_or(false),
+        );
 
         match output {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                
-                // Use regex to directly extract version pattern (e.g., "1.0.41")
-                let version_regex = regex::Regex::new(r"(\d+\.\d+\.\d+(?:-[a-zA-Z0-9.-]+)?(?:\+[a-zA-Z0-9.-]+)?)").ok();
-                
-                let version = if let Some(regex) = version_regex {
-                    regex.captures(&stdout)
-                        .and_then(|captures| captures.get(1))
-                        .map(|m| m.as_str().to_string())
-                } else {
-                    None
-                };
-                
+            Some(output) => {
+                let stdout = output.stdout;
+                let stderr = output.stderr;
+
                 let full_output = if stderr.is_empty() {
                     stdout.clone()
                 } else {
@@ -702,58 +890,94 @@ pub async fn check_claude_version(app: AppHandle) -> Result<ClaudeVersionStatus,
                 // Expected format: "1.0.17 (Claude Code)" or similar
                 let is_valid = stdout.contains("(Claude Code)") || stdout.contains("Claude Code");
 
+                // Offline users can skip the registry lookup
+                let latest_version = if check_latest.unwrap_or(true) {
+                    latest_published_version(&db).await
+                } else {
+                    None
+                };
+                let assessment = assess_version(&stdout, latest_version.as_deref());
+                if assessment.parse_failed {
+                    log::warn!("Could not parse a version from: {}", stdout.trim());
+                }
+
                 Ok(ClaudeVersionStatus {
-                    is_installed: is_valid && output.status.success(),
-                    version,
+                    is_installed: is_valid && output.success,
+                    version: assessment.parsed.as_ref().map(|v| v.raw.clone()),
                     output: full_output.trim().to_string(),
+                    parsed: assessment.parsed,
+                    parse_failed: assessment.parse_failed,
+                    minimum_version: MINIMUM_CLAUDE_VERSION.to_string(),
+                    below_minimum: assessment.below_minimum,
+                    latest_version,
+                    update_available: assessment.update_available,
                 })
             }
-            Err(e) => {
-                log::error!("Failed to run claude command: {}", e);
+            None => {
+                log::error!("Failed to run claude command: {}", claude_path);
                 Ok(ClaudeVersionStatus {
                     is_installed: false,
-                    version: None,
-                    output: format!("Command not found: {}", e),
+                    output: format!("Command not found: {}", claude_path),
+                    minimum_version: MINIMUM_CLAUDE_VERSION.to_string(),
+                    ..Default::default()
                 })
             }
         }
     }
 }
 
-/// Saves the CLAUDE.md system prompt file
+/// Saves the CLAUDE.md system prompt file unless it changed since `version`
+/// was read, in which case the current content is returned as a conflict.
+/// `force` saves regardless.
 #[tauri::command]
-pub async fn save_system_prompt(content: String) -> Result<String, String> {
+pub async fn save_system_prompt(
+    content: String,
+    version: Option<String>,
+    force: Option<bool>,
+) -> Result<MemoryFileSave, String> {
     log::info!("Saving CLAUDE.md system prompt");
 
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
     let claude_md_path = claude_dir.join("CLAUDE.md");
 
-    fs::write(&claude_md_path, content).map_err(|e| format!("Failed to write CLAUDE.md: {}", e))?;
-
-    Ok("System prompt saved successfully".to_string())
+    write_memory_file(
+        &claude_md_path,
+        &content,
+        version.as_deref(),
+        force.unwrap_or(false),
+    )
+    .map_err(|e| format!("Failed to write CLAUDE.md: {}", e))
 }
 
-/// Saves the Claude settings file
+/// Saves the Claude settings file after checking it against the keys Claude
+/// reads. Unknown keys are returned as warnings, or rejected when `strict`.
+/// Each saved version is recorded in the settings history.
 #[tauri::command]
-pub async fn save_claude_settings(settings: serde_json::Value) -> Result<String, String> {
+pub async fn save_claude_settings(
+    db: tauri::State<'_, super::agents::AgentDb>,
+    settings: serde_json::Value,
+    strict: Option<bool>,
+) -> Result<SettingsSaveResult, String> {
     log::info!("Saving Claude settings");
 
     let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
     let settings_path = claude_dir.join("settings.json");
+    let result = save_settings(&settings_path, &settings, strict.unwrap_or(false))?;
 
-    // Pretty print the JSON with 2-space indentation
-    let json_string = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-
-    fs::write(&settings_path, json_string)
-        .map_err(|e| format!("Failed to write settings file: {}", e))?;
-
-    Ok("Settings saved successfully".to_string())
+    if let Ok(content) = serde_json::to_string_pretty(&settings) {
+        record_snapshot_or_warn(&db, &settings_path, "save_claude_settings", &content);
+    }
+    Ok(result)
 }
 
-/// Recursively finds all CLAUDE.md files in a project directory
+/// Finds the memory files Claude loads for a project: `~/.claude/CLAUDE.md`,
+/// those in parent directories, and CLAUDE.md and CLAUDE.local.md files in the
+/// project down to `max_depth` levels (unlimited by default)
 #[tauri::command]
-pub async fn find_claude_md_files(project_path: String) -> Result<Vec<ClaudeMdFile>, String> {
+pub async fn find_claude_md_files(
+    project_path: String,
+    max_depth: Option<usize>,
+) -> Result<Vec<ClaudeMdFile>, String> {
     log::info!("Finding CLAUDE.md files in project: {}", project_path);
 
     let path = PathBuf::from(&project_path);
@@ -761,85 +985,17 @@ pub async fn find_claude_md_files(project_path: String) -> Result<Vec<ClaudeMdFi
         return Err(format!("Project path does not exist: {}", project_path));
     }
 
-    let mut claude_files = Vec::new();
-    find_claude_md_recursive(&path, &path, &mut claude_files)?;
-
-    // Sort by relative path
-    claude_files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let claude_files = super::memory_files::find_memory_files(&claude_dir, &path, max_depth);
 
     log::info!("Found {} CLAUDE.md files", claude_files.len());
     Ok(claude_files)
 }
 
-/// Helper function to recursively find CLAUDE.md files
-fn find_claude_md_recursive(
-    current_path: &PathBuf,
-    project_root: &PathBuf,
-    claude_files: &mut Vec<ClaudeMdFile>,
-) -> Result<(), String> {
-    let entries = fs::read_dir(current_path)
-        .map_err(|e| format!("Failed to read directory {:?}: {}", current_path, e))?;
-
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-        let path = entry.path();
-
-        // Skip hidden files/directories
-        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-            if name.starts_with('.') {
-                continue;
-            }
-        }
-
-        if path.is_dir() {
-            // Skip common directories that shouldn't be searched
-            if let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) {
-                if matches!(
-                    dir_name,
-                    "node_modules" | "target" | ".git" | "dist" | "build" | ".next" | "__pycache__"
-                ) {
-                    continue;
-                }
-            }
-
-            find_claude_md_recursive(&path, project_root, claude_files)?;
-        } else if path.is_file() {
-            // Check if it's a CLAUDE.md file (case insensitive)
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                if file_name.eq_ignore_ascii_case("CLAUDE.md") {
-                    let metadata = fs::metadata(&path)
-                        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
-
-                    let relative_path = path
-                        .strip_prefix(project_root)
-                        .map_err(|e| format!("Failed to get relative path: {}", e))?
-                        .to_string_lossy()
-                        .to_string();
-
-                    let modified = metadata
-                        .modified()
-                        .unwrap_or(SystemTime::UNIX_EPOCH)
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_secs();
-
-                    claude_files.push(ClaudeMdFile {
-                        relative_path,
-                        absolute_path: path.to_string_lossy().to_string(),
-                        size: metadata.len(),
-                        modified,
-                    });
-                }
-            }
-        }
-    }
-
-    Ok(())
-}
-
-/// Reads a specific CLAUDE.md file by its absolute path
+/// Reads a specific CLAUDE.md file by its absolute path, with the version to
+/// pass back when saving it
 #[tauri::command]
-pub async fn read_claude_md_file(file_path: String) -> Result<String, String> {
+pub async fn read_claude_md_file(file_path: String) -> Result<MemoryFileContent, String> {
     log::info!("Reading CLAUDE.md file: {}", file_path);
 
     let path = PathBuf::from(&file_path);
@@ -847,140 +1003,660 @@ pub async fn read_claude_md_file(file_path: String) -> Result<String, String> {
         return Err(format!("File does not exist: {}", file_path));
     }
 
-    fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))
+    read_memory_file(&path)
 }
 
-/// Saves a specific CLAUDE.md file by its absolute path
+/// Saves a specific CLAUDE.md file by its absolute path. The path must be the
+/// user-level CLAUDE.md, inside a project Claude knows about, or a memory file
+/// in a directory above one.
+///
+/// `version` is the one the file was read at (None if it didn't exist); if
+/// the file changed since, nothing is written and its current content is
+/// returned as a conflict, unless `force` is set.
 #[tauri::command]
-pub async fn save_claude_md_file(file_path: String, content: String) -> Result<String, String> {
+pub async fn save_claude_md_file(
+    file_path: String,
+    content: String,
+    version: Option<String>,
+    force: Option<bool>,
+) -> Result<MemoryFileSave, String> {
     log::info!("Saving CLAUDE.md file: {}", file_path);
 
     let path = PathBuf::from(&file_path);
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    super::memory_files::validate_memory_file_path(&claude_dir, &path)?;
 
-    // Ensure the parent directory exists
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create parent directory: {}", e))?;
-    }
+    write_memory_file(&path, &content, version.as_deref(), force.unwrap_or(false))
+}
 
-    fs::write(&path, content).map_err(|e| format!("Failed to write file: {}", e))?;
+/// Path of a session's JSONL file, which must exist
+pub(crate) fn session_file_path(project_id: &str, session_id: &str) -> Result<PathBuf, String> {
+    validate_path_component("project id", project_id)?;
+    validate_path_component("session id", session_id)?;
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let session_path = claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(format!("{}.jsonl", session_id));
 
-    Ok("File saved successfully".to_string())
+    if !session_path.exists() {
+        return Err(format!("Session file not found: {}", session_id));
+    }
+    Ok(session_path)
 }
 
-/// Loads the JSONL history for a specific session
+/// Loads a range of the JSONL history for a specific session, streaming the
+/// file. Indices are JSONL lines, the same ones checkpoints use as
+/// `message_index`; `count` limits the entries returned after filtering by
+/// `entry_types` (`user`, `assistant`, `summary`).
 #[tauri::command]
 pub async fn load_session_history(
     session_id: String,
     project_id: String,
-) -> Result<Vec<serde_json::Value>, String> {
+    from_index: Option<usize>,
+    count: Option<usize>,
+    entry_types: Option<Vec<String>>,
+) -> Result<super::session_history::SessionHistoryChunk, String> {
     log::info!(
         "Loading session history for session: {} in project: {}",
         session_id,
         project_id
     );
+    if let Some(entry_types) = &entry_types {
+        super::session_history::validate_entry_types(entry_types)?;
+    }
 
-    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
-    let session_path = claude_dir
-        .join("projects")
-        .join(&project_id)
-        .join(format!("{}.jsonl", session_id));
+    let session_path = session_file_path(&project_id, &session_id)?;
+    let file =
+        fs::File::open(&session_path).map_err(|e| format!("Failed to open session file: {}", e))?;
 
-    if !session_path.exists() {
-        return Err(format!("Session file not found: {}", session_id));
+    let chunk = super::session_history::read_history_chunk(
+        BufReader::new(file),
+        from_index.unwrap_or(0),
+        count,
+        entry_types.as_deref(),
+    )?;
+    if chunk.skipped_lines > 0 {
+        log::warn!(
+            "Skipped {} unparseable lines in session {}",
+            chunk.skipped_lines,
+            session_id
+        );
     }
+    Ok(chunk)
+}
 
+/// Counts the lines of a session's JSONL file without parsing them, so the
+/// transcript can be loaded in ranges
+#[tauri::command]
+pub async fn get_session_message_count(
+    project_id: String,
+    session_id: String,
+) -> Result<usize, String> {
+    let session_path = session_file_path(&project_id, &session_id)?;
     let file =
         fs::File::open(&session_path).map_err(|e| format!("Failed to open session file: {}", e))?;
+    super::session_history::count_lines(file)
+}
 
-    let reader = BufReader::new(file);
-    let mut messages = Vec::new();
+/// Days a deleted session is kept in the trash before it is purged
+const SESSION_TRASH_DAYS: u64 = 30;
 
-    for line in reader.lines() {
-        if let Ok(line) = line {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
-                messages.push(json);
+/// Reject ids that could escape the directory they name a file in
+pub(crate) fn validate_path_component(kind: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() || value == "." || value == ".." || value.contains(['/', '\\']) {
+        return Err(format!("Invalid {}: {}", kind, value));
+    }
+    Ok(())
+}
+
+/// Delete trash entries older than `SESSION_TRASH_DAYS`. Entries are named
+/// `<unix seconds>-<session id>`.
+fn purge_session_trash(trash_dir: &PathBuf) {
+    let cutoff = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .saturating_sub(SESSION_TRASH_DAYS * 24 * 60 * 60);
+    let entries = match fs::read_dir(trash_dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let deleted_at = name
+            .split('-')
+            .next()
+            .and_then(|secs| secs.parse::<u64>().ok());
+        if deleted_at.is_some_and(|secs| secs < cutoff) {
+            if let Err(e) = fs::remove_dir_all(entry.path()) {
+                log::warn!(
+                    "Failed to purge {:?} from the session trash: {}",
+                    entry.path(),
+                    e
+                );
             }
         }
     }
+}
 
-    Ok(messages)
+/// Agent run among the `running` processes that is working in the session
+fn agent_run_in_session(
+    conn: &rusqlite::Connection,
+    running: &[crate::process::ProcessInfo],
+    session_id: &str,
+) -> Result<Option<i64>, String> {
+    for process in running {
+        let run_session: Option<String> = conn
+            .query_row(
+                "SELECT session_id FROM agent_runs WHERE id = ?1",
+                rusqlite::params![process.run_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if run_session.as_deref() == Some(session_id) {
+            return Ok(Some(process.run_id));
+        }
+    }
+    Ok(None)
 }
 
+/// Move each path to the same place under `entry_dir` as it has under
+/// `claude_dir`. If one can't be moved, the ones already moved are put back.
+fn move_paths_to_trash(
+    paths: &[PathBuf],
+    claude_dir: &std::path::Path,
+    entry_dir: &std::path::Path,
+) -> Result<(), String> {
+    let mut moved: Vec<(&PathBuf, PathBuf)> = Vec::new();
+    for path in paths {
+        let result = path
+            .strip_prefix(claude_dir)
+            .map_err(|e| e.to_string())
+            .and_then(|relative| {
+                let target = entry_dir.join(relative);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)
+                        .map_err(|e| format!("Failed to create trash directory: {}", e))?;
+                }
+                fs::rename(path, &target)
+                    .map_err(|e| format!("Failed to move {:?} to the trash: {}", path, e))?;
+                Ok(target)
+            });
+        match result {
+            Ok(target) => moved.push((path, target)),
+            Err(e) => {
+                let mut restored = true;
+                for (original, target) in moved.iter().rev() {
+                    if let Err(err) = fs::rename(target, original) {
+                        log::warn!("Failed to restore {:?} from the trash: {}", original, err);
+                        restored = false;
+                    }
+                }
+                if restored {
+                    let _ = fs::remove_dir_all(entry_dir);
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
 
+/// Delete a session's JSONL file along with its todo files and session
+/// directory. With `move_to_trash` (the default) they are moved under
+/// `~/.claude/.opcode-trash/` and purged after 30 days. Sessions that are
+/// still running can't be deleted; checkpoints are reported but kept.
+#[tauri::command]
+pub async fn delete_session(
+    db: tauri::State<'_, super::agents::AgentDb>,
+    registry: tauri::State<'_, crate::process::ProcessRegistryState>,
+    project_id: String,
+    session_id: String,
+    move_to_trash: Option<bool>,
+) -> Result<DeletedSession, String> {
+    validate_path_component("project id", &project_id)?;
+    validate_path_component("session id", &session_id)?;
+
+    if registry.0.get_claude_session_by_id(&session_id)?.is_some() {
+        return Err(format!(
+            "Session {} is still running. Cancel it before deleting it.",
+            session_id
+        ));
+    }
+    let running_agent_runs = registry.0.get_running_agent_processes()?;
+    if !running_agent_runs.is_empty() {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if let Some(run_id) = agent_run_in_session(&conn, &running_agent_runs, &session_id)? {
+            return Err(format!(
+                "Session {} is still used by agent run {}. Cancel the run before deleting it.",
+                session_id, run_id
+            ));
+        }
+    }
+
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let project_dir = claude_dir.join("projects").join(&project_id);
+    let session_file = project_dir.join(format!("{}.jsonl", session_id));
+    if !session_file.exists() {
+        return Err(format!("Session file not found: {}", session_id));
+    }
+
+    // The JSONL, its directory of subagent and tool result files, and its todo lists
+    let mut paths = vec![session_file];
+    let session_dir = project_dir.join(&session_id);
+    if session_dir.is_dir() {
+        paths.push(session_dir);
+    }
+    if let Ok(entries) = fs::read_dir(claude_dir.join("todos")) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(&session_id) && name.ends_with(".json") {
+                paths.push(entry.path());
+            }
+        }
+    }
+
+    let has_checkpoints =
+        crate::checkpoint::CheckpointPaths::new(&claude_dir, &project_id, &session_id)
+            .timeline_file
+            .exists();
+
+    let trash_path = if move_to_trash.unwrap_or(true) {
+        let trash_dir = claude_dir.join(".opcode-trash");
+        purge_session_trash(&trash_dir);
+
+        let deleted_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let entry_dir = trash_dir.join(format!("{}-{}", deleted_at, session_id));
+        // Keep each file's place under ~/.claude so it can be put back by hand
+        move_paths_to_trash(&paths, &claude_dir, &entry_dir)?;
+        Some(entry_dir.to_string_lossy().into_owned())
+    } else {
+        for path in &paths {
+            let result = if path.is_dir() {
+                fs::remove_dir_all(path)
+            } else {
+                fs::remove_file(path)
+            };
+            result.map_err(|e| format!("Failed to delete {:?}: {}", path, e))?;
+        }
+        None
+    };
+
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        super::session_titles::delete_session_title(&conn, &project_id, &session_id)
+            .map_err(|e| e.to_string())?;
+        super::session_pins::delete_session_pin(&conn, &project_id, &session_id)
+            .map_err(|e| e.to_string())?;
+    }
+
+    log::info!(
+        "Deleted session {} of project {} ({} paths)",
+        session_id,
+        project_id,
+        paths.len()
+    );
+    Ok(DeletedSession {
+        removed_paths: paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+        trash_path,
+        has_checkpoints,
+    })
+}
 
-/// Execute a new interactive Claude Code session with streaming output
+/// Search user and assistant messages across every project's sessions, or
+/// one project's. New and changed session files are indexed first, emitting
+/// `session-search-index-progress` as they are.
+#[tauri::command]
+pub async fn search_sessions(
+    app: AppHandle,
+    db: tauri::State<'_, super::agents::AgentDb>,
+    query: String,
+    project_id: Option<String>,
+    limit: Option<usize>,
+    include_tool_output: Option<bool>,
+) -> Result<Vec<super::session_search::SessionSearchHit>, String> {
+    let projects_dir = get_claude_dir().map_err(|e| e.to_string())?.join("projects");
+    let limit = limit
+        .filter(|l| *l > 0)
+        .unwrap_or(super::session_search::DEFAULT_SESSION_SEARCH_LIMIT);
+
+    super::session_search::search_session_messages(
+        &db,
+        &projects_dir,
+        &query,
+        project_id.as_deref(),
+        include_tool_output.unwrap_or(false),
+        limit,
+        |progress| {
+            let _ = app.emit("session-search-index-progress", &progress);
+        },
+    )
+}
+
+
+
+/// How a Claude Code session command starts
+enum SessionStart<'a> {
+    /// A new session, with the ID Claude should give it
+    New(Option<&'a str>),
+    Continue,
+    Resume(&'a str),
+}
+
+/// CLI arguments of a session command. Shared by the execute, continue and
+/// resume commands and `preview_claude_command`, so previews match what runs.
+fn session_args(
+    start: SessionStart,
+    prompt: &str,
+    model: &str,
+    project_prompt: Option<&str>,
+    options: &ClaudeSessionOptions,
+    output_format: OutputFormat,
+) -> Result<Vec<String>, String> {
+    options.validate()?;
+    let mut args: Vec<String> = match start {
+        SessionStart::New(None) => Vec::new(),
+        SessionStart::New(Some(session_id)) => {
+            vec!["--session-id".to_string(), session_id.to_string()]
+        }
+        SessionStart::Continue => vec!["-c".to_string()],
+        SessionStart::Resume(session_id) => vec!["--resume".to_string(), session_id.to_string()],
+    };
+    args.extend(
+        [
+            "-p",
+            prompt,
+            "--model",
+            model,
+            "--output-format",
+            output_format.as_str(),
+        ]
+        .map(String::from),
+    );
+    // stream-json requires --verbose with -p; other formats stay quiet
+    if output_format.is_streaming() {
+        args.push("--verbose".to_string());
+    }
+    args.extend(options.permission_args());
+    if let Some(project_prompt) = project_prompt {
+        args.push("--append-system-prompt".to_string());
+        args.push(project_prompt.to_string());
+    }
+    args.extend(options.extra_cli_args());
+    log::debug!("Claude arguments: {:?}", args);
+    Ok(args)
+}
+
+/// A started interactive session and the attachments its prompt included
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeSessionLaunch {
+    pub session_id: String,
+    pub attachments: Vec<IncludedAttachment>,
+    /// Claude's complete stdout, for the `json` and `text` output formats
+    pub output: Option<String>,
+}
+
+/// The Claude CLI invocation a session command would start, without starting it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeCommandPreview {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Shell-quoted program and arguments
+    pub command_line: String,
+    pub working_directory: String,
+    /// The project's system prompt, appended when set
+    pub project_system_prompt: Option<String>,
+    /// The environment Claude would get, secrets masked
+    pub environment: BTreeMap<String, String>,
+    /// What the binary reports, when it runs
+    pub claude_version: Option<String>,
+}
+
+/// The session `preview_claude_command` shows the command line for
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClaudeCommandRequest {
+    pub project_path: String,
+    pub prompt: String,
+    pub model: String,
+    /// Preview the resume command for this session
+    pub session_id: Option<String>,
+    /// Preview the continue command
+    #[serde(default)]
+    pub continue_session: bool,
+    #[serde(default)]
+    pub options: ClaudeSessionOptions,
+    #[serde(default)]
+    pub output_format: OutputFormat,
+}
+
+/// Show the command line `execute_claude_code`, or with `continue_session` or
+/// `session_id` the continue or resume command, would run with `options`
+#[tauri::command]
+pub async fn preview_claude_command(
+    app: AppHandle,
+    db: tauri::State<'_, super::agents::AgentDb>,
+    request: ClaudeCommandRequest,
+) -> Result<ClaudeCommandPreview, String> {
+    let ClaudeCommandRequest {
+        project_path,
+        prompt,
+        model,
+        session_id,
+        continue_session,
+        options,
+        output_format,
+    } = request;
+    let claude_path = resolve_claude_binary(&app, &db, &project_path)?;
+    let project_system_prompt = project_system_prompt_for(&db, &project_path);
+    let start = match (&session_id, continue_session) {
+        (Some(session_id), _) => SessionStart::Resume(session_id),
+        (None, true) => SessionStart::Continue,
+        (None, false) => SessionStart::New(None),
+    };
+    let args = session_args(
+        start,
+        &prompt,
+        &model,
+        project_system_prompt.as_deref(),
+        &options,
+        output_format,
+    )?;
+    let env = claude_env_for(&db, options.env.as_ref());
+    let cmd = create_system_command(&claude_path, args.clone(), &project_path, &env);
+    let environment = effective_env(cmd.as_std());
+    let command_line = std::iter::once(claude_path.as_str())
+        .chain(args.iter().map(String::as_str))
+        .map(super::agents::shell_quote)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let claude_version = installed_claude_version(&claude_path)
+        .await
+        .map(|version| version.raw);
+
+    Ok(ClaudeCommandPreview {
+        program: claude_path,
+        args,
+        command_line,
+        working_directory: project_path,
+        project_system_prompt,
+        environment,
+        claude_version,
+    })
+}
+
+/// Execute a new interactive Claude Code session with streaming output. The
+/// project's system prompt, if set, is appended to Claude's.
+///
+/// The session gets `session_id`, a UUID, or a new one; it is returned, and
+/// the session's events are tagged with it. `attachments` are files added to
+/// the prompt; the result says how each was included.
+///
+/// With the `json` or `text` `output_format` nothing is streamed: the command
+/// waits for Claude to finish and returns its output.
+///
+/// Without a `model` the project's default model is used; with one and
+/// `remember_model`, it becomes the project's default.
 #[tauri::command]
 pub async fn execute_claude_code(
     app: AppHandle,
+    db: tauri::State<'_, super::agents::AgentDb>,
     project_path: String,
     prompt: String,
-    model: String,
-) -> Result<(), String> {
+    model: Option<String>,
+    options: Option<ClaudeSessionOptions>,
+    session_id: Option<String>,
+    attachments: Option<Vec<String>>,
+    output_format: Option<OutputFormat>,
+    remember_model: Option<bool>,
+) -> Result<ClaudeSessionLaunch, String> {
+    let model = resolve_model(&db, &project_path, model, remember_model.unwrap_or(false));
     log::info!(
         "Starting new Claude Code session in: {} with model: {}",
         project_path,
         model
     );
 
-    let claude_path = find_claude_binary(&app)?;
-    
-    let args = vec![
-        "-p".to_string(),
-        prompt.clone(),
-        "--model".to_string(),
-        model.clone(),
-        "--output-format".to_string(),
-        "stream-json".to_string(),
-        "--verbose".to_string(),
-        "--dangerously-skip-permissions".to_string(),
-    ];
-
-    let cmd = create_system_command(&claude_path, args, &project_path);
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
-}
-
-/// Continue an existing Claude Code conversation with streaming output
+    let session_id = match session_id {
+        Some(session_id) => uuid::Uuid::parse_str(&session_id)
+            .map_err(|e| format!("Invalid session ID '{}': {}", session_id, e))?
+            .to_string(),
+        None => uuid::Uuid::new_v4().to_string(),
+    };
+    let claude_path = resolve_claude_binary(&app, &db, &project_path)?;
+    let output_format = output_format.unwrap_or_default();
+    if !output_format.is_streaming() {
+        let installed = installed_claude_version(&claude_path).await;
+        check_output_format_supported(installed.as_ref(), output_format)?;
+    }
+    let attachments = prepare_attachments(&project_path, &attachments.unwrap_or_default())?;
+    let project_prompt = project_system_prompt_for(&db, &project_path);
+    let options = options.unwrap_or_default();
+    let args = session_args(
+        SessionStart::New(Some(&session_id)),
+        &attachments.apply_to(&prompt),
+        &model,
+        project_prompt.as_deref(),
+        &options,
+        output_format,
+    )?;
+    let env = claude_env_for(&db, options.env.as_ref());
+
+    // List the project straight away, before Claude writes its first message
+    let auto_title = match super::project_dirs::ensure_project(&db, &project_path) {
+        Ok(project_id) => AutoTitleWatch::start(&db, &project_id, &session_id),
+        Err(e) => {
+            log::warn!("Failed to create project directory for {}: {}", project_path, e);
+            None
+        }
+    };
+
+    let cmd = create_system_command(&claude_path, args, &project_path, &env);
+    let history_prompt = prompt.clone();
+    let (session_id, final_output) = spawn_claude_process(
+        app,
+        cmd,
+        session_id,
+        prompt,
+        model,
+        project_path.clone(),
+        RunOptions {
+            output_format,
+            auto_title,
+            ..Default::default()
+        },
+    )
+    .await?;
+    record_prompt_or_warn(&db, &history_prompt, &project_path, Some(&session_id), false);
+    let output = match final_output {
+        Some(final_output) => Some(
+            final_output
+                .await
+                .map_err(|_| "Claude's output was lost before it finished".to_string())?,
+        ),
+        None => None,
+    };
+    Ok(ClaudeSessionLaunch {
+        session_id,
+        attachments: attachments.included,
+        output,
+    })
+}
+
+/// Continue the most recent Claude Code conversation with streaming output.
+/// Its events are tagged with `session_id`, or a new ID, which is returned
+/// along with how `attachments` were included.
 #[tauri::command]
 pub async fn continue_claude_code(
     app: AppHandle,
+    db: tauri::State<'_, super::agents::AgentDb>,
     project_path: String,
     prompt: String,
     model: String,
-) -> Result<(), String> {
+    options: Option<ClaudeSessionOptions>,
+    session_id: Option<String>,
+    attachments: Option<Vec<String>>,
+) -> Result<ClaudeSessionLaunch, String> {
     log::info!(
         "Continuing Claude Code conversation in: {} with model: {}",
         project_path,
         model
     );
 
-    let claude_path = find_claude_binary(&app)?;
-    
-    let args = vec![
-        "-c".to_string(), // Continue flag
-        "-p".to_string(),
-        prompt.clone(),
-        "--model".to_string(),
-        model.clone(),
-        "--output-format".to_string(),
-        "stream-json".to_string(),
-        "--verbose".to_string(),
-        "--dangerously-skip-permissions".to_string(),
-    ];
-
-    let cmd = create_system_command(&claude_path, args, &project_path);
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
-}
-
-/// Resume an existing Claude Code session by ID with streaming output
+    let claude_path = resolve_claude_binary(&app, &db, &project_path)?;
+    let attachments = prepare_attachments(&project_path, &attachments.unwrap_or_default())?;
+    let project_prompt = project_system_prompt_for(&db, &project_path);
+    let options = options.unwrap_or_default();
+    let args = session_args(
+        SessionStart::Continue,
+        &attachments.apply_to(&prompt),
+        &model,
+        project_prompt.as_deref(),
+        &options,
+        OutputFormat::StreamJson,
+    )?;
+    let env = claude_env_for(&db, options.env.as_ref());
+
+    let cmd = create_system_command(&claude_path, args, &project_path, &env);
+    let session_id = session_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let history_prompt = prompt.clone();
+    let (session_id, _) = spawn_claude_process(
+        app,
+        cmd,
+        session_id,
+        prompt,
+        model,
+        project_path.clone(),
+        RunOptions::default(),
+    )
+    .await?;
+    record_prompt_or_warn(&db, &history_prompt, &project_path, None, false);
+    Ok(ClaudeSessionLaunch {
+        session_id,
+        attachments: attachments.included,
+        output: None,
+    })
+}
+
+/// Resume an existing Claude Code session by ID with streaming output. Its
+/// events are tagged with `session_id`, which is returned.
 #[tauri::command]
 pub async fn resume_claude_code(
     app: AppHandle,
+    db: tauri::State<'_, super::agents::AgentDb>,
     project_path: String,
     session_id: String,
     prompt: String,
     model: String,
-) -> Result<(), String> {
+    options: Option<ClaudeSessionOptions>,
+) -> Result<String, String> {
     log::info!(
         "Resuming Claude Code session: {} in: {} with model: {}",
         session_id,
@@ -988,343 +1664,469 @@ pub async fn resume_claude_code(
         model
     );
 
-    let claude_path = find_claude_binary(&app)?;
-    
-    let args = vec![
-        "--resume".to_string(),
-        session_id.clone(),
-        "-p".to_string(),
-        prompt.clone(),
-        "--model".to_string(),
-        model.clone(),
-        "--output-format".to_string(),
-        "stream-json".to_string(),
-        "--verbose".to_string(),
-        "--dangerously-skip-permissions".to_string(),
-    ];
-
-    let cmd = create_system_command(&claude_path, args, &project_path);
-    spawn_claude_process(app, cmd, prompt, model, project_path).await
+    let claude_path = resolve_claude_binary(&app, &db, &project_path)?;
+    let project_prompt = project_system_prompt_for(&db, &project_path);
+    let options = options.unwrap_or_default();
+    let args = session_args(
+        SessionStart::Resume(&session_id),
+        &prompt,
+        &model,
+        project_prompt.as_deref(),
+        &options,
+        OutputFormat::StreamJson,
+    )?;
+    let env = claude_env_for(&db, options.env.as_ref());
+
+    let auto_title = super::project_dirs::ensure_project(&db, &project_path)
+        .ok()
+        .and_then(|project_id| AutoTitleWatch::start(&db, &project_id, &session_id));
+    let cmd = create_system_command(&claude_path, args, &project_path, &env);
+    let history_prompt = prompt.clone();
+    let (session_id, _) = spawn_claude_process(
+        app,
+        cmd,
+        session_id,
+        prompt,
+        model,
+        project_path.clone(),
+        RunOptions {
+            output_format: OutputFormat::StreamJson,
+            auto_title,
+            ..Default::default()
+        },
+    )
+    .await?;
+    record_prompt_or_warn(&db, &history_prompt, &project_path, Some(&session_id), false);
+    Ok(session_id)
 }
 
-/// Cancel the currently running Claude Code execution
+/// Cancel a running Claude Code session, stopping everything it started.
+/// Its queued prompts are dropped and returned, so they can be restored to
+/// the input box.
 #[tauri::command]
 pub async fn cancel_claude_execution(
     app: AppHandle,
-    session_id: Option<String>,
-) -> Result<(), String> {
+    session_id: String,
+) -> Result<Vec<QueuedPrompt>, String> {
     log::info!(
-        "Cancelling Claude Code execution for session: {:?}",
+        "Cancelling Claude Code execution for session: {}",
         session_id
     );
 
-    let mut killed = false;
-    let mut attempted_methods = Vec::new();
-
-    // Method 1: Try to find and kill via ProcessRegistry using session ID
-    if let Some(sid) = &session_id {
-        let registry = app.state::<crate::process::ProcessRegistryState>();
-        match registry.0.get_claude_session_by_id(sid) {
-            Ok(Some(process_info)) => {
-                log::info!("Found process in registry for session {}: run_id={}, PID={}", 
-                    sid, process_info.run_id, process_info.pid);
-                match registry.0.kill_process(process_info.run_id).await {
-                    Ok(success) => {
-                        if success {
-                            log::info!("Successfully killed process via registry");
-                            killed = true;
-                        } else {
-                            log::warn!("Registry kill returned false");
-                        }
-                    }
-                    Err(e) => {
-                        log::warn!("Failed to kill via registry: {}", e);
-                    }
-                }
-                attempted_methods.push("registry");
-            }
-            Ok(None) => {
-                log::warn!("Session {} not found in ProcessRegistry", sid);
-            }
-            Err(e) => {
-                log::error!("Error querying ProcessRegistry: {}", e);
-            }
-        }
-    }
-
-    // Method 2: Try the legacy approach via ClaudeProcessState
-    if !killed {
-        let claude_state = app.state::<ClaudeProcessState>();
-        let mut current_process = claude_state.current_process.lock().await;
-
-        if let Some(mut child) = current_process.take() {
-            // Try to get the PID before killing
-            let pid = child.id();
-            log::info!("Attempting to kill Claude process via ClaudeProcessState with PID: {:?}", pid);
-
-            // Kill the process
-            match child.kill().await {
-                Ok(_) => {
-                    log::info!("Successfully killed Claude process via ClaudeProcessState");
-                    killed = true;
-                }
-                Err(e) => {
-                    log::error!("Failed to kill Claude process via ClaudeProcessState: {}", e);
-                    
-                    // Method 3: If we have a PID, try system kill as last resort
-                    if let Some(pid) = pid {
-                        log::info!("Attempting system kill as last resort for PID: {}", pid);
-                        let kill_result = if cfg!(target_os = "windows") {
-                            std::process::Command::new("taskkill")
-                                .args(["/F", "/PID", &pid.to_string()])
-                                .output()
-                        } else {
-                            std::process::Command::new("kill")
-                                .args(["-KILL", &pid.to_string()])
-                                .output()
-                        };
-                        
-                        match kill_result {
-                            Ok(output) if output.status.success() => {
-                                log::info!("Successfully killed process via system command");
-                                killed = true;
-                            }
-                            Ok(output) => {
-                                let stderr = String::from_utf8_lossy(&output.stderr);
-                                log::error!("System kill failed: {}", stderr);
-                            }
-                            Err(e) => {
-                                log::error!("Failed to execute system kill command: {}", e);
-                            }
-                        }
-                    }
-                }
-            }
-            attempted_methods.push("claude_state");
-        } else {
-            log::warn!("No active Claude process in ClaudeProcessState");
+    let claude_state = app.state::<ClaudeProcessState>();
+    let (process, cleared) = {
+        let mut sessions = claude_state.sessions.lock().await;
+        (
+            sessions.remove(&session_id),
+            sessions.clear_queue(&session_id),
+        )
+    };
+    match process {
+        Some(mut process) => {
+            let outcome = crate::process::terminate_process_group(
+                &mut process.child,
+                process.pid,
+                CANCEL_GRACE,
+            )
+            .await;
+            log::info!(
+                "Stopped Claude process group {}: {:?}",
+                process.pid,
+                outcome
+            );
         }
+        None => log::warn!("No running Claude process for session {}", session_id),
     }
 
-    if !killed && attempted_methods.is_empty() {
-        log::warn!("No active Claude process found to cancel");
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    if let Some(process_info) = registry.0.get_claude_session_by_id(&session_id)? {
+        registry.0.unregister_process(process_info.run_id)?;
     }
 
-    // Always emit cancellation events for UI consistency
-    if let Some(sid) = session_id {
-        let _ = app.emit(&format!("claude-cancelled:{}", sid), true);
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-        let _ = app.emit(&format!("claude-complete:{}", sid), false);
-    }
-    
-    // Also emit generic events for backward compatibility
-    let _ = app.emit("claude-cancelled", true);
+    let _ = app.emit(&format!("claude-cancelled:{}", session_id), true);
     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-    let _ = app.emit("claude-complete", false);
-    
-    if killed {
-        log::info!("Claude process cancellation completed successfully");
-    } else if !attempted_methods.is_empty() {
-        log::warn!("Claude process cancellation attempted but process may have already exited. Attempted methods: {:?}", attempted_methods);
+    let _ = app.emit(&format!("claude-complete:{}", session_id), false);
+
+    Ok(cleared)
+}
+
+/// Queue a prompt behind the running session's current turn. Once the turn
+/// completes it's sent with `--continue` and the same model, and
+/// `prompt-dequeued` is emitted. Returns how many prompts are waiting.
+#[tauri::command]
+pub async fn queue_prompt(
+    app: AppHandle,
+    registry: tauri::State<'_, crate::process::ProcessRegistryState>,
+    session_id: String,
+    prompt: String,
+    options: Option<ClaudeSessionOptions>,
+) -> Result<usize, String> {
+    if prompt.trim().is_empty() {
+        return Err("Prompt cannot be empty".to_string());
     }
-    
-    Ok(())
+    if let Some(options) = &options {
+        options.validate()?;
+    }
+    let process_info = registry
+        .0
+        .get_claude_session_by_id(&session_id)?
+        .ok_or_else(|| {
+            format!(
+                "Session {} is not running; send the prompt directly",
+                session_id
+            )
+        })?;
+    let queued = QueuedPrompt {
+        prompt,
+        project_path: process_info.project_path,
+        model: process_info.model,
+        options,
+    };
+    let claude_state = app.state::<ClaudeProcessState>();
+    let waiting = claude_state
+        .sessions
+        .lock()
+        .await
+        .queue_prompt(&session_id, queued)?;
+    log::info!(
+        "Queued a prompt for session {} ({} waiting)",
+        session_id,
+        waiting
+    );
+    Ok(waiting)
+}
+
+/// The prompts waiting for a session's current turn, in the order they'll be sent
+#[tauri::command]
+pub async fn list_queued_prompts(
+    app: AppHandle,
+    session_id: String,
+) -> Result<Vec<QueuedPrompt>, String> {
+    let claude_state = app.state::<ClaudeProcessState>();
+    let sessions = claude_state.sessions.lock().await;
+    Ok(sessions.queued_prompts(&session_id))
+}
+
+/// Take a prompt out of a session's queue, returning it
+#[tauri::command]
+pub async fn remove_queued_prompt(
+    app: AppHandle,
+    session_id: String,
+    index: usize,
+) -> Result<QueuedPrompt, String> {
+    let claude_state = app.state::<ClaudeProcessState>();
+    let mut sessions = claude_state.sessions.lock().await;
+    sessions.remove_queued_prompt(&session_id, index)
+}
+
+/// Send a queued prompt in the background, continuing the session under the
+/// same `session_id` so its events reach the same listeners
+fn submit_queued_prompt(app: AppHandle, session_id: String, queued: QueuedPrompt) {
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<super::agents::AgentDb>();
+        let result = continue_claude_code(
+            app.clone(),
+            db,
+            queued.project_path,
+            queued.prompt,
+            queued.model,
+            queued.options,
+            Some(session_id.clone()),
+            None,
+        )
+        .await;
+        if let Err(e) = result {
+            log::error!(
+                "Failed to send queued prompt for session {}: {}",
+                session_id,
+                e
+            );
+            let _ = app.emit(&format!("claude-error:{}", session_id), &e);
+        }
+    });
 }
 
-/// Get all running Claude sessions
+/// Get all running Claude sessions with the CPU and memory each is using.
+/// Sessions whose process has exited are flagged stale and unregistered.
 #[tauri::command]
 pub async fn list_running_claude_sessions(
     registry: tauri::State<'_, crate::process::ProcessRegistryState>,
-) -> Result<Vec<crate::process::ProcessInfo>, String> {
-    registry.0.get_running_claude_sessions()
+) -> Result<Vec<WithResources<crate::process::ProcessInfo>>, String> {
+    let sessions = registry.0.get_running_claude_sessions()?;
+    let resources = registry.0.sample_resources(&sessions)?;
+    Ok(sessions
+        .into_iter()
+        .zip(resources)
+        .map(|(item, resources)| WithResources { item, resources })
+        .collect())
+}
+
+/// Part of a Claude session's live output
+#[derive(Debug, Default, Serialize)]
+pub struct SessionOutput {
+    #[serde(flatten)]
+    pub chunk: OutputChunk,
+    /// With an offset, more output follows `next_offset`; without one, older
+    /// output before `offset` was left out
+    pub truncated: bool,
+    pub warning: Option<String>,
 }
 
-/// Get live output from a Claude session
+/// Get live output from a Claude session. With an offset this is up to
+/// `max_bytes` of complete lines from that byte offset; pass `next_offset`
+/// back to keep reading. Without one it is the whole output, cut to its last
+/// `max_bytes` with a warning when longer.
 #[tauri::command]
 pub async fn get_claude_session_output(
     registry: tauri::State<'_, crate::process::ProcessRegistryState>,
     session_id: String,
-) -> Result<String, String> {
+    offset: Option<u64>,
+    max_bytes: Option<usize>,
+) -> Result<SessionOutput, String> {
     // Find the process by session ID
-    if let Some(process_info) = registry.0.get_claude_session_by_id(&session_id)? {
-        registry.0.get_live_output(process_info.run_id)
-    } else {
-        Ok(String::new())
-    }
+    let Some(process_info) = registry.0.get_claude_session_by_id(&session_id)? else {
+        return Ok(SessionOutput::default());
+    };
+
+    let max_bytes = max_bytes
+        .unwrap_or(MAX_OUTPUT_CHUNK_BYTES)
+        .clamp(1, MAX_OUTPUT_CHUNK_BYTES);
+    let chunk = registry
+        .0
+        .read_output_from(process_info.run_id, offset, max_bytes)?;
+    let (truncated, warning) = match offset {
+        Some(_) => (chunk.next_offset < chunk.total_bytes, None),
+        None if chunk.offset > 0 => (
+            true,
+            Some(format!(
+                "Output is {} bytes; only the last {} were returned. Pass an offset to read the rest.",
+                chunk.total_bytes,
+                chunk.total_bytes - chunk.offset
+            )),
+        ),
+        None => (false, None),
+    };
+    Ok(SessionOutput {
+        chunk,
+        truncated,
+        warning,
+    })
 }
 
-/// Helper function to spawn Claude process and handle streaming
-async fn spawn_claude_process(app: AppHandle, mut cmd: Command, prompt: String, model: String, project_path: String) -> Result<(), String> {
-    use tokio::io::{AsyncBufReadExt, BufReader};
-    use std::sync::Mutex;
+/// How a spawned Claude process is read and what happens once it exits
+#[derive(Default)]
+struct RunOptions {
+    output_format: OutputFormat,
+    /// Title the session in the background if this run gives it a second answer
+    auto_title: Option<AutoTitleWatch>,
+    /// Told whether the process succeeded once it exits. Dropped unsent if
+    /// the session is cancelled.
+    on_exit: Option<tokio::sync::oneshot::Sender<bool>>,
+}
 
-    // Spawn the process
-    let mut child = cmd
-        .spawn()
-        .map_err(|e| format!("Failed to spawn Claude: {}", e))?;
+/// Helper function to spawn Claude process and handle streaming. The process
+/// is tracked under `session_id`, which tags its events and is returned.
+///
+/// Output that isn't `stream-json` isn't streamed or parsed: stdout is kept
+/// whole and delivered through the returned receiver once Claude exits.
+async fn spawn_claude_process(
+    app: AppHandle,
+    cmd: Command,
+    session_id: String,
+    prompt: String,
+    model: String,
+    project_path: String,
+    run: RunOptions,
+) -> Result<(String, Option<tokio::sync::oneshot::Receiver<String>>), String> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
 
-    // Get stdout and stderr
-    let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
-    let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
+    let max_sessions = get_max_claude_sessions_setting(&app.state::<super::agents::AgentDb>())?;
 
-    // Get the child PID for logging
-    let pid = child.id().unwrap_or(0);
+    // Spawn the process while holding the session map, so concurrent launches
+    // can't exceed the cap
+    let claude_state = app.state::<ClaudeProcessState>();
+    let program = cmd.as_std().get_program().to_os_string();
+    // Refuse a binary that no longer matches a pinned checksum, and say which
+    // runtime is missing for Bun and Deno shims
+    check_binary_pin(program).await?;
+    crate::claude_binary::check_shim_runtime(cmd.as_std())?;
+    let (stdout, stderr, pid, binary) = {
+        let mut sessions = claude_state.sessions.lock().await;
+        sessions.check_can_start(&session_id, max_sessions)?;
+        // Falls back to another installation when the configured one won't
+        // spawn, saying what's wrong with each binary if none will
+        let launch = spawn_claude_with_fallback(&app, cmd).await?;
+        let spawned = launch.spawned;
+        let pid = spawned.child.id().unwrap_or(0);
+        sessions.insert(
+            session_id.clone(),
+            ClaudeSessionProcess {
+                child: spawned.child,
+                pid,
+            },
+        );
+        (spawned.stdout, spawned.stderr, pid, launch.binary)
+    };
     log::info!(
-        "Spawned Claude process with PID: {:?}",
-        pid
+        "Spawned Claude process with PID {} for session {}",
+        pid,
+        session_id
     );
 
-    // Create readers first (before moving child)
-    let stdout_reader = BufReader::new(stdout);
-    let stderr_reader = BufReader::new(stderr);
-
-    // We'll extract the session ID from Claude's init message
-    let session_id_holder: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
-    let run_id_holder: Arc<Mutex<Option<i64>>> = Arc::new(Mutex::new(None));
-
-    // Store the child process in the global state (for backward compatibility)
-    let claude_state = app.state::<ClaudeProcessState>();
-    {
-        let mut current_process = claude_state.current_process.lock().await;
-        // If there's already a process running, kill it first
-        if let Some(mut existing_child) = current_process.take() {
-            log::warn!("Killing existing Claude process before starting new one");
-            let _ = existing_child.kill().await;
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    let run_id = match registry.0.register_claude_session(
+        session_id.clone(),
+        pid,
+        project_path,
+        prompt,
+        model,
+        binary,
+    ) {
+        Ok(run_id) => Some(run_id),
+        Err(e) => {
+            log::error!("Failed to register Claude session: {}", e);
+            None
         }
-        *current_process = Some(child);
-    }
+    };
 
     // Spawn tasks to read stdout and stderr
     let app_handle = app.clone();
-    let session_id_holder_clone = session_id_holder.clone();
-    let run_id_holder_clone = run_id_holder.clone();
-    let registry = app.state::<crate::process::ProcessRegistryState>();
     let registry_clone = registry.0.clone();
-    let project_path_clone = project_path.clone();
-    let prompt_clone = prompt.clone();
-    let model_clone = model.clone();
-    let stdout_task = tokio::spawn(async move {
-        let mut lines = stdout_reader.lines();
-        while let Ok(Some(line)) = lines.next_line().await {
-            log::debug!("Claude stdout: {}", line);
-            
-            // Parse the line to check for init message with session ID
-            if let Ok(msg) = serde_json::from_str::<serde_json::Value>(&line) {
-                if msg["type"] == "system" && msg["subtype"] == "init" {
-                    if let Some(claude_session_id) = msg["session_id"].as_str() {
-                        let mut session_id_guard = session_id_holder_clone.lock().unwrap();
-                        if session_id_guard.is_none() {
-                            *session_id_guard = Some(claude_session_id.to_string());
-                            log::info!("Extracted Claude session ID: {}", claude_session_id);
-                            
-                            // Now register with ProcessRegistry using Claude's session ID
-                            match registry_clone.register_claude_session(
-                                claude_session_id.to_string(),
-                                pid,
-                                project_path_clone.clone(),
-                                prompt_clone.clone(),
-                                model_clone.clone(),
-                            ) {
-                                Ok(run_id) => {
-                                    log::info!("Registered Claude session with run_id: {}", run_id);
-                                    let mut run_id_guard = run_id_holder_clone.lock().unwrap();
-                                    *run_id_guard = Some(run_id);
-                                }
-                                Err(e) => {
-                                    log::error!("Failed to register Claude session: {}", e);
-                                }
-                            }
-                        }
-                    }
-                }
+    let output_event = format!("claude-output:{}", session_id);
+    app.state::<LiveUsageState>().reset(&session_id);
+    let mut live_usage = LiveSessionUsage::new(session_id.clone());
+    let mut final_output = None;
+    let stdout_task = if !run.output_format.is_streaming() {
+        let (output_tx, output_rx) = tokio::sync::oneshot::channel();
+        final_output = Some(output_rx);
+        tokio::spawn(async move {
+            let mut stdout = stdout;
+            let mut bytes = Vec::new();
+            if let Err(e) = stdout.read_to_end(&mut bytes).await {
+                log::error!("Failed to read Claude output: {}", e);
             }
-            
-            // Store live output in registry if we have a run_id
-            if let Some(run_id) = *run_id_holder_clone.lock().unwrap() {
-                let _ = registry_clone.append_live_output(run_id, &line);
+            let output = String::from_utf8_lossy(&bytes).into_owned();
+            if let Some(run_id) = run_id {
+                let _ = registry_clone.append_live_output(run_id, &output);
             }
-            
-            // Emit the line to the frontend with session isolation if we have session ID
-            if let Some(ref session_id) = *session_id_holder_clone.lock().unwrap() {
-                let _ = app_handle.emit(&format!("claude-output:{}", session_id), &line);
+            let _ = output_tx.send(output);
+        })
+    } else {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                log::debug!("Claude stdout: {}", line);
+                if let Some(run_id) = run_id {
+                    let _ = registry_clone.append_live_output(run_id, &line);
+                }
+                let _ = app_handle.emit(&output_event, &line);
+
+                let update = serde_json::from_str::<serde_json::Value>(&line)
+                    .ok()
+                    .and_then(|json| live_usage.record(&json));
+                if let Some(update) = update {
+                    app_handle.state::<LiveUsageState>().store(&update);
+                    let _ = app_handle.emit(SESSION_USAGE_EVENT, &update);
+                }
             }
-            // Also emit to the generic event for backward compatibility
-            let _ = app_handle.emit("claude-output", &line);
-        }
-    });
+        })
+    };
 
     let app_handle_stderr = app.clone();
-    let session_id_holder_clone2 = session_id_holder.clone();
+    let error_event = format!("claude-error:{}", session_id);
     let stderr_task = tokio::spawn(async move {
-        let mut lines = stderr_reader.lines();
+        let mut lines = BufReader::new(stderr).lines();
         while let Ok(Some(line)) = lines.next_line().await {
             log::error!("Claude stderr: {}", line);
-            // Emit error lines to the frontend with session isolation if we have session ID
-            if let Some(ref session_id) = *session_id_holder_clone2.lock().unwrap() {
-                let _ = app_handle_stderr.emit(&format!("claude-error:{}", session_id), &line);
-            }
-            // Also emit to the generic event for backward compatibility
-            let _ = app_handle_stderr.emit("claude-error", &line);
+            let _ = app_handle_stderr.emit(&error_event, &line);
         }
     });
 
     // Wait for the process to complete
     let app_handle_wait = app.clone();
-    let claude_state_wait = claude_state.current_process.clone();
-    let session_id_holder_clone3 = session_id_holder.clone();
-    let run_id_holder_clone2 = run_id_holder.clone();
-    let registry_clone2 = registry.0.clone();
+    let sessions_wait = claude_state.sessions.clone();
+    let registry_wait = registry.0.clone();
+    let session_id_wait = session_id.clone();
+    let auto_title = run.auto_title;
+    let on_exit = run.on_exit;
     tokio::spawn(async move {
         let _ = stdout_task.await;
         let _ = stderr_task.await;
 
-        // Get the child from the state to wait on it
-        let mut current_process = claude_state_wait.lock().await;
-        if let Some(mut child) = current_process.take() {
-            match child.wait().await {
+        // A cancelled session was already taken out of the map and its events emitted
+        let process = sessions_wait
+            .lock()
+            .await
+            .remove_exited(&session_id_wait, pid);
+        if let Some(mut process) = process {
+            let success = match process.child.wait().await {
                 Ok(status) => {
                     log::info!("Claude process exited with status: {}", status);
-                    // Add a small delay to ensure all messages are processed
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
-                        let _ = app_handle_wait.emit(
-                            &format!("claude-complete:{}", session_id),
-                            status.success(),
-                        );
-                    }
-                    // Also emit to the generic event for backward compatibility
-                    let _ = app_handle_wait.emit("claude-complete", status.success());
+                    status.success()
                 }
                 Err(e) => {
                     log::error!("Failed to wait for Claude process: {}", e);
-                    // Add a small delay to ensure all messages are processed
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    if let Some(ref session_id) = *session_id_holder_clone3.lock().unwrap() {
-                        let _ = app_handle_wait
-                            .emit(&format!("claude-complete:{}", session_id), false);
-                    }
-                    // Also emit to the generic event for backward compatibility
-                    let _ = app_handle_wait.emit("claude-complete", false);
+                    false
+                }
+            };
+            // Add a small delay to ensure all messages are processed
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            let _ = app_handle_wait.emit(&format!("claude-complete:{}", session_id_wait), success);
+            if let Some(on_exit) = on_exit {
+                let _ = on_exit.send(success);
+            }
+
+            // Send the next queued prompt, or give the queue back if the turn failed
+            let mut sessions = sessions_wait.lock().await;
+            if success {
+                if let Some((next, remaining)) = sessions.next_queued_prompt(&session_id_wait) {
+                    drop(sessions);
+                    let _ = app_handle_wait.emit(
+                        "prompt-dequeued",
+                        serde_json::json!({
+                            "session_id": session_id_wait,
+                            "prompt": next.prompt,
+                            "remaining": remaining,
+                        }),
+                    );
+                    submit_queued_prompt(app_handle_wait.clone(), session_id_wait.clone(), next);
+                }
+            } else {
+                let cleared = sessions.clear_queue(&session_id_wait);
+                drop(sessions);
+                if !cleared.is_empty() {
+                    let _ = app_handle_wait.emit(
+                        "prompt-queue-cleared",
+                        serde_json::json!({
+                            "session_id": session_id_wait,
+                            "prompts": cleared,
+                        }),
+                    );
                 }
             }
         }
 
-        // Unregister from ProcessRegistry if we have a run_id
-        if let Some(run_id) = *run_id_holder_clone2.lock().unwrap() {
-            let _ = registry_clone2.unregister_process(run_id);
+        if let Some(auto_title) = auto_title {
+            auto_title.finish(&app_handle_wait);
         }
 
-        // Clear the process from state
-        *current_process = None;
+        // Unregister from ProcessRegistry if we have a run_id
+        if let Some(run_id) = run_id {
+            let _ = registry_wait.unregister_process(run_id);
+        }
     });
 
-    Ok(())
+    Ok((session_id, final_output))
 }
 
 
-/// Lists files and directories in a given path
+/// List one page of a directory's entries with their metadata. Hidden
+/// entries other than `.claude` are skipped unless `options` say otherwise.
 #[tauri::command]
-pub async fn list_directory_contents(directory_path: String) -> Result<Vec<FileEntry>, String> {
+pub async fn list_directory_contents(
+    directory_path: String,
+    options: Option<DirectoryListingOptions>,
+) -> Result<DirectoryListing, String> {
     log::info!("Listing directory contents: '{}'", directory_path);
 
     // Check if path is empty
@@ -1346,62 +2148,30 @@ pub async fn list_directory_contents(directory_path: String) -> Result<Vec<FileE
         return Err(format!("Path is not a directory: {}", directory_path));
     }
 
-    let mut entries = Vec::new();
-
-    let dir_entries =
-        fs::read_dir(&path).map_err(|e| format!("Failed to read directory: {}", e))?;
-
-    for entry in dir_entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let entry_path = entry.path();
-        let metadata = entry
-            .metadata()
-            .map_err(|e| format!("Failed to read metadata: {}", e))?;
-
-        // Skip hidden files/directories unless they are .claude directories
-        if let Some(name) = entry_path.file_name().and_then(|n| n.to_str()) {
-            if name.starts_with('.') && name != ".claude" {
-                continue;
-            }
-        }
-
-        let name = entry_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("")
-            .to_string();
-
-        let extension = if metadata.is_file() {
-            entry_path
-                .extension()
-                .and_then(|e| e.to_str())
-                .map(|e| e.to_string())
-        } else {
-            None
-        };
-
-        entries.push(FileEntry {
-            name,
-            path: entry_path.to_string_lossy().to_string(),
-            is_directory: metadata.is_dir(),
-            size: metadata.len(),
-            extension,
-        });
+    let options = options.unwrap_or_default();
+    let listing =
+        tokio::task::spawn_blocking(move || directory_listing::list_directory(&path, &options))
+            .await
+            .map_err(|e| format!("Directory listing failed: {}", e))??;
+    if listing.skipped > 0 {
+        log::warn!(
+            "Skipped {} unreadable entries in {}",
+            listing.skipped,
+            directory_path
+        );
     }
-
-    // Sort: directories first, then files, alphabetically within each group
-    entries.sort_by(|a, b| match (a.is_directory, b.is_directory) {
-        (true, false) => std::cmp::Ordering::Less,
-        (false, true) => std::cmp::Ordering::Greater,
-        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-    });
-
-    Ok(entries)
+    Ok(listing)
 }
 
-/// Search for files and directories matching a pattern
+/// Search for files and directories whose names match `query`, best matches
+/// first. Ignore files are honored and hidden entries skipped unless
+/// `options` say otherwise.
 #[tauri::command]
-pub async fn search_files(base_path: String, query: String) -> Result<Vec<FileEntry>, String> {
+pub async fn search_files(
+    base_path: String,
+    query: String,
+    options: Option<FileSearchOptions>,
+) -> Result<Vec<FileSearchResult>, String> {
     log::info!("Searching files in '{}' for: '{}'", base_path, query);
 
     // Check if path is empty
@@ -1424,96 +2194,17 @@ pub async fn search_files(base_path: String, query: String) -> Result<Vec<FileEn
         return Err(format!("Path does not exist: {}", base_path));
     }
 
-    let query_lower = query.to_lowercase();
-    let mut results = Vec::new();
-
-    search_files_recursive(&path, &path, &query_lower, &mut results, 0)?;
-
-    // Sort by relevance: exact matches first, then by name
-    results.sort_by(|a, b| {
-        let a_exact = a.name.to_lowercase() == query_lower;
-        let b_exact = b.name.to_lowercase() == query_lower;
-
-        match (a_exact, b_exact) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-        }
-    });
-
-    // Limit results to prevent overwhelming the UI
-    results.truncate(50);
-
-    Ok(results)
-}
-
-fn search_files_recursive(
-    current_path: &PathBuf,
-    base_path: &PathBuf,
-    query: &str,
-    results: &mut Vec<FileEntry>,
-    depth: usize,
-) -> Result<(), String> {
-    // Limit recursion depth to prevent excessive searching
-    if depth > 5 || results.len() >= 50 {
-        return Ok(());
-    }
-
-    let entries = fs::read_dir(current_path)
-        .map_err(|e| format!("Failed to read directory {:?}: {}", current_path, e))?;
-
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let entry_path = entry.path();
-
-        // Skip hidden files/directories
-        if let Some(name) = entry_path.file_name().and_then(|n| n.to_str()) {
-            if name.starts_with('.') {
-                continue;
-            }
-
-            // Check if name matches query
-            if name.to_lowercase().contains(query) {
-                let metadata = entry
-                    .metadata()
-                    .map_err(|e| format!("Failed to read metadata: {}", e))?;
-
-                let extension = if metadata.is_file() {
-                    entry_path
-                        .extension()
-                        .and_then(|e| e.to_str())
-                        .map(|e| e.to_string())
-                } else {
-                    None
-                };
-
-                results.push(FileEntry {
-                    name: name.to_string(),
-                    path: entry_path.to_string_lossy().to_string(),
-                    is_directory: metadata.is_dir(),
-                    size: metadata.len(),
-                    extension,
-                });
-            }
-        }
-
-        // Recurse into directories
-        if entry_path.is_dir() {
-            // Skip common directories that shouldn't be searched
-            if let Some(dir_name) = entry_path.file_name().and_then(|n| n.to_str()) {
-                if matches!(
-                    dir_name,
-                    "node_modules" | "target" | ".git" | "dist" | "build" | ".next" | "__pycache__"
-                ) {
-                    continue;
-                }
-            }
-
-            search_files_recursive(&entry_path, base_path, query, results, depth + 1)?;
-        }
-    }
-
-    Ok(())
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || {
+        file_search::search(
+            &path,
+            query.trim(),
+            &options,
+            file_search::SEARCH_TIME_LIMIT,
+        )
+    })
+    .await
+    .map_err(|e| format!("File search failed: {}", e))?
 }
 
 /// Creates a checkpoint for the current session state
@@ -1697,6 +2388,320 @@ pub async fn fork_from_checkpoint(
         .map_err(|e| format!("Failed to fork checkpoint: {}", e))
 }
 
+/// Restore a checkpoint's files without touching the session file
+async fn restore_checkpoint_files(
+    app: &crate::checkpoint::state::CheckpointState,
+    checkpoint_id: &str,
+    session_id: &str,
+    project_id: &str,
+    project_path: Option<String>,
+    project_dir: &std::path::Path,
+) -> Result<crate::checkpoint::CheckpointResult, String> {
+    let project_path = match project_path {
+        Some(project_path) => project_path,
+        None => super::project_dirs::resolve_project_path(project_dir)?,
+    };
+    let manager = app
+        .get_or_create_manager(
+            session_id.to_string(),
+            project_id.to_string(),
+            PathBuf::from(&project_path),
+        )
+        .await
+        .map_err(|e| format!("Failed to get checkpoint manager: {}", e))?;
+    manager
+        .restore_checkpoint(checkpoint_id)
+        .await
+        .map_err(|e| format!("Failed to restore checkpoint: {}", e))
+}
+
+/// Copy a session under a new ID in the same project, up to and including
+/// `message_index` or all of it. The copy appears complete or not at all, and
+/// the original isn't touched. Returns the new ID and file.
+fn copy_session(
+    project_id: &str,
+    session_id: &str,
+    message_index: Option<usize>,
+) -> Result<(String, PathBuf), String> {
+    let session_path = session_file_path(project_id, session_id)?;
+    let project_dir = session_path
+        .parent()
+        .map(PathBuf::from)
+        .ok_or_else(|| "Session file has no project directory".to_string())?;
+    let new_session_id = uuid::Uuid::new_v4().to_string();
+    let new_session_path = project_dir.join(format!("{}.jsonl", new_session_id));
+
+    let source =
+        fs::File::open(&session_path).map_err(|e| format!("Failed to open session file: {}", e))?;
+    let mut temp = tempfile::NamedTempFile::new_in(&project_dir)
+        .map_err(|e| format!("Failed to create session file: {}", e))?;
+    let reader = BufReader::new(source);
+    let writer = std::io::BufWriter::new(temp.as_file_mut());
+    match message_index {
+        Some(message_index) => super::session_history::copy_history_prefix(
+            reader,
+            writer,
+            message_index,
+            session_id,
+            &new_session_id,
+        )?,
+        None => super::session_history::copy_history(reader, writer, session_id, &new_session_id)?,
+    }
+    temp.persist(&new_session_path)
+        .map_err(|e| format!("Failed to save session file: {}", e.error))?;
+    Ok((new_session_id, new_session_path))
+}
+
+/// Start a new session from a session's history up to and including
+/// `message_index`, so Claude forgets what came after it. When
+/// `restore_checkpoint` names a checkpoint, the project's files are restored
+/// to it as well. The original session file is left untouched; the new
+/// session's ID is returned for `resume_claude_code`.
+#[tauri::command]
+pub async fn resume_session_truncated(
+    app: tauri::State<'_, crate::checkpoint::state::CheckpointState>,
+    project_id: String,
+    session_id: String,
+    message_index: usize,
+    restore_checkpoint: Option<String>,
+    project_path: Option<String>,
+) -> Result<String, String> {
+    log::info!(
+        "Resuming session {} from message {}",
+        session_id,
+        message_index
+    );
+
+    let (new_session_id, new_session_path) =
+        copy_session(&project_id, &session_id, Some(message_index))?;
+    let project_dir = new_session_path
+        .parent()
+        .map(PathBuf::from)
+        .ok_or_else(|| "Session file has no project directory".to_string())?;
+
+    if let Some(checkpoint_id) = restore_checkpoint {
+        let restored = restore_checkpoint_files(
+            &app,
+            &checkpoint_id,
+            &session_id,
+            &project_id,
+            project_path,
+            &project_dir,
+        )
+        .await;
+        match restored {
+            Ok(result) => {
+                for warning in &result.warnings {
+                    log::warn!("Restoring checkpoint {}: {}", checkpoint_id, warning);
+                }
+            }
+            Err(e) => {
+                // Don't leave a session behind whose files weren't restored
+                let _ = fs::remove_file(&new_session_path);
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(new_session_id)
+}
+
+/// What a resumed session is started with
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionLaunch {
+    pub prompt: String,
+    /// The project's default model when unset
+    pub model: Option<String>,
+    pub options: Option<ClaudeSessionOptions>,
+}
+
+/// Branch a session: copy it, or its history up to and including
+/// `message_index`, under a new ID and resume the copy with `launch`. The
+/// copy is listed with the project's sessions before Claude starts, and the
+/// original is left as it was. Returns the new session's ID, which the
+/// process and its events are tagged with.
+#[tauri::command]
+pub async fn fork_and_resume_session(
+    app: AppHandle,
+    db: tauri::State<'_, super::agents::AgentDb>,
+    project_id: String,
+    session_id: String,
+    message_index: Option<usize>,
+    launch: SessionLaunch,
+) -> Result<String, String> {
+    log::info!("Forking session {} to resume it", session_id);
+
+    let (new_session_id, new_session_path) = copy_session(&project_id, &session_id, message_index)?;
+    let project_path = new_session_path
+        .parent()
+        .ok_or_else(|| "Session file has no project directory".to_string())
+        .and_then(super::project_dirs::resolve_project_path);
+    let resumed = match project_path {
+        Ok(project_path) => {
+            let model = resolve_model(&db, &project_path, launch.model, false);
+            resume_claude_code(
+                app,
+                db,
+                project_path,
+                new_session_id,
+                launch.prompt,
+                model,
+                launch.options,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    if resumed.is_err() {
+        // Don't leave a branch behind that Claude never ran in
+        let _ = fs::remove_file(&new_session_path);
+    }
+    resumed
+}
+
+/// How long `compact_session` waits for Claude before cancelling it
+const COMPACT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// Why a session couldn't be compacted
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CompactError {
+    /// The installed Claude Code can't compact without a terminal
+    UnsupportedVersion {
+        detected_version: String,
+        required_version: String,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl From<String> for CompactError {
+    fn from(message: String) -> Self {
+        CompactError::Failed { message }
+    }
+}
+
+/// A session after `/compact`. Claude may have continued it under a new ID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactedSession {
+    pub session_id: String,
+    pub summary: String,
+    /// Messages in the history from the summary on
+    pub message_count: usize,
+}
+
+/// The compaction the last run wrote: in the newest session file of the
+/// project changed since `since`
+fn find_compaction(project_dir: &PathBuf, since: SystemTime) -> Option<CompactedSession> {
+    let mut changed: Vec<(SystemTime, PathBuf)> = fs::read_dir(project_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            (modified >= since).then_some((modified, path))
+        })
+        .collect();
+    changed.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    changed.into_iter().find_map(|(_, path)| {
+        let file = fs::File::open(&path).ok()?;
+        let compaction = super::session_history::read_compaction(BufReader::new(file))?;
+        Some(CompactedSession {
+            session_id: path.file_stem()?.to_string_lossy().into_owned(),
+            summary: compaction.summary,
+            message_count: compaction.message_count,
+        })
+    })
+}
+
+/// Run `/compact` on a session without the terminal, focused by
+/// `instructions` if given, and wait for it. Progress streams through the
+/// usual events of the session's ID.
+#[tauri::command]
+pub async fn compact_session(
+    app: AppHandle,
+    db: tauri::State<'_, super::agents::AgentDb>,
+    project_id: String,
+    session_id: String,
+    instructions: Option<String>,
+) -> Result<CompactedSession, CompactError> {
+    log::info!("Compacting session {}", session_id);
+
+    let session_path = session_file_path(&project_id, &session_id)?;
+    let project_dir = session_path
+        .parent()
+        .map(PathBuf::from)
+        .ok_or_else(|| "Session file has no project directory".to_string())?;
+    let project_path = super::project_dirs::resolve_project_path(&project_dir)?;
+    let claude_path = resolve_claude_binary(&app, &db, &project_path)?;
+
+    // An unknown version is let through, like other version checks
+    let installed = installed_claude_version(&claude_path).await;
+    if let (Some(installed), Some(required)) = (installed, parse_version(COMPACT_MINIMUM_VERSION)) {
+        if installed.compare(&required) == std::cmp::Ordering::Less {
+            return Err(CompactError::UnsupportedVersion {
+                detected_version: installed.raw,
+                required_version: required.raw,
+            });
+        }
+    }
+
+    let instructions = instructions.as_deref().map(str::trim).unwrap_or_default();
+    let prompt = if instructions.is_empty() {
+        "/compact".to_string()
+    } else {
+        format!("/compact {}", instructions)
+    };
+    let model = resolve_model(&db, &project_path, None, false);
+    let project_prompt = project_system_prompt_for(&db, &project_path);
+    let args = session_args(
+        SessionStart::Resume(&session_id),
+        &prompt,
+        &model,
+        project_prompt.as_deref(),
+        &ClaudeSessionOptions::default(),
+        OutputFormat::StreamJson,
+    )?;
+    let env = claude_env_for(&db, None);
+    let cmd = create_system_command(&claude_path, args, &project_path, &env);
+
+    let started = SystemTime::now();
+    let (exit_tx, exit_rx) = tokio::sync::oneshot::channel();
+    spawn_claude_process(
+        app.clone(),
+        cmd,
+        session_id.clone(),
+        prompt,
+        model,
+        project_path,
+        RunOptions {
+            on_exit: Some(exit_tx),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    match tokio::time::timeout(COMPACT_TIMEOUT, exit_rx).await {
+        Ok(Ok(true)) => {}
+        Ok(Ok(false)) => return Err("Claude failed to compact the session".to_string().into()),
+        Ok(Err(_)) => return Err("Compacting the session was cancelled".to_string().into()),
+        Err(_) => {
+            let _ = cancel_claude_execution(app, session_id).await;
+            return Err(format!(
+                "Compacting the session took longer than {} minutes",
+                COMPACT_TIMEOUT.as_secs() / 60
+            )
+            .into());
+        }
+    }
+
+    find_compaction(&project_dir, started).ok_or_else(|| CompactError::Failed {
+        message: "Claude finished without compacting the session".to_string(),
+    })
+}
+
 /// Gets the timeline for a session
 #[tauri::command]
 pub async fn get_session_timeline(
@@ -2072,9 +3077,11 @@ pub async fn get_hooks_config(scope: String, project_path: Option<String>) -> Re
     Ok(settings.get("hooks").cloned().unwrap_or(serde_json::json!({})))
 }
 
-/// Updates hooks configuration in settings at specified scope
+/// Updates hooks configuration in settings at specified scope. The hooks are
+/// validated first, and nothing is written when they are invalid.
 #[tauri::command]
 pub async fn update_hooks_config(
+    db: tauri::State<'_, super::agents::AgentDb>,
     scope: String, 
     hooks: serde_json::Value,
     project_path: Option<String>
@@ -2104,40 +3111,54 @@ pub async fn update_hooks_config(
         _ => return Err("Invalid scope".to_string())
     };
 
-    // Read existing settings or create new
-    let mut settings = if settings_path.exists() {
-        let content = fs::read_to_string(&settings_path)
-            .map_err(|e| format!("Failed to read settings: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse settings: {}", e))?
+    // Read existing settings, if any
+    let existing = if settings_path.exists() {
+        Some(
+            fs::read_to_string(&settings_path)
+                .map_err(|e| format!("Failed to read settings: {}", e))?,
+        )
     } else {
-        serde_json::json!({})
+        None
     };
 
-    // Update hooks section
-    settings["hooks"] = hooks;
-
-    // Write back with pretty formatting
-    let json_string = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-    
-    fs::write(&settings_path, json_string)
-        .map_err(|e| format!("Failed to write settings: {}", e))?;
+    // Validate and update the hooks section; unchanged hooks leave the file as is
+    if let Some(json_string) = super::hooks_config::apply_hooks(existing.as_deref(), hooks)? {
+        write_settings(&settings_path, &json_string)?;
+        record_snapshot_or_warn(&db, &settings_path, "update_hooks_config", &json_string);
+    }
 
     Ok("Hooks configuration updated successfully".to_string())
 }
 
-/// Validates a hook command by dry-running it
+/// Checks a hooks configuration without saving it, listing each problem with
+/// its JSON path
+#[tauri::command]
+pub async fn validate_hooks_config(
+    hooks: serde_json::Value,
+) -> Result<Vec<super::hooks_config::HookConfigIssue>, String> {
+    Ok(super::hooks_config::validate_hooks(&hooks))
+}
+
+/// Validates a hook command's syntax in the shell it will run in, without
+/// executing it. `shell` is the hook's `shell` field.
 #[tauri::command]
-pub async fn validate_hook_command(command: String) -> Result<serde_json::Value, String> {
+pub async fn validate_hook_command(
+    command: String,
+    shell: Option<String>,
+) -> Result<serde_json::Value, String> {
     log::info!("Validating hook command syntax");
 
-    // Validate syntax without executing
-    let mut cmd = std::process::Command::new("bash");
-    cmd.arg("-n") // Syntax check only
-       .arg("-c")
-       .arg(&command);
-    
+    let shell = super::hook_shell::HookShell::parse(shell.as_deref())?;
+    let mut cmd = match shell.syntax_check(&command) {
+        Some(cmd) => cmd,
+        None => {
+            return Ok(serde_json::json!({
+                "valid": true,
+                "message": "cmd can't check syntax without running the command"
+            }))
+        }
+    };
+
     match cmd.output() {
         Ok(output) => {
             if output.status.success() {
@@ -2156,3 +3177,54 @@ pub async fn validate_hook_command(command: String) -> Result<serde_json::Value,
         Err(e) => Err(format!("Failed to validate command: {}", e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_trash_move_puts_moved_paths_back() {
+        let claude_dir = tempfile::tempdir().unwrap();
+        let session_file = claude_dir.path().join("projects").join("p").join("s.jsonl");
+        fs::create_dir_all(session_file.parent().unwrap()).unwrap();
+        fs::write(&session_file, "{}").unwrap();
+        let missing = claude_dir.path().join("todos").join("s-agent.json");
+        let entry_dir = claude_dir.path().join(".opcode-trash").join("1-s");
+
+        let paths = vec![session_file.clone(), missing];
+        assert!(move_paths_to_trash(&paths, claude_dir.path(), &entry_dir).is_err());
+        assert_eq!(fs::read_to_string(&session_file).unwrap(), "{}");
+        assert!(!entry_dir.exists());
+
+        move_paths_to_trash(&paths[..1], claude_dir.path(), &entry_dir).unwrap();
+        assert!(entry_dir.join("projects").join("p").join("s.jsonl").is_file());
+    }
+
+    #[test]
+    fn test_agent_runs_are_matched_by_session_id() {
+        let conn = crate::db::test_connection();
+        let agent_id = crate::db::seed_agent(&conn, "a");
+        conn.execute(
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id) VALUES (?1, 'a', 'bot', 't', 'sonnet', '/p', 'abc')",
+            rusqlite::params![agent_id],
+        )
+        .unwrap();
+        let running = vec![crate::process::ProcessInfo {
+            run_id: conn.last_insert_rowid(),
+            process_type: crate::process::ProcessType::AgentRun {
+                agent_id,
+                agent_name: "a".to_string(),
+            },
+            pid: 1,
+            started_at: chrono::Utc::now(),
+            project_path: "/p".to_string(),
+            task: "t".to_string(),
+            model: "sonnet".to_string(),
+            deadline: None,
+            claude_binary: None,
+        }];
+
+        assert_eq!(agent_run_in_session(&conn, &running, "abc").unwrap(), Some(running[0].run_id));
+        assert_eq!(agent_run_in_session(&conn, &running, "other").unwrap(), None);
+    }
+}
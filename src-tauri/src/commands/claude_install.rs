@@ -0,0 +1,480 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+
+use super::agents::AgentDb;
+use super::binary_versions::{list_installations, INSTALLATIONS_UPDATED_EVENT};
+use crate::claude_binary::{self, ClaudeInstallation, InstallationType};
+
+/// Emitted with an `InstallProgress` for each step and line of installer output
+pub const INSTALL_PROGRESS_EVENT: &str = "claude-install-progress";
+
+/// How long an installer may run before it's killed
+const INSTALL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How many lines of installer output are kept for error messages
+const OUTPUT_TAIL_LINES: usize = 40;
+
+const NPM_PACKAGE: &str = "@anthropic-ai/claude-code";
+const INSTALL_SCRIPT_URL: &str = "https://claude.ai/install.sh";
+const INSTALL_SCRIPT_URL_WINDOWS: &str = "https://claude.ai/install.ps1";
+
+/// Only one installer runs at a time
+static INSTALL_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// How to install the Claude CLI
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallMethod {
+    /// The official install script
+    Script,
+    /// `npm install -g`
+    Npm,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallPhase {
+    Starting,
+    Downloading,
+    Installing,
+    /// Looking for the installed binary
+    Verifying,
+    Done,
+    Failed,
+}
+
+/// Payload of `INSTALL_PROGRESS_EVENT`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct InstallProgress {
+    pub phase: InstallPhase,
+    /// Percent complete, when the installer reports it
+    pub percent: Option<u8>,
+    /// The installer's output line or a description of the step
+    pub message: Option<String>,
+}
+
+/// What a line of installer output says about its progress
+pub fn parse_progress(line: &str) -> (Option<InstallPhase>, Option<u8>) {
+    let lower = line.to_lowercase();
+    let phase = if lower.contains("download") || lower.contains("fetch") {
+        Some(InstallPhase::Downloading)
+    } else if lower.contains("install") || lower.contains("added ") || lower.contains("changed ") {
+        Some(InstallPhase::Installing)
+    } else {
+        None
+    };
+    let percent = line.find('%').and_then(|end| {
+        let before = line[..end].trim_end();
+        let digits = before
+            .chars()
+            .rev()
+            .take_while(|c| c.is_ascii_digit() || *c == '.')
+            .count();
+        let percent = before[before.len() - digits..].parse::<f64>().ok()?;
+        (0.0..=100.0).contains(&percent).then_some(percent as u8)
+    });
+    (phase, percent)
+}
+
+/// A command that installs or updates the Claude CLI
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallerCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl InstallerCommand {
+    fn new(program: impl Into<String>, args: &[&str]) -> Self {
+        InstallerCommand {
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+/// The command installing the Claude CLI with `method`
+pub fn install_command(method: InstallMethod) -> InstallerCommand {
+    match method {
+        InstallMethod::Script if cfg!(windows) => InstallerCommand::new(
+            "powershell",
+            &[
+                "-NoProfile",
+                "-ExecutionPolicy",
+                "Bypass",
+                "-Command",
+                &format!("irm {} | iex", INSTALL_SCRIPT_URL_WINDOWS),
+            ],
+        ),
+        InstallMethod::Script => InstallerCommand::new(
+            "bash",
+            &[
+                "-c",
+                &format!("set -o pipefail; curl -fsSL {} | bash", INSTALL_SCRIPT_URL),
+            ],
+        ),
+        InstallMethod::Npm => InstallerCommand::new("npm", &["install", "-g", NPM_PACKAGE]),
+    }
+}
+
+/// The command updating `installation` in place, or why opcode can't. npm
+/// installations are updated with the npm next to them, so the same Node
+/// version manager prefix is used; Homebrew ones with `brew`.
+pub fn update_command(installation: &ClaudeInstallation) -> Result<InstallerCommand, String> {
+    match installation.installation_type {
+        InstallationType::Bundled => {
+            return Err("The Claude binary bundled with opcode is updated with opcode".to_string())
+        }
+        InstallationType::Wsl => {
+            return Err("Claude inside WSL has to be updated from WSL".to_string())
+        }
+        InstallationType::System | InstallationType::Custom => {}
+    }
+
+    let resolved = installation
+        .resolved_path
+        .as_deref()
+        .unwrap_or(&installation.path)
+        .replace('\\', "/");
+    if resolved.contains(&format!("node_modules/{}", NPM_PACKAGE)) {
+        let npm = Path::new(&installation.path)
+            .parent()
+            .map(|dir| dir.join(if cfg!(windows) { "npm.cmd" } else { "npm" }))
+            .filter(|npm| npm.is_file())
+            .map_or_else(
+                || "npm".to_string(),
+                |npm| npm.to_string_lossy().into_owned(),
+            );
+        let package = format!("{}@latest", NPM_PACKAGE);
+        return Ok(InstallerCommand::new(npm, &["install", "-g", &package]));
+    }
+    if resolved.contains("/Caskroom/") {
+        return Ok(InstallerCommand::new(
+            "brew",
+            &["upgrade", "--cask", "claude-code"],
+        ));
+    }
+    if resolved.contains("/Cellar/") {
+        return Ok(InstallerCommand::new("brew", &["upgrade", "claude-code"]));
+    }
+    Err(format!(
+        "opcode can only update Claude installed with npm or Homebrew; update {} (found via {}) the way it was installed",
+        installation.path, installation.source
+    ))
+}
+
+fn emit_progress(
+    app: &AppHandle,
+    phase: InstallPhase,
+    percent: Option<u8>,
+    message: Option<String>,
+) {
+    let _ = app.emit(
+        INSTALL_PROGRESS_EVENT,
+        InstallProgress {
+            phase,
+            percent,
+            message,
+        },
+    );
+}
+
+/// The last lines of some output
+#[derive(Default)]
+struct OutputTail(VecDeque<String>);
+
+impl OutputTail {
+    fn push(&mut self, line: String) {
+        if self.0.len() == OUTPUT_TAIL_LINES {
+            self.0.pop_front();
+        }
+        self.0.push_back(line);
+    }
+
+    fn text(&self) -> String {
+        self.0.iter().cloned().collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// Run an installer, streaming its output as progress events. Fails with
+/// the tail of its stderr, or stdout when it wrote nothing to stderr.
+async fn run_installer(app: &AppHandle, installer: &InstallerCommand) -> Result<(), String> {
+    log::info!("Running {} {}", installer.program, installer.args.join(" "));
+    emit_progress(
+        app,
+        InstallPhase::Starting,
+        Some(0),
+        Some(format!(
+            "{} {}",
+            installer.program,
+            installer.args.join(" ")
+        )),
+    );
+
+    let mut cmd = Command::from(claude_binary::create_command_with_env(&installer.program));
+    cmd.args(&installer.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", installer.program, e))?;
+    let mut stdout = BufReader::new(child.stdout.take().ok_or("Failed to get stdout")?).lines();
+    let mut stderr = BufReader::new(child.stderr.take().ok_or("Failed to get stderr")?).lines();
+
+    let mut phase = InstallPhase::Starting;
+    let mut stdout_tail = OutputTail::default();
+    let mut stderr_tail = OutputTail::default();
+    let run = async {
+        let (mut stdout_open, mut stderr_open) = (true, true);
+        while stdout_open || stderr_open {
+            let line = tokio::select! {
+                line = stdout.next_line(), if stdout_open => match line {
+                    Ok(Some(line)) => {
+                        stdout_tail.push(line.clone());
+                        line
+                    }
+                    _ => {
+                        stdout_open = false;
+                        continue;
+                    }
+                },
+                line = stderr.next_line(), if stderr_open => match line {
+                    Ok(Some(line)) => {
+                        stderr_tail.push(line.clone());
+                        line
+                    }
+                    _ => {
+                        stderr_open = false;
+                        continue;
+                    }
+                },
+            };
+            let (line_phase, percent) = parse_progress(&line);
+            phase = line_phase.unwrap_or(phase);
+            emit_progress(app, phase, percent, Some(line));
+        }
+        child.wait().await
+    };
+
+    let status = match tokio::time::timeout(INSTALL_TIMEOUT, run).await {
+        Ok(status) => status.map_err(|e| format!("Failed to wait for the installer: {}", e))?,
+        Err(_) => {
+            return Err(format!(
+                "The installer didn't finish within {} minutes",
+                INSTALL_TIMEOUT.as_secs() / 60
+            ))
+        }
+    };
+    if status.success() {
+        return Ok(());
+    }
+    let output = match stderr_tail.text() {
+        stderr if stderr.trim().is_empty() => stdout_tail.text(),
+        stderr => stderr,
+    };
+    Err(format!("The installer failed ({}):\n{}", status, output))
+}
+
+/// Where an installation's binary leads, to tell installations apart
+fn installation_key(installation: &ClaudeInstallation) -> String {
+    installation
+        .resolved_path
+        .clone()
+        .unwrap_or_else(|| installation.path.clone())
+}
+
+/// List installations with fresh versions, announcing the new list
+fn rediscover(app: &AppHandle, db: &AgentDb) -> Result<Vec<ClaudeInstallation>, String> {
+    emit_progress(
+        app,
+        InstallPhase::Verifying,
+        Some(100),
+        Some("Looking for the installed Claude binary".to_string()),
+    );
+    let installations = list_installations(app, db, true)?;
+    let _ = app.emit(INSTALLATIONS_UPDATED_EVENT, &installations);
+    Ok(installations)
+}
+
+/// Run an installer and then the `find` step, reporting failure of either
+/// as a progress event too
+async fn install_and_find(
+    app: &AppHandle,
+    db: &AgentDb,
+    installer: InstallerCommand,
+    find: impl FnOnce(Vec<ClaudeInstallation>) -> Option<ClaudeInstallation>,
+) -> Result<ClaudeInstallation, String> {
+    let Ok(_running) = INSTALL_LOCK.try_lock() else {
+        return Err("A Claude installation is already running".to_string());
+    };
+    let result = match run_installer(app, &installer).await {
+        Ok(()) => rediscover(app, db).and_then(|installations| {
+            find(installations)
+                .ok_or_else(|| "The installer finished, but no Claude binary was found".to_string())
+        }),
+        Err(e) => Err(e),
+    };
+    match &result {
+        Ok(installation) => emit_progress(
+            app,
+            InstallPhase::Done,
+            Some(100),
+            Some(format!("Installed Claude at {}", installation.path)),
+        ),
+        Err(e) => {
+            log::error!("Claude installation failed: {}", e);
+            emit_progress(app, InstallPhase::Failed, None, Some(e.clone()));
+        }
+    }
+    result
+}
+
+/// Install the Claude CLI with `method`, streaming `claude-install-progress`
+/// events. Returns the installation discovered afterwards: a new one when
+/// there is one, otherwise one the method installs to.
+#[tauri::command]
+pub async fn install_claude_cli(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    method: InstallMethod,
+) -> Result<ClaudeInstallation, String> {
+    let known: HashSet<String> = claude_binary::find_installation_candidates()
+        .iter()
+        .map(installation_key)
+        .collect();
+    install_and_find(&app, &db, install_command(method), |installations| {
+        let runnable = |i: &&ClaudeInstallation| {
+            !i.missing
+                && !matches!(
+                    i.installation_type,
+                    InstallationType::Wsl | InstallationType::Bundled
+                )
+        };
+        let by_method = |i: &&ClaudeInstallation| {
+            let key = installation_key(i).replace('\\', "/");
+            match method {
+                InstallMethod::Npm => key.contains("node_modules"),
+                InstallMethod::Script => !key.contains("node_modules"),
+            }
+        };
+        let found = installations.iter().filter(runnable);
+        found
+            .clone()
+            .find(|i| !known.contains(&installation_key(i)))
+            .or_else(|| found.clone().find(by_method))
+            .cloned()
+    })
+    .await
+}
+
+/// Update the selected Claude installation in place, when it was installed
+/// with npm or Homebrew. Returns it with its new version.
+#[tauri::command]
+pub async fn update_claude_cli(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+) -> Result<ClaudeInstallation, String> {
+    let path = claude_binary::find_claude_binary(&app)?;
+    let key = PathBuf::from(&path);
+    let key = std::fs::canonicalize(&key)
+        .unwrap_or(key)
+        .to_string_lossy()
+        .into_owned();
+    let installation = claude_binary::find_installation_candidates()
+        .into_iter()
+        .find(|i| installation_key(i) == key)
+        .unwrap_or_else(|| ClaudeInstallation {
+            path: path.clone(),
+            version: None,
+            source: "custom".to_string(),
+            installation_type: InstallationType::Custom,
+            resolved_path: Some(key.clone()),
+            missing: false,
+            runtime: None,
+        });
+    let installer = update_command(&installation)?;
+
+    install_and_find(&app, &db, installer, |installations| {
+        installations
+            .into_iter()
+            .find(|i| installation_key(i) == key)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installation(path: &str, resolved: &str, source: &str) -> ClaudeInstallation {
+        ClaudeInstallation {
+            path: path.to_string(),
+            version: None,
+            source: source.to_string(),
+            installation_type: InstallationType::System,
+            resolved_path: Some(resolved.to_string()),
+            missing: false,
+            runtime: None,
+        }
+    }
+
+    #[test]
+    fn test_progress_is_read_from_installer_output() {
+        assert_eq!(
+            parse_progress("Downloading claude 2.0.1 ... 42%"),
+            (Some(InstallPhase::Downloading), Some(42))
+        );
+        assert_eq!(parse_progress("##########      55.3%"), (None, Some(55)));
+        assert_eq!(
+            parse_progress("added 3 packages in 4s"),
+            (Some(InstallPhase::Installing), None)
+        );
+        assert_eq!(parse_progress("100 of 200 done, 250%"), (None, None));
+        assert_eq!(parse_progress("plain line"), (None, None));
+    }
+
+    #[test]
+    fn test_only_npm_and_homebrew_installations_are_updated() {
+        let npm = installation(
+            "/home/me/.nvm/versions/node/v20.0.0/bin/claude",
+            "/home/me/.nvm/versions/node/v20.0.0/lib/node_modules/@anthropic-ai/claude-code/cli.js",
+            "nvm",
+        );
+        let command = update_command(&npm).unwrap();
+        assert!(command.program.ends_with("npm"));
+        assert_eq!(
+            command.args,
+            ["install", "-g", "@anthropic-ai/claude-code@latest"]
+        );
+
+        let cask = installation(
+            "/opt/homebrew/bin/claude",
+            "/opt/homebrew/Caskroom/claude-code/2.0.1/claude",
+            "homebrew",
+        );
+        assert_eq!(
+            update_command(&cask).unwrap(),
+            InstallerCommand::new("brew", &["upgrade", "--cask", "claude-code"])
+        );
+
+        let native = installation(
+            "/home/me/.local/bin/claude",
+            "/home/me/.local/share/claude/versions/2.0.1",
+            "local-bin",
+        );
+        assert!(update_command(&native).is_err());
+
+        let bundled = ClaudeInstallation {
+            installation_type: InstallationType::Bundled,
+            ..installation("bundled", "/opt/opcode/claude", "bundled")
+        };
+        assert!(update_command(&bundled).is_err());
+    }
+}
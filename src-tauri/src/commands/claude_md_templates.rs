@@ -0,0 +1,335 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use tauri::State;
+
+use super::prompt_templates::substitute;
+use crate::commands::agents::AgentDb;
+
+/// A template a CLAUDE.md can be created from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ClaudeMdTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub content: String,
+    pub builtin: bool,
+}
+
+/// A CLAUDE.md written by `create_claude_md`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CreatedClaudeMd {
+    pub path: String,
+    pub bytes_written: usize,
+}
+
+/// Built-in templates: (id, name, description, content)
+const BUILTIN_TEMPLATES: [(&str, &str, &str, &str); 3] = [
+    (
+        "rust-crate",
+        "Rust crate",
+        "A single Rust crate built with Cargo",
+        "# {{PROJECT_NAME}}
+
+## Build and test
+- `cargo build` builds the crate
+- `cargo test` runs the tests
+- `cargo clippy --all-targets -- -D warnings` must pass before committing
+- `cargo fmt` formats the code
+
+## Conventions
+- Return `Result` from fallible functions rather than panicking
+- Keep public items documented with `///` comments
+- Put unit tests in a `#[cfg(test)] mod tests` at the bottom of the module they cover
+",
+    ),
+    (
+        "node-app",
+        "Node app",
+        "A Node.js application managed with npm",
+        "# {{PROJECT_NAME}}
+
+## Commands
+- `npm install` installs dependencies
+- `npm run dev` starts the development server
+- `npm test` runs the tests
+- `npm run lint` checks the code style
+
+## Conventions
+- Use TypeScript for new code and keep `strict` type checking passing
+- Keep components small and colocate their tests
+- Don't commit secrets; configuration comes from environment variables
+",
+    ),
+    (
+        "monorepo",
+        "Monorepo",
+        "Several packages in one repository",
+        "# {{PROJECT_NAME}}
+
+## Layout
+- `packages/` holds the individual packages
+- Shared configuration lives at the repository root
+
+## Working here
+- Run commands from the package you're changing unless they're workspace-wide
+- Changes that touch several packages should keep each package building on its own
+- Update the dependent packages in the same change when a shared API changes
+
+## Notes
+Created {{DATE}}.
+",
+    ),
+];
+
+fn builtin_templates() -> Vec<ClaudeMdTemplate> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .map(|(id, name, description, content)| ClaudeMdTemplate {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            content: content.to_string(),
+            builtin: true,
+        })
+        .collect()
+}
+
+/// Id of a user template: its name in lowercase words joined by dashes
+fn template_id(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Built-in templates followed by the user's, by name
+pub fn load_claude_md_templates(conn: &Connection) -> Result<Vec<ClaudeMdTemplate>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, name, description, content FROM claude_md_templates ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+    let user_templates = stmt
+        .query_map([], |row| {
+            Ok(ClaudeMdTemplate {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                content: row.get(3)?,
+                builtin: false,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut templates = builtin_templates();
+    templates.extend(user_templates);
+    Ok(templates)
+}
+
+fn find_template(conn: &Connection, id: &str) -> Result<ClaudeMdTemplate, String> {
+    if let Some(template) = builtin_templates().into_iter().find(|t| t.id == id) {
+        return Ok(template);
+    }
+    conn.query_row(
+        "SELECT id, name, description, content FROM claude_md_templates WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(ClaudeMdTemplate {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                content: row.get(3)?,
+                builtin: false,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("CLAUDE.md template not found: {}", id))
+}
+
+/// Store a user template, replacing one with the same name
+pub fn save_template(
+    conn: &Connection,
+    name: &str,
+    description: &str,
+    content: &str,
+) -> Result<ClaudeMdTemplate, String> {
+    let name = name.trim();
+    let id = template_id(name);
+    if id.is_empty() {
+        return Err("Template name must contain letters or digits".to_string());
+    }
+    if BUILTIN_TEMPLATES
+        .iter()
+        .any(|(builtin_id, ..)| *builtin_id == id)
+    {
+        return Err(format!("'{}' is the name of a built-in template", name));
+    }
+
+    conn.execute(
+        "INSERT INTO claude_md_templates (id, name, description, content) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(id) DO UPDATE SET name = ?2, description = ?3, content = ?4, updated_at = CURRENT_TIMESTAMP",
+        params![id, name, description, content],
+    )
+    .map_err(|e| format!("Failed to save CLAUDE.md template: {}", e))?;
+
+    Ok(ClaudeMdTemplate {
+        id,
+        name: name.to_string(),
+        description: description.to_string(),
+        content: content.to_string(),
+        builtin: false,
+    })
+}
+
+/// Variables a template can use, taken from the directory it's created in
+fn template_variables(directory: &Path) -> HashMap<String, String> {
+    let project_name = directory
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    HashMap::from([
+        ("PROJECT_NAME".to_string(), project_name),
+        (
+            "PROJECT_PATH".to_string(),
+            directory.to_string_lossy().into_owned(),
+        ),
+        (
+            "DATE".to_string(),
+            chrono::Local::now().format("%Y-%m-%d").to_string(),
+        ),
+    ])
+}
+
+/// Write `directory/CLAUDE.md` from a template, creating the directory if needed.
+/// An existing file is only replaced when `overwrite` is set.
+pub fn write_claude_md(
+    directory: &Path,
+    template: &ClaudeMdTemplate,
+    overwrite: bool,
+) -> Result<CreatedClaudeMd, String> {
+    fs::create_dir_all(directory).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let variables = template_variables(directory);
+    let (content, _) = substitute(&template.content, |name| variables.get(name).cloned());
+
+    let path = directory.join("CLAUDE.md");
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut file = options.open(&path).map_err(|e| match e.kind() {
+        ErrorKind::AlreadyExists => format!(
+            "{} already exists; set overwrite to replace it",
+            path.display()
+        ),
+        _ => format!("Failed to create CLAUDE.md: {}", e),
+    })?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| format!("Failed to write CLAUDE.md: {}", e))?;
+
+    Ok(CreatedClaudeMd {
+        path: path.to_string_lossy().into_owned(),
+        bytes_written: content.len(),
+    })
+}
+
+/// List the built-in and user CLAUDE.md templates
+#[tauri::command]
+pub async fn list_claude_md_templates(
+    db: State<'_, AgentDb>,
+) -> Result<Vec<ClaudeMdTemplate>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_claude_md_templates(&conn)
+}
+
+/// Save a user CLAUDE.md template. Templates may use `{{PROJECT_NAME}}`,
+/// `{{PROJECT_PATH}}` and `{{DATE}}`.
+#[tauri::command]
+pub async fn save_claude_md_template(
+    db: State<'_, AgentDb>,
+    name: String,
+    description: Option<String>,
+    content: String,
+) -> Result<ClaudeMdTemplate, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_template(&conn, &name, description.as_deref().unwrap_or(""), &content)
+}
+
+/// Create a CLAUDE.md in `directory` from a template. Refuses to replace an
+/// existing file unless `overwrite` is set.
+#[tauri::command]
+pub async fn create_claude_md(
+    db: State<'_, AgentDb>,
+    directory: String,
+    template_id: String,
+    overwrite: Option<bool>,
+) -> Result<CreatedClaudeMd, String> {
+    let template = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        find_template(&conn, &template_id)?
+    };
+    log::info!(
+        "Creating CLAUDE.md in {} from template {}",
+        directory,
+        template.id
+    );
+    write_claude_md(Path::new(&directory), &template, overwrite.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_templates_are_listed_after_builtins_and_upserted_by_name() {
+        let conn = crate::db::test_connection();
+
+        let saved = save_template(&conn, " Python Lib ", "", "# {{PROJECT_NAME}}").unwrap();
+        assert_eq!(saved.id, "python-lib");
+        save_template(&conn, "python lib", "Updated", "# {{PROJECT_NAME}}\n").unwrap();
+        assert!(save_template(&conn, "Rust Crate", "", "x").is_err());
+        assert!(save_template(&conn, " !! ", "", "x").is_err());
+
+        let templates = load_claude_md_templates(&conn).unwrap();
+        assert_eq!(templates.len(), BUILTIN_TEMPLATES.len() + 1);
+        let user = templates.last().unwrap();
+        assert_eq!(
+            (user.name.as_str(), user.description.as_str()),
+            ("python lib", "Updated")
+        );
+        assert!(find_template(&conn, "monorepo").unwrap().builtin);
+        assert!(find_template(&conn, "missing").is_err());
+    }
+
+    #[test]
+    fn test_create_fills_in_variables_and_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let directory = dir.path().join("nested").join("my-crate");
+        let template = builtin_templates().remove(0);
+
+        let created = write_claude_md(&directory, &template, false).unwrap();
+        let written = fs::read_to_string(&created.path).unwrap();
+        assert!(written.starts_with("# my-crate\n"));
+        assert_eq!(created.bytes_written, written.len());
+
+        let err = write_claude_md(&directory, &template, false).unwrap_err();
+        assert!(err.contains("already exists"));
+        fs::write(
+            &created.path,
+            "a much longer file than the template produces ".repeat(50),
+        )
+        .unwrap();
+        write_claude_md(&directory, &template, true).unwrap();
+        assert_eq!(fs::read_to_string(&created.path).unwrap(), written);
+    }
+}
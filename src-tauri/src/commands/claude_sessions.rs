@@ -0,0 +1,250 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tauri::State;
+
+use super::session_options::ClaudeSessionOptions;
+use crate::commands::agents::AgentDb;
+use crate::process::ClaudeChild;
+
+/// How many interactive Claude sessions may run at once unless configured otherwise
+const DEFAULT_MAX_CLAUDE_SESSIONS: usize = 4;
+
+/// How many prompts may wait for one session's current turn
+pub const MAX_QUEUED_PROMPTS: usize = 10;
+
+/// A running interactive Claude process
+pub struct ClaudeSessionProcess {
+    pub child: ClaudeChild,
+    /// The process's PID, which on Unix is also its process group
+    pub pid: u32,
+}
+
+/// A prompt submitted while its session was busy, sent with `--continue`
+/// once the current turn completes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueuedPrompt {
+    pub prompt: String,
+    pub project_path: String,
+    pub model: String,
+    pub options: Option<ClaudeSessionOptions>,
+}
+
+/// Running interactive Claude processes, keyed by the session ID their
+/// events are tagged with, and the prompts queued behind them
+#[derive(Default)]
+pub struct ClaudeSessions {
+    sessions: HashMap<String, ClaudeSessionProcess>,
+    queues: HashMap<String, VecDeque<QueuedPrompt>>,
+}
+
+impl ClaudeSessions {
+    /// Check that another session can start under `session_id`
+    pub fn check_can_start(&self, session_id: &str, max_sessions: usize) -> Result<(), String> {
+        if self.sessions.contains_key(session_id) {
+            return Err(format!("Session {} is already running", session_id));
+        }
+        if self.sessions.len() >= max_sessions {
+            return Err(format!(
+                "{} Claude sessions are already running; wait for one to finish or cancel it",
+                self.sessions.len()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn insert(&mut self, session_id: String, process: ClaudeSessionProcess) {
+        self.sessions.insert(session_id, process);
+    }
+
+    /// Take a session's process out of the map, e.g. to cancel it
+    pub fn remove(&mut self, session_id: &str) -> Option<ClaudeSessionProcess> {
+        self.sessions.remove(session_id)
+    }
+
+    /// Take a session's process out of the map if it is still the one with
+    /// `pid`, rather than a later run of the same session
+    pub fn remove_exited(&mut self, session_id: &str, pid: u32) -> Option<ClaudeSessionProcess> {
+        match self.sessions.get(session_id) {
+            Some(process) if process.pid == pid => self.sessions.remove(session_id),
+            _ => None,
+        }
+    }
+
+    /// Queue a prompt behind a running session's current turn, returning
+    /// how many prompts are now waiting
+    pub fn queue_prompt(
+        &mut self,
+        session_id: &str,
+        prompt: QueuedPrompt,
+    ) -> Result<usize, String> {
+        if !self.sessions.contains_key(session_id) {
+            return Err(format!(
+                "Session {} is not running; send the prompt directly",
+                session_id
+            ));
+        }
+        let queue = self.queues.entry(session_id.to_string()).or_default();
+        if queue.len() >= MAX_QUEUED_PROMPTS {
+            return Err(format!(
+                "{} prompts are already queued for this session; wait for Claude to catch up",
+                MAX_QUEUED_PROMPTS
+            ));
+        }
+        queue.push_back(prompt);
+        Ok(queue.len())
+    }
+
+    pub fn queued_prompts(&self, session_id: &str) -> Vec<QueuedPrompt> {
+        self.queues
+            .get(session_id)
+            .map(|queue| queue.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn remove_queued_prompt(
+        &mut self,
+        session_id: &str,
+        index: usize,
+    ) -> Result<QueuedPrompt, String> {
+        let queue = self.queues.get_mut(session_id);
+        let removed = queue.and_then(|queue| queue.remove(index));
+        self.drop_empty_queue(session_id);
+        removed.ok_or_else(|| format!("No queued prompt {} for session {}", index, session_id))
+    }
+
+    /// Take the prompt to send once the session's turn completes
+    pub fn next_queued_prompt(&mut self, session_id: &str) -> Option<(QueuedPrompt, usize)> {
+        let queue = self.queues.get_mut(session_id)?;
+        let next = queue.pop_front().map(|prompt| (prompt, queue.len()));
+        self.drop_empty_queue(session_id);
+        next
+    }
+
+    /// Drop a session's queued prompts, returning them
+    pub fn clear_queue(&mut self, session_id: &str) -> Vec<QueuedPrompt> {
+        self.queues
+            .remove(session_id)
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+
+    fn drop_empty_queue(&mut self, session_id: &str) {
+        if self.queues.get(session_id).is_some_and(VecDeque::is_empty) {
+            self.queues.remove(session_id);
+        }
+    }
+}
+
+pub fn get_max_claude_sessions_setting(db: &AgentDb) -> Result<usize, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = 'max_claude_sessions'",
+        [],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(value) => Ok(value
+            .parse::<usize>()
+            .ok()
+            .filter(|max| *max > 0)
+            .unwrap_or(DEFAULT_MAX_CLAUDE_SESSIONS)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(DEFAULT_MAX_CLAUDE_SESSIONS),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Get how many interactive Claude sessions may run at once
+#[tauri::command]
+pub async fn get_max_claude_sessions(db: State<'_, AgentDb>) -> Result<usize, String> {
+    get_max_claude_sessions_setting(&db)
+}
+
+/// Set how many interactive Claude sessions may run at once
+#[tauri::command]
+pub async fn set_max_claude_sessions(db: State<'_, AgentDb>, max: usize) -> Result<(), String> {
+    if max == 0 {
+        return Err("At least one Claude session must be allowed".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES ('max_claude_sessions', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1",
+        params![max.to_string()],
+    )
+    .map_err(|e| format!("Failed to save max_claude_sessions: {}", e))?;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn sleeper() -> ClaudeSessionProcess {
+        let child = tokio::process::Command::new("sleep")
+            .arg("60")
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        ClaudeSessionProcess {
+            child: child.into(),
+            pid,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_capped_and_keyed_by_session_id() {
+        let mut sessions = ClaudeSessions::default();
+        sessions.check_can_start("a", 2).unwrap();
+        sessions.insert("a".to_string(), sleeper());
+        assert!(sessions.check_can_start("a", 2).is_err());
+        sessions.check_can_start("b", 2).unwrap();
+        sessions.insert("b".to_string(), sleeper());
+        assert!(sessions.check_can_start("c", 2).is_err());
+
+        // A later run of "a" is not removed when the earlier one exits
+        let first = sessions.remove("a").unwrap();
+        sessions.insert("a".to_string(), sleeper());
+        assert!(sessions.remove_exited("a", first.pid).is_none());
+        assert_eq!(sessions.sessions.len(), 2);
+        let second_pid = sessions.sessions["a"].pid;
+        assert!(sessions.remove_exited("a", second_pid).is_some());
+        sessions.check_can_start("c", 2).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prompts_queue_behind_running_sessions_up_to_the_cap() {
+        let queued = |prompt: &str| QueuedPrompt {
+            prompt: prompt.to_string(),
+            project_path: "/p".to_string(),
+            model: "sonnet".to_string(),
+            options: None,
+        };
+        let mut sessions = ClaudeSessions::default();
+        assert!(sessions.queue_prompt("a", queued("early")).is_err());
+
+        sessions.insert("a".to_string(), sleeper());
+        for i in 0..MAX_QUEUED_PROMPTS {
+            assert_eq!(
+                sessions.queue_prompt("a", queued(&i.to_string())),
+                Ok(i + 1)
+            );
+        }
+        assert!(sessions.queue_prompt("a", queued("over")).is_err());
+
+        assert_eq!(sessions.remove_queued_prompt("a", 1).unwrap().prompt, "1");
+        assert!(sessions.remove_queued_prompt("a", 99).is_err());
+        let (next, remaining) = sessions.next_queued_prompt("a").unwrap();
+        assert_eq!(
+            (next.prompt.as_str(), remaining),
+            ("0", MAX_QUEUED_PROMPTS - 2)
+        );
+        assert_eq!(sessions.queued_prompts("a")[0].prompt, "2");
+
+        let cleared = sessions.clear_queue("a");
+        assert_eq!(cleared.len(), MAX_QUEUED_PROMPTS - 2);
+        assert!(sessions.next_queued_prompt("a").is_none());
+        assert!(sessions.queued_prompts("a").is_empty());
+    }
+}
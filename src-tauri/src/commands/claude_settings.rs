@@ -0,0 +1,393 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::hooks_config::validate_hooks;
+
+/// The shape a known settings key must have
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SettingType {
+    String,
+    Bool,
+    Number,
+    Object,
+    StringArray,
+    StringMap,
+}
+
+impl SettingType {
+    fn describe(self) -> &'static str {
+        match self {
+            SettingType::String => "a string",
+            SettingType::Bool => "true or false",
+            SettingType::Number => "a number",
+            SettingType::Object => "an object",
+            SettingType::StringArray => "an array of strings",
+            SettingType::StringMap => "an object of string values",
+        }
+    }
+
+    fn matches(self, value: &JsonValue) -> bool {
+        match self {
+            SettingType::String => value.is_string(),
+            SettingType::Bool => value.is_boolean(),
+            SettingType::Number => value.is_number(),
+            SettingType::Object => value.is_object(),
+            SettingType::StringArray => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(|item| item.is_string())),
+            SettingType::StringMap => value
+                .as_object()
+                .is_some_and(|map| map.values().all(|item| item.is_string())),
+        }
+    }
+}
+
+/// Top-level keys Claude Code reads from settings.json
+const SETTINGS_SCHEMA: [(&str, SettingType); 20] = [
+    ("$schema", SettingType::String),
+    ("apiKeyHelper", SettingType::String),
+    ("awsAuthRefresh", SettingType::String),
+    ("awsCredentialExport", SettingType::String),
+    ("cleanupPeriodDays", SettingType::Number),
+    ("disableAllHooks", SettingType::Bool),
+    ("disabledMcpjsonServers", SettingType::StringArray),
+    ("enableAllProjectMcpServers", SettingType::Bool),
+    ("enabledMcpjsonServers", SettingType::StringArray),
+    ("env", SettingType::StringMap),
+    ("forceLoginMethod", SettingType::String),
+    ("hooks", SettingType::Object),
+    ("includeCoAuthoredBy", SettingType::Bool),
+    ("model", SettingType::String),
+    ("outputStyle", SettingType::String),
+    ("permissions", SettingType::Object),
+    ("statusLine", SettingType::Object),
+    ("alwaysThinkingEnabled", SettingType::Bool),
+    ("spinnerTipsEnabled", SettingType::Bool),
+    ("verbose", SettingType::Bool),
+];
+
+/// Keys of the `permissions` object
+const PERMISSIONS_SCHEMA: [(&str, SettingType); 6] = [
+    ("allow", SettingType::StringArray),
+    ("ask", SettingType::StringArray),
+    ("deny", SettingType::StringArray),
+    ("additionalDirectories", SettingType::StringArray),
+    ("defaultMode", SettingType::String),
+    ("disableBypassPermissionsMode", SettingType::String),
+];
+
+const PERMISSION_MODES: [&str; 4] = ["default", "acceptEdits", "plan", "bypassPermissions"];
+
+/// A problem found in settings, located by its JSON path
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingsIssue {
+    pub path: String,
+    pub message: String,
+}
+
+/// Problems that block a save, and ones that only warrant a warning
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SettingsValidation {
+    pub errors: Vec<SettingsIssue>,
+    pub warnings: Vec<SettingsIssue>,
+}
+
+/// The settings file as read, with why it couldn't be parsed when it's broken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaudeSettings {
+    pub data: JsonValue,
+    /// Empty unless the file isn't valid JSON, in which case `data` is empty
+    pub parse_errors: Vec<String>,
+    /// Whether a backup of the previous settings can be restored
+    pub backup_available: bool,
+}
+
+/// Outcome of saving settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingsSaveResult {
+    /// Keys Claude Code doesn't know about, saved anyway
+    pub warnings: Vec<SettingsIssue>,
+    /// Where the previous settings were kept, when there were valid ones
+    pub backup_path: Option<String>,
+}
+
+fn issue(issues: &mut Vec<SettingsIssue>, path: String, message: impl Into<String>) {
+    issues.push(SettingsIssue {
+        path,
+        message: message.into(),
+    });
+}
+
+fn validate_permissions(
+    permissions: &serde_json::Map<String, JsonValue>,
+    result: &mut SettingsValidation,
+) {
+    for (key, value) in permissions {
+        let path = format!("$.permissions.{}", key);
+        match PERMISSIONS_SCHEMA.iter().find(|(name, _)| name == key) {
+            Some((_, kind)) if !kind.matches(value) => issue(
+                &mut result.errors,
+                path,
+                format!("Expected {}", kind.describe()),
+            ),
+            Some(_) => {}
+            None => issue(&mut result.warnings, path, "Unknown permissions key"),
+        }
+    }
+    if let Some(mode) = permissions.get("defaultMode").and_then(|m| m.as_str()) {
+        if !PERMISSION_MODES.contains(&mode) {
+            issue(
+                &mut result.errors,
+                "$.permissions.defaultMode".to_string(),
+                format!("Expected one of {}", PERMISSION_MODES.join(", ")),
+            );
+        }
+    }
+}
+
+/// Check settings against the keys and types Claude Code reads. Wrong types
+/// are errors; unknown keys are warnings, or errors when `strict`.
+pub fn validate_settings(settings: &JsonValue, strict: bool) -> SettingsValidation {
+    let mut result = SettingsValidation::default();
+    let settings = match settings.as_object() {
+        Some(settings) => settings,
+        None => {
+            issue(
+                &mut result.errors,
+                "$".to_string(),
+                "Expected a JSON object",
+            );
+            return result;
+        }
+    };
+
+    for (key, value) in settings {
+        let path = format!("$.{}", key);
+        let kind = match SETTINGS_SCHEMA.iter().find(|(name, _)| name == key) {
+            Some((_, kind)) => *kind,
+            None => {
+                let unknown = if strict {
+                    &mut result.errors
+                } else {
+                    &mut result.warnings
+                };
+                issue(unknown, path, "Unknown setting");
+                continue;
+            }
+        };
+        if !kind.matches(value) {
+            issue(
+                &mut result.errors,
+                path,
+                format!("Expected {}", kind.describe()),
+            );
+            continue;
+        }
+        match key.as_str() {
+            "permissions" => validate_permissions(value.as_object().unwrap(), &mut result),
+            "hooks" => result
+                .errors
+                .extend(
+                    validate_hooks(value)
+                        .into_iter()
+                        .map(|hook_issue| SettingsIssue {
+                            path: hook_issue.path,
+                            message: hook_issue.message,
+                        }),
+                ),
+            _ => {}
+        }
+    }
+    result
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Where the previous version of a settings file is kept
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// Read a settings file. A missing file reads as empty settings; a broken one
+/// is reported in `parse_errors` rather than failing.
+pub fn read_settings(path: &Path) -> Result<ClaudeSettings, String> {
+    let backup_available = backup_path(path).is_file();
+    let mut settings = ClaudeSettings {
+        data: serde_json::json!({}),
+        parse_errors: Vec::new(),
+        backup_available,
+    };
+    if !path.exists() {
+        return Ok(settings);
+    }
+
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read settings file: {}", e))?;
+    match serde_json::from_str::<JsonValue>(&content) {
+        Ok(data) if data.is_object() => settings.data = data,
+        Ok(_) => settings
+            .parse_errors
+            .push("Settings must be a JSON object".to_string()),
+        Err(e) => settings.parse_errors.push(e.to_string()),
+    }
+    Ok(settings)
+}
+
+/// Replace a settings file atomically: write a temp file in the same directory,
+/// fsync it, then rename it over the original. The previous version is kept as
+/// `.bak` when it was valid JSON; a broken one is set aside as `.broken` instead,
+/// so it never replaces a good backup.
+pub fn write_settings(path: &Path, content: &str) -> Result<Option<PathBuf>, String> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("Invalid settings path: {}", path.display()))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create settings directory: {}", e))?;
+
+    let mut temp = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    temp.write_all(content.as_bytes())
+        .and_then(|_| temp.as_file().sync_all())
+        .map_err(|e| format!("Failed to write settings file: {}", e))?;
+
+    let mut backup = None;
+    if let Ok(previous) = fs::read_to_string(path) {
+        if let Ok(metadata) = fs::metadata(path) {
+            let _ = fs::set_permissions(temp.path(), metadata.permissions());
+        }
+        if serde_json::from_str::<JsonValue>(&previous).is_ok() {
+            let backup_path = backup_path(path);
+            fs::write(&backup_path, previous)
+                .map_err(|e| format!("Failed to back up settings: {}", e))?;
+            backup = Some(backup_path);
+        } else {
+            fs::write(with_suffix(path, ".broken"), previous)
+                .map_err(|e| format!("Failed to set broken settings aside: {}", e))?;
+        }
+    }
+
+    temp.persist(path)
+        .map_err(|e| format!("Failed to save settings file: {}", e.error))?;
+    Ok(backup)
+}
+
+/// Validate and save settings, rejecting them when they have errors
+pub fn save_settings(
+    path: &Path,
+    settings: &JsonValue,
+    strict: bool,
+) -> Result<SettingsSaveResult, String> {
+    let validation = validate_settings(settings, strict);
+    if !validation.errors.is_empty() {
+        let details: Vec<String> = validation
+            .errors
+            .iter()
+            .map(|issue| format!("{}: {}", issue.path, issue.message))
+            .collect();
+        return Err(format!("Invalid settings:\n{}", details.join("\n")));
+    }
+    for warning in &validation.warnings {
+        log::warn!("Saving unknown setting {}", warning.path);
+    }
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let backup = write_settings(path, &content)?;
+    Ok(SettingsSaveResult {
+        warnings: validation.warnings,
+        backup_path: backup.map(|p| p.to_string_lossy().into_owned()),
+    })
+}
+
+/// Put the backup back in place of a settings file, keeping the broken file
+/// beside it as `.broken`
+pub fn restore_backup(path: &Path) -> Result<JsonValue, String> {
+    let backup = backup_path(path);
+    let content =
+        fs::read_to_string(&backup).map_err(|e| format!("No settings backup to restore: {}", e))?;
+    let data: JsonValue = serde_json::from_str(&content)
+        .map_err(|e| format!("Settings backup is not valid JSON: {}", e))?;
+
+    if path.exists() {
+        fs::rename(path, with_suffix(path, ".broken"))
+            .map_err(|e| format!("Failed to move broken settings aside: {}", e))?;
+    }
+    fs::copy(&backup, path).map_err(|e| format!("Failed to restore settings backup: {}", e))?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(issues: &[SettingsIssue]) -> Vec<&str> {
+        issues.iter().map(|i| i.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_types_are_errors_and_unknown_keys_warnings_unless_strict() {
+        let settings = json!({
+            "model": "opus",
+            "env": { "DEBUG": "1", "PORT": 8080 },
+            "permissions": { "allow": ["Bash(ls)"], "defaultMode": "yolo", "extra": true },
+            "hooks": { "Stop": [{ "hooks": [{ "type": "command" }] }] },
+            "verbose": "yes",
+            "fancyFeature": 1
+        });
+        let result = validate_settings(&settings, false);
+        assert_eq!(
+            paths(&result.errors),
+            [
+                "$.env",
+                "$.hooks.Stop[0].hooks[0].command",
+                "$.permissions.defaultMode",
+                "$.verbose"
+            ]
+        );
+        assert_eq!(
+            paths(&result.warnings),
+            ["$.fancyFeature", "$.permissions.extra"]
+        );
+
+        let strict = validate_settings(&json!({ "fancyFeature": 1 }), true);
+        assert_eq!(paths(&strict.errors), ["$.fancyFeature"]);
+        assert_eq!(paths(&validate_settings(&json!([]), false).errors), ["$"]);
+    }
+
+    #[test]
+    fn test_saves_replace_atomically_and_keep_a_valid_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        let saved = save_settings(&path, &json!({ "model": "opus" }), false).unwrap();
+        assert_eq!(saved.backup_path, None);
+        let saved = save_settings(&path, &json!({ "model": "sonnet", "x": 1 }), false).unwrap();
+        assert_eq!(paths(&saved.warnings), ["$.x"]);
+        assert_eq!(
+            fs::read_to_string(backup_path(&path)).unwrap(),
+            "{\n  \"model\": \"opus\"\n}"
+        );
+        assert!(save_settings(&path, &json!({ "verbose": 1 }), false).is_err());
+        assert!(save_settings(&path, &json!({ "x": 1 }), true).is_err());
+
+        // A broken file is reported, doesn't replace the backup and can be restored over
+        fs::write(&path, "{ \"model\": ").unwrap();
+        let read = read_settings(&path).unwrap();
+        assert_eq!(read.parse_errors.len(), 1);
+        assert!(read.backup_available);
+        assert_eq!(read.data, json!({}));
+
+        let restored = restore_backup(&path).unwrap();
+        assert_eq!(restored, json!({ "model": "opus" }));
+        assert_eq!(read_settings(&path).unwrap().data, restored);
+        assert!(dir.path().join("settings.json.broken").exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+}
@@ -0,0 +1,381 @@
+use regex::Regex;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::session_options::OutputFormat;
+use crate::commands::agents::AgentDb;
+
+/// Oldest Claude Code release that accepts every flag opcode passes, e.g.
+/// `--output-format stream-json` together with `--verbose`
+pub const MINIMUM_CLAUDE_VERSION: &str = "1.0.0";
+
+/// Oldest Claude Code release that runs `/compact` in print mode
+pub const COMPACT_MINIMUM_VERSION: &str = "1.0.60";
+
+/// Where the latest published Claude Code version is looked up
+const NPM_LATEST_URL: &str = "https://registry.npmjs.org/@anthropic-ai/claude-code/latest";
+
+/// How long `claude --version` may take before the binary is given up on
+const VERSION_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the npm registry may take before the check is given up
+const NPM_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long a registry lookup, successful or not, is reused
+const LATEST_VERSION_TTL_SECONDS: u64 = 6 * 60 * 60;
+
+/// A semantic version read from `claude --version`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClaudeVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre_release: Option<String>,
+    pub build: Option<String>,
+    /// The version as it appeared in the output
+    pub raw: String,
+}
+
+impl ClaudeVersion {
+    /// Semver precedence: build metadata is ignored and a pre-release sorts
+    /// before its release
+    pub fn compare(&self, other: &ClaudeVersion) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre_release, &other.pre_release) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => compare_pre_release(a, b),
+            })
+    }
+}
+
+fn compare_pre_release(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        let order = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+}
+
+/// The first `major.minor.patch[-pre][+build]` in `output`, e.g. from
+/// `1.0.41 (Claude Code)`
+pub fn parse_version(output: &str) -> Option<ClaudeVersion> {
+    let regex = Regex::new(
+        r"(?:^|[^\d.])v?(\d+)\.(\d+)\.(\d+)(?:-([0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*))?(?:\+([0-9A-Za-z-]+(?:\.[0-9A-Za-z-]+)*))?",
+    )
+    .ok()?;
+    let captures = regex.captures(output)?;
+    let number = |i: usize| captures.get(i)?.as_str().parse::<u64>().ok();
+    let text = |i: usize| captures.get(i).map(|m| m.as_str().to_string());
+    let start = captures.get(1)?.start();
+    let end = captures.get(0)?.end();
+    Some(ClaudeVersion {
+        major: number(1)?,
+        minor: number(2)?,
+        patch: number(3)?,
+        pre_release: text(4),
+        build: text(5),
+        raw: output[start..end].to_string(),
+    })
+}
+
+/// How an installed version compares with the required and latest releases
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VersionAssessment {
+    pub parsed: Option<ClaudeVersion>,
+    /// The output had no recognizable version
+    pub parse_failed: bool,
+    pub below_minimum: bool,
+    pub update_available: bool,
+}
+
+/// Parse `output` and compare it with the minimum and, if known, the latest
+/// published version
+pub fn assess_version(output: &str, latest: Option<&str>) -> VersionAssessment {
+    let Some(parsed) = parse_version(output) else {
+        return VersionAssessment {
+            parse_failed: true,
+            ..Default::default()
+        };
+    };
+    let below_minimum = parse_version(MINIMUM_CLAUDE_VERSION)
+        .is_some_and(|minimum| parsed.compare(&minimum) == Ordering::Less);
+    let update_available = latest
+        .and_then(parse_version)
+        .is_some_and(|latest| parsed.compare(&latest) == Ordering::Less);
+    VersionAssessment {
+        parsed: Some(parsed),
+        parse_failed: false,
+        below_minimum,
+        update_available,
+    }
+}
+
+/// The version `claude --version` reports, if it runs and can be parsed
+pub async fn installed_claude_version(claude_path: &str) -> Option<ClaudeVersion> {
+    probe_claude_version(claude_path).await.ok()
+}
+
+/// Run `claude_path --version`, failing when it can't be run, exits with an
+/// error, takes longer than `VERSION_PROBE_TIMEOUT` or prints no version
+pub async fn probe_claude_version(claude_path: &str) -> Result<ClaudeVersion, String> {
+    let output = tokio::process::Command::new(claude_path)
+        .arg("--version")
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(VERSION_PROBE_TIMEOUT, output)
+        .await
+        .map_err(|_| {
+            format!(
+                "{} --version took longer than {} seconds",
+                claude_path,
+                VERSION_PROBE_TIMEOUT.as_secs()
+            )
+        })?
+        .map_err(|e| format!("Failed to run {}: {}", claude_path, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} --version failed: {}",
+            claude_path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| format!("{} --version printed no version", claude_path))
+}
+
+/// Refuse an output format the installed version predates. An unknown
+/// version is let through, since it may well be new enough.
+pub fn check_output_format_supported(
+    installed: Option<&ClaudeVersion>,
+    format: OutputFormat,
+) -> Result<(), String> {
+    let (Some(installed), Some(required)) = (installed, parse_version(format.minimum_version()))
+    else {
+        log::warn!(
+            "Could not tell whether Claude Code supports --output-format {}",
+            format.as_str()
+        );
+        return Ok(());
+    };
+    if installed.compare(&required) == Ordering::Less {
+        return Err(format!(
+            "--output-format {} needs Claude Code {} or newer, but {} is installed",
+            format.as_str(),
+            required.raw,
+            installed.raw
+        ));
+    }
+    Ok(())
+}
+
+fn now_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The cached registry lookup, if fresh: `Some(None)` records a failed lookup
+fn cached_latest_version(conn: &Connection) -> Option<Option<String>> {
+    let read = |key: &str| -> Option<String> {
+        conn.query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .ok()
+    };
+    let checked_at = read("claude_latest_version_checked_at")?
+        .parse::<u64>()
+        .ok()?;
+    if now_seconds().saturating_sub(checked_at) >= LATEST_VERSION_TTL_SECONDS {
+        return None;
+    }
+    Some(read("claude_latest_version").filter(|v| !v.is_empty()))
+}
+
+fn cache_latest_version(conn: &Connection, version: Option<&str>) -> Result<(), String> {
+    for (key, value) in [
+        (
+            "claude_latest_version",
+            version.unwrap_or_default().to_string(),
+        ),
+        (
+            "claude_latest_version_checked_at",
+            now_seconds().to_string(),
+        ),
+    ] {
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = ?2",
+            params![key, value],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+async fn fetch_latest_version() -> Result<String, String> {
+    #[derive(Deserialize)]
+    struct NpmPackage {
+        version: String,
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(NPM_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .get(NPM_LATEST_URL)
+        .header("Accept", "application/json")
+        .header("User-Agent", "opcode-App")
+        .send()
+        .await
+        .map_err(|e| format!("Failed to query the npm registry: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("npm registry returned HTTP {}", response.status()));
+    }
+    let package: NpmPackage = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid npm registry response: {}", e))?;
+    Ok(package.version)
+}
+
+/// The latest published Claude Code version, from the cache or the npm
+/// registry. Failures are cached too so offline users wait at most once per
+/// TTL, and yield `None`.
+pub async fn latest_published_version(db: &AgentDb) -> Option<String> {
+    if let Ok(conn) = db.0.lock() {
+        if let Some(cached) = cached_latest_version(&conn) {
+            return cached;
+        }
+    }
+
+    let latest = match fetch_latest_version().await {
+        Ok(version) => Some(version),
+        Err(e) => {
+            log::warn!("Could not check for Claude Code updates: {}", e);
+            None
+        }
+    };
+    if let Ok(conn) = db.0.lock() {
+        if let Err(e) = cache_latest_version(&conn, latest.as_deref()) {
+            log::warn!("Failed to cache the latest Claude Code version: {}", e);
+        }
+    }
+    latest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_parse_and_compare() {
+        let version = parse_version("1.0.41 (Claude Code)").unwrap();
+        assert_eq!((version.major, version.minor, version.patch), (1, 0, 41));
+        assert_eq!(version.raw, "1.0.41");
+
+        let version = parse_version("claude v2.1.0-beta.2+build.7\n").unwrap();
+        assert_eq!(version.pre_release.as_deref(), Some("beta.2"));
+        assert_eq!(version.build.as_deref(), Some("build.7"));
+        assert_eq!(version.raw, "2.1.0-beta.2+build.7");
+
+        let v = |s: &str| parse_version(s).unwrap();
+        assert_eq!(v("1.0.10").compare(&v("1.0.9")), Ordering::Greater);
+        assert_eq!(v("1.0.0-rc.1").compare(&v("1.0.0")), Ordering::Less);
+        assert_eq!(
+            v("1.0.0-alpha.10").compare(&v("1.0.0-alpha.9")),
+            Ordering::Greater
+        );
+        assert_eq!(
+            v("1.0.0-alpha").compare(&v("1.0.0-alpha.1")),
+            Ordering::Less
+        );
+        assert_eq!(v("1.0.0+a").compare(&v("1.0.0+b")), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_assessment_degrades_on_garbled_output() {
+        let garbled = assess_version("command not found: claude", Some("1.0.50"));
+        assert!(garbled.parse_failed && garbled.parsed.is_none());
+        assert!(!garbled.update_available);
+
+        let old = assess_version("0.2.9 (Claude Code)", Some("1.0.50"));
+        assert!(old.below_minimum && old.update_available);
+        let current = assess_version("1.0.50 (Claude Code)", Some("1.0.50"));
+        assert!(!current.below_minimum && !current.update_available);
+        let offline = assess_version("1.0.3 (Claude Code)", None);
+        assert!(!offline.parse_failed && !offline.update_available);
+    }
+
+    #[test]
+    fn test_output_formats_need_a_recent_enough_version() {
+        let old = parse_version("0.1.9 (Claude Code)").unwrap();
+        let current = parse_version("1.0.41 (Claude Code)").unwrap();
+        assert!(check_output_format_supported(Some(&old), OutputFormat::Text).is_err());
+        assert!(check_output_format_supported(Some(&current), OutputFormat::Json).is_ok());
+        assert!(check_output_format_supported(None, OutputFormat::Text).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_binaries_that_fail_or_print_no_version_are_refused() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let script = |name: &str, body: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+
+        let pinned = script("claude", "echo '1.0.30 (Claude Code)'");
+        assert_eq!(probe_claude_version(&pinned).await.unwrap().raw, "1.0.30");
+        let failing = script("failing", "echo broken >&2; exit 1");
+        assert!(probe_claude_version(&failing).await.is_err());
+        let silent = script("silent", "true");
+        assert!(probe_claude_version(&silent).await.is_err());
+        assert!(probe_claude_version("/nonexistent/claude").await.is_err());
+    }
+
+    #[test]
+    fn test_latest_version_cache_expires() {
+        let conn = crate::db::test_connection();
+        assert_eq!(cached_latest_version(&conn), None);
+
+        cache_latest_version(&conn, Some("1.2.3")).unwrap();
+        assert_eq!(
+            cached_latest_version(&conn),
+            Some(Some("1.2.3".to_string()))
+        );
+        cache_latest_version(&conn, None).unwrap();
+        assert_eq!(cached_latest_version(&conn), Some(None));
+
+        conn.execute(
+            "UPDATE app_settings SET value = '0' WHERE key = 'claude_latest_version_checked_at'",
+            [],
+        )
+        .unwrap();
+        assert_eq!(cached_latest_version(&conn), None);
+    }
+}
@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use super::claude::FileEntry;
+
+/// Entries returned per page unless `limit` says otherwise
+const DEFAULT_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DirectorySort {
+    #[default]
+    Name,
+    Modified,
+    Size,
+}
+
+/// How `list_directory_contents` pages, filters and sorts. Directories are
+/// always listed before files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DirectoryListingOptions {
+    pub offset: usize,
+    pub limit: Option<usize>,
+    pub sort_by: DirectorySort,
+    pub descending: bool,
+    /// Only files with these extensions, e.g. `["ts", ".tsx"]`; directories
+    /// are always listed
+    pub extensions: Vec<String>,
+    /// List hidden entries besides `.claude`
+    pub include_hidden: bool,
+}
+
+/// A directory entry with the metadata the file picker shows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryEntry {
+    #[serde(flatten)]
+    pub entry: FileEntry,
+    /// Last modification, in seconds since the Unix epoch
+    pub modified: Option<u64>,
+    pub is_symlink: bool,
+    pub is_hidden: bool,
+}
+
+/// One page of a directory's entries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryListing {
+    pub entries: Vec<DirectoryEntry>,
+    /// Entries matching the filters across all pages
+    pub total: usize,
+    /// Offset of the next page, if there is one
+    pub next_offset: Option<usize>,
+    /// Entries left out because they couldn't be read, e.g. permission denied
+    pub skipped: usize,
+}
+
+#[cfg(windows)]
+fn has_hidden_attribute(metadata: &fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+}
+
+#[cfg(not(windows))]
+fn has_hidden_attribute(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Metadata of what a symlink points to, when that stays inside `root`.
+/// Links leading out of the listed directory are described by themselves.
+fn link_target_metadata(root: &Path, link: &Path) -> Option<fs::Metadata> {
+    let target = fs::canonicalize(link).ok()?;
+    if !target.starts_with(root) {
+        return None;
+    }
+    fs::metadata(target).ok()
+}
+
+fn matches_extension(extension: Option<&str>, wanted: &[String]) -> bool {
+    wanted.is_empty()
+        || extension.is_some_and(|extension| {
+            wanted
+                .iter()
+                .any(|w| w.trim_start_matches('.').eq_ignore_ascii_case(extension))
+        })
+}
+
+fn name_key(entry: &DirectoryEntry) -> String {
+    entry.entry.name.to_lowercase()
+}
+
+/// Order by the chosen key, then by name
+fn compare_entries(
+    a: &DirectoryEntry,
+    b: &DirectoryEntry,
+    options: &DirectoryListingOptions,
+) -> Ordering {
+    let order = match options.sort_by {
+        DirectorySort::Name => name_key(a).cmp(&name_key(b)),
+        DirectorySort::Modified => a.modified.cmp(&b.modified),
+        DirectorySort::Size => a.entry.size.cmp(&b.entry.size),
+    };
+    let order = if options.descending {
+        order.reverse()
+    } else {
+        order
+    };
+    order.then_with(|| name_key(a).cmp(&name_key(b)))
+}
+
+/// List one page of `directory`, skipping entries that can't be read
+pub fn list_directory(
+    directory: &Path,
+    options: &DirectoryListingOptions,
+) -> Result<DirectoryListing, String> {
+    let limit = options.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 {
+        return Err("limit must be at least 1".to_string());
+    }
+    let root =
+        fs::canonicalize(directory).map_err(|e| format!("Failed to resolve directory: {}", e))?;
+    let dir_entries =
+        fs::read_dir(directory).map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+    for entry in dir_entries {
+        // DirEntry::metadata doesn't follow symlinks
+        let Ok((entry, own_metadata)) = entry.and_then(|e| e.metadata().map(|m| (e, m))) else {
+            skipped += 1;
+            continue;
+        };
+        let entry_path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_hidden = name.starts_with('.') || has_hidden_attribute(&own_metadata);
+        if is_hidden && !options.include_hidden && name != ".claude" {
+            continue;
+        }
+
+        let is_symlink = own_metadata.file_type().is_symlink();
+        let metadata = if is_symlink {
+            link_target_metadata(&root, &entry_path).unwrap_or(own_metadata)
+        } else {
+            own_metadata
+        };
+        let extension = if metadata.is_file() {
+            entry_path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_string())
+        } else {
+            None
+        };
+        if !metadata.is_dir() && !matches_extension(extension.as_deref(), &options.extensions) {
+            continue;
+        }
+
+        entries.push(DirectoryEntry {
+            entry: FileEntry {
+                name,
+                path: entry_path.to_string_lossy().to_string(),
+                is_directory: metadata.is_dir(),
+                size: if metadata.is_dir() { 0 } else { metadata.len() },
+                extension,
+            },
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            is_symlink,
+            is_hidden,
+        });
+    }
+
+    entries.sort_by(|a, b| {
+        b.entry
+            .is_directory
+            .cmp(&a.entry.is_directory)
+            .then_with(|| compare_entries(a, b, options))
+    });
+
+    let total = entries.len();
+    let page: Vec<DirectoryEntry> = entries
+        .into_iter()
+        .skip(options.offset)
+        .take(limit)
+        .collect();
+    let end = options.offset.saturating_add(page.len());
+    Ok(DirectoryListing {
+        entries: page,
+        total,
+        next_offset: (end < total).then_some(end),
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(listing: &DirectoryListing) -> Vec<&str> {
+        listing
+            .entries
+            .iter()
+            .map(|e| e.entry.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_listing_is_sorted_filtered_and_paged() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::create_dir(root.join(".claude")).unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join("b.ts"), "1234").unwrap();
+        fs::write(root.join("A.tsx"), "12").unwrap();
+        fs::write(root.join("c.md"), "123456").unwrap();
+
+        let listing = list_directory(root, &DirectoryListingOptions::default()).unwrap();
+        assert_eq!(names(&listing), [".claude", "src", "A.tsx", "b.ts", "c.md"]);
+        assert_eq!(listing.next_offset, None);
+        assert!(listing.entries[0].is_hidden);
+
+        let by_size = DirectoryListingOptions {
+            sort_by: DirectorySort::Size,
+            descending: true,
+            extensions: vec!["TS".to_string(), ".tsx".to_string()],
+            include_hidden: true,
+            ..Default::default()
+        };
+        let listing = list_directory(root, &by_size).unwrap();
+        assert_eq!(names(&listing), [".claude", ".git", "src", "b.ts", "A.tsx"]);
+
+        let page = DirectoryListingOptions {
+            offset: 1,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let listing = list_directory(root, &page).unwrap();
+        assert_eq!(names(&listing), ["src", "A.tsx"]);
+        assert_eq!(listing.total, 5);
+        assert_eq!(listing.next_offset, Some(3));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_only_followed_inside_the_directory() {
+        let outside = tempfile::tempdir().unwrap();
+        fs::create_dir(outside.path().join("elsewhere")).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("inner")).unwrap();
+        std::os::unix::fs::symlink(root.join("inner"), root.join("inside")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("elsewhere"), root.join("outside")).unwrap();
+
+        let listing = list_directory(root, &DirectoryListingOptions::default()).unwrap();
+        let entry = |name: &str| {
+            listing
+                .entries
+                .iter()
+                .find(|e| e.entry.name == name)
+                .unwrap()
+                .clone()
+        };
+        assert!(entry("inside").is_symlink && entry("inside").entry.is_directory);
+        assert!(entry("outside").is_symlink && !entry("outside").entry.is_directory);
+    }
+}
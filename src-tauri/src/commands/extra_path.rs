@@ -0,0 +1,197 @@
+use log::warn;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{OnceLock, RwLock};
+use tauri::State;
+
+use super::agents::AgentDb;
+
+const EXTRA_PATH_SETTING_KEY: &str = "extra_path_entries";
+
+/// Directories put in front of PATH for finding Claude and for the processes
+/// opcode starts, since GUI apps on macOS get a minimal PATH
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExtraPathSettings {
+    pub entries: Vec<String>,
+    /// Entries that aren't an existing directory, which are kept anyway
+    #[serde(default)]
+    pub missing: Vec<String>,
+}
+
+/// `entry` with a leading `~` replaced by the home directory
+pub fn expand_home(entry: &str) -> PathBuf {
+    let rest = match entry.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return PathBuf::from(entry),
+    };
+    match dirs::home_dir() {
+        Some(home) => home.join(rest.trim_start_matches(['/', '\\'])),
+        None => PathBuf::from(entry),
+    }
+}
+
+/// Entries trimmed, without blanks and without repeats of an earlier entry
+/// once `~` is expanded
+pub fn normalize_entries(entries: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    entries
+        .into_iter()
+        .map(|entry| entry.trim().to_string())
+        .filter(|entry| !entry.is_empty() && seen.insert(expand_home(entry)))
+        .collect()
+}
+
+/// `path` with `entries` moved or added to the front, so each directory is
+/// listed once. The platform's separator is used, `;` on Windows.
+pub fn path_with_entries(entries: &[PathBuf], path: &OsStr) -> OsString {
+    let rest = std::env::split_paths(path).filter(|dir| !entries.contains(dir));
+    std::env::join_paths(entries.iter().cloned().chain(rest)).unwrap_or_else(|e| {
+        warn!("Ignoring extra PATH entries: {}", e);
+        path.to_os_string()
+    })
+}
+
+fn extra_path_lock() -> &'static RwLock<Vec<String>> {
+    static EXTRA_PATH: OnceLock<RwLock<Vec<String>>> = OnceLock::new();
+    EXTRA_PATH.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+/// The extra PATH entries in effect, with `~` expanded
+pub fn current_extra_path() -> Vec<PathBuf> {
+    extra_path_lock()
+        .read()
+        .map(|entries| entries.iter().map(|entry| expand_home(entry)).collect())
+        .unwrap_or_default()
+}
+
+/// This process's PATH with the extra entries in front
+pub fn extended_path() -> OsString {
+    path_with_entries(
+        &current_extra_path(),
+        &std::env::var_os("PATH").unwrap_or_default(),
+    )
+}
+
+/// Put the extra entries in front of the PATH `cmd` will run with
+pub fn apply_extra_path(cmd: &mut Command) {
+    let entries = current_extra_path();
+    if entries.is_empty() {
+        return;
+    }
+    let inherited = cmd
+        .get_envs()
+        .find(|(key, _)| *key == "PATH")
+        .and_then(|(_, value)| value.map(OsStr::to_os_string))
+        .or_else(|| std::env::var_os("PATH"))
+        .unwrap_or_default();
+    cmd.env("PATH", path_with_entries(&entries, &inherited));
+}
+
+fn missing_entries(entries: &[String]) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|entry| !expand_home(entry).is_dir())
+        .cloned()
+        .collect()
+}
+
+fn read_entries(conn: &Connection) -> Vec<String> {
+    let saved = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![EXTRA_PATH_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    );
+    match saved.map(|json| serde_json::from_str::<Vec<String>>(&json)) {
+        Ok(Ok(entries)) => entries,
+        Ok(Err(e)) => {
+            warn!("Ignoring invalid extra PATH entries: {}", e);
+            Vec::new()
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Load the saved extra PATH entries
+pub fn load_extra_path(conn: &Connection) {
+    let entries = read_entries(conn);
+    if let Ok(mut current) = extra_path_lock().write() {
+        *current = entries;
+    }
+}
+
+/// Get the directories put in front of PATH
+#[tauri::command]
+pub async fn get_extra_path_entries(db: State<'_, AgentDb>) -> Result<ExtraPathSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let entries = read_entries(&conn);
+    Ok(ExtraPathSettings {
+        missing: missing_entries(&entries),
+        entries,
+    })
+}
+
+/// Save the directories put in front of PATH, from the next process on.
+/// Entries that don't exist are kept and returned in `missing`.
+#[tauri::command]
+pub async fn set_extra_path_entries(
+    db: State<'_, AgentDb>,
+    entries: Vec<String>,
+) -> Result<ExtraPathSettings, String> {
+    let entries = normalize_entries(entries);
+    let separator = if cfg!(windows) { ';' } else { ':' };
+    if let Some(entry) = entries.iter().find(|entry| entry.contains(separator)) {
+        return Err(format!("Enter one directory per entry: {}", entry));
+    }
+    let missing = missing_entries(&entries);
+    if !missing.is_empty() {
+        warn!(
+            "Extra PATH entries that don't exist: {}",
+            missing.join(", ")
+        );
+    }
+
+    let json = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![EXTRA_PATH_SETTING_KEY, json],
+    )
+    .map_err(|e| format!("Failed to save extra PATH entries: {}", e))?;
+    load_extra_path(&conn);
+    Ok(ExtraPathSettings { entries, missing })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_are_expanded_deduplicated_and_prepended() {
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("~/.bun/bin"), home.join(".bun/bin"));
+        assert_eq!(expand_home("~user/bin"), PathBuf::from("~user/bin"));
+
+        let entries = normalize_entries(vec![
+            " ~/.bun/bin ".to_string(),
+            String::new(),
+            format!("{}", home.join(".bun/bin").display()),
+            "/opt/tools".to_string(),
+        ]);
+        assert_eq!(entries, ["~/.bun/bin", "/opt/tools"]);
+
+        let existing = std::env::join_paths(["/usr/bin", "/opt/tools"]).unwrap();
+        let path = path_with_entries(
+            &[PathBuf::from("/opt/uv"), PathBuf::from("/opt/tools")],
+            &existing,
+        );
+        assert_eq!(
+            std::env::split_paths(&path).collect::<Vec<_>>(),
+            ["/opt/uv", "/opt/tools", "/usr/bin"].map(PathBuf::from)
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::claude::FileEntry;
+
+/// Results returned unless `max_results` says otherwise
+const DEFAULT_MAX_RESULTS: usize = 100;
+
+/// Longest a search may walk before returning what it has found
+pub const SEARCH_TIME_LIMIT: Duration = Duration::from_secs(3);
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CONSECUTIVE: i64 = 6;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+/// How `search_files` walks and matches. The default honors ignore files,
+/// skips hidden entries and matches substrings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FileSearchOptions {
+    /// Match the query's characters in order rather than as a substring
+    pub fuzzy: bool,
+    /// Also search what `.gitignore` and `.ignore` files exclude
+    pub include_ignored: bool,
+    pub include_hidden: bool,
+    pub max_results: Option<usize>,
+}
+
+/// A file or directory whose name matched the query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSearchResult {
+    #[serde(flatten)]
+    pub entry: FileEntry,
+    /// Higher is a better match
+    pub score: i64,
+    /// Matched `[start, end)` character ranges of `name`
+    pub matched_ranges: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameMatch {
+    pub score: i64,
+    pub ranges: Vec<(usize, usize)>,
+}
+
+fn same_char(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Whether `chars[i]` starts a word, e.g. after a separator or a camelCase hump
+fn is_word_start(chars: &[char], i: usize) -> bool {
+    let Some(&prev) = i.checked_sub(1).and_then(|p| chars.get(p)) else {
+        return true;
+    };
+    matches!(prev, '-' | '_' | '.' | ' ' | '/' | '\\')
+        || (prev.is_lowercase() && chars[i].is_uppercase())
+}
+
+fn to_ranges(positions: &[usize]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &i in positions {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == i => *end = i + 1,
+            _ => ranges.push((i, i + 1)),
+        }
+    }
+    ranges
+}
+
+/// Case-insensitive substring match; exact names score highest, then prefixes
+pub fn substring_match(query: &str, name: &str) -> Option<NameMatch> {
+    let query: Vec<char> = query.chars().collect();
+    let chars: Vec<char> = name.chars().collect();
+    if query.is_empty() || query.len() > chars.len() {
+        return None;
+    }
+    let start = (0..=chars.len() - query.len()).find(|&start| {
+        query
+            .iter()
+            .zip(&chars[start..])
+            .all(|(&q, &c)| same_char(q, c))
+    })?;
+    let score = if query.len() == chars.len() {
+        2
+    } else if start == 0 {
+        1
+    } else {
+        0
+    };
+    Some(NameMatch {
+        score,
+        ranges: vec![(start, start + query.len())],
+    })
+}
+
+/// fzf-style subsequence match: the query's characters must appear in order.
+/// Of the possible matches the shortest one ending earliest is scored, with
+/// bonuses for consecutive characters and word starts and a penalty for gaps.
+pub fn fuzzy_match(query: &str, name: &str) -> Option<NameMatch> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    let chars: Vec<char> = name.chars().collect();
+    if query.is_empty() {
+        return None;
+    }
+
+    // Find where the first complete match ends...
+    let mut matched = 0;
+    let end = chars.iter().position(|&c| {
+        if same_char(c, query[matched]) {
+            matched += 1;
+        }
+        matched == query.len()
+    })?;
+    // ...then walk back to the latest start that still matches
+    let mut remaining = query.len();
+    let start = (0..=end)
+        .rev()
+        .find(|&i| {
+            if same_char(chars[i], query[remaining - 1]) {
+                remaining -= 1;
+            }
+            remaining == 0
+        })
+        .unwrap_or(0);
+
+    let mut positions = Vec::with_capacity(query.len());
+    for (i, &c) in chars.iter().enumerate().take(end + 1).skip(start) {
+        if positions.len() < query.len() && same_char(c, query[positions.len()]) {
+            positions.push(i);
+        }
+    }
+
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+    for &i in &positions {
+        score += SCORE_MATCH;
+        if is_word_start(&chars, i) {
+            score += BONUS_BOUNDARY;
+        }
+        match previous {
+            Some(p) if i == p + 1 => score += BONUS_CONSECUTIVE,
+            Some(p) => score -= PENALTY_GAP_START + (i - p - 2) as i64 * PENALTY_GAP_EXTENSION,
+            None => {}
+        }
+        previous = Some(i);
+    }
+
+    Some(NameMatch {
+        score,
+        ranges: to_ranges(&positions),
+    })
+}
+
+/// Search `base` for entries whose name matches `query`, best matches first.
+/// Stops walking after `time_limit` and returns what it found by then.
+pub fn search(
+    base: &Path,
+    query: &str,
+    options: &FileSearchOptions,
+    time_limit: Duration,
+) -> Result<Vec<FileSearchResult>, String> {
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+    if max_results == 0 {
+        return Err("max_results must be at least 1".to_string());
+    }
+    let respect_ignores = !options.include_ignored;
+    let walker = WalkBuilder::new(base)
+        .hidden(!options.include_hidden)
+        .ignore(respect_ignores)
+        .git_ignore(respect_ignores)
+        .git_global(respect_ignores)
+        .git_exclude(respect_ignores)
+        // Honor .gitignore files even outside a git repository
+        .require_git(false)
+        // Symlinked directories are followed; loops are reported as errors
+        .follow_links(true)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build();
+
+    let started = Instant::now();
+    let mut results = Vec::new();
+    for entry in walker {
+        if started.elapsed() > time_limit {
+            log::warn!("File search in {:?} stopped after {:?}", base, time_limit);
+            break;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::debug!("Skipping entry while searching files: {}", e);
+                continue;
+            }
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let name_match = if options.fuzzy {
+            fuzzy_match(query, &name)
+        } else {
+            substring_match(query, &name)
+        };
+        let Some(name_match) = name_match else {
+            continue;
+        };
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        let extension = if metadata.is_file() {
+            entry
+                .path()
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_string())
+        } else {
+            None
+        };
+        results.push(FileSearchResult {
+            entry: FileEntry {
+                name,
+                path: entry.path().to_string_lossy().to_string(),
+                is_directory: metadata.is_dir(),
+                size: metadata.len(),
+                extension,
+            },
+            score: name_match.score,
+            matched_ranges: name_match.ranges,
+        });
+    }
+
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.entry.name.len().cmp(&b.entry.name.len()))
+            .then_with(|| {
+                a.entry
+                    .name
+                    .to_lowercase()
+                    .cmp(&b.entry.name.to_lowercase())
+            })
+    });
+    results.truncate(max_results);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_fuzzy_matches_rank_word_starts_and_runs_first() {
+        let hit = fuzzy_match("fb", "FooBar.tsx").unwrap();
+        assert_eq!(hit.ranges, [(0, 1), (3, 4)]);
+        assert!(fuzzy_match("bf", "FooBar.tsx").is_none());
+
+        // The tightest match is reported, not the first 't'
+        let hit = fuzzy_match("tab", "data_tab.rs").unwrap();
+        assert_eq!(hit.ranges, [(5, 8)]);
+
+        let run = fuzzy_match("main", "main.rs").unwrap();
+        let spread = fuzzy_match("main", "my_animation.rs").unwrap();
+        assert!(run.score > spread.score);
+        let boundary = fuzzy_match("sv", "session_view.tsx").unwrap();
+        let middle = fuzzy_match("sv", "observer.ts").unwrap();
+        assert!(boundary.score > middle.score);
+    }
+
+    #[test]
+    fn test_substring_matches_prefer_exact_names() {
+        assert_eq!(
+            substring_match("READ", "readme.md").unwrap().ranges,
+            [(0, 4)]
+        );
+        assert_eq!(substring_match("me", "readme.md").unwrap().ranges, [(4, 6)]);
+        assert!(
+            substring_match("readme.md", "README.md").unwrap().score
+                > substring_match("readme", "readme.md").unwrap().score
+        );
+        assert!(substring_match("x", "readme.md").is_none());
+    }
+
+    #[test]
+    fn test_search_honors_ignore_files_and_hidden_option() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join(".gitignore"), "build/\n").unwrap();
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join("build/app.js"), "").unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/app.ts"), "").unwrap();
+        fs::write(root.join(".app.env"), "").unwrap();
+
+        let names = |options: FileSearchOptions| -> Vec<String> {
+            let mut names: Vec<String> = search(root, "app", &options, SEARCH_TIME_LIMIT)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.entry.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(FileSearchOptions::default()), ["app.ts"]);
+        assert_eq!(
+            names(FileSearchOptions {
+                include_ignored: true,
+                include_hidden: true,
+                ..Default::default()
+            }),
+            [".app.env", "app.js", "app.ts"]
+        );
+        let capped = FileSearchOptions {
+            include_ignored: true,
+            max_results: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            search(root, "app", &capped, SEARCH_TIME_LIMIT)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_search_survives_symlink_loops() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("nested")).unwrap();
+        fs::write(root.join("nested/target.txt"), "").unwrap();
+        std::os::unix::fs::symlink(root, root.join("nested/loop")).unwrap();
+
+        let results = search(
+            root,
+            "target",
+            &FileSearchOptions::default(),
+            SEARCH_TIME_LIMIT,
+        )
+        .unwrap();
+        assert_eq!(results.len(), 1);
+    }
+}
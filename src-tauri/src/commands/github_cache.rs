@@ -0,0 +1,145 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Seconds a cached GitHub response is used without asking GitHub again
+const DEFAULT_GITHUB_CACHE_TTL_SECONDS: u64 = 3600;
+
+/// A GitHub API response body with the headers needed to revalidate it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitHubCacheEntry {
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at: i64, // Unix seconds of the last fetch or revalidation
+}
+
+impl GitHubCacheEntry {
+    /// Whether the entry can be served without revalidating it
+    pub fn is_fresh(&self, now: i64, ttl_seconds: u64) -> bool {
+        now.saturating_sub(self.fetched_at) < ttl_seconds as i64
+    }
+
+    pub fn fetched_at_rfc3339(&self) -> String {
+        chrono::DateTime::from_timestamp(self.fetched_at, 0)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default()
+    }
+}
+
+/// Cache key of a path in a repository at a branch, tag or commit
+pub fn github_cache_key(repo: &str, git_ref: Option<&str>, path: &str) -> String {
+    let git_ref = git_ref.map(str::trim).unwrap_or_default();
+    format!("{}@{}:{}", repo.to_lowercase(), git_ref, path)
+}
+
+pub fn load_cache_entry(conn: &Connection, key: &str) -> Result<Option<GitHubCacheEntry>, String> {
+    conn.query_row(
+        "SELECT body, etag, last_modified, fetched_at FROM github_cache WHERE cache_key = ?1",
+        params![key],
+        |row| {
+            Ok(GitHubCacheEntry {
+                body: row.get(0)?,
+                etag: row.get(1)?,
+                last_modified: row.get(2)?,
+                fetched_at: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+pub fn store_cache_entry(conn: &Connection, key: &str, entry: &GitHubCacheEntry) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO github_cache (cache_key, body, etag, last_modified, fetched_at) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(cache_key) DO UPDATE SET body = ?2, etag = ?3, last_modified = ?4, fetched_at = ?5",
+        params![key, entry.body, entry.etag, entry.last_modified, entry.fetched_at],
+    )
+    .map_err(|e| format!("Failed to cache GitHub response: {}", e))?;
+    Ok(())
+}
+
+/// Mark an entry as just revalidated, after GitHub answered 304 Not Modified
+pub fn touch_cache_entry(conn: &Connection, key: &str, now: i64) -> Result<(), String> {
+    conn.execute(
+        "UPDATE github_cache SET fetched_at = ?1 WHERE cache_key = ?2",
+        params![now, key],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn get_cache_ttl_setting(conn: &Connection) -> Result<u64, String> {
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = 'github_cache_ttl_seconds'",
+        [],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(value) => Ok(value.parse::<u64>().unwrap_or(DEFAULT_GITHUB_CACHE_TTL_SECONDS)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(DEFAULT_GITHUB_CACHE_TTL_SECONDS),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Get how long cached GitHub agent listings and files are used before revalidating
+#[tauri::command]
+pub async fn get_github_cache_ttl_seconds(db: State<'_, AgentDb>) -> Result<u64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    get_cache_ttl_setting(&conn)
+}
+
+/// Set how long cached GitHub agent listings and files are used before revalidating
+#[tauri::command]
+pub async fn set_github_cache_ttl_seconds(
+    db: State<'_, AgentDb>,
+    seconds: u64,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES ('github_cache_ttl_seconds', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1",
+        params![seconds.to_string()],
+    )
+    .map_err(|e| format!("Failed to save github_cache_ttl_seconds: {}", e))?;
+    Ok(())
+}
+
+/// Remove every cached GitHub response, returning how many were removed
+#[tauri::command]
+pub async fn clear_github_cache(db: State<'_, AgentDb>) -> Result<usize, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM github_cache", [])
+        .map_err(|e| format!("Failed to clear the GitHub cache: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_round_trip_and_expire_after_ttl() {
+        let conn = crate::db::test_connection();
+        assert_eq!(get_cache_ttl_setting(&conn).unwrap(), DEFAULT_GITHUB_CACHE_TTL_SECONDS);
+
+        let key = github_cache_key("getAsterisk/opcode", Some(" main "), "cc_agents");
+        assert_eq!(key, "getasterisk/opcode@main:cc_agents");
+        assert_eq!(load_cache_entry(&conn, &key).unwrap(), None);
+
+        let entry = GitHubCacheEntry {
+            body: "[]".to_string(),
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+            fetched_at: 1_000,
+        };
+        store_cache_entry(&conn, &key, &entry).unwrap();
+        let cached = load_cache_entry(&conn, &key).unwrap().unwrap();
+        assert_eq!(cached, entry);
+        assert!(cached.is_fresh(1_000 + 59, 60));
+        assert!(!cached.is_fresh(1_000 + 60, 60));
+
+        touch_cache_entry(&conn, &key, 2_000).unwrap();
+        assert_eq!(load_cache_entry(&conn, &key).unwrap().unwrap().fetched_at, 2_000);
+    }
+}
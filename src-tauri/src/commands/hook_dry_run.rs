@@ -0,0 +1,417 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+
+use super::hook_shell::HookShell;
+use super::hooks_config::validate_hook_event;
+
+/// How long a dry-run hook may run before it is killed
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to keep reading output after a timed out hook was killed
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// Session id sent in dry-run payloads, so hooks can tell them apart
+const DRY_RUN_SESSION_ID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Permission decision a PreToolUse hook returned
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookPermission {
+    Allow,
+    Deny,
+    Ask,
+}
+
+/// What Claude would do with a hook's result
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HookDecision {
+    pub permission: Option<HookPermission>,
+    pub reason: Option<String>,
+    /// Tool input the hook replaced the original with
+    pub updated_input: Option<JsonValue>,
+    /// `false` when the hook asked Claude to stop the session
+    pub continue_session: bool,
+}
+
+/// Outcome of running a hook command against a sample payload
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HookTestResult {
+    pub payload: JsonValue,
+    /// `None` when the hook was killed
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    /// `None` when the hook exited normally without hook JSON on stdout
+    pub decision: Option<HookDecision>,
+}
+
+/// Merge `overrides` into `base`, recursing into objects so single nested
+/// fields like `tool_input.command` can be overridden
+fn merge_json(base: &mut JsonValue, overrides: JsonValue) {
+    match (base, overrides) {
+        (JsonValue::Object(base), JsonValue::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// The JSON Claude Code sends a hook on stdin for `event`, with
+/// `overrides` merged over realistic defaults
+pub fn build_hook_payload(
+    event: &str,
+    project_path: &Path,
+    overrides: Option<JsonValue>,
+) -> Result<JsonValue, String> {
+    validate_hook_event(event)?;
+    let cwd = project_path.to_string_lossy().into_owned();
+    let transcript_path = dirs::home_dir()
+        .unwrap_or_default()
+        .join(".claude")
+        .join("projects")
+        .join(cwd.replace(['/', '\\'], "-"))
+        .join(format!("{}.jsonl", DRY_RUN_SESSION_ID));
+
+    let mut payload = json!({
+        "session_id": DRY_RUN_SESSION_ID,
+        "transcript_path": transcript_path.to_string_lossy(),
+        "cwd": cwd,
+        "hook_event_name": event,
+    });
+    let tool_input = json!({ "command": "ls -la", "description": "List files in the project" });
+    let event_fields = match event {
+        "PreToolUse" => json!({ "tool_name": "Bash", "tool_input": tool_input }),
+        "PostToolUse" => json!({
+            "tool_name": "Bash",
+            "tool_input": tool_input,
+            "tool_response": { "stdout": "", "stderr": "", "interrupted": false, "isImage": false },
+        }),
+        "Notification" => json!({ "message": "Claude needs your permission to use Bash" }),
+        "UserPromptSubmit" => json!({ "prompt": "Summarize the changes on this branch" }),
+        "Stop" | "SubagentStop" => json!({ "stop_hook_active": false }),
+        "PreCompact" => json!({ "trigger": "manual", "custom_instructions": "" }),
+        "SessionStart" => json!({ "source": "startup" }),
+        "SessionEnd" => json!({ "reason": "exit" }),
+        _ => json!({}),
+    };
+    merge_json(&mut payload, event_fields);
+
+    match overrides {
+        None => {}
+        Some(overrides @ JsonValue::Object(_)) => merge_json(&mut payload, overrides),
+        Some(_) => return Err("Sample payload must be a JSON object".to_string()),
+    }
+    Ok(payload)
+}
+
+/// Interpret a hook's result the way Claude does: exit code 2 blocks with
+/// stderr as the reason, otherwise stdout may hold hook JSON
+pub fn parse_hook_decision(
+    exit_code: Option<i32>,
+    stdout: &str,
+    stderr: &str,
+) -> Option<HookDecision> {
+    if exit_code == Some(2) {
+        return Some(HookDecision {
+            permission: Some(HookPermission::Deny),
+            reason: Some(stderr.trim().to_string()).filter(|r| !r.is_empty()),
+            updated_input: None,
+            continue_session: true,
+        });
+    }
+
+    let output: JsonValue = serde_json::from_str(stdout.trim()).ok()?;
+    let output = output.as_object()?;
+    let specific = output.get("hookSpecificOutput").and_then(|o| o.as_object());
+    let text = |value: Option<&JsonValue>| value.and_then(|v| v.as_str()).map(String::from);
+
+    let mut permission = None;
+    let mut reason = None;
+    if let Some(specific) = specific {
+        permission = match specific.get("permissionDecision").and_then(|d| d.as_str()) {
+            Some("allow") => Some(HookPermission::Allow),
+            Some("deny") => Some(HookPermission::Deny),
+            Some("ask") => Some(HookPermission::Ask),
+            _ => None,
+        };
+        reason = text(specific.get("permissionDecisionReason"));
+    }
+    // The older top-level fields, still honored by Claude
+    if permission.is_none() {
+        permission = match output.get("decision").and_then(|d| d.as_str()) {
+            Some("approve") => Some(HookPermission::Allow),
+            Some("block") => Some(HookPermission::Deny),
+            _ => None,
+        };
+    }
+    let reason = reason
+        .or_else(|| text(output.get("reason")))
+        .or_else(|| text(output.get("stopReason")));
+    let updated_input = specific.and_then(|s| s.get("updatedInput")).cloned();
+    let continue_session = output
+        .get("continue")
+        .and_then(|c| c.as_bool())
+        .unwrap_or(true);
+
+    Some(HookDecision {
+        permission,
+        reason,
+        updated_input,
+        continue_session,
+    })
+}
+
+/// Kill a hook and everything it started. The hook leads its own process
+/// group on Unix, so its PID is the group id.
+fn kill_process_tree(pid: u32) {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(-(pid as i32), libc::SIGKILL);
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = std::process::Command::new("taskkill")
+            .args(["/F", "/T", "/PID", &pid.to_string()])
+            .output();
+    }
+}
+
+fn read_output<R: AsyncRead + Unpin + Send + 'static>(
+    reader: Option<R>,
+) -> tokio::task::JoinHandle<Vec<u8>> {
+    tokio::spawn(async move {
+        let mut output = Vec::new();
+        if let Some(mut reader) = reader {
+            let _ = reader.read_to_end(&mut output).await;
+        }
+        output
+    })
+}
+
+async fn collect_output(handle: tokio::task::JoinHandle<Vec<u8>>) -> String {
+    match tokio::time::timeout(OUTPUT_GRACE, handle).await {
+        Ok(Ok(output)) => String::from_utf8_lossy(&output).into_owned(),
+        _ => String::new(),
+    }
+}
+
+/// Run `command` in `shell` and `cwd` with `payload` on stdin, killing it and
+/// its children once `timeout` passes
+pub async fn run_hook(
+    command: &str,
+    shell: HookShell,
+    cwd: &Path,
+    payload: JsonValue,
+    timeout: Duration,
+) -> Result<HookTestResult, String> {
+    let mut cmd = shell.command(command);
+    super::extra_path::apply_extra_path(&mut cmd);
+    let mut cmd = Command::from(cmd);
+    cmd.current_dir(cwd)
+        .env("CLAUDE_PROJECT_DIR", cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let started = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to run hook command: {}", e))?;
+    let pid = child.id();
+    let stdout = read_output(child.stdout.take());
+    let stderr = read_output(child.stderr.take());
+
+    // A hook that ignores its input may exit before reading it
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = payload.to_string();
+        tokio::spawn(async move {
+            let _ = stdin.write_all(input.as_bytes()).await;
+        });
+    }
+
+    let (exit_code, timed_out) = match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => {
+            let status = status.map_err(|e| format!("Failed to wait for hook command: {}", e))?;
+            (status.code(), false)
+        }
+        Err(_) => {
+            log::warn!("Hook command timed out after {:?}, killing it", timeout);
+            if let Some(pid) = pid {
+                kill_process_tree(pid);
+            }
+            let _ = child.kill().await;
+            (None, true)
+        }
+    };
+
+    let stdout = collect_output(stdout).await;
+    let stderr = collect_output(stderr).await;
+    let decision = if timed_out {
+        None
+    } else {
+        parse_hook_decision(exit_code, &stdout, &stderr)
+    };
+    Ok(HookTestResult {
+        payload,
+        exit_code,
+        stdout,
+        stderr,
+        timed_out,
+        duration_ms: started.elapsed().as_millis() as u64,
+        decision,
+    })
+}
+
+/// Run a hook command against a sample payload for `event`, the way Claude
+/// would during a session. Fields in `sample_payload` override the defaults;
+/// `shell` is the hook's `shell` field, defaulting to the platform's shell.
+#[tauri::command]
+pub async fn test_hook_command(
+    command: String,
+    event: String,
+    project_path: String,
+    sample_payload: Option<JsonValue>,
+    shell: Option<String>,
+) -> Result<HookTestResult, String> {
+    let shell = HookShell::parse(shell.as_deref())?;
+    let cwd = Path::new(&project_path);
+    if !cwd.is_dir() {
+        return Err(format!("Project directory not found: {}", project_path));
+    }
+    let payload = build_hook_payload(&event, cwd, sample_payload)?;
+    log::info!("Dry-running {} hook in {}", event, project_path);
+    run_hook(&command, shell, cwd, payload, HOOK_TIMEOUT).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads_have_event_defaults_and_merge_overrides() {
+        let project = Path::new("/work/app");
+        let payload = build_hook_payload(
+            "PreToolUse",
+            project,
+            Some(json!({ "tool_input": { "command": "rm -rf /" } })),
+        )
+        .unwrap();
+        assert_eq!(payload["hook_event_name"], "PreToolUse");
+        assert_eq!(payload["cwd"], "/work/app");
+        assert_eq!(payload["tool_name"], "Bash");
+        assert_eq!(payload["tool_input"]["command"], "rm -rf /");
+        assert_eq!(
+            payload["tool_input"]["description"],
+            "List files in the project"
+        );
+
+        let payload = build_hook_payload("SessionStart", project, None).unwrap();
+        assert_eq!(payload["source"], "startup");
+        assert!(build_hook_payload("OnError", project, None).is_err());
+        assert!(build_hook_payload("Stop", project, Some(json!([]))).is_err());
+    }
+
+    #[test]
+    fn test_decisions_are_parsed_from_exit_codes_and_hook_json() {
+        let blocked = parse_hook_decision(Some(2), "", "no rm please\n").unwrap();
+        assert_eq!(blocked.permission, Some(HookPermission::Deny));
+        assert_eq!(blocked.reason.as_deref(), Some("no rm please"));
+
+        let modified = parse_hook_decision(
+            Some(0),
+            r#"{"hookSpecificOutput":{"hookEventName":"PreToolUse","permissionDecision":"allow","permissionDecisionReason":"safe","updatedInput":{"command":"ls"}}}"#,
+            "",
+        )
+        .unwrap();
+        assert_eq!(modified.permission, Some(HookPermission::Allow));
+        assert_eq!(modified.reason.as_deref(), Some("safe"));
+        assert_eq!(modified.updated_input, Some(json!({ "command": "ls" })));
+
+        let legacy = parse_hook_decision(
+            Some(0),
+            r#"{"decision":"block","reason":"x","continue":false}"#,
+            "",
+        )
+        .unwrap();
+        assert_eq!(legacy.permission, Some(HookPermission::Deny));
+        assert!(!legacy.continue_session);
+
+        assert_eq!(parse_hook_decision(Some(0), "plain output", ""), None);
+        assert_eq!(parse_hook_decision(Some(1), "", "error"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hooks_read_the_payload_and_are_killed_with_children_on_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let payload = build_hook_payload("PreToolUse", dir.path(), None).unwrap();
+
+        let command = r#"grep -q '"tool_name":"Bash"' && pwd && echo '{"decision":"approve"}' >&2"#;
+        let result = run_hook(
+            command,
+            HookShell::Sh,
+            dir.path(),
+            payload.clone(),
+            HOOK_TIMEOUT,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.exit_code, Some(0));
+        assert_eq!(
+            Path::new(result.stdout.trim()).canonicalize().unwrap(),
+            dir.path().canonicalize().unwrap()
+        );
+        assert!(result.stderr.contains("approve"));
+
+        let pid_file = dir.path().join("child.pid");
+        let command = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        let result = run_hook(
+            &command,
+            HookShell::Sh,
+            dir.path(),
+            payload,
+            Duration::from_millis(500),
+        )
+        .await
+        .unwrap();
+        assert!(result.timed_out);
+        assert_eq!(result.exit_code, None);
+
+        let child_pid: i32 = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // A killed child awaiting its reaper shows as a zombie
+        let alive = unsafe { libc::kill(child_pid, 0) } == 0
+            && !std::process::Command::new("ps")
+                .args(["-o", "stat=", "-p", &child_pid.to_string()])
+                .output()
+                .map(|output| {
+                    String::from_utf8_lossy(&output.stdout)
+                        .trim()
+                        .starts_with('Z')
+                })
+                .unwrap_or(false);
+        assert!(!alive);
+    }
+}
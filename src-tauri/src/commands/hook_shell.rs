@@ -0,0 +1,237 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// Shell a hook command runs in, set per hook with the optional `shell` field
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookShell {
+    Sh,
+    Bash,
+    Cmd,
+    PowerShell,
+}
+
+/// Names accepted in a hook's `shell` field
+pub const HOOK_SHELLS: [&str; 4] = ["sh", "bash", "cmd", "powershell"];
+
+/// Switch PowerShell's pipes to UTF-8 so unicode survives the payload and output
+const POWERSHELL_PRELUDE: &str = "[Console]::InputEncoding = [Console]::OutputEncoding = \
+                                  [System.Text.Encoding]::UTF8; $OutputEncoding = \
+                                  [System.Text.Encoding]::UTF8\n";
+
+impl HookShell {
+    /// `cmd` on Windows, `sh` everywhere else
+    pub fn platform_default() -> Self {
+        if cfg!(windows) {
+            HookShell::Cmd
+        } else {
+            HookShell::Sh
+        }
+    }
+
+    /// The shell named in a hook's `shell` field, or the platform default
+    pub fn parse(name: Option<&str>) -> Result<Self, String> {
+        match name.map(|n| n.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("") => Ok(Self::platform_default()),
+            Some("sh") => Ok(HookShell::Sh),
+            Some("bash") => Ok(HookShell::Bash),
+            Some("cmd") => Ok(HookShell::Cmd),
+            Some("powershell") | Some("pwsh") => Ok(HookShell::PowerShell),
+            Some(other) => Err(format!(
+                "Unknown hook shell '{}', expected one of {}",
+                other,
+                HOOK_SHELLS.join(", ")
+            )),
+        }
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            HookShell::Sh => "sh",
+            HookShell::Bash => "bash",
+            HookShell::Cmd => "cmd",
+            // Windows PowerShell ships with Windows; elsewhere only PowerShell 7 exists
+            HookShell::PowerShell if cfg!(windows) => "powershell",
+            HookShell::PowerShell => "pwsh",
+        }
+    }
+
+    /// A process that runs `command` in this shell, reading the hook payload on stdin
+    pub fn command(self, command: &str) -> Command {
+        let mut cmd = Command::new(self.program());
+        match self {
+            HookShell::Sh | HookShell::Bash => {
+                cmd.arg("-c").arg(command);
+            }
+            HookShell::Cmd => {
+                // Switch to the UTF-8 code page first so unicode output isn't mangled
+                let script = format!("chcp 65001 >nul & {}", command);
+                push_cmd_script(&mut cmd, &script);
+            }
+            HookShell::PowerShell => {
+                cmd.args([
+                    "-NoProfile",
+                    "-NonInteractive",
+                    "-ExecutionPolicy",
+                    "Bypass",
+                ])
+                .arg("-EncodedCommand")
+                .arg(encode_powershell(&format!(
+                    "{}{}",
+                    POWERSHELL_PRELUDE, command
+                )));
+            }
+        }
+        cmd
+    }
+
+    /// A process that checks `command`'s syntax without running it, or `None`
+    /// for shells that can't parse without executing
+    pub fn syntax_check(self, command: &str) -> Option<Command> {
+        match self {
+            HookShell::Sh | HookShell::Bash => {
+                let mut cmd = Command::new(self.program());
+                cmd.arg("-n").arg("-c").arg(command);
+                Some(cmd)
+            }
+            HookShell::Cmd => None,
+            HookShell::PowerShell => {
+                let script = format!(
+                    "$errors = $null\n\
+                     [void][System.Management.Automation.Language.Parser]::ParseInput({}, [ref]$null, [ref]$errors)\n\
+                     if ($errors) {{ $errors | ForEach-Object {{ [Console]::Error.WriteLine($_.Message) }}; exit 1 }}",
+                    powershell_string(command)
+                );
+                let mut cmd = Command::new(self.program());
+                cmd.args(["-NoProfile", "-NonInteractive"])
+                    .arg("-EncodedCommand")
+                    .arg(encode_powershell(&script));
+                Some(cmd)
+            }
+        }
+    }
+}
+
+/// Pass a script to `cmd /C` verbatim. With `/S`, cmd strips exactly the outer
+/// quotes and runs the rest as typed, so inner quotes need no escaping.
+fn push_cmd_script(cmd: &mut Command, script: &str) {
+    cmd.args(["/D", "/S", "/C"]);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.raw_arg(format!("\"{}\"", script));
+    }
+    #[cfg(not(windows))]
+    cmd.arg(script);
+}
+
+/// `-EncodedCommand` takes base64 of the UTF-16LE script, which sidesteps
+/// every layer of command line quoting
+fn encode_powershell(script: &str) -> String {
+    let utf16: Vec<u8> = script
+        .encode_utf16()
+        .flat_map(|u| u.to_le_bytes())
+        .collect();
+    base64::engine::general_purpose::STANDARD.encode(utf16)
+}
+
+/// A PowerShell single-quoted string literal, in which only quotes are special
+fn powershell_string(value: &str) -> String {
+    let mut literal = String::from("'");
+    for c in value.chars() {
+        // PowerShell also treats typographic single quotes as quote characters
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            literal.push(c);
+        }
+        literal.push(c);
+    }
+    literal.push('\'');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Quotes of both kinds and characters outside ASCII and the BMP
+    const ECHOED: &str = "it's \"quoted\" — ünïcödé 🦀";
+
+    fn run(shell: HookShell, command: &str) -> String {
+        let output = shell.command(command).output().unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .trim_end()
+            .to_string()
+    }
+
+    #[test]
+    fn test_shell_names_and_quoting() {
+        assert_eq!(
+            HookShell::parse(None).unwrap(),
+            HookShell::platform_default()
+        );
+        assert_eq!(
+            HookShell::parse(Some(" PowerShell ")).unwrap(),
+            HookShell::PowerShell
+        );
+        assert_eq!(
+            HookShell::parse(Some("pwsh")).unwrap(),
+            HookShell::PowerShell
+        );
+        assert!(HookShell::parse(Some("fish")).is_err());
+        assert!(HookShell::Cmd.syntax_check("echo hi").is_none());
+
+        assert_eq!(powershell_string("it's ‘x’"), "'it''s ‘‘x’’'");
+        let encoded = base64::engine::general_purpose::STANDARD
+            .decode(encode_powershell("é🦀"))
+            .unwrap();
+        assert_eq!(encoded, [0xE9, 0x00, 0x3E, 0xD8, 0x80, 0xDD]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_echo_hooks_round_trip_quotes_and_unicode_on_unix() {
+        let command = format!("printf '%s\\n' '{}'", ECHOED.replace('\'', "'\\''"));
+        assert_eq!(run(HookShell::Sh, &command), ECHOED);
+        assert_eq!(run(HookShell::Bash, &command), ECHOED);
+
+        let check = HookShell::Sh
+            .syntax_check("if true; then")
+            .unwrap()
+            .output()
+            .unwrap();
+        assert!(!check.status.success());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_echo_hooks_round_trip_quotes_and_unicode_on_windows() {
+        assert_eq!(run(HookShell::Cmd, &format!("echo {}", ECHOED)), ECHOED);
+        assert_eq!(
+            run(
+                HookShell::PowerShell,
+                &format!("Write-Output {}", powershell_string(ECHOED))
+            ),
+            ECHOED
+        );
+
+        let check = HookShell::PowerShell
+            .syntax_check("if ($true) {")
+            .unwrap()
+            .output()
+            .unwrap();
+        assert!(!check.status.success());
+        let check = HookShell::PowerShell
+            .syntax_check("Get-ChildItem | Where-Object { $_.Name -like \"*.rs\" }")
+            .unwrap()
+            .output()
+            .unwrap();
+        assert!(check.status.success());
+    }
+}
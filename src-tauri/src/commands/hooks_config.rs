@@ -0,0 +1,327 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use super::hook_shell::HookShell;
+
+/// What an event's `matcher` is matched against
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MatcherKind {
+    ToolName,                        // A regex over tool names; `*` or empty matches all
+    Values(&'static [&'static str]), // A regex over a fixed set of triggers
+    Ignored,                         // The event takes no matcher
+}
+
+/// Hook events Claude Code runs, with what their matcher selects
+const HOOK_EVENTS: [(&str, MatcherKind); 9] = [
+    ("PreToolUse", MatcherKind::ToolName),
+    ("PostToolUse", MatcherKind::ToolName),
+    ("Notification", MatcherKind::Ignored),
+    ("UserPromptSubmit", MatcherKind::Ignored),
+    ("Stop", MatcherKind::Ignored),
+    ("SubagentStop", MatcherKind::Ignored),
+    ("PreCompact", MatcherKind::Values(&["manual", "auto"])),
+    (
+        "SessionStart",
+        MatcherKind::Values(&["startup", "resume", "clear", "compact"]),
+    ),
+    ("SessionEnd", MatcherKind::Ignored),
+];
+
+/// Check that `event` is a hook event Claude Code runs
+pub fn validate_hook_event(event: &str) -> Result<(), String> {
+    if HOOK_EVENTS.iter().any(|(name, _)| *name == event) {
+        return Ok(());
+    }
+    let known: Vec<&str> = HOOK_EVENTS.iter().map(|(name, _)| *name).collect();
+    Err(format!(
+        "Unknown hook event '{}', expected one of {}",
+        event,
+        known.join(", ")
+    ))
+}
+
+/// A problem in a hooks configuration, located by its JSON path in settings.json
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HookConfigIssue {
+    pub path: String,
+    pub message: String,
+}
+
+fn issue(issues: &mut Vec<HookConfigIssue>, path: &str, message: impl Into<String>) {
+    issues.push(HookConfigIssue {
+        path: path.to_string(),
+        message: message.into(),
+    });
+}
+
+fn validate_matcher(
+    kind: MatcherKind,
+    matcher: &str,
+    path: &str,
+    issues: &mut Vec<HookConfigIssue>,
+) {
+    if kind == MatcherKind::Ignored || matcher.is_empty() || matcher == "*" {
+        return;
+    }
+    let regex = match Regex::new(matcher) {
+        Ok(regex) => regex,
+        Err(e) => {
+            let reason = e.to_string().lines().last().unwrap_or_default().to_string();
+            return issue(issues, path, format!("Invalid matcher regex: {}", reason));
+        }
+    };
+    if let MatcherKind::Values(values) = kind {
+        if !values.iter().any(|value| regex.is_match(value)) {
+            issue(
+                issues,
+                path,
+                format!(
+                    "Matcher '{}' matches none of {}",
+                    matcher,
+                    values.join(", ")
+                ),
+            );
+        }
+    }
+}
+
+fn validate_hook(hook: &JsonValue, path: &str, issues: &mut Vec<HookConfigIssue>) {
+    let hook = match hook.as_object() {
+        Some(hook) => hook,
+        None => return issue(issues, path, "Expected a hook object"),
+    };
+    match hook.get("type").and_then(|t| t.as_str()) {
+        Some("command") => match hook.get("command").and_then(|c| c.as_str()) {
+            Some(command) if !command.trim().is_empty() => {}
+            _ => issue(
+                issues,
+                &format!("{}.command", path),
+                "Expected a non-empty command",
+            ),
+        },
+        Some("prompt") => match hook.get("prompt").and_then(|p| p.as_str()) {
+            Some(prompt) if !prompt.trim().is_empty() => {}
+            _ => issue(
+                issues,
+                &format!("{}.prompt", path),
+                "Expected a non-empty prompt",
+            ),
+        },
+        Some(other) => issue(
+            issues,
+            &format!("{}.type", path),
+            format!("Unknown hook type '{}', expected command or prompt", other),
+        ),
+        None => issue(issues, &format!("{}.type", path), "Missing hook type"),
+    }
+    if let Some(shell) = hook.get("shell") {
+        let shell_path = format!("{}.shell", path);
+        match shell.as_str().map(|shell| HookShell::parse(Some(shell))) {
+            Some(Ok(_)) => {}
+            Some(Err(e)) => issue(issues, &shell_path, e),
+            None => issue(issues, &shell_path, "Expected the shell to be a string"),
+        }
+    }
+    if let Some(timeout) = hook.get("timeout") {
+        if !timeout.as_f64().is_some_and(|t| t > 0.0) {
+            issue(
+                issues,
+                &format!("{}.timeout", path),
+                "Expected a positive number of seconds",
+            );
+        }
+    }
+}
+
+/// Check a `hooks` settings value against the format Claude Code reads:
+/// `{ Event: [{ matcher?, hooks: [{ type, command, shell?, timeout? }] }] }`
+pub fn validate_hooks(hooks: &JsonValue) -> Vec<HookConfigIssue> {
+    let mut issues = Vec::new();
+    let events = match hooks.as_object() {
+        Some(events) => events,
+        None => {
+            issue(&mut issues, "$.hooks", "Expected an object of hook events");
+            return issues;
+        }
+    };
+
+    for (event, groups) in events {
+        let event_path = format!("$.hooks.{}", event);
+        let kind = match HOOK_EVENTS.iter().find(|(name, _)| name == event) {
+            Some((_, kind)) => *kind,
+            None => {
+                let known: Vec<&str> = HOOK_EVENTS.iter().map(|(name, _)| *name).collect();
+                issue(
+                    &mut issues,
+                    &event_path,
+                    format!("Unknown hook event, expected one of {}", known.join(", ")),
+                );
+                continue;
+            }
+        };
+        let groups = match groups.as_array() {
+            Some(groups) => groups,
+            None => {
+                issue(&mut issues, &event_path, "Expected an array of matchers");
+                continue;
+            }
+        };
+
+        for (i, group) in groups.iter().enumerate() {
+            let group_path = format!("{}[{}]", event_path, i);
+            let group = match group.as_object() {
+                Some(group) => group,
+                None => {
+                    issue(&mut issues, &group_path, "Expected a matcher object");
+                    continue;
+                }
+            };
+            let matcher_path = format!("{}.matcher", group_path);
+            match group.get("matcher") {
+                None | Some(JsonValue::Null) => {}
+                Some(JsonValue::String(matcher)) => {
+                    validate_matcher(kind, matcher, &matcher_path, &mut issues)
+                }
+                Some(_) => issue(
+                    &mut issues,
+                    &matcher_path,
+                    "Expected the matcher to be a string",
+                ),
+            }
+
+            let hooks_path = format!("{}.hooks", group_path);
+            match group.get("hooks") {
+                Some(JsonValue::Array(list)) => {
+                    for (j, hook) in list.iter().enumerate() {
+                        validate_hook(hook, &format!("{}[{}]", hooks_path, j), &mut issues);
+                    }
+                }
+                Some(_) => issue(&mut issues, &hooks_path, "Expected an array of hooks"),
+                None => issue(&mut issues, &hooks_path, "Missing hooks array"),
+            }
+        }
+    }
+    issues
+}
+
+/// Replace the `hooks` section of a settings file's content. Returns `None`
+/// when the hooks are unchanged, so an unchanged file is never rewritten.
+pub fn apply_hooks(existing: Option<&str>, hooks: JsonValue) -> Result<Option<String>, String> {
+    let issues = validate_hooks(&hooks);
+    if !issues.is_empty() {
+        let details: Vec<String> = issues
+            .iter()
+            .map(|issue| format!("{}: {}", issue.path, issue.message))
+            .collect();
+        return Err(format!(
+            "Invalid hooks configuration:\n{}",
+            details.join("\n")
+        ));
+    }
+
+    let mut settings = match existing {
+        Some(content) => {
+            serde_json::from_str(content).map_err(|e| format!("Failed to parse settings: {}", e))?
+        }
+        None => serde_json::json!({}),
+    };
+    if !settings.is_object() {
+        return Err("Failed to parse settings: expected a JSON object".to_string());
+    }
+    if settings.get("hooks") == Some(&hooks) {
+        return Ok(None);
+    }
+    settings["hooks"] = hooks;
+
+    serde_json::to_string_pretty(&settings)
+        .map(Some)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Hooks as Claude Code's `/hooks` command writes them
+    const CLAUDE_WRITTEN: &str = r#"{
+  "model": "sonnet",
+  "hooks": {
+    "PreToolUse": [
+      {
+        "matcher": "Bash",
+        "hooks": [
+          {
+            "type": "command",
+            "command": "jq -r '.tool_input.command' >> ~/.claude/bash-command-log.txt"
+          }
+        ]
+      },
+      {
+        "matcher": "Edit|MultiEdit|Write",
+        "hooks": [{ "type": "command", "command": "./check.ps1", "shell": "powershell", "timeout": 30 }]
+      }
+    ],
+    "Stop": [{ "matcher": "", "hooks": [{ "type": "command", "command": "say done" }] }],
+    "Notification": [{ "hooks": [{ "type": "command", "command": "notify-send Claude" }] }],
+    "SessionStart": [{ "matcher": "startup|resume", "hooks": [{ "type": "command", "command": "git status" }] }],
+    "PreCompact": [{ "matcher": "auto", "hooks": [{ "type": "command", "command": "backup.sh" }] }],
+    "UserPromptSubmit": [{ "hooks": [{ "type": "command", "command": "log-prompt.sh" }] }],
+    "SessionEnd": [{ "hooks": [{ "type": "command", "command": "cleanup.sh" }] }]
+  }
+}"#;
+
+    fn paths(hooks: JsonValue) -> Vec<String> {
+        validate_hooks(&hooks).into_iter().map(|i| i.path).collect()
+    }
+
+    #[test]
+    fn test_configs_written_by_claude_round_trip_untouched() {
+        let settings: JsonValue = serde_json::from_str(CLAUDE_WRITTEN).unwrap();
+        let hooks = settings["hooks"].clone();
+        assert_eq!(validate_hooks(&hooks), []);
+        assert_eq!(apply_hooks(Some(CLAUDE_WRITTEN), hooks).unwrap(), None);
+
+        let written = apply_hooks(Some(CLAUDE_WRITTEN), json!({}))
+            .unwrap()
+            .unwrap();
+        let written: JsonValue = serde_json::from_str(&written).unwrap();
+        assert_eq!(written["model"], "sonnet");
+        assert_eq!(written["hooks"], json!({}));
+    }
+
+    #[test]
+    fn test_invalid_configs_are_reported_by_json_path() {
+        assert_eq!(
+            paths(json!({
+                "PreToolUse": [
+                    { "matcher": "Bash(", "hooks": [] },
+                    { "matcher": "*", "hooks": { "type": "command" } },
+                    { "hooks": [{ "type": "command", "command": " " }, { "type": "shell" }] },
+                    { "hooks": [{ "type": "command", "command": "x", "shell": "fish" }] }
+                ],
+                "SessionStart": [{ "matcher": "boot", "hooks": [] }],
+                "Stop": { "hooks": [] },
+                "PostToolUse": [{ "matcher": 3 }],
+                "OnError": []
+            })),
+            [
+                "$.hooks.OnError",
+                "$.hooks.PostToolUse[0].matcher",
+                "$.hooks.PostToolUse[0].hooks",
+                "$.hooks.PreToolUse[0].matcher",
+                "$.hooks.PreToolUse[1].hooks",
+                "$.hooks.PreToolUse[2].hooks[0].command",
+                "$.hooks.PreToolUse[2].hooks[1].type",
+                "$.hooks.PreToolUse[3].hooks[0].shell",
+                "$.hooks.SessionStart[0].matcher",
+                "$.hooks.Stop",
+            ]
+        );
+        assert_eq!(paths(json!([])), ["$.hooks"]);
+
+        let err = apply_hooks(None, json!({ "Stop": [{ "command": "x" }] })).unwrap_err();
+        assert!(err.contains("$.hooks.Stop[0].hooks: Missing hooks array"));
+    }
+}
@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use tauri::State;
+
+use super::usage::{calculate_cost, UsageData};
+
+/// Emitted with a `SessionUsageUpdate` whenever a streaming session reports usage
+pub const SESSION_USAGE_EVENT: &str = "session-usage-updated";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct TokenCounts {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+}
+
+impl TokenCounts {
+    fn from_usage(usage: &UsageData) -> Self {
+        Self {
+            input_tokens: usage.input_tokens.unwrap_or(0),
+            output_tokens: usage.output_tokens.unwrap_or(0),
+            cache_creation_tokens: usage.cache_creation_input_tokens.unwrap_or(0),
+            cache_read_tokens: usage.cache_read_input_tokens.unwrap_or(0),
+        }
+    }
+
+    fn add(&mut self, other: &TokenCounts) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+    }
+}
+
+/// Usage of a streaming session so far
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionUsageUpdate {
+    pub session_id: String,
+    /// Tokens of the message that triggered the update; zero for a result
+    pub turn_tokens: TokenCounts,
+    pub cumulative_tokens: TokenCounts,
+    pub estimated_cost_usd: f64,
+}
+
+/// Tallies a run's stream-json output the way the usage page tallies the
+/// session's JSONL, so both arrive at the same numbers
+pub struct LiveSessionUsage {
+    seen_messages: HashSet<String>,
+    latest: SessionUsageUpdate,
+}
+
+impl LiveSessionUsage {
+    pub fn new(session_id: String) -> Self {
+        Self {
+            seen_messages: HashSet::new(),
+            latest: SessionUsageUpdate {
+                session_id,
+                turn_tokens: TokenCounts::default(),
+                cumulative_tokens: TokenCounts::default(),
+                estimated_cost_usd: 0.0,
+            },
+        }
+    }
+
+    /// Record one stream-json line, returning an update when it carried new
+    /// usage or finished the run
+    pub fn record(&mut self, json: &JsonValue) -> Option<SessionUsageUpdate> {
+        match json.get("type").and_then(|t| t.as_str()) {
+            Some("result") => {
+                self.latest.turn_tokens = TokenCounts::default();
+                return Some(self.latest.clone());
+            }
+            Some("assistant") => {}
+            _ => return None,
+        }
+
+        let message = json.get("message")?;
+        let usage: UsageData = serde_json::from_value(message.get("usage")?.clone()).ok()?;
+        // Every content block of a message repeats its usage; like the usage
+        // page, only the first is counted
+        if let Some(id) = message.get("id").and_then(|id| id.as_str()) {
+            if !self.seen_messages.insert(id.to_string()) {
+                return None;
+            }
+        }
+
+        let turn = TokenCounts::from_usage(&usage);
+        self.latest.turn_tokens = turn;
+        self.latest.cumulative_tokens.add(&turn);
+        self.latest.estimated_cost_usd += message
+            .get("model")
+            .and_then(|m| m.as_str())
+            .map(|model| calculate_cost(model, &usage))
+            .unwrap_or(0.0);
+        Some(self.latest.clone())
+    }
+}
+
+/// Latest usage of the sessions streamed by opcode, by session ID
+#[derive(Default)]
+pub struct LiveUsageState(pub Mutex<HashMap<String, SessionUsageUpdate>>);
+
+impl LiveUsageState {
+    pub fn store(&self, update: &SessionUsageUpdate) {
+        if let Ok(mut sessions) = self.0.lock() {
+            sessions.insert(update.session_id.clone(), update.clone());
+        }
+    }
+
+    /// Forget a session's usage when a new run of it starts
+    pub fn reset(&self, session_id: &str) {
+        if let Ok(mut sessions) = self.0.lock() {
+            sessions.remove(session_id);
+        }
+    }
+}
+
+/// Get the usage of a session's current or last run in opcode, or None when
+/// it hasn't reported any
+#[tauri::command]
+pub async fn get_live_session_usage(
+    state: State<'_, LiveUsageState>,
+    session_id: String,
+) -> Result<Option<SessionUsageUpdate>, String> {
+    let sessions = state.0.lock().map_err(|e| e.to_string())?;
+    Ok(sessions.get(&session_id).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn assistant(id: &str, input: u64, output: u64, cache_read: u64) -> JsonValue {
+        json!({
+            "type": "assistant",
+            "message": {
+                "id": id,
+                "model": "claude-sonnet-4-20250514",
+                "usage": {
+                    "input_tokens": input,
+                    "output_tokens": output,
+                    "cache_creation_input_tokens": 0,
+                    "cache_read_input_tokens": cache_read
+                }
+            },
+            "session_id": "s1"
+        })
+    }
+
+    #[test]
+    fn test_usage_is_counted_once_per_message_and_priced() {
+        let mut usage = LiveSessionUsage::new("s1".to_string());
+        let init = json!({ "type": "system", "subtype": "init" });
+        assert!(usage.record(&init).is_none());
+
+        let first = usage.record(&assistant("msg_1", 1000, 200, 0)).unwrap();
+        assert_eq!(first.turn_tokens.input_tokens, 1000);
+        // A second content block of the same message
+        assert!(usage.record(&assistant("msg_1", 1000, 200, 0)).is_none());
+
+        let second = usage.record(&assistant("msg_2", 10, 50, 1000)).unwrap();
+        assert_eq!(second.turn_tokens.output_tokens, 50);
+        assert_eq!(
+            second.cumulative_tokens,
+            TokenCounts {
+                input_tokens: 1010,
+                output_tokens: 250,
+                cache_creation_tokens: 0,
+                cache_read_tokens: 1000,
+            }
+        );
+        // Sonnet 4 rates: $3 input, $15 output, $0.30 cache reads per million tokens
+        assert!((second.estimated_cost_usd - 0.00708).abs() < 1e-9);
+
+        let result = json!({ "type": "result", "total_cost_usd": 1.0 });
+        let result = usage.record(&result).unwrap();
+        assert_eq!(result.turn_tokens, TokenCounts::default());
+        assert_eq!(result.cumulative_tokens, second.cumulative_tokens);
+        assert_eq!(result.estimated_cost_usd, second.estimated_cost_usd);
+    }
+}
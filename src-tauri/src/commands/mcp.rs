@@ -6,7 +6,10 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::process::Command;
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager};
+
+use super::agents::AgentDb;
+use super::claude_env::{apply_claude_env, claude_env_for};
 
 /// Helper function to create a std::process::Command with proper environment variables
 /// This ensures commands like Claude can find Node.js and other dependencies
@@ -14,6 +17,14 @@ fn create_command_with_env(program: &str) -> Command {
     crate::claude_binary::create_command_with_env(program)
 }
 
+/// Set the Claude environment settings on a command, so `claude mcp` reaches
+/// servers through the same proxy as sessions
+fn apply_saved_claude_env(app_handle: &AppHandle, cmd: &mut Command) {
+    if let Some(db) = app_handle.try_state::<AgentDb>() {
+        apply_claude_env(cmd, &claude_env_for(&db, None));
+    }
+}
+
 /// Finds the full path to the claude binary
 /// This is necessary because macOS apps have a limited PATH environment
 fn find_claude_binary(app_handle: &AppHandle) -> Result<String> {
@@ -41,6 +52,13 @@ pub struct MCPServer {
     pub is_active: bool,
     /// Server status
     pub status: ServerStatus,
+    /// Disabled servers are kept by opcode but removed from Claude's config
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
 }
 
 /// Server status information
@@ -52,6 +70,16 @@ pub struct ServerStatus {
     pub error: Option<String>,
     /// Last checked timestamp
     pub last_checked: Option<u64>,
+    /// The server is disabled, so it isn't connected to
+    #[serde(default)]
+    pub disabled: bool,
+    /// How long the last successful handshake took
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// When the last failed check's output starts in the server's log, to
+    /// pass as `since` to `mcp_get_server_logs`
+    #[serde(default)]
+    pub error_log_since: Option<String>,
 }
 
 /// MCP configuration for project scope (.mcp.json)
@@ -101,6 +129,7 @@ fn execute_claude_mcp_command(app_handle: &AppHandle, args: Vec<&str>) -> Result
 
     let claude_path = find_claude_binary(app_handle)?;
     let mut cmd = create_command_with_env(&claude_path);
+    apply_saved_claude_env(app_handle, &mut cmd);
     cmd.arg("mcp");
     for arg in args {
         cmd.arg(arg);
@@ -208,12 +237,19 @@ pub async fn mcp_add(
     }
 }
 
-/// Lists all configured MCP servers
+/// Lists all configured MCP servers, followed by disabled ones
 #[tauri::command]
 pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
+    let mut servers = list_enabled_servers(&app)?;
+    servers.extend(super::mcp_toggle::disabled_servers(&app)?);
+    Ok(servers)
+}
+
+/// Lists the MCP servers Claude has configured
+fn list_enabled_servers(app: &AppHandle) -> Result<Vec<MCPServer>, String> {
     info!("Listing MCP servers");
 
-    match execute_claude_mcp_command(&app, vec!["list"]) {
+    match execute_claude_mcp_command(app, vec!["list"]) {
         Ok(output) => {
             info!("Raw output from 'claude mcp list': {:?}", output);
             let trimmed = output.trim();
@@ -299,7 +335,11 @@ pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
                                 running: false,
                                 error: None,
                                 last_checked: None,
+                                disabled: false,
+                                latency_ms: None,
+                                error_log_since: None,
                             },
+                            enabled: true,
                         });
                         info!("Added server: {:?}", name);
 
@@ -333,21 +373,38 @@ pub async fn mcp_list(app: AppHandle) -> Result<Vec<MCPServer>, String> {
 /// Gets details for a specific MCP server
 #[tauri::command]
 pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String> {
+    get_server(&app, name)
+}
+
+/// Details of the MCP server `name`, from `claude mcp get`
+pub fn get_server(app: &AppHandle, name: String) -> Result<MCPServer, String> {
     info!("Getting MCP server details for: {}", name);
 
-    match execute_claude_mcp_command(&app, vec!["get", &name]) {
+    match execute_claude_mcp_command(app, vec!["get", &name]) {
         Ok(output) => {
             // Parse the structured text output
             let mut scope = "local".to_string();
             let mut transport = "stdio".to_string();
             let mut command = None;
             let mut args = vec![];
-            let env = HashMap::new();
+            let mut env = HashMap::new();
             let mut url = None;
+            let mut in_env = false;
 
             for line in output.lines() {
                 let line = line.trim();
 
+                // Variables are listed as KEY=value lines under "Environment:"
+                if in_env {
+                    if let Some((key, value)) = line.split_once('=') {
+                        if !key.is_empty() && !key.contains(char::is_whitespace) {
+                            env.insert(key.to_string(), value.to_string());
+                            continue;
+                        }
+                    }
+                    in_env = false;
+                }
+
                 if line.starts_with("Scope:") {
                     let scope_part = line.replace("Scope:", "").trim().to_string();
                     if scope_part.to_lowercase().contains("local") {
@@ -371,8 +428,7 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
                 } else if line.starts_with("URL:") {
                     url = Some(line.replace("URL:", "").trim().to_string());
                 } else if line.starts_with("Environment:") {
-                    // TODO: Parse environment variables if they're listed
-                    // For now, we'll leave it empty
+                    in_env = true;
                 }
             }
 
@@ -389,7 +445,11 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
                     running: false,
                     error: None,
                     last_checked: None,
+                    disabled: false,
+                    latency_ms: None,
+                    error_log_since: None,
                 },
+                enabled: true,
             })
         }
         Err(e) => {
@@ -399,14 +459,25 @@ pub async fn mcp_get(app: AppHandle, name: String) -> Result<MCPServer, String>
     }
 }
 
-/// Removes an MCP server
+/// Removes an MCP server, or forgets it if it's disabled
 #[tauri::command]
 pub async fn mcp_remove(app: AppHandle, name: String) -> Result<String, String> {
     info!("Removing MCP server: {}", name);
 
+    if let Some(db) = app.try_state::<AgentDb>() {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if super::mcp_toggle::forget_disabled_server(&conn, &name, None)? > 0 {
+            return Ok(format!("Removed disabled MCP server {}", name));
+        }
+    }
+
     match execute_claude_mcp_command(&app, vec!["remove", &name]) {
         Ok(output) => {
             info!("Successfully removed MCP server: {}", name);
+            if let Some(db) = app.try_state::<AgentDb>() {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                super::mcp_monitor::forget_status(&conn, &name)?;
+            }
             Ok(output.trim().to_string())
         }
         Err(e) => {
@@ -416,6 +487,13 @@ pub async fn mcp_remove(app: AppHandle, name: String) -> Result<String, String>
     }
 }
 
+/// Removes an MCP server from one scope
+pub fn mcp_remove_from_scope(app: &AppHandle, name: &str, scope: &str) -> Result<String, String> {
+    execute_claude_mcp_command(app, vec!["remove", "-s", scope, name])
+        .map(|output| output.trim().to_string())
+        .map_err(|e| e.to_string())
+}
+
 /// Adds an MCP server from JSON configuration
 #[tauri::command]
 pub async fn mcp_add_json(
@@ -457,6 +535,45 @@ pub async fn mcp_add_json(
     }
 }
 
+/// The add-json configuration of a stdio server entry as Claude Desktop and
+/// editors write it, with `command` and optional `args` and `env`
+pub fn stdio_server_config(server_config: &serde_json::Value) -> Result<serde_json::Value, String> {
+    let mut json_config = serde_json::Map::new();
+    json_config.insert(
+        "type".to_string(),
+        serde_json::Value::String("stdio".to_string()),
+    );
+
+    // Add command
+    if let Some(command) = server_config.get("command").and_then(|v| v.as_str()) {
+        json_config.insert(
+            "command".to_string(),
+            serde_json::Value::String(command.to_string()),
+        );
+    } else {
+        return Err("Missing command field".to_string());
+    }
+
+    // Add args if present
+    if let Some(args) = server_config.get("args").and_then(|v| v.as_array()) {
+        json_config.insert("args".to_string(), args.clone().into());
+    } else {
+        json_config.insert("args".to_string(), serde_json::Value::Array(vec![]));
+    }
+
+    // Add env if present
+    if let Some(env) = server_config.get("env").and_then(|v| v.as_object()) {
+        json_config.insert("env".to_string(), env.clone().into());
+    } else {
+        json_config.insert(
+            "env".to_string(),
+            serde_json::Value::Object(serde_json::Map::new()),
+        );
+    }
+
+    Ok(serde_json::Value::Object(json_config))
+}
+
 /// Imports MCP servers from Claude Desktop
 #[tauri::command]
 pub async fn mcp_add_from_claude_desktop(
@@ -517,51 +634,18 @@ pub async fn mcp_add_from_claude_desktop(
     for (name, server_config) in mcp_servers {
         info!("Importing server: {}", name);
 
-        // Convert Claude Desktop format to add-json format
-        let mut json_config = serde_json::Map::new();
-
-        // All Claude Desktop servers are stdio type
-        json_config.insert(
-            "type".to_string(),
-            serde_json::Value::String("stdio".to_string()),
-        );
-
-        // Add command
-        if let Some(command) = server_config.get("command").and_then(|v| v.as_str()) {
-            json_config.insert(
-                "command".to_string(),
-                serde_json::Value::String(command.to_string()),
-            );
-        } else {
-            failed_count += 1;
-            server_results.push(ImportServerResult {
-                name: name.clone(),
-                success: false,
-                error: Some("Missing command field".to_string()),
-            });
-            continue;
-        }
-
-        // Add args if present
-        if let Some(args) = server_config.get("args").and_then(|v| v.as_array()) {
-            json_config.insert("args".to_string(), args.clone().into());
-        } else {
-            json_config.insert("args".to_string(), serde_json::Value::Array(vec![]));
-        }
-
-        // Add env if present
-        if let Some(env) = server_config.get("env").and_then(|v| v.as_object()) {
-            json_config.insert("env".to_string(), env.clone().into());
-        } else {
-            json_config.insert(
-                "env".to_string(),
-                serde_json::Value::Object(serde_json::Map::new()),
-            );
-        }
-
-        // Convert to JSON string
-        let json_str = serde_json::to_string(&json_config)
-            .map_err(|e| format!("Failed to serialize config for {}: {}", name, e))?;
+        let json_str = match stdio_server_config(server_config) {
+            Ok(json_config) => json_config.to_string(),
+            Err(e) => {
+                failed_count += 1;
+                server_results.push(ImportServerResult {
+                    name: name.clone(),
+                    success: false,
+                    error: Some(e),
+                });
+                continue;
+            }
+        };
 
         // Call add-json command
         match mcp_add_json(app.clone(), name.clone(), json_str, scope.clone()).await {
@@ -625,11 +709,27 @@ pub async fn mcp_serve(app: AppHandle) -> Result<String, String> {
     };
 
     let mut cmd = create_command_with_env(&claude_path);
-    cmd.arg("mcp").arg("serve");
+    apply_saved_claude_env(&app, &mut cmd);
+    cmd.arg("mcp")
+        .arg("serve")
+        .stderr(std::process::Stdio::piped());
 
     match cmd.spawn() {
-        Ok(_) => {
+        Ok(mut child) => {
             info!("Successfully started Claude Code MCP server");
+            let log_name = super::mcp_logs::SERVE_LOG_NAME;
+            super::mcp_logs::append_log(log_name, "[opcode] Started claude mcp serve");
+            if let Some(stderr) = child.stderr.take() {
+                std::thread::spawn(move || {
+                    use std::io::BufRead;
+                    for line in std::io::BufReader::new(stderr)
+                        .lines()
+                        .map_while(|line| line.ok())
+                    {
+                        super::mcp_logs::append_log(log_name, &line);
+                    }
+                });
+            }
             Ok("Claude Code MCP server started".to_string())
         }
         Err(e) => {
@@ -639,15 +739,21 @@ pub async fn mcp_serve(app: AppHandle) -> Result<String, String> {
     }
 }
 
-/// Tests connection to an MCP server
+/// Tests connection to an MCP server with a handshake, logging a stdio
+/// server's stderr
 #[tauri::command]
 pub async fn mcp_test_connection(app: AppHandle, name: String) -> Result<String, String> {
     info!("Testing connection to MCP server: {}", name);
 
-    // For now, we'll use the get command to test if the server exists
-    match execute_claude_mcp_command(&app, vec!["get", &name]) {
-        Ok(_) => Ok(format!("Connection to {} successful", name)),
-        Err(e) => Err(e.to_string()),
+    let server = mcp_get(app, name.clone()).await?;
+    let status = super::mcp_monitor::probe_server(&server).await;
+    match status.error {
+        Some(e) => Err(e),
+        None => Ok(format!(
+            "Connection to {} successful ({} ms)",
+            name,
+            status.latency_ms.unwrap_or_default()
+        )),
     }
 }
 
@@ -668,14 +774,13 @@ pub async fn mcp_reset_project_choices(app: AppHandle) -> Result<String, String>
     }
 }
 
-/// Gets the status of MCP servers
+/// Gets the status of MCP servers as of their last check, without
+/// connecting to them. Disabled servers are reported as disabled.
 #[tauri::command]
-pub async fn mcp_get_server_status() -> Result<HashMap<String, ServerStatus>, String> {
-    info!("Getting MCP server status");
-
-    // TODO: Implement actual status checking
-    // For now, return empty status
-    Ok(HashMap::new())
+pub async fn mcp_get_server_status(
+    app: AppHandle,
+) -> Result<HashMap<String, ServerStatus>, String> {
+    super::mcp_monitor::cached_statuses(&app)
 }
 
 /// Reads .mcp.json from the current project
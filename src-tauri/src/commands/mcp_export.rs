@@ -0,0 +1,205 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+use std::path::Path;
+use tauri::AppHandle;
+
+use super::mcp::{mcp_get, mcp_list, MCPServer};
+
+/// File shape MCP servers are exported in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum McpExportFormat {
+    /// `mcpServers` of `claude_desktop_config.json`, which only runs stdio servers
+    ClaudeDesktop,
+    /// A project's `.mcp.json`, where each server names its transport
+    McpJson,
+}
+
+/// Result of exporting MCP servers
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct McpExportResult {
+    pub written_count: u32,
+    /// Servers left out because the format can't represent them
+    pub unsupported: Vec<String>,
+}
+
+/// `server` as an entry of `format`'s `mcpServers`, or None when the format
+/// can't represent it. Environment values are replaced by "" with
+/// `strip_env`, so the variables to set are still listed.
+pub fn server_entry(
+    server: &MCPServer,
+    format: McpExportFormat,
+    strip_env: bool,
+) -> Option<JsonValue> {
+    let env: Map<String, JsonValue> = server
+        .env
+        .iter()
+        .map(|(key, value)| {
+            let value = if strip_env { "" } else { value.as_str() };
+            (key.clone(), JsonValue::from(value))
+        })
+        .collect();
+    match (server.transport.as_str(), format) {
+        ("stdio", McpExportFormat::ClaudeDesktop) => Some(json!({
+            "command": server.command.as_ref()?,
+            "args": server.args,
+            "env": env,
+        })),
+        ("stdio", McpExportFormat::McpJson) => Some(json!({
+            "type": "stdio",
+            "command": server.command.as_ref()?,
+            "args": server.args,
+            "env": env,
+        })),
+        ("sse" | "http", McpExportFormat::McpJson) => Some(json!({
+            "type": server.transport,
+            "url": server.url.as_ref()?,
+        })),
+        _ => None,
+    }
+}
+
+/// The `mcpServers` object for `servers`, with the names of servers that were
+/// left out
+pub fn export_servers(
+    servers: &[MCPServer],
+    format: McpExportFormat,
+    strip_env: bool,
+) -> (Map<String, JsonValue>, Vec<String>) {
+    let mut entries = Map::new();
+    let mut unsupported = Vec::new();
+    for server in servers {
+        match server_entry(server, format, strip_env) {
+            Some(entry) => {
+                entries.insert(server.name.clone(), entry);
+            }
+            None => unsupported.push(server.name.clone()),
+        }
+    }
+    (entries, unsupported)
+}
+
+/// `existing` with its `mcpServers` replaced, keeping the file's other
+/// settings such as Claude Desktop's preferences
+fn merge_into(existing: Option<&str>, entries: Map<String, JsonValue>) -> JsonValue {
+    let mut config = existing
+        .and_then(|content| serde_json::from_str::<JsonValue>(content).ok())
+        .filter(JsonValue::is_object)
+        .unwrap_or_else(|| json!({}));
+    config["mcpServers"] = JsonValue::Object(entries);
+    config
+}
+
+/// Export the configured MCP servers, all of them or those of `scope`, to
+/// `output_path` in `format`. An existing file keeps its other settings.
+#[tauri::command]
+pub async fn mcp_export(
+    app: AppHandle,
+    scope: Option<String>,
+    format: McpExportFormat,
+    output_path: String,
+    strip_env: bool,
+) -> Result<McpExportResult, String> {
+    info!("Exporting MCP servers to {} as {:?}", output_path, format);
+
+    // The listing only names enabled servers, whose details come from
+    // `mcp get`; disabled ones are stored in full
+    let mut servers = Vec::new();
+    for listed in mcp_list(app.clone()).await? {
+        let server = if listed.enabled {
+            match mcp_get(app.clone(), listed.name.clone()).await {
+                Ok(server) => server,
+                Err(e) => {
+                    warn!("Failed to get MCP server {}: {}", listed.name, e);
+                    continue;
+                }
+            }
+        } else {
+            listed
+        };
+        if scope.is_none() || scope.as_deref() == Some(server.scope.as_str()) {
+            servers.push(server);
+        }
+    }
+
+    let (entries, unsupported) = export_servers(&servers, format, strip_env);
+    let written_count = entries.len() as u32;
+    let path = Path::new(&output_path);
+    let existing = std::fs::read_to_string(path).ok();
+    let config = merge_into(existing.as_deref(), entries);
+    let content = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("Failed to serialize MCP servers: {}", e))?;
+    if let Some(parent) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", output_path, e))?;
+
+    Ok(McpExportResult {
+        written_count,
+        unsupported,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mcp::ServerStatus;
+    use super::*;
+    use std::collections::HashMap;
+
+    fn server(name: &str, transport: &str, command: Option<&str>, url: Option<&str>) -> MCPServer {
+        MCPServer {
+            name: name.to_string(),
+            transport: transport.to_string(),
+            command: command.map(str::to_string),
+            args: vec!["-y".to_string(), "@scope/server".to_string()],
+            env: HashMap::from([("API_KEY".to_string(), "secret".to_string())]),
+            url: url.map(str::to_string),
+            scope: "user".to_string(),
+            is_active: false,
+            status: ServerStatus {
+                running: false,
+                error: None,
+                last_checked: None,
+                disabled: false,
+                latency_ms: None,
+                error_log_since: None,
+            },
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_servers_export_in_each_format() {
+        let servers = [
+            server("files", "stdio", Some("npx"), None),
+            server("remote", "sse", None, Some("https://example.com/sse")),
+        ];
+
+        let (desktop, unsupported) =
+            export_servers(&servers, McpExportFormat::ClaudeDesktop, false);
+        assert_eq!(
+            JsonValue::Object(desktop),
+            json!({"files": {
+                "command": "npx",
+                "args": ["-y", "@scope/server"],
+                "env": {"API_KEY": "secret"},
+            }})
+        );
+        assert_eq!(unsupported, ["remote"]);
+
+        let (project, unsupported) = export_servers(&servers, McpExportFormat::McpJson, true);
+        assert!(unsupported.is_empty());
+        assert_eq!(project["files"]["type"], "stdio");
+        assert_eq!(project["files"]["env"]["API_KEY"], "");
+        assert_eq!(
+            project["remote"],
+            json!({"type": "sse", "url": "https://example.com/sse"})
+        );
+
+        let merged = merge_into(Some(r#"{"globalShortcut": "Cmd+Space"}"#), project);
+        assert_eq!(merged["globalShortcut"], "Cmd+Space");
+        assert_eq!(merged["mcpServers"]["files"]["command"], "npx");
+    }
+}
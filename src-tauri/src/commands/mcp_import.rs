@@ -0,0 +1,488 @@
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use super::agents::AgentDb;
+use super::mcp::{mcp_add_json, mcp_list, mcp_remove_from_scope, stdio_server_config, MCPServer};
+
+/// An editor whose MCP servers can be imported
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum McpEditor {
+    Cursor,
+    Vscode,
+    Windsurf,
+}
+
+impl McpEditor {
+    fn name(self) -> &'static str {
+        match self {
+            McpEditor::Cursor => "Cursor",
+            McpEditor::Vscode => "VS Code",
+            McpEditor::Windsurf => "Windsurf",
+        }
+    }
+
+    fn slug(self) -> &'static str {
+        match self {
+            McpEditor::Cursor => "cursor",
+            McpEditor::Vscode => "vscode",
+            McpEditor::Windsurf => "windsurf",
+        }
+    }
+
+    /// Files the editor keeps MCP servers in, the one to prefer first
+    fn config_paths(self) -> Vec<PathBuf> {
+        let home = dirs::home_dir();
+        match self {
+            McpEditor::Cursor => home
+                .map(|home| home.join(".cursor").join("mcp.json"))
+                .into_iter()
+                .collect(),
+            // Servers moved from settings.json to their own mcp.json
+            McpEditor::Vscode => dirs::config_dir()
+                .map(|config| {
+                    let user = config.join("Code").join("User");
+                    vec![user.join("mcp.json"), user.join("settings.json")]
+                })
+                .unwrap_or_default(),
+            McpEditor::Windsurf => home
+                .map(|home| {
+                    home.join(".codeium")
+                        .join("windsurf")
+                        .join("mcp_config.json")
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// The server entries of the editor's configuration
+    fn servers_in(self, config: &JsonValue) -> Option<&Map<String, JsonValue>> {
+        match self {
+            McpEditor::Cursor | McpEditor::Windsurf => config.get("mcpServers"),
+            McpEditor::Vscode => config
+                .get("servers")
+                .or_else(|| config.get("mcp").and_then(|mcp| mcp.get("servers"))),
+        }
+        .and_then(JsonValue::as_object)
+    }
+}
+
+/// What to do when an imported server has the name of an existing one
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum McpConflictPolicy {
+    /// Keep the existing server and leave the editor's out
+    Skip,
+    /// Replace the existing server with the editor's
+    Overwrite,
+    /// Import the editor's server under a name suffixed with the editor
+    Rename,
+}
+
+/// How importing one server went
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum McpImportStatus {
+    Imported,
+    SkippedDuplicate,
+    UnsupportedTransport,
+    ParseError,
+    /// Claude refused to add the server
+    Failed,
+}
+
+/// Result for one server imported from an editor
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EditorImportServerResult {
+    /// The server's name in the editor
+    pub name: String,
+    /// The name it was added under, which differs when renamed
+    pub imported_as: Option<String>,
+    pub status: McpImportStatus,
+    pub message: Option<String>,
+}
+
+/// Result of importing an editor's MCP servers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EditorImportResult {
+    pub config_path: String,
+    pub servers: Vec<EditorImportServerResult>,
+}
+
+/// An entry's add-json configuration, or why it can't be imported
+pub type EntryConfig = Result<JsonValue, (McpImportStatus, String)>;
+
+/// `content` without the comments and trailing commas editors allow in
+/// their JSON files
+fn strip_jsonc(content: &str) -> String {
+    let mut stripped = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            stripped.push(c);
+            match c {
+                '\\' => stripped.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                stripped.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push(c);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+
+    // Drop commas that only whitespace separates from a closing bracket
+    let mut result = String::with_capacity(stripped.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in stripped.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' {
+            let next = stripped[i + 1..].trim_start().chars().next();
+            if matches!(next, Some('}' | ']')) {
+                continue;
+            }
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// The add-json configuration of an editor's server entry, or why it can't
+/// be imported
+pub fn server_config(entry: &JsonValue) -> EntryConfig {
+    let Some(object) = entry.as_object() else {
+        return Err((
+            McpImportStatus::ParseError,
+            "Server entry isn't an object".to_string(),
+        ));
+    };
+    // VS Code prompts for these when it starts the server
+    if entry.to_string().contains("${input:") {
+        return Err((
+            McpImportStatus::ParseError,
+            "Uses VS Code input variables, which only VS Code can fill in".to_string(),
+        ));
+    }
+
+    let transport = match object.get("type").and_then(JsonValue::as_str) {
+        None => None,
+        Some("stdio") => Some("stdio"),
+        Some("sse") => Some("sse"),
+        Some("http" | "streamableHttp" | "streamable-http") => Some("http"),
+        Some(other) => {
+            return Err((
+                McpImportStatus::UnsupportedTransport,
+                format!("Unsupported transport: {}", other),
+            ))
+        }
+    };
+    // Windsurf names the URL of remote servers serverUrl
+    let url = object
+        .get("url")
+        .or_else(|| object.get("serverUrl"))
+        .and_then(JsonValue::as_str);
+
+    match (transport, url) {
+        (Some("stdio"), _) | (None, None) => {
+            stdio_server_config(entry).map_err(|e| (McpImportStatus::ParseError, e))
+        }
+        (transport, Some(url)) => {
+            // Without a type, an endpoint ending in /sse is taken for SSE
+            let transport = transport.unwrap_or(if url.trim_end_matches('/').ends_with("/sse") {
+                "sse"
+            } else {
+                "http"
+            });
+            let mut config = json!({"type": transport, "url": url});
+            if let Some(headers) = object.get("headers").filter(|h| h.is_object()) {
+                config["headers"] = headers.clone();
+            }
+            Ok(config)
+        }
+        (Some(transport), None) => Err((
+            McpImportStatus::ParseError,
+            format!("Missing url field for {} server", transport),
+        )),
+    }
+}
+
+/// Each server of an editor's configuration file, with its add-json
+/// configuration or why it can't be imported
+pub fn parse_editor_config(
+    editor: McpEditor,
+    content: &str,
+) -> Result<Vec<(String, EntryConfig)>, String> {
+    let config: JsonValue = serde_json::from_str(&strip_jsonc(content))
+        .map_err(|e| format!("Failed to parse {} MCP config: {}", editor.name(), e))?;
+    let servers = editor
+        .servers_in(&config)
+        .ok_or_else(|| format!("No MCP servers found in {} config", editor.name()))?;
+    Ok(servers
+        .iter()
+        .map(|(name, entry)| (name.clone(), server_config(entry)))
+        .collect())
+}
+
+/// The name to import `name` under given the names already `taken`, or None
+/// when it's skipped
+fn import_name(
+    name: &str,
+    editor: McpEditor,
+    taken: &HashSet<String>,
+    policy: McpConflictPolicy,
+) -> Option<String> {
+    if !taken.contains(name) {
+        return Some(name.to_string());
+    }
+    match policy {
+        McpConflictPolicy::Skip => None,
+        McpConflictPolicy::Overwrite => Some(name.to_string()),
+        McpConflictPolicy::Rename => {
+            let base = format!("{}-{}", name, editor.slug());
+            std::iter::once(base.clone())
+                .chain((2..).map(|n| format!("{}-{}", base, n)))
+                .find(|candidate| !taken.contains(candidate))
+        }
+    }
+}
+
+/// Remove every server named `name`, enabled or disabled, to overwrite it
+fn remove_existing(app: &AppHandle, existing: &[MCPServer], name: &str) -> Result<(), String> {
+    for server in existing.iter().filter(|server| server.name == name) {
+        if server.enabled {
+            mcp_remove_from_scope(app, name, &server.scope)?;
+        } else {
+            let db = app.state::<AgentDb>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            super::mcp_toggle::forget_disabled_server(&conn, name, Some(&server.scope))?;
+        }
+    }
+    Ok(())
+}
+
+/// Imports the MCP servers configured in Cursor, VS Code or Windsurf into
+/// `scope`, resolving name collisions by `conflict_policy`
+#[tauri::command]
+pub async fn mcp_import_from_editor(
+    app: AppHandle,
+    editor: McpEditor,
+    scope: String,
+    conflict_policy: McpConflictPolicy,
+) -> Result<EditorImportResult, String> {
+    info!(
+        "Importing MCP servers from {} with scope: {}",
+        editor.name(),
+        scope
+    );
+
+    let config_path = editor
+        .config_paths()
+        .into_iter()
+        .find(|path| path.is_file())
+        .ok_or_else(|| format!("{} MCP configuration not found", editor.name()))?;
+    let content = std::fs::read_to_string(&config_path)
+        .map_err(|e| format!("Failed to read {}: {}", config_path.display(), e))?;
+    let entries = parse_editor_config(editor, &content)?;
+
+    let existing = mcp_list(app.clone()).await?;
+    let mut taken: HashSet<String> = existing.iter().map(|server| server.name.clone()).collect();
+    let mut servers = Vec::new();
+    for (name, config) in entries {
+        let result = |imported_as, status, message| EditorImportServerResult {
+            name: name.clone(),
+            imported_as,
+            status,
+            message,
+        };
+        let config = match config {
+            Ok(config) => config,
+            Err((status, message)) => {
+                servers.push(result(None, status, Some(message)));
+                continue;
+            }
+        };
+        let Some(target) = import_name(&name, editor, &taken, conflict_policy) else {
+            servers.push(result(
+                None,
+                McpImportStatus::SkippedDuplicate,
+                Some(format!("A server named {} already exists", name)),
+            ));
+            continue;
+        };
+        if target == name && taken.contains(&name) {
+            if let Err(e) = remove_existing(&app, &existing, &name) {
+                servers.push(result(None, McpImportStatus::Failed, Some(e)));
+                continue;
+            }
+        }
+
+        match mcp_add_json(
+            app.clone(),
+            target.clone(),
+            config.to_string(),
+            scope.clone(),
+        )
+        .await
+        {
+            Ok(added) if added.success => {
+                info!("Imported MCP server {} from {}", target, editor.name());
+                taken.insert(target.clone());
+                servers.push(result(Some(target), McpImportStatus::Imported, None));
+            }
+            Ok(added) => {
+                error!("Failed to import server {}: {}", name, added.message);
+                servers.push(result(None, McpImportStatus::Failed, Some(added.message)));
+            }
+            Err(e) => {
+                error!("Failed to import server {}: {}", name, e);
+                servers.push(result(None, McpImportStatus::Failed, Some(e)));
+            }
+        }
+    }
+
+    Ok(EditorImportResult {
+        config_path: config_path.display().to_string(),
+        servers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURSOR_FIXTURE: &str = include_str!("../../tests/fixtures/mcp_cursor.json");
+    const VSCODE_FIXTURE: &str = include_str!("../../tests/fixtures/mcp_vscode.json");
+    const WINDSURF_FIXTURE: &str = include_str!("../../tests/fixtures/mcp_windsurf.json");
+
+    fn parsed(editor: McpEditor, content: &str) -> Vec<(String, EntryConfig)> {
+        parse_editor_config(editor, content).unwrap()
+    }
+
+    #[test]
+    fn test_cursor_config_is_parsed() {
+        let servers = parsed(McpEditor::Cursor, CURSOR_FIXTURE);
+        assert_eq!(servers.len(), 4);
+        let config = |name: &str| servers.iter().find(|(n, _)| n == name).unwrap().1.clone();
+
+        assert_eq!(
+            config("github").unwrap(),
+            json!({
+                "type": "stdio",
+                "command": "npx",
+                "args": ["-y", "@modelcontextprotocol/server-github"],
+                "env": {"GITHUB_PERSONAL_ACCESS_TOKEN": "ghp_fixture"},
+            })
+        );
+        assert_eq!(
+            config("remote").unwrap(),
+            json!({"type": "sse", "url": "https://mcp.example.com/sse"})
+        );
+        assert_eq!(config("broken").unwrap_err().0, McpImportStatus::ParseError);
+        assert_eq!(
+            config("socket").unwrap_err().0,
+            McpImportStatus::UnsupportedTransport
+        );
+    }
+
+    #[test]
+    fn test_vscode_config_with_comments_is_parsed() {
+        let servers = parsed(McpEditor::Vscode, VSCODE_FIXTURE);
+        assert_eq!(servers.len(), 3);
+        let config = |name: &str| servers.iter().find(|(n, _)| n == name).unwrap().1.clone();
+
+        assert_eq!(config("fetch").unwrap()["command"], "uvx");
+        assert_eq!(
+            config("docs").unwrap(),
+            json!({
+                "type": "http",
+                "url": "https://docs.example.com/mcp",
+                "headers": {"Authorization": "Bearer // not a comment"},
+            })
+        );
+        assert_eq!(
+            config("prompted").unwrap_err().0,
+            McpImportStatus::ParseError
+        );
+
+        // Servers configured in settings.json
+        let settings = r#"{"editor.fontSize": 14, "mcp": {"servers": {"time": {"command": "uvx", "args": ["mcp-server-time"],},},}}"#;
+        let servers = parsed(McpEditor::Vscode, settings);
+        assert_eq!(servers[0].0, "time");
+        assert!(servers[0].1.is_ok());
+    }
+
+    #[test]
+    fn test_windsurf_config_is_parsed() {
+        let servers = parsed(McpEditor::Windsurf, WINDSURF_FIXTURE);
+        assert_eq!(servers.len(), 2);
+        let config = |name: &str| servers.iter().find(|(n, _)| n == name).unwrap().1.clone();
+
+        assert_eq!(config("memory").unwrap()["env"], json!({}));
+        assert_eq!(
+            config("linear").unwrap(),
+            json!({"type": "http", "url": "https://mcp.linear.app/mcp"})
+        );
+        assert!(parse_editor_config(McpEditor::Windsurf, "{not json").is_err());
+    }
+
+    #[test]
+    fn test_conflicts_follow_the_policy() {
+        let taken: HashSet<String> = ["github", "github-cursor"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        let name = |name, policy| import_name(name, McpEditor::Cursor, &taken, policy);
+
+        assert_eq!(
+            name("fetch", McpConflictPolicy::Skip).as_deref(),
+            Some("fetch")
+        );
+        assert_eq!(name("github", McpConflictPolicy::Skip), None);
+        assert_eq!(
+            name("github", McpConflictPolicy::Overwrite).as_deref(),
+            Some("github")
+        );
+        assert_eq!(
+            name("github", McpConflictPolicy::Rename).as_deref(),
+            Some("github-cursor-2")
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tokio::io::AsyncBufReadExt;
+
+/// Size a server's log grows to before it's rotated
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Log files kept per server, the one being written included
+const MAX_LOG_FILES: usize = 3;
+
+const DEFAULT_TAIL_LINES: usize = 200;
+
+/// Lines of stderr kept to explain why a server failed
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Name the output of `claude mcp serve` is logged under
+pub const SERVE_LOG_NAME: &str = "claude-mcp-serve";
+
+/// One line of a server's log
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpLogLine {
+    pub timestamp: String,
+    pub line: String,
+}
+
+fn log_dir_lock() -> &'static Mutex<Option<PathBuf>> {
+    static LOG_DIR: OnceLock<Mutex<Option<PathBuf>>> = OnceLock::new();
+    LOG_DIR.get_or_init(|| Mutex::new(None))
+}
+
+/// Held while a log is written, so lines and rotations don't interleave
+fn write_lock() -> &'static Mutex<()> {
+    static WRITING: OnceLock<Mutex<()>> = OnceLock::new();
+    WRITING.get_or_init(|| Mutex::new(()))
+}
+
+/// Keep server logs under `dir`
+pub fn set_log_dir(dir: PathBuf) {
+    if let Ok(mut log_dir) = log_dir_lock().lock() {
+        *log_dir = Some(dir);
+    }
+}
+
+fn log_dir() -> Result<PathBuf, String> {
+    log_dir_lock()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone()
+        .ok_or_else(|| "MCP server logs aren't available".to_string())
+}
+
+/// The file `name` is logged to, or its `rotation`th older one. A hash of the
+/// name keeps servers whose names only differ in replaced characters apart.
+fn log_path(dir: &Path, name: &str, rotation: usize) -> PathBuf {
+    let hash = format!("{:x}", Sha256::digest(name.as_bytes()));
+    let sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = format!("{}-{}", sanitized, &hash[..8]);
+    match rotation {
+        0 => dir.join(format!("{}.log", name)),
+        n => dir.join(format!("{}.log.{}", name, n)),
+    }
+}
+
+/// Append `line` to `name`'s log in `dir`, first rotating the log when it has
+/// reached `max_bytes`. Returns the line's timestamp.
+fn append_to(dir: &Path, name: &str, line: &str, max_bytes: u64) -> Result<String, String> {
+    let _writing = write_lock().lock().map_err(|e| e.to_string())?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let current = log_path(dir, name, 0);
+    if fs::metadata(&current).is_ok_and(|metadata| metadata.len() >= max_bytes) {
+        for rotation in (1..MAX_LOG_FILES).rev() {
+            let older = log_path(dir, name, rotation - 1);
+            if older.exists() {
+                fs::rename(&older, log_path(dir, name, rotation))
+                    .map_err(|e| format!("Failed to rotate {}: {}", older.display(), e))?;
+            }
+        }
+    }
+
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&current)
+        .map_err(|e| format!("Failed to open {}: {}", current.display(), e))?;
+    writeln!(file, "{}\t{}", timestamp, line)
+        .map_err(|e| format!("Failed to write {}: {}", current.display(), e))?;
+    Ok(timestamp)
+}
+
+/// Append `line` to `name`'s log, returning its timestamp. Failing to log is
+/// only warned about.
+pub fn append_log(name: &str, line: &str) -> Option<String> {
+    match log_dir().and_then(|dir| append_to(&dir, name, line, MAX_LOG_BYTES)) {
+        Ok(timestamp) => Some(timestamp),
+        Err(e) => {
+            warn!("Failed to log MCP server {}: {}", name, e);
+            None
+        }
+    }
+}
+
+/// Copy each line a spawned server writes to stderr into its log, until the
+/// server closes it. The task returns the last lines it copied.
+pub fn capture_stderr(
+    name: &str,
+    stderr: tokio::process::ChildStderr,
+) -> tokio::task::JoinHandle<Vec<String>> {
+    let name = name.to_string();
+    tokio::spawn(async move {
+        let mut tail = VecDeque::new();
+        let mut lines = tokio::io::BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            append_log(&name, &line);
+            tail.push_back(line);
+            if tail.len() > STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+        }
+        tail.into()
+    })
+}
+
+/// The last `lines` lines of `name`'s log in `dir`, oldest first, from `since` on
+fn read_log(
+    dir: &Path,
+    name: &str,
+    lines: usize,
+    since: Option<DateTime<FixedOffset>>,
+) -> Vec<McpLogLine> {
+    let mut tail = VecDeque::new();
+    for rotation in (0..MAX_LOG_FILES).rev() {
+        let Ok(file) = File::open(log_path(dir, name, rotation)) else {
+            continue;
+        };
+        for entry in BufReader::new(file).lines().map_while(Result::ok) {
+            let Some((timestamp, line)) = entry.split_once('\t') else {
+                continue;
+            };
+            if let Some(since) = since {
+                match DateTime::parse_from_rfc3339(timestamp) {
+                    Ok(logged) if logged >= since => {}
+                    _ => continue,
+                }
+            }
+            tail.push_back(McpLogLine {
+                timestamp: timestamp.to_string(),
+                line: line.to_string(),
+            });
+            if tail.len() > lines {
+                tail.pop_front();
+            }
+        }
+    }
+    tail.into()
+}
+
+/// Delete every log file of `name` in `dir`
+fn clear_log(dir: &Path, name: &str) -> Result<(), String> {
+    let _writing = write_lock().lock().map_err(|e| e.to_string())?;
+    for rotation in 0..MAX_LOG_FILES {
+        let path = log_path(dir, name, rotation);
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Get the last `lines` lines (200 by default) opcode captured from an MCP
+/// server, only those logged from `since` on when it's given, such as the
+/// `error_log_since` of the server's status
+#[tauri::command]
+pub async fn mcp_get_server_logs(
+    name: String,
+    lines: Option<usize>,
+    since: Option<String>,
+) -> Result<Vec<McpLogLine>, String> {
+    let since = since
+        .map(|since| {
+            DateTime::parse_from_rfc3339(&since)
+                .map_err(|e| format!("Invalid timestamp {}: {}", since, e))
+        })
+        .transpose()?;
+    let dir = log_dir()?;
+    tauri::async_runtime::spawn_blocking(move || {
+        read_log(&dir, &name, lines.unwrap_or(DEFAULT_TAIL_LINES), since)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Delete the logs opcode captured from an MCP server
+#[tauri::command]
+pub async fn mcp_clear_server_logs(name: String) -> Result<(), String> {
+    clear_log(&log_dir()?, &name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logs_rotate_and_are_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..20 {
+            append_to(dir.path(), "files/server", &format!("line {}", i), 40).unwrap();
+        }
+        let file_name = log_path(dir.path(), "files/server", 0);
+        let file_name = file_name.file_name().unwrap().to_string_lossy();
+        assert!(file_name.starts_with("files_server-") && file_name.ends_with(".log"));
+        assert_ne!(
+            log_path(dir.path(), "files/server", 0),
+            log_path(dir.path(), "files:server", 0)
+        );
+        assert!(log_path(dir.path(), "files/server", 2).exists());
+        assert!(!log_path(dir.path(), "files/server", 3).exists());
+
+        // Only what the kept files hold is left, newest last
+        let tail = read_log(dir.path(), "files/server", 3, None);
+        let lines: Vec<&str> = tail.iter().map(|l| l.line.as_str()).collect();
+        assert_eq!(lines, ["line 17", "line 18", "line 19"]);
+        assert!(read_log(dir.path(), "files/server", 100, None).len() < 20);
+
+        let since = append_to(dir.path(), "files/server", "failed", 1 << 20).unwrap();
+        let since = DateTime::parse_from_rfc3339(&since).unwrap();
+        let after = read_log(dir.path(), "files/server", 100, Some(since));
+        assert_eq!(after.last().unwrap().line, "failed");
+
+        clear_log(dir.path(), "files/server").unwrap();
+        assert!(read_log(dir.path(), "files/server", 100, None).is_empty());
+    }
+}
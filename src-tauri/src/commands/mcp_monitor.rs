@@ -0,0 +1,913 @@
+use futures::future::join_all;
+use futures::StreamExt;
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use std::collections::HashMap;
+use std::future::Future;
+use std::process::Stdio;
+use std::sync::{OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::{Mutex, Notify};
+
+use super::agents::AgentDb;
+use super::mcp::{get_server, mcp_get, mcp_list, MCPServer, ServerStatus};
+
+/// Emitted with a `McpStatusChange` when a server starts or stops answering
+pub const MCP_STATUS_EVENT: &str = "mcp-status-changed";
+
+/// `app_settings` key for the seconds between background checks, 0 for none
+const STATUS_INTERVAL_SETTING_KEY: &str = "mcp_status_interval_secs";
+
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+
+/// Shortest interval accepted, since every check starts each stdio server
+const MIN_INTERVAL_SECS: u64 = 30;
+
+/// How long a server has to answer the handshake
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Emitted with a `McpTestProgress` as each test of `mcp_test_all` finishes
+pub const MCP_TEST_PROGRESS_EVENT: &str = "mcp-test-progress";
+
+const DEFAULT_TEST_CONCURRENCY: usize = 4;
+
+/// How long `mcp_test_all` takes at most, however many servers hang
+const TEST_ALL_DEADLINE: Duration = Duration::from_secs(60);
+
+/// A server's status changed between checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpStatusChange {
+    pub name: String,
+    pub status: ServerStatus,
+}
+
+fn status_cache() -> &'static RwLock<HashMap<String, ServerStatus>> {
+    static STATUSES: OnceLock<RwLock<HashMap<String, ServerStatus>>> = OnceLock::new();
+    STATUSES.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Held while servers are checked, so manual and background checks don't overlap
+fn refresh_lock() -> &'static Mutex<()> {
+    static REFRESHING: OnceLock<Mutex<()>> = OnceLock::new();
+    REFRESHING.get_or_init(|| Mutex::new(()))
+}
+
+/// Wakes the monitor when its interval is changed
+fn interval_changed() -> &'static Notify {
+    static CHANGED: OnceLock<Notify> = OnceLock::new();
+    CHANGED.get_or_init(Notify::new)
+}
+
+fn read_statuses(conn: &Connection) -> Result<HashMap<String, ServerStatus>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name, running, latency_ms, last_checked, last_error, error_log_since
+             FROM mcp_server_status",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            let status = ServerStatus {
+                running: row.get(1)?,
+                error: row.get(4)?,
+                last_checked: row.get::<_, Option<i64>>(3)?.map(|t| t as u64),
+                disabled: false,
+                latency_ms: row.get::<_, Option<i64>>(2)?.map(|ms| ms as u64),
+                error_log_since: row.get(5)?,
+            };
+            Ok((row.get::<_, String>(0)?, status))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn store_status(conn: &Connection, name: &str, status: &ServerStatus) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO mcp_server_status
+             (name, running, latency_ms, last_checked, last_error, error_log_since)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(name) DO UPDATE SET running = excluded.running,
+             latency_ms = excluded.latency_ms, last_checked = excluded.last_checked,
+             last_error = excluded.last_error, error_log_since = excluded.error_log_since",
+        params![
+            name,
+            status.running,
+            status.latency_ms.map(|ms| ms as i64),
+            status.last_checked.map(|t| t as i64),
+            status.error,
+            status.error_log_since
+        ],
+    )
+    .map_err(|e| format!("Failed to save MCP server status: {}", e))?;
+    Ok(())
+}
+
+/// Load the statuses found by earlier checks, so they're served before the
+/// first check of this run
+pub fn load_mcp_statuses(conn: &Connection) {
+    match read_statuses(conn) {
+        Ok(statuses) => {
+            if let Ok(mut cache) = status_cache().write() {
+                *cache = statuses;
+            }
+        }
+        Err(e) => warn!("Failed to load MCP server statuses: {}", e),
+    }
+}
+
+/// Drop the cached status of `name`, as when the server is removed
+pub fn forget_status(conn: &Connection, name: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM mcp_server_status WHERE name = ?1",
+        params![name],
+    )
+    .map_err(|e| format!("Failed to forget MCP server status: {}", e))?;
+    if let Ok(mut cache) = status_cache().write() {
+        cache.remove(name);
+    }
+    Ok(())
+}
+
+/// Cached statuses by name, with disabled servers reported as disabled
+pub fn cached_statuses(app: &AppHandle) -> Result<HashMap<String, ServerStatus>, String> {
+    let mut statuses = status_cache().read().map_err(|e| e.to_string())?.clone();
+    statuses.extend(super::mcp_toggle::disabled_statuses(app)?);
+    Ok(statuses)
+}
+
+/// Whether going from `old` to `new` is worth telling the frontend about
+fn is_transition(old: Option<&ServerStatus>, new: &ServerStatus) -> bool {
+    match old {
+        Some(old) => old.running != new.running,
+        None => true,
+    }
+}
+
+/// The outcome of a handshake from one line of a stdio server's output, or
+/// None if the line isn't the answer to `initialize`
+fn handshake_response(line: &str) -> Option<Result<(), String>> {
+    let message: JsonValue = serde_json::from_str(line).ok()?;
+    if message.get("id") != Some(&json!(1)) {
+        return None;
+    }
+    if let Some(error) = message.get("error") {
+        let reason = error
+            .get("message")
+            .and_then(JsonValue::as_str)
+            .unwrap_or("unknown error");
+        return Some(Err(format!("Initialize failed: {}", reason)));
+    }
+    if message.get("result").is_some() {
+        return Some(Ok(()));
+    }
+    Some(Err("Invalid initialize response".to_string()))
+}
+
+fn initialize_request() -> JsonValue {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2024-11-05",
+            "capabilities": {},
+            "clientInfo": {"name": "opcode", "version": env!("CARGO_PKG_VERSION")},
+        },
+    })
+}
+
+/// Why a server failed its check
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum McpErrorCategory {
+    /// The server can't be checked as configured, such as without a command
+    ConfigError,
+    /// The stdio server's command couldn't be started
+    SpawnFailure,
+    /// The server didn't answer in time
+    Timeout,
+    /// The server answered, or exited, without completing the handshake
+    ProtocolError,
+    /// The server rejected its credentials
+    AuthFailure,
+    /// The remote server couldn't be reached
+    ConnectionFailure,
+}
+
+/// A failed check
+#[derive(Debug, Clone, PartialEq)]
+struct ProbeError {
+    category: McpErrorCategory,
+    detail: String,
+}
+
+impl ProbeError {
+    fn new(category: McpErrorCategory, detail: impl Into<String>) -> Self {
+        ProbeError {
+            category,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Whether a server's message says its credentials were rejected
+fn looks_like_auth_failure(message: &str) -> bool {
+    let message = message.to_lowercase();
+    [
+        "unauthorized",
+        "unauthenticated",
+        "forbidden",
+        "authentication failed",
+        "invalid api key",
+        "invalid token",
+        "bad credentials",
+    ]
+    .iter()
+    .any(|hint| message.contains(hint))
+}
+
+/// Kills a stdio server's process group when dropped, so a cancelled check
+/// doesn't leave it running
+struct ProcessGroupGuard(Option<u32>);
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            crate::process::signal_process_group(pid, true);
+        }
+    }
+}
+
+/// Start a stdio server, send `initialize` and wait up to `timeout` for its
+/// answer, logging what it writes to stderr. The server and anything it
+/// started are killed afterwards, whatever happened.
+async fn probe_stdio(server: &MCPServer, timeout: Duration) -> Result<(), ProbeError> {
+    let program = server
+        .command
+        .as_deref()
+        .ok_or_else(|| ProbeError::new(McpErrorCategory::ConfigError, "No command configured"))?;
+    let mut cmd = std::process::Command::new(program);
+    cmd.args(&server.args)
+        .envs(&server.env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    super::extra_path::apply_extra_path(&mut cmd);
+
+    // Lead a new process group so servers run through npx or a shell die too
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let mut child = Command::from(cmd).kill_on_drop(true).spawn().map_err(|e| {
+        ProbeError::new(
+            McpErrorCategory::SpawnFailure,
+            format!("Failed to start {}: {}", program, e),
+        )
+    })?;
+    let group = ProcessGroupGuard(child.id());
+    let stderr = child
+        .stderr
+        .take()
+        .map(|stderr| super::mcp_logs::capture_stderr(&server.name, stderr));
+
+    let handshake = async {
+        let protocol_error =
+            |detail: String| ProbeError::new(McpErrorCategory::ProtocolError, detail);
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| protocol_error("No stdin".to_string()))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| protocol_error("No stdout".to_string()))?;
+        let request = format!("{}\n", initialize_request());
+        stdin
+            .write_all(request.as_bytes())
+            .await
+            .map_err(|e| protocol_error(format!("Failed to send initialize: {}", e)))?;
+        let mut lines = BufReader::new(stdout).lines();
+        while let Some(line) = lines
+            .next_line()
+            .await
+            .map_err(|e| protocol_error(e.to_string()))?
+        {
+            if let Some(result) = handshake_response(&line) {
+                return result.map_err(|detail| {
+                    let category = if looks_like_auth_failure(&detail) {
+                        McpErrorCategory::AuthFailure
+                    } else {
+                        McpErrorCategory::ProtocolError
+                    };
+                    ProbeError::new(category, detail)
+                });
+            }
+        }
+        Err(protocol_error(
+            "Server exited before answering initialize".to_string(),
+        ))
+    };
+    let mut result = tokio::time::timeout(timeout, handshake)
+        .await
+        .unwrap_or_else(|_| {
+            Err(ProbeError::new(
+                McpErrorCategory::Timeout,
+                format!("No answer within {:?}", timeout),
+            ))
+        });
+
+    drop(group);
+    let _ = child.kill().await;
+    // Let the last of stderr reach the log, and explain an early exit with it
+    let stderr_tail = match stderr {
+        Some(stderr) => tokio::time::timeout(Duration::from_secs(1), stderr)
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    if let Err(error) = &mut result {
+        if error.category == McpErrorCategory::ProtocolError {
+            if let Some(last) = stderr_tail
+                .iter()
+                .rev()
+                .find(|line| !line.trim().is_empty())
+            {
+                error.detail = format!("{}: {}", error.detail, last.trim());
+            }
+            if stderr_tail.iter().any(|line| looks_like_auth_failure(line)) {
+                error.category = McpErrorCategory::AuthFailure;
+            }
+        }
+    }
+    result
+}
+
+/// Open an SSE stream, or send `initialize` to a streamable HTTP server,
+/// and check the server accepts it
+async fn probe_http(server: &MCPServer, timeout: Duration) -> Result<(), ProbeError> {
+    let url = server
+        .url
+        .as_deref()
+        .ok_or_else(|| ProbeError::new(McpErrorCategory::ConfigError, "No URL configured"))?;
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| ProbeError::new(McpErrorCategory::ConfigError, e.to_string()))?;
+    let request = if server.transport == "sse" {
+        client.get(url).header("Accept", "text/event-stream")
+    } else {
+        client
+            .post(url)
+            .header("Accept", "application/json, text/event-stream")
+            .json(&initialize_request())
+    };
+    // Only the status is read, so an SSE stream is closed when it's dropped
+    let response = request.send().await.map_err(|e| {
+        let category = if e.is_timeout() {
+            McpErrorCategory::Timeout
+        } else if e.is_connect() {
+            McpErrorCategory::ConnectionFailure
+        } else {
+            McpErrorCategory::ProtocolError
+        };
+        ProbeError::new(category, e.to_string())
+    })?;
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        status if status.as_u16() == 401 || status.as_u16() == 403 => Err(ProbeError::new(
+            McpErrorCategory::AuthFailure,
+            format!("HTTP {}", status),
+        )),
+        status => Err(ProbeError::new(
+            McpErrorCategory::ProtocolError,
+            format!("HTTP {}", status),
+        )),
+    }
+}
+
+/// Check `server` with a handshake, along with why it failed if it did. A
+/// stdio server's failed check points at where its output starts in the
+/// server's log.
+async fn check_server(server: &MCPServer) -> (ServerStatus, Option<McpErrorCategory>) {
+    let started = Instant::now();
+    let mut log_since = None;
+    let result = match server.transport.as_str() {
+        "stdio" => {
+            log_since = super::mcp_logs::append_log(
+                &server.name,
+                &format!(
+                    "[opcode] Starting {} {} for a status check",
+                    server.command.as_deref().unwrap_or_default(),
+                    server.args.join(" ")
+                ),
+            );
+            let result = probe_stdio(server, PROBE_TIMEOUT).await;
+            if let Err(e) = &result {
+                super::mcp_logs::append_log(
+                    &server.name,
+                    &format!("[opcode] Check failed: {}", e.detail),
+                );
+            }
+            result
+        }
+        "sse" | "http" => probe_http(server, PROBE_TIMEOUT).await,
+        other => Err(ProbeError::new(
+            McpErrorCategory::ConfigError,
+            format!("Unsupported transport: {}", other),
+        )),
+    };
+    let status = ServerStatus {
+        running: result.is_ok(),
+        latency_ms: result.is_ok().then(|| started.elapsed().as_millis() as u64),
+        error_log_since: log_since.filter(|_| result.is_err()),
+        error: result.as_ref().err().map(|e| e.detail.clone()),
+        last_checked: Some(chrono::Utc::now().timestamp() as u64),
+        disabled: false,
+    };
+    (status, result.err().map(|e| e.category))
+}
+
+/// Check `server` with a handshake
+pub async fn probe_server(server: &MCPServer) -> ServerStatus {
+    check_server(server).await.0
+}
+
+/// Check enabled servers, all of them or just `only`, caching the results
+/// and emitting `MCP_STATUS_EVENT` for those whose status changed
+async fn refresh_statuses(app: &AppHandle, only: Option<&str>) -> Result<(), String> {
+    let _refreshing = refresh_lock().lock().await;
+    let enabled: Vec<String> = mcp_list(app.clone())
+        .await?
+        .into_iter()
+        .filter(|server| server.enabled)
+        .map(|server| server.name)
+        .collect();
+
+    let checks = enabled
+        .iter()
+        .filter(|name| only.is_none() || only == Some(name.as_str()))
+        .map(|name| async move {
+            let status = match mcp_get(app.clone(), name.clone()).await {
+                Ok(server) => probe_server(&server).await,
+                Err(e) => ServerStatus {
+                    running: false,
+                    error: Some(e),
+                    last_checked: Some(chrono::Utc::now().timestamp() as u64),
+                    disabled: false,
+                    latency_ms: None,
+                    error_log_since: None,
+                },
+            };
+            (name.clone(), status)
+        });
+    let results = join_all(checks).await;
+
+    let mut changes = Vec::new();
+    {
+        let mut cache = status_cache().write().map_err(|e| e.to_string())?;
+        for (name, status) in &results {
+            if is_transition(cache.get(name), status) {
+                changes.push(McpStatusChange {
+                    name: name.clone(),
+                    status: status.clone(),
+                });
+            }
+            cache.insert(name.clone(), status.clone());
+        }
+        // Servers removed outside opcode are dropped by a full check
+        if only.is_none() {
+            cache.retain(|name, _| enabled.contains(name));
+        }
+    }
+
+    {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        if only.is_none() {
+            conn.execute("DELETE FROM mcp_server_status", [])
+                .map_err(|e| format!("Failed to clear MCP server statuses: {}", e))?;
+        }
+        for (name, status) in &results {
+            store_status(&conn, name, status)?;
+        }
+    }
+
+    for change in changes {
+        info!(
+            "MCP server {} is {}",
+            change.name,
+            if change.status.running { "up" } else { "down" }
+        );
+        let _ = app.emit(MCP_STATUS_EVENT, &change);
+    }
+    Ok(())
+}
+
+fn read_interval(conn: &Connection) -> u64 {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![STATUS_INTERVAL_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| value.parse().ok())
+    .unwrap_or(DEFAULT_INTERVAL_SECS)
+}
+
+/// Start the background task that checks MCP servers every interval
+pub fn start_mcp_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let interval = app
+                .state::<AgentDb>()
+                .0
+                .lock()
+                .map(|conn| read_interval(&conn))
+                .unwrap_or(DEFAULT_INTERVAL_SECS);
+            if interval == 0 {
+                interval_changed().notified().await;
+                continue;
+            }
+            if let Err(e) = refresh_statuses(&app, None).await {
+                warn!("MCP status check failed: {}", e);
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+                _ = interval_changed().notified() => {}
+            }
+        }
+    });
+}
+
+/// Check enabled MCP servers now, all of them or just `name`, and return
+/// every server's status
+#[tauri::command]
+pub async fn mcp_refresh_status(
+    app: AppHandle,
+    name: Option<String>,
+) -> Result<HashMap<String, ServerStatus>, String> {
+    refresh_statuses(&app, name.as_deref()).await?;
+    cached_statuses(&app)
+}
+
+/// Outcome of testing one server with `mcp_test_all`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpTestResult {
+    pub name: String,
+    pub ok: bool,
+    pub latency_ms: Option<u64>,
+    pub error_category: Option<McpErrorCategory>,
+    pub error_detail: Option<String>,
+}
+
+impl McpTestResult {
+    fn failed(name: String, category: McpErrorCategory, detail: String) -> Self {
+        McpTestResult {
+            name,
+            ok: false,
+            latency_ms: None,
+            error_category: Some(category),
+            error_detail: Some(detail),
+        }
+    }
+}
+
+/// One test of `mcp_test_all` finished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpTestProgress {
+    pub result: McpTestResult,
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Run the named `tests`, `concurrency` at a time, calling `on_result` with
+/// each result and how many have finished. Tests still running after
+/// `within` are cancelled and reported as timed out. Results are in the
+/// order of `tests`.
+async fn run_tests<Fut>(
+    tests: Vec<(String, Fut)>,
+    concurrency: usize,
+    within: Duration,
+    mut on_result: impl FnMut(&McpTestResult, usize),
+) -> Vec<McpTestResult>
+where
+    Fut: Future<Output = McpTestResult>,
+{
+    let deadline = tokio::time::Instant::now() + within;
+    let names: Vec<String> = tests.iter().map(|(name, _)| name.clone()).collect();
+    let mut results = Vec::with_capacity(names.len());
+    {
+        let mut running = futures::stream::iter(tests.into_iter().map(|(_, test)| test))
+            .buffer_unordered(concurrency.max(1));
+        while let Ok(Some(result)) = tokio::time::timeout_at(deadline, running.next()).await {
+            on_result(&result, results.len() + 1);
+            results.push(result);
+        }
+        // Dropping the tests left kills the servers they started
+    }
+    for name in &names {
+        if !results.iter().any(|result| &result.name == name) {
+            let result = McpTestResult::failed(
+                name.clone(),
+                McpErrorCategory::Timeout,
+                format!("Still being tested after {:?}", within),
+            );
+            on_result(&result, results.len() + 1);
+            results.push(result);
+        }
+    }
+    results.sort_by_key(|result| names.iter().position(|name| name == &result.name));
+    results
+}
+
+/// Test the connection to every enabled MCP server, or those of `scope`,
+/// `concurrency` at a time (4 by default), emitting `MCP_TEST_PROGRESS_EVENT`
+/// as each test finishes. Returns within a minute whatever happens, with the
+/// servers still being tested reported as timed out.
+#[tauri::command]
+pub async fn mcp_test_all(
+    app: AppHandle,
+    scope: Option<String>,
+    concurrency: Option<usize>,
+) -> Result<Vec<McpTestResult>, String> {
+    let started = Instant::now();
+    let names: Vec<String> = mcp_list(app.clone())
+        .await?
+        .into_iter()
+        .filter(|server| server.enabled)
+        .map(|server| server.name)
+        .collect();
+    info!("Testing {} MCP servers", names.len());
+
+    // The listing doesn't give scopes, so every server's details are read first
+    let details = names.into_iter().map(|name| {
+        let app = app.clone();
+        async move {
+            let lookup = name.clone();
+            let server = tauri::async_runtime::spawn_blocking(move || get_server(&app, lookup))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|server| server);
+            (name, server)
+        }
+    });
+    let details = tokio::time::timeout(TEST_ALL_DEADLINE, join_all(details))
+        .await
+        .map_err(|_| "Timed out reading the MCP server configuration".to_string())?;
+
+    let tests: Vec<_> = details
+        .into_iter()
+        .filter(|(_, server)| match (&scope, server) {
+            (Some(scope), Ok(server)) => &server.scope == scope,
+            _ => true,
+        })
+        .map(|(name, server)| {
+            let label = name.clone();
+            let test = async move {
+                let server = match server {
+                    Ok(server) => server,
+                    Err(e) => return McpTestResult::failed(name, McpErrorCategory::ConfigError, e),
+                };
+                let (status, category) = check_server(&server).await;
+                McpTestResult {
+                    name: server.name,
+                    ok: status.running,
+                    latency_ms: status.latency_ms,
+                    error_category: category,
+                    error_detail: status.error,
+                }
+            };
+            (label, test)
+        })
+        .collect();
+
+    let total = tests.len();
+    let within = TEST_ALL_DEADLINE.saturating_sub(started.elapsed());
+    let concurrency = concurrency.unwrap_or(DEFAULT_TEST_CONCURRENCY);
+    Ok(run_tests(tests, concurrency, within, |result, completed| {
+        let progress = McpTestProgress {
+            result: result.clone(),
+            completed,
+            total,
+        };
+        let _ = app.emit(MCP_TEST_PROGRESS_EVENT, &progress);
+    })
+    .await)
+}
+
+/// Get the seconds between background MCP server checks, 0 when they're off
+#[tauri::command]
+pub async fn get_mcp_status_interval(db: State<'_, AgentDb>) -> Result<u64, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(read_interval(&conn))
+}
+
+/// Set the seconds between background MCP server checks, 0 to turn them off
+#[tauri::command]
+pub async fn set_mcp_status_interval(db: State<'_, AgentDb>, seconds: u64) -> Result<(), String> {
+    if seconds != 0 && seconds < MIN_INTERVAL_SECS {
+        return Err(format!(
+            "MCP servers can't be checked more often than every {} seconds",
+            MIN_INTERVAL_SECS
+        ));
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![STATUS_INTERVAL_SETTING_KEY, seconds.to_string()],
+    )
+    .map_err(|e| format!("Failed to save MCP status interval: {}", e))?;
+    interval_changed().notify_one();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake_responses() {
+        assert_eq!(handshake_response("npm WARN something"), None);
+        assert_eq!(
+            handshake_response(r#"{"jsonrpc":"2.0","method":"notifications/message"}"#),
+            None
+        );
+        assert_eq!(
+            handshake_response(r#"{"jsonrpc":"2.0","id":1,"result":{"capabilities":{}}}"#),
+            Some(Ok(()))
+        );
+        assert_eq!(
+            handshake_response(r#"{"jsonrpc":"2.0","id":1,"error":{"message":"bad version"}}"#),
+            Some(Err("Initialize failed: bad version".to_string()))
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_stdio_probe_kills_the_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let script = format!(
+            "sleep 60 & echo $! > {}; read line; \
+             echo '{{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{{}}}}'; wait",
+            pid_file.display()
+        );
+        let server: MCPServer = serde_json::from_value(json!({
+            "name": "files",
+            "transport": "stdio",
+            "command": "sh",
+            "args": ["-c", script],
+            "env": {},
+            "url": null,
+            "scope": "user",
+            "is_active": false,
+            "status": {"running": false, "error": null, "last_checked": null},
+        }))
+        .unwrap();
+
+        let status = probe_server(&server).await;
+        assert!(status.running, "{:?}", status.error);
+        assert!(status.latency_ms.is_some());
+
+        // The server's own child went with it
+        let pid: u32 = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let state = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+        assert!(state.is_empty() || state.contains(") Z "), "{}", state);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_probes_are_categorized() {
+        let server = |command: &str, script: &str| -> MCPServer {
+            serde_json::from_value(json!({
+                "name": "files",
+                "transport": "stdio",
+                "command": command,
+                "args": ["-c", script],
+                "env": {},
+                "url": null,
+                "scope": "user",
+                "is_active": false,
+                "status": {"running": false, "error": null, "last_checked": null},
+            }))
+            .unwrap()
+        };
+        let timeout = Duration::from_millis(300);
+
+        let error = probe_stdio(&server("/nonexistent/mcp-server", ""), timeout)
+            .await
+            .unwrap_err();
+        assert_eq!(error.category, McpErrorCategory::SpawnFailure);
+
+        let error = probe_stdio(&server("sh", "sleep 5"), timeout)
+            .await
+            .unwrap_err();
+        assert_eq!(error.category, McpErrorCategory::Timeout);
+
+        let error = probe_stdio(
+            &server("sh", "echo 'Error: Invalid API key' >&2; exit 1"),
+            timeout,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.category, McpErrorCategory::AuthFailure);
+        assert!(
+            error.detail.ends_with("Error: Invalid API key"),
+            "{}",
+            error.detail
+        );
+
+        let error = probe_stdio(&server("sh", "read line; echo 'not json'; exit 0"), timeout)
+            .await
+            .unwrap_err();
+        assert_eq!(error.category, McpErrorCategory::ProtocolError);
+    }
+
+    #[tokio::test]
+    async fn test_tests_past_the_deadline_time_out() {
+        let ok = |name: &str| McpTestResult {
+            name: name.to_string(),
+            ok: true,
+            latency_ms: Some(1),
+            error_category: None,
+            error_detail: None,
+        };
+        let hang = ok("hang");
+        let tests = vec![
+            (
+                "hang".to_string(),
+                futures::future::Either::Left(async move {
+                    std::future::pending::<()>().await;
+                    hang
+                }),
+            ),
+            (
+                "fast".to_string(),
+                futures::future::Either::Right(std::future::ready(ok("fast"))),
+            ),
+        ];
+
+        let mut progress = Vec::new();
+        let results = run_tests(tests, 2, Duration::from_millis(100), |result, completed| {
+            progress.push((result.name.clone(), completed))
+        })
+        .await;
+        assert_eq!(progress, [("fast".to_string(), 1), ("hang".to_string(), 2)]);
+        assert_eq!(results[0].name, "hang");
+        assert_eq!(results[0].error_category, Some(McpErrorCategory::Timeout));
+        assert!(results[1].ok);
+    }
+
+    #[test]
+    fn test_statuses_are_stored() {
+        let conn = crate::db::test_connection();
+        let status = ServerStatus {
+            running: false,
+            error: Some("No answer within 10s".to_string()),
+            last_checked: Some(1_700_000_000),
+            disabled: false,
+            latency_ms: None,
+            error_log_since: Some("2025-01-01T00:00:00.000Z".to_string()),
+        };
+        store_status(&conn, "files", &status).unwrap();
+        assert_eq!(
+            read_statuses(&conn).unwrap()["files"].error_log_since,
+            status.error_log_since
+        );
+        store_status(
+            &conn,
+            "files",
+            &ServerStatus {
+                running: true,
+                error: None,
+                latency_ms: Some(42),
+                error_log_since: None,
+                ..status.clone()
+            },
+        )
+        .unwrap();
+
+        let statuses = read_statuses(&conn).unwrap();
+        assert_eq!(statuses.len(), 1);
+        assert!(statuses["files"].running);
+        assert_eq!(statuses["files"].latency_ms, Some(42));
+        assert_eq!(statuses["files"].last_checked, Some(1_700_000_000));
+        assert!(is_transition(Some(&status), &statuses["files"]));
+        assert!(!is_transition(Some(&status), &status));
+    }
+}
@@ -0,0 +1,204 @@
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+
+use super::agents::AgentDb;
+use super::mcp::{mcp_add_json, mcp_get, mcp_remove_from_scope, MCPServer, ServerStatus};
+use super::mcp_export::{server_entry, McpExportFormat};
+
+/// Disabled servers, which Claude no longer has but opcode keeps to add back
+pub fn load_disabled_servers(conn: &Connection) -> Result<Vec<MCPServer>, String> {
+    let mut stmt = conn
+        .prepare("SELECT server FROM disabled_mcp_servers ORDER BY name, scope")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+    let mut servers = Vec::new();
+    for json in rows {
+        let json = json.map_err(|e| e.to_string())?;
+        match serde_json::from_str::<MCPServer>(&json) {
+            Ok(server) => servers.push(server),
+            Err(e) => log::warn!("Ignoring invalid disabled MCP server: {}", e),
+        }
+    }
+    Ok(servers)
+}
+
+/// Keep `server` as disabled in its scope
+pub fn store_disabled_server(conn: &Connection, server: &MCPServer) -> Result<(), String> {
+    let mut server = server.clone();
+    server.enabled = false;
+    let json = serde_json::to_string(&server).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO disabled_mcp_servers (name, scope, server) VALUES (?1, ?2, ?3)
+         ON CONFLICT(name, scope) DO UPDATE SET server = excluded.server",
+        params![server.name, server.scope, json],
+    )
+    .map_err(|e| format!("Failed to save disabled MCP server: {}", e))?;
+    Ok(())
+}
+
+/// The disabled server `name` in `scope`, if there is one
+pub fn find_disabled_server(
+    conn: &Connection,
+    name: &str,
+    scope: &str,
+) -> Result<Option<MCPServer>, String> {
+    let json = conn
+        .query_row(
+            "SELECT server FROM disabled_mcp_servers WHERE name = ?1 AND scope = ?2",
+            params![name, scope],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    json.map(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+/// Forget disabled servers named `name`, in `scope` or in every scope,
+/// returning how many there were
+pub fn forget_disabled_server(
+    conn: &Connection,
+    name: &str,
+    scope: Option<&str>,
+) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM disabled_mcp_servers WHERE name = ?1 AND (?2 IS NULL OR scope = ?2)",
+        params![name, scope],
+    )
+    .map_err(|e| format!("Failed to forget disabled MCP server: {}", e))
+}
+
+/// Disabled servers for `mcp_list`
+pub fn disabled_servers(app: &AppHandle) -> Result<Vec<MCPServer>, String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_disabled_servers(&conn)
+}
+
+/// Statuses of disabled servers by name, which aren't connected to
+pub fn disabled_statuses(app: &AppHandle) -> Result<HashMap<String, ServerStatus>, String> {
+    Ok(disabled_servers(app)?
+        .into_iter()
+        .map(|server| {
+            let status = ServerStatus {
+                running: false,
+                error: None,
+                last_checked: None,
+                disabled: true,
+                latency_ms: None,
+                error_log_since: None,
+            };
+            (server.name, status)
+        })
+        .collect())
+}
+
+/// Enable or disable an MCP server. Disabling removes it from Claude's
+/// configuration while opcode keeps all of it, env included; enabling adds
+/// it back to `scope`.
+#[tauri::command]
+pub async fn mcp_set_enabled(
+    app: AppHandle,
+    name: String,
+    scope: String,
+    enabled: bool,
+) -> Result<MCPServer, String> {
+    let disabled = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        find_disabled_server(&conn, &name, &scope)?
+    };
+
+    match (enabled, disabled) {
+        (false, Some(server)) => Ok(server),
+        (false, None) => {
+            let mut server = mcp_get(app.clone(), name.clone()).await?;
+            server.scope = scope.clone();
+            // Stored before removing, so a failure can't lose the server
+            {
+                let db = app.state::<AgentDb>();
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                store_disabled_server(&conn, &server)?;
+            }
+            if let Err(e) = mcp_remove_from_scope(&app, &name, &scope) {
+                let db = app.state::<AgentDb>();
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                forget_disabled_server(&conn, &name, Some(&scope))?;
+                return Err(e);
+            }
+            info!("Disabled MCP server {} in {} scope", name, scope);
+            server.enabled = false;
+            Ok(server)
+        }
+        (true, Some(mut server)) => {
+            let config = server_entry(&server, McpExportFormat::McpJson, false)
+                .ok_or_else(|| format!("MCP server {} can't be added back", name))?;
+            let result =
+                mcp_add_json(app.clone(), name.clone(), config.to_string(), scope.clone()).await?;
+            if !result.success {
+                return Err(result.message);
+            }
+            let db = app.state::<AgentDb>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            forget_disabled_server(&conn, &name, Some(&scope))?;
+            info!("Enabled MCP server {} in {} scope", name, scope);
+            server.enabled = true;
+            Ok(server)
+        }
+        (true, None) => mcp_get(app, name).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_servers_are_kept_per_scope() {
+        let conn = crate::db::test_connection();
+
+        let server: MCPServer = serde_json::from_value(serde_json::json!({
+            "name": "files",
+            "transport": "stdio",
+            "command": "npx",
+            "args": ["-y", "@scope/server"],
+            "env": {"API_KEY": "secret"},
+            "url": null,
+            "scope": "user",
+            "is_active": false,
+            "status": {"running": false, "error": null, "last_checked": null},
+        }))
+        .unwrap();
+        assert!(server.enabled);
+        store_disabled_server(&conn, &server).unwrap();
+        store_disabled_server(
+            &conn,
+            &MCPServer {
+                scope: "project".to_string(),
+                ..server.clone()
+            },
+        )
+        .unwrap();
+
+        let stored = find_disabled_server(&conn, "files", "user")
+            .unwrap()
+            .unwrap();
+        assert!(!stored.enabled);
+        assert_eq!(stored.env["API_KEY"], "secret");
+        assert_eq!(load_disabled_servers(&conn).unwrap().len(), 2);
+
+        assert_eq!(
+            forget_disabled_server(&conn, "files", Some("user")).unwrap(),
+            1
+        );
+        assert!(find_disabled_server(&conn, "files", "user")
+            .unwrap()
+            .is_none());
+        assert_eq!(forget_disabled_server(&conn, "files", None).unwrap(), 1);
+        assert!(load_disabled_servers(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,451 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use super::claude::ClaudeMdFile;
+
+/// Project memory file, also read from `.claude/` and parent directories
+const MEMORY_FILE: &str = "CLAUDE.md";
+
+/// Personal memory file kept out of version control
+const LOCAL_MEMORY_FILE: &str = "CLAUDE.local.md";
+
+/// Directories never searched for memory files
+const SKIPPED_DIRS: [&str; 6] = [
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    "__pycache__",
+    ".next",
+];
+
+/// A memory file as read, with a token that identifies this version of it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemoryFileContent {
+    pub content: String,
+    /// SHA-256 of the content, passed back when saving; None when the file
+    /// doesn't exist
+    pub version: Option<String>,
+    /// Last modification, in seconds since the Unix epoch
+    pub modified: Option<u64>,
+}
+
+/// The outcome of saving a memory file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum MemoryFileSave {
+    Saved {
+        version: String,
+    },
+    /// The file changed since it was read, e.g. in an editor, and was left alone
+    Conflict {
+        current: MemoryFileContent,
+    },
+}
+
+/// Where a memory file sits relative to the project, and so when Claude loads it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryScope {
+    User,    // ~/.claude/CLAUDE.md
+    Project, // CLAUDE.md in the project or below it
+    Local,   // CLAUDE.local.md in the project or below it
+    Parent,  // Either file in a directory above the project
+}
+
+fn memory_scope(file_name: &str) -> Option<MemoryScope> {
+    if file_name.eq_ignore_ascii_case(MEMORY_FILE) {
+        Some(MemoryScope::Project)
+    } else if file_name.eq_ignore_ascii_case(LOCAL_MEMORY_FILE) {
+        Some(MemoryScope::Local)
+    } else {
+        None
+    }
+}
+
+fn memory_file(path: &Path, relative_path: String, scope: MemoryScope) -> Option<ClaudeMdFile> {
+    let metadata = fs::metadata(path).ok().filter(|m| m.is_file())?;
+    let modified = metadata
+        .modified()
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Some(ClaudeMdFile {
+        relative_path,
+        absolute_path: path.to_string_lossy().to_string(),
+        size: metadata.len(),
+        modified,
+        scope,
+    })
+}
+
+/// Collect memory files below `dir`, descending at most `depth_left` more levels.
+/// Symlinked directories are not followed, so links can't make the walk loop.
+fn find_in_tree(dir: &Path, root: &Path, depth_left: Option<usize>, files: &mut Vec<ClaudeMdFile>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Skipping unreadable directory {:?}: {}", dir, e);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+
+        if file_type.is_dir() {
+            if name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_str()) {
+                continue;
+            }
+            if depth_left != Some(0) {
+                find_in_tree(&path, root, depth_left.map(|d| d - 1), files);
+            }
+        } else if let Some(scope) = memory_scope(&name) {
+            let relative_path = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string();
+            files.extend(memory_file(&path, relative_path, scope));
+        }
+    }
+}
+
+/// Every memory file Claude would load for a project: the user's, those in
+/// parent directories up to the filesystem root, and those in the project tree
+/// down to `max_depth` levels below the root (unlimited when `None`).
+pub fn find_memory_files(
+    claude_dir: &Path,
+    project_root: &Path,
+    max_depth: Option<usize>,
+) -> Vec<ClaudeMdFile> {
+    let mut files = Vec::new();
+    files.extend(memory_file(
+        &claude_dir.join(MEMORY_FILE),
+        format!("~/.claude/{}", MEMORY_FILE),
+        MemoryScope::User,
+    ));
+
+    // Outermost directory first, the order Claude reads them in
+    let parents: Vec<&Path> = project_root.ancestors().skip(1).collect();
+    for (levels_up, parent) in parents.iter().enumerate().rev() {
+        for name in [MEMORY_FILE, LOCAL_MEMORY_FILE] {
+            let relative_path: PathBuf = std::iter::repeat_n("..", levels_up + 1)
+                .chain([name])
+                .collect();
+            files.extend(memory_file(
+                &parent.join(name),
+                relative_path.to_string_lossy().to_string(),
+                MemoryScope::Parent,
+            ));
+        }
+    }
+
+    let mut tree = Vec::new();
+    let dot_claude = Path::new(".claude").join(MEMORY_FILE);
+    tree.extend(memory_file(
+        &project_root.join(&dot_claude),
+        dot_claude.to_string_lossy().to_string(),
+        MemoryScope::Project,
+    ));
+    find_in_tree(project_root, project_root, max_depth, &mut tree);
+    tree.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    files.extend(tree);
+
+    files
+}
+
+/// Project directory names Claude could have given `dir` under `~/.claude/projects`
+fn encoded_dir_names(dir: &Path) -> [String; 2] {
+    let path = dir.to_string_lossy();
+    [
+        path.replace(['/', '\\'], "-"),
+        path.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect(),
+    ]
+}
+
+/// Whether `dir` is a project Claude has sessions for, or a directory above one
+fn is_known_project_or_parent(projects_dir: &Path, dir: &Path, allow_parent: bool) -> bool {
+    let encoded = encoded_dir_names(dir);
+    if encoded.iter().any(|name| projects_dir.join(name).is_dir()) {
+        return true;
+    }
+    if !allow_parent {
+        return false;
+    }
+    let prefixes: Vec<String> = encoded
+        .iter()
+        .map(|name| format!("{}-", name.trim_end_matches('-')))
+        .collect();
+    fs::read_dir(projects_dir)
+        .map(|entries| {
+            entries.flatten().any(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                prefixes
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix.as_str()))
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Check that a memory file may be written: the user-level CLAUDE.md, any file
+/// inside a project Claude knows about, or a memory file in a directory above
+/// one.
+pub fn validate_memory_file_path(claude_dir: &Path, path: &Path) -> Result<(), String> {
+    if !path.is_absolute() || path.components().any(|c| c == Component::ParentDir) {
+        return Err(format!(
+            "Expected an absolute path without '..': {}",
+            path.display()
+        ));
+    }
+    if path == claude_dir.join(MEMORY_FILE) {
+        return Ok(());
+    }
+
+    let projects_dir = claude_dir.join("projects");
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?;
+    if dir
+        .ancestors()
+        .any(|ancestor| is_known_project_or_parent(&projects_dir, ancestor, false))
+    {
+        return Ok(());
+    }
+
+    let is_memory_file = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(memory_scope)
+        .is_some();
+    if is_memory_file && is_known_project_or_parent(&projects_dir, dir, true) {
+        return Ok(());
+    }
+    Err(format!(
+        "{} is outside ~/.claude and the projects Claude knows about",
+        path.display()
+    ))
+}
+
+fn content_version(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Read a memory file; one that doesn't exist reads as empty without a version
+pub fn read_memory_file(path: &Path) -> Result<MemoryFileContent, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Ok(MemoryFileContent {
+                content: String::new(),
+                version: None,
+                modified: None,
+            })
+        }
+        Err(e) => return Err(format!("Failed to read file: {}", e)),
+    };
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    Ok(MemoryFileContent {
+        version: Some(content_version(&content)),
+        content,
+        modified,
+    })
+}
+
+/// Save a memory file if it's still the version that was read
+/// (`expected_version`, None for a file that didn't exist), or regardless when
+/// `force` is set. The file is replaced atomically and keeps its permissions.
+pub fn write_memory_file(
+    path: &Path,
+    content: &str,
+    expected_version: Option<&str>,
+    force: bool,
+) -> Result<MemoryFileSave, String> {
+    let current = read_memory_file(path)?;
+    if !force && current.version.as_deref() != expected_version {
+        log::warn!(
+            "Not saving {}: it changed since it was read",
+            path.display()
+        );
+        return Ok(MemoryFileSave::Conflict { current });
+    }
+
+    let dir = path
+        .parent()
+        .ok_or_else(|| format!("Invalid file path: {}", path.display()))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create parent directory: {}", e))?;
+    let mut temp = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    temp.write_all(content.as_bytes())
+        .and_then(|_| temp.as_file().sync_all())
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+    if let Ok(metadata) = fs::metadata(path) {
+        let _ = fs::set_permissions(temp.path(), metadata.permissions());
+    }
+    temp.persist(path)
+        .map_err(|e| format!("Failed to save file: {}", e.error))?;
+    Ok(MemoryFileSave::Saved {
+        version: content_version(content),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scopes(files: &[ClaudeMdFile]) -> Vec<(String, MemoryScope)> {
+        files
+            .iter()
+            .map(|f| (f.relative_path.clone(), f.scope))
+            .collect()
+    }
+
+    #[test]
+    fn test_finds_user_parent_project_and_local_files() {
+        let home = tempfile::tempdir().unwrap();
+        let claude_dir = home.path().join(".claude");
+        let root = home.path().join("work").join("app");
+        fs::create_dir_all(claude_dir.join("projects")).unwrap();
+        fs::create_dir_all(root.join("src/deep/er")).unwrap();
+        fs::create_dir_all(root.join(".claude")).unwrap();
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        for file in [
+            claude_dir.join("CLAUDE.md"),
+            home.path().join("work/CLAUDE.md"),
+            root.join("CLAUDE.md"),
+            root.join(".claude/CLAUDE.md"),
+            root.join("src/CLAUDE.local.md"),
+            root.join("src/deep/er/CLAUDE.md"),
+            root.join("node_modules/pkg/CLAUDE.md"),
+        ] {
+            fs::write(file, "memory").unwrap();
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, root.join("src/loop")).unwrap();
+
+        let files = find_memory_files(&claude_dir, &root, None);
+        let found = scopes(&files);
+        assert_eq!(
+            found[0],
+            ("~/.claude/CLAUDE.md".to_string(), MemoryScope::User)
+        );
+        assert_eq!(
+            found[1],
+            (
+                Path::new("../CLAUDE.md").to_string_lossy().to_string(),
+                MemoryScope::Parent
+            )
+        );
+        let tree: Vec<_> = found[2..]
+            .iter()
+            .map(|(p, s)| (p.replace('\\', "/"), *s))
+            .collect();
+        assert_eq!(
+            tree,
+            [
+                (".claude/CLAUDE.md".to_string(), MemoryScope::Project),
+                ("CLAUDE.md".to_string(), MemoryScope::Project),
+                ("src/CLAUDE.local.md".to_string(), MemoryScope::Local),
+                ("src/deep/er/CLAUDE.md".to_string(), MemoryScope::Project),
+            ]
+        );
+
+        let shallow = find_memory_files(&claude_dir, &root, Some(1));
+        assert!(!shallow.iter().any(|f| f.relative_path.contains("deep")));
+        assert!(shallow.iter().any(|f| f.scope == MemoryScope::Local));
+    }
+
+    #[test]
+    fn test_save_paths_must_belong_to_a_known_project() {
+        let home = tempfile::tempdir().unwrap();
+        let claude_dir = home.path().join(".claude");
+        let root = home.path().join("work").join("app");
+        let [encoded, _] = encoded_dir_names(&root);
+        fs::create_dir_all(claude_dir.join("projects").join(encoded)).unwrap();
+
+        let ok = |path: PathBuf| validate_memory_file_path(&claude_dir, &path).is_ok();
+        assert!(ok(claude_dir.join("CLAUDE.md")));
+        assert!(ok(root.join("CLAUDE.md")));
+        assert!(ok(root.join("src/CLAUDE.local.md")));
+        assert!(ok(root.join(".gitignore")));
+        assert!(ok(home.path().join("work/CLAUDE.md")));
+
+        assert!(!ok(claude_dir.join("settings.json")));
+        assert!(!ok(home.path().join("work/notes.txt")));
+        assert!(!ok(home.path().join("other/CLAUDE.md")));
+        assert!(!ok(root.join("../../other/CLAUDE.md")));
+        assert!(!ok(PathBuf::from("CLAUDE.md")));
+    }
+
+    #[test]
+    fn test_saves_are_rejected_when_the_file_changed_since_it_was_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("CLAUDE.md");
+
+        let missing = read_memory_file(&path).unwrap();
+        assert_eq!((missing.content.as_str(), missing.version), ("", None));
+        let MemoryFileSave::Saved { version } =
+            write_memory_file(&path, "one", None, false).unwrap()
+        else {
+            panic!("expected the new file to be saved");
+        };
+        assert!(matches!(
+            write_memory_file(&path, "again", None, false).unwrap(),
+            MemoryFileSave::Conflict { .. }
+        ));
+
+        // Someone else edits the file after it was read
+        fs::write(&path, "edited elsewhere").unwrap();
+        match write_memory_file(&path, "two", Some(&version), false).unwrap() {
+            MemoryFileSave::Conflict { current } => {
+                assert_eq!(current.content, "edited elsewhere");
+                assert!(current.modified.is_some());
+            }
+            saved => panic!("expected a conflict, got {:?}", saved),
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "edited elsewhere");
+
+        let current = read_memory_file(&path).unwrap();
+        assert!(matches!(
+            write_memory_file(&path, "two", current.version.as_deref(), false).unwrap(),
+            MemoryFileSave::Saved { .. }
+        ));
+        assert!(matches!(
+            write_memory_file(&path, "three", Some("stale"), true).unwrap(),
+            MemoryFileSave::Saved { .. }
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "three");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_saves_keep_the_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("CLAUDE.md");
+        fs::write(&path, "one").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        let version = read_memory_file(&path).unwrap().version;
+        write_memory_file(&path, "two", version.as_deref(), false).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+}
@@ -5,3 +5,61 @@ pub mod usage;
 pub mod storage;
 pub mod slash_commands;
 pub mod proxy;
+pub mod schedules;
+pub mod pipelines;
+pub mod transcripts;
+pub mod run_search;
+pub mod pricing;
+pub mod run_compare;
+pub mod agent_metrics;
+pub mod run_artifacts;
+pub mod retention;
+pub mod agent_icons;
+pub mod prompt_templates;
+pub mod project_paths;
+pub mod batches;
+pub mod agent_export;
+pub mod github_cache;
+pub mod reconcile;
+pub mod session_search;
+pub mod session_titles;
+pub mod project_archive;
+pub mod session_previews;
+pub mod session_history;
+pub mod session_pins;
+pub mod claude_md_templates;
+pub mod memory_files;
+pub mod hooks_config;
+pub mod hook_dry_run;
+pub mod hook_shell;
+pub mod claude_settings;
+pub mod settings_history;
+pub mod project_prompts;
+pub mod session_options;
+pub mod claude_sessions;
+pub mod live_usage;
+pub mod prompt_history;
+pub mod file_search;
+pub mod directory_listing;
+pub mod attachments;
+pub mod redaction;
+pub mod claude_version;
+pub mod settings_hierarchy;
+pub mod project_dirs;
+pub mod project_settings;
+pub mod session_autotitle;
+pub mod projects_watcher;
+pub mod claude_env;
+pub mod session_merge;
+pub mod token_estimates;
+pub mod binary_versions;
+pub mod claude_install;
+pub mod binary_health;
+pub mod extra_path;
+pub mod binary_pin;
+pub mod binary_fallback;
+pub mod mcp_export;
+pub mod mcp_toggle;
+pub mod mcp_monitor;
+pub mod mcp_import;
+pub mod mcp_logs;
@@ -0,0 +1,461 @@
+use log::{error, info, warn};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::{
+    execute_agent_with_options, get_session_output, validate_model_override, AgentDb,
+    AgentRunOptions,
+};
+use crate::commands::prompt_templates::{check_agent_variables, variables_from_json, variables_to_json};
+
+/// Placeholder replaced with the previous step's final assistant message
+const PREVIOUS_OUTPUT_PLACEHOLDER: &str = "{{previous_output}}";
+
+/// How often a running pipeline checks whether its current step has finished
+const STEP_POLL_SECS: u64 = 2;
+
+/// A single step of a pipeline
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PipelineStep {
+    pub agent_id: i64,
+    pub task_template: String, // May contain {{previous_output}}
+    #[serde(default)]
+    pub variables: HashMap<String, String>, // Values for the agent's system prompt variables
+}
+
+/// Represents a chain of agents run one after another
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Pipeline {
+    pub id: Option<i64>,
+    pub name: String,
+    pub steps: Vec<PipelineStep>,
+    pub created_at: String,
+}
+
+/// Represents one execution of a pipeline
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PipelineRun {
+    pub id: Option<i64>,
+    pub pipeline_id: i64,
+    pub project_path: String,
+    pub initial_task: String,
+    pub status: String, // 'running', 'completed', 'failed', 'cancelled'
+    pub current_step: i64,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+    pub model: Option<String>, // Overrides every step's agent model when set
+}
+
+/// Column list matching the field order expected by `pipeline_run_from_row`
+const PIPELINE_RUN_COLUMNS: &str = "id, pipeline_id, project_path, initial_task, status, current_step, error, created_at, completed_at, model";
+
+fn pipeline_run_from_row(row: &rusqlite::Row) -> rusqlite::Result<PipelineRun> {
+    Ok(PipelineRun {
+        id: Some(row.get(0)?),
+        pipeline_id: row.get(1)?,
+        project_path: row.get(2)?,
+        initial_task: row.get(3)?,
+        status: row.get(4)?,
+        current_step: row.get(5)?,
+        error: row.get(6)?,
+        created_at: row.get(7)?,
+        completed_at: row.get(8)?,
+        model: row.get(9)?,
+    })
+}
+
+/// Load a pipeline with its steps in order
+fn load_pipeline(conn: &rusqlite::Connection, id: i64) -> Result<Pipeline, String> {
+    let (name, created_at) = conn
+        .query_row(
+            "SELECT name, created_at FROM pipelines WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Pipeline {} not found", id),
+            e => e.to_string(),
+        })?;
+
+    let mut stmt = conn
+        .prepare("SELECT agent_id, task_template, variables FROM pipeline_steps WHERE pipeline_id = ?1 ORDER BY position ASC")
+        .map_err(|e| e.to_string())?;
+    let steps = stmt
+        .query_map(params![id], |row| {
+            Ok(PipelineStep {
+                agent_id: row.get(0)?,
+                task_template: row.get(1)?,
+                variables: variables_from_json(row.get::<_, Option<String>>(2)?.as_deref()),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(Pipeline {
+        id: Some(id),
+        name,
+        steps,
+        created_at,
+    })
+}
+
+/// Create a pipeline from an ordered list of steps
+#[tauri::command]
+pub async fn create_pipeline(
+    db: State<'_, AgentDb>,
+    name: String,
+    steps: Vec<PipelineStep>,
+) -> Result<Pipeline, String> {
+    if name.trim().is_empty() {
+        return Err("Pipeline name cannot be empty".to_string());
+    }
+    if steps.is_empty() {
+        return Err("A pipeline needs at least one step".to_string());
+    }
+
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    for (index, step) in steps.iter().enumerate() {
+        check_agent_variables(&conn, step.agent_id, &step.variables)
+            .map_err(|e| format!("Step {}: {}", index + 1, e))?;
+    }
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("INSERT INTO pipelines (name) VALUES (?1)", params![name.trim()])
+        .map_err(|e| format!("Failed to create pipeline: {}", e))?;
+    let id = tx.last_insert_rowid();
+    for (position, step) in steps.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO pipeline_steps (pipeline_id, position, agent_id, task_template, variables) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                id,
+                position as i64,
+                step.agent_id,
+                step.task_template,
+                variables_to_json(&step.variables)
+            ],
+        )
+        .map_err(|e| format!("Failed to create pipeline step: {}", e))?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    load_pipeline(&conn, id)
+}
+
+/// List all pipelines
+#[tauri::command]
+pub async fn list_pipelines(db: State<'_, AgentDb>) -> Result<Vec<Pipeline>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare("SELECT id FROM pipelines ORDER BY created_at DESC")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    ids.into_iter().map(|id| load_pipeline(&conn, id)).collect()
+}
+
+/// Delete a pipeline and its steps
+#[tauri::command]
+pub async fn delete_pipeline(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "DELETE FROM pipeline_steps WHERE pipeline_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    let deleted = conn
+        .execute("DELETE FROM pipelines WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    if deleted == 0 {
+        return Err(format!("Pipeline {} not found", id));
+    }
+
+    Ok(())
+}
+
+/// Get a pipeline run by ID
+#[tauri::command]
+pub async fn get_pipeline_run(
+    db: State<'_, AgentDb>,
+    id: i64,
+) -> Result<PipelineRun, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.query_row(
+        &format!("SELECT {} FROM pipeline_runs WHERE id = ?1", PIPELINE_RUN_COLUMNS),
+        params![id],
+        pipeline_run_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Start a pipeline in the background and return the pipeline run ID
+///
+/// Steps run one after another; `{{previous_output}}` in a step's task is replaced
+/// with the final assistant message of the step before it (the initial task for the first step).
+/// `model` overrides every step's agent model, and retries of the steps keep it.
+#[tauri::command]
+pub async fn execute_pipeline(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    pipeline_id: i64,
+    project_path: String,
+    initial_task: String,
+    model: Option<String>,
+    allow_unknown_model: Option<bool>,
+) -> Result<i64, String> {
+    let model = model
+        .map(|m| validate_model_override(&m, allow_unknown_model.unwrap_or(false)))
+        .transpose()?;
+
+    let (pipeline, pipeline_run_id) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let pipeline = load_pipeline(&conn, pipeline_id)?;
+        if pipeline.steps.is_empty() {
+            return Err(format!("Pipeline '{}' has no steps", pipeline.name));
+        }
+
+        conn.execute(
+            "INSERT INTO pipeline_runs (pipeline_id, project_path, initial_task, model) VALUES (?1, ?2, ?3, ?4)",
+            params![pipeline_id, project_path, initial_task, model],
+        )
+        .map_err(|e| format!("Failed to create pipeline run: {}", e))?;
+        (pipeline, conn.last_insert_rowid())
+    };
+
+    info!(
+        "Starting pipeline '{}' as pipeline run {}",
+        pipeline.name, pipeline_run_id
+    );
+
+    tauri::async_runtime::spawn(async move {
+        let (status, error) =
+            match run_pipeline_steps(
+                &app,
+                &pipeline,
+                pipeline_run_id,
+                &project_path,
+                initial_task,
+                model.as_deref(),
+            )
+            .await
+            {
+                Ok(()) => ("completed".to_string(), None),
+                Err((status, e)) => {
+                    warn!("Pipeline run {} stopped: {}", pipeline_run_id, e);
+                    (status, Some(e))
+                }
+            };
+
+        {
+            let db = app.state::<AgentDb>();
+            let result = db.0.lock().map_err(|e| e.to_string()).and_then(|conn| {
+                conn.execute(
+                    "UPDATE pipeline_runs SET status = ?1, error = ?2, completed_at = CURRENT_TIMESTAMP WHERE id = ?3",
+                    params![status, error, pipeline_run_id],
+                )
+                .map_err(|e| e.to_string())
+            });
+            if let Err(e) = result {
+                error!("Failed to record pipeline run {} status: {}", pipeline_run_id, e);
+            }
+        }
+
+        let _ = app.emit(&format!("pipeline-complete:{}", pipeline_run_id), &status);
+    });
+
+    Ok(pipeline_run_id)
+}
+
+/// Run every step in order, returning the final pipeline status and reason on failure
+async fn run_pipeline_steps(
+    app: &AppHandle,
+    pipeline: &Pipeline,
+    pipeline_run_id: i64,
+    project_path: &str,
+    initial_task: String,
+    model: Option<&str>,
+) -> Result<(), (String, String)> {
+    let failed = |e: String| ("failed".to_string(), e);
+    let mut previous_output = initial_task;
+
+    for (index, step) in pipeline.steps.iter().enumerate() {
+        let step_number = index + 1;
+        {
+            let db = app.state::<AgentDb>();
+            let conn = db.0.lock().map_err(|e| failed(e.to_string()))?;
+            conn.execute(
+                "UPDATE pipeline_runs SET current_step = ?1 WHERE id = ?2",
+                params![step_number as i64, pipeline_run_id],
+            )
+            .map_err(|e| failed(e.to_string()))?;
+        }
+
+        let task = step
+            .task_template
+            .replace(PREVIOUS_OUTPUT_PLACEHOLDER, &previous_output);
+        let options = AgentRunOptions {
+            pipeline_run_id: Some(pipeline_run_id),
+            variables: step.variables.clone(),
+            model: model.map(|m| m.to_string()),
+            ..Default::default()
+        };
+        let run_id = execute_agent_with_options(
+            app.clone(),
+            step.agent_id,
+            project_path.to_string(),
+            task,
+            options,
+            app.state::<AgentDb>(),
+            app.state::<crate::process::ProcessRegistryState>(),
+        )
+        .await
+        .map_err(|e| failed(format!("Step {} failed to start: {}", step_number, e)))?;
+
+        info!(
+            "Pipeline run {} step {} started agent run {}",
+            pipeline_run_id, step_number, run_id
+        );
+        let _ = app.emit(
+            &format!("pipeline-step-started:{}", pipeline_run_id),
+            serde_json::json!({ "step": step_number, "run_id": run_id }),
+        );
+
+        let status = wait_for_run(app, run_id).await.map_err(failed)?;
+        match status.as_str() {
+            "completed" => {}
+            // Killing or dequeuing a step cancels the rest of the pipeline
+            "cancelled" | "killed" => {
+                return Err((
+                    "cancelled".to_string(),
+                    format!("Step {} (run {}) was cancelled", step_number, run_id),
+                ))
+            }
+            other => {
+                return Err(failed(format!(
+                    "Step {} (run {}) ended with status '{}'",
+                    step_number, run_id, other
+                )))
+            }
+        }
+
+        let output = get_session_output(
+            app.state::<AgentDb>(),
+            app.state::<crate::process::ProcessRegistryState>(),
+            run_id,
+        )
+        .await
+        .map_err(|e| failed(format!("Failed to read output of step {}: {}", step_number, e)))?;
+        previous_output = extract_final_assistant_message(&output).ok_or_else(|| {
+            failed(format!(
+                "Step {} (run {}) produced no assistant message",
+                step_number, run_id
+            ))
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Wait until an agent run reaches a final status and return it
+async fn wait_for_run(app: &AppHandle, run_id: i64) -> Result<String, String> {
+    loop {
+        let status = {
+            let db = app.state::<AgentDb>();
+            let conn = db.0.lock().map_err(|e| e.to_string())?;
+            conn.query_row(
+                "SELECT status FROM agent_runs WHERE id = ?1",
+                params![run_id],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(|e| e.to_string())?
+        };
+
+        if !matches!(status.as_str(), "pending" | "queued" | "running") {
+            return Ok(status);
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(STEP_POLL_SECS)).await;
+    }
+}
+
+/// Extract the final assistant message from a run's JSONL output
+///
+/// Prefers the `result` message of stream-json output, falling back to the text
+/// of the last assistant message (session files have no result message).
+pub fn extract_final_assistant_message(jsonl: &str) -> Option<String> {
+    let mut last_assistant_text = None;
+
+    for line in jsonl.lines() {
+        let json = match serde_json::from_str::<JsonValue>(line) {
+            Ok(json) => json,
+            Err(_) => continue,
+        };
+
+        match json.get("type").and_then(|t| t.as_str()) {
+            Some("result") => {
+                if let Some(result) = json.get("result").and_then(|r| r.as_str()) {
+                    return Some(result.to_string());
+                }
+            }
+            Some("assistant") => {
+                let content = json.get("message").and_then(|m| m.get("content"));
+                let text = match content {
+                    Some(JsonValue::String(text)) => text.clone(),
+                    Some(JsonValue::Array(blocks)) => blocks
+                        .iter()
+                        .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))
+                        .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    _ => String::new(),
+                };
+                if !text.trim().is_empty() {
+                    last_assistant_text = Some(text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    last_assistant_text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_final_message_prefers_result() {
+        let jsonl = r#"{"type":"system","subtype":"init","session_id":"abc"}
+{"type":"assistant","message":{"content":[{"type":"text","text":"Working on it"}]}}
+{"type":"result","subtype":"success","result":"Spec written"}"#;
+        assert_eq!(
+            extract_final_assistant_message(jsonl),
+            Some("Spec written".to_string())
+        );
+    }
+
+    #[test]
+    fn test_final_message_falls_back_to_last_assistant_text() {
+        let jsonl = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"First"}]}}
+{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Read"}]}}
+{"type":"assistant","message":{"content":[{"type":"text","text":"Done"},{"type":"text","text":"Summary"}]}}
+not json"#;
+        assert_eq!(
+            extract_final_assistant_message(jsonl),
+            Some("Done\nSummary".to_string())
+        );
+        assert_eq!(extract_final_assistant_message(""), None);
+    }
+}
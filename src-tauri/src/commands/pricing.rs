@@ -0,0 +1,372 @@
+use log::warn;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use tauri::State;
+
+use super::agents::AgentDb;
+use super::usage::UsageData;
+
+/// `app_settings` key holding the pricing table as JSON
+const PRICING_SETTING_KEY: &str = "model_pricing";
+
+/// Prices in USD per million tokens
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ModelRates {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+/// Characters per token when estimating text that has no recorded usage.
+/// English prose and code both average a little under four.
+pub const DEFAULT_CHARS_PER_TOKEN: f64 = 3.5;
+
+fn default_chars_per_token() -> f64 {
+    DEFAULT_CHARS_PER_TOKEN
+}
+
+/// Rates for every model whose name contains `model_pattern`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ModelPrice {
+    pub model_pattern: String,
+    #[serde(flatten)]
+    pub rates: ModelRates,
+    /// Used to estimate token counts of text for these models
+    #[serde(default = "default_chars_per_token")]
+    pub chars_per_token: f64,
+}
+
+/// Per-model rates, checked in order, plus the rates used for unknown models
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PricingTable {
+    pub models: Vec<ModelPrice>,
+    pub default_rates: ModelRates,
+    #[serde(default = "default_chars_per_token")]
+    pub default_chars_per_token: f64,
+}
+
+const OPUS_4_RATES: ModelRates = ModelRates {
+    input: 15.0,
+    output: 75.0,
+    cache_write: 18.75,
+    cache_read: 1.50,
+};
+
+const SONNET_4_RATES: ModelRates = ModelRates {
+    input: 3.0,
+    output: 15.0,
+    cache_write: 3.75,
+    cache_read: 0.30,
+};
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        Self {
+            models: vec![
+                ModelPrice {
+                    model_pattern: "opus-4".to_string(),
+                    rates: OPUS_4_RATES,
+                    chars_per_token: DEFAULT_CHARS_PER_TOKEN,
+                },
+                ModelPrice {
+                    model_pattern: "sonnet-4".to_string(),
+                    rates: SONNET_4_RATES,
+                    chars_per_token: DEFAULT_CHARS_PER_TOKEN,
+                },
+            ],
+            default_rates: SONNET_4_RATES,
+            default_chars_per_token: DEFAULT_CHARS_PER_TOKEN,
+        }
+    }
+}
+
+impl PricingTable {
+    /// Rates of the first entry whose pattern the model name contains
+    pub fn rates_for(&self, model: &str) -> Option<ModelRates> {
+        let model = model.to_lowercase();
+        self.models
+            .iter()
+            .find(|price| model.contains(&price.model_pattern.to_lowercase()))
+            .map(|price| price.rates)
+    }
+
+    /// Characters per token for estimating a model's token counts
+    pub fn chars_per_token_for(&self, model: &str) -> f64 {
+        let model = model.to_lowercase();
+        self.models
+            .iter()
+            .find(|price| model.contains(&price.model_pattern.to_lowercase()))
+            .map_or(self.default_chars_per_token, |price| price.chars_per_token)
+    }
+
+    /// Cost of the usage, and whether the default rates had to be used
+    pub(crate) fn estimate_cost(&self, model: &str, usage: &UsageData) -> (f64, bool) {
+        match self.rates_for(model) {
+            Some(rates) => (cost_at_rates(&rates, usage), false),
+            None => (cost_at_rates(&self.default_rates, usage), true),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let valid_rates = |rates: &ModelRates| {
+            [rates.input, rates.output, rates.cache_write, rates.cache_read]
+                .iter()
+                .all(|rate| rate.is_finite() && *rate >= 0.0)
+        };
+        let valid_chars_per_token = |chars: f64| chars.is_finite() && chars > 0.0;
+        if !valid_rates(&self.default_rates) {
+            return Err("Default rates must be non-negative numbers".to_string());
+        }
+        if !valid_chars_per_token(self.default_chars_per_token) {
+            return Err("Default characters per token must be a positive number".to_string());
+        }
+        for price in &self.models {
+            if price.model_pattern.trim().is_empty() {
+                return Err("Model pattern cannot be empty".to_string());
+            }
+            if !valid_rates(&price.rates) {
+                return Err(format!(
+                    "Rates for '{}' must be non-negative numbers",
+                    price.model_pattern
+                ));
+            }
+            if !valid_chars_per_token(price.chars_per_token) {
+                return Err(format!(
+                    "Characters per token for '{}' must be a positive number",
+                    price.model_pattern
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Cost of the usage at the given per-million-token rates
+pub(crate) fn cost_at_rates(rates: &ModelRates, usage: &UsageData) -> f64 {
+    let input_tokens = usage.input_tokens.unwrap_or(0) as f64;
+    let output_tokens = usage.output_tokens.unwrap_or(0) as f64;
+    let cache_creation_tokens = usage.cache_creation_input_tokens.unwrap_or(0) as f64;
+    let cache_read_tokens = usage.cache_read_input_tokens.unwrap_or(0) as f64;
+
+    (input_tokens * rates.input
+        + output_tokens * rates.output
+        + cache_creation_tokens * rates.cache_write
+        + cache_read_tokens * rates.cache_read)
+        / 1_000_000.0
+}
+
+fn pricing_lock() -> &'static RwLock<PricingTable> {
+    static PRICING: OnceLock<RwLock<PricingTable>> = OnceLock::new();
+    PRICING.get_or_init(|| RwLock::new(PricingTable::default()))
+}
+
+/// The pricing table currently in effect
+pub fn current_pricing() -> PricingTable {
+    pricing_lock()
+        .read()
+        .map(|table| table.clone())
+        .unwrap_or_default()
+}
+
+/// Current rates for a model, or None when the table doesn't list it
+pub fn rates_for_model(model: &str) -> Option<ModelRates> {
+    pricing_lock()
+        .read()
+        .ok()
+        .and_then(|table| table.rates_for(model))
+}
+
+fn set_current_pricing(table: PricingTable) {
+    if let Ok(mut current) = pricing_lock().write() {
+        *current = table;
+    }
+}
+
+/// Load a saved pricing table, keeping the built-in one if none is stored
+pub fn load_pricing(conn: &Connection) {
+    let saved = conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![PRICING_SETTING_KEY],
+        |row| row.get::<_, String>(0),
+    );
+    if let Ok(json) = saved {
+        match serde_json::from_str::<PricingTable>(&json) {
+            Ok(table) => set_current_pricing(table),
+            Err(e) => warn!("Ignoring invalid saved model pricing: {}", e),
+        }
+    }
+}
+
+/// Token and cost totals aggregated from stream-json or session JSONL lines
+pub struct UsageTally {
+    pricing: PricingTable,
+    // Streamed assistant messages repeat their usage once per content block,
+    // so usage is keyed by message id: (input tokens, output tokens, cost, estimated)
+    usage_by_message: HashMap<String, (i64, i64, f64, bool)>,
+    reported_cost_usd: Option<f64>,
+}
+
+impl UsageTally {
+    pub fn new() -> Self {
+        Self {
+            pricing: current_pricing(),
+            usage_by_message: HashMap::new(),
+            reported_cost_usd: None,
+        }
+    }
+
+    /// Record the usage carried by one line, pricing it as `default_model`
+    /// when the message doesn't name its model
+    pub fn record(&mut self, json: &JsonValue, default_model: &str) {
+        if json.get("type").and_then(|t| t.as_str()) == Some("result") {
+            // The result message repeats the session totals, only its cost is new
+            if let Some(cost) = json.get("total_cost_usd").and_then(|c| c.as_f64()) {
+                self.reported_cost_usd = Some(cost);
+            }
+            return;
+        }
+
+        let message = match json.get("message") {
+            Some(message) => message,
+            None => return,
+        };
+        let usage = match message
+            .get("usage")
+            .and_then(|u| serde_json::from_value::<UsageData>(u.clone()).ok())
+        {
+            Some(usage) => usage,
+            None => return,
+        };
+
+        let model = message
+            .get("model")
+            .and_then(|m| m.as_str())
+            .unwrap_or(default_model);
+        let (cost, estimated) = self.pricing.estimate_cost(model, &usage);
+        let key = message
+            .get("id")
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| format!("#{}", self.usage_by_message.len()));
+        self.usage_by_message.insert(
+            key,
+            (
+                usage.input_tokens.unwrap_or(0) as i64,
+                usage.output_tokens.unwrap_or(0) as i64,
+                cost,
+                estimated,
+            ),
+        );
+    }
+
+    /// Record every line of a JSONL document
+    pub fn record_jsonl(&mut self, jsonl: &str, default_model: &str) {
+        for line in jsonl.lines() {
+            if let Ok(json) = serde_json::from_str::<JsonValue>(line) {
+                self.record(&json, default_model);
+            }
+        }
+    }
+
+    pub fn has_usage(&self) -> bool {
+        !self.usage_by_message.is_empty() || self.reported_cost_usd.is_some()
+    }
+
+    pub fn input_tokens(&self) -> i64 {
+        self.usage_by_message.values().map(|u| u.0).sum()
+    }
+
+    pub fn output_tokens(&self) -> i64 {
+        self.usage_by_message.values().map(|u| u.1).sum()
+    }
+
+    fn computed_cost_usd(&self) -> f64 {
+        self.usage_by_message.values().map(|u| u.2).sum()
+    }
+
+    pub fn cost_usd(&self) -> f64 {
+        self.computed_cost_usd()
+            .max(self.reported_cost_usd.unwrap_or(0.0))
+    }
+
+    /// Whether the cost relies on default rates rather than known model prices
+    pub fn cost_estimated(&self) -> bool {
+        let reported_covers_cost = self
+            .reported_cost_usd
+            .is_some_and(|reported| reported >= self.computed_cost_usd());
+        !reported_covers_cost && self.usage_by_message.values().any(|u| u.3)
+    }
+}
+
+impl Default for UsageTally {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get the model pricing table used for cost estimates
+#[tauri::command]
+pub async fn get_model_pricing() -> Result<PricingTable, String> {
+    Ok(current_pricing())
+}
+
+/// Replace the model pricing table
+#[tauri::command]
+pub async fn set_model_pricing(
+    db: State<'_, AgentDb>,
+    pricing: PricingTable,
+) -> Result<PricingTable, String> {
+    pricing.validate()?;
+    let json = serde_json::to_string(&pricing).map_err(|e| e.to_string())?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = ?2",
+        params![PRICING_SETTING_KEY, json],
+    )
+    .map_err(|e| e.to_string())?;
+
+    set_current_pricing(pricing.clone());
+    Ok(pricing)
+}
+
+/// Restore the built-in model pricing table
+#[tauri::command]
+pub async fn reset_model_pricing(db: State<'_, AgentDb>) -> Result<PricingTable, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM app_settings WHERE key = ?1",
+        params![PRICING_SETTING_KEY],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let pricing = PricingTable::default();
+    set_current_pricing(pricing.clone());
+    Ok(pricing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tally_prices_unknown_models_at_default_rates() {
+        let jsonl = r#"{"type":"assistant","message":{"id":"m1","model":"claude-sonnet-4-20250514","usage":{"input_tokens":1000000,"output_tokens":0}}}
+{"type":"assistant","message":{"id":"m1","model":"claude-sonnet-4-20250514","usage":{"input_tokens":1000000,"output_tokens":0}}}"#;
+        let mut tally = UsageTally::new();
+        tally.record_jsonl(jsonl, "sonnet");
+        assert_eq!(tally.input_tokens(), 1_000_000);
+        assert!((tally.cost_usd() - 3.0).abs() < 1e-9);
+        assert!(!tally.cost_estimated());
+
+        let unknown = r#"{"type":"assistant","message":{"id":"m2","usage":{"output_tokens":1000000}}}"#;
+        tally.record_jsonl(unknown, "some-new-model");
+        assert!((tally.cost_usd() - 18.0).abs() < 1e-9);
+        assert!(tally.cost_estimated());
+    }
+}
@@ -0,0 +1,71 @@
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Ids of the projects hidden from the project list
+pub fn load_archived_projects(conn: &Connection) -> Result<HashSet<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT project_id FROM archived_projects")
+        .map_err(|e| e.to_string())?;
+    let archived = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(archived)
+}
+
+pub fn save_project_archived(
+    conn: &Connection,
+    project_id: &str,
+    archived: bool,
+) -> Result<(), String> {
+    let result = if archived {
+        conn.execute(
+            "INSERT OR IGNORE INTO archived_projects (project_id) VALUES (?1)",
+            params![project_id],
+        )
+    } else {
+        conn.execute(
+            "DELETE FROM archived_projects WHERE project_id = ?1",
+            params![project_id],
+        )
+    };
+    result.map_err(|e| format!("Failed to save project archive state: {}", e))?;
+    Ok(())
+}
+
+/// Archive or unarchive a project. This is an app preference only; nothing
+/// under `~/.claude` is changed.
+#[tauri::command]
+pub async fn set_project_archived(
+    db: State<'_, AgentDb>,
+    project_id: String,
+    archived: bool,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_project_archived(&conn, &project_id, archived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archiving_is_idempotent_and_reversible() {
+        let conn = crate::db::test_connection();
+
+        save_project_archived(&conn, "-tmp-experiment", true).unwrap();
+        save_project_archived(&conn, "-tmp-experiment", true).unwrap();
+        save_project_archived(&conn, "-home-me-app", false).unwrap();
+        assert_eq!(
+            load_archived_projects(&conn).unwrap(),
+            HashSet::from(["-tmp-experiment".to_string()])
+        );
+
+        save_project_archived(&conn, "-tmp-experiment", false).unwrap();
+        assert!(load_archived_projects(&conn).unwrap().is_empty());
+    }
+}
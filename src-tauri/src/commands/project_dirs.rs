@@ -0,0 +1,271 @@
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use super::claude::get_claude_dir;
+use crate::commands::agents::AgentDb;
+
+/// The directory name Claude Code gives a project under `~/.claude/projects`:
+/// everything but ASCII letters and digits becomes `-`. Claude counts UTF-16
+/// units, so a character outside the BMP becomes two dashes.
+pub fn encode_project_path(project_path: &str) -> String {
+    let mut encoded = String::with_capacity(project_path.len());
+    for c in project_path.chars() {
+        if c.is_ascii_alphanumeric() {
+            encoded.push(c);
+        } else {
+            encoded.push_str(&"-".repeat(c.len_utf16()));
+        }
+    }
+    encoded
+}
+
+/// The name older Claude Code releases used, which only replaced separators
+fn legacy_encode_project_path(project_path: &str) -> String {
+    project_path.replace(['/', '\\'], "-")
+}
+
+/// The directory a session ran in: the `cwd` of its first entry that has
+/// one. Summaries and snapshots at the top of a file don't.
+pub fn session_cwd<R: BufRead>(reader: R) -> Option<String> {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(&line).ok())
+        .find_map(|entry| {
+            entry
+                .get("cwd")
+                .and_then(|cwd| cwd.as_str())
+                .map(str::to_string)
+        })
+}
+
+fn read_project_path(project_dir: &Path) -> Option<String> {
+    let dir_name = project_dir.file_name()?.to_str()?;
+    let mut sessions: Vec<(SystemTime, PathBuf)> = fs::read_dir(project_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+        .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+        .collect();
+    sessions.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+
+    // A session can have been moved over from another project, so prefer a
+    // path that encodes to this directory's name
+    let mut first = None;
+    for (_, path) in sessions {
+        let Some(cwd) = fs::File::open(&path)
+            .ok()
+            .and_then(|file| session_cwd(BufReader::new(file)))
+        else {
+            continue;
+        };
+        if encode_project_path(&cwd) == dir_name || legacy_encode_project_path(&cwd) == dir_name {
+            return Some(cwd);
+        }
+        first.get_or_insert(cwd);
+    }
+    first
+}
+
+/// The real path of a project directory under `~/.claude/projects`, read
+/// from its sessions since the directory name can't be decoded: a dash in it
+/// may have been a dash, a separator or a space. Cached until the directory
+/// changes.
+pub fn resolve_project_path(project_dir: &Path) -> Result<String, String> {
+    static RESOLVED: OnceLock<Mutex<HashMap<PathBuf, (SystemTime, String)>>> = OnceLock::new();
+    let cache = RESOLVED.get_or_init(|| Mutex::new(HashMap::new()));
+    let modified = fs::metadata(project_dir)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to read project directory: {}", e))?;
+    if let Some((cached_at, path)) = cache.lock().map_err(|e| e.to_string())?.get(project_dir) {
+        if *cached_at == modified {
+            return Ok(path.clone());
+        }
+    }
+
+    let path = read_project_path(project_dir)
+        .ok_or_else(|| "Could not determine project path from session files".to_string())?;
+    cache
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(project_dir.to_path_buf(), (modified, path.clone()));
+    Ok(path)
+}
+
+/// Paths of the projects opcode created directories for, by project id.
+/// Claude's encoding can't be reversed, so these are kept for projects that
+/// have no session yet to read the path from.
+pub fn load_project_dirs(conn: &Connection) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT project_id, project_path FROM project_dirs")
+        .map_err(|e| e.to_string())?;
+    let dirs = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(dirs)
+}
+
+/// Make sure `project_path` has a directory under `projects_dir`, reusing one
+/// Claude created under either encoding, and remember the path it stands for.
+/// Returns the project id.
+pub fn ensure_project_dir(
+    conn: &Connection,
+    projects_dir: &Path,
+    project_path: &str,
+) -> Result<String, String> {
+    let legacy_id = legacy_encode_project_path(project_path);
+    let project_id = if projects_dir.join(&legacy_id).is_dir() {
+        legacy_id
+    } else {
+        encode_project_path(project_path)
+    };
+    let project_dir = projects_dir.join(&project_id);
+    if !project_dir.is_dir() {
+        fs::create_dir_all(&project_dir)
+            .map_err(|e| format!("Failed to create project directory: {}", e))?;
+        log::info!("Created project directory {:?}", project_dir);
+    }
+
+    conn.execute(
+        "INSERT INTO project_dirs (project_id, project_path) VALUES (?1, ?2)
+         ON CONFLICT(project_id) DO UPDATE SET project_path = ?2",
+        params![project_id, project_path],
+    )
+    .map_err(|e| format!("Failed to record project path: {}", e))?;
+    Ok(project_id)
+}
+
+/// Make sure `project_path`, an existing directory, is a project Claude and
+/// `list_projects` know about before Claude writes its first session
+pub fn ensure_project(db: &AgentDb, project_path: &str) -> Result<String, String> {
+    if !Path::new(project_path).is_dir() {
+        return Err(format!("Project path is not a directory: {}", project_path));
+    }
+    let projects_dir = get_claude_dir()
+        .map_err(|e| e.to_string())?
+        .join("projects");
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    ensure_project_dir(&conn, &projects_dir, project_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoding_matches_the_cli() {
+        // Directory names the Claude CLI created for these paths
+        let cases = [
+            (
+                "/Users/mufeedvh/dev/jsonl-viewer",
+                "-Users-mufeedvh-dev-jsonl-viewer",
+            ),
+            ("/home/me/My Project", "-home-me-My-Project"),
+            ("/home/me/.dotfiles", "-home-me--dotfiles"),
+            ("/home/me/café", "-home-me-caf-"),
+            ("/home/me/notes📁", "-home-me-notes--"),
+            (r"C:\Users\me\repo", "C--Users-me-repo"),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(encode_project_path(path), expected, "{}", path);
+        }
+    }
+
+    fn write_session(dir: &Path, name: &str, lines: &[&str]) {
+        fs::write(dir.join(name), lines.join("\n")).unwrap();
+    }
+
+    #[test]
+    fn test_project_paths_are_read_from_sessions() {
+        let projects = tempfile::tempdir().unwrap();
+        let cases = [
+            "/home/me/work/foo-bar",
+            "/home/me/My Project",
+            "/home/me/projets/Été-2024",
+        ];
+        for path in cases {
+            let project_dir = projects.path().join(encode_project_path(path));
+            fs::create_dir(&project_dir).unwrap();
+            let entry = serde_json::json!({ "type": "user", "cwd": path }).to_string();
+            write_session(
+                &project_dir,
+                "a.jsonl",
+                &[
+                    r#"{"type":"summary","summary":"Earlier"}"#,
+                    "not json",
+                    &entry,
+                ],
+            );
+            assert_eq!(resolve_project_path(&project_dir).unwrap(), path);
+            // Served from the cache while the directory is unchanged
+            assert_eq!(resolve_project_path(&project_dir).unwrap(), path);
+        }
+
+        let empty = projects.path().join("-home-me-empty");
+        fs::create_dir(&empty).unwrap();
+        assert!(resolve_project_path(&empty).is_err());
+    }
+
+    #[test]
+    fn test_a_path_matching_the_directory_name_is_preferred() {
+        let projects = tempfile::tempdir().unwrap();
+        let project_dir = projects.path().join("-home-me-foo-bar");
+        fs::create_dir(&project_dir).unwrap();
+        write_session(&project_dir, "moved.jsonl", &[r#"{"cwd":"/elsewhere"}"#]);
+        write_session(
+            &project_dir,
+            "own.jsonl",
+            &[r#"{"cwd":"/home/me/foo-bar"}"#],
+        );
+        write_session(&project_dir, "none.jsonl", &[r#"{"type":"summary"}"#]);
+        assert_eq!(
+            read_project_path(&project_dir).as_deref(),
+            Some("/home/me/foo-bar")
+        );
+
+        fs::remove_file(project_dir.join("own.jsonl")).unwrap();
+        assert_eq!(
+            read_project_path(&project_dir).as_deref(),
+            Some("/elsewhere")
+        );
+    }
+
+    #[test]
+    fn test_ensured_projects_round_trip_and_reuse_existing_dirs() {
+        let conn = crate::db::test_connection();
+        let projects = tempfile::tempdir().unwrap();
+        let projects_dir = projects.path();
+        fs::create_dir(projects_dir.join("-srv-old.app")).unwrap();
+
+        let paths = [
+            "/home/me/my-app",
+            "/home/me/two words",
+            "/home/me/projets/Éte",
+            "/srv/old.app",
+        ];
+        let ids: Vec<String> = paths
+            .iter()
+            .map(|path| ensure_project_dir(&conn, projects_dir, path).unwrap())
+            .collect();
+        assert_eq!(ids[0], "-home-me-my-app");
+        assert_eq!(ids[3], "-srv-old.app");
+        assert!(ids.iter().all(|id| projects_dir.join(id).is_dir()));
+
+        let dirs = load_project_dirs(&conn).unwrap();
+        for (path, id) in paths.iter().zip(&ids) {
+            assert_eq!(dirs[id], *path);
+        }
+        assert_eq!(
+            ensure_project_dir(&conn, projects_dir, paths[0]).unwrap(),
+            ids[0]
+        );
+    }
+}
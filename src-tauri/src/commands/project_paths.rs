@@ -0,0 +1,253 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Paths kept per agent in the recent list
+const MAX_RECENT_PATHS_PER_AGENT: i64 = 50;
+
+/// Entries returned by `list_recent_project_paths` when no limit is given
+const DEFAULT_RECENT_PATHS_LIMIT: usize = 10;
+
+/// A project path an agent was executed in
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecentProjectPath {
+    pub path: String,
+    pub last_used_at: String,
+    pub use_count: i64,
+    pub exists: bool, // False once the directory is gone from disk
+}
+
+/// What `validate_project_path` found out about a directory
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ProjectPathValidation {
+    pub path: String,
+    pub exists: bool,
+    pub is_directory: bool,
+    pub readable: bool,
+    pub has_claude_dir: bool,  // Contains a `.claude/` directory
+    pub known_to_claude: bool,  // Has sessions under `~/.claude/projects`
+    pub hints: Vec<String>,
+}
+
+impl ProjectPathValidation {
+    /// Whether an agent can be executed in the path
+    pub fn is_usable(&self) -> bool {
+        self.exists && self.is_directory && self.readable
+    }
+}
+
+/// Remember that an agent was executed in `project_path`
+pub fn record_project_path(conn: &Connection, agent_id: i64, project_path: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO recent_project_paths (agent_id, project_path) VALUES (?1, ?2)
+         ON CONFLICT(agent_id, project_path) DO UPDATE SET use_count = use_count + 1, last_used_at = CURRENT_TIMESTAMP",
+        params![agent_id, project_path],
+    )
+    .map_err(|e| format!("Failed to record project path: {}", e))?;
+
+    // Keep only the agent's most recently used paths
+    conn.execute(
+        "DELETE FROM recent_project_paths WHERE agent_id = ?1 AND project_path NOT IN (
+             SELECT project_path FROM recent_project_paths WHERE agent_id = ?1
+             ORDER BY last_used_at DESC LIMIT ?2
+         )",
+        params![agent_id, MAX_RECENT_PATHS_PER_AGENT],
+    )
+    .map_err(|e| format!("Failed to trim recent project paths: {}", e))?;
+    Ok(())
+}
+
+fn recent_project_paths(
+    conn: &Connection,
+    agent_id: Option<i64>,
+    limit: usize,
+) -> Result<Vec<RecentProjectPath>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT project_path, MAX(last_used_at), SUM(use_count) FROM recent_project_paths
+             WHERE ?1 IS NULL OR agent_id = ?1
+             GROUP BY project_path ORDER BY MAX(last_used_at) DESC, project_path ASC LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let paths = stmt
+        .query_map(params![agent_id, limit as i64], |row| {
+            let path: String = row.get(0)?;
+            Ok(RecentProjectPath {
+                exists: Path::new(&path).is_dir(),
+                path,
+                last_used_at: row.get(1)?,
+                use_count: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(paths)
+}
+
+/// Recently used project paths, newest first, for one agent or across all agents.
+/// Paths that no longer exist are included with `exists` set to false.
+#[tauri::command]
+pub async fn list_recent_project_paths(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+    limit: Option<usize>,
+) -> Result<Vec<RecentProjectPath>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    recent_project_paths(&conn, agent_id, limit.unwrap_or(DEFAULT_RECENT_PATHS_LIMIT))
+}
+
+/// Whether `~/.claude/projects` has a session directory for the project.
+/// Claude names them after the path with separators replaced by dashes.
+fn has_claude_sessions(claude_projects_dir: &Path, project_path: &str) -> bool {
+    let slash_encoded = project_path.replace(['/', '\\'], "-");
+    let fully_encoded: String = project_path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    [slash_encoded, fully_encoded]
+        .iter()
+        .any(|name| claude_projects_dir.join(name).is_dir())
+}
+
+fn inspect_project_path(path: &str, claude_projects_dir: Option<&Path>) -> ProjectPathValidation {
+    let mut validation = ProjectPathValidation {
+        path: path.to_string(),
+        ..Default::default()
+    };
+
+    let dir = Path::new(path);
+    let metadata = match std::fs::metadata(dir) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            validation.exists = true;
+            validation.hints.push("Permission denied when accessing the path".to_string());
+            return validation;
+        }
+        Err(_) => {
+            validation.hints.push("The path does not exist".to_string());
+            return validation;
+        }
+    };
+    validation.exists = true;
+    validation.is_directory = metadata.is_dir();
+    if !validation.is_directory {
+        validation.hints.push("The path is a file, not a directory".to_string());
+        return validation;
+    }
+
+    validation.readable = std::fs::read_dir(dir).is_ok();
+    if !validation.readable {
+        validation.hints.push("The directory can't be read".to_string());
+    }
+
+    let canonical = std::fs::canonicalize(dir)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    validation.has_claude_dir = dir.join(".claude").is_dir();
+    validation.known_to_claude = claude_projects_dir.is_some_and(|projects| {
+        has_claude_sessions(projects, path) || has_claude_sessions(projects, &canonical)
+    });
+    if !validation.has_claude_dir && !validation.known_to_claude {
+        validation.hints.push(
+            "Not a Claude project yet: it has no .claude directory and no previous Claude sessions"
+                .to_string(),
+        );
+    }
+    validation.path = canonical;
+
+    validation
+}
+
+/// Reject a project path an agent can't be executed in, explaining why
+pub fn ensure_usable_project_path(path: &str) -> Result<(), String> {
+    let validation = inspect_project_path(path, None);
+    if validation.is_usable() {
+        return Ok(());
+    }
+    Err(format!(
+        "Project path {} is not usable: {}",
+        path,
+        validation.hints.join("; ")
+    ))
+}
+
+/// Check that a directory exists and is readable, and whether Claude has used it before
+#[tauri::command]
+pub async fn validate_project_path(path: String) -> Result<ProjectPathValidation, String> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Err("Project path cannot be empty".to_string());
+    }
+    let claude_projects_dir = dirs::home_dir().map(|home| home.join(".claude").join("projects"));
+    Ok(inspect_project_path(path, claude_projects_dir.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unusable_project_paths_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        std::fs::write(&file, "x").unwrap();
+
+        assert!(ensure_usable_project_path(&dir.path().to_string_lossy()).is_ok());
+        let err = ensure_usable_project_path(&file.to_string_lossy()).unwrap_err();
+        assert!(err.contains("not a directory"), "{}", err);
+        let err = ensure_usable_project_path("/definitely/not/here").unwrap_err();
+        assert!(err.contains("does not exist"), "{}", err);
+    }
+
+    #[test]
+    fn test_recent_paths_dedupe_across_agents_and_flag_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().to_string_lossy().into_owned();
+        let conn = crate::db::test_connection();
+        crate::db::seed_agent(&conn, "a");
+        crate::db::seed_agent(&conn, "b");
+
+        record_project_path(&conn, 1, "/gone/project").unwrap();
+        record_project_path(&conn, 1, &existing).unwrap();
+        record_project_path(&conn, 2, &existing).unwrap();
+        conn.execute(
+            "UPDATE recent_project_paths SET last_used_at = '2020-01-01 00:00:00' WHERE project_path = '/gone/project'",
+            [],
+        )
+        .unwrap();
+
+        let all = recent_project_paths(&conn, None, 10).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!((all[0].path.as_str(), all[0].use_count, all[0].exists), (existing.as_str(), 2, true));
+        assert_eq!((all[1].path.as_str(), all[1].exists), ("/gone/project", false));
+
+        assert_eq!(recent_project_paths(&conn, Some(2), 10).unwrap().len(), 1);
+        assert_eq!(recent_project_paths(&conn, None, 1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_validation_reports_claude_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("app");
+        let projects_dir = dir.path().join("claude-projects");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&projects_dir).unwrap();
+        let project_path = std::fs::canonicalize(&project).unwrap().to_string_lossy().into_owned();
+
+        let validation = inspect_project_path(&project_path, Some(&projects_dir));
+        assert!(validation.is_usable());
+        assert!(!validation.known_to_claude && !validation.has_claude_dir);
+        assert_eq!(validation.hints.len(), 1);
+
+        std::fs::create_dir(projects_dir.join(project_path.replace('/', "-"))).unwrap();
+        let validation = inspect_project_path(&project_path, Some(&projects_dir));
+        assert!(validation.known_to_claude && validation.hints.is_empty());
+
+        let missing = inspect_project_path(&format!("{}/nope", project_path), None);
+        assert!(!missing.exists && !missing.is_usable());
+    }
+}
@@ -0,0 +1,123 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// The key a project's prompt is stored under: its canonical path, so the
+/// same directory reached through a symlink or `..` shares one prompt
+pub fn project_key(project_path: &str) -> Result<String, String> {
+    let canonical = Path::new(project_path)
+        .canonicalize()
+        .map_err(|e| format!("Project directory not found: {} ({})", project_path, e))?;
+    Ok(canonical.to_string_lossy().into_owned())
+}
+
+pub fn load_project_system_prompt(
+    conn: &Connection,
+    project_key: &str,
+) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT prompt FROM project_system_prompts WHERE project_path = ?1",
+        params![project_key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Store a project's prompt; an empty or blank prompt clears it
+pub fn store_project_system_prompt(
+    conn: &Connection,
+    project_key: &str,
+    prompt: &str,
+) -> Result<(), String> {
+    let result = if prompt.trim().is_empty() {
+        conn.execute(
+            "DELETE FROM project_system_prompts WHERE project_path = ?1",
+            params![project_key],
+        )
+    } else {
+        conn.execute(
+            "INSERT INTO project_system_prompts (project_path, prompt) VALUES (?1, ?2)
+             ON CONFLICT(project_path) DO UPDATE SET prompt = ?2, updated_at = CURRENT_TIMESTAMP",
+            params![project_key, prompt],
+        )
+    };
+    result.map_err(|e| format!("Failed to save project system prompt: {}", e))?;
+    Ok(())
+}
+
+/// The prompt to append for a session in `project_path`, if one is set.
+/// Lookup failures are logged so they never block starting a session.
+pub fn project_system_prompt_for(db: &AgentDb, project_path: &str) -> Option<String> {
+    let key = project_key(project_path).ok()?;
+    let result = db
+        .0
+        .lock()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| load_project_system_prompt(&conn, &key));
+    result.unwrap_or_else(|e| {
+        log::warn!("Failed to load project system prompt: {}", e);
+        None
+    })
+}
+
+/// Get the system prompt appended to Claude sessions in a project, or an
+/// empty string when none is set
+#[tauri::command]
+pub async fn get_project_system_prompt(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<String, String> {
+    let key = project_key(&project_path)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Ok(load_project_system_prompt(&conn, &key)?.unwrap_or_default())
+}
+
+/// Set the system prompt appended to Claude sessions in a project. An empty
+/// prompt clears it.
+#[tauri::command]
+pub async fn save_project_system_prompt(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    prompt: String,
+) -> Result<(), String> {
+    let key = project_key(&project_path)?;
+    log::info!("Saving system prompt for project {}", key);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    store_project_system_prompt(&conn, &key, &prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompts_are_keyed_by_canonical_path_and_cleared_by_empty() {
+        let conn = crate::db::test_connection();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("app")).unwrap();
+
+        let key = project_key(&dir.path().join("app").to_string_lossy()).unwrap();
+        let dotted = dir.path().join("app").join("..").join("app");
+        assert_eq!(project_key(&dotted.to_string_lossy()).unwrap(), key);
+        assert!(project_key(&dir.path().join("missing").to_string_lossy()).is_err());
+
+        store_project_system_prompt(&conn, &key, "Answer in French").unwrap();
+        store_project_system_prompt(&conn, &key, "Answer in German").unwrap();
+        assert_eq!(
+            load_project_system_prompt(&conn, &key).unwrap().as_deref(),
+            Some("Answer in German")
+        );
+
+        let db = AgentDb(std::sync::Mutex::new(conn));
+        assert_eq!(
+            project_system_prompt_for(&db, &dotted.to_string_lossy()).as_deref(),
+            Some("Answer in German")
+        );
+        let conn = db.0.lock().unwrap();
+        store_project_system_prompt(&conn, &key, "  ").unwrap();
+        assert_eq!(load_project_system_prompt(&conn, &key).unwrap(), None);
+    }
+}
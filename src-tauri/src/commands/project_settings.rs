@@ -0,0 +1,255 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use super::claude_version::probe_claude_version;
+use super::project_prompts::project_key;
+use crate::commands::agents::AgentDb;
+
+/// Model used when neither the caller nor the project names one
+pub const FALLBACK_MODEL: &str = "sonnet";
+
+/// Preferences applied to sessions in one project. Fields must have defaults
+/// so settings stored by older versions keep loading as new ones are added.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProjectSettings {
+    /// Model for sessions started without one
+    pub default_model: Option<String>,
+    /// Claude binary used instead of the global one, e.g. a pinned older CLI.
+    /// Only changed through `set_project_claude_binary`, which checks it runs.
+    pub claude_binary: Option<String>,
+}
+
+impl ProjectSettings {
+    /// Blank values mean "not set"
+    fn normalized(mut self) -> Self {
+        self.default_model = self
+            .default_model
+            .map(|model| model.trim().to_string())
+            .filter(|model| !model.is_empty());
+        self.claude_binary = self
+            .claude_binary
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty());
+        self
+    }
+}
+
+/// A project's settings, or the defaults when none are stored or they can't
+/// be read
+pub fn load_project_settings(
+    conn: &Connection,
+    project_key: &str,
+) -> Result<ProjectSettings, String> {
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT settings FROM project_settings WHERE project_path = ?1",
+            params![project_key],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(stored
+        .and_then(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| log::warn!("Ignoring invalid settings of {}: {}", project_key, e))
+                .ok()
+        })
+        .unwrap_or_default())
+}
+
+/// Store a project's settings; all-default settings are removed
+pub fn store_project_settings(
+    conn: &Connection,
+    project_key: &str,
+    settings: &ProjectSettings,
+) -> Result<(), String> {
+    let result = if *settings == ProjectSettings::default() {
+        conn.execute(
+            "DELETE FROM project_settings WHERE project_path = ?1",
+            params![project_key],
+        )
+    } else {
+        let json = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO project_settings (project_path, settings) VALUES (?1, ?2)
+             ON CONFLICT(project_path) DO UPDATE SET settings = ?2, updated_at = CURRENT_TIMESTAMP",
+            params![project_key, json],
+        )
+    };
+    result.map_err(|e| format!("Failed to save project settings: {}", e))?;
+    Ok(())
+}
+
+/// The model to start a session in `project_path` with: `model` when given,
+/// which becomes the project's default when `remember` is set, otherwise the
+/// project's default. Settings failures are logged, never blocking a session.
+pub fn resolve_model(
+    db: &AgentDb,
+    project_path: &str,
+    model: Option<String>,
+    remember: bool,
+) -> String {
+    let model = model
+        .map(|model| model.trim().to_string())
+        .filter(|model| !model.is_empty());
+    let result = project_key(project_path).and_then(|key| {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut settings = load_project_settings(&conn, &key)?;
+        match &model {
+            Some(model) if remember && settings.default_model.as_ref() != Some(model) => {
+                settings.default_model = Some(model.clone());
+                store_project_settings(&conn, &key, &settings)?;
+            }
+            _ => {}
+        }
+        Ok(settings.default_model)
+    });
+    let default_model = result.unwrap_or_else(|e| {
+        log::warn!("Failed to apply project settings: {}", e);
+        None
+    });
+    model
+        .or(default_model)
+        .unwrap_or_else(|| FALLBACK_MODEL.to_string())
+}
+
+/// The Claude binary to run in `project_path`: the project's override when
+/// set, otherwise the global one. An override that no longer exists is an
+/// error rather than quietly falling back to another version.
+pub fn resolve_claude_binary(
+    app: &AppHandle,
+    db: &AgentDb,
+    project_path: &str,
+) -> Result<String, String> {
+    let result = project_key(project_path).and_then(|key| {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        Ok(load_project_settings(&conn, &key)?.claude_binary)
+    });
+    let claude_binary = result.unwrap_or_else(|e| {
+        log::warn!("Failed to read the project's Claude binary: {}", e);
+        None
+    });
+    match claude_binary {
+        Some(path) => match crate::claude_binary::binary_file(&path) {
+            Some(file) if file.is_file() => Ok(file.to_string_lossy().into_owned()),
+            _ => Err(format!(
+                "The Claude binary set for this project no longer exists: {}",
+                path
+            )),
+        },
+        None => crate::claude_binary::find_claude_binary(app),
+    }
+}
+
+/// Get the preferences applied to sessions in a project
+#[tauri::command]
+pub async fn get_project_settings(
+    db: State<'_, AgentDb>,
+    project_path: String,
+) -> Result<ProjectSettings, String> {
+    let key = project_key(&project_path)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_project_settings(&conn, &key)
+}
+
+/// Replace the preferences applied to sessions in a project, returning them
+/// as stored
+#[tauri::command]
+pub async fn set_project_settings(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    settings: ProjectSettings,
+) -> Result<ProjectSettings, String> {
+    let key = project_key(&project_path)?;
+    let mut settings = settings.normalized();
+    log::info!("Saving settings for project {}", key);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    settings.claude_binary = load_project_settings(&conn, &key)?.claude_binary;
+    store_project_settings(&conn, &key, &settings)?;
+    Ok(settings)
+}
+
+/// Run a project's sessions and agents with `binary_path` instead of the
+/// global Claude binary, or with the global one again when it's null.
+/// `"bundled"` selects the binary shipped with opcode. The binary must report
+/// its version within a timeout to be accepted.
+#[tauri::command]
+pub async fn set_project_claude_binary(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    binary_path: Option<String>,
+) -> Result<ProjectSettings, String> {
+    let key = project_key(&project_path)?;
+    let binary_path = binary_path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty());
+    if let Some(path) = &binary_path {
+        let file = crate::claude_binary::binary_file(path)
+            .filter(|file| file.is_file())
+            .ok_or_else(|| format!("Claude binary not found: {}", path))?;
+        let version = probe_claude_version(&file.to_string_lossy()).await?;
+        log::info!(
+            "Using Claude {} at {} for project {}",
+            version.raw,
+            path,
+            key
+        );
+    }
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut settings = load_project_settings(&conn, &key)?;
+    settings.claude_binary = binary_path;
+    store_project_settings(&conn, &key, &settings)?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_explicit_model_wins_and_can_become_the_default() {
+        let conn = crate::db::test_connection();
+        let db = AgentDb(Mutex::new(conn));
+        let project = tempfile::tempdir().unwrap();
+        let path = project.path().to_string_lossy().into_owned();
+
+        assert_eq!(resolve_model(&db, &path, None, false), FALLBACK_MODEL);
+        assert_eq!(
+            resolve_model(&db, &path, Some("opus".into()), false),
+            "opus"
+        );
+        assert_eq!(resolve_model(&db, &path, None, false), FALLBACK_MODEL);
+
+        assert_eq!(resolve_model(&db, &path, Some("opus".into()), true), "opus");
+        assert_eq!(resolve_model(&db, &path, None, false), "opus");
+        assert_eq!(
+            resolve_model(&db, &path, Some("haiku".into()), false),
+            "haiku"
+        );
+        assert_eq!(resolve_model(&db, &path, Some(" ".into()), false), "opus");
+
+        let key = project_key(&path).unwrap();
+        let conn = db.0.lock().unwrap();
+        store_project_settings(&conn, &key, &ProjectSettings::default()).unwrap();
+        assert_eq!(
+            load_project_settings(&conn, &key).unwrap(),
+            ProjectSettings::default()
+        );
+        conn.execute(
+            "INSERT INTO project_settings (project_path, settings) VALUES (?1, ?2)",
+            params![key, r#"{"default_model": "opus", "added_later": true}"#],
+        )
+        .unwrap();
+        assert_eq!(
+            load_project_settings(&conn, &key)
+                .unwrap()
+                .default_model
+                .as_deref(),
+            Some("opus")
+        );
+    }
+}
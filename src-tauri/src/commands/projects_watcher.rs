@@ -0,0 +1,292 @@
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::agents::AgentDb;
+
+/// Emitted when projects or sessions appear, disappear or are renamed
+pub const PROJECTS_CHANGED_EVENT: &str = "projects-changed";
+
+/// Emitted with `{ project_id, session_id }` when a session file is written
+pub const SESSION_UPDATED_EVENT: &str = "session-updated";
+
+/// Changes within this window are reported together, so a streaming session
+/// emits at most a couple of events a second
+const COALESCE_WINDOW: Duration = Duration::from_millis(500);
+
+/// The running watcher over `~/.claude/projects`, if watching is enabled
+#[derive(Default)]
+pub struct ProjectsWatcherState(Mutex<Option<RecommendedWatcher>>);
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SessionUpdated {
+    pub project_id: String,
+    pub session_id: String,
+}
+
+/// Filesystem changes under the projects directory, reduced to what the
+/// frontend refreshes
+#[derive(Debug, Default, PartialEq)]
+pub struct ProjectChanges {
+    pub projects_changed: bool,
+    pub sessions: BTreeSet<SessionUpdated>,
+}
+
+impl ProjectChanges {
+    /// Add an event. Only project directories and the session files directly
+    /// in them matter; reads and anything deeper are ignored.
+    pub fn record(&mut self, projects_dir: &Path, event: &Event) {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        let added_or_removed = matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+        );
+        for path in &event.paths {
+            let Ok(relative) = path.strip_prefix(projects_dir) else {
+                continue;
+            };
+            let parts: Vec<&str> = relative
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(part) => part.to_str(),
+                    _ => None,
+                })
+                .collect();
+            match parts.as_slice() {
+                [_project] => self.projects_changed = true,
+                [project, file] => {
+                    let Some(session_id) = file.strip_suffix(".jsonl") else {
+                        continue;
+                    };
+                    self.sessions.insert(SessionUpdated {
+                        project_id: project.to_string(),
+                        session_id: session_id.to_string(),
+                    });
+                    self.projects_changed |= added_or_removed;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.projects_changed && self.sessions.is_empty()
+    }
+}
+
+/// Whether the watcher runs; on unless turned off, e.g. on network
+/// filesystems where watching is unreliable or slow
+pub fn load_fs_watcher_setting(conn: &Connection) -> Result<bool, String> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = 'fs_watcher_enabled'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(value.as_deref() != Some("false"))
+}
+
+/// `~/.claude/projects`, created if Claude hasn't run yet so it can be watched
+fn projects_dir() -> Result<PathBuf, String> {
+    let dir = dirs::home_dir()
+        .ok_or("Could not find home directory")?
+        .join(".claude")
+        .join("projects");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    // Events carry canonical paths on some platforms, e.g. /private on macOS
+    dir.canonicalize().map_err(|e| e.to_string())
+}
+
+fn emit_changes(app: &AppHandle, changes: &ProjectChanges) {
+    if changes.projects_changed {
+        let _ = app.emit(PROJECTS_CHANGED_EVENT, ());
+    }
+    for session in &changes.sessions {
+        let _ = app.emit(SESSION_UPDATED_EVENT, session);
+    }
+}
+
+/// Watch the projects directory, emitting coalesced change events until the
+/// returned watcher is dropped
+fn watch_projects(app: &AppHandle) -> Result<RecommendedWatcher, String> {
+    let projects_dir = projects_dir()?;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let _ = tx.send(event);
+    })
+    .map_err(|e| format!("Failed to create filesystem watcher: {}", e))?;
+    watcher
+        .watch(&projects_dir, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", projects_dir.display(), e))?;
+    log::info!("Watching {} for changes", projects_dir.display());
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        // The channel closes once the watcher, which owns the sender, is dropped
+        while let Some(first) = rx.recv().await {
+            let mut changes = ProjectChanges::default();
+            let deadline = tokio::time::Instant::now() + COALESCE_WINDOW;
+            let mut next = Some(first);
+            while let Some(event) = next {
+                match event {
+                    Ok(event) => changes.record(&projects_dir, &event),
+                    Err(e) => log::warn!("Filesystem watcher error: {}", e),
+                }
+                next = tokio::time::timeout_at(deadline, rx.recv())
+                    .await
+                    .ok()
+                    .flatten();
+            }
+            if !changes.is_empty() {
+                emit_changes(&app, &changes);
+            }
+        }
+        log::info!("Stopped watching {}", projects_dir.display());
+    });
+    Ok(watcher)
+}
+
+/// Start watching `~/.claude/projects` at startup unless the user turned it off
+pub fn start_projects_watcher(app: &AppHandle) {
+    let enabled = app
+        .state::<AgentDb>()
+        .0
+        .lock()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| load_fs_watcher_setting(&conn))
+        .unwrap_or(true);
+    if !enabled {
+        log::info!("Filesystem watcher is disabled");
+        return;
+    }
+    match watch_projects(app) {
+        Ok(watcher) => {
+            if let Ok(mut running) = app.state::<ProjectsWatcherState>().0.lock() {
+                *running = Some(watcher);
+            }
+        }
+        Err(e) => log::warn!("Not watching projects for changes: {}", e),
+    }
+}
+
+/// Whether opcode watches `~/.claude/projects` for sessions run elsewhere
+#[tauri::command]
+pub async fn get_fs_watcher_enabled(db: State<'_, AgentDb>) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_fs_watcher_setting(&conn)
+}
+
+/// Turn watching `~/.claude/projects` on or off, starting or stopping the
+/// watcher straight away
+#[tauri::command]
+pub async fn set_fs_watcher_enabled(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    watcher: State<'_, ProjectsWatcherState>,
+    enabled: bool,
+) -> Result<(), String> {
+    {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES ('fs_watcher_enabled', ?1)
+             ON CONFLICT(key) DO UPDATE SET value = ?1",
+            params![enabled.to_string()],
+        )
+        .map_err(|e| format!("Failed to save filesystem watcher setting: {}", e))?;
+    }
+
+    let mut running = watcher.0.lock().map_err(|e| e.to_string())?;
+    if !enabled {
+        *running = None;
+    } else if running.is_none() {
+        *running = Some(watch_projects(&app)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, DataChange, RenameMode};
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn test_changes_are_reduced_to_projects_and_sessions() {
+        let root = Path::new("/home/me/.claude/projects");
+        let write = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        let mut changes = ProjectChanges::default();
+
+        changes.record(root, &event(write, "/home/me/.claude/projects/-p/s1.jsonl"));
+        changes.record(root, &event(write, "/home/me/.claude/projects/-p/s1.jsonl"));
+        changes.record(
+            root,
+            &event(
+                EventKind::Access(AccessKind::Any),
+                "/home/me/.claude/projects/-p/s2.jsonl",
+            ),
+        );
+        changes.record(
+            root,
+            &event(write, "/home/me/.claude/projects/-p/notes.txt"),
+        );
+        changes.record(
+            root,
+            &event(write, "/home/me/.claude/projects/-p/s1/x.jsonl"),
+        );
+        changes.record(root, &event(write, "/home/me/.claude/settings.json"));
+        assert!(!changes.projects_changed);
+        assert_eq!(
+            changes.sessions.iter().collect::<Vec<_>>(),
+            [&SessionUpdated {
+                project_id: "-p".to_string(),
+                session_id: "s1".to_string(),
+            }]
+        );
+
+        let mut changes = ProjectChanges::default();
+        changes.record(
+            root,
+            &event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Any)),
+                "/home/me/.claude/projects/-p/s3.jsonl",
+            ),
+        );
+        assert!(changes.projects_changed && changes.sessions.len() == 1);
+
+        let mut changes = ProjectChanges::default();
+        changes.record(
+            root,
+            &event(
+                EventKind::Create(CreateKind::Folder),
+                "/home/me/.claude/projects/-new",
+            ),
+        );
+        assert!(changes.projects_changed && changes.sessions.is_empty());
+    }
+
+    #[test]
+    fn test_watcher_is_on_by_default() {
+        let conn = crate::db::test_connection();
+        assert!(load_fs_watcher_setting(&conn).unwrap());
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES ('fs_watcher_enabled', 'false')",
+            [],
+        )
+        .unwrap();
+        assert!(!load_fs_watcher_setting(&conn).unwrap());
+    }
+}
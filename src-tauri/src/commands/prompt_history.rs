@@ -0,0 +1,326 @@
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+const DEFAULT_LIST_LIMIT: usize = 100;
+
+/// A prompt submitted to a Claude session or as an agent task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromptHistoryEntry {
+    pub id: i64,
+    pub prompt: String,
+    pub project_path: String,
+    /// The project's ID in `~/.claude/projects`
+    pub project_id: String,
+    pub session_id: Option<String>,
+    pub agent_task: bool,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromptHistorySettings {
+    /// Whether prompts are recorded at all
+    pub enabled: bool,
+    /// Entries kept; the oldest are pruned beyond it
+    pub max_entries: usize,
+}
+
+impl Default for PromptHistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+}
+
+fn read_setting(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+pub fn read_prompt_history_settings(conn: &Connection) -> Result<PromptHistorySettings, String> {
+    let defaults = PromptHistorySettings::default();
+    Ok(PromptHistorySettings {
+        enabled: read_setting(conn, "prompt_history_enabled")?
+            .map_or(defaults.enabled, |value| value != "false"),
+        max_entries: read_setting(conn, "prompt_history_max_entries")?
+            .and_then(|value| value.parse::<usize>().ok())
+            .filter(|max| *max > 0)
+            .unwrap_or(defaults.max_entries),
+    })
+}
+
+fn prune(conn: &Connection, max_entries: usize) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM prompt_history WHERE id NOT IN
+         (SELECT id FROM prompt_history ORDER BY id DESC LIMIT ?1)",
+        params![max_entries as i64],
+    )
+    .map_err(|e| format!("Failed to prune prompt history: {}", e))?;
+    Ok(())
+}
+
+/// Record a submitted prompt unless history is turned off. Submitting the
+/// latest entry's prompt again in the same project moves it up rather than
+/// adding a duplicate. Returns the entry's id.
+pub fn record_prompt(
+    conn: &Connection,
+    prompt: &str,
+    project_path: &str,
+    session_id: Option<&str>,
+    agent_task: bool,
+) -> Result<Option<i64>, String> {
+    let settings = read_prompt_history_settings(conn)?;
+    if !settings.enabled || prompt.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let latest: Option<(i64, String, String, bool)> = conn
+        .query_row(
+            "SELECT id, prompt, project_path, agent_task FROM prompt_history ORDER BY id DESC LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some((id, _, _, _)) = latest.filter(|(_, latest_prompt, latest_path, latest_agent)| {
+        latest_prompt == prompt && latest_path == project_path && *latest_agent == agent_task
+    }) {
+        conn.execute(
+            "UPDATE prompt_history SET created_at = CURRENT_TIMESTAMP,
+             session_id = COALESCE(?1, session_id) WHERE id = ?2",
+            params![session_id, id],
+        )
+        .map_err(|e| format!("Failed to record prompt: {}", e))?;
+        return Ok(Some(id));
+    }
+
+    conn.execute(
+        "INSERT INTO prompt_history (prompt, project_path, project_id, session_id, agent_task)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            prompt,
+            project_path,
+            project_path.replace('/', "-"),
+            session_id,
+            agent_task
+        ],
+    )
+    .map_err(|e| format!("Failed to record prompt: {}", e))?;
+    let id = conn.last_insert_rowid();
+    prune(conn, settings.max_entries)?;
+    Ok(Some(id))
+}
+
+/// Record a prompt, logging rather than failing so history never blocks a run
+pub fn record_prompt_or_warn(
+    db: &AgentDb,
+    prompt: &str,
+    project_path: &str,
+    session_id: Option<&str>,
+    agent_task: bool,
+) {
+    let result =
+        db.0.lock()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| record_prompt(&conn, prompt, project_path, session_id, agent_task));
+    if let Err(e) = result {
+        log::warn!("Failed to record prompt history: {}", e);
+    }
+}
+
+/// Entries newest first, optionally for one project and containing `search`
+pub fn load_prompt_history(
+    conn: &Connection,
+    limit: usize,
+    project_id: Option<&str>,
+    search: Option<&str>,
+) -> Result<Vec<PromptHistoryEntry>, String> {
+    let mut sql = "SELECT id, prompt, project_path, project_id, session_id, agent_task, created_at
+                   FROM prompt_history WHERE 1 = 1"
+        .to_string();
+    let mut values: Vec<String> = Vec::new();
+    if let Some(project_id) = project_id {
+        values.push(project_id.to_string());
+        sql.push_str(&format!(" AND project_id = ?{}", values.len()));
+    }
+    if let Some(search) = search.map(str::trim).filter(|s| !s.is_empty()) {
+        let escaped = search
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        values.push(format!("%{}%", escaped));
+        sql.push_str(&format!(" AND prompt LIKE ?{} ESCAPE '\\'", values.len()));
+    }
+    sql.push_str(&format!(" ORDER BY id DESC LIMIT {}", limit));
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params_from_iter(values.iter()), |row| {
+            Ok(PromptHistoryEntry {
+                id: row.get(0)?,
+                prompt: row.get(1)?,
+                project_path: row.get(2)?,
+                project_id: row.get(3)?,
+                session_id: row.get(4)?,
+                agent_task: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// List recorded prompts, newest first. `search` matches anywhere in the
+/// prompt, ignoring ASCII case.
+#[tauri::command]
+pub async fn list_prompt_history(
+    db: State<'_, AgentDb>,
+    limit: Option<usize>,
+    project_id: Option<String>,
+    search: Option<String>,
+) -> Result<Vec<PromptHistoryEntry>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_prompt_history(
+        &conn,
+        limit.unwrap_or(DEFAULT_LIST_LIMIT),
+        project_id.as_deref(),
+        search.as_deref(),
+    )
+}
+
+/// Delete the given entries, returning how many were removed
+#[tauri::command]
+pub async fn delete_prompt_history(db: State<'_, AgentDb>, ids: Vec<i64>) -> Result<usize, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut deleted = 0;
+    for id in ids {
+        deleted += conn
+            .execute("DELETE FROM prompt_history WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete prompt history: {}", e))?;
+    }
+    Ok(deleted)
+}
+
+/// Delete every recorded prompt, returning how many were removed
+#[tauri::command]
+pub async fn clear_prompt_history(db: State<'_, AgentDb>) -> Result<usize, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM prompt_history", [])
+        .map_err(|e| format!("Failed to clear prompt history: {}", e))
+}
+
+/// Get whether prompts are recorded and how many are kept
+#[tauri::command]
+pub async fn get_prompt_history_settings(
+    db: State<'_, AgentDb>,
+) -> Result<PromptHistorySettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    read_prompt_history_settings(&conn)
+}
+
+/// Set whether prompts are recorded and how many are kept. Turning history
+/// off also deletes the prompts already recorded.
+#[tauri::command]
+pub async fn set_prompt_history_settings(
+    db: State<'_, AgentDb>,
+    settings: PromptHistorySettings,
+) -> Result<(), String> {
+    if settings.max_entries == 0 {
+        return Err("max_entries must be at least 1".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    for (key, value) in [
+        ("prompt_history_enabled", settings.enabled.to_string()),
+        (
+            "prompt_history_max_entries",
+            settings.max_entries.to_string(),
+        ),
+    ] {
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = ?2",
+            params![key, value],
+        )
+        .map_err(|e| format!("Failed to save {}: {}", key, e))?;
+    }
+    if settings.enabled {
+        prune(&conn, settings.max_entries)
+    } else {
+        conn.execute("DELETE FROM prompt_history", [])
+            .map_err(|e| format!("Failed to clear prompt history: {}", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompts(entries: &[PromptHistoryEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.prompt.as_str()).collect()
+    }
+
+    #[test]
+    fn test_prompts_are_collapsed_capped_and_searchable() {
+        let conn = crate::db::test_connection();
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES ('prompt_history_max_entries', '3')",
+            [],
+        )
+        .unwrap();
+
+        let first = record_prompt(&conn, "fix the tests", "/work/app", None, false).unwrap();
+        let again = record_prompt(&conn, "fix the tests", "/work/app", Some("s1"), false).unwrap();
+        assert_eq!(first, again);
+        record_prompt(&conn, "Review 100% of it", "/work/app", None, true).unwrap();
+        record_prompt(&conn, "fix the tests", "/work/api", Some("s2"), false).unwrap();
+        record_prompt(&conn, "add logging", "/work/app", Some("s3"), false).unwrap();
+
+        let all = load_prompt_history(&conn, 10, None, None).unwrap();
+        assert_eq!(
+            prompts(&all),
+            ["add logging", "fix the tests", "Review 100% of it"]
+        );
+        assert!(all[2].agent_task);
+        assert_eq!(all[1].project_id, "-work-api");
+
+        let app = load_prompt_history(&conn, 10, Some("-work-app"), None).unwrap();
+        assert_eq!(prompts(&app), ["add logging", "Review 100% of it"]);
+        let found = load_prompt_history(&conn, 10, None, Some("100%")).unwrap();
+        assert_eq!(prompts(&found), ["Review 100% of it"]);
+        let found = load_prompt_history(&conn, 10, None, Some("FIX")).unwrap();
+        assert_eq!(prompts(&found), ["fix the tests"]);
+        assert!(load_prompt_history(&conn, 10, None, Some("_"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_nothing_is_recorded_when_history_is_off() {
+        let conn = crate::db::test_connection();
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES ('prompt_history_enabled', 'false')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(
+            record_prompt(&conn, "secret", "/work/app", None, false).unwrap(),
+            None
+        );
+        assert!(load_prompt_history(&conn, 10, None, None)
+            .unwrap()
+            .is_empty());
+    }
+}
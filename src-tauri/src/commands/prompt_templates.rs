@@ -0,0 +1,307 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Mutex, OnceLock};
+
+/// Shown in place of secret-looking variable values in recorded prompts
+pub const MASKED_VALUE: &str = "********";
+
+/// Variable names that suggest the value is a credential
+const SECRET_NAME_PARTS: [&str; 7] = [
+    "KEY",
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "CREDENTIAL",
+    "AUTH",
+];
+
+/// Prefixes of common API keys and tokens
+const SECRET_VALUE_PREFIXES: [&str; 9] = [
+    "sk-",
+    "ghp_",
+    "gho_",
+    "ghs_",
+    "github_pat_",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+    "AIza",
+];
+
+/// A system prompt with its template variables filled in
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedPrompt {
+    pub prompt: String,
+    pub masked: String, // Secret-looking custom values replaced, for recording on the run
+}
+
+impl ResolvedPrompt {
+    pub fn has_masked_values(&self) -> bool {
+        self.prompt != self.masked
+    }
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether a variable's name or value suggests it holds a credential
+pub fn looks_like_secret(name: &str, value: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    if SECRET_NAME_PARTS.iter().any(|part| name.contains(part)) {
+        return true;
+    }
+    if SECRET_VALUE_PREFIXES.iter().any(|prefix| value.starts_with(prefix)) {
+        return true;
+    }
+    // Long unbroken strings mixing letters and digits are likely keys
+    value.len() >= 32
+        && !value.contains(char::is_whitespace)
+        && value.chars().any(|c| c.is_ascii_alphabetic())
+        && value.chars().any(|c| c.is_ascii_digit())
+        && !value.contains('/')
+}
+
+/// Variables every agent prompt can use
+pub fn builtin_variables(project_path: &str, agent_name: &str, model: &str) -> HashMap<String, String> {
+    let project_name = std::path::Path::new(project_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    HashMap::from([
+        ("PROJECT_PATH".to_string(), project_path.to_string()),
+        ("PROJECT_NAME".to_string(), project_name),
+        ("DATE".to_string(), chrono::Local::now().format("%Y-%m-%d").to_string()),
+        ("AGENT_NAME".to_string(), agent_name.to_string()),
+        ("MODEL".to_string(), model.to_string()),
+    ])
+}
+
+/// Replace `{{NAME}}` placeholders using `lookup`, returning the text and the names
+/// it couldn't resolve. Unresolved placeholders are kept as written and `\{\{`
+/// becomes a literal `{{`.
+pub fn substitute(template: &str, lookup: impl Fn(&str) -> Option<String>) -> (String, BTreeSet<String>) {
+    let mut output = String::with_capacity(template.len());
+    let mut missing = BTreeSet::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['\\', '{']) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("\\{\\{") {
+            output.push_str("{{");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{{") {
+            match after.find("}}") {
+                Some(end) if is_variable_name(after[..end].trim()) => {
+                    let name = after[..end].trim();
+                    match lookup(name) {
+                        Some(value) => output.push_str(&value),
+                        None => {
+                            missing.insert(name.to_string());
+                            output.push_str(&rest[..end + 4]);
+                        }
+                    }
+                    rest = &after[end + 2..];
+                }
+                _ => {
+                    output.push_str("{{");
+                    rest = after;
+                }
+            }
+        } else {
+            output.push_str(&rest[..1]);
+            rest = &rest[1..];
+        }
+    }
+    output.push_str(rest);
+
+    (output, missing)
+}
+
+/// Fill in a system prompt's template variables. Custom variables take precedence
+/// over built-in ones; unresolved placeholders are an error unless `allow_missing`.
+pub fn resolve_system_prompt(
+    template: &str,
+    builtins: &HashMap<String, String>,
+    custom: &HashMap<String, String>,
+    allow_missing: bool,
+) -> Result<ResolvedPrompt, String> {
+    if let Some(name) = custom.keys().find(|name| !is_variable_name(name)) {
+        return Err(format!(
+            "Invalid template variable name '{}': use letters, digits and underscores",
+            name
+        ));
+    }
+
+    let value_of = |name: &str| custom.get(name).or_else(|| builtins.get(name)).cloned();
+    let (prompt, missing) = substitute(template, value_of);
+    if !missing.is_empty() && !allow_missing {
+        return Err(format!(
+            "Unresolved template variables in the system prompt: {}. Supply them in variables or set allow_missing to leave them as written",
+            missing.into_iter().collect::<Vec<_>>().join(", ")
+        ));
+    }
+
+    let (masked, _) = substitute(template, |name| match custom.get(name) {
+        Some(value) if looks_like_secret(name, value) => Some(MASKED_VALUE.to_string()),
+        _ => value_of(name),
+    });
+
+    Ok(ResolvedPrompt { prompt, masked })
+}
+
+/// Custom variables as stored in a TEXT column, NULL when there are none
+pub fn variables_to_json(variables: &HashMap<String, String>) -> Option<String> {
+    if variables.is_empty() {
+        return None;
+    }
+    serde_json::to_string(variables).ok()
+}
+
+/// Custom variables stored by `variables_to_json`
+pub fn variables_from_json(json: Option<&str>) -> HashMap<String, String> {
+    json.and_then(|json| serde_json::from_str(json).ok())
+        .unwrap_or_default()
+}
+
+/// The custom variables a run can record for its retries, leaving out
+/// secret-looking ones as its recorded prompt does
+pub fn recordable_variables(custom: &HashMap<String, String>) -> HashMap<String, String> {
+    custom
+        .iter()
+        .filter(|(name, value)| !looks_like_secret(name, value))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+/// Check that an agent exists and `variables` resolve its system prompt, so a
+/// schedule or pipeline step doesn't fail every time it starts
+pub fn check_agent_variables(
+    conn: &rusqlite::Connection,
+    agent_id: i64,
+    variables: &HashMap<String, String>,
+) -> Result<(), String> {
+    let (name, model, prompt): (String, String, String) = conn
+        .query_row(
+            "SELECT name, model, system_prompt FROM agents WHERE id = ?1",
+            rusqlite::params![agent_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| match e {
+            rusqlite::Error::QueryReturnedNoRows => format!("Agent {} not found", agent_id),
+            e => e.to_string(),
+        })?;
+    // Built-in values don't matter here, only which names are missing
+    resolve_system_prompt(&prompt, &builtin_variables("", &name, &model), variables, false)
+        .map(|_| ())
+}
+
+/// Resolved prompts of runs waiting to launch, kept out of the database
+/// because they may contain secrets
+fn pending_prompts() -> &'static Mutex<HashMap<i64, String>> {
+    static PENDING: OnceLock<Mutex<HashMap<i64, String>>> = OnceLock::new();
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn remember_resolved_prompt(run_id: i64, prompt: String) {
+    if let Ok(mut pending) = pending_prompts().lock() {
+        pending.insert(run_id, prompt);
+    }
+}
+
+pub fn take_resolved_prompt(run_id: i64) -> Option<String> {
+    pending_prompts().lock().ok()?.remove(&run_id)
+}
+
+/// Drop the resolved prompt of a run that will no longer launch
+pub fn forget_resolved_prompt(run_id: i64) {
+    take_resolved_prompt(run_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolves_variables_and_keeps_escapes_literal() {
+        let builtins = builtin_variables("/work/opcode", "notes", "sonnet");
+        assert_eq!(builtins["PROJECT_NAME"], "opcode");
+
+        let resolved = resolve_system_prompt(
+            "Notes for {{PROJECT_NAME}} {{ VERSION }} by {{AGENT_NAME}}, \\{\\{raw}} {{not a var}}",
+            &builtins,
+            &vars(&[("VERSION", "1.2")]),
+            false,
+        )
+        .unwrap();
+        assert_eq!(resolved.prompt, "Notes for opcode 1.2 by notes, {{raw}} {{not a var}}");
+        assert!(!resolved.has_masked_values());
+    }
+
+    #[test]
+    fn test_missing_variables_fail_unless_allowed() {
+        let builtins = HashMap::new();
+        let err = resolve_system_prompt("{{B}} {{A}} {{B}}", &builtins, &HashMap::new(), false)
+            .unwrap_err();
+        assert!(err.contains(": A, B."));
+
+        let resolved = resolve_system_prompt("{{ A }}!", &builtins, &HashMap::new(), true).unwrap();
+        assert_eq!(resolved.prompt, "{{ A }}!");
+        assert!(resolve_system_prompt("", &builtins, &vars(&[("bad-name", "x")]), true).is_err());
+    }
+
+    #[test]
+    fn test_recorded_variables_leave_out_secrets() {
+        let custom = vars(&[("VERSION", "2.1"), ("API_TOKEN", "abc")]);
+        let recorded = recordable_variables(&custom);
+        assert_eq!(recorded, vars(&[("VERSION", "2.1")]));
+
+        let json = variables_to_json(&recorded);
+        assert_eq!(variables_from_json(json.as_deref()), recorded);
+        assert_eq!(variables_to_json(&HashMap::new()), None);
+        assert!(variables_from_json(None).is_empty());
+    }
+
+    #[test]
+    fn test_agent_variables_are_checked_against_its_prompt() {
+        let conn = crate::db::test_connection();
+        let agent_id = crate::db::seed_agent(&conn, "a");
+        conn.execute(
+            "UPDATE agents SET system_prompt = 'Release {{VERSION}} of {{PROJECT_NAME}}' WHERE id = ?1",
+            rusqlite::params![agent_id],
+        )
+        .unwrap();
+
+        let err = check_agent_variables(&conn, agent_id, &HashMap::new()).unwrap_err();
+        assert!(err.contains("VERSION"), "{}", err);
+        assert!(check_agent_variables(&conn, agent_id, &vars(&[("VERSION", "2.1")])).is_ok());
+        assert_eq!(
+            check_agent_variables(&conn, 999, &HashMap::new()).unwrap_err(),
+            "Agent 999 not found"
+        );
+    }
+
+    #[test]
+    fn test_secret_looking_values_are_masked_in_recorded_prompt() {
+        let custom = vars(&[("API_KEY", "hunter2"), ("REPO", "opcode"), ("X", "ghp_abc")]);
+        let resolved =
+            resolve_system_prompt("{{REPO}} {{API_KEY}} {{X}}", &HashMap::new(), &custom, false).unwrap();
+        assert_eq!(resolved.prompt, "opcode hunter2 ghp_abc");
+        assert_eq!(resolved.masked, "opcode ******** ********");
+        assert!(resolved.has_masked_values());
+    }
+}
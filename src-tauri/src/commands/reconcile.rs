@@ -0,0 +1,247 @@
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::agents::{promote_queued_runs, AgentDb};
+use crate::process::{
+    is_claude_process, ProcessInfo, ProcessRegistry, ProcessRegistryState, ProcessType,
+};
+
+/// How often an adopted run's process is checked for exit
+const ADOPTED_RUN_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Outcome of matching in-flight runs against the processes actually running
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ReconcileReport {
+    pub recovered: usize,   // Still running, and tracked again
+    pub interrupted: usize, // Process gone, marked `interrupted`
+}
+
+/// A run the database says is pending or running but the registry doesn't track
+struct UntrackedRun {
+    id: i64,
+    agent_id: i64,
+    agent_name: String,
+    pid: Option<u32>,
+    process_started_at: Option<DateTime<Utc>>,
+    project_path: String,
+    task: String,
+    model: String,
+}
+
+/// Re-register untracked runs whose Claude process is still alive and mark the
+/// rest `interrupted`. Returns the report and the ids of the recovered runs.
+///
+/// Callers must hold the launch lock, so pending rows can't belong to a launch
+/// in progress.
+fn reconcile_runs<F>(
+    db: &AgentDb,
+    registry: &ProcessRegistry,
+    is_alive: F,
+) -> Result<(ReconcileReport, Vec<i64>), String>
+where
+    F: Fn(u32, DateTime<Utc>) -> bool,
+{
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, agent_id, agent_name, pid, process_started_at, project_path, task, model
+             FROM agent_runs WHERE status IN ('pending', 'running')",
+        )
+        .map_err(|e| e.to_string())?;
+    let runs = stmt
+        .query_map([], |row| {
+            Ok(UntrackedRun {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                agent_name: row.get(2)?,
+                pid: row.get::<_, Option<i64>>(3)?.map(|pid| pid as u32),
+                process_started_at: row
+                    .get::<_, Option<String>>(4)?
+                    .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+                    .map(|at| at.with_timezone(&Utc)),
+                project_path: row.get(5)?,
+                task: row.get(6)?,
+                model: row.get(7)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    drop(stmt);
+
+    let mut report = ReconcileReport::default();
+    let mut recovered = Vec::new();
+    for run in runs {
+        if registry.get_process(run.id)?.is_some() {
+            continue;
+        }
+
+        match (run.pid.filter(|pid| *pid > 0), run.process_started_at) {
+            (Some(pid), Some(started_at)) if is_alive(pid, started_at) => {
+                registry.adopt_agent_process(ProcessInfo {
+                    run_id: run.id,
+                    process_type: ProcessType::AgentRun {
+                        agent_id: run.agent_id,
+                        agent_name: run.agent_name,
+                    },
+                    pid,
+                    started_at,
+                    project_path: run.project_path,
+                    task: run.task,
+                    model: run.model,
+                    deadline: None,
+                    claude_binary: None,
+                })?;
+                info!("Recovered agent run {} (PID {}) after restart", run.id, pid);
+                report.recovered += 1;
+                recovered.push(run.id);
+            }
+            _ => {
+                conn.execute(
+                    "UPDATE agent_runs SET status = 'interrupted', completed_at = CURRENT_TIMESTAMP
+                     WHERE id = ?1 AND status IN ('pending', 'running')",
+                    params![run.id],
+                )
+                .map_err(|e| e.to_string())?;
+                info!("Marked agent run {} interrupted: its process is gone", run.id);
+                report.interrupted += 1;
+            }
+        }
+    }
+    Ok((report, recovered))
+}
+
+/// Wait for an adopted run's process to exit, then mark the run `interrupted`,
+/// since its result was lost with the previous app session
+fn watch_adopted_run(app: AppHandle, run_id: i64) {
+    tauri::async_runtime::spawn(async move {
+        let registry = app.state::<ProcessRegistryState>();
+        loop {
+            tokio::time::sleep(ADOPTED_RUN_POLL_INTERVAL).await;
+            match registry.0.is_process_running(run_id).await {
+                Ok(true) => continue,
+                Ok(false) => break,
+                Err(e) => {
+                    warn!("Failed to check adopted agent run {}: {}", run_id, e);
+                    break;
+                }
+            }
+        }
+        let _ = registry.0.unregister_process(run_id);
+
+        let db = app.state::<AgentDb>();
+        let updated = match db.0.lock() {
+            Ok(conn) => conn
+                .execute(
+                    "UPDATE agent_runs SET status = 'interrupted', completed_at = CURRENT_TIMESTAMP
+                     WHERE id = ?1 AND status = 'running'",
+                    params![run_id],
+                )
+                .unwrap_or(0),
+            Err(_) => 0,
+        };
+        if updated > 0 {
+            let _ = app.emit(&format!("agent-complete:{}", run_id), false);
+        }
+        promote_queued_runs(app.clone()).await;
+    });
+}
+
+/// Match pending and running agent runs against live processes: runs whose
+/// Claude process survived are tracked again, the rest are marked `interrupted`
+pub async fn reconcile_and_watch(app: &AppHandle) -> Result<ReconcileReport, String> {
+    let db = app.state::<AgentDb>();
+    let registry = app.state::<ProcessRegistryState>();
+
+    let (report, recovered) = {
+        let _launch_guard = registry.0.lock_launch().await;
+        reconcile_runs(&db, &registry.0, is_claude_process)?
+    };
+    for run_id in recovered {
+        watch_adopted_run(app.clone(), run_id);
+    }
+    if report.interrupted > 0 {
+        let _ = app.emit("agent-runs-reconciled", &report);
+    }
+
+    // Interrupted runs free their slots for anything queued
+    promote_queued_runs(app.clone()).await;
+    Ok(report)
+}
+
+/// Reconcile agent runs left pending or running by a previous app session
+pub fn start_run_reconciliation(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        match reconcile_and_watch(&app).await {
+            Ok(report) => info!(
+                "Reconciled agent runs: {} recovered, {} interrupted",
+                report.recovered, report.interrupted
+            ),
+            Err(e) => warn!("Failed to reconcile agent runs: {}", e),
+        }
+    });
+}
+
+/// Re-check pending and running agent runs against the live processes,
+/// returning how many were recovered and how many were marked interrupted
+#[tauri::command]
+pub async fn reconcile_agent_runs(app: AppHandle) -> Result<ReconcileReport, String> {
+    reconcile_and_watch(&app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn status(db: &AgentDb, id: i64) -> String {
+        let conn = db.0.lock().unwrap();
+        conn.query_row("SELECT status FROM agent_runs WHERE id = ?1", params![id], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_live_runs_are_adopted_and_dead_ones_interrupted() {
+        let conn = crate::db::test_connection();
+        let started = "2026-10-18T09:00:00+00:00";
+        crate::db::seed_agent(&conn, "a");
+        conn.execute_batch(&format!(
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, pid, process_started_at) VALUES
+                 (1, 'a', 'bot', 't', 'sonnet', '/live', '', 'running', 100, '{0}'),
+                 (1, 'a', 'bot', 't', 'sonnet', '/reused', '', 'running', 200, '{0}'),
+                 (1, 'a', 'bot', 't', 'sonnet', '/p', '', 'pending', NULL, NULL),
+                 (1, 'a', 'bot', 't', 'sonnet', '/p', '', 'queued', NULL, NULL),
+                 (1, 'a', 'bot', 't', 'sonnet', '/p', '', 'completed', 300, '{0}');",
+            started
+        ))
+        .unwrap();
+        let db = AgentDb(Mutex::new(conn));
+        let registry = ProcessRegistry::new();
+
+        // PID 200 is alive but belongs to a process started later
+        let expected_start = DateTime::parse_from_rfc3339(started).unwrap();
+        let (report, recovered) = reconcile_runs(&db, &registry, |pid, started_at| {
+            pid == 100 && started_at == expected_start
+        })
+        .unwrap();
+        assert_eq!(report, ReconcileReport { recovered: 1, interrupted: 2 });
+        assert_eq!(recovered, vec![1]);
+
+        let adopted = registry.get_process(1).unwrap().unwrap();
+        assert_eq!((adopted.pid, adopted.project_path.as_str()), (100, "/live"));
+        assert_eq!(adopted.started_at, expected_start);
+        assert_eq!(
+            (1..=5).map(|id| status(&db, id)).collect::<Vec<_>>(),
+            ["running", "interrupted", "interrupted", "queued", "completed"]
+        );
+
+        // Tracked runs are left alone on a second pass
+        let (report, _) = reconcile_runs(&db, &registry, |_, _| false).unwrap();
+        assert_eq!(report, ReconcileReport::default());
+        assert_eq!(status(&db, 1), "running");
+    }
+}
@@ -0,0 +1,398 @@
+use log::{error, info, warn};
+use rusqlite::{params, params_from_iter, Connection, ToSql};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::AgentDb;
+use crate::commands::run_search::remove_runs_from_index;
+use crate::process::ProcessRegistryState;
+
+/// Runs deleted per transaction, so a large cleanup never holds the database for long
+const DELETE_BATCH_SIZE: usize = 200;
+
+/// Runs in these states are still going and are never deleted
+const ACTIVE_RUN_FILTER: &str = "status IN ('pending', 'queued', 'running')";
+
+/// How much run history to keep (None means no limit)
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RetentionSettings {
+    pub max_runs_per_agent: Option<u64>,
+    pub max_run_age_days: Option<u64>,
+}
+
+/// What a deletion removed and which runs it left alone
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RunDeletionReport {
+    pub runs_deleted: usize,
+    pub bytes_removed: u64, // Output files plus indexed search text
+    pub skipped_running: usize,
+    pub skipped_starred: usize,
+    pub skipped_with_notes: usize, // Only age and count based cleanup keeps noted runs
+}
+
+/// Runs matched for deletion, split into those that may go and those kept
+#[derive(Debug, Default)]
+struct RunSelection {
+    deletable: Vec<i64>,
+    report: RunDeletionReport,
+}
+
+/// Sort the runs matching `filter` into deletable and protected ones
+fn select_runs(
+    conn: &Connection,
+    filter: &str,
+    filter_params: &[&dyn ToSql],
+    keep_noted: bool,
+    selection: &mut RunSelection,
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, {}, starred, COALESCE(notes, '') != '' FROM agent_runs WHERE {} ORDER BY id",
+            ACTIVE_RUN_FILTER, filter
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(filter_params, |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, bool>(1)?,
+                row.get::<_, bool>(2)?,
+                row.get::<_, bool>(3)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    for row in rows {
+        let (id, active, starred, noted) = row.map_err(|e| e.to_string())?;
+        if active {
+            selection.report.skipped_running += 1;
+        } else if starred {
+            selection.report.skipped_starred += 1;
+        } else if keep_noted && noted {
+            selection.report.skipped_with_notes += 1;
+        } else {
+            selection.deletable.push(id);
+        }
+    }
+    Ok(())
+}
+
+fn select_by_ids(conn: &Connection, run_ids: &[i64]) -> Result<RunSelection, String> {
+    let mut selection = RunSelection::default();
+    for batch in run_ids.chunks(DELETE_BATCH_SIZE) {
+        let placeholders = vec!["?"; batch.len()].join(", ");
+        let batch_params: Vec<&dyn ToSql> = batch.iter().map(|id| id as &dyn ToSql).collect();
+        select_runs(
+            conn,
+            &format!("id IN ({})", placeholders),
+            &batch_params,
+            false,
+            &mut selection,
+        )?;
+    }
+    Ok(selection)
+}
+
+fn select_older_than(conn: &Connection, days: u64, agent_id: Option<i64>) -> Result<RunSelection, String> {
+    let mut selection = RunSelection::default();
+    select_runs(
+        conn,
+        "created_at < datetime('now', ?1) AND (?2 IS NULL OR agent_id = ?2)",
+        params![format!("-{} days", days), agent_id],
+        true,
+        &mut selection,
+    )?;
+    Ok(selection)
+}
+
+/// Runs outside the retention settings: too old, or beyond the newest
+/// `max_runs_per_agent` of their agent
+fn select_for_retention(conn: &Connection, settings: &RetentionSettings) -> Result<RunSelection, String> {
+    let mut selection = RunSelection::default();
+    if settings.max_run_age_days.is_none() && settings.max_runs_per_agent.is_none() {
+        return Ok(selection);
+    }
+
+    // An unset limit binds NULL, which matches nothing
+    let age = settings.max_run_age_days.map(|days| format!("-{} days", days));
+    let max_runs = settings.max_runs_per_agent.map(|max| max as i64);
+    select_runs(
+        conn,
+        "created_at < datetime('now', ?1)
+         OR id IN (
+             SELECT id FROM (
+                 SELECT id, ROW_NUMBER() OVER (PARTITION BY agent_id ORDER BY created_at DESC, id DESC) AS position
+                 FROM agent_runs
+             ) WHERE position > ?2
+         )",
+        params![age, max_runs],
+        true,
+        &mut selection,
+    )?;
+    Ok(selection)
+}
+
+/// Delete one batch of runs and their index rows in a single transaction,
+/// returning the runs actually deleted and the index bytes removed
+fn delete_run_batch(conn: &mut Connection, run_ids: &[i64]) -> Result<(Vec<i64>, u64), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    // A run may have been restarted since it was selected
+    let placeholders = vec!["?"; run_ids.len()].join(", ");
+    let still_finished: Vec<i64> = {
+        let mut stmt = tx
+            .prepare(&format!(
+                "SELECT id FROM agent_runs WHERE id IN ({}) AND NOT ({})",
+                placeholders, ACTIVE_RUN_FILTER
+            ))
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map(params_from_iter(run_ids), |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(|e| e.to_string())?;
+        ids
+    };
+    if still_finished.is_empty() {
+        return Ok((still_finished, 0));
+    }
+
+    let index_bytes = remove_runs_from_index(&tx, &still_finished)?;
+    tx.execute(
+        &format!(
+            "DELETE FROM agent_runs WHERE id IN ({})",
+            vec!["?"; still_finished.len()].join(", ")
+        ),
+        params_from_iter(&still_finished),
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok((still_finished, index_bytes))
+}
+
+/// Remove a deleted run's output file, returning its size
+fn remove_output_file(registry: &ProcessRegistryState, run_id: i64) -> u64 {
+    let Some(path) = registry.0.output_path(run_id) else {
+        return 0;
+    };
+    let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    match std::fs::remove_file(&path) {
+        Ok(()) => size,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(e) => {
+            warn!("Failed to remove output of run {}: {}", run_id, e);
+            0
+        }
+    }
+}
+
+/// Delete the selected runs batch by batch, releasing the database between batches
+async fn delete_selection(
+    db: &AgentDb,
+    registry: &ProcessRegistryState,
+    selection: RunSelection,
+) -> Result<RunDeletionReport, String> {
+    let mut report = selection.report;
+    for batch in selection.deletable.chunks(DELETE_BATCH_SIZE) {
+        let (deleted, index_bytes) = {
+            let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+            delete_run_batch(&mut conn, batch)?
+        };
+        report.runs_deleted += deleted.len();
+        report.bytes_removed += index_bytes;
+        report.skipped_running += batch.len() - deleted.len();
+        for run_id in deleted {
+            report.bytes_removed += remove_output_file(registry, run_id);
+        }
+        tokio::task::yield_now().await;
+    }
+    Ok(report)
+}
+
+fn read_limit_setting(conn: &Connection, key: &str) -> Result<Option<u64>, String> {
+    match conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(value) => Ok(value.parse::<u64>().ok().filter(|limit| *limit > 0)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn read_retention_settings(conn: &Connection) -> Result<RetentionSettings, String> {
+    Ok(RetentionSettings {
+        max_runs_per_agent: read_limit_setting(conn, "max_runs_per_agent")?,
+        max_run_age_days: read_limit_setting(conn, "max_run_age_days")?,
+    })
+}
+
+/// Get how much run history is kept
+#[tauri::command]
+pub async fn get_retention_settings(db: State<'_, AgentDb>) -> Result<RetentionSettings, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    read_retention_settings(&conn)
+}
+
+/// Set how much run history is kept (None or 0 removes a limit)
+#[tauri::command]
+pub async fn set_retention_settings(
+    db: State<'_, AgentDb>,
+    settings: RetentionSettings,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    for (key, value) in [
+        ("max_runs_per_agent", settings.max_runs_per_agent),
+        ("max_run_age_days", settings.max_run_age_days),
+    ] {
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = ?2",
+            params![key, value.unwrap_or(0).to_string()],
+        )
+        .map_err(|e| format!("Failed to save {}: {}", key, e))?;
+    }
+    Ok(())
+}
+
+/// Delete the given runs with their output and search index entries,
+/// skipping runs that are still going or starred
+#[tauri::command]
+pub async fn delete_agent_runs(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    run_ids: Vec<i64>,
+) -> Result<RunDeletionReport, String> {
+    let selection = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        select_by_ids(&conn, &run_ids)?
+    };
+    let report = delete_selection(&db, &registry, selection).await?;
+    info!("Deleted {} agent runs ({} bytes)", report.runs_deleted, report.bytes_removed);
+    Ok(report)
+}
+
+/// Delete runs created more than `days` ago, optionally for one agent only.
+/// Running, starred and noted runs are kept.
+#[tauri::command]
+pub async fn delete_runs_older_than(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+    days: u64,
+    agent_id: Option<i64>,
+) -> Result<RunDeletionReport, String> {
+    let selection = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        select_older_than(&conn, days, agent_id)?
+    };
+    let report = delete_selection(&db, &registry, selection).await?;
+    info!(
+        "Deleted {} agent runs older than {} days ({} bytes)",
+        report.runs_deleted, days, report.bytes_removed
+    );
+    Ok(report)
+}
+
+async fn apply_retention(db: &AgentDb, registry: &ProcessRegistryState) -> Result<RunDeletionReport, String> {
+    let selection = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let settings = read_retention_settings(&conn)?;
+        select_for_retention(&conn, &settings)?
+    };
+    let report = delete_selection(db, registry, selection).await?;
+    if report.runs_deleted > 0 {
+        info!(
+            "Retention cleanup deleted {} agent runs ({} bytes)",
+            report.runs_deleted, report.bytes_removed
+        );
+    }
+    Ok(report)
+}
+
+/// Delete runs outside the retention settings now
+#[tauri::command]
+pub async fn run_retention_cleanup(
+    db: State<'_, AgentDb>,
+    registry: State<'_, ProcessRegistryState>,
+) -> Result<RunDeletionReport, String> {
+    apply_retention(&db, &registry).await
+}
+
+/// Apply the retention settings once in the background at startup
+pub fn start_retention_cleanup(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let db = app.state::<AgentDb>();
+        let registry = app.state::<ProcessRegistryState>();
+        if let Err(e) = apply_retention(&db, &registry).await {
+            error!("Retention cleanup failed: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> Connection {
+        let conn = crate::db::test_connection();
+        crate::db::seed_agent(&conn, "a");
+        crate::db::seed_agent(&conn, "b");
+        conn.execute_batch(
+            "INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status, created_at, starred, notes) VALUES
+                 (1, 'a', 'bot', 't', 'sonnet', '/p', 's1', 'completed', datetime('now', '-40 days'), 0, NULL),
+                 (1, 'a', 'bot', 't', 'sonnet', '/p', 's2', 'completed', datetime('now', '-40 days'), 1, NULL),
+                 (1, 'a', 'bot', 't', 'sonnet', '/p', 's3', 'failed', datetime('now', '-40 days'), 0, 'keep me'),
+                 (1, 'a', 'bot', 't', 'sonnet', '/p', 's4', 'running', datetime('now', '-40 days'), 0, NULL),
+                 (1, 'a', 'bot', 't', 'sonnet', '/p', 's5', 'completed', datetime('now', '-1 days'), 0, NULL),
+                 (2, 'b', 'bot', 't', 'sonnet', '/p', 's6', 'completed', datetime('now', '-2 days'), 0, NULL),
+                 (2, 'b', 'bot', 't', 'sonnet', '/p', 's7', 'completed', datetime('now', '-1 days'), 0, NULL);",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_selection_protects_running_starred_and_noted_runs() {
+        let conn = test_db();
+
+        let selection = select_older_than(&conn, 30, None).unwrap();
+        assert_eq!(selection.deletable, vec![1]);
+        assert_eq!(
+            (
+                selection.report.skipped_running,
+                selection.report.skipped_starred,
+                selection.report.skipped_with_notes
+            ),
+            (1, 1, 1)
+        );
+        assert!(select_older_than(&conn, 30, Some(2)).unwrap().deletable.is_empty());
+
+        // Explicit deletion only protects running and starred runs
+        let selection = select_by_ids(&conn, &[1, 2, 3, 4, 99]).unwrap();
+        assert_eq!(selection.deletable, vec![1, 3]);
+
+        let settings = RetentionSettings {
+            max_runs_per_agent: Some(1),
+            max_run_age_days: None,
+        };
+        assert_eq!(select_for_retention(&conn, &settings).unwrap().deletable, vec![1, 6]);
+        assert!(select_for_retention(&conn, &RetentionSettings::default())
+            .unwrap()
+            .deletable
+            .is_empty());
+    }
+
+    #[test]
+    fn test_batch_delete_skips_runs_restarted_since_selection() {
+        let mut conn = test_db();
+        let (deleted, index_bytes) = delete_run_batch(&mut conn, &[1, 4, 5]).unwrap();
+        assert_eq!((deleted, index_bytes), (vec![1, 5], 0));
+
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM agent_runs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 5);
+    }
+}
@@ -0,0 +1,338 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tauri::State;
+use tokio::task::JoinHandle;
+
+use crate::checkpoint::is_ignored_dir;
+use crate::commands::agents::AgentDb;
+
+/// Longest a project snapshot may take before the run's artifacts are skipped
+const SNAPSHOT_TIME_LIMIT: Duration = Duration::from_secs(30);
+
+/// Build output and dependency directories left out of snapshots even when
+/// no `.gitignore` lists them
+const BUILD_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    "out",
+    "__pycache__",
+    "venv",
+];
+
+/// Size and modification time of one project file
+#[derive(Debug, Clone, PartialEq)]
+struct FileState {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Files of a project keyed by path relative to its root
+type ProjectSnapshot = BTreeMap<String, FileState>;
+
+/// A file the run added, modified or deleted
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FileChange {
+    pub path: String,
+    pub size: Option<u64>,          // None for deleted files
+    pub previous_size: Option<u64>, // None for added files
+}
+
+/// Files changed in the project while a run was in progress
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RunArtifacts {
+    pub status: String,         // 'captured' or 'skipped'
+    pub reason: Option<String>, // Why capture was skipped
+    pub added: Vec<FileChange>,
+    pub modified: Vec<FileChange>,
+    pub deleted: Vec<FileChange>,
+}
+
+impl RunArtifacts {
+    fn skipped(reason: String) -> Self {
+        Self {
+            status: "skipped".to_string(),
+            reason: Some(reason),
+            ..Default::default()
+        }
+    }
+
+    pub fn changes_count(&self) -> usize {
+        self.added.len() + self.modified.len() + self.deleted.len()
+    }
+}
+
+/// Record every file under `root` that isn't ignored, giving up once
+/// `time_limit` has passed
+fn snapshot_project(root: &Path, time_limit: Duration) -> Result<ProjectSnapshot, String> {
+    let started = Instant::now();
+    let mut snapshot = ProjectSnapshot::new();
+
+    let walker = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        // Honor .gitignore files even outside a git repository
+        .require_git(false)
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_some_and(|kind| kind.is_dir())
+                || !entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| is_ignored_dir(name) || BUILD_DIRS.contains(&name))
+        })
+        .build();
+    for entry in walker.filter_map(Result::ok) {
+        if started.elapsed() > time_limit {
+            return Err(format!(
+                "hashing the project took longer than {}s",
+                time_limit.as_secs()
+            ));
+        }
+        if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+            continue;
+        }
+        let relative = match entry.path().strip_prefix(root) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => continue,
+        };
+        // Files that vanish mid-walk are left out
+        if let Ok(metadata) = entry.metadata() {
+            snapshot.insert(
+                relative,
+                FileState {
+                    size: metadata.len(),
+                    modified: metadata.modified().ok(),
+                },
+            );
+        }
+    }
+
+    Ok(snapshot)
+}
+
+/// Compare snapshots taken before and after a run
+fn diff_snapshots(before: &ProjectSnapshot, after: &ProjectSnapshot) -> RunArtifacts {
+    let mut artifacts = RunArtifacts {
+        status: "captured".to_string(),
+        ..Default::default()
+    };
+
+    for (path, state) in after {
+        match before.get(path) {
+            None => artifacts.added.push(FileChange {
+                path: path.clone(),
+                size: Some(state.size),
+                previous_size: None,
+            }),
+            Some(previous) if previous != state => artifacts.modified.push(FileChange {
+                path: path.clone(),
+                size: Some(state.size),
+                previous_size: Some(previous.size),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (path, previous) in before {
+        if !after.contains_key(path) {
+            artifacts.deleted.push(FileChange {
+                path: path.clone(),
+                size: None,
+                previous_size: Some(previous.size),
+            });
+        }
+    }
+
+    artifacts
+}
+
+type PendingSnapshot = JoinHandle<Result<ProjectSnapshot, String>>;
+
+/// Snapshots taken when runs started, waiting for the runs to finish
+fn pending_snapshots() -> &'static Mutex<HashMap<i64, PendingSnapshot>> {
+    static PENDING: OnceLock<Mutex<HashMap<i64, PendingSnapshot>>> = OnceLock::new();
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+async fn snapshot_in_background(project_path: &str) -> Result<ProjectSnapshot, String> {
+    let root = PathBuf::from(project_path);
+    tokio::task::spawn_blocking(move || snapshot_project(&root, SNAPSHOT_TIME_LIMIT))
+        .await
+        .map_err(|e| format!("snapshot task failed: {}", e))?
+}
+
+/// Start snapshotting the project as a run starts. Reading file sizes and
+/// times is quick next to Claude starting up, so the snapshot is taken
+/// alongside the launch rather than holding it up.
+pub fn begin_capture(run_id: i64, project_path: &str) {
+    let project_path = project_path.to_string();
+    let snapshot = tokio::spawn(async move {
+        let snapshot = snapshot_in_background(&project_path).await;
+        if let Err(e) = &snapshot {
+            warn!("Skipping file artifacts for run {}: {}", run_id, e);
+        }
+        snapshot
+    });
+    if let Ok(mut pending) = pending_snapshots().lock() {
+        pending.insert(run_id, snapshot);
+    }
+}
+
+/// Drop the snapshot of a run that failed to start
+pub fn discard_capture(run_id: i64) {
+    if let Ok(mut pending) = pending_snapshots().lock() {
+        if let Some(snapshot) = pending.remove(&run_id) {
+            snapshot.abort();
+        }
+    }
+}
+
+/// Snapshot the project after a run and store what changed on the run row
+pub async fn finish_capture(db_path: PathBuf, run_id: i64, project_path: String) {
+    let before = match pending_snapshots()
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.remove(&run_id))
+    {
+        Some(before) => before
+            .await
+            .unwrap_or_else(|e| Err(format!("snapshot task failed: {}", e))),
+        None => return,
+    };
+
+    let artifacts = match before {
+        Ok(before) => match snapshot_in_background(&project_path).await {
+            Ok(after) => diff_snapshots(&before, &after),
+            Err(e) => RunArtifacts::skipped(e),
+        },
+        Err(e) => RunArtifacts::skipped(e),
+    };
+    let changes_count = (artifacts.status == "captured").then(|| artifacts.changes_count() as i64);
+
+    let result = serde_json::to_string(&artifacts)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            let conn = Connection::open(&db_path).map_err(|e| e.to_string())?;
+            conn.execute(
+                "UPDATE agent_runs SET artifacts = ?1, changes_count = ?2 WHERE id = ?3",
+                params![json, changes_count, run_id],
+            )
+            .map_err(|e| e.to_string())
+        });
+    match result {
+        Ok(_) => info!(
+            "Recorded {} file changes for run {}",
+            changes_count.unwrap_or(0),
+            run_id
+        ),
+        Err(e) => warn!("Failed to save file artifacts for run {}: {}", run_id, e),
+    }
+}
+
+/// Files added, modified and deleted during a run, or None when they
+/// weren't recorded (the run is still going or predates artifact capture)
+#[tauri::command]
+pub async fn get_agent_run_artifacts(
+    db: State<'_, AgentDb>,
+    run_id: i64,
+) -> Result<Option<RunArtifacts>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT artifacts FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Agent run {} not found: {}", run_id, e))?;
+
+    json.map(|json| {
+        serde_json::from_str(&json).map_err(|e| format!("Invalid artifacts for run {}: {}", run_id, e))
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshots_diff_into_changes_and_skip_hidden_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join("src/keep.rs"), "same").unwrap();
+        std::fs::write(root.join("src/edit.rs"), "old").unwrap();
+        std::fs::write(root.join("gone.txt"), "bye").unwrap();
+        std::fs::write(root.join(".git/HEAD"), "ref").unwrap();
+        let before = snapshot_project(root, SNAPSHOT_TIME_LIMIT).unwrap();
+
+        std::fs::write(root.join("src/edit.rs"), "newer").unwrap();
+        std::fs::remove_file(root.join("gone.txt")).unwrap();
+        std::fs::write(root.join("new.md"), "hi").unwrap();
+        std::fs::write(root.join(".git/HEAD"), "changed").unwrap();
+        let after = snapshot_project(root, SNAPSHOT_TIME_LIMIT).unwrap();
+
+        let artifacts = diff_snapshots(&before, &after);
+        assert_eq!(artifacts.added[0].path, "new.md");
+        assert_eq!(
+            artifacts.modified,
+            vec![FileChange {
+                path: "src/edit.rs".to_string(),
+                size: Some(5),
+                previous_size: Some(3),
+            }]
+        );
+        assert_eq!(artifacts.deleted[0].path, "gone.txt");
+        assert_eq!(artifacts.changes_count(), 3);
+    }
+
+    #[test]
+    fn test_snapshot_skips_ignored_files_and_build_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["src", "generated", "node_modules/pkg", "target/debug"] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "generated/\n*.log\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("generated/schema.rs"), "").unwrap();
+        std::fs::write(root.join("debug.log"), "").unwrap();
+        std::fs::write(root.join("node_modules/pkg/index.js"), "").unwrap();
+        std::fs::write(root.join("target/debug/app"), "").unwrap();
+
+        let snapshot = snapshot_project(root, SNAPSHOT_TIME_LIMIT).unwrap();
+        let paths: Vec<&str> = snapshot.keys().map(String::as_str).collect();
+        assert_eq!(paths, [".gitignore", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_same_size_rewrite_is_a_modification() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "old").unwrap();
+        let before = snapshot_project(dir.path(), SNAPSHOT_TIME_LIMIT).unwrap();
+
+        std::fs::write(&path, "new").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        let after = snapshot_project(dir.path(), SNAPSHOT_TIME_LIMIT).unwrap();
+        assert_eq!(diff_snapshots(&before, &after).modified.len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_gives_up_after_time_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+        assert!(snapshot_project(dir.path(), Duration::ZERO).is_err());
+    }
+}
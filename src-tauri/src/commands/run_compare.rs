@@ -0,0 +1,289 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::State;
+
+use crate::commands::agents::{
+    agent_run_from_row, AgentDb, AgentRun, AgentRunMetrics, AGENT_RUN_COLUMNS,
+};
+use crate::commands::run_search::read_run_jsonl;
+
+/// Longest turn content included in a comparison; `get_session_output` has the rest
+const MAX_TURN_CONTENT_CHARS: usize = 2000;
+
+/// One run of a comparison with the details needed to explain differences
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ComparedRun {
+    #[serde(flatten)]
+    pub run: AgentRun,
+    pub system_prompt_sha256: Option<String>,
+    pub metrics: Option<AgentRunMetrics>,
+    pub turn_count: usize,
+}
+
+/// Run B's metrics minus run A's, where both are known
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RunMetricDeltas {
+    pub duration_ms: Option<i64>,
+    pub total_tokens: Option<i64>,
+    pub cost_usd: Option<f64>,
+    pub message_count: Option<i64>,
+}
+
+/// A pair of turns at the same index
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TurnComparison {
+    pub index: usize,
+    pub status: String, // 'identical', 'changed', 'only_in_a', 'only_in_b'
+    pub role_a: Option<String>,
+    pub role_b: Option<String>,
+    pub content_a: Option<String>,
+    pub content_b: Option<String>, // Omitted for identical turns
+    pub truncated: bool,
+}
+
+/// Side-by-side comparison of two agent runs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentRunComparison {
+    pub run_a: ComparedRun,
+    pub run_b: ComparedRun,
+    pub same_task: bool,
+    pub same_model: bool,
+    pub same_system_prompt: Option<bool>, // None when a run predates prompt hashing
+    pub deltas: RunMetricDeltas,
+    pub turns: Vec<TurnComparison>,
+}
+
+/// A user or assistant turn reduced to its comparable content
+#[derive(Debug, Clone, PartialEq)]
+struct ComparableTurn {
+    role: String,
+    content: String,
+}
+
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Comparable form of one content block. Tool calls keep their name and
+/// input but not their ids, which differ on every run.
+fn normalize_block(block: &JsonValue) -> Option<String> {
+    match block.get("type").and_then(|t| t.as_str()) {
+        Some("text") => block
+            .get("text")
+            .and_then(|t| t.as_str())
+            .map(normalize_text),
+        Some("tool_use") => Some(format!(
+            "[tool_use {}] {}",
+            block.get("name").and_then(|n| n.as_str()).unwrap_or("tool"),
+            block.get("input").cloned().unwrap_or(JsonValue::Null)
+        )),
+        Some("tool_result") => {
+            let text = match block.get("content") {
+                Some(JsonValue::String(text)) => text.clone(),
+                Some(JsonValue::Array(items)) => items
+                    .iter()
+                    .filter_map(|item| item.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => String::new(),
+            };
+            let is_error = block
+                .get("is_error")
+                .and_then(|e| e.as_bool())
+                .unwrap_or(false);
+            Some(format!(
+                "[{}] {}",
+                if is_error { "tool_error" } else { "tool_result" },
+                normalize_text(&text)
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// User and assistant turns of a run, with streamed lines of one turn merged
+fn comparable_turns(jsonl: &str) -> Vec<ComparableTurn> {
+    let mut turns: Vec<ComparableTurn> = Vec::new();
+
+    for json in jsonl
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+    {
+        let role = match json.get("type").and_then(|t| t.as_str()) {
+            Some(role @ ("user" | "assistant")) => role.to_string(),
+            _ => continue,
+        };
+        let blocks: Vec<String> = match json.get("message").and_then(|m| m.get("content")) {
+            Some(JsonValue::String(text)) => vec![normalize_text(text)],
+            Some(JsonValue::Array(items)) => items.iter().filter_map(normalize_block).collect(),
+            _ => Vec::new(),
+        };
+        let blocks: Vec<String> = blocks.into_iter().filter(|b| !b.is_empty()).collect();
+        if blocks.is_empty() {
+            continue;
+        }
+
+        let content = blocks.join("\n");
+        match turns.last_mut() {
+            Some(last) if last.role == role => {
+                last.content.push('\n');
+                last.content.push_str(&content);
+            }
+            _ => turns.push(ComparableTurn { role, content }),
+        }
+    }
+
+    turns
+}
+
+/// Cap turn content, reporting whether anything was cut
+fn capped(content: &str) -> (String, bool) {
+    match content.char_indices().nth(MAX_TURN_CONTENT_CHARS) {
+        Some((index, _)) => (content[..index].to_string(), true),
+        None => (content.to_string(), false),
+    }
+}
+
+/// Align two transcripts by turn index
+fn compare_turns(a: &[ComparableTurn], b: &[ComparableTurn]) -> Vec<TurnComparison> {
+    (0..a.len().max(b.len()))
+        .map(|index| {
+            let (turn_a, turn_b) = (a.get(index), b.get(index));
+            let status = match (turn_a, turn_b) {
+                (Some(x), Some(y)) if x == y => "identical",
+                (Some(_), Some(_)) => "changed",
+                (Some(_), None) => "only_in_a",
+                _ => "only_in_b",
+            };
+
+            let (content_a, truncated_a) = match turn_a {
+                Some(turn) => {
+                    let (content, truncated) = capped(&turn.content);
+                    (Some(content), truncated)
+                }
+                None => (None, false),
+            };
+            let (content_b, truncated_b) = match turn_b {
+                Some(turn) if status != "identical" => {
+                    let (content, truncated) = capped(&turn.content);
+                    (Some(content), truncated)
+                }
+                _ => (None, false),
+            };
+
+            TurnComparison {
+                index,
+                status: status.to_string(),
+                role_a: turn_a.map(|t| t.role.clone()),
+                role_b: turn_b.map(|t| t.role.clone()),
+                content_a,
+                content_b,
+                truncated: truncated_a || truncated_b,
+            }
+        })
+        .collect()
+}
+
+fn prompt_hash(run: &AgentRun) -> Option<String> {
+    run.metadata
+        .as_deref()
+        .and_then(|m| serde_json::from_str::<JsonValue>(m).ok())
+        .and_then(|m| {
+            m.get("system_prompt_sha256")
+                .and_then(|h| h.as_str())
+                .map(|h| h.to_string())
+        })
+}
+
+fn delta<T: std::ops::Sub<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+    Some(b? - a?)
+}
+
+/// Compare two agent runs' parameters, metrics and transcripts
+#[tauri::command]
+pub async fn compare_agent_runs(
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id_a: i64,
+    run_id_b: i64,
+) -> Result<AgentRunComparison, String> {
+    let (run_a, run_b) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let sql = format!("SELECT {} FROM agent_runs WHERE id = ?1", AGENT_RUN_COLUMNS);
+        let load = |id: i64| {
+            conn.query_row(&sql, params![id], agent_run_from_row)
+                .map_err(|e| format!("Agent run {} not found: {}", id, e))
+        };
+        (load(run_id_a)?, load(run_id_b)?)
+    };
+
+    let load_side = |run: AgentRun| {
+        let jsonl = read_run_jsonl(&run, &registry.0);
+        let turns = jsonl.as_deref().map(comparable_turns).unwrap_or_default();
+        let side = ComparedRun {
+            system_prompt_sha256: prompt_hash(&run),
+            metrics: jsonl
+                .as_deref()
+                .map(|jsonl| AgentRunMetrics::for_run(&run, jsonl)),
+            turn_count: turns.len(),
+            run,
+        };
+        (side, turns)
+    };
+    let (run_a, turns_a) = load_side(run_a);
+    let (run_b, turns_b) = load_side(run_b);
+
+    let metrics_a = run_a.metrics.clone();
+    let metrics_b = run_b.metrics.clone();
+    let deltas = match (metrics_a, metrics_b) {
+        (Some(a), Some(b)) => RunMetricDeltas {
+            duration_ms: delta(a.duration_ms, b.duration_ms),
+            total_tokens: delta(a.total_tokens, b.total_tokens),
+            cost_usd: delta(a.cost_usd, b.cost_usd),
+            message_count: delta(a.message_count, b.message_count),
+        },
+        _ => RunMetricDeltas::default(),
+    };
+
+    Ok(AgentRunComparison {
+        same_task: run_a.run.task == run_b.run.task,
+        same_model: run_a.run.model == run_b.run.model,
+        same_system_prompt: match (&run_a.system_prompt_sha256, &run_b.system_prompt_sha256) {
+            (Some(a), Some(b)) => Some(a == b),
+            _ => None,
+        },
+        deltas,
+        turns: compare_turns(&turns_a, &turns_b),
+        run_a,
+        run_b,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_calls_compare_without_ids() {
+        let a = r#"{"type":"user","message":{"content":"Fix   the bug"}}
+{"type":"assistant","message":{"content":[{"type":"tool_use","id":"toolu_1","name":"Read","input":{"file_path":"a.rs"}}]}}
+{"type":"assistant","message":{"content":[{"type":"text","text":"Done"}]}}"#;
+        let b = r#"{"type":"user","message":{"content":"Fix the bug"}}
+{"type":"assistant","message":{"content":[{"type":"tool_use","id":"toolu_2","name":"Read","input":{"file_path":"a.rs"}}]}}
+{"type":"assistant","message":{"content":[{"type":"text","text":"Fixed it"}]}}
+{"type":"user","message":{"content":"Thanks"}}"#;
+        let turns = compare_turns(&comparable_turns(a), &comparable_turns(b));
+        let statuses: Vec<&str> = turns.iter().map(|t| t.status.as_str()).collect();
+        assert_eq!(statuses, vec!["identical", "changed", "only_in_b"]);
+        assert!(turns[0].content_b.is_none());
+    }
+
+    #[test]
+    fn test_long_turns_are_capped() {
+        let long = "x".repeat(MAX_TURN_CONTENT_CHARS + 10);
+        let (content, truncated) = capped(&long);
+        assert!(truncated);
+        assert_eq!(content.chars().count(), MAX_TURN_CONTENT_CHARS);
+    }
+}
@@ -0,0 +1,393 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet};
+use tauri::State;
+
+use crate::commands::agents::{
+    agent_run_from_row, find_session_file, AgentDb, AgentRun, AGENT_RUN_COLUMNS,
+};
+
+/// Characters of context kept on each side of a match in snippets
+const SNIPPET_CONTEXT_CHARS: usize = 80;
+
+/// Results returned when no limit is given
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+/// Shortest query the trigram index can answer
+pub const MIN_FTS_QUERY_CHARS: usize = 3;
+
+/// An agent run whose output matched a search
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentRunSearchResult {
+    #[serde(flatten)]
+    pub run: AgentRun,
+    pub snippet: String,
+    pub message_index: usize, // Index into the run's session history messages
+}
+
+/// Create the full-text index tables. FTS5 may be missing from the SQLite build,
+/// in which case searches fall back to scanning the JSONL files.
+pub fn init_search_index(conn: &Connection) {
+    if let Err(e) = conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS agent_run_fts USING fts5(
+            content, run_id UNINDEXED, message_index UNINDEXED, tokenize = 'trigram'
+        )",
+        [],
+    ) {
+        warn!("Full-text search index unavailable: {}", e);
+        return;
+    }
+    let _ = conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_fts_runs (
+            run_id INTEGER PRIMARY KEY,
+            indexed_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    );
+}
+
+fn search_index_available(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE name IN ('agent_run_fts', 'agent_run_fts_runs')",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .is_ok_and(|count| count == 2)
+}
+
+/// Collect every string value of a JSON message, which covers text, tool input and tool output
+fn collect_strings(value: &JsonValue, out: &mut Vec<String>) {
+    match value {
+        JsonValue::String(s) => out.push(s.clone()),
+        JsonValue::Array(items) => items.iter().for_each(|item| collect_strings(item, out)),
+        JsonValue::Object(map) => map.values().for_each(|item| collect_strings(item, out)),
+        _ => {}
+    }
+}
+
+/// Searchable text of each message, indexed like `load_agent_session_history` results
+fn message_texts(jsonl: &str) -> Vec<String> {
+    jsonl
+        .lines()
+        .filter_map(|line| serde_json::from_str::<JsonValue>(line).ok())
+        .map(|json| {
+            let mut strings = Vec::new();
+            match json.get("message") {
+                Some(message) => collect_strings(message, &mut strings),
+                None => collect_strings(&json, &mut strings),
+            }
+            strings.join("\n")
+        })
+        .collect()
+}
+
+/// Read a run's JSONL from its session file, falling back to its output file
+pub fn read_run_jsonl(
+    run: &AgentRun,
+    registry: &crate::process::ProcessRegistry,
+) -> Option<String> {
+    if !run.session_id.is_empty() {
+        if let Ok(Some(path)) = find_session_file(&run.session_id) {
+            if let Ok(jsonl) = std::fs::read_to_string(path) {
+                return Some(jsonl);
+            }
+        }
+    }
+    registry
+        .read_run_output(run.id?)
+        .ok()
+        .filter(|output| !output.is_empty())
+}
+
+/// Replace a run's entries in the full-text index
+pub fn index_run_output(conn: &Connection, run_id: i64, jsonl: &str) -> Result<(), String> {
+    if !search_index_available(conn) {
+        return Ok(());
+    }
+
+    conn.execute("DELETE FROM agent_run_fts WHERE run_id = ?1", params![run_id])
+        .map_err(|e| e.to_string())?;
+    {
+        let mut stmt = conn
+            .prepare("INSERT INTO agent_run_fts (content, run_id, message_index) VALUES (?1, ?2, ?3)")
+            .map_err(|e| e.to_string())?;
+        for (index, text) in message_texts(jsonl).iter().enumerate() {
+            if !text.is_empty() {
+                stmt.execute(params![text, run_id, index as i64])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO agent_run_fts_runs (run_id) VALUES (?1)",
+        params![run_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Remove runs from the full-text index, returning the bytes of indexed text dropped
+pub fn remove_runs_from_index(conn: &Connection, run_ids: &[i64]) -> Result<u64, String> {
+    if run_ids.is_empty() || !search_index_available(conn) {
+        return Ok(0);
+    }
+
+    let placeholders = vec!["?"; run_ids.len()].join(", ");
+    let bytes: i64 = conn
+        .query_row(
+            &format!(
+                "SELECT COALESCE(SUM(LENGTH(CAST(content AS BLOB))), 0) FROM agent_run_fts WHERE run_id IN ({})",
+                placeholders
+            ),
+            rusqlite::params_from_iter(run_ids),
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    for table in ["agent_run_fts", "agent_run_fts_runs"] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE run_id IN ({})", table, placeholders),
+            rusqlite::params_from_iter(run_ids),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(bytes as u64)
+}
+
+/// How many of `chars` lowercase into `query_lower`, matching from their start
+fn folded_match_len(chars: &[char], query_lower: &str) -> Option<usize> {
+    let mut rest = query_lower;
+    for (matched, c) in chars.iter().enumerate() {
+        if rest.is_empty() {
+            return Some(matched);
+        }
+        // The query can end partway through a char's lowercase form
+        for lower in c.to_lowercase() {
+            if rest.is_empty() {
+                break;
+            }
+            rest = rest.strip_prefix(lower)?;
+        }
+    }
+    rest.is_empty().then_some(chars.len())
+}
+
+/// Find the first case-insensitive literal match, returning a snippet around it
+pub fn find_match(text: &str, query_lower: &str) -> Option<String> {
+    // Lowercasing can add chars, so the match is found in `text` itself
+    // rather than in a lowercased copy whose positions don't line up
+    let chars: Vec<char> = text.chars().collect();
+    let (match_start, match_len) = (0..=chars.len())
+        .find_map(|start| folded_match_len(&chars[start..], query_lower).map(|len| (start, len)))?;
+    let start = match_start.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let end = (match_start + match_len + SNIPPET_CONTEXT_CHARS).min(chars.len());
+
+    let mut snippet: String = chars[start..end].iter().collect();
+    snippet = snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+/// First matching message of each indexed run: run_id -> (message index, snippet)
+fn search_index(
+    conn: &Connection,
+    query: &str,
+    query_lower: &str,
+) -> Result<HashMap<i64, (usize, String)>, String> {
+    let (sql, param) = if query.chars().count() >= MIN_FTS_QUERY_CHARS {
+        // A quoted phrase is a substring match with the trigram tokenizer
+        (
+            "SELECT run_id, message_index, content FROM agent_run_fts WHERE agent_run_fts MATCH ?1 ORDER BY run_id, message_index",
+            format!("\"{}\"", query.replace('"', "\"\"")),
+        )
+    } else if query.is_ascii() {
+        (
+            "SELECT run_id, message_index, content FROM agent_run_fts WHERE instr(lower(content), ?1) > 0 ORDER BY run_id, message_index",
+            query_lower.to_string(),
+        )
+    } else {
+        // SQLite's lower() only folds ASCII, so leave the matching to find_match
+        (
+            "SELECT run_id, message_index, content FROM agent_run_fts WHERE ?1 IS NOT NULL ORDER BY run_id, message_index",
+            query_lower.to_string(),
+        )
+    };
+
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![param], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut matches = HashMap::new();
+    for row in rows {
+        let (run_id, message_index, content) = row.map_err(|e| e.to_string())?;
+        if matches.contains_key(&run_id) {
+            continue;
+        }
+        // Confirm the literal match; it also yields the snippet
+        if let Some(snippet) = find_match(&content, query_lower) {
+            matches.insert(run_id, (message_index as usize, snippet));
+        }
+    }
+    Ok(matches)
+}
+
+/// Search agent run outputs for a case-insensitive literal string
+#[tauri::command]
+pub async fn search_agent_runs(
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    query: String,
+    agent_id: Option<i64>,
+    status: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<AgentRunSearchResult>, String> {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return Err("Search query cannot be empty".to_string());
+    }
+    let query_lower = query.to_lowercase();
+    let limit = limit.filter(|l| *l > 0).unwrap_or(DEFAULT_SEARCH_LIMIT);
+
+    let (runs, index_available, indexed_runs, index_matches) = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM agent_runs WHERE (?1 IS NULL OR agent_id = ?1) AND (?2 IS NULL OR status = ?2) ORDER BY created_at DESC, id DESC",
+                AGENT_RUN_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let runs = stmt
+            .query_map(params![agent_id, status], agent_run_from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let index_available = search_index_available(&conn);
+        let mut indexed_runs = HashSet::new();
+        let mut index_matches = HashMap::new();
+        if index_available {
+            let mut stmt = conn
+                .prepare("SELECT run_id FROM agent_run_fts_runs")
+                .map_err(|e| e.to_string())?;
+            indexed_runs = stmt
+                .query_map([], |row| row.get::<_, i64>(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<HashSet<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            index_matches = search_index(&conn, &query, &query_lower)?;
+        } else {
+            info!("Full-text index unavailable, scanning run outputs");
+        }
+        (runs, index_available, indexed_runs, index_matches)
+    };
+
+    let mut results = Vec::new();
+    for run in runs {
+        if results.len() >= limit {
+            break;
+        }
+        let run_id = match run.id {
+            Some(id) => id,
+            None => continue,
+        };
+
+        let found = if indexed_runs.contains(&run_id) {
+            index_matches.get(&run_id).cloned()
+        } else {
+            // Slow path: read the JSONL, and index finished runs for next time
+            let jsonl = match read_run_jsonl(&run, &registry.0) {
+                Some(jsonl) => jsonl,
+                None => continue,
+            };
+            let is_finished = !matches!(run.status.as_str(), "pending" | "queued" | "running");
+            if index_available && is_finished {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                if let Err(e) = index_run_output(&conn, run_id, &jsonl) {
+                    warn!("Failed to index output of run {}: {}", run_id, e);
+                }
+            }
+            message_texts(&jsonl)
+                .iter()
+                .enumerate()
+                .find_map(|(index, text)| find_match(text, &query_lower).map(|s| (index, s)))
+        };
+
+        if let Some((message_index, snippet)) = found {
+            results.push(AgentRunSearchResult {
+                run,
+                snippet,
+                message_index,
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_match_is_case_insensitive_with_context() {
+        let text = format!("{} CREATE TABLE User_Sessions_V2 (id) {}", "a".repeat(100), "b".repeat(100));
+        let snippet = find_match(&text, "user_sessions_v2").unwrap();
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("User_Sessions_V2"));
+        assert_eq!(find_match("nothing here", "user_sessions_v2"), None);
+    }
+
+    #[test]
+    fn test_find_match_where_lowercasing_adds_chars() {
+        // 'İ' lowercases to two chars, which used to push the match past the text
+        let text = format!("{} needle {}", "İ".repeat(200), "İ".repeat(3));
+        let snippet = find_match(&text, "needle").unwrap();
+        assert!(snippet.starts_with('…') && snippet.ends_with("İ needle İİİ"));
+
+        let snippet = find_match("Welcome to İSTANBUL", &"İstanbul".to_lowercase()).unwrap();
+        assert_eq!(snippet, "Welcome to İSTANBUL");
+        assert!(find_match("İİİ", "i").is_some());
+        assert_eq!(find_match("İİİ", "needle"), None);
+    }
+
+    #[test]
+    fn test_short_non_ascii_queries_match_the_index() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_search_index(&conn);
+        conn.execute(
+            "INSERT INTO agent_run_fts (content, run_id, message_index) VALUES ('Grüße aus MÜNCHEN', 7, 2)",
+            [],
+        )
+        .unwrap();
+
+        let matches = search_index(&conn, "Ün", "ün").unwrap();
+        assert_eq!(matches[&7], (2, "Grüße aus MÜNCHEN".to_string()));
+        assert!(search_index(&conn, "Öl", "öl").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_message_texts_follow_history_indexes() {
+        let jsonl = r#"{"type":"system","subtype":"init","session_id":"abc"}
+broken line
+{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Write","input":{"content":"DROP TABLE user_sessions_v2"}}]}}"#;
+        let texts = message_texts(jsonl);
+        assert_eq!(texts.len(), 2);
+        assert!(texts[1].contains("user_sessions_v2"));
+    }
+}
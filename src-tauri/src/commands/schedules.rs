@@ -0,0 +1,596 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Timelike};
+use std::collections::HashMap;
+use log::{error, info, warn};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::agents::{execute_agent_with_options, AgentDb, AgentRunOptions};
+use crate::commands::prompt_templates::{check_agent_variables, variables_from_json, variables_to_json};
+
+/// How often the scheduler checks for due schedules
+const SCHEDULER_TICK_SECS: u64 = 30;
+
+/// A due time further in the past than this counts as missed (app closed or asleep)
+const MISSED_GRACE_SECS: i64 = 120;
+
+/// Represents a cron-style schedule for an agent
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AgentSchedule {
+    pub id: Option<i64>,
+    pub agent_id: i64,
+    pub cron_expr: String,
+    pub project_path: String,
+    pub task: String,
+    pub catch_up: bool, // Run once on startup if a trigger was missed while the app was closed
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    pub next_run_at: Option<String>,
+    pub created_at: String,
+    #[serde(default)]
+    pub variables: HashMap<String, String>, // Values for the agent's system prompt variables
+}
+
+/// A parsed five-field cron expression: minute hour day-of-month month day-of-week
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpr {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl CronExpr {
+    /// Parse a standard cron expression or one of the @hourly/@daily/... shorthands
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expanded = match expr.trim().to_lowercase().as_str() {
+            "@hourly" => "0 * * * *".to_string(),
+            "@daily" | "@midnight" => "0 0 * * *".to_string(),
+            "@weekly" => "0 0 * * 0".to_string(),
+            "@monthly" => "0 0 1 * *".to_string(),
+            "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+            other => other.to_string(),
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "Invalid cron expression '{}': expected 5 fields, found {}",
+                expr,
+                fields.len()
+            ));
+        }
+
+        let (minutes, _) = parse_cron_field(fields[0], 0, 59, &[])?;
+        let (hours, _) = parse_cron_field(fields[1], 0, 23, &[])?;
+        let (days_of_month, dom_restricted) = parse_cron_field(fields[2], 1, 31, &[])?;
+        let (months, _) = parse_cron_field(fields[3], 1, 12, &MONTH_NAMES)?;
+        let (mut days_of_week, dow_restricted) = parse_cron_field(fields[4], 0, 7, &DAY_NAMES)?;
+
+        // Both 0 and 7 mean Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            minutes,
+            hours,
+            days_of_month,
+            months,
+            days_of_week,
+            dom_restricted,
+            dow_restricted,
+        })
+    }
+
+    /// Whether the expression fires on the given date (ignoring time of day)
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+
+        let dom = self.days_of_month & (1 << date.day()) != 0;
+        let dow = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+
+        // Standard cron semantics: when both day fields are restricted either may match
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+
+    /// Compute the first firing time strictly after `after`
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let start = after
+            .naive_local()
+            .with_second(0)?
+            .with_nanosecond(0)?
+            + Duration::minutes(1);
+
+        // Five years covers every valid expression, including Feb 29 only schedules
+        let mut date = start.date();
+        for _ in 0..(366 * 5) {
+            if self.matches_date(date) {
+                for hour in 0..24 {
+                    if self.hours & (1 << hour) == 0 {
+                        continue;
+                    }
+                    for minute in 0..60 {
+                        if self.minutes & (1 << minute) == 0 {
+                            continue;
+                        }
+                        let candidate = date.and_hms_opt(hour, minute, 0)?;
+                        if candidate < start {
+                            continue;
+                        }
+                        // Skip local times that don't exist (DST gaps)
+                        if let Some(dt) = tz.from_local_datetime(&candidate).earliest() {
+                            if dt > *after {
+                                return Some(dt);
+                            }
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+
+        None
+    }
+}
+
+/// Parse a single cron field into a bitmask, returning whether it was restricted (not `*`)
+fn parse_cron_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<(u64, bool), String> {
+    let mut mask = 0u64;
+    let restricted = !field.starts_with('*');
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("Invalid step '{}' in cron field '{}'", step, field))?;
+                if step == 0 {
+                    return Err(format!("Step cannot be zero in cron field '{}'", field));
+                }
+                if step > max {
+                    return Err(format!(
+                        "Step {} is larger than {} in cron field '{}'",
+                        step, max, field
+                    ));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (
+                parse_cron_value(a, min, max, names)?,
+                parse_cron_value(b, min, max, names)?,
+            )
+        } else {
+            let value = parse_cron_value(range, min, max, names)?;
+            // "5/15" means "from 5 to the end, every 15"
+            if part.contains('/') {
+                (value, max)
+            } else {
+                (value, value)
+            }
+        };
+
+        if start > end {
+            return Err(format!("Invalid range '{}' in cron field '{}'", range, field));
+        }
+
+        let mut value = start;
+        while value <= end {
+            mask |= 1 << value;
+            value += step;
+        }
+    }
+
+    Ok((mask, restricted))
+}
+
+/// Parse a single cron value, accepting three-letter month/day names where applicable
+fn parse_cron_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, String> {
+    if let Some(index) = names.iter().position(|n| n.eq_ignore_ascii_case(value)) {
+        // Month names start at 1, day names at 0
+        return Ok(index as u32 + min);
+    }
+
+    let parsed: u32 = value
+        .parse()
+        .map_err(|_| format!("Invalid cron value '{}'", value))?;
+    if parsed < min || parsed > max {
+        return Err(format!(
+            "Cron value {} out of range {}-{}",
+            parsed, min, max
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Map a schedules row to an `AgentSchedule`
+fn schedule_from_row(row: &rusqlite::Row) -> rusqlite::Result<AgentSchedule> {
+    Ok(AgentSchedule {
+        id: Some(row.get(0)?),
+        agent_id: row.get(1)?,
+        cron_expr: row.get(2)?,
+        project_path: row.get(3)?,
+        task: row.get(4)?,
+        catch_up: row.get::<_, bool>(5).unwrap_or(false),
+        enabled: row.get::<_, bool>(6).unwrap_or(true),
+        last_run_at: row.get(7)?,
+        next_run_at: row.get(8)?,
+        created_at: row.get(9)?,
+        variables: variables_from_json(row.get::<_, Option<String>>(10)?.as_deref()),
+    })
+}
+
+const SCHEDULE_COLUMNS: &str = "id, agent_id, cron_expr, project_path, task, catch_up, enabled, last_run_at, next_run_at, created_at, variables";
+
+/// Create a cron-style schedule for an agent
+#[tauri::command]
+pub async fn create_agent_schedule(
+    db: State<'_, AgentDb>,
+    agent_id: i64,
+    cron_expr: String,
+    project_path: String,
+    task: String,
+    catch_up: Option<bool>,
+    variables: Option<HashMap<String, String>>,
+) -> Result<AgentSchedule, String> {
+    let variables = variables.unwrap_or_default();
+    let cron = CronExpr::parse(&cron_expr)?;
+
+    if !std::path::Path::new(&project_path).is_dir() {
+        return Err(format!("Project path does not exist: {}", project_path));
+    }
+
+    let next_run_at = cron
+        .next_after(&Local::now())
+        .ok_or_else(|| format!("Cron expression '{}' never fires", cron_expr))?
+        .to_rfc3339();
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    check_agent_variables(&conn, agent_id, &variables)?;
+
+    conn.execute(
+        "INSERT INTO schedules (agent_id, cron_expr, project_path, task, catch_up, next_run_at, variables) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            agent_id,
+            cron_expr.trim(),
+            project_path,
+            task,
+            catch_up.unwrap_or(false),
+            next_run_at,
+            variables_to_json(&variables)
+        ],
+    )
+    .map_err(|e| format!("Failed to create schedule: {}", e))?;
+
+    let id = conn.last_insert_rowid();
+    conn.query_row(
+        &format!("SELECT {} FROM schedules WHERE id = ?1", SCHEDULE_COLUMNS),
+        params![id],
+        schedule_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// List agent schedules (optionally filtered by agent_id)
+#[tauri::command]
+pub async fn list_agent_schedules(
+    db: State<'_, AgentDb>,
+    agent_id: Option<i64>,
+) -> Result<Vec<AgentSchedule>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM schedules WHERE ?1 IS NULL OR agent_id = ?1 ORDER BY created_at DESC",
+            SCHEDULE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let schedules = stmt
+        .query_map(params![agent_id], schedule_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(schedules)
+}
+
+/// Delete an agent schedule
+#[tauri::command]
+pub async fn delete_agent_schedule(db: State<'_, AgentDb>, id: i64) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let deleted = conn
+        .execute("DELETE FROM schedules WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    if deleted == 0 {
+        return Err(format!("Schedule {} not found", id));
+    }
+
+    Ok(())
+}
+
+/// Start the background scheduler that triggers due agent schedules
+pub fn start_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        info!("Agent scheduler started");
+        loop {
+            if let Err(e) = run_due_schedules(&app).await {
+                error!("Agent scheduler tick failed: {}", e);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(SCHEDULER_TICK_SECS)).await;
+        }
+    });
+}
+
+/// Trigger every schedule whose next run time has passed
+async fn run_due_schedules(app: &AppHandle) -> Result<(), String> {
+    let now = Local::now();
+
+    let schedules = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM schedules WHERE enabled = 1",
+                SCHEDULE_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let schedules = stmt
+            .query_map([], schedule_from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        schedules
+    };
+
+    for schedule in schedules {
+        let id = schedule.id.unwrap_or_default();
+        let cron = match CronExpr::parse(&schedule.cron_expr) {
+            Ok(cron) => cron,
+            Err(e) => {
+                warn!("Skipping schedule {} with invalid cron expression: {}", id, e);
+                continue;
+            }
+        };
+
+        let due_at = schedule
+            .next_run_at
+            .as_deref()
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|dt| dt.with_timezone(&Local));
+
+        let due_at = match due_at {
+            Some(due_at) if due_at <= now => due_at,
+            Some(_) => continue,
+            None => {
+                // Never computed (or unparseable) - schedule from now without running
+                update_schedule_times(app, id, None, cron.next_after(&now))?;
+                continue;
+            }
+        };
+
+        let missed = (now - due_at).num_seconds() > MISSED_GRACE_SECS;
+        let next_run_at = cron.next_after(&now);
+
+        if missed && !schedule.catch_up {
+            info!(
+                "Skipping missed run of schedule {} (was due at {})",
+                id, due_at
+            );
+            update_schedule_times(app, id, None, next_run_at)?;
+            continue;
+        }
+
+        if agent_has_active_run(app, schedule.agent_id)? {
+            info!(
+                "Agent {} is already running, skipping schedule {} trigger",
+                schedule.agent_id, id
+            );
+            update_schedule_times(app, id, None, next_run_at)?;
+            continue;
+        }
+
+        // Advance the schedule before executing so a slow spawn can't trigger it twice
+        update_schedule_times(app, id, Some(now), next_run_at)?;
+
+        info!(
+            "Triggering schedule {} for agent {} ({})",
+            id, schedule.agent_id, schedule.cron_expr
+        );
+        // A scheduled run waits for the project rather than being dropped
+        let options = AgentRunOptions {
+            triggered_by: Some("schedule".to_string()),
+            queue_if_busy: true,
+            variables: schedule.variables.clone(),
+            ..Default::default()
+        };
+        match execute_agent_with_options(
+            app.clone(),
+            schedule.agent_id,
+            schedule.project_path.clone(),
+            schedule.task.clone(),
+            options,
+            app.state::<AgentDb>(),
+            app.state::<crate::process::ProcessRegistryState>(),
+        )
+        .await
+        {
+            Ok(run_id) => info!("Schedule {} started agent run {}", id, run_id),
+            Err(e) => error!("Schedule {} failed to start agent run: {}", id, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Check whether the agent has a queued run or one still alive in the process registry
+fn agent_has_active_run(app: &AppHandle, agent_id: i64) -> Result<bool, String> {
+    let running_ids: Vec<i64> = {
+        let db = app.state::<AgentDb>();
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+        // A queued run will start on its own, so it counts as active too
+        let queued: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM agent_runs WHERE agent_id = ?1 AND status = 'queued'",
+                params![agent_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if queued > 0 {
+            return Ok(true);
+        }
+
+        let mut stmt = conn
+            .prepare("SELECT id FROM agent_runs WHERE agent_id = ?1 AND status = 'running'")
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map(params![agent_id], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        ids
+    };
+
+    if running_ids.is_empty() {
+        return Ok(false);
+    }
+
+    let registry = app.state::<crate::process::ProcessRegistryState>();
+    let processes = registry.0.get_running_agent_processes()?;
+    Ok(processes.iter().any(|p| running_ids.contains(&p.run_id)))
+}
+
+/// Persist the last/next run times of a schedule
+fn update_schedule_times(
+    app: &AppHandle,
+    id: i64,
+    last_run_at: Option<DateTime<Local>>,
+    next_run_at: Option<DateTime<Local>>,
+) -> Result<(), String> {
+    let db = app.state::<AgentDb>();
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE schedules SET last_run_at = COALESCE(?1, last_run_at), next_run_at = ?2 WHERE id = ?3",
+        params![
+            last_run_at.map(|dt| dt.to_rfc3339()),
+            next_run_at.map(|dt| dt.to_rfc3339()),
+            id
+        ],
+    )
+    .map_err(|e| format!("Failed to update schedule {}: {}", id, e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn test_daily_schedule() {
+        let cron = CronExpr::parse("0 2 * * *").unwrap();
+        assert_eq!(
+            cron.next_after(&utc(2024, 3, 10, 1, 30)),
+            Some(utc(2024, 3, 10, 2, 0))
+        );
+        assert_eq!(
+            cron.next_after(&utc(2024, 3, 10, 2, 0)),
+            Some(utc(2024, 3, 11, 2, 0))
+        );
+    }
+
+    #[test]
+    fn test_steps_ranges_and_names() {
+        let cron = CronExpr::parse("*/15 9-17 * * mon-fri").unwrap();
+        // Saturday 2024-03-09 rolls to Monday 09:00
+        assert_eq!(
+            cron.next_after(&utc(2024, 3, 9, 12, 0)),
+            Some(utc(2024, 3, 11, 9, 0))
+        );
+        assert_eq!(
+            cron.next_after(&utc(2024, 3, 11, 9, 7)),
+            Some(utc(2024, 3, 11, 9, 15))
+        );
+
+        let cron = CronExpr::parse("30 0 1 jan,jul *").unwrap();
+        assert_eq!(
+            cron.next_after(&utc(2024, 2, 1, 0, 0)),
+            Some(utc(2024, 7, 1, 0, 30))
+        );
+    }
+
+    #[test]
+    fn test_day_of_month_or_day_of_week() {
+        // Fires on the 13th OR on Fridays
+        let cron = CronExpr::parse("0 0 13 * 5").unwrap();
+        assert_eq!(
+            cron.next_after(&utc(2024, 3, 1, 12, 0)),
+            Some(utc(2024, 3, 8, 0, 0))
+        );
+        assert_eq!(
+            cron.next_after(&utc(2024, 3, 11, 0, 0)),
+            Some(utc(2024, 3, 13, 0, 0))
+        );
+
+        // 7 is Sunday as well
+        let cron = CronExpr::parse("0 0 * * 7").unwrap();
+        assert_eq!(
+            cron.next_after(&utc(2024, 3, 11, 0, 0)),
+            Some(utc(2024, 3, 17, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_leap_day_and_shorthands() {
+        let cron = CronExpr::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            cron.next_after(&utc(2024, 3, 1, 0, 0)),
+            Some(utc(2028, 2, 29, 0, 0))
+        );
+
+        assert_eq!(
+            CronExpr::parse("@daily").unwrap(),
+            CronExpr::parse("0 0 * * *").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert!(CronExpr::parse("* * * *").is_err());
+        assert!(CronExpr::parse("60 * * * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+        assert!(CronExpr::parse("59/4294967295 * * * *").is_err());
+        assert!(CronExpr::parse("*/60 * * * *").is_err());
+        assert!(CronExpr::parse("5-1 * * * *").is_err());
+        assert!(CronExpr::parse("0 0 31 2 *").unwrap().next_after(&utc(2024, 1, 1, 0, 0)).is_none());
+    }
+}
@@ -0,0 +1,347 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::claude::{create_system_command, get_claude_dir};
+use super::claude_env::claude_env_for;
+use super::session_titles::{load_session_titles, save_session_title};
+use crate::commands::agents::AgentDb;
+
+/// Emitted with `{ project_id, session_id, title }` when a title is generated
+pub const SESSION_TITLE_EVENT: &str = "session-title-updated";
+
+/// The cheapest model, which is plenty for a title
+const TITLE_MODEL: &str = "haiku";
+
+/// Longest the title call may take before it's abandoned
+const TITLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Opening messages the title is based on
+const TITLE_TURNS: usize = 4;
+
+/// Characters kept of each message, so pasted logs don't dominate
+const MAX_TURN_CHARS: usize = 1500;
+
+/// Assistant answers after which a new session is titled
+const AUTO_TITLE_AFTER_TURNS: usize = 2;
+
+const TITLE_PROMPT: &str =
+    "Summarize the topic of this conversation as a title of at most 8 words. \
+Reply with the title only, without quotes or a trailing period.";
+
+/// The text of a message's content, whether a string or text blocks
+fn message_text(content: &JsonValue) -> String {
+    match content {
+        JsonValue::String(text) => text.clone(),
+        JsonValue::Array(blocks) => blocks
+            .iter()
+            .filter(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))
+            .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// Messages the CLI adds around slash commands rather than the user typing them
+fn is_generated_text(text: &str) -> bool {
+    text.starts_with("<command-")
+        || text.starts_with("<local-command")
+        || text.starts_with("Caveat:")
+}
+
+/// The first `max_turns` user and assistant messages with text, as (role, text)
+pub fn extract_title_turns<R: BufRead>(reader: R, max_turns: usize) -> Vec<(String, String)> {
+    let mut turns = Vec::new();
+    for line in reader.lines().map_while(Result::ok) {
+        if turns.len() >= max_turns {
+            break;
+        }
+        let Ok(entry) = serde_json::from_str::<JsonValue>(&line) else {
+            continue;
+        };
+        let role = match entry.get("type").and_then(|t| t.as_str()) {
+            Some(role @ ("user" | "assistant")) => role,
+            _ => continue,
+        };
+        if entry.get("isMeta").and_then(|m| m.as_bool()) == Some(true) {
+            continue;
+        }
+        let text = entry
+            .get("message")
+            .and_then(|m| m.get("content"))
+            .map(message_text)
+            .unwrap_or_default();
+        let text = text.trim();
+        if text.is_empty() || is_generated_text(text) {
+            continue;
+        }
+        turns.push((
+            role.to_string(),
+            text.chars().take(MAX_TURN_CHARS).collect(),
+        ));
+    }
+    turns
+}
+
+/// How many answers Claude has given: assistant messages are written once per
+/// content block, so they're counted by message id
+pub fn count_assistant_turns<R: BufRead>(reader: R) -> usize {
+    let mut ids = HashSet::new();
+    let mut without_id = 0;
+    for line in reader.lines().map_while(Result::ok) {
+        let Ok(entry) = serde_json::from_str::<JsonValue>(&line) else {
+            continue;
+        };
+        if entry.get("type").and_then(|t| t.as_str()) != Some("assistant") {
+            continue;
+        }
+        match entry
+            .get("message")
+            .and_then(|m| m.get("id"))
+            .and_then(|id| id.as_str())
+        {
+            Some(id) => {
+                ids.insert(id.to_string());
+            }
+            None => without_id += 1,
+        }
+    }
+    ids.len() + without_id
+}
+
+/// The model's reply reduced to a title: its first line, unquoted
+pub fn clean_title(reply: &str) -> Option<String> {
+    let line = reply.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.trim_start_matches("Title:").trim();
+    let title = line
+        .trim_matches(|c| matches!(c, '"' | '\'' | '`' | '*'))
+        .trim_end_matches('.')
+        .trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+fn session_file(project_id: &str, session_id: &str) -> Result<PathBuf, String> {
+    Ok(get_claude_dir()
+        .map_err(|e| e.to_string())?
+        .join("projects")
+        .join(project_id)
+        .join(format!("{}.jsonl", session_id)))
+}
+
+fn count_session_turns(project_id: &str, session_id: &str) -> usize {
+    session_file(project_id, session_id)
+        .and_then(|path| fs::File::open(path).map_err(|e| e.to_string()))
+        .map(|file| count_assistant_turns(BufReader::new(file)))
+        .unwrap_or(0)
+}
+
+/// Whether auto-titling is on; it is off until turned on
+pub fn load_auto_title_setting(conn: &Connection) -> Result<bool, String> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = 'auto_title_sessions'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(value.as_deref() == Some("true"))
+}
+
+/// Ask Claude for a title for a session and store it as the session's title
+async fn generate_title(
+    app: &AppHandle,
+    db: &AgentDb,
+    project_id: &str,
+    session_id: &str,
+) -> Result<String, String> {
+    let path = session_file(project_id, session_id)?;
+    let file = fs::File::open(&path).map_err(|e| format!("Failed to open session file: {}", e))?;
+    let turns = extract_title_turns(BufReader::new(file), TITLE_TURNS);
+    if turns.is_empty() {
+        return Err("The session has no messages to title it by".to_string());
+    }
+    let conversation = turns
+        .iter()
+        .map(|(role, text)| format!("{}: {}", role, text))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let prompt = format!(
+        "{}\n\n<conversation>\n{}\n</conversation>",
+        TITLE_PROMPT, conversation
+    );
+
+    let claude_path = crate::claude_binary::find_claude_binary(app)?;
+    let args = [
+        "-p",
+        &prompt,
+        "--model",
+        TITLE_MODEL,
+        "--output-format",
+        "text",
+        "--max-turns",
+        "1",
+    ]
+    .map(String::from)
+    .to_vec();
+    // Outside the project, so the title call doesn't show up among its sessions
+    let scratch_dir = std::env::temp_dir();
+    let env = claude_env_for(db, None);
+    let mut cmd = create_system_command(&claude_path, args, &scratch_dir.to_string_lossy(), &env);
+    cmd.kill_on_drop(true);
+    let output = tokio::time::timeout(TITLE_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| "Timed out generating a session title".to_string())?
+        .map_err(|e| format!("Failed to run Claude: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Claude failed to generate a title: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let title = clean_title(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| "Claude returned an empty title".to_string())?;
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let title = save_session_title(&conn, project_id, session_id, &title)?
+        .ok_or_else(|| "Claude returned an empty title".to_string())?;
+    let _ = app.emit(
+        SESSION_TITLE_EVENT,
+        serde_json::json!({
+            "project_id": project_id,
+            "session_id": session_id,
+            "title": title,
+        }),
+    );
+    Ok(title)
+}
+
+/// A session to title in the background once Claude has answered twice
+pub struct AutoTitleWatch {
+    project_id: String,
+    session_id: String,
+    turns_before: usize,
+}
+
+impl AutoTitleWatch {
+    /// Watch a run of a session when auto-titling is on, the session has no
+    /// title and it hasn't had two answers yet
+    pub fn start(db: &AgentDb, project_id: &str, session_id: &str) -> Option<Self> {
+        let titled = db.0.lock().ok().and_then(|conn| {
+            let enabled = load_auto_title_setting(&conn).ok()?;
+            let titles = load_session_titles(&conn, project_id).ok()?;
+            Some(!enabled || titles.contains_key(session_id))
+        });
+        if titled != Some(false) {
+            return None;
+        }
+        let turns_before = count_session_turns(project_id, session_id);
+        (turns_before < AUTO_TITLE_AFTER_TURNS).then(|| Self {
+            project_id: project_id.to_string(),
+            session_id: session_id.to_string(),
+            turns_before,
+        })
+    }
+
+    /// After the run, title the session if it reached its second answer.
+    /// Runs in the background and only logs failures.
+    pub fn finish(self, app: &AppHandle) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let turns = count_session_turns(&self.project_id, &self.session_id);
+            if self.turns_before >= AUTO_TITLE_AFTER_TURNS || turns < AUTO_TITLE_AFTER_TURNS {
+                return;
+            }
+            let db = app.state::<AgentDb>();
+            match generate_title(&app, &db, &self.project_id, &self.session_id).await {
+                Ok(title) => log::info!("Titled session {}: {}", self.session_id, title),
+                Err(e) => log::debug!("Skipped titling session {}: {}", self.session_id, e),
+            }
+        });
+    }
+}
+
+/// Generate a title for a session from its first messages with a quick
+/// Claude call, store it as the session's custom title and return it
+#[tauri::command]
+pub async fn generate_session_title(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    project_id: String,
+    session_id: String,
+) -> Result<String, String> {
+    log::info!("Generating a title for session {}", session_id);
+    generate_title(&app, &db, &project_id, &session_id).await
+}
+
+/// Whether new sessions are titled automatically after Claude's second answer
+#[tauri::command]
+pub async fn get_auto_title_sessions(db: State<'_, AgentDb>) -> Result<bool, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_auto_title_setting(&conn)
+}
+
+/// Turn automatic session titles on or off
+#[tauri::command]
+pub async fn set_auto_title_sessions(db: State<'_, AgentDb>, enabled: bool) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value) VALUES ('auto_title_sessions', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = ?1",
+        params![enabled.to_string()],
+    )
+    .map_err(|e| format!("Failed to save auto-title setting: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_turns_skip_generated_messages_and_count_answers_by_id() {
+        let lines = [
+            r#"{"type":"summary","summary":"old"}"#,
+            r#"{"type":"user","message":{"role":"user","content":"<command-name>/clear</command-name>"}}"#,
+            r#"{"type":"user","isMeta":true,"message":{"role":"user","content":"meta"}}"#,
+            r#"{"type":"user","message":{"role":"user","content":"Fix the flaky upload test"}}"#,
+            r#"{"type":"assistant","message":{"id":"m1","content":[{"type":"text","text":"Looking."}]}}"#,
+            r#"{"type":"assistant","message":{"id":"m1","content":[{"type":"tool_use","name":"Read"}]}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","content":"..."}]}}"#,
+            r#"{"type":"assistant","message":{"id":"m2","content":[{"type":"text","text":"Fixed it."}]}}"#,
+        ]
+        .join("\n");
+
+        let turns = extract_title_turns(Cursor::new(&lines), TITLE_TURNS);
+        assert_eq!(
+            turns,
+            [
+                ("user".to_string(), "Fix the flaky upload test".to_string()),
+                ("assistant".to_string(), "Looking.".to_string()),
+                ("assistant".to_string(), "Fixed it.".to_string()),
+            ]
+        );
+        assert_eq!(extract_title_turns(Cursor::new(&lines), 1).len(), 1);
+        assert_eq!(count_assistant_turns(Cursor::new(&lines)), 2);
+    }
+
+    #[test]
+    fn test_replies_are_cleaned_into_titles() {
+        assert_eq!(
+            clean_title("\n\"Fix flaky upload test.\"\nextra").as_deref(),
+            Some("Fix flaky upload test")
+        );
+        assert_eq!(
+            clean_title("Title: **S3 presign helper**").as_deref(),
+            Some("S3 presign helper")
+        );
+        assert_eq!(clean_title("  \n \"\" "), None);
+    }
+}
@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::io::{BufRead, Read, Write};
+
+/// Entry types that can be asked for when loading history
+const HISTORY_ENTRY_TYPES: [&str; 3] = ["user", "assistant", "summary"];
+
+/// A range of a session's JSONL entries
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SessionHistoryChunk {
+    pub messages: Vec<JsonValue>,
+    /// JSONL line of each message; the same index checkpoints record as `message_index`
+    pub indices: Vec<usize>,
+    /// Lines in the scanned range that weren't valid JSON
+    pub skipped_lines: usize,
+    /// Line to continue from, or `None` once the end of the file was reached
+    pub next_index: Option<usize>,
+}
+
+/// Check the entry types a caller filters history by
+pub fn validate_entry_types(entry_types: &[String]) -> Result<(), String> {
+    match entry_types
+        .iter()
+        .find(|t| !HISTORY_ENTRY_TYPES.contains(&t.as_str()))
+    {
+        Some(unknown) => Err(format!(
+            "Unsupported entry type '{}', expected one of {}",
+            unknown,
+            HISTORY_ENTRY_TYPES.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Stream JSONL lines from `from_index` on, collecting up to `count` entries
+/// whose type is in `entry_types` (all entries when `None`). Lines before the
+/// range are skipped without being parsed.
+pub fn read_history_chunk<R: BufRead>(
+    mut reader: R,
+    from_index: usize,
+    count: Option<usize>,
+    entry_types: Option<&[String]>,
+) -> Result<SessionHistoryChunk, String> {
+    let mut chunk = SessionHistoryChunk::default();
+    let mut line = Vec::new();
+    let mut index = 0;
+    loop {
+        if count.is_some_and(|count| chunk.messages.len() >= count) {
+            chunk.next_index = Some(index);
+            break;
+        }
+        line.clear();
+        let read = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| format!("Failed to read session file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        index += 1;
+        if index <= from_index {
+            continue;
+        }
+
+        let json = match serde_json::from_slice::<JsonValue>(&line) {
+            Ok(json) => json,
+            Err(_) => {
+                chunk.skipped_lines += 1;
+                continue;
+            }
+        };
+        if let Some(entry_types) = entry_types {
+            let entry_type = json
+                .get("type")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default();
+            if !entry_types.iter().any(|t| t == entry_type) {
+                continue;
+            }
+        }
+        chunk.messages.push(json);
+        chunk.indices.push(index - 1);
+    }
+    Ok(chunk)
+}
+
+/// Number of lines in a session file, counted without parsing them
+pub fn count_lines<R: Read>(mut reader: R) -> Result<usize, String> {
+    let mut buf = vec![0; 64 * 1024];
+    let mut lines = 0;
+    let mut last = b'\n';
+    loop {
+        let read = reader
+            .read(&mut buf)
+            .map_err(|e| format!("Failed to read session file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        lines += buf[..read].iter().filter(|b| **b == b'\n').count();
+        last = buf[read - 1];
+    }
+    // A final line without a trailing newline still counts
+    if last != b'\n' {
+        lines += 1;
+    }
+    Ok(lines)
+}
+
+/// Copy a session's JSONL lines up to and including `message_index` to
+/// `writer`, moving them to `new_session_id`. Leading summary lines are kept
+/// like any other; the entry at `message_index` must be a complete JSON line.
+pub fn copy_history_prefix<R: BufRead, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    message_index: usize,
+    session_id: &str,
+    new_session_id: &str,
+) -> Result<(), String> {
+    let old_field = format!("\"sessionId\":\"{}\"", session_id);
+    let new_field = format!("\"sessionId\":\"{}\"", new_session_id);
+    let mut line = Vec::new();
+    for index in 0..=message_index {
+        line.clear();
+        let read = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| format!("Failed to read session file: {}", e))?;
+        if read == 0 {
+            return Err(format!(
+                "Message {} doesn't exist; the session has {} entries",
+                message_index, index
+            ));
+        }
+        if index == message_index && serde_json::from_slice::<JsonValue>(&line).is_err() {
+            return Err(format!(
+                "Message {} isn't a complete session entry",
+                message_index
+            ));
+        }
+
+        write_moved_line(&mut writer, &line, &old_field, &new_field)?;
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to write session file: {}", e))
+}
+
+/// Copy all of a session's complete JSONL lines to `writer`, moving them to
+/// `new_session_id`. A last line that is still being written is left out.
+pub fn copy_history<R: BufRead, W: Write>(
+    mut reader: R,
+    mut writer: W,
+    session_id: &str,
+    new_session_id: &str,
+) -> Result<(), String> {
+    let old_field = format!("\"sessionId\":\"{}\"", session_id);
+    let new_field = format!("\"sessionId\":\"{}\"", new_session_id);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| format!("Failed to read session file: {}", e))?;
+        if !line.ends_with(b"\n") {
+            break;
+        }
+        write_moved_line(&mut writer, &line, &old_field, &new_field)?;
+    }
+    writer
+        .flush()
+        .map_err(|e| format!("Failed to write session file: {}", e))
+}
+
+fn write_moved_line<W: Write>(
+    writer: &mut W,
+    line: &[u8],
+    old_field: &str,
+    new_field: &str,
+) -> Result<(), String> {
+    let text = String::from_utf8_lossy(line);
+    let text = text
+        .trim_end_matches(['\r', '\n'])
+        .replace(old_field, new_field);
+    writer
+        .write_all(text.as_bytes())
+        .and_then(|_| writer.write_all(b"\n"))
+        .map_err(|e| format!("Failed to write session file: {}", e))
+}
+
+/// The latest compaction in a session: Claude's summary, and how many
+/// messages the history has from it on, the summary included
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Compaction {
+    pub summary: String,
+    pub message_count: usize,
+}
+
+/// Find the last compaction summary in a session's JSONL
+pub fn read_compaction<R: BufRead>(reader: R) -> Option<Compaction> {
+    let mut latest: Option<Compaction> = None;
+    for line in reader.lines().map_while(Result::ok) {
+        let Ok(entry) = serde_json::from_str::<JsonValue>(&line) else {
+            continue;
+        };
+        if entry.get("isCompactSummary").and_then(|v| v.as_bool()) == Some(true) {
+            let summary = match entry.pointer("/message/content") {
+                Some(JsonValue::String(text)) => text.clone(),
+                Some(JsonValue::Array(blocks)) => blocks
+                    .iter()
+                    .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                _ => String::new(),
+            };
+            latest = Some(Compaction {
+                summary,
+                message_count: 1,
+            });
+        } else if let Some(compaction) = latest.as_mut() {
+            let kind = entry.get("type").and_then(|t| t.as_str());
+            if matches!(kind, Some("user") | Some("assistant")) {
+                compaction.message_count += 1;
+            }
+        }
+    }
+    latest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const SESSION: &str = "{\"type\":\"summary\"}\n{\"type\":\"user\",\"n\":1}\nnot json\n\n{\"type\":\"assistant\",\"n\":2}\n{\"type\":\"user\",\"n\":3}";
+
+    #[test]
+    fn test_chunks_keep_line_indices_and_skip_bad_lines() {
+        assert_eq!(count_lines(Cursor::new(SESSION)).unwrap(), 6);
+        assert_eq!(count_lines(Cursor::new("a\nb\n")).unwrap(), 2);
+        assert_eq!(count_lines(Cursor::new("")).unwrap(), 0);
+
+        let all = read_history_chunk(Cursor::new(SESSION), 0, None, None).unwrap();
+        assert_eq!(all.indices, [0, 1, 4, 5]);
+        assert_eq!(all.skipped_lines, 2);
+        assert_eq!(all.next_index, None);
+
+        let page = read_history_chunk(Cursor::new(SESSION), 1, Some(2), None).unwrap();
+        assert_eq!(page.indices, [1, 4]);
+        assert_eq!(page.messages[1]["n"], 2);
+        assert_eq!(page.next_index, Some(5));
+
+        let users = ["user".to_string()];
+        let page = read_history_chunk(Cursor::new(SESSION), 2, None, Some(&users)).unwrap();
+        assert_eq!(page.indices, [5]);
+        assert!(validate_entry_types(&users).is_ok());
+        assert!(validate_entry_types(&["system".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_history_prefix_is_cut_on_lines_and_moved_to_the_new_session() {
+        let session = "{\"type\":\"summary\"}\n{\"type\":\"user\",\"sessionId\":\"old\"}\n{\"type\":\"assistant\",\"sessionId\":\"old\"}\n{\"type\":\"user\",\"sess";
+
+        let mut copy = Vec::new();
+        copy_history_prefix(Cursor::new(session), &mut copy, 1, "old", "new").unwrap();
+        assert_eq!(
+            String::from_utf8(copy).unwrap(),
+            "{\"type\":\"summary\"}\n{\"type\":\"user\",\"sessionId\":\"new\"}\n"
+        );
+
+        // A partially written last line and indices past the end are refused
+        assert!(copy_history_prefix(Cursor::new(session), Vec::new(), 3, "old", "new").is_err());
+        assert!(copy_history_prefix(Cursor::new(session), Vec::new(), 4, "old", "new").is_err());
+    }
+
+    #[test]
+    fn test_latest_compaction_is_found_with_the_messages_after_it() {
+        let session = [
+            r#"{"type":"user","message":{"content":"old"}}"#,
+            r#"{"type":"user","isCompactSummary":true,"message":{"content":"first"}}"#,
+            r#"{"type":"system","subtype":"compact_boundary"}"#,
+            r#"{"type":"user","isCompactSummary":true,"message":{"content":[{"type":"text","text":"second"}]}}"#,
+            r#"{"type":"assistant","message":{"content":"ok"}}"#,
+        ]
+        .join("\n");
+        assert_eq!(
+            read_compaction(Cursor::new(session)),
+            Some(Compaction {
+                summary: "second".to_string(),
+                message_count: 2,
+            })
+        );
+        assert_eq!(read_compaction(Cursor::new(SESSION)), None);
+    }
+
+    #[test]
+    fn test_whole_history_is_copied_without_a_partial_last_line() {
+        let session = "{\"type\":\"user\",\"sessionId\":\"old\"}\n{\"type\":\"assistant\",\"sessionId\":\"old\"}\n{\"type\":\"user\",\"sess";
+
+        let mut copy = Vec::new();
+        copy_history(Cursor::new(session), &mut copy, "old", "new").unwrap();
+        assert_eq!(
+            String::from_utf8(copy).unwrap(),
+            "{\"type\":\"user\",\"sessionId\":\"new\"}\n{\"type\":\"assistant\",\"sessionId\":\"new\"}\n"
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+
+use super::claude::session_file_path;
+
+/// How entries of the merged sessions are ordered
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// Turns from all sessions interleaved by when they started
+    Chronological,
+    /// Each session's turns after the previous session's, in the given order
+    Sequential,
+}
+
+/// Entries the merged session took from one source session
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MergeSourceCount {
+    pub session_id: String,
+    pub entries: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedSession {
+    pub session_id: String,
+    pub sources: Vec<MergeSourceCount>,
+    pub warnings: Vec<String>,
+}
+
+/// Merged entries and how many came from each source
+#[derive(Debug, Default)]
+pub struct MergedEntries {
+    pub entries: Vec<JsonValue>,
+    pub taken: Vec<usize>,
+    pub warnings: Vec<String>,
+}
+
+/// Whether an entry is a prompt the user typed, as opposed to a tool result,
+/// which has to stay right after the tool use it answers
+fn is_prompt(entry: &JsonValue) -> bool {
+    if entry.get("type").and_then(|t| t.as_str()) != Some("user") {
+        return false;
+    }
+    match entry.pointer("/message/content") {
+        Some(JsonValue::Array(items)) => !items
+            .iter()
+            .any(|item| item.get("type").and_then(|t| t.as_str()) == Some("tool_result")),
+        _ => true,
+    }
+}
+
+fn timestamp(entry: &JsonValue) -> Option<DateTime<Utc>> {
+    entry
+        .get("timestamp")
+        .and_then(|t| t.as_str())
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// A session's entries split into turns, each starting at a prompt, so
+/// interleaving never separates a tool use from its result
+fn split_turns(entries: Vec<JsonValue>) -> Vec<Vec<JsonValue>> {
+    let mut turns: Vec<Vec<JsonValue>> = Vec::new();
+    for entry in entries {
+        match turns.last_mut() {
+            Some(turn) if !is_prompt(&entry) => turn.push(entry),
+            _ => turns.push(vec![entry]),
+        }
+    }
+    turns
+}
+
+/// Merge sessions' entries into one history under `new_session_id`.
+///
+/// Summaries go first, without duplicates. Entries seen before, such as the
+/// shared history of a fork and its original, are kept once. Every message
+/// gets a new UUID and its parent becomes the message before it, so the
+/// result is a single chain Claude can resume.
+pub fn merge_session_entries(
+    sources: Vec<Vec<JsonValue>>,
+    strategy: MergeStrategy,
+    new_session_id: &str,
+) -> MergedEntries {
+    let mut merged = MergedEntries {
+        taken: vec![0; sources.len()],
+        ..Default::default()
+    };
+
+    // Sessions that ran in different directories may not make sense together
+    let distinct: HashSet<&str> = sources
+        .iter()
+        .filter_map(|entries| {
+            entries
+                .iter()
+                .find_map(|entry| entry.get("cwd").and_then(|cwd| cwd.as_str()))
+        })
+        .collect();
+    if distinct.len() > 1 {
+        let mut distinct: Vec<&str> = distinct.into_iter().collect();
+        distinct.sort_unstable();
+        merged.warnings.push(format!(
+            "The sessions ran in different directories: {}",
+            distinct.join(", ")
+        ));
+    }
+
+    let mut summaries: Vec<(usize, JsonValue)> = Vec::new();
+    let mut queues: Vec<VecDeque<Vec<JsonValue>>> = Vec::new();
+    for entries in sources {
+        let (source_summaries, rest): (Vec<JsonValue>, Vec<JsonValue>) = entries
+            .into_iter()
+            .partition(|entry| entry.get("type").and_then(|t| t.as_str()) == Some("summary"));
+        summaries.extend(source_summaries.into_iter().map(|s| (queues.len(), s)));
+        queues.push(split_turns(rest).into());
+    }
+
+    // Pick turns in order, from the earliest pending turn or source by source
+    let mut ordered: Vec<(usize, Vec<JsonValue>)> = Vec::new();
+    loop {
+        let next = match strategy {
+            MergeStrategy::Sequential => queues.iter().position(|queue| !queue.is_empty()),
+            MergeStrategy::Chronological => queues
+                .iter()
+                .enumerate()
+                .filter_map(|(source, queue)| {
+                    let turn = queue.front()?;
+                    let started = turn.iter().find_map(timestamp);
+                    Some((started.unwrap_or(DateTime::<Utc>::MIN_UTC), source))
+                })
+                .min()
+                .map(|(_, source)| source),
+        };
+        let Some(source) = next else {
+            break;
+        };
+        if let Some(turn) = queues[source].pop_front() {
+            ordered.push((source, turn));
+        }
+    }
+
+    let mut new_uuids: HashMap<String, String> = HashMap::new();
+    let mut seen_entries: HashSet<String> = HashSet::new();
+    let mut parent: Option<String> = None;
+    let mut body = Vec::new();
+    for (source, turn) in ordered {
+        for mut entry in turn {
+            let Some(object) = entry.as_object_mut() else {
+                continue;
+            };
+            match object
+                .get("uuid")
+                .and_then(|u| u.as_str())
+                .map(str::to_string)
+            {
+                Some(uuid) => {
+                    if new_uuids.contains_key(&uuid) {
+                        continue;
+                    }
+                    let new_uuid = uuid::Uuid::new_v4().to_string();
+                    new_uuids.insert(uuid, new_uuid.clone());
+                    object.insert("uuid".to_string(), JsonValue::String(new_uuid.clone()));
+                    object.insert(
+                        "parentUuid".to_string(),
+                        parent.clone().map_or(JsonValue::Null, JsonValue::String),
+                    );
+                    parent = Some(new_uuid);
+                }
+                // Entries without an ID, like headers, are collapsed when identical
+                None => {
+                    if !seen_entries.insert(JsonValue::Object(object.clone()).to_string()) {
+                        continue;
+                    }
+                }
+            }
+            if object.contains_key("sessionId") {
+                object.insert(
+                    "sessionId".to_string(),
+                    JsonValue::String(new_session_id.to_string()),
+                );
+            }
+            merged.taken[source] += 1;
+            body.push(entry);
+        }
+    }
+
+    let mut seen_summaries = HashSet::new();
+    for (source, mut summary) in summaries {
+        if let Some(leaf) = summary.get("leafUuid").and_then(|u| u.as_str()) {
+            if let Some(new_uuid) = new_uuids.get(leaf) {
+                summary["leafUuid"] = JsonValue::String(new_uuid.clone());
+            }
+        }
+        if seen_summaries.insert(summary.to_string()) {
+            merged.taken[source] += 1;
+            merged.entries.push(summary);
+        }
+    }
+    merged.entries.extend(body);
+    merged
+}
+
+/// A session's entries, skipping lines that aren't valid JSON
+fn read_entries(project_id: &str, session_id: &str) -> Result<Vec<JsonValue>, String> {
+    let path = session_file_path(project_id, session_id)?;
+    let file = fs::File::open(&path).map_err(|e| format!("Failed to open session file: {}", e))?;
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read session file: {}", e))?;
+        if let Ok(entry) = serde_json::from_str::<JsonValue>(&line) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Combine sessions of a project into a new session that can be resumed,
+/// ordered by `strategy`. The source sessions are left as they were.
+#[tauri::command]
+pub async fn merge_sessions(
+    project_id: String,
+    session_ids: Vec<String>,
+    strategy: MergeStrategy,
+) -> Result<MergedSession, String> {
+    if session_ids.len() < 2 {
+        return Err("Choose at least two sessions to merge".to_string());
+    }
+    if session_ids.iter().collect::<HashSet<_>>().len() != session_ids.len() {
+        return Err("A session can only be merged once".to_string());
+    }
+    log::info!("Merging sessions {:?} in {}", session_ids, project_id);
+
+    let sources = session_ids
+        .iter()
+        .map(|session_id| read_entries(&project_id, session_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let new_session_id = uuid::Uuid::new_v4().to_string();
+    let merged = merge_session_entries(sources, strategy, &new_session_id);
+    for warning in &merged.warnings {
+        log::warn!("Merging sessions: {}", warning);
+    }
+
+    // Written next to the first session, appearing only once complete
+    let project_dir = session_file_path(&project_id, &session_ids[0])?
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .ok_or_else(|| "Session file has no project directory".to_string())?;
+    let mut temp = tempfile::NamedTempFile::new_in(&project_dir)
+        .map_err(|e| format!("Failed to create session file: {}", e))?;
+    {
+        let mut writer = std::io::BufWriter::new(temp.as_file_mut());
+        for entry in &merged.entries {
+            writeln!(writer, "{}", entry)
+                .map_err(|e| format!("Failed to write session file: {}", e))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| format!("Failed to write session file: {}", e))?;
+    }
+    temp.persist(project_dir.join(format!("{}.jsonl", new_session_id)))
+        .map_err(|e| format!("Failed to save session file: {}", e.error))?;
+
+    Ok(MergedSession {
+        session_id: new_session_id,
+        sources: session_ids
+            .into_iter()
+            .zip(merged.taken)
+            .map(|(session_id, entries)| MergeSourceCount {
+                session_id,
+                entries,
+            })
+            .collect(),
+        warnings: merged.warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(kind: &str, uuid: &str, parent: Option<&str>, at: &str, text: &str) -> JsonValue {
+        json!({
+            "type": kind,
+            "uuid": uuid,
+            "parentUuid": parent,
+            "sessionId": "old",
+            "cwd": "/work",
+            "timestamp": at,
+            "message": { "role": kind, "content": text }
+        })
+    }
+
+    fn tool_result(uuid: &str, parent: &str, at: &str) -> JsonValue {
+        json!({
+            "type": "user",
+            "uuid": uuid,
+            "parentUuid": parent,
+            "sessionId": "old",
+            "timestamp": at,
+            "message": { "role": "user", "content": [{ "type": "tool_result", "content": "ok" }] }
+        })
+    }
+
+    fn texts(merged: &MergedEntries) -> Vec<String> {
+        merged
+            .entries
+            .iter()
+            .map(|entry| match entry.pointer("/message/content") {
+                Some(JsonValue::String(text)) => text.clone(),
+                Some(_) => "tool_result".to_string(),
+                None => entry["type"].as_str().unwrap_or_default().to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chronological_merge_interleaves_turns_and_rechains_them() {
+        let first = vec![
+            json!({ "type": "summary", "summary": "Work", "leafUuid": "a2" }),
+            message("user", "a1", None, "2024-01-01T10:00:00Z", "a prompt"),
+            message(
+                "assistant",
+                "a2",
+                Some("a1"),
+                "2024-01-01T10:00:05Z",
+                "a answer",
+            ),
+            tool_result("a3", "a2", "2024-01-01T10:02:00Z"),
+            message("user", "a4", Some("a3"), "2024-01-01T10:03:00Z", "a again"),
+        ];
+        let second = vec![
+            json!({ "type": "summary", "summary": "Work", "leafUuid": "a2" }),
+            message("user", "b1", None, "2024-01-01T10:01:00Z", "b prompt"),
+            message(
+                "assistant",
+                "b2",
+                Some("b1"),
+                "2024-01-01T10:01:05Z",
+                "b answer",
+            ),
+        ];
+
+        let merged =
+            merge_session_entries(vec![first, second], MergeStrategy::Chronological, "new");
+        // The tool result stays with the turn it belongs to
+        assert_eq!(
+            texts(&merged),
+            [
+                "summary",
+                "a prompt",
+                "a answer",
+                "tool_result",
+                "b prompt",
+                "b answer",
+                "a again"
+            ]
+        );
+        assert_eq!(merged.taken, [5, 2]);
+        assert!(merged.warnings.is_empty());
+
+        let messages = &merged.entries[1..];
+        assert!(messages[0]["parentUuid"].is_null());
+        for pair in messages.windows(2) {
+            assert_eq!(pair[1]["parentUuid"], pair[0]["uuid"]);
+        }
+        assert!(messages.iter().all(|entry| entry["sessionId"] == "new"));
+        assert_eq!(merged.entries[0]["leafUuid"], messages[1]["uuid"]);
+    }
+
+    #[test]
+    fn test_sequential_merge_keeps_shared_history_once_and_warns_on_cwd() {
+        let original = vec![
+            message("user", "a1", None, "2024-01-02T10:00:00Z", "shared"),
+            message(
+                "assistant",
+                "a2",
+                Some("a1"),
+                "2024-01-02T10:00:05Z",
+                "later original",
+            ),
+        ];
+        let mut fork = vec![
+            message("user", "a1", None, "2024-01-02T10:00:00Z", "shared"),
+            message("user", "f1", Some("a1"), "2024-01-01T09:00:00Z", "fork"),
+        ];
+        fork[1]["cwd"] = json!("/elsewhere");
+
+        let merged = merge_session_entries(vec![original, fork], MergeStrategy::Sequential, "new");
+        assert_eq!(texts(&merged), ["shared", "later original", "fork"]);
+        assert_eq!(merged.taken, [2, 1]);
+        assert_eq!(merged.warnings.len(), 0);
+
+        let mut elsewhere = vec![message("user", "c1", None, "2024-01-02T10:00:00Z", "c")];
+        elsewhere[0]["cwd"] = json!("/elsewhere");
+        let merged = merge_session_entries(
+            vec![
+                vec![message("user", "d1", None, "2024-01-02T10:00:00Z", "d")],
+                elsewhere,
+            ],
+            MergeStrategy::Sequential,
+            "new",
+        );
+        assert_eq!(merged.warnings.len(), 1);
+    }
+}
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::claude_env::ClaudeEnvSettings;
+
+/// Flags opcode sets itself; overriding them would break the stream parser
+/// or the session the command was asked to start
+const RESERVED_FLAGS: [&str; 11] = [
+    "-p",
+    "--print",
+    "--output-format",
+    "--input-format",
+    "--verbose",
+    "--model",
+    "-c",
+    "--continue",
+    "-r",
+    "--resume",
+    "--session-id",
+];
+
+/// Permission modes the Claude CLI accepts for `--permission-mode`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionMode {
+    Default,
+    AcceptEdits,
+    Plan,
+    BypassPermissions,
+}
+
+impl PermissionMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PermissionMode::Default => "default",
+            PermissionMode::AcceptEdits => "acceptEdits",
+            PermissionMode::Plan => "plan",
+            PermissionMode::BypassPermissions => "bypassPermissions",
+        }
+    }
+}
+
+/// What `claude -p` writes to stdout
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// One JSON message per line, streamed to the UI as it arrives
+    #[default]
+    StreamJson,
+    /// A single JSON result once Claude finishes
+    Json,
+    /// Claude's final answer as plain text
+    Text,
+}
+
+impl OutputFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OutputFormat::StreamJson => "stream-json",
+            OutputFormat::Json => "json",
+            OutputFormat::Text => "text",
+        }
+    }
+
+    /// Whether stdout is JSONL that the stream parser, live usage and
+    /// checkpoints can follow line by line
+    pub fn is_streaming(self) -> bool {
+        self == OutputFormat::StreamJson
+    }
+
+    /// Oldest Claude Code release that supports this format as opcode uses it
+    pub fn minimum_version(self) -> &'static str {
+        match self {
+            // Needs `--verbose` alongside `-p`
+            OutputFormat::StreamJson => "1.0.0",
+            OutputFormat::Json | OutputFormat::Text => "0.2.0",
+        }
+    }
+}
+
+/// Extra Claude CLI options for a session. The default adds nothing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClaudeSessionOptions {
+    pub max_turns: Option<u32>,
+    /// Replaces `--dangerously-skip-permissions` when set
+    pub permission_mode: Option<PermissionMode>,
+    /// Directories Claude may use besides the project, passed as `--add-dir`
+    #[serde(default)]
+    pub additional_directories: Vec<String>,
+    /// Passed through after the other arguments
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    /// Environment for this session, over the saved Claude environment
+    #[serde(default)]
+    pub env: Option<ClaudeEnvSettings>,
+}
+
+impl ClaudeSessionOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_turns == Some(0) {
+            return Err("max_turns must be at least 1".to_string());
+        }
+        for dir in &self.additional_directories {
+            if !Path::new(dir).is_dir() {
+                return Err(format!("Additional directory not found: {}", dir));
+            }
+        }
+        for arg in &self.extra_args {
+            if arg.contains('\0') {
+                return Err("Extra arguments can't contain NUL bytes".to_string());
+            }
+            let flag = arg.split('=').next().unwrap_or_default();
+            if RESERVED_FLAGS.contains(&flag) {
+                return Err(format!(
+                    "'{}' is set by opcode and can't be passed as an extra argument",
+                    flag
+                ));
+            }
+        }
+        if let Some(env) = &self.env {
+            env.validate()?;
+        }
+        Ok(())
+    }
+
+    /// The permission arguments: today's `--dangerously-skip-permissions`
+    /// unless a permission mode was chosen
+    pub fn permission_args(&self) -> Vec<String> {
+        match self.permission_mode {
+            Some(mode) => vec!["--permission-mode".to_string(), mode.as_str().to_string()],
+            None => vec!["--dangerously-skip-permissions".to_string()],
+        }
+    }
+
+    /// Arguments for the remaining options, in the order they're passed
+    pub fn extra_cli_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(max_turns) = self.max_turns {
+            args.push("--max-turns".to_string());
+            args.push(max_turns.to_string());
+        }
+        for dir in &self.additional_directories {
+            args.push("--add-dir".to_string());
+            args.push(dir.clone());
+        }
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_options_keep_todays_arguments() {
+        let options = ClaudeSessionOptions::default();
+        assert!(options.validate().is_ok());
+        assert_eq!(
+            options.permission_args(),
+            ["--dangerously-skip-permissions"]
+        );
+        assert!(options.extra_cli_args().is_empty());
+
+        let parsed: ClaudeSessionOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(parsed, options);
+    }
+
+    #[test]
+    fn test_options_are_validated_and_ordered() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir_path = dir.path().to_string_lossy().into_owned();
+        let options: ClaudeSessionOptions = serde_json::from_value(serde_json::json!({
+            "max_turns": 5,
+            "permission_mode": "plan",
+            "additional_directories": [dir_path],
+            "extra_args": ["--debug"]
+        }))
+        .unwrap();
+        assert!(options.validate().is_ok());
+        assert_eq!(options.permission_args(), ["--permission-mode", "plan"]);
+        assert_eq!(
+            options.extra_cli_args(),
+            [
+                "--max-turns",
+                "5",
+                "--add-dir",
+                dir_path.as_str(),
+                "--debug"
+            ]
+        );
+
+        let invalid = |options: ClaudeSessionOptions| options.validate().is_err();
+        assert!(invalid(ClaudeSessionOptions {
+            max_turns: Some(0),
+            ..Default::default()
+        }));
+        assert!(invalid(ClaudeSessionOptions {
+            additional_directories: vec![dir.path().join("missing").to_string_lossy().into()],
+            ..Default::default()
+        }));
+        assert!(invalid(ClaudeSessionOptions {
+            extra_args: vec!["--output-format=json".to_string()],
+            ..Default::default()
+        }));
+        assert!(
+            serde_json::from_str::<ClaudeSessionOptions>(r#"{"permission_mode":"yolo"}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn test_output_formats_use_cli_names() {
+        let format: OutputFormat = serde_json::from_str(r#""stream-json""#).unwrap();
+        assert_eq!(format, OutputFormat::default());
+        assert!(format.is_streaming());
+        let format: OutputFormat = serde_json::from_str(r#""text""#).unwrap();
+        assert_eq!(format.as_str(), "text");
+        assert!(!format.is_streaming());
+        assert!(serde_json::from_str::<OutputFormat>(r#""yaml""#).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// A session pinned as a favorite
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PinnedSession {
+    pub project_id: String,
+    pub session_id: String,
+    pub pinned_at: String,
+    pub custom_title: Option<String>,
+}
+
+/// Ids of a project's pinned sessions
+pub fn load_pinned_sessions(
+    conn: &Connection,
+    project_id: &str,
+) -> Result<HashSet<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT session_id FROM pinned_sessions WHERE project_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let pinned = stmt
+        .query_map(params![project_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(pinned)
+}
+
+/// Pinned sessions of every project, most recently pinned first
+pub fn load_all_pinned_sessions(conn: &Connection) -> Result<Vec<PinnedSession>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.project_id, p.session_id, p.pinned_at, t.title
+             FROM pinned_sessions p
+             LEFT JOIN session_titles t ON t.project_id = p.project_id AND t.session_id = p.session_id
+             ORDER BY p.pinned_at DESC, p.project_id, p.session_id",
+        )
+        .map_err(|e| e.to_string())?;
+    let pinned = stmt
+        .query_map([], |row| {
+            Ok(PinnedSession {
+                project_id: row.get(0)?,
+                session_id: row.get(1)?,
+                pinned_at: row.get(2)?,
+                custom_title: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(pinned)
+}
+
+pub fn save_session_pinned(
+    conn: &Connection,
+    project_id: &str,
+    session_id: &str,
+    pinned: bool,
+) -> Result<(), String> {
+    let result = if pinned {
+        conn.execute(
+            "INSERT OR IGNORE INTO pinned_sessions (project_id, session_id) VALUES (?1, ?2)",
+            params![project_id, session_id],
+        )
+    } else {
+        delete_session_pin(conn, project_id, session_id).map(|_| 0)
+    };
+    result.map_err(|e| format!("Failed to save session pin: {}", e))?;
+    Ok(())
+}
+
+pub fn delete_session_pin(
+    conn: &Connection,
+    project_id: &str,
+    session_id: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM pinned_sessions WHERE project_id = ?1 AND session_id = ?2",
+        params![project_id, session_id],
+    )?;
+    Ok(())
+}
+
+/// Pin or unpin a session. Pinned sessions are listed first in their project.
+#[tauri::command]
+pub async fn set_session_pinned(
+    db: State<'_, AgentDb>,
+    project_id: String,
+    session_id: String,
+    pinned: bool,
+) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_session_pinned(&conn, &project_id, &session_id, pinned)
+}
+
+/// List the pinned sessions of all projects
+#[tauri::command]
+pub async fn list_pinned_sessions(db: State<'_, AgentDb>) -> Result<Vec<PinnedSession>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_all_pinned_sessions(&conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pins_are_per_project_and_listed_with_titles() {
+        let conn = crate::db::test_connection();
+
+        save_session_pinned(&conn, "p", "s1", true).unwrap();
+        save_session_pinned(&conn, "p", "s1", true).unwrap();
+        save_session_pinned(&conn, "q", "s2", true).unwrap();
+        conn.execute(
+            "INSERT INTO session_titles (project_id, session_id, title) VALUES ('q', 's2', 'Deploy')",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(
+            load_pinned_sessions(&conn, "p").unwrap(),
+            HashSet::from(["s1".to_string()])
+        );
+        let all = load_all_pinned_sessions(&conn).unwrap();
+        assert_eq!(all.len(), 2);
+        let titled = all.iter().find(|p| p.session_id == "s2").unwrap();
+        assert_eq!(titled.custom_title.as_deref(), Some("Deploy"));
+
+        save_session_pinned(&conn, "p", "s1", false).unwrap();
+        delete_session_pin(&conn, "q", "s2").unwrap();
+        assert!(load_all_pinned_sessions(&conn).unwrap().is_empty());
+    }
+}
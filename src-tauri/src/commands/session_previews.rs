@@ -0,0 +1,178 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+/// How much of a session file is read to find its first user message
+const PREVIEW_WINDOW_BYTES: u64 = 16 * 1024;
+
+/// Shown when the first user message lies beyond the preview window
+pub const NO_PREVIEW: &str = "(no preview)";
+
+/// First user message of a session and its timestamp
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionPreview {
+    pub first_message: Option<String>,
+    pub message_timestamp: Option<String>,
+}
+
+/// Read the preview from the start of a session file. Only complete lines
+/// within the window are parsed; when no user message is found there but the
+/// file goes on, the preview is `NO_PREVIEW`.
+pub fn read_session_preview(path: &Path) -> SessionPreview {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return SessionPreview::default(),
+    };
+    let mut window = Vec::new();
+    if file
+        .take(PREVIEW_WINDOW_BYTES + 1)
+        .read_to_end(&mut window)
+        .is_err()
+    {
+        return SessionPreview::default();
+    }
+
+    let truncated = window.len() as u64 > PREVIEW_WINDOW_BYTES;
+    if truncated {
+        // Drop the partial last line
+        let end = window
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map_or(0, |i| i + 1);
+        window.truncate(end);
+    }
+
+    let (first_message, message_timestamp) = super::claude::first_user_message(Cursor::new(window));
+    match first_message {
+        Some(_) => SessionPreview {
+            first_message,
+            message_timestamp,
+        },
+        None if truncated => SessionPreview {
+            first_message: Some(NO_PREVIEW.to_string()),
+            message_timestamp: None,
+        },
+        None => SessionPreview::default(),
+    }
+}
+
+/// Cached previews of the given session files that are still current:
+/// path -> preview, for paths whose cached mtime matches
+pub fn load_cached_previews(
+    conn: &Connection,
+    files: &[(String, i64)],
+) -> Result<HashMap<String, SessionPreview>, String> {
+    let mut stmt = conn
+        .prepare("SELECT first_message, message_timestamp FROM session_previews WHERE path = ?1 AND mtime = ?2")
+        .map_err(|e| e.to_string())?;
+    let mut previews = HashMap::new();
+    for (path, mtime) in files {
+        let cached = stmt
+            .query_row(params![path, mtime], |row| {
+                Ok(SessionPreview {
+                    first_message: row.get(0)?,
+                    message_timestamp: row.get(1)?,
+                })
+            })
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if let Some(preview) = cached {
+            previews.insert(path.clone(), preview);
+        }
+    }
+    Ok(previews)
+}
+
+/// Store freshly read previews, replacing older entries for the same files
+pub fn save_previews(
+    conn: &mut Connection,
+    previews: &[(String, i64, SessionPreview)],
+) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    {
+        let mut stmt = tx
+            .prepare(
+                "INSERT OR REPLACE INTO session_previews (path, mtime, first_message, message_timestamp)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )
+            .map_err(|e| e.to_string())?;
+        for (path, mtime, preview) in previews {
+            stmt.execute(params![
+                path,
+                mtime,
+                preview.first_message,
+                preview.message_timestamp
+            ])
+            .map_err(|e| format!("Failed to cache session preview: {}", e))?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn user_line(text: &str) -> String {
+        format!(
+            "{{\"type\":\"user\",\"message\":{{\"role\":\"user\",\"content\":\"{}\"}},\"timestamp\":\"2026-10-18T09:00:00Z\"}}\n",
+            text
+        )
+    }
+
+    #[test]
+    fn test_preview_is_read_from_the_start_of_the_file_only() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let short = dir.path().join("short.jsonl");
+        fs::write(
+            &short,
+            format!(
+                "{{\"type\":\"summary\"}}\n{}",
+                user_line("Fix the login bug")
+            ),
+        )
+        .unwrap();
+        let preview = read_session_preview(&short);
+        assert_eq!(preview.first_message.as_deref(), Some("Fix the login bug"));
+        assert_eq!(
+            preview.message_timestamp.as_deref(),
+            Some("2026-10-18T09:00:00Z")
+        );
+
+        let empty = dir.path().join("empty.jsonl");
+        fs::write(&empty, "{\"type\":\"summary\"}\n").unwrap();
+        assert_eq!(read_session_preview(&empty), SessionPreview::default());
+
+        // The user message starts within the window but ends past it
+        let late = dir.path().join("late.jsonl");
+        fs::write(&late, user_line(&"x".repeat(PREVIEW_WINDOW_BYTES as usize))).unwrap();
+        assert_eq!(
+            read_session_preview(&late).first_message.as_deref(),
+            Some(NO_PREVIEW)
+        );
+    }
+
+    #[test]
+    fn test_cached_previews_are_keyed_by_path_and_mtime() {
+        let mut conn = crate::db::test_connection();
+
+        let preview = SessionPreview {
+            first_message: Some("hello".to_string()),
+            message_timestamp: None,
+        };
+        save_previews(&mut conn, &[("/a.jsonl".to_string(), 10, preview.clone())]).unwrap();
+
+        let files = [("/a.jsonl".to_string(), 10), ("/b.jsonl".to_string(), 10)];
+        let cached = load_cached_previews(&conn, &files).unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached["/a.jsonl"], preview);
+
+        // A modified file misses the cache
+        let files = [("/a.jsonl".to_string(), 11)];
+        assert!(load_cached_previews(&conn, &files).unwrap().is_empty());
+    }
+}
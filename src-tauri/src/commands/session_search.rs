@@ -0,0 +1,551 @@
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::agents::AgentDb;
+use super::run_search::{find_match, MIN_FTS_QUERY_CHARS};
+
+/// Shortest query accepted by `search_sessions`
+pub const MIN_SESSION_QUERY_CHARS: usize = 2;
+
+/// Results returned when no limit is given
+pub const DEFAULT_SESSION_SEARCH_LIMIT: usize = 50;
+
+/// Session files indexed between progress events
+const PROGRESS_EVERY_FILES: usize = 25;
+
+/// A session message that matched a search
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SessionSearchHit {
+    pub project_id: String,
+    pub session_id: String,
+    pub message_index: usize, // JSONL line, as in `load_session_history` results
+    pub timestamp: Option<String>,
+    pub snippet: String,
+}
+
+/// Progress of (re)indexing session files, emitted as `session-search-index-progress`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SessionIndexProgress {
+    pub indexed_files: usize,
+    pub total_files: usize,
+}
+
+/// Searchable text of one session message
+#[derive(Debug, Clone, PartialEq)]
+struct MessageText {
+    message_index: usize,
+    timestamp: Option<String>,
+    text: String,
+    tool_output: bool, // Tool calls and results rather than conversation text
+}
+
+/// A session file's location under `~/.claude/projects`
+struct SessionFile {
+    path: PathBuf,
+    project_id: String,
+    session_id: String,
+    mtime: i64,
+}
+
+/// Create the session full-text index tables. FTS5 may be missing from the
+/// SQLite build, in which case searches fall back to scanning the JSONL files.
+pub fn init_session_search_index(conn: &Connection) {
+    if let Err(e) = conn.execute(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS session_fts USING fts5(
+            content, project_id UNINDEXED, session_id UNINDEXED, message_index UNINDEXED,
+            timestamp UNINDEXED, tool_output UNINDEXED, tokenize = 'trigram'
+        )",
+        [],
+    ) {
+        warn!("Session search index unavailable: {}", e);
+        return;
+    }
+    let _ = conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_fts_files (
+            project_id TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            mtime INTEGER NOT NULL,
+            PRIMARY KEY (project_id, session_id)
+        )",
+        [],
+    );
+}
+
+fn session_index_available(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE name IN ('session_fts', 'session_fts_files')",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .is_ok_and(|count| count == 2)
+}
+
+/// Conversation text and tool text of a message's content, which is either a
+/// plain string or a list of content blocks
+fn split_content(content: &JsonValue, text: &mut Vec<String>, tool: &mut Vec<String>) {
+    match content {
+        JsonValue::String(s) => text.push(s.clone()),
+        JsonValue::Array(blocks) => {
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        if let Some(s) = block.get("text").and_then(|t| t.as_str()) {
+                            text.push(s.to_string());
+                        }
+                    }
+                    Some("tool_result") => {
+                        if let Some(content) = block.get("content") {
+                            let (mut nested_text, mut nested_tool) = (Vec::new(), Vec::new());
+                            split_content(content, &mut nested_text, &mut nested_tool);
+                            tool.extend(nested_text);
+                            tool.extend(nested_tool);
+                        }
+                    }
+                    Some("tool_use") => {
+                        if let Some(input) = block.get("input") {
+                            tool.push(input.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Searchable text of each user and assistant message, indexed by JSONL line
+/// like `load_session_history` results
+fn session_message_texts(jsonl: &str) -> Vec<MessageText> {
+    let mut texts = Vec::new();
+    for (message_index, line) in jsonl.lines().enumerate() {
+        let json = match serde_json::from_str::<JsonValue>(line) {
+            Ok(json) => json,
+            Err(_) => continue,
+        };
+        if !matches!(json.get("type").and_then(|t| t.as_str()), Some("user" | "assistant")) {
+            continue;
+        }
+        let content = match json.pointer("/message/content") {
+            Some(content) => content,
+            None => continue,
+        };
+        let timestamp = json.get("timestamp").and_then(|t| t.as_str()).map(String::from);
+
+        let (mut text, mut tool) = (Vec::new(), Vec::new());
+        split_content(content, &mut text, &mut tool);
+        for (parts, tool_output) in [(text, false), (tool, true)] {
+            let joined = parts.join("\n");
+            if !joined.trim().is_empty() {
+                texts.push(MessageText {
+                    message_index,
+                    timestamp: timestamp.clone(),
+                    text: joined,
+                    tool_output,
+                });
+            }
+        }
+    }
+    texts
+}
+
+/// Every session file under `projects_dir`, or under one project
+fn list_session_files(projects_dir: &Path, project_id: Option<&str>) -> Vec<SessionFile> {
+    let project_dirs: Vec<PathBuf> = match project_id {
+        Some(project_id) => vec![projects_dir.join(project_id)],
+        None => std::fs::read_dir(projects_dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default(),
+    };
+
+    let mut files = Vec::new();
+    for project_dir in project_dirs.into_iter().filter(|dir| dir.is_dir()) {
+        let project_id = match project_dir.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let entries = match std::fs::read_dir(&project_dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let (Some(session_id), Ok(metadata)) = (
+                path.file_stem().and_then(|s| s.to_str()).map(String::from),
+                std::fs::metadata(&path),
+            ) else {
+                continue;
+            };
+            let mtime = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as i64)
+                .unwrap_or(0);
+            files.push(SessionFile {
+                path,
+                project_id: project_id.clone(),
+                session_id,
+                mtime,
+            });
+        }
+    }
+    files
+}
+
+/// Replace a session's entries in the full-text index
+fn index_session_file(conn: &Connection, file: &SessionFile, jsonl: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM session_fts WHERE project_id = ?1 AND session_id = ?2",
+        params![file.project_id, file.session_id],
+    )
+    .map_err(|e| e.to_string())?;
+    {
+        let mut stmt = conn
+            .prepare(
+                "INSERT INTO session_fts (content, project_id, session_id, message_index, timestamp, tool_output)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .map_err(|e| e.to_string())?;
+        for message in session_message_texts(jsonl) {
+            stmt.execute(params![
+                message.text,
+                file.project_id,
+                file.session_id,
+                message.message_index as i64,
+                message.timestamp,
+                message.tool_output
+            ])
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    conn.execute(
+        "INSERT INTO session_fts_files (project_id, session_id, mtime) VALUES (?1, ?2, ?3)
+         ON CONFLICT(project_id, session_id) DO UPDATE SET mtime = ?3",
+        params![file.project_id, file.session_id, file.mtime],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Bring the index up to date with the session files: files whose mtime
+/// changed are re-read, deleted files are dropped. `progress` is called as
+/// files are indexed, when any need it. Returns the number of files indexed.
+fn sync_session_index<P>(
+    db: &AgentDb,
+    projects_dir: &Path,
+    project_id: Option<&str>,
+    mut progress: P,
+) -> Result<usize, String>
+where
+    P: FnMut(SessionIndexProgress),
+{
+    let files = list_session_files(projects_dir, project_id);
+
+    let stale: Vec<&SessionFile> = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT project_id, session_id, mtime FROM session_fts_files WHERE ?1 IS NULL OR project_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let indexed = stmt
+            .query_map(params![project_id], |row| {
+                Ok(((row.get::<_, String>(0)?, row.get::<_, String>(1)?), row.get::<_, i64>(2)?))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<HashMap<_, _>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        // Drop sessions whose file is gone
+        for (project_id, session_id) in indexed.keys() {
+            if !files.iter().any(|f| &f.project_id == project_id && &f.session_id == session_id) {
+                conn.execute(
+                    "DELETE FROM session_fts WHERE project_id = ?1 AND session_id = ?2",
+                    params![project_id, session_id],
+                )
+                .map_err(|e| e.to_string())?;
+                conn.execute(
+                    "DELETE FROM session_fts_files WHERE project_id = ?1 AND session_id = ?2",
+                    params![project_id, session_id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+
+        files
+            .iter()
+            .filter(|f| indexed.get(&(f.project_id.clone(), f.session_id.clone())) != Some(&f.mtime))
+            .collect()
+    };
+    if stale.is_empty() {
+        return Ok(0);
+    }
+
+    info!("Indexing {} session files for search", stale.len());
+    let total_files = stale.len();
+    for (done, file) in stale.into_iter().enumerate() {
+        // Read outside the lock so other commands aren't held up by disk I/O
+        match std::fs::read_to_string(&file.path) {
+            Ok(jsonl) => {
+                let conn = db.0.lock().map_err(|e| e.to_string())?;
+                index_session_file(&conn, file, &jsonl)?;
+            }
+            Err(e) => warn!("Failed to read session file {:?}: {}", file.path, e),
+        }
+        if (done + 1) % PROGRESS_EVERY_FILES == 0 || done + 1 == total_files {
+            progress(SessionIndexProgress {
+                indexed_files: done + 1,
+                total_files,
+            });
+        }
+    }
+    Ok(total_files)
+}
+
+/// Matching messages from the index, newest first
+fn query_session_index(
+    conn: &Connection,
+    query: &str,
+    project_id: Option<&str>,
+    include_tool_output: bool,
+    limit: usize,
+) -> Result<Vec<SessionSearchHit>, String> {
+    let query_lower = query.to_lowercase();
+    let (filter, param) = if query.chars().count() >= MIN_FTS_QUERY_CHARS {
+        // A quoted phrase is a substring match with the trigram tokenizer
+        (
+            "session_fts MATCH ?1",
+            format!("\"{}\"", query.replace('"', "\"\"")),
+        )
+    } else if query.is_ascii() {
+        ("instr(lower(content), ?1) > 0", query_lower.clone())
+    } else {
+        // SQLite's lower() only folds ASCII, so leave the matching to find_match
+        ("?1 IS NOT NULL", query_lower.clone())
+    };
+    let sql = format!(
+        "SELECT project_id, session_id, message_index, timestamp, content FROM session_fts
+         WHERE {} AND (?2 IS NULL OR project_id = ?2) AND (?3 OR tool_output = 0)
+         ORDER BY timestamp DESC, session_id, message_index",
+        filter
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![param, project_id, include_tool_output], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut hits: Vec<SessionSearchHit> = Vec::new();
+    for row in rows {
+        let (project_id, session_id, message_index, timestamp, content) =
+            row.map_err(|e| e.to_string())?;
+        let message_index = message_index as usize;
+        // Text and tool output of one message are separate rows
+        if hits.iter().any(|hit| {
+            hit.session_id == session_id && hit.project_id == project_id && hit.message_index == message_index
+        }) {
+            continue;
+        }
+        // Confirm the literal match; it also yields the snippet
+        if let Some(snippet) = find_match(&content, &query_lower) {
+            hits.push(SessionSearchHit {
+                project_id,
+                session_id,
+                message_index,
+                timestamp,
+                snippet,
+            });
+            if hits.len() >= limit {
+                break;
+            }
+        }
+    }
+    Ok(hits)
+}
+
+/// Search session files directly, for SQLite builds without FTS5
+fn scan_session_files(
+    projects_dir: &Path,
+    query: &str,
+    project_id: Option<&str>,
+    include_tool_output: bool,
+    limit: usize,
+) -> Vec<SessionSearchHit> {
+    let query_lower = query.to_lowercase();
+    let mut hits = Vec::new();
+    for file in list_session_files(projects_dir, project_id) {
+        let jsonl = match std::fs::read_to_string(&file.path) {
+            Ok(jsonl) => jsonl,
+            Err(_) => continue,
+        };
+        for message in session_message_texts(&jsonl) {
+            if message.tool_output && !include_tool_output {
+                continue;
+            }
+            let duplicate = hits.iter().any(|hit: &SessionSearchHit| {
+                hit.session_id == file.session_id
+                    && hit.project_id == file.project_id
+                    && hit.message_index == message.message_index
+            });
+            if duplicate {
+                continue;
+            }
+            if let Some(snippet) = find_match(&message.text, &query_lower) {
+                hits.push(SessionSearchHit {
+                    project_id: file.project_id.clone(),
+                    session_id: file.session_id.clone(),
+                    message_index: message.message_index,
+                    timestamp: message.timestamp,
+                    snippet,
+                });
+            }
+        }
+    }
+    hits.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    hits.truncate(limit);
+    hits
+}
+
+/// Search the user and assistant messages of every session under
+/// `projects_dir`, updating the index first
+pub fn search_session_messages<P>(
+    db: &AgentDb,
+    projects_dir: &Path,
+    query: &str,
+    project_id: Option<&str>,
+    include_tool_output: bool,
+    limit: usize,
+    progress: P,
+) -> Result<Vec<SessionSearchHit>, String>
+where
+    P: FnMut(SessionIndexProgress),
+{
+    let query = query.trim();
+    if query.chars().count() < MIN_SESSION_QUERY_CHARS {
+        return Err(format!(
+            "Search query must be at least {} characters",
+            MIN_SESSION_QUERY_CHARS
+        ));
+    }
+
+    let index_available = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        session_index_available(&conn)
+    };
+    if !index_available {
+        info!("Session search index unavailable, scanning session files");
+        return Ok(scan_session_files(
+            projects_dir,
+            query,
+            project_id,
+            include_tool_output,
+            limit,
+        ));
+    }
+
+    sync_session_index(db, projects_dir, project_id, progress)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    query_session_index(&conn, query, project_id, include_tool_output, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const SESSION_A: &str = r#"{"type":"summary","summary":"S3 helpers"}
+{"type":"user","timestamp":"2025-05-01T10:00:00Z","message":{"role":"user","content":"Write an S3 presign helper"}}
+{"type":"assistant","timestamp":"2025-05-01T10:00:05Z","message":{"role":"assistant","content":[{"type":"text","text":"Here is presign_url()"},{"type":"tool_use","name":"Write","input":{"file_path":"s3.rs"}}]}}
+{"type":"user","timestamp":"2025-05-01T10:00:09Z","message":{"role":"user","content":[{"type":"tool_result","content":"wrote presign_url to s3.rs"}]}}"#;
+
+    fn test_db() -> AgentDb {
+        let conn = Connection::open_in_memory().unwrap();
+        init_session_search_index(&conn);
+        AgentDb(Mutex::new(conn))
+    }
+
+    #[test]
+    fn test_message_texts_split_conversation_from_tool_output() {
+        let texts = session_message_texts(SESSION_A);
+        let summary: Vec<(usize, bool)> = texts.iter().map(|t| (t.message_index, t.tool_output)).collect();
+        assert_eq!(summary, [(1, false), (2, false), (2, true), (3, true)]);
+        assert_eq!(texts[1].text, "Here is presign_url()");
+        assert_eq!(texts[0].timestamp.as_deref(), Some("2025-05-01T10:00:00Z"));
+    }
+
+    #[test]
+    fn test_search_indexes_incrementally_and_skips_tool_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-home-me-app");
+        std::fs::create_dir(&project).unwrap();
+        std::fs::write(project.join("a.jsonl"), SESSION_A).unwrap();
+        std::fs::write(dir.path().join("-home-me-other").with_extension("txt"), "").unwrap();
+        let db = test_db();
+
+        let mut events = Vec::new();
+        let hits = search_session_messages(&db, dir.path(), "presign", None, false, 10, |p| events.push(p))
+            .unwrap();
+        assert_eq!(
+            hits.iter().map(|h| h.message_index).collect::<Vec<_>>(),
+            [2, 1] // Newest first, tool result excluded
+        );
+        assert_eq!(hits[0].project_id, "-home-me-app");
+        assert_eq!(events, [SessionIndexProgress { indexed_files: 1, total_files: 1 }]);
+
+        let hits = search_session_messages(&db, dir.path(), "wrote", None, true, 10, |_| {}).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(search_session_messages(&db, dir.path(), "wrote", None, false, 10, |_| {})
+            .unwrap()
+            .is_empty());
+
+        // Unchanged files aren't re-read; short queries are rejected
+        assert_eq!(sync_session_index(&db, dir.path(), None, |_| {}).unwrap(), 0);
+        assert!(search_session_messages(&db, dir.path(), " s ", None, false, 10, |_| {}).is_err());
+        assert_eq!(
+            search_session_messages(&db, dir.path(), "S3", None, false, 10, |_| {}).unwrap().len(),
+            1
+        );
+
+        // Deleted sessions drop out of the index
+        std::fs::remove_file(project.join("a.jsonl")).unwrap();
+        assert!(search_session_messages(&db, dir.path(), "presign", None, false, 10, |_| {})
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_search_folds_non_ascii_case() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("-home-me-trip");
+        std::fs::create_dir(&project).unwrap();
+        let session = format!(
+            r#"{{"type":"user","timestamp":"2025-05-01T10:00:00Z","message":{{"role":"user","content":"{}Grüße aus MÜNCHEN und İSTANBUL"}}}}"#,
+            "İ".repeat(200)
+        );
+        std::fs::write(project.join("b.jsonl"), &session).unwrap();
+        let db = test_db();
+
+        for query in ["münchen", "Ün", "İstanbul"] {
+            let hits = search_session_messages(&db, dir.path(), query, None, false, 10, |_| {}).unwrap();
+            assert_eq!(hits.len(), 1, "{}", query);
+            assert!(hits[0].snippet.ends_with("MÜNCHEN und İSTANBUL"), "{}", hits[0].snippet);
+
+            // The file scan used without FTS5 agrees with the index
+            assert_eq!(scan_session_files(dir.path(), query, None, false, 10), hits);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::commands::agents::AgentDb;
+
+/// Longest title kept, in characters
+const MAX_SESSION_TITLE_CHARS: usize = 200;
+
+/// Trim a title and cap its length, or `None` when nothing is left
+fn normalize_session_title(title: &str) -> Option<String> {
+    let title = title.trim();
+    if title.is_empty() {
+        return None;
+    }
+    Some(
+        title
+            .chars()
+            .take(MAX_SESSION_TITLE_CHARS)
+            .collect::<String>()
+            .trim_end()
+            .to_string(),
+    )
+}
+
+/// Custom titles of a project's sessions: session_id -> title
+pub fn load_session_titles(
+    conn: &Connection,
+    project_id: &str,
+) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT session_id, title FROM session_titles WHERE project_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let titles = stmt
+        .query_map(params![project_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(titles)
+}
+
+/// Store a session's title, or remove it when the title is blank. Returns the stored title.
+pub fn save_session_title(
+    conn: &Connection,
+    project_id: &str,
+    session_id: &str,
+    title: &str,
+) -> Result<Option<String>, String> {
+    let title = normalize_session_title(title);
+    match &title {
+        Some(title) => conn.execute(
+            "INSERT INTO session_titles (project_id, session_id, title) VALUES (?1, ?2, ?3)
+             ON CONFLICT(project_id, session_id) DO UPDATE SET title = ?3, updated_at = CURRENT_TIMESTAMP",
+            params![project_id, session_id, title],
+        ),
+        None => delete_session_title(conn, project_id, session_id).map(|_| 0),
+    }
+    .map_err(|e| format!("Failed to save session title: {}", e))?;
+    Ok(title)
+}
+
+pub fn delete_session_title(
+    conn: &Connection,
+    project_id: &str,
+    session_id: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM session_titles WHERE project_id = ?1 AND session_id = ?2",
+        params![project_id, session_id],
+    )?;
+    Ok(())
+}
+
+/// Give a session a custom title, or clear it with a blank title. The session
+/// file itself is left untouched.
+#[tauri::command]
+pub async fn set_session_title(
+    db: State<'_, AgentDb>,
+    project_id: String,
+    session_id: String,
+    title: String,
+) -> Result<Option<String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    save_session_title(&conn, &project_id, &session_id, &title)
+}
+
+/// Get the custom titles of a project's sessions, keyed by session id
+#[tauri::command]
+pub async fn get_session_titles(
+    db: State<'_, AgentDb>,
+    project_id: String,
+) -> Result<HashMap<String, String>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_session_titles(&conn, &project_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_titles_are_trimmed_capped_and_cleared_when_blank() {
+        let conn = crate::db::test_connection();
+
+        let stored = save_session_title(&conn, "p", "s1", "  S3 presign helper \n").unwrap();
+        assert_eq!(stored.as_deref(), Some("S3 presign helper"));
+        let long = save_session_title(&conn, "p", "s2", &"é".repeat(250))
+            .unwrap()
+            .unwrap();
+        assert_eq!(long.chars().count(), MAX_SESSION_TITLE_CHARS);
+        save_session_title(&conn, "other", "s3", "elsewhere").unwrap();
+
+        let titles = load_session_titles(&conn, "p").unwrap();
+        assert_eq!(titles.len(), 2);
+        assert_eq!(titles["s1"], "S3 presign helper");
+
+        assert_eq!(save_session_title(&conn, "p", "s1", "   ").unwrap(), None);
+        delete_session_title(&conn, "p", "s2").unwrap();
+        assert!(load_session_titles(&conn, "p").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,334 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::claude::get_claude_dir;
+use super::claude_settings::{read_settings, save_settings, SettingsSaveResult};
+use super::settings_history::record_snapshot_or_warn;
+use crate::commands::agents::AgentDb;
+
+/// A settings file Claude Code reads, lowest precedence first
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingsScope {
+    /// `~/.claude/settings.json`
+    User,
+    /// `<project>/.claude/settings.json`, shared through version control
+    Project,
+    /// `<project>/.claude/settings.local.json`, personal to this checkout
+    Local,
+    /// Enterprise policy, which overrides everything and opcode never writes
+    Managed,
+}
+
+/// One file that took part in the merge
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingsSource {
+    pub scope: SettingsScope,
+    pub path: String,
+    pub exists: bool,
+    /// Why the file was left out of the merge; empty when it's valid
+    pub parse_errors: Vec<String>,
+}
+
+/// The settings Claude Code ends up using for a project
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EffectiveSettings {
+    pub merged: JsonValue,
+    /// The file each value came from, keyed by JSON pointer, e.g.
+    /// `/permissions/allow/0`
+    pub provenance: BTreeMap<String, String>,
+    pub sources: Vec<SettingsSource>,
+}
+
+#[cfg(target_os = "macos")]
+fn managed_settings_path() -> Option<PathBuf> {
+    Some(PathBuf::from(
+        "/Library/Application Support/ClaudeCode/managed-settings.json",
+    ))
+}
+
+#[cfg(target_os = "windows")]
+fn managed_settings_path() -> Option<PathBuf> {
+    Some(PathBuf::from(
+        r"C:\ProgramData\ClaudeCode\managed-settings.json",
+    ))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn managed_settings_path() -> Option<PathBuf> {
+    Some(PathBuf::from("/etc/claude-code/managed-settings.json"))
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+fn managed_settings_path() -> Option<PathBuf> {
+    None
+}
+
+/// Where a scope's settings live for `project_path`
+pub fn scope_path(scope: SettingsScope, claude_dir: &Path, project_path: &Path) -> Option<PathBuf> {
+    match scope {
+        SettingsScope::User => Some(claude_dir.join("settings.json")),
+        SettingsScope::Project => Some(project_path.join(".claude").join("settings.json")),
+        SettingsScope::Local => Some(project_path.join(".claude").join("settings.local.json")),
+        SettingsScope::Managed => managed_settings_path(),
+    }
+}
+
+/// Escape a key for use in a JSON pointer (RFC 6901)
+fn pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Attribute every leaf of `value`, and empty containers, to `source`
+fn record_leaves(
+    value: &JsonValue,
+    pointer: &str,
+    source: &str,
+    provenance: &mut BTreeMap<String, String>,
+) {
+    match value {
+        JsonValue::Object(map) if !map.is_empty() => {
+            for (key, item) in map {
+                let child = format!("{}/{}", pointer, pointer_token(key));
+                record_leaves(item, &child, source, provenance);
+            }
+        }
+        JsonValue::Array(items) if !items.is_empty() => {
+            for (i, item) in items.iter().enumerate() {
+                record_leaves(item, &format!("{}/{}", pointer, i), source, provenance);
+            }
+        }
+        _ => {
+            provenance.insert(pointer.to_string(), source.to_string());
+        }
+    }
+}
+
+/// Merge `value` from `source` over `target`: objects merge key by key,
+/// arrays such as permission rules are combined without duplicates, and
+/// anything else is replaced
+fn merge_into(
+    target: &mut JsonValue,
+    value: &JsonValue,
+    pointer: &str,
+    source: &str,
+    provenance: &mut BTreeMap<String, String>,
+) {
+    match (target, value) {
+        (JsonValue::Object(target), JsonValue::Object(map)) => {
+            if map.is_empty() && target.is_empty() {
+                provenance.insert(pointer.to_string(), source.to_string());
+            }
+            for (key, item) in map {
+                let child = format!("{}/{}", pointer, pointer_token(key));
+                match target.get_mut(key) {
+                    Some(existing) => merge_into(existing, item, &child, source, provenance),
+                    None => {
+                        record_leaves(item, &child, source, provenance);
+                        target.insert(key.clone(), item.clone());
+                    }
+                }
+            }
+            if !target.is_empty() {
+                provenance.remove(pointer);
+            }
+        }
+        (JsonValue::Array(target), JsonValue::Array(items)) => {
+            for item in items {
+                if !target.contains(item) {
+                    let child = format!("{}/{}", pointer, target.len());
+                    record_leaves(item, &child, source, provenance);
+                    target.push(item.clone());
+                }
+            }
+            if !target.is_empty() {
+                provenance.remove(pointer);
+            }
+        }
+        (target, value) => {
+            let nested = format!("{}/", pointer);
+            provenance.retain(|key, _| key != pointer && !key.starts_with(&nested));
+            record_leaves(value, pointer, source, provenance);
+            *target = value.clone();
+        }
+    }
+}
+
+/// Merge the settings files that exist, lowest precedence first. Files that
+/// can't be read or aren't JSON objects are reported in their source and skipped.
+pub fn merge_settings_files(files: &[(SettingsScope, PathBuf)]) -> EffectiveSettings {
+    let mut merged = JsonValue::Object(Map::new());
+    let mut provenance = BTreeMap::new();
+    let mut sources = Vec::new();
+    for (scope, path) in files {
+        let path_string = path.to_string_lossy().into_owned();
+        let exists = path.is_file();
+        let parse_errors = match read_settings(path) {
+            Ok(settings) => {
+                if exists && settings.parse_errors.is_empty() {
+                    merge_into(
+                        &mut merged,
+                        &settings.data,
+                        "",
+                        &path_string,
+                        &mut provenance,
+                    );
+                }
+                settings.parse_errors
+            }
+            Err(e) => vec![e],
+        };
+        sources.push(SettingsSource {
+            scope: *scope,
+            path: path_string,
+            exists,
+            parse_errors,
+        });
+    }
+    EffectiveSettings {
+        merged,
+        provenance,
+        sources,
+    }
+}
+
+/// Merge user, project, local and managed settings the way Claude Code does
+/// for `project_path`, reporting which file each value came from
+#[tauri::command]
+pub async fn get_effective_claude_settings(
+    project_path: String,
+) -> Result<EffectiveSettings, String> {
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let project = PathBuf::from(&project_path);
+    let files: Vec<(SettingsScope, PathBuf)> = [
+        SettingsScope::User,
+        SettingsScope::Project,
+        SettingsScope::Local,
+        SettingsScope::Managed,
+    ]
+    .into_iter()
+    .filter_map(|scope| scope_path(scope, &claude_dir, &project).map(|path| (scope, path)))
+    .collect();
+
+    let effective = merge_settings_files(&files);
+    for source in &effective.sources {
+        if !source.parse_errors.is_empty() {
+            log::warn!(
+                "Skipping broken settings file {}: {:?}",
+                source.path,
+                source.parse_errors
+            );
+        }
+    }
+    Ok(effective)
+}
+
+/// Save settings to the user, project or local scope of `project_path`,
+/// with the same validation, backup and history as `save_claude_settings`
+#[tauri::command]
+pub async fn save_claude_settings_scoped(
+    db: State<'_, AgentDb>,
+    project_path: String,
+    scope: SettingsScope,
+    settings: JsonValue,
+    strict: Option<bool>,
+) -> Result<SettingsSaveResult, String> {
+    if scope == SettingsScope::Managed {
+        return Err("Managed settings are set by your administrator".to_string());
+    }
+    let claude_dir = get_claude_dir().map_err(|e| e.to_string())?;
+    let project = PathBuf::from(&project_path);
+    if scope != SettingsScope::User && !project.is_dir() {
+        return Err(format!("Project path does not exist: {}", project_path));
+    }
+    let settings_path = scope_path(scope, &claude_dir, &project)
+        .ok_or_else(|| "No settings file for this scope".to_string())?;
+    log::info!("Saving Claude settings to {}", settings_path.display());
+
+    let result = save_settings(&settings_path, &settings, strict.unwrap_or(false))?;
+    if let Ok(content) = serde_json::to_string_pretty(&settings) {
+        record_snapshot_or_warn(&db, &settings_path, "save_claude_settings_scoped", &content);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_merge_overrides_scalars_combines_arrays_and_tracks_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = dir.path().join("user.json");
+        let project = dir.path().join("project.json");
+        let local = dir.path().join("local.json");
+        let broken = dir.path().join("broken.json");
+        fs::write(
+            &user,
+            r#"{"model": "opus", "env": {"A": "1"}, "permissions": {"allow": ["Bash(ls)"]}}"#,
+        )
+        .unwrap();
+        fs::write(
+            &project,
+            r#"{"model": "sonnet", "permissions": {"allow": ["Bash(ls)", "Read"]}}"#,
+        )
+        .unwrap();
+        fs::write(&local, r#"{"env": {"a/b": "2"}}"#).unwrap();
+        fs::write(&broken, "{ not json").unwrap();
+
+        let effective = merge_settings_files(&[
+            (SettingsScope::User, user.clone()),
+            (SettingsScope::Project, project.clone()),
+            (SettingsScope::Local, local.clone()),
+            (SettingsScope::Managed, broken.clone()),
+            (SettingsScope::Managed, dir.path().join("missing.json")),
+        ]);
+
+        assert_eq!(
+            effective.merged,
+            json!({
+                "model": "sonnet",
+                "env": {"A": "1", "a/b": "2"},
+                "permissions": {"allow": ["Bash(ls)", "Read"]}
+            })
+        );
+        let source = |pointer: &str| PathBuf::from(&effective.provenance[pointer]);
+        assert_eq!(source("/model"), project);
+        assert_eq!(source("/env/A"), user);
+        assert_eq!(source("/env/a~1b"), local);
+        assert_eq!(source("/permissions/allow/0"), user);
+        assert_eq!(source("/permissions/allow/1"), project);
+        assert_eq!(effective.provenance.len(), 5);
+
+        assert!(!effective.sources[3].parse_errors.is_empty());
+        assert!(!effective.sources[4].exists && effective.sources[4].parse_errors.is_empty());
+    }
+
+    #[test]
+    fn test_replacing_an_object_drops_its_old_provenance() {
+        let mut merged = json!({});
+        let mut provenance = BTreeMap::new();
+        merge_into(
+            &mut merged,
+            &json!({"statusLine": {"type": "command", "command": "x"}}),
+            "",
+            "user",
+            &mut provenance,
+        );
+        merge_into(
+            &mut merged,
+            &json!({"statusLine": "off"}),
+            "",
+            "local",
+            &mut provenance,
+        );
+        assert_eq!(merged, json!({"statusLine": "off"}));
+        assert_eq!(provenance.len(), 1);
+        assert_eq!(provenance["/statusLine"], "local");
+    }
+}
@@ -0,0 +1,237 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tauri::State;
+use zstd::stream::{decode_all, encode_all};
+
+use super::claude_settings::write_settings;
+use crate::commands::agents::AgentDb;
+
+/// Snapshots kept; older ones are dropped as new ones are recorded
+const MAX_SNAPSHOTS: i64 = 50;
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// A recorded version of a settings file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingsBackup {
+    pub id: i64,
+    pub settings_path: String,
+    /// The command that wrote this version
+    pub source: String,
+    pub created_at: String,
+    pub settings: JsonValue,
+}
+
+fn decompress(content: &[u8]) -> Result<String, String> {
+    let bytes = decode_all(content).map_err(|e| format!("Failed to decompress settings: {}", e))?;
+    String::from_utf8(bytes).map_err(|e| format!("Settings snapshot is not UTF-8: {}", e))
+}
+
+/// Record the content just written to a settings file. Nothing is recorded
+/// when it matches the file's latest snapshot; returns the new snapshot's id.
+pub fn record_snapshot(
+    conn: &Connection,
+    settings_path: &Path,
+    source: &str,
+    content: &str,
+) -> Result<Option<i64>, String> {
+    let settings_path = settings_path.to_string_lossy();
+    let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+    let latest: Option<String> = conn
+        .query_row(
+            "SELECT content_hash FROM settings_history WHERE settings_path = ?1 ORDER BY id DESC LIMIT 1",
+            params![settings_path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if latest.as_deref() == Some(hash.as_str()) {
+        return Ok(None);
+    }
+
+    let compressed = encode_all(content.as_bytes(), COMPRESSION_LEVEL)
+        .map_err(|e| format!("Failed to compress settings: {}", e))?;
+    conn.execute(
+        "INSERT INTO settings_history (settings_path, source, content, content_hash) VALUES (?1, ?2, ?3, ?4)",
+        params![settings_path, source, compressed, hash],
+    )
+    .map_err(|e| format!("Failed to record settings snapshot: {}", e))?;
+    let id = conn.last_insert_rowid();
+    conn.execute(
+        "DELETE FROM settings_history WHERE id NOT IN
+         (SELECT id FROM settings_history ORDER BY id DESC LIMIT ?1)",
+        params![MAX_SNAPSHOTS],
+    )
+    .map_err(|e| format!("Failed to prune settings history: {}", e))?;
+    Ok(Some(id))
+}
+
+/// Record a snapshot, logging rather than failing since the file is already written
+pub fn record_snapshot_or_warn(db: &AgentDb, settings_path: &Path, source: &str, content: &str) {
+    let result =
+        db.0.lock()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| record_snapshot(&conn, settings_path, source, content));
+    if let Err(e) = result {
+        log::warn!("Failed to record settings history: {}", e);
+    }
+}
+
+fn backup_from_row(row: &rusqlite::Row) -> rusqlite::Result<(SettingsBackup, Vec<u8>)> {
+    Ok((
+        SettingsBackup {
+            id: row.get(0)?,
+            settings_path: row.get(1)?,
+            source: row.get(2)?,
+            created_at: row.get(3)?,
+            settings: JsonValue::Null,
+        },
+        row.get(4)?,
+    ))
+}
+
+fn with_settings(
+    (mut backup, content): (SettingsBackup, Vec<u8>),
+) -> Result<SettingsBackup, String> {
+    let content = decompress(&content)?;
+    backup.settings = serde_json::from_str(&content)
+        .map_err(|e| format!("Settings snapshot {} is not valid JSON: {}", backup.id, e))?;
+    Ok(backup)
+}
+
+/// Recorded snapshots, newest first
+pub fn load_settings_backups(conn: &Connection) -> Result<Vec<SettingsBackup>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, settings_path, source, created_at, content FROM settings_history ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], backup_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    rows.into_iter().map(with_settings).collect()
+}
+
+/// Write a snapshot back to its settings file and record the restore
+pub fn restore_snapshot(conn: &Connection, backup_id: i64) -> Result<SettingsBackup, String> {
+    let row = conn
+        .query_row(
+            "SELECT id, settings_path, source, created_at, content FROM settings_history WHERE id = ?1",
+            params![backup_id],
+            backup_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Settings backup not found: {}", backup_id))?;
+    let content = decompress(&row.1)?;
+    let backup = with_settings(row)?;
+
+    let settings_path = Path::new(&backup.settings_path);
+    write_settings(settings_path, &content)?;
+    record_snapshot(
+        conn,
+        settings_path,
+        &format!("restore_settings_backup:{}", backup.id),
+        &content,
+    )?;
+    Ok(backup)
+}
+
+/// List recorded versions of the settings files opcode has written, newest first
+#[tauri::command]
+pub async fn list_settings_backups(db: State<'_, AgentDb>) -> Result<Vec<SettingsBackup>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    load_settings_backups(&conn)
+}
+
+/// Write a recorded version back to its settings file
+#[tauri::command]
+pub async fn restore_settings_backup(
+    db: State<'_, AgentDb>,
+    backup_id: i64,
+) -> Result<SettingsBackup, String> {
+    log::info!("Restoring settings backup {}", backup_id);
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    restore_snapshot(&conn, backup_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshots_are_deduplicated_and_capped() {
+        let conn = crate::db::test_connection();
+        let path = Path::new("/home/me/.claude/settings.json");
+        let other = Path::new("/work/app/.claude/settings.json");
+
+        assert!(record_snapshot(&conn, path, "save_claude_settings", "{}")
+            .unwrap()
+            .is_some());
+        assert!(record_snapshot(&conn, path, "update_hooks_config", "{}")
+            .unwrap()
+            .is_none());
+        assert!(record_snapshot(&conn, other, "update_hooks_config", "{}")
+            .unwrap()
+            .is_some());
+        for i in 0..MAX_SNAPSHOTS {
+            let content = format!("{{\"cleanupPeriodDays\": {}}}", i);
+            record_snapshot(&conn, path, "save_claude_settings", &content).unwrap();
+        }
+
+        let backups = load_settings_backups(&conn).unwrap();
+        assert_eq!(backups.len() as i64, MAX_SNAPSHOTS);
+        assert_eq!(
+            backups[0].settings,
+            serde_json::json!({ "cleanupPeriodDays": MAX_SNAPSHOTS - 1 })
+        );
+        assert!(backups
+            .iter()
+            .all(|b| b.settings_path == path.to_string_lossy()));
+    }
+
+    #[test]
+    fn test_restores_write_the_file_and_are_recorded() {
+        let conn = crate::db::test_connection();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+
+        std::fs::write(&path, "{\"model\": \"opus\"}").unwrap();
+        record_snapshot(
+            &conn,
+            &path,
+            "save_claude_settings",
+            "{\"model\": \"opus\"}",
+        )
+        .unwrap();
+        std::fs::write(&path, "{\"model\": \"sonnet\"}").unwrap();
+        record_snapshot(
+            &conn,
+            &path,
+            "save_claude_settings",
+            "{\"model\": \"sonnet\"}",
+        )
+        .unwrap();
+
+        let oldest = load_settings_backups(&conn).unwrap().pop().unwrap();
+        let restored = restore_snapshot(&conn, oldest.id).unwrap();
+        assert_eq!(restored.settings["model"], "opus");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"model\": \"opus\"}"
+        );
+
+        let backups = load_settings_backups(&conn).unwrap();
+        assert_eq!(backups.len(), 3);
+        assert_eq!(
+            backups[0].source,
+            format!("restore_settings_backup:{}", oldest.id)
+        );
+        assert!(restore_snapshot(&conn, 999).is_err());
+    }
+}
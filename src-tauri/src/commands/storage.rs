@@ -447,8 +447,58 @@ pub async fn storage_reset_database(app: AppHandle) -> Result<(), String> {
             .map_err(|e| format!("Failed to drop agent_runs table: {}", e))?;
         conn.execute("DROP TABLE IF EXISTS agents", [])
             .map_err(|e| format!("Failed to drop agents table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_versions", [])
+            .map_err(|e| format!("Failed to drop agent_versions table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_icons", [])
+            .map_err(|e| format!("Failed to drop agent_icons table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS recent_project_paths", [])
+            .map_err(|e| format!("Failed to drop recent_project_paths table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_run_batches", [])
+            .map_err(|e| format!("Failed to drop agent_run_batches table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS github_cache", [])
+            .map_err(|e| format!("Failed to drop github_cache table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_titles", [])
+            .map_err(|e| format!("Failed to drop session_titles table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS archived_projects", [])
+            .map_err(|e| format!("Failed to drop archived_projects table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_previews", [])
+            .map_err(|e| format!("Failed to drop session_previews table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS pinned_sessions", [])
+            .map_err(|e| format!("Failed to drop pinned_sessions table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS claude_md_templates", [])
+            .map_err(|e| format!("Failed to drop claude_md_templates table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS settings_history", [])
+            .map_err(|e| format!("Failed to drop settings_history table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS project_system_prompts", [])
+            .map_err(|e| format!("Failed to drop project_system_prompts table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS prompt_history", [])
+            .map_err(|e| format!("Failed to drop prompt_history table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS redaction_patterns", [])
+            .map_err(|e| format!("Failed to drop redaction_patterns table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS project_dirs", [])
+            .map_err(|e| format!("Failed to drop project_dirs table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS project_settings", [])
+            .map_err(|e| format!("Failed to drop project_settings table: {}", e))?;
         conn.execute("DROP TABLE IF EXISTS app_settings", [])
             .map_err(|e| format!("Failed to drop app_settings table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS schedules", [])
+            .map_err(|e| format!("Failed to drop schedules table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS pipeline_runs", [])
+            .map_err(|e| format!("Failed to drop pipeline_runs table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS pipeline_steps", [])
+            .map_err(|e| format!("Failed to drop pipeline_steps table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS pipelines", [])
+            .map_err(|e| format!("Failed to drop pipelines table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_run_fts", [])
+            .map_err(|e| format!("Failed to drop agent_run_fts table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS agent_run_fts_runs", [])
+            .map_err(|e| format!("Failed to drop agent_run_fts_runs table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_fts", [])
+            .map_err(|e| format!("Failed to drop session_fts table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS session_fts_files", [])
+            .map_err(|e| format!("Failed to drop session_fts_files table: {}", e))?;
+        conn.execute("DROP TABLE IF EXISTS schema_version", [])
+            .map_err(|e| format!("Failed to drop schema_version table: {}", e))?;
         
         // Re-enable foreign key constraints
         conn.execute("PRAGMA foreign_keys = ON", [])
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{BufRead, BufReader};
+
+use super::claude::session_file_path;
+use super::pricing::current_pricing;
+
+/// Roughly how many tokens a session's history takes up. Recorded usage is
+/// exact; everything else is estimated from its length.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SessionTokenEstimate {
+    /// Output tokens Claude reported for its own messages
+    pub exact_tokens: u64,
+    /// Estimated from characters for entries without recorded usage
+    pub estimated_tokens: u64,
+    pub total: u64,
+    /// Exact and estimated tokens by entry type, e.g. `user` and `assistant`
+    pub breakdown_by_role: BTreeMap<String, u64>,
+    /// What estimated tokens were counted with
+    pub chars_per_token: f64,
+}
+
+/// Estimated token count of some text
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TextTokenEstimate {
+    pub estimated_tokens: u64,
+    pub chars_per_token: f64,
+}
+
+/// Estimated tokens of `chars` characters
+pub fn estimate_tokens(chars: usize, chars_per_token: f64) -> u64 {
+    (chars as f64 / chars_per_token).ceil() as u64
+}
+
+/// Characters of the text in message content: strings and the text, input
+/// and results of content blocks, without IDs and other bookkeeping fields
+fn content_chars(content: &JsonValue) -> usize {
+    match content {
+        JsonValue::String(text) => text.chars().count(),
+        JsonValue::Array(items) => items.iter().map(content_chars).sum(),
+        JsonValue::Object(block) => block
+            .iter()
+            .map(|(key, value)| match key.as_str() {
+                "text" | "thinking" | "content" => content_chars(value),
+                "input" => value.to_string().chars().count(),
+                _ => 0,
+            })
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Tally the entries of a session's JSONL. Assistant messages are split over
+/// several entries that repeat the same usage, so it's counted once per
+/// message ID.
+pub fn estimate_session_entries<R: BufRead>(
+    reader: R,
+    chars_per_token: f64,
+) -> SessionTokenEstimate {
+    let mut estimate = SessionTokenEstimate {
+        chars_per_token,
+        ..Default::default()
+    };
+    let mut counted_messages = HashSet::new();
+    for line in reader.lines().map_while(Result::ok) {
+        let Ok(entry) = serde_json::from_str::<JsonValue>(&line) else {
+            continue;
+        };
+        let role = entry
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or("other")
+            .to_string();
+
+        let output_tokens = entry
+            .pointer("/message/usage/output_tokens")
+            .and_then(|t| t.as_u64());
+        let tokens = match output_tokens {
+            Some(output_tokens) => {
+                let message_id = entry.pointer("/message/id").and_then(|id| id.as_str());
+                if message_id.is_some_and(|id| !counted_messages.insert(id.to_string())) {
+                    continue;
+                }
+                estimate.exact_tokens += output_tokens;
+                output_tokens
+            }
+            None => {
+                let chars = match role.as_str() {
+                    "summary" => entry.get("summary").map_or(0, content_chars),
+                    _ => entry.pointer("/message/content").map_or(0, content_chars),
+                };
+                let tokens = estimate_tokens(chars, chars_per_token);
+                estimate.estimated_tokens += tokens;
+                tokens
+            }
+        };
+        if tokens > 0 {
+            *estimate.breakdown_by_role.entry(role).or_insert(0) += tokens;
+        }
+    }
+    estimate.total = estimate.exact_tokens + estimate.estimated_tokens;
+    estimate
+}
+
+/// The model a session's last assistant message came from
+fn session_model<R: BufRead>(reader: R) -> Option<String> {
+    reader
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<JsonValue>(&line).ok())
+        .filter_map(|entry| {
+            entry
+                .pointer("/message/model")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+        })
+        .last()
+}
+
+fn resolve_chars_per_token(chars_per_token: Option<f64>, model: &str) -> Result<f64, String> {
+    match chars_per_token {
+        Some(chars) if chars.is_finite() && chars > 0.0 => Ok(chars),
+        Some(_) => Err("Characters per token must be a positive number".to_string()),
+        None => Ok(current_pricing().chars_per_token_for(model)),
+    }
+}
+
+/// Estimate how many tokens a session's history takes up, e.g. before
+/// resuming it. Counts are per the model table's characters per token for
+/// the session's model unless `chars_per_token` is given.
+#[tauri::command]
+pub async fn estimate_session_tokens(
+    project_id: String,
+    session_id: String,
+    chars_per_token: Option<f64>,
+) -> Result<SessionTokenEstimate, String> {
+    let path = session_file_path(&project_id, &session_id)?;
+    let open = || fs::File::open(&path).map_err(|e| format!("Failed to open session file: {}", e));
+    let model = session_model(BufReader::new(open()?)).unwrap_or_default();
+    let chars_per_token = resolve_chars_per_token(chars_per_token, &model)?;
+    Ok(estimate_session_entries(
+        BufReader::new(open()?),
+        chars_per_token,
+    ))
+}
+
+/// Estimate the tokens of some text for a model, e.g. a prompt being written
+#[tauri::command]
+pub async fn estimate_text_tokens(
+    text: String,
+    model: Option<String>,
+) -> Result<TextTokenEstimate, String> {
+    let chars_per_token = resolve_chars_per_token(None, model.as_deref().unwrap_or_default())?;
+    Ok(TextTokenEstimate {
+        estimated_tokens: estimate_tokens(text.chars().count(), chars_per_token),
+        chars_per_token,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_usage_is_exact_and_the_rest_estimated() {
+        let session = [
+            r#"{"type":"summary","summary":"abcdefgh"}"#,
+            r#"{"type":"user","message":{"role":"user","content":"abcdefghij"}}"#,
+            r#"{"type":"assistant","message":{"id":"m1","model":"claude-sonnet-4","content":[{"type":"text","text":"x"}],"usage":{"output_tokens":40}}}"#,
+            r#"{"type":"assistant","message":{"id":"m1","model":"claude-sonnet-4","content":[{"type":"tool_use","id":"t1","input":{"a":1}}],"usage":{"output_tokens":40}}}"#,
+            r#"{"type":"user","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"abcd"}]}}"#,
+            "not json",
+        ]
+        .join("\n");
+
+        let estimate = estimate_session_entries(Cursor::new(&session), 2.0);
+        assert_eq!(estimate.exact_tokens, 40);
+        // 8 summary characters, then 10 and 4 user characters, at 2 per token
+        assert_eq!(estimate.estimated_tokens, 4 + 5 + 2);
+        assert_eq!(estimate.total, 51);
+        assert_eq!(
+            estimate.breakdown_by_role,
+            BTreeMap::from([
+                ("assistant".to_string(), 40),
+                ("summary".to_string(), 4),
+                ("user".to_string(), 7),
+            ])
+        );
+        assert_eq!(
+            session_model(Cursor::new(&session)).as_deref(),
+            Some("claude-sonnet-4")
+        );
+    }
+
+    #[test]
+    fn test_text_estimates_round_up() {
+        assert_eq!(estimate_tokens(0, 3.5), 0);
+        assert_eq!(estimate_tokens(7, 3.5), 2);
+        assert_eq!(estimate_tokens(8, 3.5), 3);
+        assert!(resolve_chars_per_token(Some(0.0), "opus").is_err());
+        assert_eq!(resolve_chars_per_token(Some(4.0), "opus"), Ok(4.0));
+    }
+}
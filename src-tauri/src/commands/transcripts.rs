@@ -0,0 +1,1008 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use tauri::{AppHandle, State};
+
+use crate::commands::agents::{find_session_file, get_agent_run, AgentDb, AgentRun};
+use crate::commands::redaction::{RedactionCounts, Redactor};
+
+/// Longest tool result kept in markdown and HTML transcripts
+const MAX_TOOL_RESULT_CHARS: usize = 2000;
+
+/// A piece of a transcript message
+#[derive(Debug, Clone, PartialEq)]
+enum TranscriptBlock {
+    Text(String),
+    Thinking(String),
+    ToolCall { name: String, summary: String, input: JsonValue },
+    ToolResult { text: String, is_error: bool },
+}
+
+/// A user or assistant turn
+#[derive(Debug, Clone, PartialEq)]
+struct TranscriptTurn {
+    role: String,
+    blocks: Vec<TranscriptBlock>,
+}
+
+/// JSONL lines of a run with the malformed ones removed
+struct ParsedTranscript {
+    lines: Vec<(String, JsonValue)>,
+    skipped_lines: usize,
+}
+
+fn parse_jsonl(jsonl: &str) -> ParsedTranscript {
+    let mut lines = Vec::new();
+    let mut skipped_lines = 0;
+
+    for line in jsonl.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<JsonValue>(line) {
+            Ok(json) => lines.push((line.to_string(), json)),
+            Err(_) => skipped_lines += 1,
+        }
+    }
+
+    ParsedTranscript {
+        lines,
+        skipped_lines,
+    }
+}
+
+/// One-line description of a tool call's input
+fn summarize_tool_input(input: &JsonValue) -> String {
+    for key in ["command", "file_path", "path", "pattern", "url", "description"] {
+        if let Some(value) = input.get(key).and_then(|v| v.as_str()) {
+            return value.lines().next().unwrap_or_default().to_string();
+        }
+    }
+    match input.as_object() {
+        Some(object) if !object.is_empty() => {
+            let keys: Vec<&str> = object.keys().map(|k| k.as_str()).collect();
+            format!("({})", keys.join(", "))
+        }
+        _ => String::new(),
+    }
+}
+
+fn tool_result_text(content: Option<&JsonValue>) -> String {
+    match content {
+        Some(JsonValue::String(text)) => text.clone(),
+        Some(JsonValue::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((index, _)) => format!("{}\n… ({} more characters)", &text[..index], text[index..].chars().count()),
+        None => text.to_string(),
+    }
+}
+
+/// Role and blocks of a user or assistant JSONL message, or `None` for other lines
+fn message_blocks(json: &JsonValue, include_thinking: bool) -> Option<(String, Vec<TranscriptBlock>)> {
+    let role = match json.get("type").and_then(|t| t.as_str()) {
+        Some(role @ ("user" | "assistant")) => role.to_string(),
+        _ => return None,
+    };
+
+    let blocks = match json.get("message").and_then(|m| m.get("content")) {
+        Some(JsonValue::String(text)) => vec![TranscriptBlock::Text(text.clone())],
+        Some(JsonValue::Array(items)) => items
+            .iter()
+            .filter_map(|item| match item.get("type").and_then(|t| t.as_str()) {
+                Some("text") => item
+                    .get("text")
+                    .and_then(|t| t.as_str())
+                    .map(|t| TranscriptBlock::Text(t.to_string())),
+                Some("thinking") if include_thinking => item
+                    .get("thinking")
+                    .and_then(|t| t.as_str())
+                    .map(|t| TranscriptBlock::Thinking(t.to_string())),
+                Some("tool_use") => {
+                    let input = item.get("input").cloned().unwrap_or(JsonValue::Null);
+                    Some(TranscriptBlock::ToolCall {
+                        name: item
+                            .get("name")
+                            .and_then(|n| n.as_str())
+                            .unwrap_or("tool")
+                            .to_string(),
+                        summary: summarize_tool_input(&input),
+                        input,
+                    })
+                }
+                Some("tool_result") => Some(TranscriptBlock::ToolResult {
+                    text: tool_result_text(item.get("content")),
+                    is_error: item
+                        .get("is_error")
+                        .and_then(|e| e.as_bool())
+                        .unwrap_or(false),
+                }),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    if blocks.is_empty() {
+        return None;
+    }
+    Some((role, blocks))
+}
+
+/// Group JSONL messages into user and assistant turns
+fn collect_turns(parsed: &ParsedTranscript) -> Vec<TranscriptTurn> {
+    let mut turns = Vec::new();
+
+    for (_, json) in &parsed.lines {
+        let (role, blocks) = match message_blocks(json, false) {
+            Some(message) => message,
+            None => continue,
+        };
+
+        // Streamed output splits one message into several lines
+        match turns.last_mut() {
+            Some(TranscriptTurn { role: last_role, blocks: last_blocks }) if *last_role == role => {
+                last_blocks.extend(blocks)
+            }
+            _ => turns.push(TranscriptTurn { role, blocks }),
+        }
+    }
+
+    turns
+}
+
+/// Header lines describing the run and anything missing from the export
+fn header_notes(run: &AgentRun, in_progress: bool, skipped_lines: usize) -> Vec<String> {
+    let mut notes = vec![
+        format!("Task: {}", run.task),
+        format!("Model: {} | Project: {} | Started: {}", run.model, run.project_path, run.created_at),
+        format!("Status: {}", run.status),
+    ];
+    if in_progress {
+        notes.push("Run still executing - this transcript is incomplete".to_string());
+    }
+    if skipped_lines > 0 {
+        notes.push(format!("Skipped {} malformed JSONL line(s)", skipped_lines));
+    }
+    notes
+}
+
+fn render_jsonl(run: &AgentRun, parsed: &ParsedTranscript, in_progress: bool) -> String {
+    let mut out = String::new();
+    if in_progress || parsed.skipped_lines > 0 {
+        let header = serde_json::json!({
+            "type": "export_header",
+            "run_id": run.id,
+            "status": run.status,
+            "incomplete": in_progress,
+            "skipped_lines": parsed.skipped_lines,
+        });
+        out.push_str(&header.to_string());
+        out.push('\n');
+    }
+    for (line, _) in &parsed.lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Pick a code fence longer than any backtick run inside the text
+fn code_fence(text: &str) -> String {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in text.chars() {
+        if c == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    "`".repeat((longest + 1).max(3))
+}
+
+fn render_markdown(run: &AgentRun, parsed: &ParsedTranscript, in_progress: bool) -> String {
+    let mut out = format!("# {} - run {}\n\n", run.agent_name, run.id.unwrap_or_default());
+    for note in header_notes(run, in_progress, parsed.skipped_lines) {
+        out.push_str(&format!("> {}\n", note));
+    }
+    out.push('\n');
+
+    for turn in collect_turns(parsed) {
+        let title = if turn.role == "user" { "User" } else { "Assistant" };
+        out.push_str(&format!("## {}\n\n", title));
+        for block in turn.blocks {
+            match block {
+                TranscriptBlock::Text(text) => {
+                    out.push_str(text.trim_end());
+                    out.push_str("\n\n");
+                }
+                TranscriptBlock::Thinking(_) => {}
+                TranscriptBlock::ToolCall { name, summary, .. } => {
+                    if summary.is_empty() {
+                        out.push_str(&format!("- Tool call: `{}`\n\n", name));
+                    } else {
+                        out.push_str(&format!("- Tool call: `{}` - {}\n\n", name, summary));
+                    }
+                }
+                TranscriptBlock::ToolResult { text, is_error } => {
+                    let text = truncate_chars(text.trim_end(), MAX_TOOL_RESULT_CHARS);
+                    let fence = code_fence(&text);
+                    let label = if is_error { "Tool error" } else { "Tool result" };
+                    out.push_str(&format!("{}:\n\n{}\n{}\n{}\n\n", label, fence, text, fence));
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Start of an HTML transcript, up to and including its heading. `title` must already be escaped.
+fn html_document_start(title: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n\
+         body {{ font-family: -apple-system, sans-serif; max-width: 860px; margin: 2rem auto; padding: 0 1rem; color: #222; }}\n\
+         .notes {{ color: #666; border-left: 3px solid #ccc; padding-left: 1rem; }}\n\
+         .turn {{ margin: 1.5rem 0; }}\n\
+         .user h2 {{ color: #2563eb; }} .assistant h2 {{ color: #7c3aed; }}\n\
+         .text {{ white-space: pre-wrap; }}\n\
+         .tool {{ font-family: monospace; color: #555; }}\n\
+         .thinking {{ color: #666; font-style: italic; }}\n\
+         pre {{ background: #f5f5f5; padding: 0.75rem; overflow-x: auto; }} pre.error {{ background: #fdecec; }}\n\
+         </style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        title, title
+    )
+}
+
+fn render_html(run: &AgentRun, parsed: &ParsedTranscript, in_progress: bool) -> String {
+    let title = escape_html(&format!("{} - run {}", run.agent_name, run.id.unwrap_or_default()));
+    let mut out = html_document_start(&title);
+    out.push_str("<div class=\"notes\">\n");
+    for note in header_notes(run, in_progress, parsed.skipped_lines) {
+        out.push_str(&format!("<p>{}</p>\n", escape_html(&note)));
+    }
+    out.push_str("</div>\n");
+
+    for turn in collect_turns(parsed) {
+        let title = if turn.role == "user" { "User" } else { "Assistant" };
+        out.push_str(&format!("<div class=\"turn {}\">\n<h2>{}</h2>\n", turn.role, title));
+        for block in turn.blocks {
+            match block {
+                TranscriptBlock::Text(text) => {
+                    out.push_str(&format!("<div class=\"text\">{}</div>\n", escape_html(text.trim_end())));
+                }
+                TranscriptBlock::Thinking(_) => {}
+                TranscriptBlock::ToolCall { name, summary, .. } => {
+                    out.push_str(&format!(
+                        "<p class=\"tool\">Tool call: {} {}</p>\n",
+                        escape_html(&name),
+                        escape_html(&summary)
+                    ));
+                }
+                TranscriptBlock::ToolResult { text, is_error } => {
+                    let text = truncate_chars(text.trim_end(), MAX_TOOL_RESULT_CHARS);
+                    let class = if is_error { " class=\"error\"" } else { "" };
+                    out.push_str(&format!("<pre{}>{}</pre>\n", class, escape_html(&text)));
+                }
+            }
+        }
+        out.push_str("</div>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Write a file by renaming a temporary file next to it, so readers never see a partial export
+fn write_atomically(path: &std::path::Path, data: &[u8]) -> Result<(), String> {
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| std::path::Path::new("."));
+    let mut temp = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    temp.write_all(data)
+        .map_err(|e| format!("Failed to write transcript: {}", e))?;
+    temp.persist(path)
+        .map_err(|e| format!("Failed to save transcript: {}", e.error))?;
+    Ok(())
+}
+
+/// Export an agent run's transcript as `jsonl`, `markdown` or `html`, returning bytes written
+#[tauri::command]
+pub async fn export_agent_run(
+    db: State<'_, AgentDb>,
+    registry: State<'_, crate::process::ProcessRegistryState>,
+    run_id: i64,
+    format: String,
+    output_path: String,
+) -> Result<u64, String> {
+    let render: fn(&AgentRun, &ParsedTranscript, bool) -> String = match format.as_str() {
+        "jsonl" => render_jsonl,
+        "markdown" | "md" => render_markdown,
+        "html" => render_html,
+        other => {
+            return Err(format!(
+                "Unsupported transcript format '{}', expected jsonl, markdown or html",
+                other
+            ))
+        }
+    };
+
+    let run = get_agent_run(db, run_id).await?;
+    let in_progress = matches!(run.status.as_str(), "pending" | "queued" | "running");
+
+    // Read from the session file like load_agent_session_history, falling back to live output
+    let session_file = if run.session_id.is_empty() {
+        None
+    } else {
+        find_session_file(&run.session_id)?
+    };
+    let jsonl = match session_file {
+        Some(path) => std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read session file: {}", e))?,
+        None => registry.0.read_run_output(run_id)?,
+    };
+    if jsonl.trim().is_empty() {
+        return Err(format!("No output recorded for run {}", run_id));
+    }
+
+    let parsed = parse_jsonl(&jsonl);
+    if parsed.skipped_lines > 0 {
+        warn!(
+            "Skipped {} malformed JSONL lines exporting run {}",
+            parsed.skipped_lines, run_id
+        );
+    }
+
+    let rendered = render(&run, &parsed, in_progress);
+    write_atomically(std::path::Path::new(&output_path), rendered.as_bytes())?;
+
+    info!(
+        "Exported run {} as {} to {} ({} bytes)",
+        run_id,
+        format,
+        output_path,
+        rendered.len()
+    );
+    Ok(rendered.len() as u64)
+}
+
+/// Output formats of `export_session`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SessionFormat {
+    Markdown,
+    Html,
+}
+
+/// Result of exporting a session transcript
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SessionExport {
+    pub bytes_written: u64,
+    pub messages: usize,
+    pub skipped_lines: usize, // Malformed JSONL lines left out of the export
+    /// Replacements made by each redaction rule; empty for an unredacted export
+    pub redactions: RedactionCounts,
+}
+
+/// Token usage summed over a session's assistant messages. Streamed messages
+/// repeat their usage on every line, so each message id is counted once.
+#[derive(Debug, Default)]
+struct UsageTotals {
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_creation_tokens: u64,
+    cache_read_tokens: u64,
+    counted_messages: HashSet<String>,
+}
+
+impl UsageTotals {
+    fn add(&mut self, json: &JsonValue) {
+        let message = match json.get("message") {
+            Some(message) => message,
+            None => return,
+        };
+        let usage = match message.get("usage") {
+            Some(usage) => usage,
+            None => return,
+        };
+        if let Some(id) = message.get("id").and_then(|id| id.as_str()) {
+            if !self.counted_messages.insert(id.to_string()) {
+                return;
+            }
+        }
+        let tokens = |key: &str| usage.get(key).and_then(|t| t.as_u64()).unwrap_or(0);
+        self.input_tokens += tokens("input_tokens");
+        self.output_tokens += tokens("output_tokens");
+        self.cache_creation_tokens += tokens("cache_creation_input_tokens");
+        self.cache_read_tokens += tokens("cache_read_input_tokens");
+    }
+}
+
+fn html_code_block(language: &str, code: &str) -> String {
+    let class = if language.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"language-{}\"", escape_html(language))
+    };
+    format!("<pre><code{}>{}</code></pre>\n", class, escape_html(code))
+}
+
+/// Render text with its fenced code blocks as HTML, keeping each block's language hint
+fn render_html_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut prose: Vec<&str> = Vec::new();
+    let mut code: Option<(String, String, Vec<&str>)> = None; // (fence, language, lines)
+
+    let flush_prose = |prose: &mut Vec<&str>, out: &mut String| {
+        let joined = prose.join("\n");
+        if !joined.trim().is_empty() {
+            out.push_str(&format!("<div class=\"text\">{}</div>\n", escape_html(joined.trim())));
+        }
+        prose.clear();
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match code.as_mut() {
+            Some((fence, language, lines)) => {
+                if trimmed.trim_end() == fence.as_str() {
+                    out.push_str(&html_code_block(language, &lines.join("\n")));
+                    code = None;
+                } else {
+                    lines.push(line);
+                }
+            }
+            None if trimmed.starts_with("```") => {
+                flush_prose(&mut prose, &mut out);
+                let fence: String = trimmed.chars().take_while(|c| *c == '`').collect();
+                let language = trimmed[fence.len()..].trim().to_string();
+                code = Some((fence, language, Vec::new()));
+            }
+            None => prose.push(line),
+        }
+    }
+    // An unclosed fence runs to the end of the message
+    if let Some((_, language, lines)) = code {
+        out.push_str(&html_code_block(&language, &lines.join("\n")));
+    }
+    flush_prose(&mut prose, &mut out);
+    out
+}
+
+/// Writes a session transcript turn by turn as its JSONL is read
+struct SessionTranscriptWriter<'r, W: Write> {
+    out: W,
+    format: SessionFormat,
+    include_thinking: bool,
+    redactor: Option<&'r Redactor>,
+    current_role: Option<String>,
+    usage: UsageTotals,
+    messages: usize,
+    skipped_lines: usize,
+    redactions: RedactionCounts,
+}
+
+impl<'r, W: Write> SessionTranscriptWriter<'r, W> {
+    fn new(
+        out: W,
+        format: SessionFormat,
+        include_thinking: bool,
+        redactor: Option<&'r Redactor>,
+    ) -> Self {
+        Self {
+            out,
+            format,
+            include_thinking,
+            redactor,
+            current_role: None,
+            usage: UsageTotals::default(),
+            messages: 0,
+            skipped_lines: 0,
+            redactions: redactor.map(Redactor::empty_counts).unwrap_or_default(),
+        }
+    }
+
+    /// Write rendered text, redacting it first unless the export is unredacted
+    fn write(&mut self, text: &str) -> Result<(), String> {
+        let text = match self.redactor {
+            Some(redactor) => redactor.redact(text, &mut self.redactions),
+            None => std::borrow::Cow::Borrowed(text),
+        };
+        self.out
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to write transcript: {}", e))
+    }
+
+    fn start(&mut self, title: &str, notes: &[String]) -> Result<(), String> {
+        let header = match self.format {
+            SessionFormat::Markdown => {
+                let mut header = format!("# {}\n\n", title);
+                for note in notes {
+                    header.push_str(&format!("> {}\n", note));
+                }
+                header.push('\n');
+                header
+            }
+            SessionFormat::Html => {
+                let mut header = html_document_start(&escape_html(title));
+                header.push_str("<div class=\"notes\">\n");
+                for note in notes {
+                    header.push_str(&format!("<p>{}</p>\n", escape_html(note)));
+                }
+                header.push_str("</div>\n");
+                header
+            }
+        };
+        self.write(&header)
+    }
+
+    /// Render one JSONL line, counting it as skipped when it isn't valid JSON
+    fn push_line(&mut self, line: &str) -> Result<(), String> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let json = match serde_json::from_str::<JsonValue>(line) {
+            Ok(json) => json,
+            Err(_) => {
+                self.skipped_lines += 1;
+                return Ok(());
+            }
+        };
+        if json.get("type").and_then(|t| t.as_str()) == Some("assistant") {
+            self.usage.add(&json);
+        }
+        let (role, blocks) = match message_blocks(&json, self.include_thinking) {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+        self.messages += 1;
+
+        // Streamed output splits one message into several lines
+        if self.current_role.as_deref() != Some(role.as_str()) {
+            let title = if role == "user" { "User" } else { "Assistant" };
+            let heading = match (self.format, self.current_role.is_some()) {
+                (SessionFormat::Markdown, _) => format!("## {}\n\n", title),
+                (SessionFormat::Html, true) => format!("</div>\n<div class=\"turn {}\">\n<h2>{}</h2>\n", role, title),
+                (SessionFormat::Html, false) => format!("<div class=\"turn {}\">\n<h2>{}</h2>\n", role, title),
+            };
+            self.write(&heading)?;
+            self.current_role = Some(role.clone());
+        }
+
+        for block in blocks {
+            let rendered = match self.format {
+                SessionFormat::Markdown => markdown_session_block(&role, block),
+                SessionFormat::Html => html_session_block(block),
+            };
+            self.write(&rendered)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(usize, usize, RedactionCounts), String> {
+        let usage = format!(
+            "Token usage: {} input, {} output, {} cache write, {} cache read",
+            self.usage.input_tokens,
+            self.usage.output_tokens,
+            self.usage.cache_creation_tokens,
+            self.usage.cache_read_tokens
+        );
+        let mut notes = vec![usage, format!("Messages: {}", self.messages)];
+        if self.skipped_lines > 0 {
+            notes.push(format!("Skipped {} malformed JSONL line(s)", self.skipped_lines));
+        }
+
+        let footer = match self.format {
+            SessionFormat::Markdown => format!("---\n\n{}\n", notes.join("  \n")),
+            SessionFormat::Html => {
+                let mut footer = String::new();
+                if self.current_role.is_some() {
+                    footer.push_str("</div>\n");
+                }
+                footer.push_str("<hr>\n<div class=\"notes\">\n");
+                for note in &notes {
+                    footer.push_str(&format!("<p>{}</p>\n", escape_html(note)));
+                }
+                footer.push_str("</div>\n</body>\n</html>\n");
+                footer
+            }
+        };
+        self.write(&footer)?;
+        self.out
+            .flush()
+            .map_err(|e| format!("Failed to write transcript: {}", e))?;
+        Ok((self.messages, self.skipped_lines, self.redactions))
+    }
+}
+
+fn markdown_session_block(role: &str, block: TranscriptBlock) -> String {
+    match block {
+        // User text is quoted so it stands apart from the assistant's markdown
+        TranscriptBlock::Text(text) if role == "user" => {
+            let quoted: Vec<String> = text
+                .trim_end()
+                .lines()
+                .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                .collect();
+            format!("{}\n\n", quoted.join("\n"))
+        }
+        TranscriptBlock::Text(text) => format!("{}\n\n", text.trim_end()),
+        TranscriptBlock::Thinking(text) => format!(
+            "<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>\n\n",
+            text.trim_end()
+        ),
+        TranscriptBlock::ToolCall { name, summary, input } => {
+            let input = serde_json::to_string_pretty(&input).unwrap_or_default();
+            let fence = code_fence(&input);
+            format!(
+                "<details>\n<summary>Tool call: {} {}</summary>\n\n{}json\n{}\n{}\n\n</details>\n\n",
+                escape_html(&name),
+                escape_html(&summary),
+                fence,
+                input,
+                fence
+            )
+        }
+        TranscriptBlock::ToolResult { text, is_error } => {
+            let text = truncate_chars(text.trim_end(), MAX_TOOL_RESULT_CHARS);
+            let fence = code_fence(&text);
+            let label = if is_error { "Tool error" } else { "Tool result" };
+            format!("{}:\n\n{}\n{}\n{}\n\n", label, fence, text, fence)
+        }
+    }
+}
+
+fn html_session_block(block: TranscriptBlock) -> String {
+    match block {
+        TranscriptBlock::Text(text) => render_html_text(&text),
+        TranscriptBlock::Thinking(text) => format!(
+            "<details class=\"thinking\">\n<summary>Thinking</summary>\n{}</details>\n",
+            render_html_text(&text)
+        ),
+        TranscriptBlock::ToolCall { name, summary, input } => format!(
+            "<details class=\"tool\">\n<summary>Tool call: {} {}</summary>\n<pre><code class=\"language-json\">{}</code></pre>\n</details>\n",
+            escape_html(&name),
+            escape_html(&summary),
+            escape_html(&serde_json::to_string_pretty(&input).unwrap_or_default())
+        ),
+        TranscriptBlock::ToolResult { text, is_error } => {
+            let text = truncate_chars(text.trim_end(), MAX_TOOL_RESULT_CHARS);
+            let class = if is_error { " class=\"error\"" } else { "" };
+            format!("<pre{}>{}</pre>\n", class, escape_html(&text))
+        }
+    }
+}
+
+/// Render a session's JSONL as it is read, line by line. Lines that aren't
+/// valid JSON or UTF-8, such as a truncated last line, are skipped and counted.
+/// Returns (messages rendered, lines skipped, redactions made).
+fn write_session_transcript<R: BufRead, W: Write>(
+    mut reader: R,
+    out: W,
+    format: SessionFormat,
+    include_thinking: bool,
+    redactor: Option<&Redactor>,
+    title: &str,
+    notes: &[String],
+) -> Result<(usize, usize, RedactionCounts), String> {
+    let mut writer = SessionTranscriptWriter::new(out, format, include_thinking, redactor);
+    writer.start(title, notes)?;
+
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| format!("Failed to read session file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        match std::str::from_utf8(&line) {
+            Ok(text) => writer.push_line(text)?,
+            Err(_) => writer.skipped_lines += 1,
+        }
+    }
+    writer.finish()
+}
+
+fn parse_session_format(format: &str) -> Result<SessionFormat, String> {
+    match format {
+        "markdown" | "md" => Ok(SessionFormat::Markdown),
+        "html" => Ok(SessionFormat::Html),
+        other => Err(format!(
+            "Unsupported transcript format '{}', expected markdown or html",
+            other
+        )),
+    }
+}
+
+/// A session's JSONL file with the title and notes its transcript starts with
+fn open_session(
+    db: &AgentDb,
+    project_id: &str,
+    session_id: &str,
+) -> Result<(std::fs::File, String, Vec<String>), String> {
+    super::claude::validate_path_component("project id", project_id)?;
+    super::claude::validate_path_component("session id", session_id)?;
+    let claude_dir = super::claude::get_claude_dir().map_err(|e| e.to_string())?;
+    let session_path = claude_dir
+        .join("projects")
+        .join(project_id)
+        .join(format!("{}.jsonl", session_id));
+    let file = std::fs::File::open(&session_path)
+        .map_err(|_| format!("Session file not found: {}", session_id))?;
+
+    let custom_title = {
+        let conn = db.0.lock().map_err(|e| e.to_string())?;
+        super::session_titles::load_session_titles(&conn, project_id)?.remove(session_id)
+    };
+    let title = custom_title.unwrap_or_else(|| format!("Session {}", session_id));
+    let notes = vec![
+        format!("Session: {}", session_id),
+        format!("Project: {}", project_id),
+        format!("Exported: {}", chrono::Utc::now().to_rfc3339()),
+    ];
+    Ok((file, title, notes))
+}
+
+/// The redaction rules to apply, or None for an unredacted transcript
+fn load_redactor(db: &AgentDb, unredacted: bool) -> Result<Option<Redactor>, String> {
+    if unredacted {
+        return Ok(None);
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    Redactor::load(&conn).map(Some)
+}
+
+fn log_redactions(session_id: &str, redactions: &RedactionCounts) {
+    let total: usize = redactions.values().sum();
+    if total > 0 {
+        info!("Redacted {} secret(s) from session {}", total, session_id);
+    }
+}
+
+/// Export a Claude session's transcript as `markdown` or `html`. Thinking
+/// blocks are left out unless `include_thinking` is set. Secrets matching
+/// the redaction patterns are replaced unless `unredacted` is set.
+#[tauri::command]
+pub async fn export_session(
+    db: State<'_, AgentDb>,
+    project_id: String,
+    session_id: String,
+    format: String,
+    output_path: String,
+    include_thinking: Option<bool>,
+    unredacted: Option<bool>,
+) -> Result<SessionExport, String> {
+    let format = parse_session_format(&format)?;
+    let (file, title, notes) = open_session(&db, &project_id, &session_id)?;
+    let redactor = load_redactor(&db, unredacted.unwrap_or(false))?;
+
+    // Stream into a temporary file next to the output, so readers never see a partial export
+    let output_path = std::path::Path::new(&output_path);
+    let dir = output_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or_else(|| std::path::Path::new("."));
+    let temp = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    let (messages, skipped_lines, redactions) = write_session_transcript(
+        BufReader::new(file),
+        BufWriter::new(temp.as_file()),
+        format,
+        include_thinking.unwrap_or(false),
+        redactor.as_ref(),
+        &title,
+        &notes,
+    )?;
+    let bytes_written = temp
+        .as_file()
+        .metadata()
+        .map_err(|e| format!("Failed to write transcript: {}", e))?
+        .len();
+    temp.persist(output_path)
+        .map_err(|e| format!("Failed to save transcript: {}", e.error))?;
+
+    if skipped_lines > 0 {
+        warn!(
+            "Skipped {} malformed JSONL lines exporting session {}",
+            skipped_lines, session_id
+        );
+    }
+    log_redactions(&session_id, &redactions);
+    info!(
+        "Exported session {} as {:?} to {:?} ({} bytes)",
+        session_id, format, output_path, bytes_written
+    );
+    Ok(SessionExport {
+        bytes_written,
+        messages,
+        skipped_lines,
+        redactions,
+    })
+}
+
+/// Copy a Claude session's transcript to the clipboard as `markdown`
+/// (the default) or `html`, redacted like `export_session`
+#[tauri::command]
+pub async fn copy_session_to_clipboard(
+    app: AppHandle,
+    db: State<'_, AgentDb>,
+    project_id: String,
+    session_id: String,
+    format: Option<String>,
+    include_thinking: Option<bool>,
+    unredacted: Option<bool>,
+) -> Result<SessionExport, String> {
+    use tauri_plugin_clipboard_manager::ClipboardExt;
+
+    let format = parse_session_format(format.as_deref().unwrap_or("markdown"))?;
+    let (file, title, notes) = open_session(&db, &project_id, &session_id)?;
+    let redactor = load_redactor(&db, unredacted.unwrap_or(false))?;
+
+    let mut out = Vec::new();
+    let (messages, skipped_lines, redactions) = write_session_transcript(
+        BufReader::new(file),
+        &mut out,
+        format,
+        include_thinking.unwrap_or(false),
+        redactor.as_ref(),
+        &title,
+        &notes,
+    )?;
+    let text = String::from_utf8(out).map_err(|e| format!("Failed to render transcript: {}", e))?;
+    let bytes_written = text.len() as u64;
+    app.clipboard()
+        .write_text(text)
+        .map_err(|e| format!("Failed to copy transcript: {}", e))?;
+
+    log_redactions(&session_id, &redactions);
+    Ok(SessionExport {
+        bytes_written,
+        messages,
+        skipped_lines,
+        redactions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{"type":"system","subtype":"init","session_id":"abc"}
+{"type":"user","message":{"role":"user","content":"Fix the build"}}
+{"type":"assistant","message":{"content":[{"type":"text","text":"Checking"}]}}
+{"type":"assistant","message":{"content":[{"type":"tool_use","name":"Bash","input":{"command":"cargo build"}}]}}
+{"type":"user","message":{"content":[{"type":"tool_result","content":"error: ```oops```","is_error":true}]}}
+{"type":"assistant","message":{"content":[{"type":"te"#;
+
+    #[test]
+    fn test_malformed_lines_are_counted_and_skipped() {
+        let parsed = parse_jsonl(SAMPLE);
+        assert_eq!(parsed.lines.len(), 5);
+        assert_eq!(parsed.skipped_lines, 1);
+    }
+
+    #[test]
+    fn test_streamed_lines_merge_into_turns() {
+        let turns = collect_turns(&parse_jsonl(SAMPLE));
+        let roles: Vec<&str> = turns.iter().map(|t| t.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "user"]);
+        assert_eq!(
+            turns[1].blocks[1],
+            TranscriptBlock::ToolCall {
+                name: "Bash".to_string(),
+                summary: "cargo build".to_string(),
+                input: serde_json::json!({"command": "cargo build"}),
+            }
+        );
+    }
+
+    const SESSION: &str = r#"{"type":"user","message":{"role":"user","content":"Write a presign helper\n\nfor S3"}}
+{"type":"assistant","message":{"id":"m1","content":[{"type":"thinking","thinking":"Use the SDK"}],"usage":{"input_tokens":10,"output_tokens":5}}}
+{"type":"assistant","message":{"id":"m1","content":[{"type":"text","text":"Here:\n```rust\nfn presign() {}\n```"}],"usage":{"input_tokens":10,"output_tokens":5}}}
+{"type":"assistant","message":{"id":"m2","content":[{"type":"tool_use","name":"Write","input":{"file_path":"s3.rs"}}],"usage":{"input_tokens":3,"output_tokens":2,"cache_read_input_tokens":7}}}
+{"type":"user","message":{"content":[{"type":"tool_result","content":"ok"}]}}
+{"type":"assistant","message":{"id":"m3","content":[{"type":"te"#;
+
+    fn export(format: SessionFormat, include_thinking: bool) -> (String, usize, usize) {
+        let mut out = Vec::new();
+        let (messages, skipped, _) = write_session_transcript(
+            SESSION.as_bytes(),
+            &mut out,
+            format,
+            include_thinking,
+            None,
+            "Session s1",
+            &["Project: p".to_string()],
+        )
+        .unwrap();
+        (String::from_utf8(out).unwrap(), messages, skipped)
+    }
+
+    #[test]
+    fn test_session_markdown_quotes_users_and_totals_usage() {
+        let (markdown, messages, skipped) = export(SessionFormat::Markdown, false);
+        assert_eq!((messages, skipped), (4, 1));
+        assert!(markdown.starts_with("# Session s1\n\n> Project: p\n"));
+        assert!(markdown.contains("## User\n\n> Write a presign helper\n>\n> for S3\n"));
+        assert!(markdown.contains("```rust\nfn presign() {}\n```"));
+        assert!(markdown.contains("<summary>Tool call: Write s3.rs</summary>\n\n```json\n"));
+        assert!(!markdown.contains("Use the SDK"));
+        assert!(markdown.contains("Token usage: 13 input, 7 output, 0 cache write, 7 cache read"));
+        assert!(markdown.contains("Skipped 1 malformed JSONL line(s)"));
+        // Consecutive assistant lines share one heading
+        assert_eq!(markdown.matches("## Assistant").count(), 1);
+
+        let (markdown, _, _) = export(SessionFormat::Markdown, true);
+        assert!(markdown.contains("<summary>Thinking</summary>\n\nUse the SDK"));
+    }
+
+    #[test]
+    fn test_session_html_keeps_code_language_and_closes_turns() {
+        let (html, _, _) = export(SessionFormat::Html, false);
+        assert!(html.contains("<pre><code class=\"language-rust\">fn presign() {}</code></pre>"));
+        assert!(html.contains("<div class=\"text\">Here:</div>"));
+        assert_eq!(html.matches("<div class=\"turn ").count(), 3);
+        assert_eq!(html.matches("<div").count(), html.matches("</div>").count());
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn test_session_exports_are_redacted_unless_asked_not_to() {
+        let conn = crate::db::test_connection();
+        let redactor = Redactor::load(&conn).unwrap();
+        let session = r#"{"type":"user","message":{"role":"user","content":"My key is sk-ant-REDACTED"}}"#;
+
+        let mut out = Vec::new();
+        let (_, _, redactions) = write_session_transcript(
+            session.as_bytes(),
+            &mut out,
+            SessionFormat::Markdown,
+            false,
+            Some(&redactor),
+            "Session s1",
+            &[],
+        )
+        .unwrap();
+        let markdown = String::from_utf8(out).unwrap();
+        assert!(markdown.contains("> My key is [REDACTED:api_key]"));
+        assert_eq!(redactions["api_key"], 1);
+
+        let mut out = Vec::new();
+        let (_, _, redactions) = write_session_transcript(
+            session.as_bytes(),
+            &mut out,
+            SessionFormat::Markdown,
+            false,
+            None,
+            "Session s1",
+            &[],
+        )
+        .unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("sk-ant-api03-"));
+        assert!(redactions.is_empty());
+    }
+
+    #[test]
+    fn test_code_fence_outgrows_content_backticks() {
+        assert_eq!(code_fence("plain"), "```");
+        assert_eq!(code_fence("error: ```oops```"), "````");
+    }
+}
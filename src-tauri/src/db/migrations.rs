@@ -0,0 +1,891 @@
+use log::info;
+use rusqlite::{params, Connection};
+
+/// One step of the agents database schema. Steps are applied in order inside
+/// a transaction and must be safe to re-run against a database that already
+/// has some of their changes, as databases from before versioning do.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub apply: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+/// Every migration, oldest first. Append new ones with the next version.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        apply: initial_schema,
+    },
+    Migration {
+        version: 2,
+        name: "schedules",
+        apply: schedules,
+    },
+    Migration {
+        version: 3,
+        name: "run_provenance",
+        apply: run_provenance,
+    },
+    Migration {
+        version: 4,
+        name: "run_limits",
+        apply: run_limits,
+    },
+    Migration {
+        version: 5,
+        name: "pipelines",
+        apply: pipelines,
+    },
+    Migration {
+        version: 6,
+        name: "agent_versions",
+        apply: agent_versions,
+    },
+    Migration {
+        version: 7,
+        name: "run_usage",
+        apply: run_usage,
+    },
+    Migration {
+        version: 8,
+        name: "run_notes_and_continuations",
+        apply: run_notes_and_continuations,
+    },
+    Migration {
+        version: 9,
+        name: "agent_ordering",
+        apply: agent_ordering,
+    },
+    Migration {
+        version: 10,
+        name: "run_indexes",
+        apply: run_indexes,
+    },
+    Migration {
+        version: 11,
+        name: "agent_sources_and_tools",
+        apply: agent_sources_and_tools,
+    },
+    Migration {
+        version: 12,
+        name: "run_artifacts",
+        apply: run_artifacts,
+    },
+    Migration {
+        version: 13,
+        name: "agent_icons",
+        apply: agent_icons,
+    },
+    Migration {
+        version: 14,
+        name: "run_resolved_prompts",
+        apply: run_resolved_prompts,
+    },
+    Migration {
+        version: 15,
+        name: "run_busy_projects",
+        apply: run_busy_projects,
+    },
+    Migration {
+        version: 16,
+        name: "recent_project_paths",
+        apply: recent_project_paths,
+    },
+    Migration {
+        version: 17,
+        name: "agent_run_batches",
+        apply: agent_run_batches,
+    },
+    Migration {
+        version: 18,
+        name: "github_cache",
+        apply: github_cache,
+    },
+    Migration {
+        version: 19,
+        name: "session_titles",
+        apply: session_titles,
+    },
+    Migration {
+        version: 20,
+        name: "archived_projects",
+        apply: archived_projects,
+    },
+    Migration {
+        version: 21,
+        name: "session_previews",
+        apply: session_previews,
+    },
+    Migration {
+        version: 22,
+        name: "pinned_sessions",
+        apply: pinned_sessions,
+    },
+    Migration {
+        version: 23,
+        name: "claude_md_templates",
+        apply: claude_md_templates,
+    },
+    Migration {
+        version: 24,
+        name: "settings_history",
+        apply: settings_history,
+    },
+    Migration {
+        version: 25,
+        name: "project_system_prompts",
+        apply: project_system_prompts,
+    },
+    Migration {
+        version: 26,
+        name: "prompt_history",
+        apply: prompt_history,
+    },
+    Migration {
+        version: 27,
+        name: "redaction_patterns",
+        apply: redaction_patterns,
+    },
+    Migration {
+        version: 28,
+        name: "project_dirs",
+        apply: project_dirs,
+    },
+    Migration {
+        version: 29,
+        name: "project_settings",
+        apply: project_settings,
+    },
+    Migration {
+        version: 30,
+        name: "run_claude_binaries",
+        apply: run_claude_binaries,
+    },
+    Migration {
+        version: 31,
+        name: "claude_version_cache",
+        apply: claude_version_cache,
+    },
+    Migration {
+        version: 32,
+        name: "disabled_mcp_servers",
+        apply: disabled_mcp_servers,
+    },
+    Migration {
+        version: 33,
+        name: "mcp_server_status",
+        apply: mcp_server_status,
+    },
+    Migration {
+        version: 34,
+        name: "mcp_status_error_log",
+        apply: mcp_status_error_log,
+    },
+    Migration {
+        version: 35,
+        name: "template_variables",
+        apply: template_variables,
+    },
+];
+
+/// Add a column unless the table already has it
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> rusqlite::Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn initial_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agents (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            icon TEXT NOT NULL,
+            system_prompt TEXT NOT NULL,
+            default_task TEXT,
+            model TEXT NOT NULL DEFAULT 'sonnet',
+            enable_file_read BOOLEAN NOT NULL DEFAULT 1,
+            enable_file_write BOOLEAN NOT NULL DEFAULT 1,
+            enable_network BOOLEAN NOT NULL DEFAULT 0,
+            hooks TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+
+    // Columns missing from databases created by early releases
+    add_column(conn, "agents", "default_task", "TEXT")?;
+    add_column(conn, "agents", "model", "TEXT DEFAULT 'sonnet'")?;
+    add_column(conn, "agents", "hooks", "TEXT")?;
+    add_column(conn, "agents", "enable_file_read", "BOOLEAN DEFAULT 1")?;
+    add_column(conn, "agents", "enable_file_write", "BOOLEAN DEFAULT 1")?;
+    add_column(conn, "agents", "enable_network", "BOOLEAN DEFAULT 0")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            agent_name TEXT NOT NULL,
+            agent_icon TEXT NOT NULL,
+            task TEXT NOT NULL,
+            model TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            pid INTEGER,
+            process_started_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            completed_at TEXT,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    add_column(conn, "agent_runs", "session_id", "TEXT")?;
+    add_column(conn, "agent_runs", "status", "TEXT DEFAULT 'pending'")?;
+    add_column(conn, "agent_runs", "pid", "INTEGER")?;
+    add_column(conn, "agent_runs", "process_started_at", "TEXT")?;
+
+    // Runs from before session and status tracking
+    conn.execute(
+        "UPDATE agent_runs SET session_id = '' WHERE session_id IS NULL",
+        [],
+    )?;
+    conn.execute(
+        "UPDATE agent_runs SET status = 'completed' WHERE status IS NULL AND completed_at IS NOT NULL",
+        [],
+    )?;
+    conn.execute(
+        "UPDATE agent_runs SET status = 'pending' WHERE status IS NULL",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS update_agent_timestamp
+         AFTER UPDATE ON agents
+         FOR EACH ROW
+         BEGIN
+             UPDATE agents SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+         END",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS update_app_settings_timestamp
+         AFTER UPDATE ON app_settings
+         FOR EACH ROW
+         BEGIN
+             UPDATE app_settings SET updated_at = CURRENT_TIMESTAMP WHERE key = NEW.key;
+         END",
+        [],
+    )?;
+    Ok(())
+}
+
+fn schedules(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schedules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            cron_expr TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            task TEXT NOT NULL,
+            catch_up BOOLEAN NOT NULL DEFAULT 0,
+            enabled BOOLEAN NOT NULL DEFAULT 1,
+            last_run_at TEXT,
+            next_run_at TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn run_provenance(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agent_runs", "triggered_by", "TEXT DEFAULT 'manual'")?;
+    add_column(conn, "agent_runs", "retried_from_run_id", "INTEGER")?;
+    add_column(conn, "agent_runs", "metadata", "TEXT")
+}
+
+fn run_limits(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agents", "environment_variables", "TEXT")?;
+    add_column(conn, "agents", "timeout_minutes", "INTEGER")?;
+    add_column(conn, "agents", "max_cost_usd", "REAL")?;
+    add_column(conn, "agents", "max_tokens", "INTEGER")?;
+    add_column(conn, "agent_runs", "timeout_minutes", "INTEGER")?;
+    add_column(conn, "agent_runs", "max_cost_usd", "REAL")?;
+    add_column(conn, "agent_runs", "max_tokens", "INTEGER")
+}
+
+fn pipelines(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pipelines (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pipeline_steps (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pipeline_id INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            agent_id INTEGER NOT NULL,
+            task_template TEXT NOT NULL,
+            FOREIGN KEY (pipeline_id) REFERENCES pipelines(id) ON DELETE CASCADE,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pipeline_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            pipeline_id INTEGER NOT NULL,
+            project_path TEXT NOT NULL,
+            initial_task TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'running',
+            current_step INTEGER NOT NULL DEFAULT 0,
+            error TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            completed_at TEXT,
+            FOREIGN KEY (pipeline_id) REFERENCES pipelines(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    add_column(conn, "pipeline_runs", "model", "TEXT")?;
+    add_column(conn, "agent_runs", "pipeline_run_id", "INTEGER")
+}
+
+fn agent_versions(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_versions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            system_prompt TEXT NOT NULL,
+            default_task TEXT,
+            model TEXT NOT NULL,
+            reason TEXT NOT NULL DEFAULT 'update',
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_versions_agent_id ON agent_versions(agent_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn run_usage(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agent_runs", "input_tokens", "INTEGER")?;
+    add_column(conn, "agent_runs", "output_tokens", "INTEGER")?;
+    add_column(conn, "agent_runs", "cost_usd", "REAL")?;
+    add_column(conn, "agent_runs", "cost_estimated", "BOOLEAN NOT NULL DEFAULT 0")
+}
+
+fn run_notes_and_continuations(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agent_runs", "notes", "TEXT")?;
+    add_column(conn, "agent_runs", "starred", "BOOLEAN NOT NULL DEFAULT 0")?;
+    add_column(conn, "agent_runs", "parent_run_id", "INTEGER")
+}
+
+fn agent_ordering(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agents", "favorite", "BOOLEAN NOT NULL DEFAULT 0")?;
+    add_column(conn, "agents", "sort_order", "INTEGER")?;
+
+    // Agents from before manual ordering keep their creation order
+    conn.execute(
+        "UPDATE agents SET sort_order = id WHERE sort_order IS NULL",
+        [],
+    )?;
+
+    // Append new agents to the end of the manual ordering
+    conn.execute(
+        "CREATE TRIGGER IF NOT EXISTS append_agent_sort_order
+         AFTER INSERT ON agents
+         FOR EACH ROW WHEN NEW.sort_order IS NULL
+         BEGIN
+             UPDATE agents SET sort_order = (
+                 SELECT COALESCE(MAX(sort_order), 0) + 1 FROM agents WHERE id != NEW.id
+             ) WHERE id = NEW.id;
+         END",
+        [],
+    )?;
+    Ok(())
+}
+
+fn run_indexes(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_runs_agent_id_created_at ON agent_runs(agent_id, created_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_runs_created_at ON agent_runs(created_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn agent_sources_and_tools(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agents", "source_url", "TEXT")?;
+    add_column(conn, "agents", "allowed_tools", "TEXT")?;
+    add_column(conn, "agents", "disallowed_tools", "TEXT")?;
+    add_column(conn, "agent_runs", "allowed_tools", "TEXT")?;
+    add_column(conn, "agent_runs", "disallowed_tools", "TEXT")
+}
+
+fn run_artifacts(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agent_runs", "artifacts", "TEXT")?;
+    add_column(conn, "agent_runs", "changes_count", "INTEGER")
+}
+
+fn agent_icons(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_icons (
+            agent_id INTEGER PRIMARY KEY,
+            mime TEXT NOT NULL,
+            data BLOB NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn run_resolved_prompts(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agent_runs", "resolved_system_prompt", "TEXT")
+}
+
+fn run_busy_projects(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agent_runs", "allow_busy_project", "BOOLEAN NOT NULL DEFAULT 0")
+}
+
+fn recent_project_paths(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recent_project_paths (
+            agent_id INTEGER NOT NULL,
+            project_path TEXT NOT NULL,
+            use_count INTEGER NOT NULL DEFAULT 1,
+            last_used_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (agent_id, project_path),
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_recent_project_paths_last_used_at ON recent_project_paths(last_used_at)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn agent_run_batches(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS agent_run_batches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            task TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'running',
+            start_errors TEXT,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            completed_at TEXT,
+            FOREIGN KEY (agent_id) REFERENCES agents(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    add_column(conn, "agent_runs", "batch_id", "INTEGER")?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_agent_runs_batch_id ON agent_runs(batch_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn github_cache(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS github_cache (
+            cache_key TEXT PRIMARY KEY,
+            body TEXT NOT NULL,
+            etag TEXT,
+            last_modified TEXT,
+            fetched_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn session_titles(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_titles (
+            project_id TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            title TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (project_id, session_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn archived_projects(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS archived_projects (
+            project_id TEXT PRIMARY KEY,
+            archived_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn session_previews(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS session_previews (
+            path TEXT PRIMARY KEY,
+            mtime INTEGER NOT NULL,
+            first_message TEXT,
+            message_timestamp TEXT
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn pinned_sessions(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS pinned_sessions (
+            project_id TEXT NOT NULL,
+            session_id TEXT NOT NULL,
+            pinned_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (project_id, session_id)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn claude_md_templates(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS claude_md_templates (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL DEFAULT '',
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn settings_history(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            settings_path TEXT NOT NULL,
+            source TEXT NOT NULL,
+            content BLOB NOT NULL,
+            content_hash TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn project_system_prompts(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_system_prompts (
+            project_path TEXT PRIMARY KEY,
+            prompt TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn prompt_history(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS prompt_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            prompt TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            project_id TEXT NOT NULL,
+            session_id TEXT,
+            agent_task BOOLEAN NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_prompt_history_project ON prompt_history(project_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn redaction_patterns(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS redaction_patterns (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            pattern TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn project_dirs(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_dirs (
+            project_id TEXT PRIMARY KEY,
+            project_path TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn project_settings(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS project_settings (
+            project_path TEXT PRIMARY KEY,
+            settings TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn run_claude_binaries(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agent_runs", "claude_binary", "TEXT")?;
+    add_column(conn, "agent_runs", "claude_version", "TEXT")
+}
+
+fn claude_version_cache(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS claude_version_cache (
+            path TEXT PRIMARY KEY,
+            mtime INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            stdout TEXT NOT NULL,
+            stderr TEXT NOT NULL,
+            success BOOLEAN NOT NULL,
+            checked_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn disabled_mcp_servers(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS disabled_mcp_servers (
+            name TEXT NOT NULL,
+            scope TEXT NOT NULL,
+            server TEXT NOT NULL,
+            disabled_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (name, scope)
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn mcp_server_status(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS mcp_server_status (
+            name TEXT PRIMARY KEY,
+            running BOOLEAN NOT NULL,
+            latency_ms INTEGER,
+            last_checked INTEGER,
+            last_error TEXT
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn mcp_status_error_log(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "mcp_server_status", "error_log_since", "TEXT")
+}
+
+fn template_variables(conn: &Connection) -> rusqlite::Result<()> {
+    add_column(conn, "agent_runs", "template_variables", "TEXT")?;
+    add_column(conn, "schedules", "variables", "TEXT")?;
+    add_column(conn, "pipeline_steps", "variables", "TEXT")
+}
+
+/// Apply the given migrations above the database's current version,
+/// returning the version it ends at
+fn apply_migrations(conn: &mut Connection, migrations: &[Migration]) -> Result<i64, String> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )
+    .map_err(|e| format!("Failed to create schema_version table: {}", e))?;
+
+    let current: i64 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    let mut version = current;
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        let failed = |e: rusqlite::Error| {
+            format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.name, e
+            )
+        };
+        let tx = conn.transaction().map_err(failed)?;
+        (migration.apply)(&tx).map_err(failed)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name) VALUES (?1, ?2)",
+            params![migration.version, migration.name],
+        )
+        .map_err(failed)?;
+        tx.commit().map_err(failed)?;
+
+        info!(
+            "Applied agents database migration {} ({})",
+            migration.version, migration.name
+        );
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+/// Bring the agents database up to the latest schema
+pub fn run_migrations(conn: &mut Connection) -> Result<i64, String> {
+    let version = apply_migrations(conn, MIGRATIONS)?;
+    info!("Agents database schema is at version {}", version);
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_one_database_migrates_to_head_keeping_data() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(apply_migrations(&mut conn, &MIGRATIONS[..1]).unwrap(), 1);
+        conn.execute_batch(
+            "INSERT INTO agents (name, icon, system_prompt) VALUES ('reviewer', 'bot', 'Review code');
+             INSERT INTO agent_runs (agent_id, agent_name, agent_icon, task, model, project_path, session_id, status)
+                 VALUES (1, 'reviewer', 'bot', 'Review', 'sonnet', '/tmp/p', 'abc', 'completed');
+             INSERT INTO app_settings (key, value) VALUES ('max_concurrent_runs', '2');",
+        )
+        .unwrap();
+
+        let head = MIGRATIONS.last().unwrap().version;
+        assert_eq!(run_migrations(&mut conn).unwrap(), head);
+        // Running again finds nothing left to apply
+        assert_eq!(run_migrations(&mut conn).unwrap(), head);
+
+        let (name, favorite, sort_order): (String, bool, i64) = conn
+            .query_row(
+                "SELECT name, favorite, sort_order FROM agents WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((name.as_str(), favorite, sort_order), ("reviewer", false, 1));
+
+        let (session_id, triggered_by, starred): (String, String, bool) = conn
+            .query_row(
+                "SELECT session_id, triggered_by, starred FROM agent_runs WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((session_id.as_str(), triggered_by.as_str(), starred), ("abc", "manual", false));
+
+        let setting: String = conn
+            .query_row(
+                "SELECT value FROM app_settings WHERE key = 'max_concurrent_runs'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(setting, "2");
+    }
+
+    #[test]
+    fn test_failed_migration_names_itself_and_rolls_back() {
+        fn broken(conn: &Connection) -> rusqlite::Result<()> {
+            conn.execute("CREATE TABLE half_done (id INTEGER)", [])?;
+            conn.execute("SELECT * FROM missing_table", [])?;
+            Ok(())
+        }
+        let migrations = [
+            Migration {
+                version: 1,
+                name: "initial_schema",
+                apply: initial_schema,
+            },
+            Migration {
+                version: 2,
+                name: "broken_step",
+                apply: broken,
+            },
+        ];
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        let error = apply_migrations(&mut conn, &migrations).unwrap_err();
+        assert!(error.starts_with("Migration 2 (broken_step) failed"), "{}", error);
+
+        let version: i64 = conn
+            .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, 1);
+        let half_done: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'half_done'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(half_done, 0);
+    }
+}
@@ -0,0 +1,20 @@
+pub mod migrations;
+
+/// An in-memory database with every migration applied, for tests
+#[cfg(test)]
+pub fn test_connection() -> rusqlite::Connection {
+    let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+    migrations::run_migrations(&mut conn).unwrap();
+    conn
+}
+
+/// Add an agent to a test database, returning its id
+#[cfg(test)]
+pub fn seed_agent(conn: &rusqlite::Connection, name: &str) -> i64 {
+    conn.execute(
+        "INSERT INTO agents (name, icon, system_prompt) VALUES (?1, 'bot', 'p')",
+        [name],
+    )
+    .unwrap();
+    conn.last_insert_rowid()
+}
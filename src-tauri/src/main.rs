@@ -4,17 +4,18 @@
 mod checkpoint;
 mod claude_binary;
 mod commands;
+mod db;
 mod process;
 
 use checkpoint::state::CheckpointState;
 use commands::agents::{
-    cleanup_finished_processes, create_agent, delete_agent, execute_agent, export_agent,
-    export_agent_to_file, fetch_github_agent_content, fetch_github_agents, get_agent,
-    get_agent_run, get_agent_run_with_real_time_metrics, get_claude_binary_path,
-    get_live_session_output, get_session_output, get_session_status, import_agent,
-    import_agent_from_file, import_agent_from_github, init_database, kill_agent_session,
-    list_agent_runs, list_agent_runs_with_metrics, list_agents, list_claude_installations,
-    list_running_sessions, load_agent_session_history, set_claude_binary_path, stream_session_output, update_agent, AgentDb,
+    cancel_queued_run, cleanup_finished_processes, continue_agent_run, create_agent, delete_agent, duplicate_agent, execute_agent, export_agent,
+    export_agent_to_file, export_all_agents, fetch_github_agent_content, fetch_github_agents, get_agent,
+    get_agent_kill_grace_seconds, get_agent_stall_seconds, get_agent_run, get_agent_run_with_real_time_metrics, get_claude_binary_path,
+    get_agent_version_limit, get_live_session_output, get_max_concurrent_runs, get_session_output, get_session_status, import_agent,
+    import_agent_from_file, import_agent_from_github, import_agent_from_url, set_github_token, has_github_token, import_agents_from_directory, init_database, kill_agent_session,
+    list_agent_runs, list_agent_runs_with_metrics, list_agent_versions, list_agents, list_claude_installations, list_queued_runs,
+    list_running_sessions, load_agent_session_history, preview_agent_execution, reorder_agents, restore_agent_version, retry_agent_run, set_agent_favorite, set_agent_kill_grace_seconds, set_agent_stall_seconds, set_agent_run_note, set_agent_run_starred, set_agent_version_limit, set_claude_binary_path, set_max_concurrent_runs, start_timeout_reaper, stream_session_output, update_agent, AgentDb,
 };
 use commands::claude::{
     cancel_claude_execution, check_auto_checkpoint, check_claude_version, cleanup_old_checkpoints,
@@ -22,11 +23,13 @@ use commands::claude::{
     find_claude_md_files, fork_from_checkpoint, get_checkpoint_diff, get_checkpoint_settings,
     get_checkpoint_state_stats, get_claude_session_output, get_claude_settings, get_home_directory, get_project_sessions,
     get_recently_modified_files, get_session_timeline, get_system_prompt, list_checkpoints,
-    list_directory_contents, list_projects, list_running_claude_sessions, load_session_history,
-    open_new_session, read_claude_md_file, restore_checkpoint, resume_claude_code,
-    save_claude_md_file, save_claude_settings, save_system_prompt, search_files,
+    list_directory_contents, list_projects, list_running_claude_sessions, load_session_history, get_session_message_count, search_sessions, delete_session,
+    open_new_session, preview_claude_command, read_claude_md_file, restore_checkpoint, resume_claude_code,
+    resume_session_truncated, fork_and_resume_session, compact_session,
+    save_claude_md_file, save_claude_settings, restore_claude_settings_backup, save_system_prompt, search_files,
     track_checkpoint_message, track_session_messages, update_checkpoint_settings,
-    get_hooks_config, update_hooks_config, validate_hook_command,
+    get_hooks_config, update_hooks_config, validate_hook_command, validate_hooks_config,
+    queue_prompt, list_queued_prompts, remove_queued_prompt,
     ClaudeProcessState,
 };
 use commands::mcp::{
@@ -43,6 +46,75 @@ use commands::storage::{
     storage_insert_row, storage_execute_sql, storage_reset_database,
 };
 use commands::proxy::{get_proxy_settings, save_proxy_settings, apply_proxy_settings};
+use commands::schedules::{
+    create_agent_schedule, delete_agent_schedule, list_agent_schedules, start_scheduler,
+};
+use commands::transcripts::{copy_session_to_clipboard, export_agent_run, export_session};
+use commands::settings_hierarchy::{get_effective_claude_settings, save_claude_settings_scoped};
+use commands::redaction::{
+    delete_redaction_pattern, list_redaction_patterns, save_redaction_pattern,
+};
+use commands::run_search::search_agent_runs;
+use commands::run_compare::compare_agent_runs;
+use commands::agent_metrics::get_agent_metrics;
+use commands::run_artifacts::get_agent_run_artifacts;
+use commands::agent_icons::{get_agent_icon_image, set_agent_icon_image};
+use commands::project_paths::{list_recent_project_paths, validate_project_path};
+use commands::batches::{cancel_agent_batch, execute_agent_batch, get_batch_status};
+use commands::reconcile::{reconcile_agent_runs, start_run_reconciliation};
+use commands::session_titles::{get_session_titles, set_session_title};
+use commands::project_archive::set_project_archived;
+use commands::session_pins::{list_pinned_sessions, set_session_pinned};
+use commands::claude_md_templates::{
+    create_claude_md, list_claude_md_templates, save_claude_md_template,
+};
+use commands::hook_dry_run::test_hook_command;
+use commands::settings_history::{list_settings_backups, restore_settings_backup};
+use commands::project_prompts::{get_project_system_prompt, save_project_system_prompt};
+use commands::project_settings::{
+    get_project_settings, set_project_claude_binary, set_project_settings,
+};
+use commands::projects_watcher::{
+    get_fs_watcher_enabled, set_fs_watcher_enabled, start_projects_watcher, ProjectsWatcherState,
+};
+use commands::session_autotitle::{
+    generate_session_title, get_auto_title_sessions, set_auto_title_sessions,
+};
+use commands::claude_env::{get_claude_env_settings, set_claude_env_settings};
+use commands::session_merge::merge_sessions;
+use commands::token_estimates::{estimate_session_tokens, estimate_text_tokens};
+use commands::claude_install::{install_claude_cli, update_claude_cli};
+use commands::binary_health::check_claude_binary_health;
+use commands::extra_path::{get_extra_path_entries, set_extra_path_entries};
+use commands::mcp_export::mcp_export;
+use commands::mcp_import::mcp_import_from_editor;
+use commands::mcp_logs::{mcp_clear_server_logs, mcp_get_server_logs};
+use commands::mcp_toggle::mcp_set_enabled;
+use commands::mcp_monitor::{
+    get_mcp_status_interval, mcp_refresh_status, mcp_test_all, set_mcp_status_interval,
+    start_mcp_monitor,
+};
+use commands::binary_fallback::{get_persist_binary_fallback, set_persist_binary_fallback};
+use commands::binary_pin::{
+    clear_claude_binary_pin, get_claude_binary_pin, pin_claude_binary_checksum,
+};
+use commands::claude_sessions::{get_max_claude_sessions, set_max_claude_sessions};
+use commands::live_usage::{get_live_session_usage, LiveUsageState};
+use commands::prompt_history::{
+    clear_prompt_history, delete_prompt_history, get_prompt_history_settings,
+    list_prompt_history, set_prompt_history_settings,
+};
+use commands::github_cache::{
+    clear_github_cache, get_github_cache_ttl_seconds, set_github_cache_ttl_seconds,
+};
+use commands::retention::{
+    delete_agent_runs, delete_runs_older_than, get_retention_settings, run_retention_cleanup,
+    set_retention_settings, start_retention_cleanup,
+};
+use commands::pricing::{get_model_pricing, reset_model_pricing, set_model_pricing};
+use commands::pipelines::{
+    create_pipeline, delete_pipeline, execute_pipeline, get_pipeline_run, list_pipelines,
+};
 use process::ProcessRegistryState;
 use std::sync::Mutex;
 use tauri::Manager;
@@ -59,9 +131,11 @@ fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .setup(|app| {
             // Initialize agents database
-            let conn = init_database(&app.handle()).expect("Failed to initialize agents database");
+            let conn = init_database(&app.handle())
+                .map_err(|e| format!("Failed to initialize agents database: {}", e))?;
             
             // Load and apply proxy settings from the database
             {
@@ -110,7 +184,8 @@ fn main() {
             }
             
             // Re-open the connection for the app to manage
-            let conn = init_database(&app.handle()).expect("Failed to initialize agents database");
+            let conn = init_database(&app.handle())
+                .map_err(|e| format!("Failed to initialize agents database: {}", e))?;
             app.manage(AgentDb(Mutex::new(conn)));
 
             // Initialize checkpoint state
@@ -134,11 +209,40 @@ fn main() {
 
             app.manage(checkpoint_state);
 
-            // Initialize process registry
-            app.manage(ProcessRegistryState::default());
+            // Initialize process registry, spilling agent run output under the app data dir
+            let registry = ProcessRegistryState::default();
+            if let Ok(app_dir) = app.path().app_data_dir() {
+                let _ = registry.0.set_output_dir(app_dir.join("run_outputs"));
+            }
+            app.manage(registry);
+
+            // Keep the stderr of MCP servers opcode starts under the app data dir
+            if let Ok(app_dir) = app.path().app_data_dir() {
+                commands::mcp_logs::set_log_dir(app_dir.join("mcp_logs"));
+            }
 
             // Initialize Claude process state
             app.manage(ClaudeProcessState::default());
+            app.manage(LiveUsageState::default());
+
+            // Recover or clean up runs left behind by a crash or force-quit
+            start_run_reconciliation(app.handle().clone());
+
+            // Start the background scheduler for cron-style agent runs
+            start_scheduler(app.handle().clone());
+
+            // Kill agent runs that exceed their hard timeout
+            start_timeout_reaper(app.handle().clone());
+
+            // Delete run history outside the retention settings
+            start_retention_cleanup(app.handle().clone());
+
+            // Notice sessions started with the Claude CLI outside opcode
+            app.manage(ProjectsWatcherState::default());
+            start_projects_watcher(app.handle());
+
+            // Check MCP servers in the background so their status is served from cache
+            start_mcp_monitor(app.handle().clone());
 
             // Apply window vibrancy with rounded corners on macOS
             #[cfg(target_os = "macos")]
@@ -183,28 +287,76 @@ fn main() {
             check_claude_version,
             save_system_prompt,
             save_claude_settings,
+            restore_claude_settings_backup,
+            get_effective_claude_settings,
+            save_claude_settings_scoped,
+            list_settings_backups,
+            restore_settings_backup,
+            get_project_system_prompt,
+            save_project_system_prompt,
+            get_project_settings,
+            set_project_settings,
+            set_project_claude_binary,
+            generate_session_title,
+            get_auto_title_sessions,
+            set_auto_title_sessions,
+            get_fs_watcher_enabled,
+            set_fs_watcher_enabled,
+            get_claude_env_settings,
+            set_claude_env_settings,
             find_claude_md_files,
             read_claude_md_file,
             save_claude_md_file,
             load_session_history,
+            get_session_message_count,
+            search_sessions,
+            delete_session,
+            set_session_title,
+            get_session_titles,
+            set_project_archived,
+            set_session_pinned,
+            list_pinned_sessions,
+            list_claude_md_templates,
+            save_claude_md_template,
+            create_claude_md,
             execute_claude_code,
             continue_claude_code,
             resume_claude_code,
+            preview_claude_command,
             cancel_claude_execution,
+            queue_prompt,
+            list_queued_prompts,
+            remove_queued_prompt,
             list_running_claude_sessions,
+            get_max_claude_sessions,
+            set_max_claude_sessions,
+            get_live_session_usage,
+            list_prompt_history,
+            delete_prompt_history,
+            clear_prompt_history,
+            get_prompt_history_settings,
+            set_prompt_history_settings,
             get_claude_session_output,
             list_directory_contents,
             search_files,
             get_recently_modified_files,
             get_hooks_config,
             update_hooks_config,
+            validate_hooks_config,
             validate_hook_command,
+            test_hook_command,
             
             // Checkpoint Management
             create_checkpoint,
             restore_checkpoint,
             list_checkpoints,
             fork_from_checkpoint,
+            resume_session_truncated,
+            fork_and_resume_session,
+            compact_session,
+            merge_sessions,
+            estimate_session_tokens,
+            estimate_text_tokens,
             get_session_timeline,
             update_checkpoint_settings,
             get_checkpoint_diff,
@@ -218,33 +370,107 @@ fn main() {
             
             // Agent Management
             list_agents,
+            set_agent_favorite,
+            reorder_agents,
             create_agent,
             update_agent,
             delete_agent,
+            duplicate_agent,
+            list_agent_versions,
+            restore_agent_version,
+            get_agent_version_limit,
+            set_agent_version_limit,
             get_agent,
             execute_agent,
+            retry_agent_run,
+            continue_agent_run,
             list_agent_runs,
             get_agent_run,
             list_agent_runs_with_metrics,
+            set_agent_run_note,
+            set_agent_run_starred,
             get_agent_run_with_real_time_metrics,
+            preview_agent_execution,
             list_running_sessions,
             kill_agent_session,
             get_session_status,
             cleanup_finished_processes,
+            list_queued_runs,
+            cancel_queued_run,
+            get_max_concurrent_runs,
+            set_max_concurrent_runs,
+            get_agent_kill_grace_seconds,
+            set_agent_kill_grace_seconds,
+            get_agent_stall_seconds,
+            set_agent_stall_seconds,
             get_session_output,
             get_live_session_output,
             stream_session_output,
             load_agent_session_history,
+            export_agent_run,
+            export_session,
+            copy_session_to_clipboard,
+            list_redaction_patterns,
+            save_redaction_pattern,
+            delete_redaction_pattern,
+            search_agent_runs,
+            compare_agent_runs,
+            get_agent_metrics,
+            get_agent_run_artifacts,
+            set_agent_icon_image,
+            get_agent_icon_image,
+            list_recent_project_paths,
+            validate_project_path,
+            execute_agent_batch,
+            get_batch_status,
+            cancel_agent_batch,
+            clear_github_cache,
+            reconcile_agent_runs,
+            get_github_cache_ttl_seconds,
+            set_github_cache_ttl_seconds,
+            get_retention_settings,
+            set_retention_settings,
+            delete_agent_runs,
+            delete_runs_older_than,
+            run_retention_cleanup,
+            get_model_pricing,
+            set_model_pricing,
+            reset_model_pricing,
             get_claude_binary_path,
             set_claude_binary_path,
             list_claude_installations,
+            install_claude_cli,
+            update_claude_cli,
+            check_claude_binary_health,
+            get_extra_path_entries,
+            set_extra_path_entries,
+            get_claude_binary_pin,
+            pin_claude_binary_checksum,
+            clear_claude_binary_pin,
+            get_persist_binary_fallback,
+            set_persist_binary_fallback,
             export_agent,
             export_agent_to_file,
+            export_all_agents,
             import_agent,
             import_agent_from_file,
+            import_agents_from_directory,
             fetch_github_agents,
             fetch_github_agent_content,
             import_agent_from_github,
+            import_agent_from_url,
+            set_github_token,
+            has_github_token,
+            create_agent_schedule,
+            list_agent_schedules,
+            delete_agent_schedule,
+            
+            // Agent Pipelines
+            create_pipeline,
+            list_pipelines,
+            delete_pipeline,
+            execute_pipeline,
+            get_pipeline_run,
             
             // Usage & Analytics
             get_usage_stats,
@@ -259,12 +485,21 @@ fn main() {
             mcp_remove,
             mcp_add_json,
             mcp_add_from_claude_desktop,
+            mcp_import_from_editor,
             mcp_serve,
             mcp_test_connection,
             mcp_reset_project_choices,
             mcp_get_server_status,
             mcp_read_project_config,
             mcp_save_project_config,
+            mcp_export,
+            mcp_set_enabled,
+            mcp_refresh_status,
+            get_mcp_status_interval,
+            set_mcp_status_interval,
+            mcp_test_all,
+            mcp_get_server_logs,
+            mcp_clear_server_logs,
             
             // Storage Management
             storage_list_tables,
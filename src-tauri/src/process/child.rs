@@ -0,0 +1,214 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::process::ExitStatus;
+
+use tauri::AppHandle;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+use tokio::process::{Child, Command};
+use tokio::sync::watch;
+
+use crate::claude_binary::{is_bundled_binary, BUNDLED_SIDECAR};
+
+/// How much sidecar output is buffered before its forwarding waits on readers
+const SIDECAR_PIPE_BYTES: usize = 64 * 1024;
+
+/// How a Claude process exited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChildExit {
+    /// None when it was ended by a signal
+    pub code: Option<i32>,
+}
+
+impl ChildExit {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl From<ExitStatus> for ChildExit {
+    fn from(status: ExitStatus) -> Self {
+        ChildExit {
+            code: status.code(),
+        }
+    }
+}
+
+impl fmt::Display for ChildExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "exit code: {}", code),
+            None => write!(f, "terminated by signal"),
+        }
+    }
+}
+
+/// A Claude process spawned either directly or, for the bundled binary, as a
+/// sidecar through the shell plugin
+pub enum ClaudeChild {
+    System(Child),
+    Sidecar(SidecarChild),
+}
+
+/// A sidecar process. Its exit is reported through the shell plugin's events.
+pub struct SidecarChild {
+    /// Taken when it's killed, since killing consumes it
+    child: Option<CommandChild>,
+    pid: u32,
+    exit: watch::Receiver<Option<ChildExit>>,
+}
+
+impl From<Child> for ClaudeChild {
+    fn from(child: Child) -> Self {
+        ClaudeChild::System(child)
+    }
+}
+
+impl ClaudeChild {
+    /// The process ID, None once a directly spawned child has been waited on
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            ClaudeChild::System(child) => child.id(),
+            ClaudeChild::Sidecar(sidecar) => Some(sidecar.pid),
+        }
+    }
+
+    /// How the process exited, or None while it's still running
+    pub fn try_wait(&mut self) -> io::Result<Option<ChildExit>> {
+        match self {
+            ClaudeChild::System(child) => Ok(child.try_wait()?.map(ChildExit::from)),
+            ClaudeChild::Sidecar(sidecar) => Ok(*sidecar.exit.borrow()),
+        }
+    }
+
+    /// Wait for the process to exit
+    pub async fn wait(&mut self) -> io::Result<ChildExit> {
+        match self {
+            ClaudeChild::System(child) => Ok(child.wait().await?.into()),
+            ClaudeChild::Sidecar(sidecar) => {
+                let exit = *sidecar
+                    .exit
+                    .wait_for(Option::is_some)
+                    .await
+                    .map_err(io::Error::other)?;
+                Ok(exit.unwrap_or(ChildExit { code: None }))
+            }
+        }
+    }
+
+    /// Kill the process without waiting for it to exit
+    pub fn start_kill(&mut self) -> io::Result<()> {
+        match self {
+            ClaudeChild::System(child) => child.start_kill(),
+            ClaudeChild::Sidecar(sidecar) => match sidecar.child.take() {
+                Some(child) => child.kill().map_err(|e| io::Error::other(e.to_string())),
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Kill the process and wait for it to exit
+    pub async fn kill(&mut self) -> io::Result<()> {
+        self.start_kill()?;
+        self.wait().await.map(|_| ())
+    }
+}
+
+/// A spawned Claude process with its output
+pub struct SpawnedClaude {
+    pub child: ClaudeChild,
+    pub stdout: Box<dyn AsyncRead + Send + Unpin>,
+    pub stderr: Box<dyn AsyncRead + Send + Unpin>,
+}
+
+/// Spawn a command built for a Claude binary, with piped stdout and stderr.
+/// The bundled binary is run as a sidecar with the command's arguments,
+/// directory and environment; any other binary is spawned directly.
+pub fn spawn_claude(app: &AppHandle, mut cmd: Command) -> io::Result<SpawnedClaude> {
+    if is_bundled_binary(Path::new(cmd.as_std().get_program())) {
+        return spawn_sidecar(app, &cmd);
+    }
+
+    let mut child = cmd.spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| io::Error::other("Failed to get stdout"))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| io::Error::other("Failed to get stderr"))?;
+    Ok(SpawnedClaude {
+        child: child.into(),
+        stdout: Box::new(stdout),
+        stderr: Box::new(stderr),
+    })
+}
+
+/// Spawn the bundled binary through the shell plugin, forwarding its output
+/// events into pipes so it's read like a directly spawned process's
+fn spawn_sidecar(app: &AppHandle, cmd: &Command) -> io::Result<SpawnedClaude> {
+    let std_cmd = cmd.as_std();
+    let mut sidecar = app
+        .shell()
+        .sidecar(BUNDLED_SIDECAR)
+        .map_err(|e| io::Error::other(e.to_string()))?
+        .args(std_cmd.get_args())
+        .set_raw_out(true)
+        // Like a directly spawned Claude, so stopping it stops its MCP servers
+        .set_process_group(true);
+    if let Some(dir) = std_cmd.get_current_dir() {
+        sidecar = sidecar.current_dir(dir);
+    }
+    for (key, value) in std_cmd.get_envs() {
+        if let Some(value) = value {
+            sidecar = sidecar.env(key, value);
+        }
+    }
+
+    let (mut events, child) = sidecar
+        .spawn()
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    let pid = child.pid();
+    let (mut stdout_tx, stdout) = tokio::io::duplex(SIDECAR_PIPE_BYTES);
+    let (mut stderr_tx, stderr) = tokio::io::duplex(SIDECAR_PIPE_BYTES);
+    let (exit_tx, exit) = watch::channel(None);
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = events.recv().await {
+            match event {
+                CommandEvent::Stdout(bytes) => {
+                    let _ = stdout_tx.write_all(&bytes).await;
+                }
+                CommandEvent::Stderr(bytes) => {
+                    let _ = stderr_tx.write_all(&bytes).await;
+                }
+                CommandEvent::Error(e) => log::error!("Claude sidecar {}: {}", pid, e),
+                CommandEvent::Terminated(payload) => {
+                    exit_tx.send_replace(Some(ChildExit { code: payload.code }));
+                }
+                _ => {}
+            }
+        }
+        // The events end with the process, closing its output
+        exit_tx.send_if_modified(|exit| {
+            let unreported = exit.is_none();
+            if unreported {
+                *exit = Some(ChildExit { code: None });
+            }
+            unreported
+        });
+    });
+
+    log::info!("Spawned bundled Claude sidecar with PID {}", pid);
+    Ok(SpawnedClaude {
+        child: ClaudeChild::Sidecar(SidecarChild {
+            child: Some(child),
+            pid,
+            exit,
+        }),
+        stdout: Box::new(stdout),
+        stderr: Box::new(stderr),
+    })
+}
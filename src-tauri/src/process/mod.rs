@@ -1,3 +1,11 @@
+pub mod child;
+pub mod output;
 pub mod registry;
+pub mod resources;
+pub mod throughput;
 
+pub use child::*;
+pub use output::*;
 pub use registry::*;
+pub use resources::*;
+pub use throughput::*;
@@ -0,0 +1,287 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Bytes of live output kept in memory for quick polling
+pub const LIVE_OUTPUT_TAIL_BYTES: usize = 256 * 1024;
+
+/// Largest range returned by one offset read
+pub const MAX_OUTPUT_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// A range of a run's output file
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OutputChunk {
+    pub offset: u64,
+    pub next_offset: u64, // Pass back as `offset` to continue reading
+    pub total_bytes: u64,
+    pub content: String,
+}
+
+/// Live output of a process: the most recent lines in memory, and every line
+/// in an append-only file once `spill_to` has been called
+#[derive(Default)]
+pub struct LiveOutput {
+    buffer: String,
+    file: Option<File>,
+    path: Option<PathBuf>,
+}
+
+impl LiveOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write all output to `path`, replacing any previous file there
+    pub fn spill_to(&mut self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(path)?;
+        file.write_all(self.buffer.as_bytes())?;
+        self.file = Some(file);
+        self.path = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Keep appending to an existing output file, loading its tail into memory
+    pub fn resume_from(&mut self, path: &Path) -> std::io::Result<()> {
+        self.buffer = read_output_tail(path, LIVE_OUTPUT_TAIL_BYTES)?;
+        self.file = Some(OpenOptions::new().append(true).open(path)?);
+        self.path = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    pub fn append_line(&mut self, line: &str) {
+        let line = format!("{}\n", line);
+        if let Some(file) = self.file.as_mut() {
+            if let Err(e) = file.write_all(line.as_bytes()) {
+                warn!("Failed to write live output to {:?}: {}", self.path, e);
+                self.file = None;
+            }
+        }
+
+        // Trim in batches so appends don't move the whole buffer each time
+        self.buffer.push_str(&line);
+        if self.buffer.len() > LIVE_OUTPUT_TAIL_BYTES * 2 {
+            let start = tail_start(&self.buffer, LIVE_OUTPUT_TAIL_BYTES);
+            self.buffer.drain(..start);
+        }
+    }
+
+    /// The last `LIVE_OUTPUT_TAIL_BYTES` of output, starting at a line
+    pub fn tail(&self) -> String {
+        self.buffer[tail_start(&self.buffer, LIVE_OUTPUT_TAIL_BYTES)..].to_string()
+    }
+}
+
+/// Start of the last `max_bytes` of `text`, moved forward to the next line
+/// unless the final line alone is longer than that
+pub(crate) fn tail_start(text: &str, max_bytes: usize) -> usize {
+    if text.len() <= max_bytes {
+        return 0;
+    }
+    let mut start = text.len() - max_bytes;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    match text[start..].find('\n') {
+        Some(newline) if start + newline + 1 < text.len() => start + newline + 1,
+        _ => start,
+    }
+}
+
+/// Where a run's output is spilled under `output_dir`
+pub fn run_output_path(output_dir: &Path, run_id: i64) -> PathBuf {
+    output_dir.join(format!("{}.jsonl", run_id))
+}
+
+/// Read up to `max_bytes` of complete lines starting at `offset`
+pub fn read_output_range(path: &Path, offset: u64, max_bytes: usize) -> std::io::Result<OutputChunk> {
+    let mut file = File::open(path)?;
+    let total_bytes = file.metadata()?.len();
+    let offset = offset.min(total_bytes);
+    let length = (total_bytes - offset).min(max_bytes as u64) as usize;
+
+    let mut bytes = vec![0; length];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut bytes)?;
+
+    // Stop after the last complete line. A single line longer than the limit
+    // is split at a character boundary instead.
+    let end = match bytes.iter().rposition(|b| *b == b'\n') {
+        Some(newline) => newline + 1,
+        None if length == max_bytes => match std::str::from_utf8(&bytes) {
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            _ => length,
+        },
+        // A partial line at the end of the file is still being written
+        None => 0,
+    };
+    bytes.truncate(end);
+
+    Ok(OutputChunk {
+        offset,
+        next_offset: offset + end as u64,
+        total_bytes,
+        content: String::from_utf8_lossy(&bytes).into_owned(),
+    })
+}
+
+/// Read the complete lines within the last `max_bytes` of a file, as a chunk
+/// that `read_output_range` can continue from
+pub fn read_output_tail_range(path: &Path, max_bytes: usize) -> std::io::Result<OutputChunk> {
+    let mut file = File::open(path)?;
+    let total_bytes = file.metadata()?.len();
+    let mut start = total_bytes.saturating_sub(max_bytes as u64);
+
+    // Begin at the first line that starts inside the window
+    if start > 0 {
+        let mut bytes = Vec::new();
+        file.seek(SeekFrom::Start(start))?;
+        file.take(max_bytes as u64).read_to_end(&mut bytes)?;
+        if let Some(newline) = bytes.iter().position(|b| *b == b'\n') {
+            if newline + 1 < bytes.len() {
+                start += newline as u64 + 1;
+            }
+        }
+    }
+    read_output_range(path, start, max_bytes)
+}
+
+/// Pass each chunk `read` returns from `offset` onwards to `emit` until there
+/// is no complete line left, returning the offset to continue from. Stops
+/// early, without advancing past the chunk, when `emit` returns false.
+pub fn drain_output<R, E>(mut read: R, mut offset: u64, mut emit: E) -> Result<u64, String>
+where
+    R: FnMut(u64) -> Result<OutputChunk, String>,
+    E: FnMut(&OutputChunk) -> bool,
+{
+    loop {
+        let chunk = read(offset)?;
+        if chunk.content.is_empty() || !emit(&chunk) {
+            return Ok(offset);
+        }
+        offset = chunk.next_offset;
+    }
+}
+
+/// Read the last `max_bytes` of a file, starting at a line
+pub fn read_output_tail(path: &Path, max_bytes: usize) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let total_bytes = file.metadata()?.len();
+    let start = total_bytes.saturating_sub(max_bytes as u64);
+
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(start))?;
+    file.read_to_end(&mut bytes)?;
+    if start > 0 {
+        if let Some(newline) = bytes.iter().position(|b| *b == b'\n') {
+            if newline + 1 < bytes.len() {
+                bytes.drain(..=newline);
+            }
+        }
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_output_keeps_tail_and_spills_everything() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = run_output_path(dir.path(), 7);
+        let mut output = LiveOutput::new();
+        output.spill_to(&path).unwrap();
+
+        let line = "x".repeat(1000);
+        for _ in 0..(LIVE_OUTPUT_TAIL_BYTES / 1000) * 3 {
+            output.append_line(&line);
+        }
+        let tail = output.tail();
+        assert!(tail.len() <= LIVE_OUTPUT_TAIL_BYTES);
+        assert!(tail.starts_with('x') && tail.ends_with('\n'));
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written.len(), (LIVE_OUTPUT_TAIL_BYTES / 1000) * 3 * 1001);
+        assert_eq!(read_output_tail(&path, LIVE_OUTPUT_TAIL_BYTES).unwrap(), tail);
+    }
+
+    #[test]
+    fn test_range_reads_stop_at_complete_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.jsonl");
+        std::fs::write(&path, "first\nsecond\npartial").unwrap();
+
+        let chunk = read_output_range(&path, 0, 10).unwrap();
+        assert_eq!((chunk.content.as_str(), chunk.next_offset), ("first\n", 6));
+        let chunk = read_output_range(&path, 6, 1024).unwrap();
+        assert_eq!((chunk.content.as_str(), chunk.next_offset), ("second\n", 13));
+        let chunk = read_output_range(&path, 13, 1024).unwrap();
+        assert_eq!((chunk.content.as_str(), chunk.next_offset, chunk.total_bytes), ("", 13, 20));
+
+        // A line longer than the limit is split without breaking a character
+        std::fs::write(&path, "ééé\n").unwrap();
+        let chunk = read_output_range(&path, 0, 3).unwrap();
+        assert_eq!((chunk.content.as_str(), chunk.next_offset), ("é", 2));
+    }
+
+    #[test]
+    fn test_tail_range_starts_at_a_line_and_can_be_continued() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.jsonl");
+        std::fs::write(&path, "first\nsecond\nthird\n").unwrap();
+
+        let chunk = read_output_tail_range(&path, 10).unwrap();
+        assert_eq!((chunk.content.as_str(), chunk.offset, chunk.next_offset), ("third\n", 13, 19));
+        let chunk = read_output_tail_range(&path, 1024).unwrap();
+        assert_eq!((chunk.offset, chunk.next_offset, chunk.total_bytes), (0, 19, 19));
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"fourth\n").unwrap();
+        let chunk = read_output_range(&path, 19, 1024).unwrap();
+        assert_eq!(chunk.content, "fourth\n");
+    }
+
+    #[test]
+    fn test_reconnect_mid_write_resumes_without_gaps_or_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = run_output_path(dir.path(), 3);
+        let mut output = LiveOutput::new();
+        output.spill_to(&path).unwrap();
+        let read = |offset| read_output_range(&path, offset, 16).map_err(|e| e.to_string());
+
+        let mut received = Vec::new();
+        let mut emit = |chunk: &OutputChunk| {
+            received.push(chunk.clone());
+            true
+        };
+        for i in 0..5 {
+            output.append_line(&format!("{{\"line\":{}}}", i));
+        }
+        let offset = drain_output(read, 0, &mut emit).unwrap();
+
+        // The client disconnects while a line is half written
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"line\":").unwrap();
+        let resume_from = drain_output(read, offset, &mut emit).unwrap();
+        assert_eq!(resume_from, offset);
+        file.write_all(b"5}\n").unwrap();
+        output.append_line("{\"line\":6}");
+
+        // and reconnects from the last next_offset it received
+        let end = drain_output(read, resume_from, &mut emit).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(end, written.len() as u64);
+        let streamed: String = received.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(streamed, written);
+        assert!(received.windows(2).all(|pair| pair[0].next_offset == pair[1].offset));
+
+        // A superseded stream stops without moving past the refused chunk
+        assert_eq!(drain_output(read, 0, |_| false).unwrap(), 0);
+    }
+}
@@ -1,8 +1,16 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use tokio::process::Child;
+
+use super::child::{ChildExit, ClaudeChild};
+use super::output::{
+    read_output_range, read_output_tail, read_output_tail_range, run_output_path, tail_start,
+    LiveOutput, OutputChunk, LIVE_OUTPUT_TAIL_BYTES, MAX_OUTPUT_CHUNK_BYTES,
+};
+use super::resources::{ProcessResources, ResourceSampler};
+use super::throughput::{RunThroughput, ThroughputMetrics};
 
 /// Type of process being tracked
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,20 +34,40 @@ pub struct ProcessInfo {
     pub project_path: String,
     pub task: String,
     pub model: String,
+    pub deadline: Option<DateTime<Utc>>, // Hard timeout after which the run is killed
+    #[serde(default)]
+    pub claude_binary: Option<String>, // Binary a Claude session runs, which may be a fallback
+}
+
+/// How a process ended after being asked to stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminationOutcome {
+    /// No such process was found
+    NotFound,
+    /// It exited within the grace period
+    Graceful,
+    /// It had to be force-killed
+    ForceKilled,
 }
 
 /// Information about a running process with handle
 #[allow(dead_code)]
 pub struct ProcessHandle {
     pub info: ProcessInfo,
-    pub child: Arc<Mutex<Option<Child>>>,
-    pub live_output: Arc<Mutex<String>>,
+    pub child: Arc<Mutex<Option<ClaudeChild>>>,
+    pub live_output: Arc<Mutex<LiveOutput>>,
+    pub throughput: Arc<Mutex<RunThroughput>>,
+    pub adopted: bool, // Started before the app restarted, so tracked by PID alone
 }
 
 /// Registry for tracking active agent processes
 pub struct ProcessRegistry {
     processes: Arc<Mutex<HashMap<i64, ProcessHandle>>>, // run_id -> ProcessHandle
     next_id: Arc<Mutex<i64>>, // Auto-incrementing ID for non-agent processes
+    launch_lock: Arc<tokio::sync::Mutex<()>>, // Serializes agent launches against the run queue
+    output_dir: Arc<Mutex<Option<PathBuf>>>, // Where agent run output is spilled
+    resources: Arc<Mutex<ResourceSampler>>, // CPU and memory of the registered processes
+    exits: Arc<Mutex<HashMap<i64, ChildExit>>>, // How reaped children exited, until unregistered
 }
 
 impl ProcessRegistry {
@@ -47,9 +75,38 @@ impl ProcessRegistry {
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
             next_id: Arc::new(Mutex::new(1000000)), // Start at high number to avoid conflicts
+            launch_lock: Arc::new(tokio::sync::Mutex::new(())),
+            output_dir: Arc::new(Mutex::new(None)),
+            resources: Arc::new(Mutex::new(ResourceSampler::default())),
+            exits: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Remember how a run's child exited, whichever call reaped it
+    fn record_exit(&self, run_id: i64, exit: ChildExit) {
+        if let Ok(mut exits) = self.exits.lock() {
+            exits.insert(run_id, exit);
+        }
+    }
+
+    /// How a run's child exited, once it has been reaped
+    pub fn exit_status(&self, run_id: i64) -> Option<ChildExit> {
+        self.exits.lock().ok()?.get(&run_id).copied()
+    }
+
+    /// Set the directory agent run output is written to
+    pub fn set_output_dir(&self, dir: PathBuf) -> Result<(), String> {
+        let mut output_dir = self.output_dir.lock().map_err(|e| e.to_string())?;
+        *output_dir = Some(dir);
+        Ok(())
+    }
+
+    /// The file holding a run's full output, which outlives the process
+    pub fn output_path(&self, run_id: i64) -> Option<PathBuf> {
+        let output_dir = self.output_dir.lock().ok()?;
+        output_dir.as_deref().map(|dir| run_output_path(dir, run_id))
+    }
+
     /// Generate a unique ID for non-agent processes
     pub fn generate_id(&self) -> Result<i64, String> {
         let mut next_id = self.next_id.lock().map_err(|e| e.to_string())?;
@@ -68,7 +125,7 @@ impl ProcessRegistry {
         project_path: String,
         task: String,
         model: String,
-        child: Child,
+        child: ClaudeChild,
     ) -> Result<(), String> {
         let process_info = ProcessInfo {
             run_id,
@@ -78,9 +135,23 @@ impl ProcessRegistry {
             project_path,
             task,
             model,
+            deadline: None,
+            claude_binary: None,
         };
 
-        self.register_process_internal(run_id, process_info, child)
+        self.register_process_internal(run_id, process_info, child)?;
+
+        // Spill the run's output to disk so only its tail stays in memory
+        if let Some(path) = self.output_path(run_id) {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            if let Some(handle) = processes.get(&run_id) {
+                let mut live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
+                if let Err(e) = live_output.spill_to(&path) {
+                    log::warn!("Failed to create output file {:?}: {}", path, e);
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Register a new running agent process using sidecar (similar to register_process but for sidecar children)
@@ -102,6 +173,8 @@ impl ProcessRegistry {
             project_path,
             task,
             model,
+            deadline: None,
+            claude_binary: None,
         };
 
         // For sidecar processes, we register without the child handle since it's managed differently
@@ -109,14 +182,42 @@ impl ProcessRegistry {
         
         let process_handle = ProcessHandle {
             info: process_info,
-            child: Arc::new(Mutex::new(None)), // No tokio::process::Child handle for sidecar
-            live_output: Arc::new(Mutex::new(String::new())),
+            child: Arc::new(Mutex::new(None)), // No child handle for sidecar
+            live_output: Arc::new(Mutex::new(LiveOutput::new())),
+            throughput: Arc::new(Mutex::new(RunThroughput::new(std::time::Instant::now()))),
+            adopted: false,
         };
 
         processes.insert(run_id, process_handle);
         Ok(())
     }
 
+    /// Track an agent process that outlived a previous app session. There is
+    /// no child handle, so liveness is checked by PID; output keeps going to
+    /// the run's existing output file.
+    pub fn adopt_agent_process(&self, process_info: ProcessInfo) -> Result<(), String> {
+        let run_id = process_info.run_id;
+        let mut live_output = LiveOutput::new();
+        if let Some(path) = self.output_path(run_id).filter(|path| path.exists()) {
+            if let Err(e) = live_output.resume_from(&path) {
+                log::warn!("Failed to reopen output file {:?}: {}", path, e);
+            }
+        }
+
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        processes.insert(
+            run_id,
+            ProcessHandle {
+                info: process_info,
+                child: Arc::new(Mutex::new(None)),
+                live_output: Arc::new(Mutex::new(live_output)),
+                throughput: Arc::new(Mutex::new(RunThroughput::new(std::time::Instant::now()))),
+                adopted: true,
+            },
+        );
+        Ok(())
+    }
+
     /// Register a new Claude session (without child process - handled separately)
     pub fn register_claude_session(
         &self,
@@ -125,6 +226,7 @@ impl ProcessRegistry {
         project_path: String,
         task: String,
         model: String,
+        claude_binary: String,
     ) -> Result<i64, String> {
         let run_id = self.generate_id()?;
         
@@ -136,15 +238,27 @@ impl ProcessRegistry {
             project_path,
             task,
             model,
+            deadline: None,
+            claude_binary: Some(claude_binary),
         };
 
+        // Spill to disk like agent runs so offset reads stay cheap in long sessions
+        let mut live_output = LiveOutput::new();
+        if let Some(path) = self.output_path(run_id) {
+            if let Err(e) = live_output.spill_to(&path) {
+                log::warn!("Failed to create output file {:?}: {}", path, e);
+            }
+        }
+
         // Register without child - Claude sessions use ClaudeProcessState for process management
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
         
         let process_handle = ProcessHandle {
             info: process_info,
             child: Arc::new(Mutex::new(None)), // No child handle for Claude sessions
-            live_output: Arc::new(Mutex::new(String::new())),
+            live_output: Arc::new(Mutex::new(live_output)),
+            throughput: Arc::new(Mutex::new(RunThroughput::new(std::time::Instant::now()))),
+            adopted: false,
         };
 
         processes.insert(run_id, process_handle);
@@ -156,14 +270,16 @@ impl ProcessRegistry {
         &self,
         run_id: i64,
         process_info: ProcessInfo,
-        child: Child,
+        child: ClaudeChild,
     ) -> Result<(), String> {
         let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
 
         let process_handle = ProcessHandle {
             info: process_info,
             child: Arc::new(Mutex::new(Some(child))),
-            live_output: Arc::new(Mutex::new(String::new())),
+            live_output: Arc::new(Mutex::new(LiveOutput::new())),
+            throughput: Arc::new(Mutex::new(RunThroughput::new(std::time::Instant::now()))),
+            adopted: false,
         };
 
         processes.insert(run_id, process_handle);
@@ -199,10 +315,26 @@ impl ProcessRegistry {
     }
 
     /// Unregister a process (called when it completes)
-    #[allow(dead_code)]
     pub fn unregister_process(&self, run_id: i64) -> Result<(), String> {
-        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
-        processes.remove(&run_id);
+        let removed = {
+            let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+            processes.remove(&run_id)
+        };
+        if let Ok(mut exits) = self.exits.lock() {
+            exits.remove(&run_id);
+        }
+
+        // Agent output is kept with the run; a Claude session's lives in its JSONL
+        let was_claude_session = removed.is_some_and(|handle| {
+            matches!(handle.info.process_type, ProcessType::ClaudeSession { .. })
+        });
+        if was_claude_session {
+            if let Some(path) = self.output_path(run_id).filter(|path| path.exists()) {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    log::warn!("Failed to remove output file {:?}: {}", path, e);
+                }
+            }
+        }
         Ok(())
     }
 
@@ -230,6 +362,130 @@ impl ProcessRegistry {
             .collect())
     }
 
+    /// Count agent processes whose child has not exited yet
+    pub fn live_agent_process_count(&self) -> Result<usize, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let mut count = 0;
+        for handle in processes.values() {
+            if !matches!(handle.info.process_type, ProcessType::AgentRun { .. }) {
+                continue;
+            }
+            if handle.adopted {
+                count += process_alive(handle.info.pid) as usize;
+                continue;
+            }
+            let mut child_guard = handle.child.lock().map_err(|e| e.to_string())?;
+            if let Some(child) = child_guard.as_mut() {
+                match child.try_wait() {
+                    Ok(None) => count += 1,
+                    exited => {
+                        if let Ok(Some(exit)) = exited {
+                            self.record_exit(handle.info.run_id, exit);
+                        }
+                        *child_guard = None; // Exited (or unknowable) - release the slot
+                    }
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    /// Find a live agent run working in the same project directory. Runs whose
+    /// process has exited no longer count, even if they were never unregistered.
+    pub fn find_live_agent_run_on_path(&self, project_path: &str) -> Result<Option<ProcessInfo>, String> {
+        let target = normalize_project_path(project_path);
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        for handle in processes.values() {
+            if !matches!(handle.info.process_type, ProcessType::AgentRun { .. })
+                || normalize_project_path(&handle.info.project_path) != target
+            {
+                continue;
+            }
+            if handle.adopted {
+                if process_alive(handle.info.pid) {
+                    return Ok(Some(handle.info.clone()));
+                }
+                continue;
+            }
+            let mut child_guard = handle.child.lock().map_err(|e| e.to_string())?;
+            if let Some(child) = child_guard.as_mut() {
+                match child.try_wait() {
+                    Ok(None) => return Ok(Some(handle.info.clone())),
+                    exited => {
+                        if let Ok(Some(exit)) = exited {
+                            self.record_exit(handle.info.run_id, exit);
+                        }
+                        *child_guard = None;
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Acquire the lock that serializes agent launches against the concurrency limit
+    pub async fn lock_launch(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.launch_lock.lock().await
+    }
+
+    /// Wait for a process to exit and release its child handle
+    pub async fn wait_for_exit(&self, run_id: i64, timeout: std::time::Duration) -> Result<bool, String> {
+        let child_arc = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            match processes.get(&run_id) {
+                Some(handle) => handle.child.clone(),
+                None => return Ok(true),
+            }
+        };
+
+        let deadline = std::time::Instant::now() + timeout;
+        loop {
+            {
+                let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
+                match child_guard.as_mut() {
+                    Some(child) => match child.try_wait() {
+                        Ok(None) => {}
+                        exited => {
+                            if let Ok(Some(exit)) = exited {
+                                self.record_exit(run_id, exit);
+                            }
+                            *child_guard = None;
+                            return Ok(true);
+                        }
+                    },
+                    None => return Ok(true),
+                }
+            }
+
+            if std::time::Instant::now() >= deadline {
+                return Ok(false);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Set a hard timeout for a process, measured from when it was registered
+    pub fn set_process_timeout(&self, run_id: i64, timeout_minutes: i64) -> Result<(), String> {
+        let mut processes = self.processes.lock().map_err(|e| e.to_string())?;
+        if let Some(handle) = processes.get_mut(&run_id) {
+            handle.info.deadline =
+                Some(handle.info.started_at + chrono::Duration::minutes(timeout_minutes));
+        }
+        Ok(())
+    }
+
+    /// Get agent processes whose hard timeout has passed
+    pub fn get_timed_out_agent_processes(&self) -> Result<Vec<ProcessInfo>, String> {
+        let now = Utc::now();
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        Ok(processes
+            .values()
+            .filter(|handle| matches!(handle.info.process_type, ProcessType::AgentRun { .. }))
+            .filter(|handle| handle.info.deadline.is_some_and(|deadline| deadline <= now))
+            .map(|handle| handle.info.clone())
+            .collect())
+    }
+
     /// Get a specific running process
     #[allow(dead_code)]
     pub fn get_process(&self, run_id: i64) -> Result<Option<ProcessInfo>, String> {
@@ -237,7 +493,89 @@ impl ProcessRegistry {
         Ok(processes.get(&run_id).map(|handle| handle.info.clone()))
     }
 
+    /// Ask a process and its children to exit, force-killing the whole tree if it
+    /// is still running after `grace`. The registry entry is removed either way.
+    pub async fn terminate_process(
+        &self,
+        run_id: i64,
+        grace: std::time::Duration,
+    ) -> Result<TerminationOutcome, String> {
+        use log::{info, warn};
+
+        let (pid, child_arc) = {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            match processes.get(&run_id) {
+                Some(handle) => (handle.info.pid, handle.child.clone()),
+                None => return Ok(TerminationOutcome::NotFound),
+            }
+        };
+
+        info!(
+            "Asking process {} (PID: {}) to exit, grace period {:?}",
+            run_id, pid, grace
+        );
+        signal_process_tree(pid, false);
+
+        let outcome = if self.wait_for_exit(run_id, grace).await? {
+            info!("Process {} exited gracefully", run_id);
+            TerminationOutcome::Graceful
+        } else {
+            warn!(
+                "Process {} still running after {:?}, force-killing its process tree",
+                run_id, grace
+            );
+            signal_process_tree(pid, true);
+            if let Ok(mut child_guard) = child_arc.lock() {
+                if let Some(child) = child_guard.as_mut() {
+                    let _ = child.start_kill();
+                }
+            }
+            if !self
+                .wait_for_exit(run_id, std::time::Duration::from_secs(2))
+                .await?
+            {
+                warn!("Process {} did not exit after being force-killed", run_id);
+            }
+            TerminationOutcome::ForceKilled
+        };
+
+        // Children such as MCP servers can outlive a graceful exit of the main
+        // process. It has been reaped by now, so only its group is signalled.
+        if outcome == TerminationOutcome::Graceful {
+            signal_process_group(pid, true);
+        }
+
+        self.unregister_process(run_id)?;
+        Ok(outcome)
+    }
+
+    /// Like `terminate_process`, for a process known only by its PID
+    pub async fn terminate_pid(
+        &self,
+        pid: u32,
+        grace: std::time::Duration,
+    ) -> TerminationOutcome {
+        if !process_alive(pid) {
+            return TerminationOutcome::NotFound;
+        }
+
+        signal_process_tree(pid, false);
+        let deadline = std::time::Instant::now() + grace;
+        while std::time::Instant::now() < deadline {
+            if !process_alive(pid) {
+                signal_process_group(pid, true);
+                return TerminationOutcome::Graceful;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+
+        log::warn!("PID {} still running after {:?}, force-killing it", pid, grace);
+        signal_process_tree(pid, true);
+        TerminationOutcome::ForceKilled
+    }
+
     /// Kill a running process with proper cleanup
+    #[allow(dead_code)]
     pub async fn kill_process(&self, run_id: i64) -> Result<bool, String> {
         use log::{error, info, warn};
 
@@ -299,6 +637,7 @@ impl ProcessRegistry {
                         match child.try_wait() {
                             Ok(Some(status)) => {
                                 info!("Process {} exited with status: {:?}", run_id, status);
+                                self.record_exit(run_id, status);
                                 *child_guard = None; // Clear the child handle
                                 Some(Ok::<(), String>(()))
                             }
@@ -427,19 +766,22 @@ impl ProcessRegistry {
     }
 
     /// Check if a process is still running by trying to get its status
-    #[allow(dead_code)]
     pub async fn is_process_running(&self, run_id: i64) -> Result<bool, String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
 
         if let Some(handle) = processes.get(&run_id) {
+            if handle.adopted {
+                return Ok(process_alive(handle.info.pid));
+            }
             let child_arc = handle.child.clone();
             drop(processes); // Release the lock before async operation
 
             let mut child_guard = child_arc.lock().map_err(|e| e.to_string())?;
             if let Some(ref mut child) = child_guard.as_mut() {
                 match child.try_wait() {
-                    Ok(Some(_)) => {
+                    Ok(Some(exit)) => {
                         // Process has exited
+                        self.record_exit(run_id, exit);
                         *child_guard = None;
                         Ok(false)
                     }
@@ -466,21 +808,178 @@ impl ProcessRegistry {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
         if let Some(handle) = processes.get(&run_id) {
             let mut live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
-            live_output.push_str(output);
-            live_output.push('\n');
+            live_output.append_line(output);
         }
         Ok(())
     }
 
-    /// Get live output for a process
-    pub fn get_live_output(&self, run_id: i64) -> Result<String, String> {
+    /// Append a line to a run's output, writing to its output file when the
+    /// process has already been stopped and unregistered
+    pub fn append_run_output(&self, run_id: i64, line: &str) -> Result<(), String> {
+        {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            if let Some(handle) = processes.get(&run_id) {
+                let mut live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
+                live_output.append_line(line);
+                return Ok(());
+            }
+        }
+        if let Some(path) = self.output_path(run_id).filter(|path| path.exists()) {
+            use std::io::Write;
+            std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| writeln!(file, "{}", line))
+                .map_err(|e| format!("Failed to append to run output: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Count a parsed line of a process's output towards its throughput
+    pub fn record_throughput(&self, run_id: i64, line: &serde_json::Value) -> Result<(), String> {
         let processes = self.processes.lock().map_err(|e| e.to_string())?;
         if let Some(handle) = processes.get(&run_id) {
-            let live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
-            Ok(live_output.clone())
-        } else {
-            Ok(String::new())
+            let mut throughput = handle.throughput.lock().map_err(|e| e.to_string())?;
+            throughput.record(line, std::time::Instant::now());
         }
+        Ok(())
+    }
+
+    /// Output rates of a running process, or None when it isn't running
+    pub fn get_throughput(
+        &self,
+        run_id: i64,
+        stall_after: std::time::Duration,
+    ) -> Result<Option<ThroughputMetrics>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        match processes.get(&run_id) {
+            Some(handle) => {
+                let mut throughput = handle.throughput.lock().map_err(|e| e.to_string())?;
+                Ok(Some(throughput.metrics(std::time::Instant::now(), stall_after)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Agent runs that have stalled since the last call, each reported once
+    /// until it produces output again
+    pub fn take_newly_stalled_agent_runs(
+        &self,
+        stall_after: std::time::Duration,
+    ) -> Result<Vec<(i64, ThroughputMetrics)>, String> {
+        let processes = self.processes.lock().map_err(|e| e.to_string())?;
+        let now = std::time::Instant::now();
+        let mut stalled = Vec::new();
+        for (run_id, handle) in processes.iter() {
+            if !matches!(handle.info.process_type, ProcessType::AgentRun { .. }) {
+                continue;
+            }
+            let mut throughput = handle.throughput.lock().map_err(|e| e.to_string())?;
+            if throughput.take_new_stall(now, stall_after) {
+                stalled.push((*run_id, throughput.metrics(now, stall_after)));
+            }
+        }
+        Ok(stalled)
+    }
+
+    /// Get the tail of a process's live output, read from its output file
+    /// once the process is gone
+    pub fn get_live_output(&self, run_id: i64) -> Result<String, String> {
+        {
+            let processes = self.processes.lock().map_err(|e| e.to_string())?;
+            if let Some(handle) = processes.get(&run_id) {
+                let live_output = handle.live_output.lock().map_err(|e| e.to_string())?;
+                return Ok(live_output.tail());
+            }
+        }
+        match self.output_path(run_id).filter(|path| path.exists()) {
+            Some(path) => read_output_tail(&path, LIVE_OUTPUT_TAIL_BYTES)
+                .map_err(|e| format!("Failed to read run output: {}", e)),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// Read a range of a run's output file starting at `offset`
+    pub fn read_live_output_range(&self, run_id: i64, offset: u64) -> Result<OutputChunk, String> {
+        match self.output_path(run_id).filter(|path| path.exists()) {
+            Some(path) => read_output_range(&path, offset, MAX_OUTPUT_CHUNK_BYTES)
+                .map_err(|e| format!("Failed to read run output: {}", e)),
+            None => Ok(OutputChunk {
+                offset: 0,
+                next_offset: 0,
+                total_bytes: 0,
+                content: String::new(),
+            }),
+        }
+    }
+
+    /// Read up to `max_bytes` of output from `offset`, or the last `max_bytes`
+    /// without one. Falls back to the in-memory tail when nothing was spilled.
+    pub fn read_output_from(
+        &self,
+        run_id: i64,
+        offset: Option<u64>,
+        max_bytes: usize,
+    ) -> Result<OutputChunk, String> {
+        if let Some(path) = self.output_path(run_id).filter(|path| path.exists()) {
+            let chunk = match offset {
+                Some(offset) => read_output_range(&path, offset, max_bytes),
+                None => read_output_tail_range(&path, max_bytes),
+            };
+            return chunk.map_err(|e| format!("Failed to read run output: {}", e));
+        }
+
+        let tail = self.get_live_output(run_id)?;
+        let total_bytes = tail.len() as u64;
+        let start = match offset {
+            Some(offset) => offset.min(total_bytes) as usize,
+            None => tail_start(&tail, max_bytes),
+        };
+        let content = tail.get(start..).unwrap_or_default().to_string();
+        Ok(OutputChunk {
+            offset: start as u64,
+            next_offset: total_bytes,
+            total_bytes,
+            content,
+        })
+    }
+
+    /// A run's complete output from its output file, or the in-memory tail
+    /// when it has none
+    pub fn read_run_output(&self, run_id: i64) -> Result<String, String> {
+        match self.output_path(run_id).filter(|path| path.exists()) {
+            Some(path) => std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read run output: {}", e)),
+            None => self.get_live_output(run_id),
+        }
+    }
+
+    /// What each of `processes` is using, in the same order. Processes that
+    /// exited without being unregistered are reported stale and unregistered.
+    pub fn sample_resources(
+        &self,
+        processes: &[ProcessInfo],
+    ) -> Result<Vec<ProcessResources>, String> {
+        let targets: Vec<(u32, DateTime<Utc>)> = processes
+            .iter()
+            .map(|process| (process.pid, process.started_at))
+            .collect();
+        let sampled = self
+            .resources
+            .lock()
+            .map_err(|e| e.to_string())?
+            .sample(&targets);
+        for (process, resources) in processes.iter().zip(&sampled) {
+            if resources.stale {
+                log::warn!(
+                    "Process {} of run {} exited without being unregistered",
+                    process.pid,
+                    process.run_id
+                );
+                self.unregister_process(process.run_id)?;
+            }
+        }
+        Ok(sampled)
     }
 
     /// Cleanup finished processes
@@ -514,6 +1013,191 @@ impl ProcessRegistry {
     }
 }
 
+/// A project path in the form used to compare runs: symlinks resolved,
+/// and lowercased where the file system is usually case-insensitive
+pub fn normalize_project_path(project_path: &str) -> String {
+    let path = std::path::Path::new(project_path);
+    let resolved = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let normalized = resolved.to_string_lossy();
+    let normalized = normalized.trim_end_matches(['/', '\\']);
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        normalized.to_lowercase()
+    } else {
+        normalized.to_string()
+    }
+}
+
+/// Send a stop signal to a process and its descendants. Agent processes are
+/// spawned as process group leaders on Unix, so the group id is their PID.
+/// Only for processes still running, since the PID alone is signalled when
+/// there's no such group.
+pub fn signal_process_tree(pid: u32, force: bool) {
+    #[cfg(unix)]
+    {
+        // Fall back to the single process when it doesn't lead a group
+        if !signal_process_group(pid, force) {
+            let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+            unsafe {
+                libc::kill(pid as i32, signal);
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let pid = pid.to_string();
+        let mut args = vec!["/T", "/PID", pid.as_str()];
+        if force {
+            args.insert(0, "/F");
+        }
+        let _ = std::process::Command::new("taskkill").args(&args).output();
+    }
+}
+
+/// Send a stop signal to the process group `pid` led, returning whether there
+/// was one. Unlike `signal_process_tree` this is safe once the leader has been
+/// reaped, as a process that reused its PID is never signalled. Windows has no
+/// such groups, so nothing is sent there.
+pub fn signal_process_group(pid: u32, force: bool) -> bool {
+    #[cfg(unix)]
+    {
+        let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+        unsafe { libc::kill(-(pid as i32), signal) == 0 }
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = (pid, force);
+        false
+    }
+}
+
+/// Stop a child spawned as a process group leader together with everything it
+/// started, force-killing the group if the child outlives `grace`. `group` is
+/// the child's PID at spawn, which stays valid for the group after it exits.
+/// Windows has no process groups, so there the tree under the still running
+/// child is force-killed instead.
+pub async fn terminate_process_group(
+    child: &mut ClaudeChild,
+    group: u32,
+    grace: std::time::Duration,
+) -> TerminationOutcome {
+    if let Ok(Some(_)) = child.try_wait() {
+        // Only stragglers such as MCP servers can be left
+        signal_process_group(group, true);
+        return TerminationOutcome::NotFound;
+    }
+
+    signal_process_tree(group, false);
+    let outcome = match tokio::time::timeout(grace, child.wait()).await {
+        Ok(_) => TerminationOutcome::Graceful,
+        Err(_) => {
+            log::warn!(
+                "Process group {} still running after {:?}, force-killing it",
+                group,
+                grace
+            );
+            TerminationOutcome::ForceKilled
+        }
+    };
+    signal_process_group(group, true);
+    if outcome == TerminationOutcome::ForceKilled {
+        // `taskkill /T` finds the children through the leader, so it goes first
+        #[cfg(windows)]
+        signal_process_tree(group, true);
+        let _ = child.kill().await;
+    }
+    outcome
+}
+
+/// Whether a process with this PID exists
+fn process_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        unsafe { libc::kill(pid as i32, 0) == 0 }
+    }
+
+    #[cfg(windows)]
+    {
+        std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .output()
+            .map(|output| tasklist_lists_pid(&String::from_utf8_lossy(&output.stdout), pid))
+            .unwrap_or(false)
+    }
+}
+
+/// Whether `tasklist /FO CSV /NH` output has a row for exactly this PID
+#[cfg_attr(not(windows), allow(dead_code))]
+fn tasklist_lists_pid(output: &str, pid: u32) -> bool {
+    let pid = pid.to_string();
+    output
+        .lines()
+        .any(|line| line.trim().trim_matches('"').split("\",\"").nth(1) == Some(pid.as_str()))
+}
+
+/// How far a process's start time may be from the one recorded for its run
+/// before the PID is assumed to have been reused
+const PID_START_TOLERANCE_SECONDS: i64 = 120;
+
+/// Whether `pid` is a live Claude process started around `started_at`, rather
+/// than an unrelated process that reused the PID after the run ended
+pub fn is_claude_process(pid: u32, started_at: DateTime<Utc>) -> bool {
+    #[cfg(unix)]
+    {
+        let output = match std::process::Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", "lstart=", "-o", "args="])
+            .env("LC_ALL", "C")
+            .output()
+        {
+            Ok(output) if output.status.success() => output,
+            _ => return false,
+        };
+        match parse_ps_identity(&String::from_utf8_lossy(&output.stdout)) {
+            Some((process_started_at, command)) => {
+                looks_like_claude(&command)
+                    && (process_started_at - started_at).num_seconds().abs()
+                        <= PID_START_TOLERANCE_SECONDS
+            }
+            None => false,
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        // tasklist has no start time, so only the image name is checked
+        let _ = started_at;
+        std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .output()
+            .map(|output| {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                tasklist_lists_pid(&stdout, pid) && looks_like_claude(&stdout)
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Parse `ps -o lstart= -o args=` output into the local start time and command line
+fn parse_ps_identity(output: &str) -> Option<(DateTime<Utc>, String)> {
+    let line = output.lines().find(|line| !line.trim().is_empty())?;
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 6 {
+        return None;
+    }
+    let started = chrono::NaiveDateTime::parse_from_str(&fields[..5].join(" "), "%a %b %d %H:%M:%S %Y")
+        .ok()?
+        .and_local_timezone(chrono::Local)
+        .earliest()?
+        .with_timezone(&Utc);
+    Some((started, fields[5..].join(" ")))
+}
+
+/// Claude installs run either as a `claude` binary or as node running its CLI script
+fn looks_like_claude(command: &str) -> bool {
+    command.to_lowercase().contains("claude")
+}
+
 impl Default for ProcessRegistry {
     fn default() -> Self {
         Self::new()
@@ -528,3 +1212,223 @@ impl Default for ProcessRegistryState {
         Self(Arc::new(ProcessRegistry::new()))
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncBufReadExt;
+
+    /// Spawn a shell that forks a `sleep` child, returning the registered
+    /// registry and the child's PID
+    async fn spawn_tree(run_id: i64, script: &str) -> (ProcessRegistry, u32) {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", script])
+            .stdout(std::process::Stdio::piped())
+            .process_group(0);
+        let mut child = cmd.spawn().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let mut lines = tokio::io::BufReader::new(stdout).lines();
+        let child_pid: u32 = lines.next_line().await.unwrap().unwrap().trim().parse().unwrap();
+
+        let registry = ProcessRegistry::new();
+        let pid = child.id().unwrap();
+        registry
+            .register_process(
+                run_id,
+                1,
+                "test".to_string(),
+                pid,
+                String::new(),
+                String::new(),
+                String::new(),
+                child.into(),
+            )
+            .unwrap();
+        (registry, child_pid)
+    }
+
+    #[tokio::test]
+    async fn test_exit_status_is_kept_by_whichever_call_reaps() {
+        let (registry, _) = spawn_tree(4, "echo 0; exit 3").await;
+        assert!(registry
+            .wait_for_exit(4, std::time::Duration::from_secs(5))
+            .await
+            .unwrap());
+        // A second wait finds the child gone, but the exit is still known
+        assert!(registry
+            .wait_for_exit(4, std::time::Duration::from_secs(5))
+            .await
+            .unwrap());
+        assert_eq!(registry.exit_status(4), Some(ChildExit { code: Some(3) }));
+        assert!(!registry.exit_status(4).unwrap().success());
+
+        registry.unregister_process(4).unwrap();
+        assert_eq!(registry.exit_status(4), None);
+    }
+
+    #[test]
+    fn test_run_output_is_appended_to_the_file_once_unregistered() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = ProcessRegistry::new();
+        registry.set_output_dir(dir.path().to_path_buf()).unwrap();
+        let path = registry.output_path(5).unwrap();
+        std::fs::write(&path, "first\n").unwrap();
+
+        registry.append_run_output(5, "marker").unwrap();
+        assert_eq!(registry.read_run_output(5).unwrap(), "first\nmarker\n");
+        registry.append_run_output(6, "no file").unwrap();
+        assert!(!registry.output_path(6).unwrap().exists());
+    }
+
+    /// Whether a PID is gone, counting zombies awaiting their reaper as gone
+    fn is_gone(pid: u32) -> bool {
+        !process_alive(pid)
+            || std::process::Command::new("ps")
+                .args(["-o", "stat=", "-p", &pid.to_string()])
+                .output()
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().starts_with('Z'))
+                .unwrap_or(false)
+    }
+
+    #[tokio::test]
+    async fn test_terminate_process_stops_children() {
+        let (registry, child_pid) = spawn_tree(1, "sleep 60 & echo $!; wait").await;
+        let outcome = registry
+            .terminate_process(1, std::time::Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(outcome, TerminationOutcome::Graceful);
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        assert!(is_gone(child_pid));
+        assert!(registry.get_process(1).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_terminate_process_escalates_to_kill() {
+        let (registry, child_pid) =
+            spawn_tree(2, "trap '' TERM; sleep 60 & echo $!; wait").await;
+        let outcome = registry
+            .terminate_process(2, std::time::Duration::from_millis(500))
+            .await
+            .unwrap();
+        assert_eq!(outcome, TerminationOutcome::ForceKilled);
+        tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+        assert!(is_gone(child_pid));
+        assert!(registry.get_process(2).unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_terminate_process_group_stops_leader_and_children() {
+        for (script, expected) in [
+            ("sleep 60 & echo $!; wait", TerminationOutcome::Graceful),
+            (
+                "trap '' TERM; sleep 60 & echo $!; wait",
+                TerminationOutcome::ForceKilled,
+            ),
+        ] {
+            let mut child = tokio::process::Command::new("sh")
+                .args(["-c", script])
+                .stdout(std::process::Stdio::piped())
+                .process_group(0)
+                .spawn()
+                .unwrap();
+            let stdout = child.stdout.take().unwrap();
+            let mut lines = tokio::io::BufReader::new(stdout).lines();
+            let child_pid: u32 = lines.next_line().await.unwrap().unwrap().trim().parse().unwrap();
+            let group = child.id().unwrap();
+            let mut child = ClaudeChild::from(child);
+
+            let outcome =
+                terminate_process_group(&mut child, group, std::time::Duration::from_millis(500))
+                    .await;
+            assert_eq!(outcome, expected);
+            tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+            assert!(child.try_wait().unwrap().is_some());
+            assert!(is_gone(group));
+            assert!(is_gone(child_pid));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signal_process_group_leaves_lone_processes_alone() {
+        // Not a group leader, like a process that reused a reaped leader's PID
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("60")
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        let pid = child.id().unwrap();
+        assert!(!signal_process_group(pid, true));
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().await.unwrap();
+    }
+
+    #[test]
+    fn test_tasklist_pid_must_match_exactly() {
+        let output = "\"claude.exe\",\"1234\",\"Console\",\"1\",\"52,112 K\"\r\n";
+        assert!(tasklist_lists_pid(output, 1234));
+        assert!(!tasklist_lists_pid(output, 12));
+        assert!(!tasklist_lists_pid(
+            "INFO: No tasks are running which match the specified criteria.",
+            12
+        ));
+    }
+
+    #[test]
+    fn test_ps_identity_reads_local_start_time_and_command() {
+        let (started, command) = parse_ps_identity(
+            "Thu Oct  8 09:05:03 2026 node /usr/lib/node_modules/@anthropic-ai/claude-code/cli.js -p task\n",
+        )
+        .unwrap();
+        let expected = chrono::NaiveDate::from_ymd_opt(2026, 10, 8)
+            .unwrap()
+            .and_hms_opt(9, 5, 3)
+            .unwrap()
+            .and_local_timezone(chrono::Local)
+            .earliest()
+            .unwrap();
+        assert_eq!(started, expected);
+        assert!(looks_like_claude(&command));
+        assert!(!looks_like_claude("/usr/bin/python3 server.py"));
+        assert!(parse_ps_identity("").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_busy_project_matches_through_symlinks_until_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        let link = dir.path().join("link");
+        std::fs::create_dir(&project).unwrap();
+        std::os::unix::fs::symlink(&project, &link).unwrap();
+
+        let child = tokio::process::Command::new("sh")
+            .args(["-c", "sleep 0.3"])
+            .spawn()
+            .unwrap();
+        let registry = ProcessRegistry::new();
+        registry
+            .register_process(
+                3,
+                1,
+                "test".to_string(),
+                child.id().unwrap(),
+                project.to_string_lossy().into_owned(),
+                String::new(),
+                String::new(),
+                child.into(),
+            )
+            .unwrap();
+
+        let busy = registry
+            .find_live_agent_run_on_path(&format!("{}/", link.display()))
+            .unwrap();
+        assert_eq!(busy.map(|info| info.run_id), Some(3));
+
+        // A run that has exited frees the project even while still registered
+        tokio::time::sleep(tokio::time::Duration::from_millis(600)).await;
+        assert!(registry
+            .find_live_agent_run_on_path(&project.to_string_lossy())
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System};
+
+/// How long a sample is reused, so polling the running sessions doesn't
+/// re-read /proc on every call
+const SAMPLE_TTL: Duration = Duration::from_millis(1500);
+
+/// What a running process is using
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProcessResources {
+    /// Share of one core since the previous sample, so it can exceed 100 on
+    /// multi-core machines; 0 on the first sample
+    pub cpu_percent: f32,
+    /// Resident memory in bytes
+    pub memory_bytes: u64,
+    pub elapsed_seconds: u64,
+    /// The process exited after it was registered
+    pub stale: bool,
+}
+
+/// A listed item together with what its process is using
+#[derive(Debug, Clone, Serialize)]
+pub struct WithResources<T> {
+    #[serde(flatten)]
+    pub item: T,
+    pub resources: ProcessResources,
+}
+
+/// CPU and memory of one process, or None if it's gone
+type Usage = Option<(f32, u64)>;
+
+/// Samples processes by PID. CPU usage is measured between samples, so one
+/// sampler is kept for the app's lifetime.
+pub struct ResourceSampler {
+    system: System,
+    sampled_at: Option<Instant>,
+    usage: HashMap<u32, Usage>,
+}
+
+impl Default for ResourceSampler {
+    fn default() -> Self {
+        Self {
+            system: System::new(),
+            sampled_at: None,
+            usage: HashMap::new(),
+        }
+    }
+}
+
+impl ResourceSampler {
+    /// Resources of processes started at the given times, sampling again only
+    /// when the last sample is too old or didn't include all of them
+    pub fn sample(&mut self, processes: &[(u32, DateTime<Utc>)]) -> Vec<ProcessResources> {
+        let now = Instant::now();
+        let fresh = self
+            .sampled_at
+            .is_some_and(|at| now.duration_since(at) < SAMPLE_TTL)
+            && processes
+                .iter()
+                .all(|(pid, _)| self.usage.contains_key(pid));
+        if !fresh {
+            self.refresh(processes.iter().map(|(pid, _)| *pid).collect());
+            self.sampled_at = Some(now);
+        }
+
+        processes
+            .iter()
+            .map(|(pid, started_at)| {
+                let elapsed_seconds = (Utc::now() - *started_at).num_seconds().max(0) as u64;
+                match self.usage.get(pid).copied().flatten() {
+                    Some((cpu_percent, memory_bytes)) => ProcessResources {
+                        cpu_percent,
+                        memory_bytes,
+                        elapsed_seconds,
+                        stale: false,
+                    },
+                    None => ProcessResources {
+                        elapsed_seconds,
+                        stale: true,
+                        ..Default::default()
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn refresh(&mut self, pids: Vec<u32>) {
+        let sys_pids: Vec<Pid> = pids.iter().map(|pid| Pid::from_u32(*pid)).collect();
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&sys_pids),
+            true,
+            ProcessRefreshKind::new().with_cpu().with_memory(),
+        );
+        self.usage = pids
+            .into_iter()
+            .map(|pid| {
+                // An exited process that hasn't been reaped yet is as good as gone
+                let usage = self
+                    .system
+                    .process(Pid::from_u32(pid))
+                    .filter(|process| {
+                        !matches!(
+                            process.status(),
+                            ProcessStatus::Zombie | ProcessStatus::Dead
+                        )
+                    })
+                    .map(|process| (process.cpu_usage(), process.memory()));
+                (pid, usage)
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_processes_are_measured_and_exited_ones_are_stale() {
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+            .arg("--help")
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let exited_pid = child.id();
+        child.wait().unwrap();
+
+        let started_at = Utc::now() - chrono::Duration::seconds(30);
+        let mut sampler = ResourceSampler::default();
+        let sampled = sampler.sample(&[(std::process::id(), started_at), (exited_pid, started_at)]);
+        assert!(!sampled[0].stale);
+        assert!(sampled[0].memory_bytes > 0);
+        assert!(sampled[0].elapsed_seconds >= 30);
+        assert!(sampled[1].stale);
+        assert_eq!(sampled[1].memory_bytes, 0);
+
+        // A sample that covers every PID is reused
+        let sampled_at = sampler.sampled_at;
+        sampler.sample(&[(exited_pid, started_at)]);
+        assert_eq!(sampler.sampled_at, sampled_at);
+    }
+}
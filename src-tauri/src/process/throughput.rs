@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Window the rates are averaged over
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Shortest span a rate is averaged over, so the first message of a run
+/// doesn't read as a huge rate
+const MIN_RATE_SPAN: Duration = Duration::from_secs(10);
+
+/// Samples kept per run, bounding memory for very chatty runs
+const MAX_SAMPLES: usize = 1024;
+
+/// How fast a running session is producing output
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ThroughputMetrics {
+    pub tokens_per_second_1m: f64,
+    pub messages_per_minute: f64,
+    pub seconds_since_last_output: u64,
+    pub stalled: bool, // No output for longer than the stall threshold
+}
+
+/// Output samples of one run: a ring buffer of (time, output tokens) for each
+/// assistant message in the last minute, and when any output was last seen
+#[derive(Debug)]
+pub struct RunThroughput {
+    started_at: Instant,
+    last_output_at: Option<Instant>,
+    samples: VecDeque<(Instant, u64)>,
+    stall_reported: bool,
+}
+
+impl RunThroughput {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            started_at: now,
+            last_output_at: None,
+            samples: VecDeque::new(),
+            stall_reported: false,
+        }
+    }
+
+    /// Record a line of stream-json output. Only assistant messages count
+    /// towards the rates; system and tool lines just mark the run as alive.
+    pub fn record(&mut self, line: &JsonValue, now: Instant) {
+        self.last_output_at = Some(now);
+        self.stall_reported = false;
+
+        if line.get("type").and_then(|t| t.as_str()) != Some("assistant") {
+            return;
+        }
+        let output_tokens = line
+            .pointer("/message/usage/output_tokens")
+            .and_then(|t| t.as_u64())
+            .unwrap_or(0);
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((now, output_tokens));
+        self.prune(now);
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some((at, _)) = self.samples.front() {
+            if now.duration_since(*at) <= RATE_WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn metrics(&mut self, now: Instant, stall_after: Duration) -> ThroughputMetrics {
+        self.prune(now);
+
+        // Young runs are averaged over their lifetime rather than a full minute
+        let span = now
+            .duration_since(self.started_at)
+            .clamp(MIN_RATE_SPAN, RATE_WINDOW)
+            .as_secs_f64();
+        let tokens: u64 = self.samples.iter().map(|(_, tokens)| tokens).sum();
+        let quiet_for = now.duration_since(self.last_output_at.unwrap_or(self.started_at));
+
+        ThroughputMetrics {
+            tokens_per_second_1m: tokens as f64 / span,
+            messages_per_minute: self.samples.len() as f64 * 60.0 / span,
+            seconds_since_last_output: quiet_for.as_secs(),
+            stalled: quiet_for > stall_after,
+        }
+    }
+
+    /// Whether the run has stalled and this is the first time it's asked
+    /// since its last output
+    pub fn take_new_stall(&mut self, now: Instant, stall_after: Duration) -> bool {
+        if self.stall_reported || !self.metrics(now, stall_after).stalled {
+            return false;
+        }
+        self.stall_reported = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn assistant(tokens: u64) -> JsonValue {
+        json!({"type": "assistant", "message": {"usage": {"output_tokens": tokens}}})
+    }
+
+    #[test]
+    fn test_rates_ignore_system_burst_and_cover_the_last_minute() {
+        let start = Instant::now();
+        let mut throughput = RunThroughput::new(start);
+        let stall_after = Duration::from_secs(120);
+        assert_eq!(throughput.metrics(start, stall_after).tokens_per_second_1m, 0.0);
+
+        // The startup burst of system messages isn't throughput
+        for _ in 0..20 {
+            throughput.record(&json!({"type": "system", "subtype": "init"}), start);
+        }
+        throughput.record(&assistant(500), start + Duration::from_secs(1));
+        let metrics = throughput.metrics(start + Duration::from_secs(1), stall_after);
+        assert_eq!((metrics.tokens_per_second_1m, metrics.messages_per_minute), (50.0, 6.0));
+
+        throughput.record(&assistant(100), start + Duration::from_secs(90));
+        let metrics = throughput.metrics(start + Duration::from_secs(90), stall_after);
+        assert_eq!(metrics.tokens_per_second_1m, 100.0 / 60.0);
+        assert_eq!(metrics.messages_per_minute, 1.0);
+        assert!(!metrics.stalled);
+    }
+
+    #[test]
+    fn test_stalls_are_reported_once_until_output_resumes() {
+        let start = Instant::now();
+        let mut throughput = RunThroughput::new(start);
+        let stall_after = Duration::from_secs(30);
+        let later = start + Duration::from_secs(31);
+
+        let metrics = throughput.metrics(later, stall_after);
+        assert!(metrics.stalled);
+        assert_eq!(metrics.seconds_since_last_output, 31);
+        assert!(throughput.take_new_stall(later, stall_after));
+        assert!(!throughput.take_new_stall(later, stall_after));
+
+        throughput.record(&assistant(1), later);
+        assert!(!throughput.metrics(later, stall_after).stalled);
+        assert!(throughput.take_new_stall(later + Duration::from_secs(31), stall_after));
+    }
+}
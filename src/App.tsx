@@ -170,7 +170,7 @@ function AppContent() {
     try {
       setLoading(true);
       setError(null);
-      const sessionList = await api.getProjectSessions(project.id);
+      const { sessions: sessionList } = await api.getProjectSessions(project.id);
       setSessions(sessionList);
       setSelectedProject(project);
     } catch (err) {
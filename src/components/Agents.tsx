@@ -62,7 +62,7 @@ export const Agents: React.FC = () => {
 
   const loadRunningAgents = async () => {
     try {
-      const runs = await api.listAgentRunsWithMetrics();
+      const { runs } = await api.listAgentRunsWithMetrics();
       setRunningAgents(runs);
     } catch (error) {
       console.error('Failed to load running agents:', error);
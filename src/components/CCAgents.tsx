@@ -104,7 +104,7 @@ export const CCAgents: React.FC<CCAgentsProps> = ({ onBack, className }) => {
   const loadRuns = async () => {
     try {
       setRunsLoading(true);
-      const runsList = await api.listAgentRuns();
+      const { runs: runsList } = await api.listAgentRuns();
       setRuns(runsList);
     } catch (err) {
       console.error("Failed to load runs:", err);
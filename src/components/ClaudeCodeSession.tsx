@@ -113,6 +113,8 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
   const queuedPromptsRef = useRef<Array<{ id: string; prompt: string; model: "sonnet" | "opus" }>>([]);
   const isMountedRef = useRef(true);
   const isListeningRef = useRef(false);
+  // The session ID the running process's events are tagged with
+  const runningSessionIdRef = useRef<string | null>(null);
   const sessionStartTime = useRef<number>(Date.now());
   const isIMEComposingRef = useRef(false);
   
@@ -315,7 +317,7 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
       setIsLoading(true);
       setError(null);
       
-      const history = await api.loadSessionHistory(session.id, session.project_id);
+      const { messages: history } = await api.loadSessionHistory(session.id, session.project_id);
       
       // Save session data for restoration
       if (history && history.length > 0) {
@@ -408,6 +410,7 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
     
     // IMPORTANT: Set the session ID before setting up listeners
     setClaudeSessionId(sessionId);
+    runningSessionIdRef.current = sessionId;
     
     // Mark as listening
     isListeningRef.current = true;
@@ -497,53 +500,30 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
         // --------------------------------------------------------------------
         // 1️⃣  Event Listener Setup Strategy
         // --------------------------------------------------------------------
-        // Claude Code may emit a *new* session_id even when we pass --resume. If
-        // we listen only on the old session-scoped channel we will miss the
-        // stream until the user navigates away & back. To avoid this we:
-        //   • Always start with GENERIC listeners (no suffix) so we catch the
-        //     very first "system:init" message regardless of the session id.
-        //   • Once that init message provides the *actual* session_id, we
-        //     dynamically switch to session-scoped listeners and stop the
-        //     generic ones to prevent duplicate handling.
+        // Several sessions can run at once, so every run's events are tagged
+        // with the session ID it was launched under: the resumed session's ID,
+        // or for a new session an ID we choose here and pass to Claude. We
+        // listen on that ID before launching so no output is missed. Claude may
+        // still report a *new* session_id in its init message when resuming;
+        // that ID is what later prompts resume.
         // --------------------------------------------------------------------
 
-        console.log('[ClaudeCodeSession] Setting up generic event listeners first');
+        const resuming = !!effectiveSession && !isFirstPrompt;
+        const launchSessionId = resuming ? effectiveSession!.id : crypto.randomUUID();
+        runningSessionIdRef.current = launchSessionId;
+        console.log('[ClaudeCodeSession] Listening for events of session', launchSessionId);
 
         let currentSessionId: string | null = claudeSessionId || effectiveSession?.id || null;
 
-        // Helper to attach session-specific listeners **once we are sure**
-        const attachSessionSpecificListeners = async (sid: string) => {
-          console.log('[ClaudeCodeSession] Attaching session-specific listeners for', sid);
-
-          const specificOutputUnlisten = await listen<string>(`claude-output:${sid}`, (evt) => {
-            handleStreamMessage(evt.payload);
-          });
-
-          const specificErrorUnlisten = await listen<string>(`claude-error:${sid}`, (evt) => {
-            console.error('Claude error (scoped):', evt.payload);
-            setError(evt.payload);
-          });
-
-          const specificCompleteUnlisten = await listen<boolean>(`claude-complete:${sid}`, (evt) => {
-            console.log('[ClaudeCodeSession] Received claude-complete (scoped):', evt.payload);
-            processComplete(evt.payload);
-          });
-
-          // Replace existing unlisten refs with these new ones (after cleaning up)
-          unlistenRefs.current.forEach((u) => u());
-          unlistenRefs.current = [specificOutputUnlisten, specificErrorUnlisten, specificCompleteUnlisten];
-        };
-
-        // Generic listeners (catch-all)
-        const genericOutputUnlisten = await listen<string>('claude-output', async (event) => {
+        const outputUnlisten = await listen<string>(`claude-output:${launchSessionId}`, (event) => {
           handleStreamMessage(event.payload);
 
-          // Attempt to extract session_id on the fly (for the very first init)
+          // Pick up the session_id Claude reports in its init message
           try {
             const msg = JSON.parse(event.payload) as ClaudeStreamMessage;
             if (msg.type === 'system' && msg.subtype === 'init' && msg.session_id) {
               if (!currentSessionId || currentSessionId !== msg.session_id) {
-                console.log('[ClaudeCodeSession] Detected new session_id from generic listener:', msg.session_id);
+                console.log('[ClaudeCodeSession] Detected session_id from init message:', msg.session_id);
                 currentSessionId = msg.session_id;
                 setClaudeSessionId(msg.session_id);
 
@@ -560,9 +540,6 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
                     messages.length
                   );
                 }
-
-                // Switch to session-specific listeners
-                await attachSessionSpecificListeners(msg.session_id);
               }
             }
           } catch {
@@ -654,7 +631,7 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
           }
         }
 
-        // Helper to handle completion events (both generic and scoped)
+        // Helper to handle completion events
         const processComplete = async (success: boolean) => {
           setIsLoading(false);
           hasActiveSessionRef.current = false;
@@ -753,18 +730,17 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
           }
         };
 
-        const genericErrorUnlisten = await listen<string>('claude-error', (evt) => {
+        const errorUnlisten = await listen<string>(`claude-error:${launchSessionId}`, (evt) => {
           console.error('Claude error:', evt.payload);
           setError(evt.payload);
         });
 
-        const genericCompleteUnlisten = await listen<boolean>('claude-complete', (evt) => {
-          console.log('[ClaudeCodeSession] Received claude-complete (generic):', evt.payload);
+        const completeUnlisten = await listen<boolean>(`claude-complete:${launchSessionId}`, (evt) => {
+          console.log('[ClaudeCodeSession] Received claude-complete:', evt.payload);
           processComplete(evt.payload);
         });
 
-        // Store the generic unlisteners for now; they may be replaced later.
-        unlistenRefs.current = [genericOutputUnlisten, genericErrorUnlisten, genericCompleteUnlisten];
+        unlistenRefs.current = [outputUnlisten, errorUnlisten, completeUnlisten];
 
         // --------------------------------------------------------------------
         // 2️⃣  Auto-checkpoint logic moved after listener setup (unchanged)
@@ -825,24 +801,26 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
         });
 
         // Execute the appropriate command
-        if (effectiveSession && !isFirstPrompt) {
-          console.log('[ClaudeCodeSession] Resuming session:', effectiveSession.id);
-          trackEvent.sessionResumed(effectiveSession.id);
+        if (resuming) {
+          console.log('[ClaudeCodeSession] Resuming session:', launchSessionId);
+          trackEvent.sessionResumed(launchSessionId);
           trackEvent.modelSelected(model);
-          await api.resumeClaudeCode(projectPath, effectiveSession.id, prompt, model);
+          await api.resumeClaudeCode(projectPath, launchSessionId, prompt, model);
         } else {
-          console.log('[ClaudeCodeSession] Starting new session');
+          console.log('[ClaudeCodeSession] Starting new session:', launchSessionId);
           setIsFirstPrompt(false);
           trackEvent.sessionCreated(model, 'prompt_input');
           trackEvent.modelSelected(model);
-          await api.executeClaudeCode(projectPath, prompt, model);
+          await api.executeClaudeCode(projectPath, prompt, model, undefined, launchSessionId);
         }
       }
     } catch (err) {
       console.error("Failed to send prompt:", err);
-      setError("Failed to send prompt");
+      // Launch errors such as the session cap come back as strings
+      setError(typeof err === 'string' ? err : "Failed to send prompt");
       setIsLoading(false);
       hasActiveSessionRef.current = false;
+      isListeningRef.current = false;
     }
   };
 
@@ -937,13 +915,14 @@ export const ClaudeCodeSession: React.FC<ClaudeCodeSessionProps> = ({
   };
 
   const handleCancelExecution = async () => {
-    if (!claudeSessionId || !isLoading) return;
+    const runningSessionId = runningSessionIdRef.current ?? claudeSessionId;
+    if (!runningSessionId || !isLoading) return;
     
     try {
       const sessionStartTime = messages.length > 0 ? messages[0].timestamp || Date.now() : Date.now();
       const duration = Date.now() - sessionStartTime;
       
-      await api.cancelClaudeExecution(claudeSessionId);
+      await api.cancelClaudeExecution(runningSessionId);
       
       // Calculate metrics for enhanced analytics
       const metrics = sessionMetrics.current;
@@ -38,6 +38,7 @@ export const ClaudeFileEditor: React.FC<ClaudeFileEditorProps> = ({
 }) => {
   const [content, setContent] = useState<string>("");
   const [originalContent, setOriginalContent] = useState<string>("");
+  const [version, setVersion] = useState<string | null>(null);
   const [loading, setLoading] = useState(true);
   const [saving, setSaving] = useState(false);
   const [error, setError] = useState<string | null>(null);
@@ -54,9 +55,10 @@ export const ClaudeFileEditor: React.FC<ClaudeFileEditorProps> = ({
     try {
       setLoading(true);
       setError(null);
-      const fileContent = await api.readClaudeMdFile(file.absolute_path);
-      setContent(fileContent);
-      setOriginalContent(fileContent);
+      const loaded = await api.readClaudeMdFile(file.absolute_path);
+      setContent(loaded.content);
+      setOriginalContent(loaded.content);
+      setVersion(loaded.version);
     } catch (err) {
       console.error("Failed to load file:", err);
       setError("Failed to load CLAUDE.md file");
@@ -70,7 +72,24 @@ export const ClaudeFileEditor: React.FC<ClaudeFileEditorProps> = ({
       setSaving(true);
       setError(null);
       setToast(null);
-      await api.saveClaudeMdFile(file.absolute_path, content);
+      let result = await api.saveClaudeMdFile(file.absolute_path, content, version);
+      if (result.status === "conflict") {
+        const overwrite = window.confirm(
+          "This file was changed outside opcode since it was opened. Overwrite those changes?"
+        );
+        if (!overwrite) {
+          // Show what's on disk instead, dropping the edits made here
+          setContent(result.current.content);
+          setOriginalContent(result.current.content);
+          setVersion(result.current.version);
+          setToast({ message: "Loaded the file's current content", type: "error" });
+          return;
+        }
+        result = await api.saveClaudeMdFile(file.absolute_path, content, version, true);
+      }
+      if (result.status === "saved") {
+        setVersion(result.version);
+      }
       setOriginalContent(content);
       setToast({ message: "File saved successfully", type: "success" });
     } catch (err) {
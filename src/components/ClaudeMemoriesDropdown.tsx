@@ -125,6 +125,9 @@ export const ClaudeMemoriesDropdown: React.FC<ClaudeMemoriesDropdownProps> = ({
                         <div className="flex-1 min-w-0 mr-2">
                           <p className="text-xs font-mono truncate">{file.relative_path}</p>
                           <div className="flex items-center space-x-3 mt-1">
+                            <span className="text-xs text-muted-foreground capitalize">
+                              {file.scope}
+                            </span>
                             <span className="text-xs text-muted-foreground">
                               {formatFileSize(file.size)}
                             </span>
@@ -1,10 +1,16 @@
 import React, { useState, useEffect } from "react";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import { Button } from "@/components/ui/button";
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select";
 import { Badge } from "@/components/ui/badge";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
 import { Label } from "@/components/ui/label";
-import { api, type ClaudeInstallation } from "@/lib/api";
+import {
+  api,
+  type ClaudeInstallation,
+  type ClaudeInstallMethod,
+  type ClaudeInstallProgress,
+} from "@/lib/api";
 import { cn } from "@/lib/utils";
 import { CheckCircle, HardDrive, Settings, Terminal, Info } from "lucide-react";
 
@@ -62,11 +68,24 @@ export const ClaudeVersionSelector: React.FC<ClaudeVersionSelectorProps> = ({
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
   const [selectedInstallation, setSelectedInstallation] = useState<ClaudeInstallation | null>(null);
+  const [installing, setInstalling] = useState(false);
+  const [installProgress, setInstallProgress] = useState<ClaudeInstallProgress | null>(null);
 
   useEffect(() => {
     loadInstallations();
   }, []);
 
+  useEffect(() => {
+    // Stale versions are re-detected in the background
+    let unlisten: UnlistenFn | undefined;
+    listen<ClaudeInstallation[]>("claude-installations-updated", (event) => {
+      setInstallations(event.payload);
+    }).then((fn) => {
+      unlisten = fn;
+    });
+    return () => unlisten?.();
+  }, []);
+
   useEffect(() => {
     // Update selected installation when selectedPath changes
     if (selectedPath && installations.length > 0) {
@@ -77,11 +96,11 @@ export const ClaudeVersionSelector: React.FC<ClaudeVersionSelectorProps> = ({
     }
   }, [selectedPath, installations]);
 
-  const loadInstallations = async () => {
+  const loadInstallations = async (forceRefresh = false) => {
     try {
       setLoading(true);
       setError(null);
-      const foundInstallations = await api.listClaudeInstallations();
+      const foundInstallations = await api.listClaudeInstallations(forceRefresh);
       setInstallations(foundInstallations);
       
       // If we have a selected path, find and select it
@@ -103,6 +122,24 @@ export const ClaudeVersionSelector: React.FC<ClaudeVersionSelectorProps> = ({
     }
   };
 
+  const installClaude = async (method: ClaudeInstallMethod) => {
+    setInstalling(true);
+    setInstallProgress(null);
+    const unlisten = await listen<ClaudeInstallProgress>("claude-install-progress", (event) => {
+      setInstallProgress(event.payload);
+    });
+    try {
+      await api.installClaudeCli(method);
+      await loadInstallations();
+    } catch (err) {
+      // Installer failures carry its stderr
+      setError(err instanceof Error ? err.message : String(err));
+    } finally {
+      unlisten();
+      setInstalling(false);
+    }
+  };
+
   const handleInstallationChange = (installationPath: string) => {
     const installation = installations.find(i => i.path === installationPath);
     if (installation) {
@@ -160,15 +197,36 @@ export const ClaudeVersionSelector: React.FC<ClaudeVersionSelectorProps> = ({
   }
 
   if (error) {
+    // Nothing was found or listing failed, so offer to install Claude
+    const installActions = (
+      <div className="space-y-2">
+        <div className="flex flex-wrap gap-2">
+          <Button onClick={() => loadInstallations(true)} variant="outline" size="sm" disabled={installing}>
+            Retry
+          </Button>
+          <Button onClick={() => installClaude("script")} size="sm" disabled={installing}>
+            {installing ? "Installing..." : "Install Claude"}
+          </Button>
+          <Button onClick={() => installClaude("npm")} variant="outline" size="sm" disabled={installing}>
+            Install with npm
+          </Button>
+        </div>
+        {installing && installProgress && (
+          <p className="text-xs text-muted-foreground font-mono truncate">
+            {installProgress.percent != null && `${installProgress.percent}% · `}
+            {installProgress.message}
+          </p>
+        )}
+      </div>
+    );
+
     if (simplified) {
       return (
         <div className="space-y-2">
           <Label className="text-sm font-medium">Claude Installation</Label>
           <div className="p-3 border border-destructive/50 rounded-lg bg-destructive/10">
-            <p className="text-sm text-destructive mb-2">{error}</p>
-            <Button onClick={loadInstallations} variant="outline" size="sm">
-              Retry
-            </Button>
+            <p className="text-sm text-destructive mb-2 whitespace-pre-wrap">{error}</p>
+            {installActions}
           </div>
         </div>
       );
@@ -180,10 +238,8 @@ export const ClaudeVersionSelector: React.FC<ClaudeVersionSelectorProps> = ({
           <CardDescription>Error loading installations</CardDescription>
         </CardHeader>
         <CardContent>
-          <div className="text-sm text-destructive mb-4">{error}</div>
-          <Button onClick={loadInstallations} variant="outline" size="sm">
-            Retry
-          </Button>
+          <div className="text-sm text-destructive mb-4 whitespace-pre-wrap">{error}</div>
+          {installActions}
         </CardContent>
       </Card>
     );
@@ -232,13 +288,20 @@ export const ClaudeVersionSelector: React.FC<ClaudeVersionSelectorProps> = ({
             ) : (
               <>
                 {installations.map((installation) => (
-                  <SelectItem key={installation.path} value={installation.path} className="cursor-pointer hover:bg-accent focus:bg-accent">
+                  <SelectItem
+                    key={installation.path}
+                    value={installation.path}
+                    disabled={installation.installation_type === "Wsl" || installation.missing}
+                    className="cursor-pointer hover:bg-accent focus:bg-accent"
+                  >
                     <div className="flex items-center gap-2 py-1">
                       <Terminal className="h-3.5 w-3.5 text-muted-foreground" />
                       <div className="flex-1">
-                        <div className="font-mono text-sm">{installation.path}</div>
+                        <div className="font-mono text-sm">
+                          {installation.installation_type === "Bundled" ? "Bundled with opcode" : installation.path}
+                        </div>
                         <div className="flex items-center gap-2 text-xs text-muted-foreground">
-                          <span>{installation.version || "Unknown version"}</span>
+                          <span>{installation.missing ? "Missing" : installation.version || "Unknown version"}</span>
                           <span>•</span>
                           <span>{installation.source}</span>
                           <Badge variant={getInstallationTypeColor(installation)} className="text-xs ml-2">
@@ -359,6 +422,9 @@ export const ClaudeVersionSelector: React.FC<ClaudeVersionSelectorProps> = ({
               {selectedInstallation.version && (
                 <div><strong>Version:</strong> {selectedInstallation.version}</div>
               )}
+              {selectedInstallation.runtime && (
+                <div><strong>Runtime:</strong> {selectedInstallation.runtime}</div>
+              )}
             </div>
           </div>
         )}
@@ -133,7 +133,7 @@ export const FilePicker: React.FC<FilePickerProps> = React.memo(({
     setError(null);
     
     try {
-      const result = await api.listDirectoryContents(path);
+      const { entries: result } = await api.listDirectoryContents(path);
       globalDirectoryCache.set(cacheKey, result);
       setEntries(result);
     } catch (err) {
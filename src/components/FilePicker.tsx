@@ -262,7 +262,7 @@ export const FilePicker: React.FC<FilePickerProps> = ({
       }
       
       // Always fetch fresh data in background
-      const contents = await api.listDirectoryContents(path);
+      const { entries: contents } = await api.listDirectoryContents(path);
       console.log('[FilePicker] Loaded fresh contents:', contents.length, 'items');
       
       // Cache the results
@@ -66,7 +66,7 @@ export const GitHubAgentBrowser: React.FC<GitHubAgentBrowserProps> = ({
     try {
       setLoading(true);
       setError(null);
-      const agentFiles = await api.fetchGitHubAgents();
+      const { agents: agentFiles } = await api.fetchGitHubAgents();
       setAgents(agentFiles);
     } catch (err) {
       console.error("Failed to fetch GitHub agents:", err);
@@ -85,7 +85,7 @@ export const GitHubAgentBrowser: React.FC<GitHubAgentBrowserProps> = ({
     });
 
     try {
-      const agentData = await api.fetchGitHubAgentContent(file.download_url);
+      const agentData = await api.fetchGitHubAgentContent(file.path);
       setSelectedAgent({
         file,
         data: agentData,
@@ -115,7 +115,7 @@ export const GitHubAgentBrowser: React.FC<GitHubAgentBrowserProps> = ({
 
     try {
       setImporting(true);
-      await api.importAgentFromGitHub(selectedAgent.file.download_url);
+      await api.importAgentFromGitHub(selectedAgent.file.path);
       
       // Refresh existing agents list
       await fetchExistingAgents();
@@ -59,6 +59,11 @@ import {
   HookTemplate,
   COMMON_TOOL_MATCHERS,
   HOOK_TEMPLATES,
+  MATCHER_EVENTS,
+  DIRECT_EVENTS,
+  MatcherEvent,
+  DirectEvent,
+  directEventCommands,
 } from '@/types/hooks';
 
 interface HooksEditorProps {
@@ -105,9 +110,36 @@ const EVENT_INFO: Record<HookEvent, { label: string; description: string; icon:
     label: 'Subagent Stop',
     description: 'Runs when a Claude subagent (Task) finishes',
     icon: <Terminal className="h-4 w-4" />
+  },
+  UserPromptSubmit: {
+    label: 'Prompt Submit',
+    description: 'Runs when you submit a prompt, before Claude sees it',
+    icon: <FileText className="h-4 w-4" />
+  },
+  PreCompact: {
+    label: 'Pre Compact',
+    description: 'Runs before the conversation is compacted (matcher: manual or auto)',
+    icon: <Info className="h-4 w-4" />
+  },
+  SessionStart: {
+    label: 'Session Start',
+    description: 'Runs when a session starts (matcher: startup, resume, clear or compact)',
+    icon: <Clock className="h-4 w-4" />
+  },
+  SessionEnd: {
+    label: 'Session End',
+    description: 'Runs when a session ends',
+    icon: <Clock className="h-4 w-4" />
   }
 };
 
+type EditableHooks = Record<MatcherEvent, EditableHookMatcher[]> & Record<DirectEvent, EditableHookCommand[]>;
+
+const emptyEditableHooks = (): EditableHooks => ({
+  ...Object.fromEntries(MATCHER_EVENTS.map(event => [event, [] as EditableHookMatcher[]])),
+  ...Object.fromEntries(DIRECT_EVENTS.map(event => [event, [] as EditableHookCommand[]])),
+} as EditableHooks);
+
 export const HooksEditor: React.FC<HooksEditorProps> = ({
   projectPath,
   scope,
@@ -127,26 +159,14 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
   const [loadError, setLoadError] = useState<string | null>(null);
   const [hooks, setHooks] = useState<HooksConfiguration>({});
   
-  // Events with matchers (tool-related)
-  const matcherEvents = ['PreToolUse', 'PostToolUse'] as const;
-  // Events without matchers (non-tool-related)
-  const directEvents = ['Notification', 'Stop', 'SubagentStop'] as const;
+  // Events with matchers (tool names, compaction or session start triggers)
+  const matcherEvents = MATCHER_EVENTS;
+  // Events without matchers
+  const directEvents = DIRECT_EVENTS;
   
   // Convert hooks to editable format with IDs
-  const [editableHooks, setEditableHooks] = useState<{
-    PreToolUse: EditableHookMatcher[];
-    PostToolUse: EditableHookMatcher[];
-    Notification: EditableHookCommand[];
-    Stop: EditableHookCommand[];
-    SubagentStop: EditableHookCommand[];
-  }>(() => {
-    const result = {
-      PreToolUse: [],
-      PostToolUse: [],
-      Notification: [],
-      Stop: [],
-      SubagentStop: []
-    } as any;
+  const [editableHooks, setEditableHooks] = useState<EditableHooks>(() => {
+    const result = emptyEditableHooks();
     
     // Initialize matcher events
     matcherEvents.forEach(event => {
@@ -166,13 +186,10 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     // Initialize direct events
     directEvents.forEach(event => {
-      const commands = hooks?.[event] as HookCommand[] | undefined;
-      if (commands && Array.isArray(commands)) {
-        result[event] = commands.map(hook => ({
-          ...hook,
-          id: HooksManager.generateId()
-        }));
-      }
+      result[event] = directEventCommands(hooks?.[event]).map(hook => ({
+        ...hook,
+        id: HooksManager.generateId()
+      }));
     });
     
     return result;
@@ -210,13 +227,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     setHasUnsavedChanges(false); // Reset unsaved changes when hooks prop changes
     
     // Reinitialize editable hooks when hooks prop changes
-    const result = {
-      PreToolUse: [],
-      PostToolUse: [],
-      Notification: [],
-      Stop: [],
-      SubagentStop: []
-    } as any;
+    const result = emptyEditableHooks();
     
     // Initialize matcher events
     matcherEvents.forEach(event => {
@@ -236,13 +247,10 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     // Initialize direct events
     directEvents.forEach(event => {
-      const commands = hooks?.[event] as HookCommand[] | undefined;
-      if (commands && Array.isArray(commands)) {
-        result[event] = commands.map(hook => ({
-          ...hook,
-          id: HooksManager.generateId()
-        }));
-      }
+      result[event] = directEventCommands(hooks?.[event]).map(hook => ({
+        ...hook,
+        id: HooksManager.generateId()
+      }));
     });
     
     setEditableHooks(result);
@@ -279,7 +287,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
         directEvents.forEach(event => {
           const commands = editableHooks[event];
           if (commands.length > 0) {
-            newHooks[event] = commands.map(({ id, ...hook }) => hook);
+            newHooks[event] = [{ hooks: commands.map(({ id, ...hook }) => hook) }];
           }
         });
         
@@ -313,7 +321,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     directEvents.forEach(event => {
       const commands = editableHooks[event];
       if (commands.length > 0) {
-        newHooks[event] = commands.map(({ id, ...hook }) => hook);
+        newHooks[event] = [{ hooks: commands.map(({ id, ...hook }) => hook) }];
       }
     });
     
@@ -342,7 +350,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     setEditableHooks(prev => ({
       ...prev,
-      [event]: [...(prev[event as 'PreToolUse' | 'PostToolUse'] as EditableHookMatcher[]), newMatcher]
+      [event]: [...(prev[event as MatcherEvent] as EditableHookMatcher[]), newMatcher]
     }));
   };
   
@@ -358,7 +366,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     setEditableHooks(prev => ({
       ...prev,
-      [event]: [...(prev[event as 'Notification' | 'Stop' | 'SubagentStop'] as EditableHookCommand[]), newCommand]
+      [event]: [...(prev[event as DirectEvent] as EditableHookCommand[]), newCommand]
     }));
   };
 
@@ -367,7 +375,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     setEditableHooks(prev => ({
       ...prev,
-      [event]: (prev[event as 'PreToolUse' | 'PostToolUse'] as EditableHookMatcher[]).map(matcher =>
+      [event]: (prev[event as MatcherEvent] as EditableHookMatcher[]).map(matcher =>
         matcher.id === matcherId ? { ...matcher, ...updates } : matcher
       )
     }));
@@ -378,7 +386,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     setEditableHooks(prev => ({
       ...prev,
-      [event]: (prev[event as 'PreToolUse' | 'PostToolUse'] as EditableHookMatcher[]).filter(matcher => matcher.id !== matcherId)
+      [event]: (prev[event as MatcherEvent] as EditableHookMatcher[]).filter(matcher => matcher.id !== matcherId)
     }));
   };
   
@@ -387,7 +395,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     setEditableHooks(prev => ({
       ...prev,
-      [event]: (prev[event as 'Notification' | 'Stop' | 'SubagentStop'] as EditableHookCommand[]).map(cmd =>
+      [event]: (prev[event as DirectEvent] as EditableHookCommand[]).map(cmd =>
         cmd.id === commandId ? { ...cmd, ...updates } : cmd
       )
     }));
@@ -398,7 +406,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     setEditableHooks(prev => ({
       ...prev,
-      [event]: (prev[event as 'Notification' | 'Stop' | 'SubagentStop'] as EditableHookCommand[]).filter(cmd => cmd.id !== commandId)
+      [event]: (prev[event as DirectEvent] as EditableHookCommand[]).filter(cmd => cmd.id !== commandId)
     }));
   };
 
@@ -418,7 +426,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
       
       setEditableHooks(prev => ({
         ...prev,
-        [template.event]: [...(prev[template.event as 'PreToolUse' | 'PostToolUse'] as EditableHookMatcher[]), newMatcher]
+        [template.event]: [...(prev[template.event as MatcherEvent] as EditableHookMatcher[]), newMatcher]
       }));
     } else {
       // For direct events
@@ -430,7 +438,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
       
       setEditableHooks(prev => ({
         ...prev,
-        [template.event]: [...(prev[template.event as 'Notification' | 'Stop' | 'SubagentStop'] as EditableHookCommand[]), ...newCommands]
+        [template.event]: [...(prev[template.event as DirectEvent] as EditableHookCommand[]), ...newCommands]
       }));
     }
     
@@ -465,7 +473,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     setEditableHooks(prev => ({
       ...prev,
-      [event]: (prev[event as 'PreToolUse' | 'PostToolUse'] as EditableHookMatcher[]).map(matcher =>
+      [event]: (prev[event as MatcherEvent] as EditableHookMatcher[]).map(matcher =>
         matcher.id === matcherId
           ? { ...matcher, hooks: [...matcher.hooks, newCommand] }
           : matcher
@@ -483,7 +491,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     setEditableHooks(prev => ({
       ...prev,
-      [event]: (prev[event as 'PreToolUse' | 'PostToolUse'] as EditableHookMatcher[]).map(matcher =>
+      [event]: (prev[event as MatcherEvent] as EditableHookMatcher[]).map(matcher =>
         matcher.id === matcherId
           ? {
               ...matcher,
@@ -501,7 +509,7 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
     
     setEditableHooks(prev => ({
       ...prev,
-      [event]: (prev[event as 'PreToolUse' | 'PostToolUse'] as EditableHookMatcher[]).map(matcher =>
+      [event]: (prev[event as MatcherEvent] as EditableHookMatcher[]).map(matcher =>
         matcher.id === matcherId
           ? { ...matcher, hooks: matcher.hooks.filter(cmd => cmd.id !== commandId) }
           : matcher
@@ -824,8 +832,8 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
               {(Object.keys(EVENT_INFO) as HookEvent[]).map(event => {
                 const isMatcherEvent = matcherEvents.includes(event as any);
                 const count = isMatcherEvent 
-                  ? (editableHooks[event as 'PreToolUse' | 'PostToolUse'] as EditableHookMatcher[]).length
-                  : (editableHooks[event as 'Notification' | 'Stop' | 'SubagentStop'] as EditableHookCommand[]).length;
+                  ? (editableHooks[event as MatcherEvent] as EditableHookMatcher[]).length
+                  : (editableHooks[event as DirectEvent] as EditableHookCommand[]).length;
                 
                 return (
                   <TabsTrigger key={event} value={event} className="flex items-center gap-2">
@@ -844,8 +852,8 @@ export const HooksEditor: React.FC<HooksEditorProps> = ({
             {(Object.keys(EVENT_INFO) as HookEvent[]).map(event => {
               const isMatcherEvent = matcherEvents.includes(event as any);
               const items = isMatcherEvent 
-                ? (editableHooks[event as 'PreToolUse' | 'PostToolUse'] as EditableHookMatcher[])
-                : (editableHooks[event as 'Notification' | 'Stop' | 'SubagentStop'] as EditableHookCommand[]);
+                ? (editableHooks[event as MatcherEvent] as EditableHookMatcher[])
+                : (editableHooks[event as DirectEvent] as EditableHookCommand[]);
               
               return (
                 <TabsContent key={event} value={event} className="space-y-4">
@@ -1,10 +1,10 @@
 import React, { useState } from "react";
-import { Download, Upload, FileText, Loader2, Info, Network, Settings2 } from "lucide-react";
+import { Download, Upload, FileText, Loader2, Info, Network, Settings2, Code2 } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { Card } from "@/components/ui/card";
 import { Label } from "@/components/ui/label";
 import { SelectComponent } from "@/components/ui/select";
-import { api } from "@/lib/api";
+import { api, type MCPConflictPolicy, type MCPEditor } from "@/lib/api";
 
 interface MCPImportExportProps {
   /**
@@ -27,6 +27,9 @@ export const MCPImportExport: React.FC<MCPImportExportProps> = ({
   const [importingDesktop, setImportingDesktop] = useState(false);
   const [importingJson, setImportingJson] = useState(false);
   const [importScope, setImportScope] = useState("local");
+  const [importingEditor, setImportingEditor] = useState(false);
+  const [editor, setEditor] = useState<MCPEditor>("cursor");
+  const [conflictPolicy, setConflictPolicy] = useState<MCPConflictPolicy>("skip");
 
   /**
    * Imports servers from Claude Desktop
@@ -68,6 +71,31 @@ export const MCPImportExport: React.FC<MCPImportExportProps> = ({
     }
   };
 
+  /**
+   * Imports servers from Cursor, VS Code or Windsurf
+   */
+  const handleImportFromEditor = async () => {
+    try {
+      setImportingEditor(true);
+      const result = await api.mcpImportFromEditor(editor, importScope, conflictPolicy);
+      const imported = result.servers.filter(s => s.status === "imported");
+      const notImported = result.servers.filter(s => s.status !== "imported");
+      onImportCompleted(imported.length, notImported.length);
+
+      if (notImported.length > 0) {
+        const details = notImported
+          .map(s => `${s.name}: ${s.status.replace(/_/g, " ")}${s.message ? ` (${s.message})` : ""}`)
+          .join("\n");
+        onError(`Some servers weren't imported:\n${details}`);
+      }
+    } catch (error: any) {
+      console.error("Failed to import from editor:", error);
+      onError(error.toString() || "Failed to import from editor");
+    } finally {
+      setImportingEditor(false);
+    }
+  };
+
   /**
    * Handles JSON file import
    */
@@ -187,7 +215,7 @@ export const MCPImportExport: React.FC<MCPImportExportProps> = ({
               ]}
             />
             <p className="text-xs text-muted-foreground">
-              Choose where to save imported servers from JSON files
+              Choose where to save servers imported from JSON files and editors
             </p>
           </div>
         </Card>
@@ -226,6 +254,61 @@ export const MCPImportExport: React.FC<MCPImportExportProps> = ({
           </div>
         </Card>
 
+        {/* Import from an editor */}
+        <Card className="p-4 hover:bg-accent/5 transition-colors">
+          <div className="space-y-3">
+            <div className="flex items-start gap-3">
+              <div className="p-2.5 bg-orange-500/10 rounded-lg">
+                <Code2 className="h-5 w-5 text-orange-500" />
+              </div>
+              <div className="flex-1">
+                <h4 className="text-sm font-medium">Import from an Editor</h4>
+                <p className="text-xs text-muted-foreground mt-1">
+                  Imports the MCP servers configured in Cursor, VS Code or Windsurf, env vars included, into the import scope.
+                </p>
+              </div>
+            </div>
+            <div className="grid grid-cols-2 gap-2">
+              <SelectComponent
+                value={editor}
+                onValueChange={(value: string) => setEditor(value as MCPEditor)}
+                options={[
+                  { value: "cursor", label: "Cursor" },
+                  { value: "vscode", label: "VS Code" },
+                  { value: "windsurf", label: "Windsurf" },
+                ]}
+              />
+              <SelectComponent
+                value={conflictPolicy}
+                onValueChange={(value: string) => setConflictPolicy(value as MCPConflictPolicy)}
+                options={[
+                  { value: "skip", label: "Skip existing names" },
+                  { value: "overwrite", label: "Overwrite existing" },
+                  { value: "rename", label: "Rename imported" },
+                ]}
+              />
+            </div>
+            <Button
+              onClick={handleImportFromEditor}
+              disabled={importingEditor}
+              className="w-full gap-2"
+              variant="outline"
+            >
+              {importingEditor ? (
+                <>
+                  <Loader2 className="h-4 w-4 animate-spin" />
+                  Importing...
+                </>
+              ) : (
+                <>
+                  <Code2 className="h-4 w-4" />
+                  Import from Editor
+                </>
+              )}
+            </Button>
+          </div>
+        </Card>
+
         {/* Import from JSON */}
         <Card className="p-4 hover:bg-accent/5 transition-colors">
           <div className="space-y-3">
@@ -18,6 +18,7 @@ import {
 } from "lucide-react";
 import { Button } from "@/components/ui/button";
 import { Badge } from "@/components/ui/badge";
+import { Switch } from "@/components/ui/switch";
 import { api, type MCPServer } from "@/lib/api";
 import { useTrackEvent } from "@/hooks";
 
@@ -52,6 +53,7 @@ export const MCPServerList: React.FC<MCPServerListProps> = ({
 }) => {
   const [removingServer, setRemovingServer] = useState<string | null>(null);
   const [testingServer, setTestingServer] = useState<string | null>(null);
+  const [togglingServer, setTogglingServer] = useState<string | null>(null);
   const [expandedServers, setExpandedServers] = useState<Set<string>>(new Set());
   const [copiedServer, setCopiedServer] = useState<string | null>(null);
   const [connectedServers] = useState<string[]>([]);
@@ -121,6 +123,21 @@ export const MCPServerList: React.FC<MCPServerListProps> = ({
     }
   };
 
+  /**
+   * Enables or disables a server, keeping its config while disabled
+   */
+  const handleToggleServer = async (server: MCPServer, enabled: boolean) => {
+    try {
+      setTogglingServer(server.name);
+      await api.mcpSetEnabled(server.name, server.scope, enabled);
+      onRefresh();
+    } catch (error) {
+      console.error("Failed to toggle server:", error);
+    } finally {
+      setTogglingServer(null);
+    }
+  };
+
   /**
    * Tests connection to a server
    */
@@ -217,6 +234,11 @@ export const MCPServerList: React.FC<MCPServerListProps> = ({
                   {getTransportIcon(server.transport)}
                 </div>
                 <h4 className="font-medium truncate">{server.name}</h4>
+                {server.enabled === false && (
+                  <Badge variant="outline" className="flex-shrink-0 text-muted-foreground">
+                    Disabled
+                  </Badge>
+                )}
                 {server.status?.running && (
                   <Badge variant="outline" className="gap-1 flex-shrink-0 border-green-500/50 text-green-600 bg-green-500/10">
                     <CheckCircle className="h-3 w-3" />
@@ -257,12 +279,20 @@ export const MCPServerList: React.FC<MCPServerListProps> = ({
               )}
             </div>
             
+            <div className="flex items-center gap-2 flex-shrink-0">
+              <Switch
+                checked={server.enabled !== false}
+                onCheckedChange={(checked) => handleToggleServer(server, checked)}
+                disabled={togglingServer === server.name}
+                title={server.enabled === false ? "Enable server" : "Disable server"}
+              />
+            </div>
             <div className="flex items-center gap-2 opacity-0 group-hover:opacity-100 transition-opacity flex-shrink-0">
               <Button
                 variant="ghost"
                 size="sm"
                 onClick={() => handleTestConnection(server.name)}
-                disabled={testingServer === server.name}
+                disabled={testingServer === server.name || server.enabled === false}
                 className="hover:bg-green-500/10 hover:text-green-600"
               >
                 {testingServer === server.name ? (
@@ -29,6 +29,7 @@ export const MarkdownEditor: React.FC<MarkdownEditorProps> = ({
 }) => {
   const [content, setContent] = useState<string>("");
   const [originalContent, setOriginalContent] = useState<string>("");
+  const [version, setVersion] = useState<string | null>(null);
   const [loading, setLoading] = useState(true);
   const [saving, setSaving] = useState(false);
   const [error, setError] = useState<string | null>(null);
@@ -46,8 +47,9 @@ export const MarkdownEditor: React.FC<MarkdownEditorProps> = ({
       setLoading(true);
       setError(null);
       const prompt = await api.getSystemPrompt();
-      setContent(prompt);
-      setOriginalContent(prompt);
+      setContent(prompt.content);
+      setOriginalContent(prompt.content);
+      setVersion(prompt.version);
     } catch (err) {
       console.error("Failed to load system prompt:", err);
       setError("Failed to load CLAUDE.md file");
@@ -61,7 +63,24 @@ export const MarkdownEditor: React.FC<MarkdownEditorProps> = ({
       setSaving(true);
       setError(null);
       setToast(null);
-      await api.saveSystemPrompt(content);
+      let result = await api.saveSystemPrompt(content, version);
+      if (result.status === "conflict") {
+        const overwrite = window.confirm(
+          "CLAUDE.md was changed outside opcode since it was opened. Overwrite those changes?"
+        );
+        if (!overwrite) {
+          // Show what's on disk instead, dropping the edits made here
+          setContent(result.current.content);
+          setOriginalContent(result.current.content);
+          setVersion(result.current.version);
+          setToast({ message: "Loaded the current CLAUDE.md", type: "error" });
+          return;
+        }
+        result = await api.saveSystemPrompt(content, version, true);
+      }
+      if (result.status === "saved") {
+        setVersion(result.version);
+      }
       setOriginalContent(content);
       setToast({ message: "CLAUDE.md saved successfully", type: "success" });
     } catch (err) {
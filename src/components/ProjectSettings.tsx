@@ -47,8 +47,8 @@ export const ProjectSettings: React.FC<ProjectSettingsProps> = ({
     try {
       // Check if .claude/settings.local.json is in .gitignore
       const gitignorePath = `${project.path}/.gitignore`;
-      const gitignoreContent = await api.readClaudeMdFile(gitignorePath);
-      setGitIgnoreLocal(gitignoreContent.includes('.claude/settings.local.json'));
+      const gitignore = await api.readClaudeMdFile(gitignorePath);
+      setGitIgnoreLocal(gitignore.content.includes('.claude/settings.local.json'));
     } catch {
       // .gitignore might not exist
       setGitIgnoreLocal(false);
@@ -59,16 +59,21 @@ export const ProjectSettings: React.FC<ProjectSettingsProps> = ({
     try {
       const gitignorePath = `${project.path}/.gitignore`;
       let content = '';
+      let version: string | null = null;
       
       try {
-        content = await api.readClaudeMdFile(gitignorePath);
+        ({ content, version } = await api.readClaudeMdFile(gitignorePath));
       } catch {
         // File doesn't exist, create it
       }
       
       if (!content.includes('.claude/settings.local.json')) {
         content += '\n# Claude local settings (machine-specific)\n.claude/settings.local.json\n';
-        await api.saveClaudeMdFile(gitignorePath, content);
+        const result = await api.saveClaudeMdFile(gitignorePath, content, version);
+        if (result.status === 'conflict') {
+          setToast({ message: '.gitignore changed while updating it; try again', type: 'error' });
+          return;
+        }
         setGitIgnoreLocal(true);
         setToast({ message: 'Added to .gitignore', type: 'success' });
       }
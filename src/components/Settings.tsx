@@ -64,6 +64,7 @@ export const Settings: React.FC<SettingsProps> = ({
   const [loading, setLoading] = useState(true);
   const [saving, setSaving] = useState(false);
   const [error, setError] = useState<string | null>(null);
+  const [backupAvailable, setBackupAvailable] = useState(false);
   const [activeTab, setActiveTab] = useState("general");
   const [currentBinaryPath, setCurrentBinaryPath] = useState<string | null>(null);
   const [selectedInstallation, setSelectedInstallation] = useState<ClaudeInstallation | null>(null);
@@ -140,7 +141,12 @@ export const Settings: React.FC<SettingsProps> = ({
     try {
       setLoading(true);
       setError(null);
-      const loadedSettings = await api.getClaudeSettings();
+      setBackupAvailable(false);
+      const { data: loadedSettings, parse_errors, backup_available } = await api.getClaudeSettings();
+      if (parse_errors.length > 0) {
+        setError(`~/.claude/settings.json is not valid JSON: ${parse_errors.join("; ")}`);
+        setBackupAvailable(backup_available);
+      }
       
       // Ensure loadedSettings is an object
       if (!loadedSettings || typeof loadedSettings !== 'object') {
@@ -190,6 +196,20 @@ export const Settings: React.FC<SettingsProps> = ({
     }
   };
 
+  /**
+   * Replaces a broken settings file with its backup and reloads it
+   */
+  const restoreSettingsBackup = async () => {
+    try {
+      await api.restoreClaudeSettingsBackup();
+      await loadSettings();
+      setToast({ message: "Settings restored from backup", type: "success" });
+    } catch (err) {
+      console.error("Failed to restore settings backup:", err);
+      setToast({ message: `Failed to restore settings: ${err}`, type: "error" });
+    }
+  };
+
   /**
    * Saves the current settings
    */
@@ -214,8 +234,9 @@ export const Settings: React.FC<SettingsProps> = ({
         }, {} as Record<string, string>),
       };
 
-      await api.saveClaudeSettings(updatedSettings);
+      const { warnings } = await api.saveClaudeSettings(updatedSettings);
       setSettings(updatedSettings);
+      setBackupAvailable(false);
 
       // Save Claude binary path if changed
       if (binaryPathChanged && selectedInstallation) {
@@ -237,10 +258,15 @@ export const Settings: React.FC<SettingsProps> = ({
         setProxySettingsChanged(false);
       }
 
-      setToast({ message: "设置保存成功！", type: "success" });
+      setToast({
+        message: warnings.length > 0
+          ? `设置保存成功！Unknown settings kept: ${warnings.map(w => w.path).join(", ")}`
+          : "设置保存成功！",
+        type: "success",
+      });
     } catch (err) {
       console.error("Failed to save settings:", err);
-      setError("Failed to save settings.");
+      setError(`Failed to save settings: ${err}`);
       setToast({ message: "Failed to save settings", type: "error" });
     } finally {
       setSaving(false);
@@ -381,6 +407,11 @@ export const Settings: React.FC<SettingsProps> = ({
           >
             <AlertCircle className="h-4 w-4" />
             {error}
+            {backupAvailable && (
+              <Button size="sm" variant="outline" className="ml-auto" onClick={restoreSettingsBackup}>
+                Restore backup
+              </Button>
+            )}
           </motion.div>
         )}
       </AnimatePresence>
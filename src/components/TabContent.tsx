@@ -64,7 +64,7 @@ const TabPanel: React.FC<TabPanelProps> = ({ tab, isActive }) => {
     try {
       setLoading(true);
       setError(null);
-      const sessionList = await api.getProjectSessions(project.id);
+      const { sessions: sessionList } = await api.getProjectSessions(project.id);
       setSessions(sessionList);
       setSelectedProject(project);
       
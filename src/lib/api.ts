@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { HooksConfiguration } from '@/types/hooks';
+import type { HooksConfiguration, HookShell } from '@/types/hooks';
 
 /** Process type for tracking in ProcessRegistry */
 export type ProcessType = 
@@ -15,6 +15,73 @@ export interface ProcessInfo {
   project_path: string;
   task: string;
   model: string;
+  /** Binary a Claude session runs, which may be a fallback for the configured one */
+  claude_binary?: string | null;
+}
+
+/** What a running process is using */
+export interface ProcessResources {
+  /** Share of one core, so it can exceed 100 on multi-core machines */
+  cpu_percent: number;
+  memory_bytes: number;
+  elapsed_seconds: number;
+  /** The process exited after it was registered */
+  stale: boolean;
+}
+
+/** Roughly how many tokens a session's history takes up */
+export interface SessionTokenEstimate {
+  /** Output tokens Claude reported for its own messages */
+  exact_tokens: number;
+  /** Estimated from characters for entries without recorded usage */
+  estimated_tokens: number;
+  total: number;
+  breakdown_by_role: Record<string, number>;
+  chars_per_token: number;
+}
+
+/** Estimated token count of some text */
+export interface TextTokenEstimate {
+  estimated_tokens: number;
+  chars_per_token: number;
+}
+
+/** A session made by merging others */
+export interface MergedSession {
+  session_id: string;
+  /** Entries taken from each source session */
+  sources: { session_id: string; entries: number }[];
+  /** E.g. when the sessions ran in different directories */
+  warnings: string[];
+}
+
+/** A session after `/compact`. Claude may have continued it under a new ID. */
+export interface CompactedSession {
+  session_id: string;
+  summary: string;
+  /** Messages in the history from the summary on */
+  message_count: number;
+}
+
+/** Why `compactSession` failed */
+export type CompactError =
+  | { kind: 'unsupported_version'; detected_version: string; required_version: string }
+  | { kind: 'failed'; message: string };
+
+/** A range of a run's output file */
+export interface OutputChunk {
+  content: string;
+  offset: number;
+  /** Pass back as `offset` to continue reading */
+  next_offset: number;
+  total_bytes: number;
+}
+
+/** Part of a Claude session's live output */
+export interface SessionOutput extends OutputChunk {
+  /** With an offset, more output follows; without one, older output was left out */
+  truncated: boolean;
+  warning?: string | null;
 }
 
 /**
@@ -29,10 +96,20 @@ export interface Project {
   sessions: string[];
   /** Unix timestamp when the project directory was created */
   created_at: number;
-  /** Unix timestamp of the most recent session (if any) */
-  most_recent_session?: number;
+  /** Number of sessions in the project */
+  session_count: number;
+  /** Unix timestamp of the most recently modified session (if any) */
+  last_session_at?: number;
+  /** Combined size of the session files in bytes */
+  total_size_bytes: number;
+  /** Whether the project is archived in the app (hidden unless asked for) */
+  archived: boolean;
+  /** Whether the project's directory no longer exists on disk */
+  missing: boolean;
 }
 
+export type ProjectSort = "recent" | "name" | "sessions";
+
 /**
  * Represents a session with its metadata
  */
@@ -51,6 +128,56 @@ export interface Session {
   first_message?: string;
   /** Timestamp of the first user message (if available) */
   message_timestamp?: string;
+  /** Title given to the session in the app, if any */
+  custom_title?: string | null;
+  /** Whether the session is pinned; pinned sessions are listed first */
+  pinned?: boolean;
+}
+
+/**
+ * A session pinned as a favorite
+ */
+export interface PinnedSession {
+  project_id: string;
+  session_id: string;
+  pinned_at: string;
+  custom_title?: string | null;
+}
+
+/**
+ * A page of a project's sessions
+ */
+export interface SessionPage {
+  sessions: Session[];
+  /** Number of sessions in the project, across all pages */
+  total_count: number;
+}
+
+export type SessionEntryType = "user" | "assistant" | "summary";
+
+/**
+ * A range of a session's JSONL entries
+ */
+export interface SessionHistoryChunk {
+  messages: any[];
+  /** JSONL line of each message, as used for checkpoint message indices */
+  indices: number[];
+  /** Lines in the range that weren't valid JSON */
+  skipped_lines: number;
+  /** Line to continue from, or null at the end of the file */
+  next_index: number | null;
+}
+
+/**
+ * What deleting a session removed
+ */
+export interface DeletedSession {
+  /** Files and directories removed, as they were named before removal */
+  removed_paths: string[];
+  /** Where they were moved, when moved to the trash rather than deleted */
+  trash_path?: string | null;
+  /** Whether checkpoints of the session exist; they are left in place */
+  has_checkpoints: boolean;
 }
 
 /**
@@ -60,6 +187,197 @@ export interface ClaudeSettings {
   [key: string]: any;
 }
 
+/**
+ * Extra Claude CLI options for a session; omitted fields keep the defaults
+ */
+export interface ClaudeSessionOptions {
+  max_turns?: number;
+  /** Replaces skipping permission checks when set */
+  permission_mode?: 'default' | 'acceptEdits' | 'plan' | 'bypassPermissions';
+  /** Directories Claude may use besides the project */
+  additional_directories?: string[];
+  /** Passed through to the CLI; flags opcode sets itself are rejected */
+  extra_args?: string[];
+  /** Environment for this session, over the saved Claude environment */
+  env?: ClaudeEnvSettings;
+}
+
+/** Environment given to every Claude process, e.g. a corporate proxy */
+export interface ClaudeEnvSettings {
+  http_proxy?: string | null;
+  https_proxy?: string | null;
+  no_proxy?: string | null;
+  /** Any other variables; the proxy fields win over these */
+  extra_env?: Record<string, string>;
+}
+
+export interface TokenCounts {
+  input_tokens: number;
+  output_tokens: number;
+  cache_creation_tokens: number;
+  cache_read_tokens: number;
+}
+
+/**
+ * Usage of a streaming session so far, also emitted as `session-usage-updated`
+ */
+export interface SessionUsageUpdate {
+  session_id: string;
+  /** Tokens of the message that triggered the update; zero for a result */
+  turn_tokens: TokenCounts;
+  cumulative_tokens: TokenCounts;
+  estimated_cost_usd: number;
+}
+
+/**
+ * How a file attached to a prompt was included
+ */
+export interface IncludedAttachment {
+  path: string;
+  mime_type: string;
+  /** Images are referenced in place or copied into the project; text files are inlined */
+  method: "referenced" | "copied" | "inlined";
+  /** The path Claude was pointed at, for images */
+  referenced_path: string | null;
+}
+
+/**
+ * A started interactive session
+ */
+export interface ClaudeSessionLaunch {
+  session_id: string;
+  attachments: IncludedAttachment[];
+  /** Claude's complete stdout, for the `json` and `text` output formats */
+  output?: string | null;
+}
+
+/**
+ * A memory file as read, with the version to pass back when saving it
+ */
+export interface MemoryFileContent {
+  content: string;
+  /** Null when the file doesn't exist */
+  version: string | null;
+  modified: number | null;
+}
+
+/**
+ * The outcome of saving a memory file: saved, or left alone because it
+ * changed since it was read
+ */
+export type MemoryFileSave =
+  | { status: "saved"; version: string }
+  | { status: "conflict"; current: MemoryFileContent };
+
+/**
+ * A prompt waiting for its session's current turn to complete
+ */
+export interface QueuedPrompt {
+  prompt: string;
+  project_path: string;
+  model: string;
+  options?: ClaudeSessionOptions | null;
+}
+
+/**
+ * Preferences applied to sessions in one project
+ */
+export interface ProjectSettings {
+  /** Model for sessions started without one */
+  default_model?: string | null;
+  /** Claude binary used instead of the global one; set with `setProjectClaudeBinary` */
+  claude_binary?: string | null;
+}
+
+/**
+ * A Claude Code session started outside opcode
+ */
+export interface OpenedSession {
+  message: string;
+  /** The project the session belongs to, listed even before Claude writes to it */
+  project_id?: string | null;
+}
+
+/**
+ * What `claude -p` writes to stdout; only `stream-json` is streamed to the UI
+ */
+export type OutputFormat = "stream-json" | "json" | "text";
+
+/**
+ * A prompt submitted to a Claude session or as an agent task
+ */
+export interface PromptHistoryEntry {
+  id: number;
+  prompt: string;
+  project_path: string;
+  project_id: string;
+  session_id: string | null;
+  agent_task: boolean;
+  created_at: string;
+}
+
+export interface PromptHistorySettings {
+  /** Whether prompts are recorded at all */
+  enabled: boolean;
+  /** Entries kept; the oldest are pruned beyond it */
+  max_entries: number;
+}
+
+/**
+ * A problem found in settings, located by its JSON path
+ */
+export interface SettingsIssue {
+  path: string;
+  message: string;
+}
+
+/**
+ * The settings file as read from disk
+ */
+export interface ClaudeSettingsFile {
+  data: ClaudeSettings;
+  /** Why the file couldn't be parsed; empty when it's valid */
+  parse_errors: string[];
+  /** Whether a backup of the previous settings can be restored */
+  backup_available: boolean;
+}
+
+/**
+ * Outcome of saving the settings file
+ */
+export interface SettingsSaveResult {
+  /** Unknown keys that were saved anyway */
+  warnings: SettingsIssue[];
+  /** Where the previous settings were backed up */
+  backup_path?: string | null;
+}
+
+/**
+ * A settings file Claude Code reads, lowest precedence first
+ */
+export type SettingsScope = "user" | "project" | "local" | "managed";
+
+/**
+ * One settings file that took part in the merge
+ */
+export interface SettingsSource {
+  scope: SettingsScope;
+  path: string;
+  exists: boolean;
+  /** Why the file was left out of the merge; empty when it's valid */
+  parse_errors: string[];
+}
+
+/**
+ * The settings Claude Code ends up using for a project
+ */
+export interface EffectiveSettings {
+  merged: ClaudeSettings;
+  /** The file each value came from, keyed by JSON pointer, e.g. `/permissions/allow/0` */
+  provenance: Record<string, string>;
+  sources: SettingsSource[];
+}
+
 /**
  * Represents the Claude Code version status
  */
@@ -70,10 +388,34 @@ export interface ClaudeVersionStatus {
   version?: string;
   /** The full output from the command */
   output: string;
+  /** The version, parsed */
+  parsed?: ClaudeVersion;
+  /** The output was read but held no recognizable version */
+  parse_failed: boolean;
+  /** Oldest version supporting the flags opcode uses */
+  minimum_version: string;
+  /** The installed version is older than `minimum_version` */
+  below_minimum: boolean;
+  /** Latest version on npm, when checked */
+  latest_version?: string;
+  update_available: boolean;
 }
 
 /**
- * Represents a CLAUDE.md file found in the project
+ * A semantic version read from `claude --version`
+ */
+export interface ClaudeVersion {
+  major: number;
+  minor: number;
+  patch: number;
+  pre_release?: string;
+  build?: string;
+  /** The version as it appeared in the output */
+  raw: string;
+}
+
+/**
+ * Represents a memory file (CLAUDE.md or CLAUDE.local.md) Claude loads for the project
  */
 export interface ClaudeMdFile {
   /** Relative path from the project root */
@@ -84,6 +426,8 @@ export interface ClaudeMdFile {
   size: number;
   /** Last modified timestamp */
   modified: number;
+  /** The user's file, the project's, a local one or a parent directory's */
+  scope: "user" | "project" | "local" | "parent";
 }
 
 /**
@@ -97,18 +441,145 @@ export interface FileEntry {
   extension?: string;
 }
 
+/**
+ * How listDirectoryContents pages, filters and sorts; directories always come first
+ */
+export interface DirectoryListingOptions {
+  offset?: number;
+  /** Defaults to 1000 */
+  limit?: number;
+  sort_by?: "name" | "modified" | "size";
+  descending?: boolean;
+  /** Only files with these extensions, e.g. ["ts", ".tsx"]; directories are always listed */
+  extensions?: string[];
+  /** List hidden entries besides .claude */
+  include_hidden?: boolean;
+}
+
+/**
+ * A directory entry with its metadata
+ */
+export interface DirectoryEntry extends FileEntry {
+  /** Last modification, in seconds since the Unix epoch */
+  modified: number | null;
+  is_symlink: boolean;
+  is_hidden: boolean;
+}
+
+/**
+ * One page of a directory's entries
+ */
+export interface DirectoryListing {
+  entries: DirectoryEntry[];
+  /** Entries matching the filters across all pages */
+  total: number;
+  /** Offset of the next page, if there is one */
+  next_offset: number | null;
+  /** Entries left out because they couldn't be read */
+  skipped: number;
+}
+
+/**
+ * How searchFiles walks and matches; by default ignore files are honored,
+ * hidden entries skipped and names matched as substrings
+ */
+export interface FileSearchOptions {
+  /** Match the query's characters in order rather than as a substring */
+  fuzzy?: boolean;
+  /** Also search what .gitignore and .ignore files exclude */
+  include_ignored?: boolean;
+  include_hidden?: boolean;
+  /** Defaults to 100 */
+  max_results?: number;
+}
+
+/**
+ * A file or directory whose name matched a search
+ */
+export interface FileSearchResult extends FileEntry {
+  /** Higher is a better match */
+  score: number;
+  /** Matched [start, end) character ranges of the name */
+  matched_ranges: [number, number][];
+}
+
 /**
  * Represents a Claude installation found on the system
  */
 export interface ClaudeInstallation {
-  /** Full path to the Claude binary */
+  /** Full path to the Claude binary, or "bundled" for the one shipped with opcode */
   path: string;
   /** Version string if available */
   version?: string;
   /** Source of discovery (e.g., "nvm", "system", "homebrew", "which") */
   source: string;
-  /** Type of installation */
-  installation_type: "System" | "Custom";
+  /** Type of installation. WSL ones are listed but can't be selected. */
+  installation_type: "System" | "Custom" | "Wsl" | "Bundled";
+  /** Where the path leads once symlinks are followed */
+  resolved_path?: string;
+  /** Found by an earlier listing but gone from disk since */
+  missing?: boolean;
+  /** The Bun or Deno a shim runs under and its version, e.g. "bun 1.1.30" */
+  runtime?: string | null;
+}
+
+/**
+ * Whether a Claude binary can run, and what's wrong with it if not
+ */
+export type ClaudeBinaryHealth =
+  | { status: "ok"; version: string }
+  | { status: "missing"; path: string }
+  | { status: "not_executable"; path: string }
+  | { status: "interpreter_missing"; interpreter: string }
+  | { status: "version_unparseable"; output: string }
+  | { status: "auth_required"; message: string }
+  | { status: "failed"; message: string };
+
+/** How `installClaudeCli` installs the Claude CLI */
+export type ClaudeInstallMethod = "script" | "npm";
+
+/**
+ * Payload of the `claude-install-progress` event
+ */
+export interface ClaudeInstallProgress {
+  phase: "starting" | "downloading" | "installing" | "verifying" | "done" | "failed";
+  /** Percent complete, when the installer reports it */
+  percent: number | null;
+  /** The installer's output line or a description of the step */
+  message: string | null;
+}
+
+/**
+ * Directories put in front of PATH for finding Claude and the processes opcode starts
+ */
+export interface ExtraPathSettings {
+  entries: string[];
+  /** Entries that aren't an existing directory */
+  missing: string[];
+}
+
+/**
+ * The SHA-256 a Claude binary must have to be launched. When it doesn't match,
+ * launches fail with an error starting with `binary_checksum_mismatch`.
+ */
+export interface ClaudeBinaryPin {
+  path: string;
+  sha256: string;
+  pinned_at: string;
+}
+
+/**
+ * Payload of the `claude-binary-fallback` event, emitted when the configured
+ * Claude binary failed to spawn and another installation was run instead
+ */
+export interface ClaudeBinaryFallback {
+  failed_path: string;
+  /** Why the configured binary couldn't be spawned */
+  error: string;
+  used_path: string;
+  version: string;
+  /** Whether `used_path` was saved as the configured binary */
+  persisted: boolean;
 }
 
 // Agent API types
@@ -127,6 +598,7 @@ export interface Agent {
 export interface AgentExport {
   version: number;
   exported_at: string;
+  checksum?: string; // SHA-256 of the agent payload, from version 2
   agent: {
     name: string;
     icon: string;
@@ -134,6 +606,9 @@ export interface AgentExport {
     default_task?: string;
     model: string;
     hooks?: string;
+    enable_file_read: boolean;
+    enable_file_write: boolean;
+    enable_network: boolean;
   };
 }
 
@@ -145,6 +620,17 @@ export interface GitHubAgentFile {
   sha: string;
 }
 
+export type GitHubAgentContent = AgentExport & {
+  stale: boolean; // Cached copy served because GitHub couldn't be reached
+  fetched_at: string;
+};
+
+export interface GitHubAgentListing {
+  agents: GitHubAgentFile[];
+  stale: boolean; // Cached copy served because GitHub couldn't be reached
+  fetched_at: string;
+}
+
 export interface AgentRun {
   id?: number;
   agent_id: number;
@@ -159,6 +645,9 @@ export interface AgentRun {
   process_started_at?: string;
   created_at: string;
   completed_at?: string;
+  /** Binary the run was spawned with, and the version it reported */
+  claude_binary?: string | null;
+  claude_version?: string | null;
 }
 
 export interface AgentRunMetrics {
@@ -168,6 +657,13 @@ export interface AgentRunMetrics {
   message_count?: number;
 }
 
+export interface ThroughputMetrics {
+  tokens_per_second_1m: number;
+  messages_per_minute: number;
+  seconds_since_last_output: number;
+  stalled: boolean;
+}
+
 export interface AgentRunWithMetrics {
   id?: number;
   agent_id: number;
@@ -186,6 +682,28 @@ export interface AgentRunWithMetrics {
   completed_at?: string;
   metrics?: AgentRunMetrics;
   output?: string; // Real-time JSONL content
+  throughput?: ThroughputMetrics; // Only while the run is in progress
+}
+
+/**
+ * Filters and pagination for listing agent runs. Pass a page's
+ * next_before_id as before_id to fetch the page after it.
+ */
+export interface AgentRunQuery {
+  agent_id?: number;
+  starred_only?: boolean;
+  status?: string;
+  since?: string; // RFC 3339 or YYYY-MM-DD, inclusive
+  until?: string; // Inclusive, a bare date including that whole day
+  before_id?: number;
+  offset?: number;
+  limit?: number; // Defaults to 100, at most 1000
+}
+
+export interface AgentRunPage {
+  runs: AgentRunWithMetrics[];
+  total_count: number;
+  next_before_id?: number;
 }
 
 // Usage Dashboard types
@@ -357,6 +875,8 @@ export interface MCPServer {
   is_active: boolean;
   /** Server status */
   status: ServerStatus;
+  /** Disabled servers are kept by opcode but removed from Claude's config */
+  enabled: boolean;
 }
 
 /**
@@ -369,6 +889,61 @@ export interface ServerStatus {
   error?: string;
   /** Last checked timestamp */
   last_checked?: number;
+  /** The server is disabled, so it isn't connected to */
+  disabled?: boolean;
+  /** How long the last successful handshake took */
+  latency_ms?: number;
+  /** When the last failed check's output starts in the server's log, to pass as `since` to `mcpGetServerLogs` */
+  error_log_since?: string;
+}
+
+/**
+ * A line opcode captured from an MCP server's stderr
+ */
+export interface MCPLogLine {
+  timestamp: string;
+  line: string;
+}
+
+/**
+ * Payload of the `mcp-status-changed` event, emitted when a server starts or
+ * stops answering
+ */
+export interface MCPStatusChange {
+  name: string;
+  status: ServerStatus;
+}
+
+/**
+ * Why a server's connection test failed
+ */
+export type MCPErrorCategory =
+  | "config_error"
+  | "spawn_failure"
+  | "timeout"
+  | "protocol_error"
+  | "auth_failure"
+  | "connection_failure";
+
+/**
+ * Outcome of testing one server with `mcpTestAll`
+ */
+export interface MCPTestResult {
+  name: string;
+  ok: boolean;
+  latency_ms?: number;
+  error_category?: MCPErrorCategory;
+  error_detail?: string;
+}
+
+/**
+ * Payload of the `mcp-test-progress` event, emitted as each test of
+ * `mcpTestAll` finishes
+ */
+export interface MCPTestProgress {
+  result: MCPTestResult;
+  completed: number;
+  total: number;
 }
 
 /**
@@ -387,6 +962,21 @@ export interface MCPServerConfig {
   env: Record<string, string>;
 }
 
+/**
+ * File shape MCP servers are exported in: Claude Desktop's `mcpServers`,
+ * which only runs stdio servers, or a project's `.mcp.json`
+ */
+export type MCPExportFormat = "claude_desktop" | "mcp_json";
+
+/**
+ * Result of exporting MCP servers
+ */
+export interface MCPExportResult {
+  written_count: number;
+  /** Servers left out because the format can't represent them */
+  unsupported: string[];
+}
+
 /**
  * Represents a custom slash command
  */
@@ -444,6 +1034,46 @@ export interface ImportServerResult {
   error?: string;
 }
 
+/**
+ * Editor whose MCP servers can be imported
+ */
+export type MCPEditor = "cursor" | "vscode" | "windsurf";
+
+/**
+ * What to do when an imported server has the name of an existing one
+ */
+export type MCPConflictPolicy = "skip" | "overwrite" | "rename";
+
+/**
+ * How importing one server from an editor went
+ */
+export type MCPImportStatus =
+  | "imported"
+  | "skipped_duplicate"
+  | "unsupported_transport"
+  | "parse_error"
+  | "failed";
+
+/**
+ * Result for one server imported from an editor
+ */
+export interface EditorImportServerResult {
+  /** The server's name in the editor */
+  name: string;
+  /** The name it was added under, which differs when renamed */
+  imported_as?: string;
+  status: MCPImportStatus;
+  message?: string;
+}
+
+/**
+ * Result of importing an editor's MCP servers
+ */
+export interface EditorImportResult {
+  config_path: string;
+  servers: EditorImportServerResult[];
+}
+
 /**
  * API client for interacting with the Rust backend
  */
@@ -463,17 +1093,40 @@ export const api = {
 
   /**
    * Lists all projects in the ~/.claude/projects directory
+   * @param includeArchived - Also return archived projects (default false)
+   * @param sortBy - Sort order: "recent" (default), "name" or "sessions"
+   * @param limit - Maximum number of projects to return
+   * @param offset - Number of projects to skip
    * @returns Promise resolving to an array of projects
    */
-  async listProjects(): Promise<Project[]> {
+  async listProjects(
+    includeArchived?: boolean,
+    sortBy?: ProjectSort,
+    limit?: number,
+    offset?: number
+  ): Promise<Project[]> {
     try {
-      return await invoke<Project[]>("list_projects");
+      return await invoke<Project[]>("list_projects", { includeArchived, sortBy, limit, offset });
     } catch (error) {
       console.error("Failed to list projects:", error);
       throw error;
     }
   },
 
+  /**
+   * Archives or unarchives a project. Only the app's preference changes; ~/.claude is untouched.
+   * @param projectId - The ID of the project
+   * @param archived - Whether the project should be archived
+   */
+  async setProjectArchived(projectId: string, archived: boolean): Promise<void> {
+    try {
+      await invoke("set_project_archived", { projectId, archived });
+    } catch (error) {
+      console.error("Failed to set project archived:", error);
+      throw error;
+    }
+  },
+
   /**
    * Creates a new project for the given directory path
    * @param path - The directory path to create a project for
@@ -489,26 +1142,74 @@ export const api = {
   },
 
   /**
-   * Retrieves sessions for a specific project
+   * Pins or unpins a session so it is listed first in its project
+   * @param projectId - The ID of the project
+   * @param sessionId - The ID of the session
+   * @param pinned - Whether the session should be pinned
+   */
+  async setSessionPinned(projectId: string, sessionId: string, pinned: boolean): Promise<void> {
+    try {
+      await invoke("set_session_pinned", { projectId, sessionId, pinned });
+    } catch (error) {
+      console.error("Failed to set session pinned:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Lists the pinned sessions of all projects, most recently pinned first
+   */
+  async listPinnedSessions(): Promise<PinnedSession[]> {
+    try {
+      return await invoke<PinnedSession[]>("list_pinned_sessions");
+    } catch (error) {
+      console.error("Failed to list pinned sessions:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Retrieves a page of a project's sessions, newest first
    * @param projectId - The ID of the project to retrieve sessions for
-   * @returns Promise resolving to an array of sessions
+   * @param limit - Maximum number of sessions to return
+   * @param offset - Number of sessions to skip
+   * @returns Promise resolving to the sessions and the project's total session count
    */
-  async getProjectSessions(projectId: string): Promise<Session[]> {
+  async getProjectSessions(projectId: string, limit?: number, offset?: number): Promise<SessionPage> {
     try {
-      return await invoke<Session[]>('get_project_sessions', { projectId });
+      return await invoke<SessionPage>('get_project_sessions', { projectId, limit, offset });
     } catch (error) {
       console.error("Failed to get project sessions:", error);
       throw error;
     }
   },
 
+  /**
+   * Deletes a session's JSONL file and its todo data
+   * @param projectId - The ID of the project the session belongs to
+   * @param sessionId - The session to delete
+   * @param moveToTrash - Move it to ~/.claude/.opcode-trash/ for 30 days instead of deleting it (default true)
+   * @returns Promise resolving to what was removed and whether checkpoints of the session remain
+   */
+  async deleteSession(projectId: string, sessionId: string, moveToTrash?: boolean): Promise<DeletedSession> {
+    try {
+      return await invoke<DeletedSession>('delete_session', { projectId, sessionId, moveToTrash });
+    } catch (error) {
+      console.error("Failed to delete session:", error);
+      throw error;
+    }
+  },
+
   /**
    * Fetch list of agents from GitHub repository
-   * @returns Promise resolving to list of available agents on GitHub
+   * @param gitRef - Optional branch, tag or commit to read from
+   * @param repo - Optional owner/name repository, defaults to the opcode repository
+   * @param forceRefresh - Skip the cached listing and ask GitHub again
+   * @returns Promise resolving to the agents available on GitHub, marked stale when served offline
    */
-  async fetchGitHubAgents(): Promise<GitHubAgentFile[]> {
+  async fetchGitHubAgents(gitRef?: string, repo?: string, forceRefresh?: boolean): Promise<GitHubAgentListing> {
     try {
-      return await invoke<GitHubAgentFile[]>('fetch_github_agents');
+      return await invoke<GitHubAgentListing>('fetch_github_agents', { gitRef, repo, forceRefresh });
     } catch (error) {
       console.error("Failed to fetch GitHub agents:", error);
       throw error;
@@ -517,63 +1218,113 @@ export const api = {
 
   /**
    * Fetch and preview a specific agent from GitHub
-   * @param downloadUrl - The download URL for the agent file
-   * @returns Promise resolving to the agent export data
+   * @param path - The agent file's path in the repository
+   * @param gitRef - Optional branch, tag or commit to read from
+   * @param repo - Optional owner/name repository
+   * @param forceRefresh - Skip the cached file and ask GitHub again
+   * @returns Promise resolving to the agent export data, marked stale when served offline
    */
-  async fetchGitHubAgentContent(downloadUrl: string): Promise<AgentExport> {
+  async fetchGitHubAgentContent(
+    path: string,
+    gitRef?: string,
+    repo?: string,
+    forceRefresh?: boolean
+  ): Promise<GitHubAgentContent> {
     try {
-      return await invoke<AgentExport>('fetch_github_agent_content', { downloadUrl });
+      return await invoke<GitHubAgentContent>('fetch_github_agent_content', { path, gitRef, repo, forceRefresh });
     } catch (error) {
       console.error("Failed to fetch GitHub agent content:", error);
       throw error;
     }
   },
 
+  /**
+   * Remove every cached GitHub agent listing and file
+   * @returns Promise resolving to the number of cached responses removed
+   */
+  async clearGitHubCache(): Promise<number> {
+    return invoke<number>('clear_github_cache');
+  },
+
   /**
    * Import an agent directly from GitHub
-   * @param downloadUrl - The download URL for the agent file
+   * @param path - The agent file's path in the repository
+   * @param gitRef - Optional branch, tag or commit to read from
+   * @param repo - Optional owner/name repository
    * @returns Promise resolving to the imported agent
    */
-  async importAgentFromGitHub(downloadUrl: string): Promise<Agent> {
+  async importAgentFromGitHub(path: string, gitRef?: string, repo?: string): Promise<Agent> {
     try {
-      return await invoke<Agent>('import_agent_from_github', { downloadUrl });
+      return await invoke<Agent>('import_agent_from_github', { path, gitRef, repo });
     } catch (error) {
       console.error("Failed to import agent from GitHub:", error);
       throw error;
     }
   },
 
+  /**
+   * Save the GitHub token used for agent imports in the system keychain
+   * @param token - Personal access token; an empty string removes it
+   * @returns Promise resolving when the token is saved
+   */
+  async setGitHubToken(token: string): Promise<void> {
+    try {
+      return await invoke<void>('set_github_token', { token });
+    } catch (error) {
+      console.error("Failed to save GitHub token:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Check whether a GitHub token is saved
+   * @returns Promise resolving to true when a token is saved
+   */
+  async hasGitHubToken(): Promise<boolean> {
+    try {
+      return await invoke<boolean>('has_github_token');
+    } catch (error) {
+      console.error("Failed to check GitHub token:", error);
+      throw error;
+    }
+  },
+
   /**
    * Reads the Claude settings file
-   * @returns Promise resolving to the settings object
+   * @returns Promise resolving to the settings, with parse errors when the file is broken
    */
-  async getClaudeSettings(): Promise<ClaudeSettings> {
+  async getClaudeSettings(): Promise<ClaudeSettingsFile> {
     try {
-      const result = await invoke<{ data: ClaudeSettings }>("get_claude_settings");
-      console.log("Raw result from get_claude_settings:", result);
-      
-      // The Rust backend returns ClaudeSettings { data: ... }
-      // We need to extract the data field
-      if (result && typeof result === 'object' && 'data' in result) {
-        return result.data;
-      }
-      
-      // If the result is already the settings object, return it
-      return result as ClaudeSettings;
+      return await invoke<ClaudeSettingsFile>("get_claude_settings");
     } catch (error) {
       console.error("Failed to get Claude settings:", error);
       throw error;
     }
   },
 
+  /**
+   * Replaces a broken settings file with its backup
+   * @returns Promise resolving to the restored settings
+   */
+  async restoreClaudeSettingsBackup(): Promise<ClaudeSettingsFile> {
+    try {
+      return await invoke<ClaudeSettingsFile>("restore_claude_settings_backup");
+    } catch (error) {
+      console.error("Failed to restore Claude settings backup:", error);
+      throw error;
+    }
+  },
+
   /**
    * Opens a new Claude Code session
    * @param path - Optional path to open the session in
-   * @returns Promise resolving when the session is opened
+   * @param model - The model to use; the project's default when omitted
+   * @param rememberModel - Make `model` the project's default
+   * @returns Promise resolving to the project the session belongs to, listed even before Claude writes to it
    */
-  async openNewSession(path?: string): Promise<string> {
+  async openNewSession(path?: string, model?: string, rememberModel?: boolean): Promise<OpenedSession> {
     try {
-      return await invoke<string>("open_new_session", { path });
+      return await invoke<OpenedSession>("open_new_session", { path, model, rememberModel });
     } catch (error) {
       console.error("Failed to open new session:", error);
       throw error;
@@ -582,11 +1333,11 @@ export const api = {
 
   /**
    * Reads the CLAUDE.md system prompt file
-   * @returns Promise resolving to the system prompt content
+   * @returns Promise resolving to the system prompt content and its version
    */
-  async getSystemPrompt(): Promise<string> {
+  async getSystemPrompt(): Promise<MemoryFileContent> {
     try {
-      return await invoke<string>("get_system_prompt");
+      return await invoke<MemoryFileContent>("get_system_prompt");
     } catch (error) {
       console.error("Failed to get system prompt:", error);
       throw error;
@@ -595,11 +1346,12 @@ export const api = {
 
   /**
    * Checks if Claude Code is installed and gets its version
+   * @param forceRefresh - Run `--version` again instead of using the cached result
    * @returns Promise resolving to the version status
    */
-  async checkClaudeVersion(): Promise<ClaudeVersionStatus> {
+  async checkClaudeVersion(checkLatest?: boolean, forceRefresh?: boolean): Promise<ClaudeVersionStatus> {
     try {
-      return await invoke<ClaudeVersionStatus>("check_claude_version");
+      return await invoke<ClaudeVersionStatus>("check_claude_version", { checkLatest, forceRefresh });
     } catch (error) {
       console.error("Failed to check Claude version:", error);
       throw error;
@@ -607,41 +1359,254 @@ export const api = {
   },
 
   /**
-   * Saves the CLAUDE.md system prompt file
-   * @param content - The new content for the system prompt
-   * @returns Promise resolving when the file is saved
+   * Saves the CLAUDE.md system prompt file unless it changed since it was read
+   * @param content - The new content for the system prompt
+   * @param version - The version it was read at
+   * @param force - Save even if the file changed since
+   * @returns Promise resolving to the new version, or the current content on a conflict
+   */
+  async saveSystemPrompt(content: string, version: string | null, force?: boolean): Promise<MemoryFileSave> {
+    try {
+      return await invoke<MemoryFileSave>("save_system_prompt", { content, version, force });
+    } catch (error) {
+      console.error("Failed to save system prompt:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the system prompt appended to Claude sessions in a project
+   * @param projectPath - The project directory
+   * @returns Promise resolving to the prompt, or an empty string when none is set
+   */
+  async getProjectSystemPrompt(projectPath: string): Promise<string> {
+    try {
+      return await invoke<string>("get_project_system_prompt", { projectPath });
+    } catch (error) {
+      console.error("Failed to get project system prompt:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets the system prompt appended to Claude sessions in a project
+   * @param projectPath - The project directory
+   * @param prompt - The prompt; an empty string clears it
+   */
+  async saveProjectSystemPrompt(projectPath: string, prompt: string): Promise<void> {
+    try {
+      await invoke("save_project_system_prompt", { projectPath, prompt });
+    } catch (error) {
+      console.error("Failed to save project system prompt:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the preferences applied to sessions in a project
+   * @param projectPath - The project directory
+   * @returns Promise resolving to the project's settings
+   */
+  async getProjectSettings(projectPath: string): Promise<ProjectSettings> {
+    try {
+      return await invoke<ProjectSettings>("get_project_settings", { projectPath });
+    } catch (error) {
+      console.error("Failed to get project settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Replaces the preferences applied to sessions in a project
+   * @param projectPath - The project directory
+   * @param settings - The new settings
+   * @returns Promise resolving to the settings as stored
+   */
+  async setProjectSettings(projectPath: string, settings: ProjectSettings): Promise<ProjectSettings> {
+    try {
+      return await invoke<ProjectSettings>("set_project_settings", { projectPath, settings });
+    } catch (error) {
+      console.error("Failed to save project settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Runs a project's sessions and agents with another Claude binary, e.g. a
+   * pinned older CLI. The binary must report its version to be accepted.
+   * @param projectPath - The project directory
+   * @param binaryPath - The binary, or null to use the global one again
+   * @returns Promise resolving to the project's settings as stored
+   */
+  async setProjectClaudeBinary(projectPath: string, binaryPath: string | null): Promise<ProjectSettings> {
+    try {
+      return await invoke<ProjectSettings>("set_project_claude_binary", { projectPath, binaryPath });
+    } catch (error) {
+      console.error("Failed to set the project's Claude binary:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Generates a short title for a session from its first messages and stores it
+   * @param projectId - The project the session belongs to
+   * @param sessionId - The session to title
+   * @returns Promise resolving to the stored title
+   */
+  async generateSessionTitle(projectId: string, sessionId: string): Promise<string> {
+    try {
+      return await invoke<string>("generate_session_title", { projectId, sessionId });
+    } catch (error) {
+      console.error("Failed to generate session title:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets whether new sessions are titled automatically after Claude's second answer.
+   * Titles are announced with the `session-title-updated` event.
+   * @returns Promise resolving to whether auto-titling is on
+   */
+  async getAutoTitleSessions(): Promise<boolean> {
+    try {
+      return await invoke<boolean>("get_auto_title_sessions");
+    } catch (error) {
+      console.error("Failed to get auto-title setting:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Turns automatic session titles on or off
+   * @param enabled - Whether to title new sessions automatically
+   */
+  async setAutoTitleSessions(enabled: boolean): Promise<void> {
+    try {
+      await invoke("set_auto_title_sessions", { enabled });
+    } catch (error) {
+      console.error("Failed to save auto-title setting:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets whether ~/.claude/projects is watched for sessions run outside the app.
+   * Changes are announced with the `projects-changed` and `session-updated` events.
+   * @returns Promise resolving to whether the watcher is enabled
+   */
+  async getFsWatcherEnabled(): Promise<boolean> {
+    try {
+      return await invoke<boolean>("get_fs_watcher_enabled");
+    } catch (error) {
+      console.error("Failed to get filesystem watcher setting:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Turns watching ~/.claude/projects on or off, e.g. for network filesystems
+   * @param enabled - Whether to watch for changes
+   */
+  async setFsWatcherEnabled(enabled: boolean): Promise<void> {
+    try {
+      await invoke("set_fs_watcher_enabled", { enabled });
+    } catch (error) {
+      console.error("Failed to save filesystem watcher setting:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the environment given to every Claude process opcode starts
+   * @returns Promise resolving to the saved proxy and extra variables
+   */
+  async getClaudeEnvSettings(): Promise<ClaudeEnvSettings> {
+    try {
+      return await invoke<ClaudeEnvSettings>("get_claude_env_settings");
+    } catch (error) {
+      console.error("Failed to get Claude environment settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the environment given to every Claude process, from the next one on
+   * @param settings - Proxies and extra variables
+   */
+  async setClaudeEnvSettings(settings: ClaudeEnvSettings): Promise<void> {
+    try {
+      await invoke("set_claude_env_settings", { settings });
+    } catch (error) {
+      console.error("Failed to save Claude environment settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the Claude settings file
+   * @param settings - The settings object to save
+   * @param strict - Reject keys Claude doesn't know instead of warning about them
+   * @returns Promise resolving to warnings about unknown keys
+   */
+  async saveClaudeSettings(settings: ClaudeSettings, strict?: boolean): Promise<SettingsSaveResult> {
+    try {
+      return await invoke<SettingsSaveResult>("save_claude_settings", { settings, strict });
+    } catch (error) {
+      console.error("Failed to save Claude settings:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Merges user, project, local and managed settings the way Claude Code does
+   * @param projectPath - The absolute path to the project
+   * @returns Promise resolving to the merged settings and which file each value came from
    */
-  async saveSystemPrompt(content: string): Promise<string> {
+  async getEffectiveClaudeSettings(projectPath: string): Promise<EffectiveSettings> {
     try {
-      return await invoke<string>("save_system_prompt", { content });
+      return await invoke<EffectiveSettings>("get_effective_claude_settings", { projectPath });
     } catch (error) {
-      console.error("Failed to save system prompt:", error);
+      console.error("Failed to get effective Claude settings:", error);
       throw error;
     }
   },
 
   /**
-   * Saves the Claude settings file
+   * Saves settings to the user, project or local settings file of a project
+   * @param projectPath - The absolute path to the project
+   * @param scope - Which settings file to write
    * @param settings - The settings object to save
-   * @returns Promise resolving when the settings are saved
+   * @param strict - Reject keys Claude doesn't know instead of warning about them
+   * @returns Promise resolving to warnings about unknown keys
    */
-  async saveClaudeSettings(settings: ClaudeSettings): Promise<string> {
+  async saveClaudeSettingsScoped(
+    projectPath: string,
+    scope: Exclude<SettingsScope, "managed">,
+    settings: ClaudeSettings,
+    strict?: boolean
+  ): Promise<SettingsSaveResult> {
     try {
-      return await invoke<string>("save_claude_settings", { settings });
+      return await invoke<SettingsSaveResult>("save_claude_settings_scoped", {
+        projectPath,
+        scope,
+        settings,
+        strict,
+      });
     } catch (error) {
-      console.error("Failed to save Claude settings:", error);
+      console.error("Failed to save scoped Claude settings:", error);
       throw error;
     }
   },
 
   /**
-   * Finds all CLAUDE.md files in a project directory
+   * Finds the memory files Claude loads for a project: the user's, parent directories' and the project's own
    * @param projectPath - The absolute path to the project
+   * @param maxDepth - How many directory levels below the project to search (default unlimited)
    * @returns Promise resolving to an array of CLAUDE.md files
    */
-  async findClaudeMdFiles(projectPath: string): Promise<ClaudeMdFile[]> {
+  async findClaudeMdFiles(projectPath: string, maxDepth?: number): Promise<ClaudeMdFile[]> {
     try {
-      return await invoke<ClaudeMdFile[]>("find_claude_md_files", { projectPath });
+      return await invoke<ClaudeMdFile[]>("find_claude_md_files", { projectPath, maxDepth });
     } catch (error) {
       console.error("Failed to find CLAUDE.md files:", error);
       throw error;
@@ -651,11 +1616,11 @@ export const api = {
   /**
    * Reads a specific CLAUDE.md file
    * @param filePath - The absolute path to the file
-   * @returns Promise resolving to the file content
+   * @returns Promise resolving to the file content and its version
    */
-  async readClaudeMdFile(filePath: string): Promise<string> {
+  async readClaudeMdFile(filePath: string): Promise<MemoryFileContent> {
     try {
-      return await invoke<string>("read_claude_md_file", { filePath });
+      return await invoke<MemoryFileContent>("read_claude_md_file", { filePath });
     } catch (error) {
       console.error("Failed to read CLAUDE.md file:", error);
       throw error;
@@ -663,14 +1628,16 @@ export const api = {
   },
 
   /**
-   * Saves a specific CLAUDE.md file
+   * Saves a specific CLAUDE.md file unless it changed since it was read
    * @param filePath - The absolute path to the file
    * @param content - The new content for the file
-   * @returns Promise resolving when the file is saved
+   * @param version - The version it was read at, or null if it didn't exist
+   * @param force - Save even if the file changed since
+   * @returns Promise resolving to the new version, or the current content on a conflict
    */
-  async saveClaudeMdFile(filePath: string, content: string): Promise<string> {
+  async saveClaudeMdFile(filePath: string, content: string, version: string | null, force?: boolean): Promise<MemoryFileSave> {
     try {
-      return await invoke<string>("save_claude_md_file", { filePath, content });
+      return await invoke<MemoryFileSave>("save_claude_md_file", { filePath, content, version, force });
     } catch (error) {
       console.error("Failed to save CLAUDE.md file:", error);
       throw error;
@@ -850,32 +1817,32 @@ export const api = {
   },
 
   /**
-   * Lists agent runs without metrics (basic info only)
-   * @param agentId - Optional agent ID to filter runs
-   * @returns Promise resolving to an array of agent runs
+   * Lists agent runs without metrics (basic info only), newest first
+   * @param query - Optional filters and pagination
+   * @returns Promise resolving to one page of agent runs
    */
-  async listAgentRuns(agentId?: number): Promise<AgentRunWithMetrics[]> {
+  async listAgentRuns(query?: AgentRunQuery): Promise<AgentRunPage> {
     try {
-      return await invoke<AgentRunWithMetrics[]>('list_agent_runs', { agentId });
+      return await invoke<AgentRunPage>('list_agent_runs', { query });
     } catch (error) {
       console.error("Failed to list agent runs:", error);
-      // Return empty array instead of throwing to prevent UI crashes
-      return [];
+      // Return an empty page instead of throwing to prevent UI crashes
+      return { runs: [], total_count: 0 };
     }
   },
 
   /**
-   * Lists agent runs with metrics (includes token counts and duration)
-   * @param agentId - Optional agent ID to filter runs
-   * @returns Promise resolving to an array of agent runs with metrics
+   * Lists agent runs with metrics (includes token counts and duration), newest first
+   * @param query - Optional filters and pagination
+   * @returns Promise resolving to one page of agent runs with metrics
    */
-  async listAgentRunsWithMetrics(agentId?: number): Promise<AgentRunWithMetrics[]> {
+  async listAgentRunsWithMetrics(query?: AgentRunQuery): Promise<AgentRunPage> {
     try {
-      return await invoke<AgentRunWithMetrics[]>('list_agent_runs_with_metrics', { agentId });
+      return await invoke<AgentRunPage>('list_agent_runs_with_metrics', { query });
     } catch (error) {
       console.error("Failed to list agent runs with metrics:", error);
-      // Return empty array instead of throwing to prevent UI crashes
-      return [];
+      // Return an empty page instead of throwing to prevent UI crashes
+      return { runs: [], total_count: 0 };
     }
   },
 
@@ -909,11 +1876,11 @@ export const api = {
 
   /**
    * Lists all currently running agent sessions
-   * @returns Promise resolving to list of running agent sessions
+   * @returns Promise resolving to list of running agent sessions with their resource usage
    */
-  async listRunningAgentSessions(): Promise<AgentRun[]> {
+  async listRunningAgentSessions(): Promise<(AgentRun & { resources: ProcessResources })[]> {
     try {
-      return await invoke<AgentRun[]>('list_running_sessions');
+      return await invoke<(AgentRun & { resources: ProcessResources })[]>('list_running_sessions');
     } catch (error) {
       console.error("Failed to list running agent sessions:", error);
       throw new Error(`Failed to list running agent sessions: ${error instanceof Error ? error.message : 'Unknown error'}`);
@@ -978,11 +1945,13 @@ export const api = {
   /**
    * Get live output directly from process stdout buffer
    * @param runId - The run ID to get live output for
-   * @returns Promise resolving to the current live output
+   * @param offset - Optional byte offset to read the run's output file from;
+   *   without one the tail of the output is returned
+   * @returns Promise resolving to the output, with the offset to continue from
    */
-  async getLiveSessionOutput(runId: number): Promise<string> {
+  async getLiveSessionOutput(runId: number, offset?: number): Promise<OutputChunk> {
     try {
-      return await invoke<string>('get_live_session_output', { runId });
+      return await invoke<OutputChunk>('get_live_session_output', { runId, offset });
     } catch (error) {
       console.error("Failed to get live session output:", error);
       throw new Error(`Failed to get live session output: ${error instanceof Error ? error.message : 'Unknown error'}`);
@@ -992,11 +1961,13 @@ export const api = {
   /**
    * Start streaming real-time output for a running session
    * @param runId - The run ID to stream output for
+   * @param fromOffset - Optional byte offset to replay the run's output file from,
+   *   emitting chunks on `session-output-chunk:{runId}`
    * @returns Promise that resolves when streaming starts
    */
-  async streamSessionOutput(runId: number): Promise<void> {
+  async streamSessionOutput(runId: number, fromOffset?: number): Promise<void> {
     try {
-      return await invoke<void>('stream_session_output', { runId });
+      return await invoke<void>('stream_session_output', { runId, fromOffset });
     } catch (error) {
       console.error("Failed to start streaming session output:", error);
       throw new Error(`Failed to start streaming session output: ${error instanceof Error ? error.message : 'Unknown error'}`);
@@ -1004,10 +1975,26 @@ export const api = {
   },
 
   /**
-   * Loads the JSONL history for a specific session
+   * Loads a range of the JSONL history for a specific session
+   * @param fromIndex - JSONL line to start at (default 0)
+   * @param count - Maximum number of entries to return (default all)
+   * @param entryTypes - Only return entries of these types
+   */
+  async loadSessionHistory(
+    sessionId: string,
+    projectId: string,
+    fromIndex?: number,
+    count?: number,
+    entryTypes?: SessionEntryType[]
+  ): Promise<SessionHistoryChunk> {
+    return invoke("load_session_history", { sessionId, projectId, fromIndex, count, entryTypes });
+  },
+
+  /**
+   * Counts the JSONL lines of a session without parsing them
    */
-  async loadSessionHistory(sessionId: string, projectId: string): Promise<any[]> {
-    return invoke("load_session_history", { sessionId, projectId });
+  async getSessionMessageCount(projectId: string, sessionId: string): Promise<number> {
+    return invoke("get_session_message_count", { projectId, sessionId });
   },
 
   /**
@@ -1027,62 +2014,161 @@ export const api = {
 
   /**
    * Executes a new interactive Claude Code session with streaming output
+   * @param sessionId - Optional UUID for the new session; one is generated if omitted
+   * @param attachments - Paths of images and text files to add to the prompt
+   * @param outputFormat - `json` or `text` wait for Claude to finish and return its output instead of streaming
+   * @param rememberModel - Make `model` the project's default; without a model the default is used
+   * @returns Promise resolving to the session ID its events are tagged with and how attachments were included
    */
-  async executeClaudeCode(projectPath: string, prompt: string, model: string): Promise<void> {
-    return invoke("execute_claude_code", { projectPath, prompt, model });
+  async executeClaudeCode(projectPath: string, prompt: string, model?: string, options?: ClaudeSessionOptions, sessionId?: string, attachments?: string[], outputFormat?: OutputFormat, rememberModel?: boolean): Promise<ClaudeSessionLaunch> {
+    return invoke<ClaudeSessionLaunch>("execute_claude_code", { projectPath, prompt, model, options, sessionId, attachments, outputFormat, rememberModel });
   },
 
   /**
-   * Continues an existing Claude Code conversation with streaming output
+   * Continues the most recent Claude Code conversation with streaming output
+   * @param sessionId - Optional ID to tag the run's events with; one is generated if omitted
+   * @param attachments - Paths of images and text files to add to the prompt
+   * @returns Promise resolving to the session ID its events are tagged with and how attachments were included
    */
-  async continueClaudeCode(projectPath: string, prompt: string, model: string): Promise<void> {
-    return invoke("continue_claude_code", { projectPath, prompt, model });
+  async continueClaudeCode(projectPath: string, prompt: string, model: string, options?: ClaudeSessionOptions, sessionId?: string, attachments?: string[]): Promise<ClaudeSessionLaunch> {
+    return invoke<ClaudeSessionLaunch>("continue_claude_code", { projectPath, prompt, model, options, sessionId, attachments });
   },
 
   /**
    * Resumes an existing Claude Code session by ID with streaming output
+   * @returns Promise resolving to the session ID its events are tagged with
+   */
+  async resumeClaudeCode(projectPath: string, sessionId: string, prompt: string, model: string, options?: ClaudeSessionOptions): Promise<string> {
+    return invoke<string>("resume_claude_code", { projectPath, sessionId, prompt, model, options });
+  },
+
+  /**
+   * Cancels a running Claude Code session, dropping its queued prompts
+   * @param sessionId - The session ID its events are tagged with
+   * @returns Promise resolving to the prompts that were queued, to restore to the input
+   */
+  async cancelClaudeExecution(sessionId: string): Promise<QueuedPrompt[]> {
+    return invoke<QueuedPrompt[]>("cancel_claude_execution", { sessionId });
+  },
+
+  /**
+   * Queues a prompt behind a running session's current turn. It's sent with
+   * --continue when the turn completes, announced by a `prompt-dequeued` event.
+   * @param sessionId - The session ID its events are tagged with
+   * @returns Promise resolving to how many prompts are waiting
+   */
+  async queuePrompt(sessionId: string, prompt: string, options?: ClaudeSessionOptions): Promise<number> {
+    return invoke<number>("queue_prompt", { sessionId, prompt, options });
+  },
+
+  /**
+   * Lists the prompts waiting for a session's current turn, in sending order
    */
-  async resumeClaudeCode(projectPath: string, sessionId: string, prompt: string, model: string): Promise<void> {
-    return invoke("resume_claude_code", { projectPath, sessionId, prompt, model });
+  async listQueuedPrompts(sessionId: string): Promise<QueuedPrompt[]> {
+    return invoke<QueuedPrompt[]>("list_queued_prompts", { sessionId });
   },
 
   /**
-   * Cancels the currently running Claude Code execution
-   * @param sessionId - Optional session ID to cancel a specific session
+   * Removes a prompt from a session's queue
+   * @param index - Its position in the queue, from 0
+   * @returns Promise resolving to the removed prompt
    */
-  async cancelClaudeExecution(sessionId?: string): Promise<void> {
-    return invoke("cancel_claude_execution", { sessionId });
+  async removeQueuedPrompt(sessionId: string, index: number): Promise<QueuedPrompt> {
+    return invoke<QueuedPrompt>("remove_queued_prompt", { sessionId, index });
   },
 
   /**
    * Lists all currently running Claude sessions
-   * @returns Promise resolving to list of running Claude sessions
+   * @returns Promise resolving to list of running Claude sessions with their resource usage
    */
-  async listRunningClaudeSessions(): Promise<any[]> {
+  async listRunningClaudeSessions(): Promise<(ProcessInfo & { resources: ProcessResources })[]> {
     return invoke("list_running_claude_sessions");
   },
 
   /**
    * Gets live output from a Claude session
    * @param sessionId - The session ID to get output for
-   * @returns Promise resolving to the current live output
+   * @param offset - Byte offset to read from; omit for the latest output
+   * @param maxBytes - Most bytes to return, capped at 1 MB
+   * @returns Promise resolving to the output and the offset to continue from
+   */
+  async getClaudeSessionOutput(
+    sessionId: string,
+    offset?: number,
+    maxBytes?: number
+  ): Promise<SessionOutput> {
+    return invoke("get_claude_session_output", { sessionId, offset, maxBytes });
+  },
+
+  /**
+   * Gets the token usage of a session's current or last run in opcode
+   * @param sessionId - The session ID its events are tagged with
+   * @returns Promise resolving to the usage, or null when none was reported
+   */
+  async getLiveSessionUsage(sessionId: string): Promise<SessionUsageUpdate | null> {
+    return invoke<SessionUsageUpdate | null>("get_live_session_usage", { sessionId });
+  },
+
+  /**
+   * Lists submitted prompts, newest first
+   * @param limit - Maximum entries to return (default 100)
+   * @param projectId - Only prompts submitted in this project
+   * @param search - Only prompts containing this text
+   */
+  async listPromptHistory(
+    limit?: number,
+    projectId?: string,
+    search?: string
+  ): Promise<PromptHistoryEntry[]> {
+    return invoke<PromptHistoryEntry[]>("list_prompt_history", { limit, projectId, search });
+  },
+
+  /**
+   * Deletes prompt history entries
+   * @returns Promise resolving to how many were deleted
+   */
+  async deletePromptHistory(ids: number[]): Promise<number> {
+    return invoke<number>("delete_prompt_history", { ids });
+  },
+
+  /**
+   * Deletes all prompt history
+   * @returns Promise resolving to how many were deleted
+   */
+  async clearPromptHistory(): Promise<number> {
+    return invoke<number>("clear_prompt_history");
+  },
+
+  async getPromptHistorySettings(): Promise<PromptHistorySettings> {
+    return invoke<PromptHistorySettings>("get_prompt_history_settings");
+  },
+
+  /**
+   * Saves prompt history settings; turning history off deletes what is stored
    */
-  async getClaudeSessionOutput(sessionId: string): Promise<string> {
-    return invoke("get_claude_session_output", { sessionId });
+  async setPromptHistorySettings(settings: PromptHistorySettings): Promise<void> {
+    return invoke("set_prompt_history_settings", { settings });
   },
 
   /**
-   * Lists files and directories in a given path
+   * Lists one page of a directory's entries with their metadata
    */
-  async listDirectoryContents(directoryPath: string): Promise<FileEntry[]> {
-    return invoke("list_directory_contents", { directoryPath });
+  async listDirectoryContents(
+    directoryPath: string,
+    options?: DirectoryListingOptions
+  ): Promise<DirectoryListing> {
+    return invoke<DirectoryListing>("list_directory_contents", { directoryPath, options });
   },
 
   /**
-   * Searches for files and directories matching a pattern
+   * Searches for files and directories whose names match a query, best matches first
    */
-  async searchFiles(basePath: string, query: string): Promise<FileEntry[]> {
-    return invoke("search_files", { basePath, query });
+  async searchFiles(
+    basePath: string,
+    query: string,
+    options?: FileSearchOptions
+  ): Promise<FileSearchResult[]> {
+    return invoke("search_files", { basePath, query, options });
   },
 
   /**
@@ -1223,6 +2309,101 @@ export const api = {
     });
   },
 
+  /**
+   * Starts a new session from a session's history up to and including a
+   * message, optionally restoring a checkpoint's files too
+   * @returns Promise resolving to the new session's ID, ready to resume
+   */
+  async resumeSessionTruncated(
+    projectId: string,
+    sessionId: string,
+    messageIndex: number,
+    restoreCheckpoint?: string,
+    projectPath?: string
+  ): Promise<string> {
+    return invoke<string>("resume_session_truncated", {
+      projectId,
+      sessionId,
+      messageIndex,
+      restoreCheckpoint,
+      projectPath
+    });
+  },
+
+  /**
+   * Branches a session under a new ID and resumes the copy, leaving the
+   * original untouched
+   * @param messageIndex - Copy only the history up to and including this entry
+   * @returns Promise resolving to the new session's ID, which its events are tagged with
+   */
+  async forkAndResumeSession(
+    projectId: string,
+    sessionId: string,
+    prompt: string,
+    model?: string,
+    options?: ClaudeSessionOptions,
+    messageIndex?: number
+  ): Promise<string> {
+    return invoke<string>("fork_and_resume_session", {
+      projectId,
+      sessionId,
+      messageIndex,
+      launch: { prompt, model, options }
+    });
+  },
+
+  /**
+   * Runs `/compact` on a session and waits for it. Progress streams through
+   * the session's usual output events. Rejects with a `CompactError`.
+   * @param instructions - What the summary should focus on
+   * @returns Promise resolving to the summary and the new message count
+   */
+  async compactSession(
+    projectId: string,
+    sessionId: string,
+    instructions?: string
+  ): Promise<CompactedSession> {
+    return invoke<CompactedSession>("compact_session", { projectId, sessionId, instructions });
+  },
+
+  /**
+   * Combines sessions of a project into a new session that can be resumed.
+   * The source sessions are left as they were.
+   * @param strategy - `chronological` interleaves turns by time, `sequential` keeps the given order
+   * @returns Promise resolving to the new session's ID, entries taken from each source and any warnings
+   */
+  async mergeSessions(
+    projectId: string,
+    sessionIds: string[],
+    strategy: 'chronological' | 'sequential'
+  ): Promise<MergedSession> {
+    return invoke<MergedSession>("merge_sessions", { projectId, sessionIds, strategy });
+  },
+
+  /**
+   * Estimates how many tokens a session's history takes up, e.g. before resuming it.
+   * Recorded usage is exact; the rest is estimated from character counts.
+   * @param charsPerToken - Overrides the model table's characters per token
+   */
+  async estimateSessionTokens(
+    projectId: string,
+    sessionId: string,
+    charsPerToken?: number
+  ): Promise<SessionTokenEstimate> {
+    return invoke<SessionTokenEstimate>("estimate_session_tokens", {
+      projectId,
+      sessionId,
+      charsPerToken
+    });
+  },
+
+  /**
+   * Estimates the tokens of some text for a model, e.g. for a live prompt counter
+   */
+  async estimateTextTokens(text: string, model?: string): Promise<TextTokenEstimate> {
+    return invoke<TextTokenEstimate>("estimate_text_tokens", { text, model });
+  },
+
   /**
    * Gets the timeline for a session
    */
@@ -1448,6 +2629,22 @@ export const api = {
     }
   },
 
+  /**
+   * Enables or disables an MCP server; disabled servers keep their whole config, env included
+   * @param name - Server name
+   * @param scope - "local", "project" or "user"
+   * @param enabled - Whether Claude should see the server
+   * @returns Promise resolving to the server
+   */
+  async mcpSetEnabled(name: string, scope: string, enabled: boolean): Promise<MCPServer> {
+    try {
+      return await invoke<MCPServer>("mcp_set_enabled", { name, scope, enabled });
+    } catch (error) {
+      console.error("Failed to enable or disable MCP server:", error);
+      throw error;
+    }
+  },
+
   /**
    * Removes an MCP server
    */
@@ -1484,6 +2681,29 @@ export const api = {
     }
   },
 
+  /**
+   * Imports MCP servers from Cursor, VS Code or Windsurf
+   * @param editor - The editor to import from
+   * @param scope - Scope to add the servers to
+   * @param conflictPolicy - What to do with servers named like existing ones
+   */
+  async mcpImportFromEditor(
+    editor: MCPEditor,
+    scope: string,
+    conflictPolicy: MCPConflictPolicy
+  ): Promise<EditorImportResult> {
+    try {
+      return await invoke<EditorImportResult>("mcp_import_from_editor", {
+        editor,
+        scope,
+        conflictPolicy,
+      });
+    } catch (error) {
+      console.error("Failed to import MCP servers from editor:", error);
+      throw error;
+    }
+  },
+
   /**
    * Starts Claude Code as an MCP server
    */
@@ -1521,7 +2741,7 @@ export const api = {
   },
 
   /**
-   * Gets the status of MCP servers
+   * Gets the status of MCP servers as of their last check
    */
   async mcpGetServerStatus(): Promise<Record<string, ServerStatus>> {
     try {
@@ -1532,6 +2752,86 @@ export const api = {
     }
   },
 
+  /**
+   * Checks enabled MCP servers now, all of them or just one
+   * @param name - The server to check, or all of them when omitted
+   * @returns Every server's status
+   */
+  async mcpRefreshStatus(name?: string): Promise<Record<string, ServerStatus>> {
+    try {
+      return await invoke<Record<string, ServerStatus>>("mcp_refresh_status", { name });
+    } catch (error) {
+      console.error("Failed to refresh server status:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the seconds between background MCP server checks, 0 when they're off
+   */
+  async getMcpStatusInterval(): Promise<number> {
+    try {
+      return await invoke<number>("get_mcp_status_interval");
+    } catch (error) {
+      console.error("Failed to get MCP status interval:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets the seconds between background MCP server checks, 0 to turn them off
+   */
+  async setMcpStatusInterval(seconds: number): Promise<void> {
+    try {
+      return await invoke<void>("set_mcp_status_interval", { seconds });
+    } catch (error) {
+      console.error("Failed to set MCP status interval:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Tests the connection to every enabled MCP server in parallel
+   * @param scope - Only test servers of this scope
+   * @param concurrency - How many servers are tested at once, 4 by default
+   * @returns Promise resolving to each server's result, in listing order
+   */
+  async mcpTestAll(scope?: string, concurrency?: number): Promise<MCPTestResult[]> {
+    try {
+      return await invoke<MCPTestResult[]>("mcp_test_all", { scope, concurrency });
+    } catch (error) {
+      console.error("Failed to test MCP servers:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the last lines opcode captured from an MCP server's stderr
+   * @param name - The server, or "claude-mcp-serve" for `claude mcp serve`
+   * @param lines - How many lines to return, 200 by default
+   * @param since - Only return lines logged from this timestamp on
+   */
+  async mcpGetServerLogs(name: string, lines?: number, since?: string): Promise<MCPLogLine[]> {
+    try {
+      return await invoke<MCPLogLine[]>("mcp_get_server_logs", { name, lines, since });
+    } catch (error) {
+      console.error("Failed to get MCP server logs:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Deletes the logs opcode captured from an MCP server
+   */
+  async mcpClearServerLogs(name: string): Promise<void> {
+    try {
+      return await invoke<void>("mcp_clear_server_logs", { name });
+    } catch (error) {
+      console.error("Failed to clear MCP server logs:", error);
+      throw error;
+    }
+  },
+
   /**
    * Reads .mcp.json from the current project
    */
@@ -1556,6 +2856,27 @@ export const api = {
     }
   },
 
+  /**
+   * Exports the configured MCP servers to a file; an existing file keeps its other settings
+   * @param scope - Only servers of this scope, or all when null
+   * @param format - Claude Desktop or .mcp.json shape
+   * @param outputPath - File to write
+   * @param stripEnv - Leave out environment values, keeping the variable names
+   */
+  async mcpExport(
+    scope: string | null,
+    format: MCPExportFormat,
+    outputPath: string,
+    stripEnv: boolean
+  ): Promise<MCPExportResult> {
+    try {
+      return await invoke<MCPExportResult>("mcp_export", { scope, format, outputPath, stripEnv });
+    } catch (error) {
+      console.error("Failed to export MCP servers:", error);
+      throw error;
+    }
+  },
+
   /**
    * Get the stored Claude binary path from settings
    * @returns Promise resolving to the path if set, null otherwise
@@ -1571,7 +2892,7 @@ export const api = {
 
   /**
    * Set the Claude binary path in settings
-   * @param path - The absolute path to the Claude binary
+   * @param path - The absolute path to the Claude binary, or "bundled" for the one shipped with opcode
    * @returns Promise resolving when the path is saved
    */
   async setClaudeBinaryPath(path: string): Promise<void> {
@@ -1584,18 +2905,158 @@ export const api = {
   },
 
   /**
-   * List all available Claude installations on the system
+   * List all available Claude installations on the system. Cached versions
+   * are returned right away; stale ones are re-detected in the background and
+   * announced with a `claude-installations-updated` event.
+   * @param forceRefresh - Re-detect every version before returning
    * @returns Promise resolving to an array of Claude installations
    */
-  async listClaudeInstallations(): Promise<ClaudeInstallation[]> {
+  async listClaudeInstallations(forceRefresh?: boolean): Promise<ClaudeInstallation[]> {
     try {
-      return await invoke<ClaudeInstallation[]>("list_claude_installations");
+      return await invoke<ClaudeInstallation[]>("list_claude_installations", { forceRefresh });
     } catch (error) {
       console.error("Failed to list Claude installations:", error);
       throw error;
     }
   },
 
+  /**
+   * Checks whether a Claude binary can run: the file, its interpreter,
+   * `--version` and, unless skipped, a one-turn test prompt
+   * @param path - The binary to check, or "bundled"
+   * @param skipPrompt - Leave out the test prompt, which uses the API
+   * @returns Promise resolving to the diagnosis
+   */
+  async checkClaudeBinaryHealth(path: string, skipPrompt?: boolean): Promise<ClaudeBinaryHealth> {
+    try {
+      return await invoke<ClaudeBinaryHealth>("check_claude_binary_health", { path, skipPrompt });
+    } catch (error) {
+      console.error("Failed to check Claude binary:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Installs the Claude CLI, streaming `claude-install-progress` events
+   * @param method - The official install script or `npm install -g`
+   * @returns Promise resolving to the installation found afterwards
+   */
+  async installClaudeCli(method: ClaudeInstallMethod): Promise<ClaudeInstallation> {
+    try {
+      return await invoke<ClaudeInstallation>("install_claude_cli", { method });
+    } catch (error) {
+      console.error("Failed to install Claude:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Updates the selected Claude installation in place, if it was installed
+   * with npm or Homebrew. Streams `claude-install-progress` events.
+   * @returns Promise resolving to the installation with its new version
+   */
+  async updateClaudeCli(): Promise<ClaudeInstallation> {
+    try {
+      return await invoke<ClaudeInstallation>("update_claude_cli");
+    } catch (error) {
+      console.error("Failed to update Claude:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the directories put in front of PATH
+   * @returns Promise resolving to the entries and which of them don't exist
+   */
+  async getExtraPathEntries(): Promise<ExtraPathSettings> {
+    try {
+      return await invoke<ExtraPathSettings>("get_extra_path_entries");
+    } catch (error) {
+      console.error("Failed to get extra PATH entries:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Saves the directories put in front of PATH; entries that don't exist are kept
+   * @param entries - Directories, one per entry, `~` allowed
+   * @returns Promise resolving to the saved entries and which of them don't exist
+   */
+  async setExtraPathEntries(entries: string[]): Promise<ExtraPathSettings> {
+    try {
+      return await invoke<ExtraPathSettings>("set_extra_path_entries", { entries });
+    } catch (error) {
+      console.error("Failed to save extra PATH entries:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets the pinned Claude binary checksum
+   * @returns Promise resolving to the pin, or null when none is set
+   */
+  async getClaudeBinaryPin(): Promise<ClaudeBinaryPin | null> {
+    try {
+      return await invoke<ClaudeBinaryPin | null>("get_claude_binary_pin");
+    } catch (error) {
+      console.error("Failed to get Claude binary pin:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Pins the SHA-256 of a Claude binary; launches are refused once it changes
+   * @param path - The selected binary, or "bundled"
+   * @returns Promise resolving to the new pin
+   */
+  async pinClaudeBinaryChecksum(path: string): Promise<ClaudeBinaryPin> {
+    try {
+      return await invoke<ClaudeBinaryPin>("pin_claude_binary_checksum", { path });
+    } catch (error) {
+      console.error("Failed to pin Claude binary:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Clears the pinned Claude binary checksum
+   */
+  async clearClaudeBinaryPin(): Promise<void> {
+    try {
+      await invoke("clear_claude_binary_pin");
+    } catch (error) {
+      console.error("Failed to clear Claude binary pin:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Gets whether a binary run after the configured one failed to spawn is saved
+   * as the configured binary
+   */
+  async getPersistBinaryFallback(): Promise<boolean> {
+    try {
+      return await invoke<boolean>("get_persist_binary_fallback");
+    } catch (error) {
+      console.error("Failed to get fallback setting:", error);
+      throw error;
+    }
+  },
+
+  /**
+   * Sets whether a binary run after the configured one failed to spawn is saved
+   * as the configured binary
+   * @param enabled - Save the fallback binary
+   */
+  async setPersistBinaryFallback(enabled: boolean): Promise<void> {
+    try {
+      await invoke("set_persist_binary_fallback", { enabled });
+    } catch (error) {
+      console.error("Failed to save fallback setting:", error);
+      throw error;
+    }
+  },
+
   // Storage API methods
 
   /**
@@ -1830,9 +3291,9 @@ export const api = {
    * @param command - The shell command to validate
    * @returns Promise resolving to validation result
    */
-  async validateHookCommand(command: string): Promise<{ valid: boolean; message: string }> {
+  async validateHookCommand(command: string, shell?: HookShell): Promise<{ valid: boolean; message: string }> {
     try {
-      return await invoke<{ valid: boolean; message: string }>("validate_hook_command", { command });
+      return await invoke<{ valid: boolean; message: string }>("validate_hook_command", { command, shell });
     } catch (error) {
       console.error("Failed to validate hook command:", error);
       throw error;
@@ -9,6 +9,9 @@ import {
   HookValidationError,
   HookValidationWarning,
   HookCommand,
+  MATCHER_EVENTS,
+  DIRECT_EVENTS,
+  directEventCommands,
 } from '@/types/hooks';
 
 export class HooksManager {
@@ -23,14 +26,8 @@ export class HooksManager {
   ): HooksConfiguration {
     const merged: HooksConfiguration = {};
     
-    // Events with matchers (tool-related)
-    const matcherEvents: (keyof HooksConfiguration)[] = ['PreToolUse', 'PostToolUse'];
-    
-    // Events without matchers (non-tool-related)
-    const directEvents: (keyof HooksConfiguration)[] = ['Notification', 'Stop', 'SubagentStop'];
-
     // Merge events with matchers
-    for (const event of matcherEvents) {
+    for (const event of MATCHER_EVENTS) {
       // Start with user hooks
       let matchers = [...((user[event] as HookMatcher[] | undefined) || [])];
       
@@ -50,27 +47,16 @@ export class HooksManager {
     }
     
     // Merge events without matchers
-    for (const event of directEvents) {
+    for (const event of DIRECT_EVENTS) {
       // Combine all hooks from all levels (local takes precedence)
-      const hooks: HookCommand[] = [];
-      
-      // Add user hooks
-      if (user[event]) {
-        hooks.push(...(user[event] as HookCommand[]));
-      }
-      
-      // Add project hooks
-      if (project[event]) {
-        hooks.push(...(project[event] as HookCommand[]));
-      }
-      
-      // Add local hooks (highest priority)
-      if (local[event]) {
-        hooks.push(...(local[event] as HookCommand[]));
-      }
+      const hooks: HookCommand[] = [
+        ...directEventCommands(user[event]),
+        ...directEventCommands(project[event]),
+        ...directEventCommands(local[event]),
+      ];
       
       if (hooks.length > 0) {
-        (merged as any)[event] = hooks;
+        merged[event] = [{ hooks }];
       }
     }
     
@@ -115,14 +101,8 @@ export class HooksManager {
       return { valid: true, errors, warnings };
     }
 
-    // Events with matchers
-    const matcherEvents = ['PreToolUse', 'PostToolUse'] as const;
-    
-    // Events without matchers
-    const directEvents = ['Notification', 'Stop', 'SubagentStop'] as const;
-
     // Validate events with matchers
-    for (const event of matcherEvents) {
+    for (const event of MATCHER_EVENTS) {
       const matchers = hooks[event];
       if (!matchers || !Array.isArray(matchers)) continue;
 
@@ -165,11 +145,8 @@ export class HooksManager {
     }
 
     // Validate events without matchers
-    for (const event of directEvents) {
-      const directHooks = hooks[event];
-      if (!directHooks || !Array.isArray(directHooks)) continue;
-
-      for (const hook of directHooks) {
+    for (const event of DIRECT_EVENTS) {
+      for (const hook of directEventCommands(hooks[event])) {
         if (!hook.command || !hook.command.trim()) {
           errors.push({
             event,
@@ -150,9 +150,9 @@ export class SessionPersistenceService {
       const sessionData = this.loadSession(sessionId);
       if (!sessionData) return false;
 
-      // Try to verify the session exists on disk by loading its history
-      const history = await api.loadSessionHistory(sessionId, projectId);
-      return history && history.length > 0;
+      // Verify the session exists on disk and isn't empty
+      const count = await api.getSessionMessageCount(projectId, sessionId);
+      return count > 0;
     } catch (error) {
       console.error('Failed to check session restorability:', error);
       return false;
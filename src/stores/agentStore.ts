@@ -62,7 +62,7 @@ const agentStore: StateCreator<
       set({ isLoadingRuns: true, error: null });
       
       try {
-        const runs = await api.listAgentRuns();
+        const { runs } = await api.listAgentRuns();
         const runningIds = runs
           .filter((r) => r.status === 'running' || r.status === 'pending')
           .map((r) => r.id?.toString() || '')
@@ -66,7 +66,7 @@ const sessionStore: StateCreator<
     fetchProjectSessions: async (projectId: string) => {
       set({ isLoadingSessions: true, error: null });
       try {
-        const projectSessions = await api.getProjectSessions(projectId);
+        const { sessions: projectSessions } = await api.getProjectSessions(projectId);
         set((state) => ({
           sessions: {
             ...state.sessions,
@@ -106,10 +106,13 @@ const sessionStore: StateCreator<
       set({ isLoadingOutputs: true, error: null });
       try {
         const output = await api.getClaudeSessionOutput(sessionId);
+        if (output.warning) {
+          console.warn(output.warning);
+        }
         set((state) => ({
           sessionOutputs: {
             ...state.sessionOutputs,
-            [sessionId]: output
+            [sessionId]: output.content
           },
           isLoadingOutputs: false
         }));
@@ -124,9 +127,8 @@ const sessionStore: StateCreator<
     // Delete session
     deleteSession: async (sessionId: string, projectId: string) => {
       try {
-        // Note: API doesn't have a deleteSession method, so this is a placeholder
-        console.warn('deleteSession not implemented in API');
-        
+        await api.deleteSession(projectId, sessionId);
+
         // Update local state
         set((state) => ({
           sessions: {
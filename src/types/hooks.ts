@@ -2,9 +2,13 @@
  * Types for Claude Code hooks configuration
  */
 
+// Shell a hook command runs in; defaults to cmd on Windows and sh elsewhere
+export type HookShell = 'sh' | 'bash' | 'cmd' | 'powershell';
+
 export interface HookCommand {
   type: 'command';
   command: string;
+  shell?: HookShell;
   timeout?: number; // Optional timeout in seconds (default: 60)
 }
 
@@ -16,13 +20,36 @@ export interface HookMatcher {
 export interface HooksConfiguration {
   PreToolUse?: HookMatcher[];
   PostToolUse?: HookMatcher[];
-  Notification?: HookCommand[];
-  Stop?: HookCommand[];
-  SubagentStop?: HookCommand[];
+  Notification?: HookMatcher[];
+  UserPromptSubmit?: HookMatcher[];
+  Stop?: HookMatcher[];
+  SubagentStop?: HookMatcher[];
+  PreCompact?: HookMatcher[];
+  SessionStart?: HookMatcher[];
+  SessionEnd?: HookMatcher[];
 }
 
 export type HookEvent = keyof HooksConfiguration;
 
+// Events whose matcher selects when hooks run (tool names, compaction or session start triggers)
+export const MATCHER_EVENTS = ['PreToolUse', 'PostToolUse', 'PreCompact', 'SessionStart'] as const;
+// Events that take no matcher
+export const DIRECT_EVENTS = ['Notification', 'UserPromptSubmit', 'Stop', 'SubagentStop', 'SessionEnd'] as const;
+
+export type MatcherEvent = typeof MATCHER_EVENTS[number];
+export type DirectEvent = typeof DIRECT_EVENTS[number];
+
+/**
+ * Commands configured for an event without matchers. Older configs listed the
+ * commands directly instead of inside a matcher group; both are accepted.
+ */
+export function directEventCommands(entries: unknown): HookCommand[] {
+  if (!Array.isArray(entries)) return [];
+  return entries.flatMap((entry: any) =>
+    Array.isArray(entry?.hooks) ? entry.hooks : entry?.command !== undefined ? [entry] : []
+  );
+}
+
 export interface ClaudeSettingsWithHooks {
   hooks?: HooksConfiguration;
   [key: string]: any;